@@ -3,7 +3,29 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{DeriveInput, parse_macro_input};
 
-#[proc_macro_derive(DeriveInsertable)]
+fn is_option(field: &syn::Field) -> bool {
+    match &field.ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .iter()
+            .any(|seg| seg.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Whether field is marked #[primary_key]
+fn is_primary_key(field: &syn::Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("primary_key"))
+}
+
+/// pri columns are the fields marked #[primary_key] if any are, otherwise every non-Option field
+/// - with marked fields, the unmarked non-Option fields are data columns that are always written,
+///   bound after the pri columns along with the Option fields that are set
+#[proc_macro_derive(DeriveInsertable, attributes(primary_key))]
 pub fn derive_insertable(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -15,44 +37,38 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
         _ => panic!("Insertable can only be derived for structs"),
     };
 
+    let explicit = fields.iter().any(is_primary_key);
     let pri_field_names: Vec<_> = fields
         .iter()
-        .filter_map(|field| {
-            if let syn::Type::Path(type_path) = &field.ty {
-                if type_path
-                    .path
-                    .segments
-                    .iter()
-                    .any(|seg| seg.ident == "Option")
-                {
-                    return None;
-                }
-                return Some(field.ident.as_ref().unwrap());
+        .filter(|field| {
+            if explicit {
+                is_primary_key(field)
+            } else {
+                !is_option(field)
             }
-            None
         })
+        .map(|field| field.ident.as_ref().unwrap())
+        .collect();
+    let req_field_names: Vec<_> = fields
+        .iter()
+        .filter(|field| explicit && !is_primary_key(field) && !is_option(field))
+        .map(|field| field.ident.as_ref().unwrap())
         .collect();
     let opt_field_names: Vec<_> = fields
         .iter()
-        .filter_map(|field| {
-            if let syn::Type::Path(type_path) = &field.ty {
-                if type_path
-                    .path
-                    .segments
-                    .iter()
-                    .any(|seg| seg.ident == "Option")
-                {
-                    return Some(field.ident.as_ref().unwrap());
-                }
-                return None;
-            }
-            None
-        })
+        .filter(|field| !is_primary_key(field) && is_option(field))
+        .map(|field| field.ident.as_ref().unwrap())
         .collect();
     let pri_field_str: Vec<_> = pri_field_names
         .iter()
         .map(|field| field.to_string())
         .collect();
+    let all_field_str: Vec<_> = pri_field_names
+        .iter()
+        .chain(req_field_names.iter())
+        .chain(opt_field_names.iter())
+        .map(|field| field.to_string())
+        .collect();
 
     let expanded = quote! {
         #[async_trait::async_trait]
@@ -65,8 +81,12 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
                 vec![#(#pri_field_str),*]
             }
 
+            fn column_names() -> Vec<&'static str> {
+                vec![#(#all_field_str),*]
+            }
+
             fn opt_column_names(&self) -> Vec<&'static str> {
-                let mut cols = Vec::new();
+                let mut cols = vec![#(stringify!(#req_field_names)),*];
                 #(
                     if self.#opt_field_names.is_some() {
                         cols.push(stringify!(#opt_field_names));
@@ -101,6 +121,7 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
 
             fn bind_opt<'q>(&'q self, sql: &'q str) -> Query<'q, Postgres, PgArguments> {
                 let mut query = sqlx::query(sql);
+                #(query = query.bind(&self.#req_field_names);)*
                 #(
                     if self.#opt_field_names.is_some() {
                         query = query.bind(self.#opt_field_names.as_ref().unwrap().clone());
@@ -114,6 +135,7 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
                 query: QueryAs<'q, Postgres, T, PgArguments>,
             ) -> QueryAs<'q, Postgres, T, PgArguments> {
                 let mut query = query;
+                #(query = query.bind(&self.#req_field_names);)*
                 #(
                     if self.#opt_field_names.is_some() {
                         query = query.bind(self.#opt_field_names.as_ref().unwrap().clone());
@@ -127,6 +149,7 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
                 query: sqlx::query::Query<'q, sqlx::Postgres, PgArguments>,
             ) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments> {
                 let mut query = query;
+                #(query = query.bind(&self.#req_field_names);)*
                 #(
                     if self.#opt_field_names.is_some() {
                         query = query.bind(self.#opt_field_names.as_ref().unwrap().clone());
@@ -135,6 +158,35 @@ pub fn derive_insertable(input: TokenStream) -> TokenStream {
                 query
             }
 
+            fn many_column_names(rows: &[Self]) -> Result<Vec<&'static str>, String> {
+                let first = rows
+                    .first()
+                    .ok_or_else(|| format!("Expected at least one {} row", #table_name))?;
+                let opt_cols = first.opt_column_names();
+                if let Some(row) = rows.iter().find(|row| row.opt_column_names() != opt_cols) {
+                    return Err(format!(
+                        "Every {} row bound together has to set the same optional columns: {:?} vs {:?}",
+                        #table_name,
+                        opt_cols,
+                        row.opt_column_names()
+                    ));
+                }
+                let mut cols = first.pri_column_names();
+                cols.extend(opt_cols);
+                Ok(cols)
+            }
+
+            fn bind_many_to_query<'q>(
+                rows: &'q [Self],
+                query: sqlx::query::Query<'q, sqlx::Postgres, PgArguments>,
+            ) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments> {
+                let mut query = query;
+                for row in rows {
+                    query = row.bind_pri_to_query(query);
+                    query = row.bind_opt_to_query(query);
+                }
+                query
+            }
         }
     };
 
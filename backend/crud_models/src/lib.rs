@@ -1,9 +1,37 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{DeriveInput, Type, parse_macro_input};
+use syn::{DeriveInput, Field, Type, parse_macro_input};
 
-#[proc_macro_derive(ExtractPrimaryKeys)]
+/// Whether field is marked #[primary_key]
+fn is_primary_key(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("primary_key"))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Whether any field is marked #[primary_key] - the primary key is then exactly the marked fields
+/// and every other field an update key, rather than the non-Option fields / the Option fields
+fn has_explicit_primary_keys(data: &syn::DataStruct) -> bool {
+    data.fields.iter().any(is_primary_key)
+}
+
+/// Primary keys are the fields marked #[primary_key] if any are, otherwise every non-Option field
+/// - #[primary_key] on an Option field is a compile error, a nullable primary key is almost
+///   always a mistake
+#[proc_macro_derive(ExtractPrimaryKeys, attributes(primary_key))]
 pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -15,9 +43,24 @@ pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
         _ => panic!("ExtractPrimaryKeys only works on Struct!"),
     };
 
+    if let Some(field) = data
+        .fields
+        .iter()
+        .find(|field| is_primary_key(field) && is_option(&field.ty))
+    {
+        return syn::Error::new_spanned(
+            &field.ty,
+            "#[primary_key] can't be placed on an Option field - primary keys can't be null",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let explicit = has_explicit_primary_keys(data);
+
     let primary_key_fields: Vec<_> = data
         .fields
         .iter()
+        .filter(|field| !explicit || is_primary_key(field))
         .filter_map(|field| {
             let serde_attrs: Vec<_> = field
                 .attrs
@@ -52,7 +95,10 @@ pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
     .into()
 }
 
-#[proc_macro_derive(ExtractFullKeys)]
+/// Full keys are every field with its Option stripped, so nullable columns are only those declared
+/// Option<Option<T>> - if any field is marked #[primary_key], fields are kept as declared instead
+/// (Option only for the nullable columns)
+#[proc_macro_derive(ExtractFullKeys, attributes(primary_key))]
 pub fn extract_full_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -64,6 +110,8 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
         _ => panic!("ExtractFullKeys only works on Struct!"),
     };
 
+    let explicit = has_explicit_primary_keys(data);
+
     let full_key_fields: Vec<_> = data
         .fields
         .iter()
@@ -75,6 +123,14 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
                 .cloned()
                 .collect();
 
+            if explicit {
+                let field_name = &field.ident;
+                let ty = &field.ty;
+                return Some(quote! {
+                    #(#serde_attrs)*
+                    pub #field_name : #ty
+                });
+            }
             if let Type::Path(ref type_path) = field.ty {
                 if let Some(segment) = type_path.path.segments.last() {
                     if segment.ident == "Option" {
@@ -111,7 +167,9 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
     .into()
 }
 
-#[proc_macro_derive(ExtractUpdateKeys)]
+/// Update keys are every field not marked #[primary_key] if any field is, as an Option, otherwise
+/// every Option field
+#[proc_macro_derive(ExtractUpdateKeys, attributes(primary_key))]
 pub fn extract_update_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -122,10 +180,12 @@ pub fn extract_update_keys(input: TokenStream) -> TokenStream {
         syn::Data::Struct(ref s) => s,
         _ => panic!("ExtractUpdateKeys only works on Struct!"),
     };
+    let explicit = has_explicit_primary_keys(data);
 
     let update_key_fields: Vec<_> = data
         .fields
         .iter()
+        .filter(|field| !explicit || !is_primary_key(field))
         .filter_map(|field| {
             let serde_attrs: Vec<_> = field
                 .attrs
@@ -134,6 +194,15 @@ pub fn extract_update_keys(input: TokenStream) -> TokenStream {
                 .cloned()
                 .collect();
 
+            if explicit && !is_option(&field.ty) {
+                // still optional in an update, a data column is only set if it is given
+                let field_name = &field.ident;
+                let ty = &field.ty;
+                return Some(quote! {
+                    #(#serde_attrs)*
+                    pub #field_name : Option<#ty>
+                });
+            }
             if let Type::Path(ref type_path) = field.ty {
                 if let Some(segment) = type_path.path.segments.last() {
                     if segment.ident == "Option" {
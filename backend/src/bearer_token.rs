@@ -0,0 +1,51 @@
+use std::fmt::Display;
+
+/// Shortest BEARER_TOKEN the server starts with
+pub const MIN_BEARER_TOKEN_LEN: usize = 32;
+/// Fewest distinct characters a BEARER_TOKEN may be made of - rules out e.g. 32 zeros
+pub const MIN_BEARER_TOKEN_DISTINCT_CHARS: usize = 10;
+
+/// BEARER_TOKEN requests (and the websocket) are authenticated against
+/// - only built through parse, so a blank or short token (an expected header of just "Bearer ")
+///   can't be deployed by accident
+#[derive(Clone)]
+pub struct BearerToken(String);
+
+impl BearerToken {
+    pub fn parse(token: &str) -> Result<Self, String> {
+        if token.chars().any(char::is_whitespace) {
+            return Err("BEARER_TOKEN must not contain whitespace".to_string());
+        }
+        let len = token.chars().count();
+        if len < MIN_BEARER_TOKEN_LEN {
+            return Err(format!(
+                "BEARER_TOKEN must be at least {} characters, got {}",
+                MIN_BEARER_TOKEN_LEN, len
+            ));
+        }
+        let mut chars: Vec<char> = token.chars().collect();
+        chars.sort_unstable();
+        chars.dedup();
+        if chars.len() < MIN_BEARER_TOKEN_DISTINCT_CHARS {
+            return Err(format!(
+                "BEARER_TOKEN must use at least {} distinct characters, got {}",
+                MIN_BEARER_TOKEN_DISTINCT_CHARS,
+                chars.len()
+            ));
+        }
+        Ok(Self(token.to_string()))
+    }
+}
+
+impl Display for BearerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// kept out of logs / panics
+impl std::fmt::Debug for BearerToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BearerToken(..)")
+    }
+}
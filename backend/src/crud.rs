@@ -46,6 +46,10 @@ macro_rules! bind_json_value {
                 }
             }
             serde_json::Value::Bool(b) => Ok($query.bind(*b)),
+            // JSONB columns, e.g. strategy_params.params
+            json @ (serde_json::Value::Object(_) | serde_json::Value::Array(_)) => {
+                Ok($query.bind(sqlx::types::Json(json.clone())))
+            }
             _ => Err(anyhow::anyhow!(
                 "Unsupported value type for column `{}`",
                 $key
@@ -54,6 +58,17 @@ macro_rules! bind_json_value {
     }};
 }
 
+/// Placeholder for column - values are bound as text, so the enum columns cast theirs to the
+/// postgres type, from serde's variant names (Stopping / Stock / Call) to the type's labels
+fn placeholder(column: &str, index: usize) -> String {
+    match column {
+        "status" => format!("LOWER(${})::status", index),
+        "asset_type" => format!("LOWER(${})::asset_type", index),
+        "option_type" => format!("LEFT(${}, 1)::option_type", index),
+        _ => format!("${}", index),
+    }
+}
+
 #[async_trait]
 impl<
     FullKeys: Sized + Send + Sync + Serialize + for<'de> Deserialize<'de>,
@@ -76,7 +91,11 @@ impl<
             .ok_or_else(|| anyhow!("Expected JSON object"))?;
 
         let columns: Vec<_> = item.keys().map(|value| format!("{}", value)).collect();
-        let placeholders: Vec<_> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
+        let placeholders: Vec<_> = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| placeholder(column, index + 1))
+            .collect();
 
         let sql = format!(
             "INSERT INTO {} ({}) VALUES ({})",
@@ -105,7 +124,7 @@ impl<
         let conditions = pk
             .keys()
             .enumerate()
-            .map(|(index, column)| format!("{} = ${}", column, index + 1))
+            .map(|(index, column)| format!("{} = {}", column, placeholder(column, index + 1)))
             .collect::<Vec<_>>()
             .join(" AND ");
 
@@ -147,7 +166,7 @@ impl<
         for (key, value) in update.iter() {
             if !value.is_null() {
                 index += 1;
-                set_clause_vec.push(format!("{} = ${}", key, index));
+                set_clause_vec.push(format!("{} = {}", key, placeholder(key, index)));
             }
         }
         let set_clause = set_clause_vec.join(", ");
@@ -156,7 +175,7 @@ impl<
         let mut where_clause_vec = Vec::new();
         for key in pk.keys() {
             index += 1;
-            where_clause_vec.push(format!("{} = ${}", key, index));
+            where_clause_vec.push(format!("{} = {}", key, placeholder(key, index)));
         }
         let where_clause = where_clause_vec.join(" AND ");
 
@@ -189,7 +208,7 @@ impl<
         let conditions = pk
             .keys()
             .enumerate()
-            .map(|(index, key)| format!("{} = ${}", key, index + 1))
+            .map(|(index, key)| format!("{} = {}", key, placeholder(key, index + 1)))
             .collect::<Vec<_>>()
             .join(" AND ");
 
@@ -1,9 +1,76 @@
+use axum::{
+    Json,
+    extract::rejection::JsonRejection,
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use serde::Serialize;
+use serde_json::json;
+
+/// Body of a create / update request, or the response rejecting it
+/// - a body that doesn't deserialize or fails validation::validate (NaN prices, empty keys, ...)
+///   gets a 422, other rejections (e.g. 413 past the body limit) keep their own status
+pub(crate) fn validated_payload<T: Serialize>(
+    payload: Result<Json<T>, JsonRejection>,
+) -> Result<T, Response> {
+    let Json(payload) = payload.map_err(|rejection| match rejection {
+        JsonRejection::JsonSyntaxError(_) | JsonRejection::JsonDataError(_) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, rejection.body_text()).into_response()
+        }
+        rejection => rejection.into_response(),
+    })?;
+    crate::validation::validate(&payload)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err).into_response())?;
+    Ok(payload)
+}
+
+/// Response to a failed create - a unique violation (23505), i.e. the row already exists, gets a
+/// 409 naming the conflicting key, anything else a 500
+pub(crate) fn create_error_response(err: anyhow::Error) -> Response {
+    let unique_violation = err
+        .downcast_ref::<sqlx::Error>()
+        .and_then(|err| err.as_database_error())
+        .filter(|db_err| db_err.code().as_deref() == Some("23505"));
+    match unique_violation {
+        Some(db_err) => {
+            // Postgres reports the key as "Key (col, ...)=(val, ...) already exists."
+            let key = db_err
+                .try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+                .and_then(|pg_err| pg_err.detail())
+                .map(|detail| {
+                    detail
+                        .trim_start_matches("Key ")
+                        .trim_end_matches(" already exists.")
+                        .to_string()
+                });
+            (
+                StatusCode::CONFLICT,
+                Json(json!({
+                    "error": "Item already exists",
+                    "constraint": db_err.constraint(),
+                    "key": key,
+                })),
+            )
+                .into_response()
+        }
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create: {}", err),
+        )
+            .into_response(),
+    }
+}
+
 macro_rules! make_create_handler {
     ($fn_name:ident, $full_ty:ty, $primary_ty:ty, $update_ty:ty, $table:expr) => {
         async fn $fn_name(
             State(state): State<AppState>,
-            Json(payload): Json<$full_ty>,
+            payload: Result<Json<$full_ty>, axum::extract::rejection::JsonRejection>,
         ) -> impl IntoResponse {
+            let payload = match crate::crud_impl::validated_payload(payload) {
+                Ok(payload) => payload,
+                Err(rejection) => return rejection,
+            };
             let crud = crud::CRUD::<$full_ty, $primary_ty, $update_ty>::new(
                 state.db.clone(),
                 $table.to_string(),
@@ -11,11 +78,7 @@ macro_rules! make_create_handler {
 
             match crud.create(&payload).await {
                 Ok(_) => "Created".into_response(),
-                Err(err) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to create: {}", err),
-                )
-                    .into_response(),
+                Err(err) => crate::crud_impl::create_error_response(err),
             }
         }
     };
@@ -66,8 +129,15 @@ macro_rules! make_update_handler {
     ($fn_name:ident, $full_ty:ty, $primary_ty:ty, $update_ty:ty, $table:expr) => {
         async fn $fn_name(
             State(state): State<AppState>,
-            Json((pk, update)): Json<($primary_ty, $update_ty)>,
+            payload: Result<
+                Json<($primary_ty, $update_ty)>,
+                axum::extract::rejection::JsonRejection,
+            >,
         ) -> impl IntoResponse {
+            let (pk, update) = match crate::crud_impl::validated_payload(payload) {
+                Ok(payload) => payload,
+                Err(rejection) => return rejection,
+            };
             let crud = crud::CRUD::<$full_ty, $primary_ty, $update_ty>::new(
                 state.db.clone(),
                 $table.to_string(),
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::{
+    Decimal,
+    prelude::{FromPrimitive, ToPrimitive},
+};
+use shared::marks::{BarClose, close_as_of};
+use sqlx::PgPool;
+
+use crate::portfolio_values::{PortfolioValue, PortfolioValueStrategy};
+
+/// Currency the books are kept in and the currency values are shown in
+/// - everything stored and computed (capital, transactions, portfolio values) is in base_currency,
+///   only responses are converted to display_currency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrencyConfig {
+    pub base_currency: String,
+    pub display_currency: String,
+}
+
+impl Default for CurrencyConfig {
+    fn default() -> Self {
+        Self {
+            base_currency: "USD".to_string(),
+            display_currency: "USD".to_string(),
+        }
+    }
+}
+
+impl CurrencyConfig {
+    pub fn needs_conversion(&self) -> bool {
+        self.base_currency != self.display_currency
+    }
+}
+
+/// Display currency per unit of base currency over time, from the closes of the FX pair in
+/// market_data.historical_data
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxRates(Vec<BarClose>);
+
+impl FxRates {
+    /// The same rate at every time, e.g. 1 when no conversion is needed
+    pub fn constant(rate: f64) -> Self {
+        Self(vec![(DateTime::<Utc>::MIN_UTC, rate)])
+    }
+
+    /// Rates from (time, rate) closes in any order - None if there are none
+    pub fn from_closes(closes: Vec<BarClose>) -> Option<Self> {
+        (!closes.is_empty()).then_some(Self(closes))
+    }
+
+    /// Rate as of time: the newest close at or before it, or the oldest close for a time before
+    /// the pair's history starts
+    pub fn at(&self, time: DateTime<Utc>) -> f64 {
+        close_as_of(self.0.iter().copied(), time).unwrap_or_else(|| {
+            self.0
+                .iter()
+                .min_by_key(|(close_time, _)| *close_time)
+                .map_or(1.0, |(_, rate)| *rate)
+        })
+    }
+
+    /// Rate of the newest close
+    pub fn latest(&self) -> f64 {
+        self.at(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+/// Display currency per unit of base currency at every close of the FX pair
+/// - the pair is looked up as BASE.DISPLAY (e.g. USD.EUR) or, inverted, DISPLAY.BASE (e.g.
+///   EUR.USD), the way IBKR names cash contracts
+/// - a constant 1 when no conversion is needed
+pub async fn fx_rates(db: &PgPool, config: &CurrencyConfig) -> Result<FxRates, String> {
+    fx_rates_between(db, &config.base_currency, &config.display_currency).await
+}
+
+/// to per unit of from at every close of the FX pair, looked up as fx_rates does
+pub async fn fx_rates_between(db: &PgPool, from: &str, to: &str) -> Result<FxRates, String> {
+    if from == to {
+        return Ok(FxRates::constant(1.0));
+    }
+    let direct = format!("{}.{}", from, to);
+    let inverse = format!("{}.{}", to, from);
+    let closes = sqlx::query_as::<_, (String, DateTime<Utc>, f64)>(
+        r#"
+        SELECT stock, time, close FROM market_data.historical_data
+        WHERE stock IN ($1, $2) AND close > 0
+        "#,
+    )
+    .bind(&direct)
+    .bind(&inverse)
+    .fetch_all(db)
+    .await
+    .map_err(|err| format!("Failed to read FX rates {}: {}", direct, err))?;
+
+    FxRates::from_closes(
+        closes
+            .into_iter()
+            .map(|(pair, time, close)| {
+                if pair == direct {
+                    (time, close)
+                } else {
+                    (time, 1.0 / close)
+                }
+            })
+            .collect(),
+    )
+    .ok_or_else(|| format!("No FX rate to convert {} to {}", from, to))
+}
+
+/// amount converted at rate, rounded to decimal_places
+fn convert(amount: f64, rate: f64, decimal_places: u32) -> f64 {
+    match (Decimal::from_f64(amount), Decimal::from_f64(rate)) {
+        (Some(amount), Some(rate)) => (amount * rate)
+            .round_dp(decimal_places)
+            .to_f64()
+            .unwrap_or(0.0),
+        // infinite / NaN - nothing to convert
+        _ => amount,
+    }
+}
+
+/// Strategy's money values (portfolio values, average trade return, position prices and pnl)
+/// converted into config's display currency - each portfolio value at the rate of its own time,
+/// the rest (as of now) at the latest rate
+/// - ratios (cagr, sharpe, drawdown, ...) and quantities are left as they are
+pub fn convert_strategy(
+    mut value: PortfolioValueStrategy,
+    config: &CurrencyConfig,
+    rates: &FxRates,
+    decimal_places: u32,
+) -> PortfolioValueStrategy {
+    value.currency = config.display_currency.clone();
+    for (time, portfolio_value) in value.portfolio.iter_mut() {
+        *portfolio_value = convert(*portfolio_value, rates.at(*time), decimal_places);
+    }
+    let rate = rates.latest();
+    value.metrics.avg_trade_return = convert(value.metrics.avg_trade_return, rate, decimal_places);
+    for position in value.metrics.positions.values_mut() {
+        position.avg_price = convert(position.avg_price, rate, decimal_places);
+        position.last_pnl = convert(position.last_pnl, rate, decimal_places);
+    }
+    value
+}
+
+/// Every strategy's and the overall portfolio value converted, see convert_strategy
+pub fn convert_overall(
+    mut value: PortfolioValue,
+    config: &CurrencyConfig,
+    rates: &FxRates,
+    decimal_places: u32,
+) -> PortfolioValue {
+    value.currency = config.display_currency.clone();
+    for (time, portfolio_value) in value.portfolio.iter_mut() {
+        *portfolio_value = convert(*portfolio_value, rates.at(*time), decimal_places);
+    }
+    value.strategies = value
+        .strategies
+        .into_iter()
+        .map(|strategy| convert_strategy(strategy, config, rates, decimal_places))
+        .collect();
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        models,
+        portfolio_values::{PortfolioMetrics, PositionInfo},
+    };
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, day, 20, 0, 0).unwrap()
+    }
+
+    fn eur() -> CurrencyConfig {
+        CurrencyConfig {
+            base_currency: "USD".to_string(),
+            display_currency: "EUR".to_string(),
+        }
+    }
+
+    fn strategy_value() -> PortfolioValueStrategy {
+        PortfolioValueStrategy {
+            strategy: "strat".to_string(),
+            status: models::Status::Active,
+            currency: "USD".to_string(),
+            portfolio: vec![(day(1), 1000.0), (day(2), 1100.0), (day(3), 1200.0)],
+            metrics: PortfolioMetrics {
+                cagr: 0.5,
+                sharpe_ratio: 1.5,
+                sortino_ratio: 2.0,
+                max_drawdown: 0.1,
+                calmar_ratio: 5.0,
+                profit_factor: 2.0,
+                win_rate: 0.6,
+                avg_trade_return: 10.0,
+                positions: HashMap::from([(
+                    "QQQ".to_string(),
+                    PositionInfo {
+                        avg_price: 500.0,
+                        quantity: 2.0,
+                        last_pnl: 20.0,
+                        contract_type: "stock".to_string(),
+                        option_details: None,
+                    },
+                )]),
+                insufficient_data: false,
+            },
+        }
+    }
+
+    #[test]
+    fn rate_is_the_newest_close_at_or_before_the_time() {
+        let rates = FxRates::from_closes(vec![(day(2), 0.8), (day(1), 0.9)]).unwrap();
+
+        assert_eq!(rates.at(day(1)), 0.9);
+        assert_eq!(rates.at(day(1) + chrono::Duration::hours(12)), 0.9);
+        assert_eq!(rates.at(day(5)), 0.8);
+        // before the pair's history - its oldest close
+        assert_eq!(rates.at(day(1) - chrono::Duration::days(3)), 0.9);
+        assert_eq!(rates.latest(), 0.8);
+        assert_eq!(FxRates::from_closes(Vec::new()), None);
+    }
+
+    #[test]
+    fn each_portfolio_value_is_converted_at_its_own_dates_rate() {
+        let rates =
+            FxRates::from_closes(vec![(day(1), 0.9), (day(2), 0.8), (day(3), 0.5)]).unwrap();
+
+        let converted = convert_strategy(strategy_value(), &eur(), &rates, 2);
+
+        assert_eq!(converted.currency, "EUR");
+        assert_eq!(
+            converted.portfolio,
+            vec![(day(1), 900.0), (day(2), 880.0), (day(3), 600.0)]
+        );
+        // values as of now at the latest rate, ratios and quantities untouched
+        assert_eq!(converted.metrics.avg_trade_return, 5.0);
+        assert_eq!(converted.metrics.positions["QQQ"].avg_price, 250.0);
+        assert_eq!(converted.metrics.positions["QQQ"].last_pnl, 10.0);
+        assert_eq!(converted.metrics.positions["QQQ"].quantity, 2.0);
+        assert_eq!(converted.metrics.sharpe_ratio, 1.5);
+    }
+
+    #[test]
+    fn constant_rate_of_one_leaves_values_as_they_are() {
+        let converted = convert_strategy(
+            strategy_value(),
+            &CurrencyConfig::default(),
+            &FxRates::constant(1.0),
+            2,
+        );
+
+        assert_eq!(converted.portfolio, strategy_value().portfolio);
+        assert_eq!(converted.metrics.avg_trade_return, 10.0);
+    }
+}
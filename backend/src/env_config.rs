@@ -0,0 +1,181 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::{bearer_token::BearerToken, currency::CurrencyConfig, validation};
+
+/// Everything the backend reads from its environment, validated before anything is started
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    pub database_url: String,
+    pub bearer_token: BearerToken,
+    pub server_host: String,
+    // Window notifications to the websocket client are batched in - 0 sends each straight away
+    pub ws_batch_window: Duration,
+    // Identical notifications within this window are only sent once - 0 sends every one
+    pub notification_dedup_window: Duration,
+    pub money_decimal_places: u32,
+    pub min_metrics_observations: usize,
+    pub portfolio_concurrency: usize,
+    // How long a computed portfolio value is served to dashboard polls before it is recomputed
+    pub portfolio_cache_ttl: Duration,
+    pub portfolio_query_timeout: Duration,
+    pub currency: CurrencyConfig,
+    pub reconcile_tolerance_pct: f64,
+    pub max_request_body_bytes: usize,
+    pub stale_position_window: Duration,
+}
+
+impl EnvConfig {
+    /// Reads DATABASE_URL, BEARER_TOKEN and SERVER_HOST (required) plus the optional settings,
+    /// failing on the first one missing or invalid
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// from_env over var, which looks up a variable by name
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let required = |name: &str| var(name).ok_or_else(|| format!("{} must be set", name));
+        let bearer_token = BearerToken::parse(&required("BEARER_TOKEN")?)?;
+
+        // Capital, transactions and computed portfolio values are in BASE_CURRENCY - responses
+        // are converted to DISPLAY_CURRENCY, both default to USD (no conversion)
+        let base_currency = var("BASE_CURRENCY")
+            .map(|currency| currency.to_uppercase())
+            .unwrap_or_else(|| "USD".to_string());
+        let display_currency = var("DISPLAY_CURRENCY")
+            .map(|currency| currency.to_uppercase())
+            .unwrap_or_else(|| base_currency.clone());
+
+        Ok(Self {
+            database_url: required("DATABASE_URL")?,
+            bearer_token,
+            server_host: required("SERVER_HOST")?,
+            ws_batch_window: Duration::from_millis(parsed(
+                &var,
+                "WS_BATCH_WINDOW_MS",
+                0,
+                "a number of milliseconds",
+            )?),
+            notification_dedup_window: Duration::from_millis(parsed(
+                &var,
+                "NOTIFICATION_DEDUP_WINDOW_MS",
+                0,
+                "a number of milliseconds",
+            )?),
+            money_decimal_places: parsed(
+                &var,
+                "MONEY_DECIMAL_PLACES",
+                2,
+                "a non-negative integer",
+            )?,
+            min_metrics_observations: parsed(
+                &var,
+                "MIN_METRICS_OBSERVATIONS",
+                30,
+                "a non-negative integer",
+            )?,
+            portfolio_concurrency: parsed(&var, "PORTFOLIO_CONCURRENCY", 2, "a positive integer")?,
+            portfolio_cache_ttl: Duration::from_secs(parsed(
+                &var,
+                "PORTFOLIO_CACHE_TTL_SECS",
+                10,
+                "a number of seconds",
+            )?),
+            portfolio_query_timeout: Duration::from_secs(parsed(
+                &var,
+                "PORTFOLIO_QUERY_TIMEOUT_SECS",
+                30,
+                "a number of seconds",
+            )?),
+            currency: CurrencyConfig {
+                base_currency,
+                display_currency,
+            },
+            reconcile_tolerance_pct: parsed(&var, "RECONCILE_TOLERANCE_PCT", 1.0, "a percentage")?,
+            max_request_body_bytes: parsed(
+                &var,
+                "MAX_REQUEST_BODY_BYTES",
+                validation::DEFAULT_MAX_REQUEST_BODY_BYTES,
+                "a number of bytes",
+            )?,
+            stale_position_window: Duration::from_secs(parsed(
+                &var,
+                "STALE_POSITION_WINDOW_SECS",
+                3 * 24 * 60 * 60,
+                "a number of seconds",
+            )?),
+        })
+    }
+}
+
+/// Variable name parsed as a T, default when it is unset
+fn parsed<T: FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    default: T,
+    expected: &str,
+) -> Result<T, String> {
+    match var(name) {
+        Some(value) => value
+            .trim()
+            .parse::<T>()
+            .map_err(|_| format!("{} must be {}, got {:?}", name, expected, value)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn config_with(vars: &[(&str, &str)]) -> Result<EnvConfig, String> {
+        let vars: HashMap<String, String> = [
+            ("DATABASE_URL", "postgres://localhost/trading_system"),
+            ("BEARER_TOKEN", "0123456789abcdefghijklmnopqrstuv"),
+            ("SERVER_HOST", "0.0.0.0"),
+        ]
+        .iter()
+        .chain(vars)
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        EnvConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn required_vars_with_defaults_for_the_rest() {
+        let config = config_with(&[]).expect("Expected required vars to be enough");
+        assert_eq!(config.server_host, "0.0.0.0");
+        assert_eq!(config.money_decimal_places, 2);
+        assert_eq!(
+            config.stale_position_window,
+            Duration::from_secs(3 * 24 * 60 * 60)
+        );
+        assert_eq!(config.currency, CurrencyConfig::default());
+
+        let config = config_with(&[("BASE_CURRENCY", "sgd"), ("WS_BATCH_WINDOW_MS", "250")])
+            .expect("Expected overrides to parse");
+        assert_eq!(config.currency.display_currency, "SGD");
+        assert_eq!(config.ws_batch_window, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn empty_or_short_bearer_token_fails_validation() {
+        assert!(BearerToken::parse("").is_err());
+        assert!(BearerToken::parse("0123456789abcdef").is_err());
+        assert!(BearerToken::parse("00000000000000000000000000000000").is_err());
+        assert!(BearerToken::parse("0123456789abcdefghijklmnopqrstuv").is_ok());
+
+        let error = config_with(&[("BEARER_TOKEN", "")]).expect_err("Expected empty token to fail");
+        assert!(error.contains("BEARER_TOKEN must be at least 32 characters"));
+        assert!(config_with(&[("BEARER_TOKEN", "short")]).is_err());
+    }
+
+    #[test]
+    fn invalid_optional_var_is_named() {
+        assert_eq!(
+            config_with(&[("PORTFOLIO_CONCURRENCY", "many")]).map(|_| ()),
+            Err("PORTFOLIO_CONCURRENCY must be a positive integer, got \"many\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const BROKER_EVENTS_TABLE: &str = "trading.broker_events";
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Kind of raw IBKR event the trading app logs to trading.broker_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrokerEventType {
+    Execution,
+    Order,
+    Commission,
+}
+
+impl BrokerEventType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BrokerEventType::Execution => "execution",
+            BrokerEventType::Order => "order",
+            BrokerEventType::Commission => "commission",
+        }
+    }
+}
+
+/// Query of GET /events - every filter is optional, from is inclusive and to exclusive
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    #[serde(rename = "type")]
+    pub event_type: Option<BrokerEventType>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub order_id: Option<i32>,
+    /// Events per page, DEFAULT_PAGE_SIZE if unset and capped at MAX_PAGE_SIZE
+    pub limit: Option<i64>,
+    /// Events to skip, next_start of the previous page
+    pub start: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct BrokerEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub order_id: Option<i32>,
+    pub execution_id: String,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub time: DateTime<Utc>,
+    pub payload: serde_json::Value,
+}
+
+/// Page of events oldest first - next_start is None on the last page
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPage {
+    pub events: Vec<BrokerEvent>,
+    pub next_start: Option<i64>,
+}
+
+/// GET /events?type=execution|order|commission&from=...&to=...&order_id=...&limit=...&start=...
+/// - Returns the raw order / execution / commission events IBKR sent, oldest first, for
+///   post-trade forensics
+pub async fn list_events(
+    State(state): State<crate::AppState>,
+    Query(filter): Query<EventFilter>,
+) -> Result<(StatusCode, Json<EventPage>), (StatusCode, String)> {
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let start = filter.start.unwrap_or(0);
+    if limit <= 0 || start < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "limit must be positive and start non-negative".to_string(),
+        ));
+    }
+    let limit = limit.min(MAX_PAGE_SIZE);
+
+    // one row past the page tells whether there is another page
+    let mut events = sqlx::query_as::<_, BrokerEvent>(
+        "SELECT id, event_type, order_id, execution_id, time, payload
+        FROM trading.broker_events
+        WHERE ($1::text IS NULL OR event_type = $1)
+            AND ($2::timestamptz IS NULL OR time >= $2)
+            AND ($3::timestamptz IS NULL OR time < $3)
+            AND ($4::integer IS NULL OR order_id = $4)
+        ORDER BY time, id
+        LIMIT $5 OFFSET $6",
+    )
+    .bind(filter.event_type.map(|event_type| event_type.as_str()))
+    .bind(filter.from)
+    .bind(filter.to)
+    .bind(filter.order_id)
+    .bind(limit + 1)
+    .bind(start)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read broker events: {}", err),
+        )
+    })?;
+
+    let next_start = (events.len() as i64 > limit).then_some(start + limit);
+    events.truncate(limit as usize);
+    Ok((StatusCode::OK, Json(EventPage { events, next_start })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn type_filter_returns_only_that_kind_a_page_at_a_time() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.broker_events WHERE order_id BETWEEN 714001 AND 714002;
+            INSERT INTO trading.broker_events (event_type, order_id, execution_id, time, payload)
+            VALUES
+                ('order', 714001, '', NOW() - INTERVAL '3 minutes', '{\"status\": \"Submitted\"}'),
+                ('execution', 714001, 'exec.714.1', NOW() - INTERVAL '2 minutes',
+                '{\"shares\": 5}'),
+                ('commission', 714001, 'exec.714.1', NOW() - INTERVAL '2 minutes',
+                '{\"commission\": 1.0}'),
+                ('execution', 714001, 'exec.714.2', NOW() - INTERVAL '1 minute',
+                '{\"shares\": 5}'),
+                ('execution', 714002, 'exec.714.3', NOW(), '{\"shares\": 1}');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert broker events");
+
+        let events = |filter: EventFilter| {
+            let state = test_support::app_state(db.clone());
+            async move { list_events(State(state), Query(filter)).await }
+        };
+        let first_page = events(EventFilter {
+            event_type: Some(BrokerEventType::Execution),
+            order_id: Some(714001),
+            limit: Some(1),
+            ..EventFilter::default()
+        })
+        .await;
+        let rest = events(EventFilter {
+            event_type: Some(BrokerEventType::Execution),
+            order_id: Some(714001),
+            limit: Some(1),
+            start: Some(1),
+            ..EventFilter::default()
+        })
+        .await;
+        let commissions = events(EventFilter {
+            event_type: Some(BrokerEventType::Commission),
+            order_id: Some(714001),
+            ..EventFilter::default()
+        })
+        .await;
+
+        sqlx::query("DELETE FROM trading.broker_events WHERE order_id BETWEEN 714001 AND 714002")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up broker events");
+
+        let (status, Json(first_page)) = first_page.expect("Expected a page of events");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first_page.events.len(), 1);
+        assert_eq!(first_page.events[0].execution_id, "exec.714.1");
+        assert_eq!(first_page.next_start, Some(1));
+
+        let (_, Json(rest)) = rest.expect("Expected the second page of events");
+        assert_eq!(rest.events.len(), 1);
+        assert_eq!(rest.events[0].execution_id, "exec.714.2");
+        assert_eq!(rest.next_start, None);
+
+        let (_, Json(commissions)) = commissions.expect("Expected commission events");
+        assert_eq!(commissions.events.len(), 1);
+        assert!(
+            commissions
+                .events
+                .iter()
+                .all(|event| event.event_type == "commission")
+        );
+        assert_eq!(commissions.events[0].payload["commission"], 1.0);
+    }
+}
@@ -0,0 +1,79 @@
+use axum::{Json, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Longest the health check waits on the trading app before treating it as unreachable
+const TRADING_APP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+}
+
+/// Freshness of the trading app's market data, as served on its GET /health/market_data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketDataFreshness {
+    pub status: HealthStatus,
+    pub market_open: bool,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub last_bar_time: Option<DateTime<Utc>>,
+    pub stale_after_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Health {
+    pub status: HealthStatus,
+    /// None if the trading app couldn't be reached - it only serves while a session is running
+    pub market_data: Option<MarketDataFreshness>,
+}
+
+/// Market data freshness reported by the trading app
+async fn market_data_freshness() -> Result<MarketDataFreshness, String> {
+    let url = format!("http://{}/health/market_data", env!("TRADING_BOT_URL"));
+    let client = Client::builder()
+        .timeout(TRADING_APP_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Failed to build health check client: {}", err))?;
+    let body = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Error occurred during market data health request: {}", err))?
+        .text()
+        .await
+        .map_err(|err| format!("Failed to read market data health response: {}", err))?;
+    serde_json::from_str(&body)
+        .map_err(|err| format!("Failed to parse market data health response: {}", err))
+}
+
+/// ANY /check-health
+/// - degraded (503) if the trading app reports its market data stalled during market hours
+pub async fn check_health() -> impl IntoResponse {
+    let market_data = match market_data_freshness().await {
+        Ok(freshness) => Some(freshness),
+        Err(err) => {
+            tracing::debug!("Market data health unavailable: {}", err);
+            None
+        }
+    };
+    let status = match &market_data {
+        Some(freshness) if freshness.status == HealthStatus::Degraded => HealthStatus::Degraded,
+        _ => HealthStatus::Ok,
+    };
+    let status_code = match status {
+        HealthStatus::Ok => StatusCode::OK,
+        HealthStatus::Degraded => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (
+        status_code,
+        Json(Health {
+            status,
+            market_data,
+        }),
+    )
+}
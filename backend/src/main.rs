@@ -21,15 +21,40 @@ use http::header::CONTENT_TYPE;
 use tower_http::cors::{Any, CorsLayer};
 // use futures::future::join_all;
 use reqwest::Client;
+use futures::{SinkExt, StreamExt};
 
 mod models;
 mod portfolio_values;
+mod portfolio_cache;
 mod logs;
+mod positions;
+mod open_orders;
+mod strategy_reset;
+mod strategy_clone;
+mod strategy_allocation;
+mod timestamps;
+mod strategy_params;
+mod orders;
+mod notifier;
+mod currency;
+mod health;
+mod reconcile;
+mod ws_commands;
+mod validation;
+mod bearer_token;
+mod env_config;
+mod stale_positions;
+mod schema_check;
+mod events;
+#[cfg(test)]
+mod test_support;
 
 #[async_trait::async_trait]
 pub trait Insertable {
     fn table_name() -> &'static str;
     fn pri_column_names(&self) -> Vec<&'static str>;
+    /// Every column of the model - its pri columns then its optional columns
+    fn column_names() -> Vec<&'static str>;
     fn opt_column_names(&self) -> Vec<&'static str>;
     fn bind_pri<'q>(&'q self, sql: &'q str) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments>;
     fn bind_pri_to_query<'q>(
@@ -49,13 +74,47 @@ pub trait Insertable {
         &'q self,
         query: QueryAs<'q, Postgres, T, PgArguments>,
     ) -> QueryAs<'q, Postgres, T, PgArguments>;
+    /// Columns of a multi-row VALUES clause for rows - pri columns, then the optional columns,
+    /// which every row has to set the same of
+    fn many_column_names(rows: &[Self]) -> Result<Vec<&'static str>, String>
+    where
+        Self: Sized;
+    /// Binds every row in order, each as its pri then its set optional columns - lines up with
+    /// the placeholders of many_column_names repeated once per row
+    fn bind_many_to_query<'q>(
+        rows: &'q [Self],
+        query: sqlx::query::Query<'q, sqlx::Postgres, PgArguments>,
+    ) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments>
+    where
+        Self: Sized;
 }
 
 #[derive(Clone)]
 struct AppState {
-    auth_token: Arc<String>,
+    auth_token: Arc<bearer_token::BearerToken>,
     db: PgPool,
-    client: Arc<Mutex<Option<WebSocket>>>
+    client: Arc<Mutex<Option<notifier::WsSender>>>,
+    notifier: notifier::WsNotifier,
+    // Decimal places portfolio values are rounded to before being returned
+    money_decimal_places: u32,
+    // Portfolio values needed before return based metrics (cagr, sharpe, ...) are computed
+    min_metrics_observations: usize,
+    // Strategies whose portfolio value is computed at once for the overall portfolio value
+    portfolio_concurrency: usize,
+    portfolio_cache: portfolio_cache::PortfolioCache,
+    // Longest a single portfolio value query may run before the request gives up with a 504
+    portfolio_query_timeout: std::time::Duration,
+    // Currency accounting is kept in and the currency portfolio values are returned in
+    currency: currency::CurrencyConfig,
+    // Percentage the computed portfolio value may differ from the broker's net liquidation by
+    // before /portfolio/reconcile flags it
+    reconcile_tolerance_pct: f64,
+    // Broker positions as of the last positions sync, which stale positions are checked against
+    last_sync: stale_positions::LastSync,
+    // Stale positions already alerted, so each sync only alerts the newly stale
+    alerted_stale: stale_positions::AlertedStale,
+    // How long a position may go without a transaction while mismatched before it is stale
+    stale_position_window: std::time::Duration,
 }
 
 #[tokio::main]
@@ -68,9 +127,8 @@ async fn main() {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let bearer_token = std::env::var("BEARER_TOKEN").expect("BEARER_TOKEN must be set");
-    let server_host = std::env::var("SERVER_HOST").expect("SERVER_HOST must be set");
+    // Every variable is checked up front - a missing or weak BEARER_TOKEN stops the server here
+    let config = env_config::EnvConfig::from_env().unwrap_or_else(|e| panic!("{}", e));
 
     let cors = CorsLayer::new()
        .allow_methods([Method::GET, Method::POST])
@@ -80,28 +138,54 @@ async fn main() {
 
     let db = PgPoolOptions::new()
         .max_connections(5)
-        .connect(&database_url)
+        .connect(&config.database_url)
         .await
         .expect("Failed to connect to Postgres");
+    // The trading app owns the migrations - a drifted model fails here rather than on its first request
+    schema_check::validate_schema(&db, &registered_models())
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
 
+    let client = Arc::new(Mutex::new(None));
     let state = AppState {
-        auth_token: Arc::new(bearer_token),
+        auth_token: Arc::new(config.bearer_token),
         db,
-        client: Arc::new(Mutex::new(None))
+        client: client.clone(),
+        notifier: notifier::WsNotifier::new(client, config.ws_batch_window)
+            .with_dedup_window(config.notification_dedup_window),
+        money_decimal_places: config.money_decimal_places,
+        min_metrics_observations: config.min_metrics_observations,
+        portfolio_concurrency: config.portfolio_concurrency,
+        portfolio_cache: portfolio_cache::PortfolioCache::new(config.portfolio_cache_ttl),
+        portfolio_query_timeout: config.portfolio_query_timeout,
+        currency: config.currency,
+        reconcile_tolerance_pct: config.reconcile_tolerance_pct,
+        last_sync: stale_positions::LastSync::new(),
+        alerted_stale: stale_positions::AlertedStale::new(),
+        stale_position_window: config.stale_position_window,
     };
 
     let auth_routes = Router::new()
         .route("/send_notification", post(send_notification))
 
         .route("/send/positions_mismatch", post(positions_mismatch_alert))
+        .route("/positions/stale", get(crate::stale_positions::get_stale_positions))
         .route("/current_position/fix", post(fix_current_positions))
 
         .route("/get_portfolio/strategy", get(get_portfolio_value_for_strategy))
         .route("/get_portfolio", get(get_overall_portfolio_value))
+        .route("/portfolio/reconcile", get(crate::reconcile::reconcile_portfolio))
 
         .route("/strategy/pause", post(pause_strategy))
         .route("/strategy/resume", post(resume_strategy))
+        .route("/strategy/reset", post(crate::strategy_reset::reset_strategy))
+        .route("/strategy/clone", post(crate::strategy_clone::clone_strategy))
+        .route("/strategy/rebalance", post(crate::strategy_allocation::rebalance_strategies))
+        .route("/strategy_params", get(crate::strategy_params::read_strategy_params))
+        .route("/strategy_params", put(crate::strategy_params::update_strategy_params))
         .route("/account/pause", post(pause_account))
+        .route("/orders/cancel", post(crate::orders::cancel_order))
+        .route("/events", get(crate::events::list_events))
 
         .route("/strategy", post(create_strategy))
         .route("/strategy", get(read_strategy))
@@ -112,6 +196,8 @@ async fn main() {
         .route("/logs", get(crate::logs::list_logs))
         .route("/logs/:filename", get(crate::logs::read_log))
 
+        .route("/current_positions", get(crate::positions::get_current_positions_for_strategy))
+
         .route("/current_stock_positions", post(create_current_stock_positions))
         .route("/current_stock_positions", get(read_current_stock_positions))
         .route("/current_stock_positions/all", get(read_all_current_stock_positions))
@@ -129,6 +215,7 @@ async fn main() {
         .route("/target_stock_positions/all", get(read_all_target_stock_positions))
         .route("/target_stock_positions", put(update_target_stock_positions))
         .route("/target_stock_positions", delete(delete_target_stock_positions))
+        .route("/target_stock_positions/validate", post(crate::orders::validate_target_stock_positions))
 
         .route("/target_option_positions", post(create_target_option_positions))
         .route("/target_option_positions", get(read_target_option_positions))
@@ -136,6 +223,8 @@ async fn main() {
         .route("/target_option_positions", put(update_target_option_positions))
         .route("/target_option_positions", delete(delete_target_option_positions))
 
+        .route("/open_orders", get(crate::open_orders::get_open_orders_for_strategy))
+
         .route("/open_stock_orders", post(create_open_stock_orders))
         .route("/open_stock_orders", get(read_open_stock_orders))
         .route("/open_stock_orders/all", get(read_all_open_stock_orders))
@@ -166,38 +255,30 @@ async fn main() {
         .route("/historical_data", put(update_historical_data))
         .route("/historical_data", delete(delete_historical_data))
 
-        .route("/historical_volatility_data", post(create_historical_volatility_data))
-        .route("/historical_volatility_data", get(read_historical_volatility_data))
-        .route("/historical_volatility_data/all", get(read_all_historical_volatility_data))
-        .route("/historical_volatility_data", put(update_historical_volatility_data))
-        .route("/historical_volatility_data", delete(delete_historical_volatility_data))
-
         .route("/historical_options_data", post(create_historical_options_data))
         .route("/historical_options_data", get(read_historical_options_data))
         .route("/historical_options_data/all", get(read_all_historical_options_data))
         .route("/historical_options_data", put(update_historical_options_data))
         .route("/historical_options_data", delete(delete_historical_options_data))
 
-        .route("/phantom_portfolio_value", post(create_phantom_portfolio_value))
-        .route("/phantom_portfolio_value", get(read_phantom_portfolio_value))
-        .route("/phantom_portfolio_value/all", get(read_all_phantom_portfolio_value))
-        .route("/phantom_portfolio_value", put(update_phantom_portfolio_value))
-        .route("/phantom_portfolio_value", delete(delete_phantom_portfolio_value))
+        .route("/account_summary", get(read_account_summary))
+        .route("/account_summary/all", get(read_all_account_summary))
 
         .with_state(state.clone())
         .layer(axum::middleware::from_fn_with_state(state.clone(), auth_middleware));
 
     let public_routes = Router::new()
-        .route("/check-health", any(check_health))
+        .route("/check-health", any(crate::health::check_health))
         .route("/ws", any(ws_handler))
         .with_state(state.clone());
 
     let app = public_routes
         .merge(auth_routes)
+        .layer(axum::extract::DefaultBodyLimit::max(config.max_request_body_bytes))
         .layer(cors);
 
     // run it with hyper
-    let listener = tokio::net::TcpListener::bind(format!("{}:3000", server_host))
+    let listener = tokio::net::TcpListener::bind(format!("{}:3000", config.server_host))
         .await
         .unwrap();
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
@@ -209,10 +290,6 @@ async fn main() {
     .unwrap();
 }
 
-async fn check_health() -> impl IntoResponse {
-    (StatusCode::OK, axum::Json(serde_json::json!({ "status": "ok" })))
-}
-
 async fn auth_middleware(
     State(state): State<AppState>,
     request: Request<axum::body::Body>,
@@ -243,10 +320,27 @@ async fn ws_handler(
     ws.on_upgrade(|web_socket| {insert_client(web_socket, state)})
 }
 
-async fn insert_client(mut socket: WebSocket, state: AppState) {
-    let mut client_guard = state.client.lock().await;
-    socket.send(Message::Text("Hello bb".into())).await.ok();
-    client_guard.replace(socket);
+/// Makes the connection the one notifications are sent to, then answers the commands the frontend
+/// sends on it (see ws_commands::WsCommand) until it disconnects
+async fn insert_client(socket: WebSocket, state: AppState) {
+    let (mut sender, mut receiver) = socket.split();
+    sender.send(Message::Text("Hello bb".into())).await.ok();
+    // replies go back on this connection even once a newer one has taken over notifications
+    let sender = Arc::new(Mutex::new(sender));
+    state.client.lock().await.replace(sender.clone());
+
+    while let Some(Ok(message)) = receiver.next().await {
+        match message {
+            Message::Text(text) => {
+                let reply = ws_commands::handle_message(state.clone(), &text).await;
+                if let Err(err) = notifier::send_to_sender(&sender, notifier::WsMessage::Single(reply)).await {
+                    tracing::error!("Failed to reply to websocket command: {}", err);
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
 }
 
 async fn send_notification(
@@ -255,36 +349,19 @@ async fn send_notification(
 ) -> impl IntoResponse {
     let notification = &payload;
 
-    // Get the client
-    let mut client_guard = state.client.lock().await;
-    let client_optional = client_guard.as_mut();
-
-    // only if client exists
-    if let Some(client) = client_optional {
-         let json_notification = match serde_json::to_string(notification) {
-            Ok(s) => s,
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to serialize notification".into_response(),
-                );
-            }
-        };
+    let json_notification = match serde_json::to_string(notification) {
+        Ok(s) => s,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to serialize notification".into_response(),
+            );
+        }
+    };
 
-        match client.send(Message::Text(json_notification)).await {
-            Ok(_) => return (StatusCode::OK, "Notification passed along!".into_response()),
-            Err(err) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error when sending message to client: {}", err).into_response(),
-                );
-            }
-        } 
-    } else {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Client not connected yet!".into_response(),
-        );
+    match state.notifier.send(json_notification).await {
+        Ok(_) => (StatusCode::OK, "Notification passed along!".into_response()),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.into_response()),
     }
 }
 
@@ -294,13 +371,75 @@ pub struct Quantity {
     pub strategy: String,
 }
 
+/// Option position as reported by the broker
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct BrokerOptionPosition {
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: models::OptionType,
+    pub quantity: f64,
+}
+
+/// Body of POST /send/positions_mismatch
+/// - both fields are required so a sender still on the stocks-only payload is rejected rather than
+///   read as holding no options
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct BrokerPositions {
+    /// stock -> quantity
+    pub stocks: HashMap<String, f64>,
+    pub options: Vec<BrokerOptionPosition>,
+}
+
+/// Alert sent to the client - option mismatches are keyed by OptionMismatchedPosition::contract_key
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PositionsMismatch {
+    pub stocks: HashMap<String, Vec<models::MismatchedPosition>>,
+    pub options: HashMap<String, Vec<models::OptionMismatchedPosition>>,
+}
+
+/// One fully specified entry per strategy holding the contract locally if the strategies' total
+/// differs from the broker's quantity
+/// - a contract only the broker holds is attributed to the unknown strategy
+fn option_mismatches(
+    broker_position: &BrokerOptionPosition,
+    local_positions: &[Quantity],
+) -> Vec<models::OptionMismatchedPosition> {
+    let local_total: f64 = local_positions.iter().map(|position| position.quantity).sum();
+    if local_total == broker_position.quantity {
+        return Vec::new();
+    }
+    let mismatch = |strategy: &str, local: f64, fix: f64| models::OptionMismatchedPosition {
+        strategy: strategy.to_string(),
+        stock: broker_position.stock.clone(),
+        primary_exchange: broker_position.primary_exchange.clone(),
+        expiry: broker_position.expiry.clone(),
+        strike: broker_position.strike,
+        multiplier: broker_position.multiplier.clone(),
+        option_type: broker_position.option_type.clone(),
+        broker: broker_position.quantity,
+        local,
+        fix,
+    };
+    if local_positions.is_empty() {
+        return vec![mismatch("unknown", 0.0, broker_position.quantity)];
+    }
+    local_positions
+        .iter()
+        .map(|position| mismatch(&position.strategy, position.quantity, position.quantity))
+        .collect()
+}
+
 // VERY BAD FUNCTION CURRENTLY
 async fn positions_mismatch_alert(
     State(state): State<AppState>, 
-    Json(broker_positions): Json<HashMap<String, f64>>
+    Json(broker_positions): Json<BrokerPositions>
 ) {
+    state.last_sync.record(&broker_positions).await;
     let mut mismatched_positions = HashMap::<String, Vec<models::MismatchedPosition>>::new();
-    for (stock, broker_position) in  broker_positions.iter() {
+    for (stock, broker_position) in  broker_positions.stocks.iter() {
         let sql = format!("SELECT SUM(quantity) AS quantity, strategy FROM trading.current_positions WHERE stock={} GROUP BY strategy", stock);
         let query = sqlx::query_as::<_, Quantity>(&sql);
         let result = query.fetch_all(&state.db).await;
@@ -318,28 +457,50 @@ async fn positions_mismatch_alert(
                         });
                 });
             },
-            Err(_error) => {
-                println!("ERROR IN POSITIONS MISMATCH ALERT")
+            Err(err) => {
+                tracing::error!("Failed to read local stock positions for mismatch alert: {}", err)
             }
         }
     };
 
-    // Get the client
-    let mut client_guard = state.client.lock().await;
-    let client_optional = client_guard.as_mut();
+    let mut mismatched_option_positions = HashMap::<String, Vec<models::OptionMismatchedPosition>>::new();
+    for broker_position in broker_positions.options.iter() {
+        let result = sqlx::query_as::<_, Quantity>(
+            "SELECT SUM(quantity) AS quantity, strategy FROM trading.current_option_positions
+            WHERE stock = $1 AND primary_exchange = $2 AND expiry = $3 AND strike = $4 AND multiplier = $5 AND option_type = $6
+            GROUP BY strategy",
+        )
+        .bind(&broker_position.stock)
+        .bind(&broker_position.primary_exchange)
+        .bind(&broker_position.expiry)
+        .bind(broker_position.strike)
+        .bind(&broker_position.multiplier)
+        .bind(&broker_position.option_type)
+        .fetch_all(&state.db)
+        .await;
+        match result {
+            Ok(local_positions) => {
+                for mismatch in option_mismatches(broker_position, &local_positions) {
+                    mismatched_option_positions
+                        .entry(mismatch.contract_key())
+                        .or_insert_with(Vec::new)
+                        .push(mismatch);
+                }
+            },
+            Err(err) => {
+                tracing::error!("Failed to read local option positions for mismatch alert: {}", err)
+            }
+        }
+    }
 
-    // only if client exists
-    if let Some(client) = client_optional {
-        match client.send(serde_json::to_string(&mismatched_positions).unwrap().into()).await {
-            Ok(_) => {},
-            Err(_error) => {println!("ERROR");}
-        };
-    } else {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Client not connected yet!".into_response(),
-        );
+    let mismatch = PositionsMismatch {
+        stocks: mismatched_positions,
+        options: mismatched_option_positions,
+    };
+    if let Err(err) = state.notifier.send(serde_json::to_string(&mismatch).unwrap()).await {
+        tracing::error!("Failed to send positions mismatch alert: {}", err);
     }
+    stale_positions::notify_stale_positions(&state).await;
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, sqlx::FromRow)]
@@ -406,7 +567,7 @@ async fn pause_strategy(
     State(state): State<AppState>,
     Json(pause_strategy_details): Json<PauseStrategy>
    ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let strategy_crud = crud::CRUD::<models::StrategyFullKeys, models::StrategyPrimaryKeys, models::StrategyUpdateKeys>::new(state.db.clone(), "trading.strategy".to_string());
+    let strategy_crud = crud::CRUD::<models::StrategyFullKeys, models::StrategyPrimaryKeys, models::StrategyUpdateKeys>::new(state.db.clone(), STRATEGY_TABLE.to_string());
 
     if pause_strategy_details.graceful{
         strategy_crud.update(&models::StrategyPrimaryKeys{
@@ -478,7 +639,7 @@ async fn resume_strategy(
     State(state): State<AppState>,
     Json(resume_strategy_details): Json<ResumeStrategy>
    ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let strategy_crud = crud::CRUD::<models::StrategyFullKeys, models::StrategyPrimaryKeys, models::StrategyUpdateKeys>::new(state.db.clone(), "trading.strategy".to_string());
+    let strategy_crud = crud::CRUD::<models::StrategyFullKeys, models::StrategyPrimaryKeys, models::StrategyUpdateKeys>::new(state.db.clone(), STRATEGY_TABLE.to_string());
 
     strategy_crud.update(&models::StrategyPrimaryKeys{
         strategy: resume_strategy_details.strategy
@@ -531,7 +692,7 @@ async fn fix_current_positions(
     Json(mismatched_positions): Json<HashMap<(String, String), Vec<models::MismatchedPosition>>>,
 ) -> impl IntoResponse {
 
-    let current_position_crud = crud::CRUD::<models::CurrentStockPositionsFullKeys, models::CurrentStockPositionsPrimaryKeys, models::CurrentStockPositionsUpdateKeys>::new(state.db.clone(), "trading.current_positions".to_string());
+    let current_position_crud = crud::CRUD::<models::CurrentStockPositionsFullKeys, models::CurrentStockPositionsPrimaryKeys, models::CurrentStockPositionsUpdateKeys>::new(state.db.clone(), CURRENT_STOCK_POSITIONS_TABLE.to_string());
     for (stock_and_pri_exch, mismatched_position) in &mismatched_positions {
         for mismatched_position_strategy in mismatched_position {
             let primary_keys = models::CurrentStockPositionsPrimaryKeys {
@@ -552,26 +713,9 @@ async fn fix_current_positions(
         }
     }
 
-    // Get the client
-    let mut client_guard = state.client.lock().await;
-    let client_optional = client_guard.as_mut();
-
-    // only if client exists
-    if let Some(client) = client_optional {
-        match client.send(Message::Text("Current Positions Mismatch Updated!".to_string())).await {
-            Ok(_) => return (StatusCode::OK, "Notification passed along!".into_response()),
-            Err(err) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error when sending message to client: {}", err).into_response(),
-                );
-            }
-        } 
-    } else {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Client not connected yet!".into_response(),
-        );
+    match state.notifier.send("Current Positions Mismatch Updated!".to_string()).await {
+        Ok(_) => (StatusCode::OK, "Notification passed along!".into_response()),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.into_response()),
     }
 }
 
@@ -579,21 +723,78 @@ async fn get_portfolio_value_for_strategy(
     State(state): State<AppState>,
     axum::extract::Query(strategy): axum::extract::Query<portfolio_values::Strategy>,
 ) ->  Result<(StatusCode, Json<portfolio_values::PortfolioValueStrategy>), (StatusCode, String)>{
-    match portfolio_values::compute_portfolio_value_for_strategy(state, strategy).await {
-        Ok(res) => Ok((StatusCode::OK, res)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e))
+    let currency = state.currency.clone();
+    let money_decimal_places = state.money_decimal_places;
+    let db = state.db.clone();
+    match portfolio_cache::cached_portfolio_value_for_strategy(state, strategy).await {
+        Ok(res) if currency.needs_conversion() => {
+            let rates = currency::fx_rates(&db, &currency)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            Ok((StatusCode::OK, Json(currency::convert_strategy(res, &currency, &rates, money_decimal_places))))
+        }
+        Ok(res) => Ok((StatusCode::OK, Json(res))),
+        Err(e @ portfolio_values::PortfolioValueError::MissingPricing(_)) => Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string())),
+        Err(e @ portfolio_values::PortfolioValueError::Timeout(_)) => Err((StatusCode::GATEWAY_TIMEOUT, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
     }
 }
 
 async fn get_overall_portfolio_value(
     State(state): State<AppState>,
 ) ->  Result<(StatusCode, Json<portfolio_values::PortfolioValue>), (StatusCode, String)>{
-    match portfolio_values::compute_overall_portfolio_value(state).await {
-        Ok(res) => Ok((StatusCode::OK, res)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e))
+    let currency = state.currency.clone();
+    let money_decimal_places = state.money_decimal_places;
+    let db = state.db.clone();
+    match portfolio_cache::cached_overall_portfolio_value(state).await {
+        Ok(res) if currency.needs_conversion() => {
+            let rates = currency::fx_rates(&db, &currency)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            Ok((StatusCode::OK, Json(currency::convert_overall(res, &currency, &rates, money_decimal_places))))
+        }
+        Ok(res) => Ok((StatusCode::OK, Json(res))),
+        Err(e @ portfolio_values::PortfolioValueError::Timeout(_)) => Err((StatusCode::GATEWAY_TIMEOUT, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
     }
 }
 
+// Tables the CRUD handlers below read / write
+const STRATEGY_TABLE: &str = "trading.strategy";
+const CURRENT_STOCK_POSITIONS_TABLE: &str = "trading.current_stock_positions";
+const CURRENT_OPTION_POSITIONS_TABLE: &str = "trading.current_option_positions";
+const TARGET_STOCK_POSITIONS_TABLE: &str = "trading.target_stock_positions";
+const TARGET_OPTION_POSITIONS_TABLE: &str = "trading.target_option_positions";
+const OPEN_STOCK_ORDERS_TABLE: &str = "trading.open_stock_orders";
+const OPEN_OPTION_ORDERS_TABLE: &str = "trading.open_option_orders";
+const STOCK_TRANSACTIONS_TABLE: &str = "trading.stock_transactions";
+const OPTION_TRANSACTIONS_TABLE: &str = "trading.option_transactions";
+const HISTORICAL_DATA_TABLE: &str = "market_data.historical_data";
+const HISTORICAL_OPTIONS_DATA_TABLE: &str = "market_data.historical_options_data";
+const ACCOUNT_SUMMARY_TABLE: &str = "trading.account_summary";
+
+/// Every model with the table its handlers are built on, for schema_check::validate_schema
+fn registered_models() -> Vec<schema_check::ModelTable> {
+    vec![
+        schema_check::ModelTable::of::<models::Strategy>(STRATEGY_TABLE),
+        schema_check::ModelTable::of::<models::CurrentStockPositions>(CURRENT_STOCK_POSITIONS_TABLE),
+        schema_check::ModelTable::of::<models::CurrentOptionPositions>(CURRENT_OPTION_POSITIONS_TABLE),
+        schema_check::ModelTable::of::<models::TargetStockPositions>(TARGET_STOCK_POSITIONS_TABLE),
+        schema_check::ModelTable::of::<models::TargetOptionPositions>(TARGET_OPTION_POSITIONS_TABLE),
+        schema_check::ModelTable::of::<models::OpenStockOrders>(OPEN_STOCK_ORDERS_TABLE),
+        schema_check::ModelTable::of::<models::OpenOptionOrders>(OPEN_OPTION_ORDERS_TABLE),
+        schema_check::ModelTable::of::<models::StockTransactions>(STOCK_TRANSACTIONS_TABLE),
+        schema_check::ModelTable::of::<models::OptionTransactions>(OPTION_TRANSACTIONS_TABLE),
+        schema_check::ModelTable::of::<models::HistoricalData>(HISTORICAL_DATA_TABLE),
+        schema_check::ModelTable::of::<models::HistoricalOptionsData>(HISTORICAL_OPTIONS_DATA_TABLE),
+        schema_check::ModelTable::of::<models::AccountSummary>(ACCOUNT_SUMMARY_TABLE),
+        schema_check::ModelTable::new(
+            events::BROKER_EVENTS_TABLE,
+            vec!["id", "event_type", "order_id", "execution_id", "time", "payload"],
+        ),
+    ]
+}
+
 macro_rules! make_crud_handlers {
     (
         $create_name:ident,
@@ -653,7 +854,7 @@ make_crud_handlers!(
     models::StrategyFullKeys,
     models::StrategyPrimaryKeys,
     models::StrategyUpdateKeys, 
-    "trading.strategy"
+    STRATEGY_TABLE
 );
 make_crud_handlers!(
     create_current_stock_positions,
@@ -664,7 +865,7 @@ make_crud_handlers!(
     models::CurrentStockPositionsFullKeys,
     models::CurrentStockPositionsPrimaryKeys,
     models::CurrentStockPositionsUpdateKeys,
-    "trading.current_stock_positions"
+    CURRENT_STOCK_POSITIONS_TABLE
 );
 make_crud_handlers!(
     create_current_option_positions,
@@ -675,7 +876,7 @@ make_crud_handlers!(
     models::CurrentOptionPositionsFullKeys,
     models::CurrentOptionPositionsPrimaryKeys,
     models::CurrentOptionPositionsUpdateKeys,
-    "trading.current_option_positions"
+    CURRENT_OPTION_POSITIONS_TABLE
 );
 make_crud_handlers!(
     create_target_stock_positions,
@@ -686,7 +887,7 @@ make_crud_handlers!(
     models::TargetStockPositionsFullKeys,
     models::TargetStockPositionsPrimaryKeys,
     models::TargetStockPositionsUpdateKeys,
-    "trading.target_stock_positions"
+    TARGET_STOCK_POSITIONS_TABLE
 );
 make_crud_handlers!(
     create_target_option_positions,
@@ -697,7 +898,7 @@ make_crud_handlers!(
     models::TargetOptionPositionsFullKeys,
     models::TargetOptionPositionsPrimaryKeys,
     models::TargetOptionPositionsUpdateKeys,
-    "trading.target_option_positions"
+    TARGET_OPTION_POSITIONS_TABLE
 );
 make_crud_handlers!(
     create_open_stock_orders,
@@ -708,7 +909,7 @@ make_crud_handlers!(
     models::OpenStockOrdersFullKeys,
     models::OpenStockOrdersPrimaryKeys,
     models::OpenStockOrdersUpdateKeys,
-    "trading.open_stock_orders"
+    OPEN_STOCK_ORDERS_TABLE
 );
 make_crud_handlers!(
     create_open_option_orders,
@@ -719,7 +920,7 @@ make_crud_handlers!(
     models::OpenOptionOrdersFullKeys,
     models::OpenOptionOrdersPrimaryKeys,
     models::OpenOptionOrdersUpdateKeys,
-    "trading.open_option_orders"
+    OPEN_OPTION_ORDERS_TABLE
 );
 make_crud_handlers!(
     create_stock_transactions,
@@ -730,7 +931,7 @@ make_crud_handlers!(
     models::StockTransactionsFullKeys,
     models::StockTransactionsPrimaryKeys,
     models::StockTransactionsUpdateKeys,
-    "trading.stock_transactions"
+    STOCK_TRANSACTIONS_TABLE
 );
 make_crud_handlers!(
     create_option_transactions,
@@ -741,7 +942,7 @@ make_crud_handlers!(
     models::OptionTransactionsFullKeys,
     models::OptionTransactionsPrimaryKeys,
     models::OptionTransactionsUpdateKeys,
-    "trading.option_transactions"
+    OPTION_TRANSACTIONS_TABLE
 );
 make_crud_handlers!(
     create_historical_data, 
@@ -752,18 +953,7 @@ make_crud_handlers!(
     models::HistoricalDataFullKeys,
     models::HistoricalDataPrimaryKeys,
     models::HistoricalDataUpdateKeys, 
-    "market_data.historical_data"
-);
-make_crud_handlers!(
-    create_historical_volatility_data,
-    read_historical_volatility_data,
-    read_all_historical_volatility_data,
-    update_historical_volatility_data,
-    delete_historical_volatility_data,
-    models::HistoricalVolatilityDataFullKeys,
-    models::HistoricalVolatilityDataPrimaryKeys,
-    models::HistoricalVolatilityDataUpdateKeys, 
-    "market_data.historical_volatility_data"
+    HISTORICAL_DATA_TABLE
 );
 make_crud_handlers!(
     create_historical_options_data,
@@ -774,16 +964,139 @@ make_crud_handlers!(
     models::HistoricalOptionsDataFullKeys,
     models::HistoricalOptionsDataPrimaryKeys,
     models::HistoricalOptionsDataUpdateKeys, 
-    "phantom_trading.historical_options_data"
+    HISTORICAL_OPTIONS_DATA_TABLE
 );
-make_crud_handlers!(
-    create_phantom_portfolio_value,
-    read_phantom_portfolio_value,
-    read_all_phantom_portfolio_value,
-    update_phantom_portfolio_value,
-    delete_phantom_portfolio_value,
-    models::PhantomPortfolioValueFullKeys,
-    models::PhantomPortfolioValuePrimaryKeys,
-    models::PhantomPortfolioValueUpdateKeys, 
-    "phantom_trading.phantom_portfolio_value"
+// written by the trading app only, so there is nothing to create / update / delete here
+crate::crud_impl::make_read_handler!(
+    read_account_summary,
+    models::AccountSummaryFullKeys,
+    models::AccountSummaryPrimaryKeys,
+    models::AccountSummaryUpdateKeys,
+    ACCOUNT_SUMMARY_TABLE
+);
+crate::crud_impl::make_read_all_handler!(
+    read_all_account_summary,
+    models::AccountSummaryFullKeys,
+    models::AccountSummaryPrimaryKeys,
+    models::AccountSummaryUpdateKeys,
+    ACCOUNT_SUMMARY_TABLE
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broker_positions_need_both_stocks_and_options() {
+        let positions: BrokerPositions = serde_json::from_str(
+            r#"{
+                "stocks": {"QQQ": 10.0},
+                "options": [{
+                    "stock": "QQQ",
+                    "primary_exchange": "NASDAQ",
+                    "expiry": "20250718",
+                    "strike": 500.0,
+                    "multiplier": "100",
+                    "option_type": "Put",
+                    "quantity": -2.0
+                }]
+            }"#,
+        )
+        .expect("Expected full payload to parse");
+        assert_eq!(positions.stocks["QQQ"], 10.0);
+        assert_eq!(positions.options.len(), 1);
+
+        assert!(serde_json::from_str::<BrokerPositions>(r#"{"stocks": {"QQQ": 10.0}}"#).is_err());
+        assert!(serde_json::from_str::<BrokerPositions>(r#"{"options": []}"#).is_err());
+    }
+
+    #[test]
+    fn option_only_held_by_broker_is_attributed_to_unknown() {
+        let broker_position = BrokerOptionPosition {
+            stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+            expiry: "20250718".to_string(),
+            strike: 500.0,
+            multiplier: "100".to_string(),
+            option_type: models::OptionType::Put,
+            quantity: -2.0,
+        };
+        let mismatches = option_mismatches(&broker_position, &[]);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].strategy, "unknown");
+        assert_eq!(mismatches[0].fix, -2.0);
+
+        let matching = [Quantity {
+            quantity: -2.0,
+            strategy: "hedge".to_string(),
+        }];
+        assert!(option_mismatches(&broker_position, &matching).is_empty());
+    }
+
+    #[tokio::test]
+    async fn nan_price_is_unprocessable() {
+        let db = sqlx::PgPool::connect_lazy("postgres://localhost/unused")
+            .expect("Expected lazy pool");
+
+        let response = create_stock_transactions(
+            State(test_support::app_state(db)),
+            Json::from_bytes(
+                br#"{
+                    "execution_id": "nan_price",
+                    "strategy": "strat",
+                    "stock": "QQQ",
+                    "primary_exchange": "NASDAQ",
+                    "order_perm_id": 1,
+                    "time": "2025-07-18T14:30:00Z",
+                    "price": NaN,
+                    "quantity": 10.0,
+                    "fees": 1.0,
+                    "realized_pnl": null
+                }"#,
+            ),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn duplicate_primary_key_is_a_conflict() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'duplicate_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clear strategy");
+
+        let create = || {
+            create_strategy(
+                State(test_support::app_state(db.clone())),
+                Json::from_bytes(
+                    br#"{
+                        "strategy": "duplicate_strat",
+                        "capital": 1000.0,
+                        "initial_capital": 1000.0,
+                        "status": "Active"
+                    }"#,
+                ),
+            )
+        };
+        let created = create().await.into_response();
+        let duplicate = create().await.into_response();
+
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'duplicate_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up strategy");
+        assert_eq!(created.status(), StatusCode::OK);
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(duplicate.into_body(), usize::MAX)
+            .await
+            .expect("Expected body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("Expected JSON body");
+        assert_eq!(body["error"], "Item already exists");
+        assert_eq!(body["key"], "(strategy)=(duplicate_strat)");
+    }
+}
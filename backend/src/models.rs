@@ -85,6 +85,49 @@ pub struct MismatchedPosition {
     pub fix: f64,
 }
 
+/// MismatchedPosition for an option, carrying the full contract so the mismatch can be
+/// reconciled against exactly one row of current_option_positions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionMismatchedPosition {
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+    pub broker: f64,
+    pub local: f64,
+    pub fix: f64,
+}
+
+/// e.g. "AAPL 20250718 200 C x100" - how an option contract is named in alerts
+pub fn option_contract_key(
+    stock: &str,
+    expiry: &str,
+    strike: f64,
+    option_type: &OptionType,
+    multiplier: &str,
+) -> String {
+    format!(
+        "{} {} {} {} x{}",
+        stock, expiry, strike, option_type, multiplier
+    )
+}
+
+impl OptionMismatchedPosition {
+    /// What option mismatches are grouped under in alerts, see option_contract_key
+    pub fn contract_key(&self) -> String {
+        option_contract_key(
+            &self.stock,
+            &self.expiry,
+            self.strike,
+            &self.option_type,
+            &self.multiplier,
+        )
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -120,6 +163,23 @@ pub struct Strategy {
     pub status: Option<Status>,
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    ExtractFullKeys,
+    ExtractPrimaryKeys,
+    ExtractUpdateKeys,
+    DeriveInsertable,
+    FromRow,
+)]
+pub struct StrategyParams {
+    pub strategy: String,
+    pub params: Option<serde_json::Value>,
+    pub params_schema: Option<serde_json::Value>,
+}
+
 #[derive(
     Debug,
     Clone,
@@ -223,6 +283,7 @@ pub struct OpenStockOrders {
     pub strategy: Option<String>,
     pub stock: Option<String>,
     pub primary_exchange: Option<String>,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: Option<DateTime<Utc>>,
     pub quantity: Option<f64>,
 
@@ -251,6 +312,7 @@ pub struct OpenOptionOrders {
     pub strike: Option<f64>,
     pub multiplier: Option<String>,
     pub option_type: Option<OptionType>,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: Option<DateTime<Utc>>,
     pub quantity: Option<f64>,
 
@@ -270,15 +332,19 @@ pub struct OpenOptionOrders {
     FromRow,
 )]
 pub struct StockTransactions {
+    #[primary_key]
     pub execution_id: String,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub order_perm_id: Option<i32>,
-    pub time: Option<DateTime<Utc>>,
-    pub price: Option<f64>,
-    pub quantity: Option<f64>,
-    pub fees: Option<Decimal>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub order_perm_id: i32,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub quantity: f64,
+    pub fees: Decimal,
+    // Recorded by the trading app when the fill reduced a position
+    pub realized_pnl: Option<f64>,
 }
 
 #[derive(
@@ -293,19 +359,23 @@ pub struct StockTransactions {
     FromRow,
 )]
 pub struct OptionTransactions {
+    #[primary_key]
     pub execution_id: String,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub expiry: Option<String>,
-    pub strike: Option<f64>,
-    pub multiplier: Option<String>,
-    pub option_type: Option<OptionType>,
-    pub order_perm_id: Option<i32>,
-    pub time: Option<DateTime<Utc>>,
-    pub price: Option<f64>,
-    pub quantity: Option<f64>,
-    pub fees: Option<rust_decimal::Decimal>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+    pub order_perm_id: i32,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub quantity: f64,
+    pub fees: rust_decimal::Decimal,
+    // Recorded by the trading app when the fill reduced a position
+    pub realized_pnl: Option<f64>,
 }
 
 #[derive(
@@ -338,6 +408,7 @@ pub struct StagedCommissions {
 pub struct HistoricalData {
     pub stock: String,
     pub primary_exchange: String,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: DateTime<Utc>,
     pub open: Option<f64>,
     pub high: Option<f64>,
@@ -359,6 +430,7 @@ pub struct HistoricalData {
 )]
 pub struct DailyHistoricalData {
     pub stock: String,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: DateTime<Utc>,
     pub open: Option<Decimal>,
     pub high: Option<Decimal>,
@@ -367,26 +439,6 @@ pub struct DailyHistoricalData {
     pub volume: Option<Decimal>,
 }
 
-#[derive(
-    Debug,
-    Clone,
-    Serialize,
-    Deserialize,
-    ExtractFullKeys,
-    ExtractPrimaryKeys,
-    ExtractUpdateKeys,
-    DeriveInsertable,
-    FromRow,
-)]
-pub struct HistoricalVolatilityData {
-    pub stock: String,
-    pub time: DateTime<Utc>,
-    pub open: Option<f64>,
-    pub high: Option<f64>,
-    pub low: Option<f64>,
-    pub close: Option<f64>,
-}
-
 #[derive(
     Debug,
     Clone,
@@ -405,6 +457,7 @@ pub struct HistoricalOptionsData {
     pub strike: f64,
     pub multiplier: String,
     pub option_type: OptionType,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: DateTime<Utc>,
     pub open: Option<f64>,
     pub high: Option<f64>,
@@ -425,6 +478,7 @@ pub struct HistoricalOptionsData {
     FromRow,
 )]
 pub struct Logs {
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: DateTime<Utc>,
     pub level: String,
     pub name: String,
@@ -442,13 +496,20 @@ pub struct Logs {
     ExtractUpdateKeys,
     DeriveInsertable,
 )]
-pub struct PhantomPortfolioValue {
+pub struct AccountSummary {
+    #[primary_key]
+    pub account: String,
+    #[primary_key]
+    #[serde(serialize_with = "crate::timestamps::serialize")]
     pub time: DateTime<Utc>,
-    pub cash_portfolio_value: Option<f64>,
-    pub option_portfolio_value: Option<f64>,
-    pub bought_price: Option<f64>,
-    pub strike: Option<f64>,
-    pub peak: Option<f64>,
-    pub paused: Option<bool>,
-    pub resume_trades: Option<i32>,
+    // Written by the trading app at the session open / close - None where IBKR didn't report
+    // the tag
+    pub currency: Option<String>,
+    pub net_liquidation: Option<f64>,
+    pub total_cash_value: Option<f64>,
+    pub buying_power: Option<f64>,
+    pub available_funds: Option<f64>,
+    pub excess_liquidity: Option<f64>,
+    pub init_margin_req: Option<f64>,
+    pub maint_margin_req: Option<f64>,
 }
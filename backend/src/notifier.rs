@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, stream::SplitSink};
+use tokio::{
+    sync::{Mutex, mpsc},
+    time::Instant,
+};
+
+/// Sending half of a websocket connection - the receiving half is read by insert_client, which
+/// replies to commands on it while notifications go to whichever connection is the client
+pub type WsSender = Arc<Mutex<SplitSink<WebSocket, Message>>>;
+
+/// What is actually written to the websocket
+/// - Single messages are sent as is, so the frontend sees exactly what it did before batching
+/// - Batch is sent as { "batch": [...] } with each message as its own string
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsMessage {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl WsMessage {
+    fn into_text(self) -> String {
+        match self {
+            WsMessage::Single(message) => message,
+            WsMessage::Batch(messages) => serde_json::json!({ "batch": messages }).to_string(),
+        }
+    }
+}
+
+/// Sends notifications to the connected websocket client
+/// - with a zero window every notification is sent straight away
+/// - otherwise notifications arriving within window of the first one are coalesced into a
+///   single WsMessage::Batch so a burst doesn't flood the frontend
+#[derive(Clone)]
+pub struct WsNotifier {
+    client: Arc<Mutex<Option<WsSender>>>,
+    batch_sender: Option<mpsc::UnboundedSender<String>>,
+    deduplicator: Option<Deduplicator>,
+}
+
+impl WsNotifier {
+    pub fn new(client: Arc<Mutex<Option<WsSender>>>, window: Duration) -> Self {
+        if window.is_zero() {
+            return Self {
+                client,
+                batch_sender: None,
+                deduplicator: None,
+            };
+        }
+
+        let (batch_sender, batch_receiver) = mpsc::unbounded_channel::<String>();
+        let batch_client = client.clone();
+        tokio::spawn(async move {
+            coalesce(batch_receiver, window, |ws_message| {
+                let batch_client = batch_client.clone();
+                async move {
+                    if let Err(err) = send_to_client(&batch_client, ws_message).await {
+                        tracing::error!("Failed to send batched notifications: {}", err);
+                    }
+                }
+            })
+            .await
+        });
+
+        Self {
+            client,
+            batch_sender: Some(batch_sender),
+            deduplicator: None,
+        }
+    }
+
+    /// Drops messages identical to one sent within window - a zero window sends every message
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.deduplicator = (!window.is_zero()).then(|| Deduplicator::new(window));
+        self
+    }
+
+    /// Sends (or queues, when batching) a message to the websocket client
+    /// - errors are only reported for immediate sends - batched sends are logged instead
+    /// - a duplicate within the dedup window is dropped and reported as sent
+    pub async fn send(&self, message: String) -> Result<(), String> {
+        if let Some(deduplicator) = &self.deduplicator
+            && !deduplicator.first_in_window(&message)
+        {
+            tracing::debug!("Dropping notification sent within the dedup window");
+            return Ok(());
+        }
+        match &self.batch_sender {
+            Some(batch_sender) => batch_sender
+                .send(message)
+                .map_err(|err| format!("Notification batcher stopped: {}", err)),
+            None => send_to_client(&self.client, WsMessage::Single(message)).await,
+        }
+    }
+}
+
+/// Remembers when each message content (by hash) was last let through
+#[derive(Clone)]
+pub struct Deduplicator {
+    window: Duration,
+    last_sent: Arc<std::sync::Mutex<HashMap<u64, Instant>>>,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_sent: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether message should be sent, i.e. no identical message was let through within the
+    /// window - records it as sent if so
+    pub fn first_in_window(&self, message: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        message.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let now = Instant::now();
+        let mut last_sent = self
+            .last_sent
+            .lock()
+            .expect("Expected to be able to acquire lock for Deduplicator.last_sent");
+        last_sent.retain(|_, sent_at| now.duration_since(*sent_at) < self.window);
+        if last_sent.contains_key(&key) {
+            return false;
+        }
+        last_sent.insert(key, now);
+        true
+    }
+}
+
+pub async fn send_to_client(
+    client: &Arc<Mutex<Option<WsSender>>>,
+    ws_message: WsMessage,
+) -> Result<(), String> {
+    let sender = client
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Client not connected yet!".to_string())?;
+    send_to_sender(&sender, ws_message).await
+}
+
+/// Sends ws_message on one connection's sender
+pub async fn send_to_sender(sender: &WsSender, ws_message: WsMessage) -> Result<(), String> {
+    sender
+        .lock()
+        .await
+        .send(Message::Text(ws_message.into_text()))
+        .await
+        .map_err(|err| format!("Error when sending message to client: {}", err))
+}
+
+/// Groups messages received within window of the first message of a group and hands each group
+/// to send - a group of one is sent as WsMessage::Single
+pub async fn coalesce<F, Fut>(
+    mut receiver: mpsc::UnboundedReceiver<String>,
+    window: Duration,
+    mut send: F,
+) where
+    F: FnMut(WsMessage) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    while let Some(first_message) = receiver.recv().await {
+        let mut messages = vec![first_message];
+        let deadline = tokio::time::Instant::now() + window;
+        while let Ok(Some(message)) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+            messages.push(message);
+        }
+
+        let ws_message = if messages.len() == 1 {
+            WsMessage::Single(messages.remove(0))
+        } else {
+            WsMessage::Batch(messages)
+        };
+        send(ws_message).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_message_is_only_let_through_once_per_window() {
+        let deduplicator = Deduplicator::new(Duration::from_millis(50));
+
+        assert!(deduplicator.first_in_window("position mismatch QQQ"));
+        assert!(!deduplicator.first_in_window("position mismatch QQQ"));
+        assert!(deduplicator.first_in_window("position mismatch SPY"));
+        // clones share what has been sent
+        assert!(
+            !deduplicator
+                .clone()
+                .first_in_window("position mismatch SPY")
+        );
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(deduplicator.first_in_window("position mismatch QQQ"));
+    }
+}
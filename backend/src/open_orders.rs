@@ -0,0 +1,179 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{models, positions::StrategyFilter};
+
+/// Open order annotated with how long it has been working and how much of it has filled
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkingOrder<T> {
+    #[serde(flatten)]
+    pub order: T,
+    /// Seconds since the order was opened - None if its time is unknown
+    pub age_seconds: Option<f64>,
+    /// filled / quantity - None if either is unknown or the quantity is 0
+    pub fill_pct: Option<f64>,
+}
+
+impl<T> WorkingOrder<T> {
+    pub fn new(
+        order: T,
+        time: Option<DateTime<Utc>>,
+        quantity: Option<f64>,
+        filled: Option<f64>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let age_seconds = time.map(|time| (now - time).num_milliseconds() as f64 / 1000.0);
+        let fill_pct = match (filled, quantity) {
+            (Some(filled), Some(quantity)) if quantity != 0.0 => Some(filled / quantity),
+            _ => None,
+        };
+        Self {
+            order,
+            age_seconds,
+            fill_pct,
+        }
+    }
+}
+
+/// Working order of a strategy, tagged with its asset type like StrategyPosition
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "asset_type", rename_all = "lowercase")]
+pub enum StrategyOpenOrder {
+    Stock(WorkingOrder<models::OpenStockOrders>),
+    Option(WorkingOrder<models::OpenOptionOrders>),
+}
+
+impl StrategyOpenOrder {
+    pub fn stock(order: models::OpenStockOrders, now: DateTime<Utc>) -> Self {
+        let (time, quantity, filled) = (order.time, order.quantity, order.filled);
+        Self::Stock(WorkingOrder::new(order, time, quantity, filled, now))
+    }
+
+    pub fn option(order: models::OpenOptionOrders, now: DateTime<Utc>) -> Self {
+        let (time, quantity, filled) = (order.time, order.quantity, order.filled);
+        Self::Option(WorkingOrder::new(order, time, quantity, filled, now))
+    }
+
+    fn age_seconds(&self) -> f64 {
+        match self {
+            Self::Stock(order) => order.age_seconds,
+            Self::Option(order) => order.age_seconds,
+        }
+        .unwrap_or(0.0)
+    }
+}
+
+/// GET /open_orders?strategy=...
+/// - Returns both stock and option open orders of the strategy, oldest first, each with its
+///   age_seconds and fill_pct as of the request
+pub async fn get_open_orders_for_strategy(
+    State(state): State<crate::AppState>,
+    Query(filter): Query<StrategyFilter>,
+) -> Result<(StatusCode, Json<Vec<StrategyOpenOrder>>), (StatusCode, String)> {
+    let stock_orders = sqlx::query_as::<_, models::OpenStockOrders>(
+        "SELECT * FROM trading.open_stock_orders WHERE strategy = $1",
+    )
+    .bind(&filter.strategy)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read open stock orders for strategy: {}", err),
+        )
+    })?;
+
+    let option_orders = sqlx::query_as::<_, models::OpenOptionOrders>(
+        "SELECT * FROM trading.open_option_orders WHERE strategy = $1",
+    )
+    .bind(&filter.strategy)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read open option orders for strategy: {}", err),
+        )
+    })?;
+
+    let now = Utc::now();
+    let mut orders: Vec<StrategyOpenOrder> = stock_orders
+        .into_iter()
+        .map(|order| StrategyOpenOrder::stock(order, now))
+        .chain(
+            option_orders
+                .into_iter()
+                .map(|order| StrategyOpenOrder::option(order, now)),
+        )
+        .collect();
+    // oldest - most likely stuck - first across both asset types
+    orders.sort_by(|a, b| b.age_seconds().total_cmp(&a.age_seconds()));
+
+    Ok((StatusCode::OK, Json(orders)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn orders_of_the_strategy_come_oldest_first_with_their_fill() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy IN ('open_orders_strat', 'other_strat');
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status) VALUES
+                ('open_orders_strat', 1000, 1000, 'active'),
+                ('other_strat', 1000, 1000, 'active');
+            INSERT INTO trading.open_stock_orders
+                (strategy, order_perm_id, order_id, time, stock, primary_exchange, quantity,
+                filled, executions)
+            VALUES
+                ('open_orders_strat', 701001, 1, NOW() - INTERVAL '1 minute', 'QQQ', 'NASDAQ',
+                10, 4, '{}'),
+                ('other_strat', 701002, 2, NOW() - INTERVAL '1 hour', 'QQQ', 'NASDAQ', 5, 0, '{}');
+            INSERT INTO trading.open_option_orders
+                (strategy, order_perm_id, order_id, time, stock, primary_exchange, quantity,
+                filled, executions, expiry, strike, multiplier, option_type)
+            VALUES
+                ('open_orders_strat', 701003, 3, NOW() - INTERVAL '10 minutes', 'QQQ', 'NASDAQ',
+                -2, 0, '{}', '20250718', 500, '100', 'P');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert open orders");
+
+        let result = get_open_orders_for_strategy(
+            State(test_support::app_state(db.clone())),
+            Query(StrategyFilter {
+                strategy: "open_orders_strat".to_string(),
+            }),
+        )
+        .await;
+
+        sqlx::query(
+            "DELETE FROM trading.strategy WHERE strategy IN ('open_orders_strat', 'other_strat')",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up strategies");
+        let (status, Json(orders)) = result.expect("Expected open orders");
+        assert_eq!(status, StatusCode::OK);
+        let orders = serde_json::to_value(&orders).expect("Expected orders to serialize");
+        let orders = orders.as_array().expect("Expected a list of orders");
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0]["asset_type"], "option");
+        assert_eq!(orders[0]["order_perm_id"], 701003);
+        assert_eq!(orders[0]["fill_pct"], 0.0);
+        assert!(orders[0]["age_seconds"].as_f64().unwrap() >= 600.0);
+        assert_eq!(orders[1]["asset_type"], "stock");
+        assert_eq!(orders[1]["order_perm_id"], 701001);
+        assert_eq!(orders[1]["fill_pct"], 0.4);
+    }
+}
@@ -0,0 +1,145 @@
+use axum::{Json, extract::Query, http::StatusCode};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Identifies a single open order - either id is enough
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelOrder {
+    pub order_id: Option<i32>,
+    pub perm_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelOrderStatus {
+    pub order_id: i32,
+    pub perm_id: i32,
+    /// Status reported by IBKR, e.g. Cancelled / PendingCancel
+    pub status: String,
+    /// Whether the trading bot removed the open order row, i.e. IBKR confirmed the cancel
+    pub removed: bool,
+}
+
+/// POST /orders/cancel
+/// - Forwards the cancel to the trading bot, which sends it to IBKR and removes the open order
+///   once IBKR confirms it
+pub async fn cancel_order(
+    Json(cancel_order): Json<CancelOrder>,
+) -> Result<(StatusCode, Json<CancelOrderStatus>), (StatusCode, String)> {
+    if cancel_order.order_id.is_none() && cancel_order.perm_id.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Either order_id or perm_id has to be given".to_string(),
+        ));
+    }
+
+    let url = format!("http://{}/cancel-order", env!("TRADING_BOT_URL"));
+
+    let body = serde_json::to_string(&cancel_order).map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to serialize cancel-order request: {}", err),
+        )
+    })?;
+
+    let client = Client::new();
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error occurred during cancel-order request: {}", err),
+            )
+        })?
+        .error_for_status()
+        .map_err(|err| {
+            (
+                err.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                format!("Error occurred during cancel-order request: {}", err),
+            )
+        })?;
+
+    let response_body = response.text().await.map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read cancel-order response: {}", err),
+        )
+    })?;
+    let cancel_order_status =
+        serde_json::from_str::<CancelOrderStatus>(&response_body).map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to parse cancel-order response: {}", err),
+            )
+        })?;
+
+    Ok((StatusCode::OK, Json(cancel_order_status)))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyQuery {
+    pub strategy: String,
+}
+
+/// An order a strategy's target positions would place, as previewed by the trading bot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewOrder {
+    pub stock: String,
+    pub primary_exchange: String,
+    pub quantity: f64,
+    pub price: f64,
+    pub estimated_notional: f64,
+    /// Risk limits the order exceeds - empty when the order is within limits
+    pub violations: Vec<String>,
+}
+
+/// POST /target_stock_positions/validate?strategy=...
+/// - Dry run before flipping a strategy to Active: the trading bot diffs target against current
+///   positions and returns the orders it would place, nothing is sent to IBKR
+pub async fn validate_target_stock_positions(
+    Query(query): Query<StrategyQuery>,
+) -> Result<(StatusCode, Json<Vec<PreviewOrder>>), (StatusCode, String)> {
+    let url = format!(
+        "http://{}/target_stock_positions/validate",
+        env!("TRADING_BOT_URL")
+    );
+
+    let client = Client::new();
+    let response = client
+        .post(url)
+        .query(&query)
+        .send()
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error occurred during validate request: {}", err),
+            )
+        })?
+        .error_for_status()
+        .map_err(|err| {
+            (
+                err.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                format!("Error occurred during validate request: {}", err),
+            )
+        })?;
+
+    let response_body = response.text().await.map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read validate response: {}", err),
+        )
+    })?;
+    let preview_orders =
+        serde_json::from_str::<Vec<PreviewOrder>>(&response_body).map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to parse validate response: {}", err),
+            )
+        })?;
+
+    Ok((StatusCode::OK, Json(preview_orders)))
+}
@@ -0,0 +1,170 @@
+use std::{future::Future, time::Duration};
+
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use sqlx::PgPool;
+
+use crate::portfolio_values::{
+    PortfolioValue, PortfolioValueError, PortfolioValueStrategy, Strategy,
+    compute_overall_portfolio_value, compute_portfolio_value_for_strategy,
+};
+
+/// Number of transactions, the latest one's time and the number of strategies - a new transaction
+/// written by either the trading app or the CRUD endpoints changes it, so a value cached under an
+/// older stamp is stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::FromRow)]
+struct TransactionStamp {
+    transactions: i64,
+    last_time: Option<DateTime<Utc>>,
+    strategies: i64,
+}
+
+/// Portfolio values computed within the last ttl, so dashboard polls don't recompute them
+/// - entries are keyed on the strategy and the query options, the overall value on its own
+/// - a strategy's entry is dropped as soon as it has a new transaction
+#[derive(Clone)]
+pub struct PortfolioCache {
+    strategies: Cache<String, (TransactionStamp, PortfolioValueStrategy)>,
+    overall: Cache<(), (TransactionStamp, PortfolioValue)>,
+}
+
+impl PortfolioCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            strategies: Cache::builder().time_to_live(ttl).build(),
+            overall: Cache::builder().time_to_live(ttl).build(),
+        }
+    }
+}
+
+/// Stamp of strategy's transactions, or of every strategy's for None
+async fn transaction_stamp(
+    db: &PgPool,
+    strategy: Option<&str>,
+) -> Result<TransactionStamp, String> {
+    sqlx::query_as::<_, TransactionStamp>(
+        r#"
+        SELECT
+            COUNT(*) AS transactions,
+            MAX(time) AS last_time,
+            (SELECT COUNT(*) FROM trading.strategy) AS strategies
+        FROM (
+            SELECT time FROM trading.stock_transactions WHERE $1::text IS NULL OR strategy = $1
+            UNION ALL
+            SELECT time FROM trading.option_transactions WHERE $1::text IS NULL OR strategy = $1
+        ) transactions
+        "#,
+    )
+    .bind(strategy)
+    .fetch_one(db)
+    .await
+    .map_err(|err| format!("Failed to read transaction stamp: {}", err))
+}
+
+/// Cached value under key if it was computed from the current stamp, otherwise computes and
+/// caches it
+/// - if the stamp can't be read the value is computed without touching the cache
+async fn get_or_compute<K, V, E, F>(
+    cache: &Cache<K, (TransactionStamp, V)>,
+    key: K,
+    stamp: Result<TransactionStamp, String>,
+    compute: F,
+) -> Result<V, E>
+where
+    K: std::hash::Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    F: Future<Output = Result<V, E>>,
+{
+    let stamp = match stamp {
+        Ok(stamp) => stamp,
+        Err(err) => {
+            tracing::warn!("{}, computing portfolio value uncached", err);
+            return compute.await;
+        }
+    };
+    if let Some((cached_stamp, value)) = cache.get(&key).await
+        && cached_stamp == stamp
+    {
+        return Ok(value);
+    }
+    let value = compute.await?;
+    cache.insert(key, (stamp, value.clone())).await;
+    Ok(value)
+}
+
+pub async fn cached_portfolio_value_for_strategy(
+    state: crate::AppState,
+    strategy: Strategy,
+) -> Result<PortfolioValueStrategy, PortfolioValueError> {
+    let key = format!(
+        "{}:{:?}:{}",
+        strategy.strategy, strategy.pricing_fallback, strategy.risk_free_rate
+    );
+    let stamp = transaction_stamp(&state.db, Some(&strategy.strategy)).await;
+    let cache = state.portfolio_cache.strategies.clone();
+    get_or_compute(&cache, key, stamp, async move {
+        compute_portfolio_value_for_strategy(state, strategy)
+            .await
+            .map(|json| json.0)
+    })
+    .await
+}
+
+pub async fn cached_overall_portfolio_value(
+    state: crate::AppState,
+) -> Result<PortfolioValue, PortfolioValueError> {
+    let stamp = transaction_stamp(&state.db, None).await;
+    let cache = state.portfolio_cache.overall.clone();
+    get_or_compute(&cache, (), stamp, async move {
+        compute_overall_portfolio_value(state)
+            .await
+            .map(|json| json.0)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(transactions: i64) -> Result<TransactionStamp, String> {
+        Ok(TransactionStamp {
+            transactions,
+            last_time: None,
+            strategies: 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached_and_values_are_until_the_stamp_changes() {
+        let cache: Cache<(), (TransactionStamp, f64)> = Cache::builder()
+            .time_to_live(Duration::from_secs(60))
+            .build();
+
+        let failed: Result<f64, String> =
+            get_or_compute(&cache, (), stamp(1), async { Err("timed out".to_string()) }).await;
+        assert_eq!(failed, Err("timed out".to_string()));
+        assert!(cache.get(&()).await.is_none());
+
+        let computed = get_or_compute(&cache, (), stamp(1), async { Ok::<_, String>(100.0) }).await;
+        assert_eq!(computed, Ok(100.0));
+        let cached = get_or_compute(&cache, (), stamp(1), async { Ok::<_, String>(200.0) }).await;
+        assert_eq!(cached, Ok(100.0));
+
+        // a failed recompute after a new transaction leaves the stale value to be recomputed
+        let failed: Result<f64, String> =
+            get_or_compute(&cache, (), stamp(2), async { Err("timed out".to_string()) }).await;
+        assert_eq!(failed, Err("timed out".to_string()));
+        let recomputed =
+            get_or_compute(&cache, (), stamp(2), async { Ok::<_, String>(300.0) }).await;
+        assert_eq!(recomputed, Ok(300.0));
+
+        // no stamp - computed without the cache
+        let uncached = get_or_compute(&cache, (), Err("no stamp".to_string()), async {
+            Ok::<_, String>(400.0)
+        })
+        .await;
+        assert_eq!(uncached, Ok(400.0));
+        assert_eq!(cache.get(&()).await, Some((stamp(2).unwrap(), 300.0)));
+    }
+}
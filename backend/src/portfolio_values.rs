@@ -1,13 +1,24 @@
 use crate::models;
 use axum::Json;
-use futures::future::join_all;
-use rust_decimal::{dec, prelude::ToPrimitive};
+use futures::{StreamExt, stream};
+use rust_decimal::{
+    Decimal,
+    prelude::{FromPrimitive, ToPrimitive},
+};
 use serde::{Deserialize, Serialize};
+use shared::options::{OptionRight, OptionSettlement};
 use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
 use std::f64;
 
+/// Money is accumulated as Decimal and only converted back to f64 for the JSON response, so long
+/// equity curves don't pick up float drift
+/// - NaN / infinite values (which should never be prices) count as 0
+fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or(Decimal::ZERO)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PositionInfo {
     pub avg_price: f64,
@@ -29,12 +40,15 @@ pub struct OptionDetails {
 pub struct PortfolioMetrics {
     pub cagr: f64,
     pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
     pub max_drawdown: f64,
     pub calmar_ratio: f64,
     pub profit_factor: f64,
     pub win_rate: f64,
     pub avg_trade_return: f64,
     pub positions: HashMap<String, PositionInfo>,
+    // Too few portfolio values for cagr / sharpe / sortino / drawdown / calmar - those are left at 0
+    pub insufficient_data: bool,
 }
 
 // pub fn compute_portfolio_metrics(
@@ -171,22 +185,33 @@ pub struct PortfolioMetrics {
 //     }
 // }
 
+/// 5min returns per year (12 per hour * 24 * 252) - used to annualize Sharpe
+const PERIODS_PER_YEAR: f64 = 252.0 * 24.0 * 12.0;
+
+/// risk_free_rate is annual (e.g. 0.04 for 4%) and is scaled down to a 5min log return before
+/// being subtracted from the mean return for Sharpe
+/// - with fewer than min_observations portfolio values the return based metrics are not computed
+///   and insufficient_data is set instead
 pub fn compute_portfolio_metrics(
     portfolio_values: &Vec<(DateTime<Utc>, f64)>,
     stock_transactions: &Vec<crate::models::StockTransactions>,
     option_transactions: &Vec<crate::models::OptionTransactions>,
+    risk_free_rate: f64,
+    min_observations: usize,
 ) -> PortfolioMetrics {
     // ===== Portfolio Value Metrics =====
     if portfolio_values.is_empty() {
         return PortfolioMetrics {
             cagr: 0.0,
             sharpe_ratio: 0.0,
+            sortino_ratio: 0.0,
             max_drawdown: 0.0,
             calmar_ratio: 0.0,
             profit_factor: 0.0,
             win_rate: 0.0,
             avg_trade_return: 0.0,
             positions: HashMap::new(),
+            insufficient_data: true,
         };
     }
 
@@ -234,9 +259,28 @@ pub fn compute_portfolio_metrics(
         0.0
     };
 
+    let period_risk_free_rate = (1.0 + risk_free_rate).ln() / PERIODS_PER_YEAR;
     let sharpe_ratio = if std_return != 0.0 {
-        mean_return / std_return * ((252.0 * 24.0 * 12.0) as f64).sqrt() // annualizing 5min returns (12 per hour * 24 * 365.25)
+        (mean_return - period_risk_free_rate) / std_return * PERIODS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    };
+
+    // Downside deviation only counts returns below the risk free rate, over every period
+    let downside_deviation = if !returns.is_empty() {
+        (returns
+            .iter()
+            .map(|r| (r - period_risk_free_rate).min(0.0).powi(2))
+            .sum::<f64>()
+            / returns.len() as f64)
+            .sqrt()
+    } else {
+        0.0
+    };
+    let sortino_ratio = if downside_deviation != 0.0 {
+        (mean_return - period_risk_free_rate) / downside_deviation * PERIODS_PER_YEAR.sqrt()
     } else {
+        tracing::debug!("No returns below the risk free rate, reporting Sortino ratio as 0");
         0.0
     };
 
@@ -263,42 +307,57 @@ pub fn compute_portfolio_metrics(
         0.0
     };
 
+    // A couple of points give meaningless extremes (e.g. a 9000% CAGR) - the frontend shows
+    // "not enough data" instead
+    let insufficient_data = portfolio_values.len() < min_observations;
+    let (cagr, sharpe_ratio, sortino_ratio, max_drawdown, calmar_ratio) = if insufficient_data {
+        (0.0, 0.0, 0.0, 0.0, 0.0)
+    } else {
+        (
+            cagr,
+            sharpe_ratio,
+            sortino_ratio,
+            max_drawdown,
+            calmar_ratio,
+        )
+    };
+
     // ===== Transaction Metrics =====
-    let mut combined_profits: Vec<f64> = vec![];
+    let mut combined_profits: Vec<Decimal> = vec![];
 
     // Process stock transactions
     let mut open_stock_positions = HashMap::<String, (f64, f64)>::new(); // (avg_price, quantity)
     let mut stock_last_pnl = HashMap::<String, f64>::new();
 
     for txn in stock_transactions {
-        let price = txn.price.unwrap_or(0.0);
-        let qty = txn.quantity.unwrap_or(0.0);
+        let price = txn.price;
+        let qty = txn.quantity;
 
         if qty > 0.0 {
             // Buy
-            let curr_position = open_stock_positions
-                .get(&txn.stock.clone().unwrap())
-                .unwrap_or(&(0.0, 0.0));
+            let curr_position = open_stock_positions.get(&txn.stock).unwrap_or(&(0.0, 0.0));
             let new_avg_price = if curr_position.1 + qty > 0.0 {
                 ((curr_position.0 * curr_position.1) + (price * qty)) / (curr_position.1 + qty)
             } else {
                 0.0
             };
-            open_stock_positions.insert(
-                txn.stock.clone().unwrap().clone(),
-                (new_avg_price, curr_position.1 + qty),
-            );
+            open_stock_positions.insert(txn.stock.clone(), (new_avg_price, curr_position.1 + qty));
+            // Buy covering a short
+            if let Some(profit) = txn.realized_pnl {
+                combined_profits.push(to_decimal(profit));
+                stock_last_pnl.insert(txn.stock.clone(), profit);
+            }
         } else if qty < 0.0 {
             // Sell
-            if let Some(curr_position) = open_stock_positions.get(&txn.stock.clone().unwrap()) {
-                let profit = -qty * (price - curr_position.0);
-                combined_profits.push(profit);
-                stock_last_pnl.insert(txn.stock.clone().unwrap(), profit);
-
-                open_stock_positions.insert(
-                    txn.stock.clone().unwrap(),
-                    (curr_position.0, curr_position.1 + qty),
-                );
+            if let Some(curr_position) = open_stock_positions.get(&txn.stock) {
+                // PnL recorded at execution time is authoritative, pairing is only the fallback
+                // for transactions booked before it was recorded
+                let profit = txn.realized_pnl.unwrap_or(-qty * (price - curr_position.0));
+                combined_profits.push(to_decimal(profit));
+                stock_last_pnl.insert(txn.stock.clone(), profit);
+
+                open_stock_positions
+                    .insert(txn.stock.clone(), (curr_position.0, curr_position.1 + qty));
             }
         }
     }
@@ -309,15 +368,11 @@ pub fn compute_portfolio_metrics(
     let mut option_last_pnl = HashMap::<String, f64>::new();
 
     for txn in option_transactions {
-        let price = txn.price.unwrap_or(0.0);
-        let qty = txn.quantity.unwrap_or(0.0);
+        let price = txn.price;
+        let qty = txn.quantity;
         let option_key = format!(
             "{}_{}_{}_{}_{}",
-            txn.stock.clone().unwrap(),
-            txn.expiry.clone().unwrap(),
-            txn.strike.clone().unwrap(),
-            txn.option_type.clone().unwrap().to_string(),
-            txn.multiplier.clone().unwrap()
+            txn.stock, txn.expiry, txn.strike, txn.option_type, txn.multiplier
         );
 
         if qty > 0.0 {
@@ -325,10 +380,10 @@ pub fn compute_portfolio_metrics(
             let fallback_value = (
                 0.0,
                 0.0,
-                txn.expiry.clone().unwrap(),
-                txn.option_type.clone().unwrap().to_string(),
-                txn.strike.clone().unwrap(),
-                txn.multiplier.clone().unwrap(),
+                txn.expiry.clone(),
+                txn.option_type.to_string(),
+                txn.strike,
+                txn.multiplier.clone(),
             );
             let curr_position = open_option_positions
                 .get(&option_key)
@@ -343,23 +398,31 @@ pub fn compute_portfolio_metrics(
                 (
                     new_avg_price,
                     curr_position.1 + qty,
-                    txn.expiry.clone().unwrap(),
-                    txn.option_type.clone().unwrap().to_string(),
-                    txn.strike.unwrap(),
-                    txn.multiplier.clone().unwrap(),
+                    txn.expiry.clone(),
+                    txn.option_type.to_string(),
+                    txn.strike,
+                    txn.multiplier.clone(),
                 ),
             );
+            // Buy covering a short
+            if let Some(profit) = txn.realized_pnl {
+                combined_profits.push(to_decimal(profit));
+                option_last_pnl.insert(option_key.clone(), profit);
+            }
         } else if qty < 0.0 {
             // Sell
             if let Some(curr_position) = open_option_positions.get(&option_key) {
-                let multiplier: f64 = txn
-                    .multiplier
-                    .clone()
-                    .unwrap()
-                    .parse()
-                    .expect("Expected multiplier to be easily convertible to f64");
-                let profit = -qty * (price - curr_position.0) * multiplier;
-                combined_profits.push(profit);
+                let profit = match txn.realized_pnl {
+                    Some(profit) => profit,
+                    None => {
+                        let multiplier: f64 = txn
+                            .multiplier
+                            .parse()
+                            .expect("Expected multiplier to be easily convertible to f64");
+                        -qty * (price - curr_position.0) * multiplier
+                    }
+                };
+                combined_profits.push(to_decimal(profit));
                 option_last_pnl.insert(option_key.clone(), profit);
 
                 open_option_positions.insert(
@@ -423,21 +486,27 @@ pub fn compute_portfolio_metrics(
 
     // Calculate profit metrics
     combined_profits.iter().for_each(|&p| print!("{}", p));
-    let gross_profit: f64 = combined_profits.iter().filter(|&&p| p > 0.0).sum();
-    let gross_loss: f64 = combined_profits
+    let gross_profit: Decimal = combined_profits
         .iter()
-        .filter(|&&p| p < 0.0)
+        .filter(|&&p| p > Decimal::ZERO)
+        .sum();
+    let gross_loss: Decimal = combined_profits
+        .iter()
+        .filter(|&&p| p < Decimal::ZERO)
         .map(|p| p.abs())
         .sum();
-    let profit_factor = if gross_loss != 0.0 {
-        gross_profit / gross_loss
+    let profit_factor = if !gross_loss.is_zero() {
+        (gross_profit / gross_loss).to_f64().unwrap_or(0.0)
     } else if combined_profits.len() == 0 {
         -1.0
     } else {
         f64::INFINITY
     };
 
-    let wins = combined_profits.iter().filter(|&&p| p > 0.0).count();
+    let wins = combined_profits
+        .iter()
+        .filter(|&&p| p > Decimal::ZERO)
+        .count();
     let total = combined_profits.len();
     let win_rate = if total > 0 {
         wins as f64 / total as f64
@@ -446,7 +515,9 @@ pub fn compute_portfolio_metrics(
     };
 
     let avg_trade_return = if total > 0 {
-        combined_profits.iter().sum::<f64>() / total as f64
+        (combined_profits.iter().sum::<Decimal>() / Decimal::from(total))
+            .to_f64()
+            .unwrap_or(0.0)
     } else {
         0.0
     };
@@ -454,23 +525,90 @@ pub fn compute_portfolio_metrics(
     PortfolioMetrics {
         cagr,
         sharpe_ratio,
+        sortino_ratio,
         max_drawdown,
         calmar_ratio,
         profit_factor,
         win_rate,
         avg_trade_return,
         positions: positions_latest_pnl,
+        insufficient_data,
+    }
+}
+
+/// How a held position is priced when no historical bar exists for it at or before a point in time
+/// - lookup order is always: latest bar at or before the time -> fallback below
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PricingFallback {
+    /// Mark at avg_price - the position looks flat, which can hide data gaps
+    MarkToCost,
+    /// Carry the last price the position was transacted at
+    #[default]
+    CarryLast,
+    /// Refuse to value the portfolio and report every position that lacked pricing
+    Error,
+}
+
+impl PricingFallback {
+    /// None only under Error, i.e. the position cannot be priced
+    fn resolve(
+        &self,
+        bar_price: Option<f64>,
+        avg_price: f64,
+        last_price: Option<f64>,
+    ) -> Option<f64> {
+        match (bar_price, self) {
+            (Some(bar_price), _) => Some(bar_price),
+            (None, PricingFallback::MarkToCost) => Some(avg_price),
+            (None, PricingFallback::CarryLast) => Some(last_price.unwrap_or(avg_price)),
+            (None, PricingFallback::Error) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PortfolioValueError {
+    Database(String),
+    /// A query ran past the configured portfolio query timeout
+    Timeout(String),
+    /// Positions (stock symbol / option key) with no price data under PricingFallback::Error
+    MissingPricing(Vec<String>),
+}
+
+impl From<String> for PortfolioValueError {
+    fn from(err: String) -> Self {
+        PortfolioValueError::Database(err)
+    }
+}
+
+impl std::fmt::Display for PortfolioValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortfolioValueError::Database(err) => write!(f, "{}", err),
+            PortfolioValueError::Timeout(err) => write!(f, "{}", err),
+            PortfolioValueError::MissingPricing(positions) => {
+                write!(f, "No price data for positions: {}", positions.join(", "))
+            }
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Strategy {
     pub strategy: String,
+    #[serde(default)]
+    pub pricing_fallback: PricingFallback,
+    /// Annual risk free rate Sharpe is measured against - 0 when not given
+    #[serde(default)]
+    pub risk_free_rate: f64,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioValueStrategy {
     pub strategy: String,
     pub status: models::Status,
+    /// Currency the portfolio values, prices and pnl are in
+    pub currency: String,
+    #[serde(with = "crate::timestamps::series")]
     pub portfolio: Vec<(chrono::DateTime<chrono::Utc>, f64)>,
     pub metrics: PortfolioMetrics,
 }
@@ -634,10 +772,52 @@ pub struct PortfolioValueStrategy {
 //     }))
 // }
 
+/// Postgres' SQLSTATE for a statement cancelled by statement_timeout
+const QUERY_CANCELED: &str = "57014";
+
+/// Transaction whose statements Postgres cancels after timeout, so a pathological query over the
+/// historical tables is stopped on the server rather than left holding the connection after the
+/// request gives up on it
+pub async fn begin_timed(
+    db: &sqlx::PgPool,
+    timeout: std::time::Duration,
+) -> Result<sqlx::Transaction<'static, sqlx::Postgres>, PortfolioValueError> {
+    let mut tx = db.begin().await.map_err(|err| {
+        PortfolioValueError::Database(format!("Failed to begin portfolio transaction: {}", err))
+    })?;
+    // SET can't take bind parameters - the value is a number of milliseconds, not user input
+    sqlx::query(&format!(
+        "SET LOCAL statement_timeout = {}",
+        timeout.as_millis().max(1)
+    ))
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| {
+        PortfolioValueError::Database(format!("Failed to set portfolio query timeout: {}", err))
+    })?;
+    Ok(tx)
+}
+
+/// Maps an error from a query run under begin_timed, a cancelled statement being a Timeout
+pub fn query_error(
+    what: &str,
+    timeout: std::time::Duration,
+) -> impl FnOnce(sqlx::Error) -> PortfolioValueError + '_ {
+    move |err| match &err {
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some(QUERY_CANCELED) => {
+            PortfolioValueError::Timeout(format!(
+                "Query for {} took longer than {:?}",
+                what, timeout
+            ))
+        }
+        _ => PortfolioValueError::Database(format!("Failed to find {} in Database: {}", what, err)),
+    }
+}
+
 pub async fn compute_portfolio_value_for_strategy(
     state: crate::AppState,
     strategy: Strategy,
-) -> Result<Json<PortfolioValueStrategy>, String> {
+) -> Result<Json<PortfolioValueStrategy>, PortfolioValueError> {
     // Get strategy information
     let sql_strategy = format!(
         "SELECT * FROM trading.strategy WHERE strategy = '{}'",
@@ -658,70 +838,49 @@ pub async fn compute_portfolio_value_for_strategy(
 
     // Get historical stock data
     let sql_historical_stock_data = format!(
-        "SELECT *, time AT TIME ZONE 'UTC' AT TIME ZONE 'US/Eastern' AS time_est FROM market_data.historical_data WHERE stock IN (SELECT DISTINCT stock FROM trading.stock_transactions WHERE strategy = '{}') ORDER BY time ASC",
+        "SELECT *, time AT TIME ZONE 'UTC' AT TIME ZONE 'US/Eastern' AS time_est FROM market_data.historical_data WHERE stock IN (SELECT stock FROM trading.stock_transactions WHERE strategy = '{0}' UNION SELECT stock FROM trading.option_transactions WHERE strategy = '{0}') ORDER BY time ASC",
         strategy.strategy
     );
 
     // Get historical options data
     let sql_historical_options_data = format!(
-        "SELECT *, time AT TIME ZONE 'UTC' AT TIME ZONE 'US/Eastern' AS time_est FROM phantom_trading.historical_options_data WHERE stock IN (SELECT DISTINCT stock FROM trading.option_transactions WHERE strategy = '{}') ORDER BY time ASC",
+        "SELECT *, time AT TIME ZONE 'UTC' AT TIME ZONE 'US/Eastern' AS time_est FROM market_data.historical_options_data WHERE stock IN (SELECT DISTINCT stock FROM trading.option_transactions WHERE strategy = '{}') ORDER BY time ASC",
         strategy.strategy
     );
 
     // Execute queries
-    let query_strategy = sqlx::query_as::<_, crate::models::Strategy>(&sql_strategy);
-    let strategy_info = query_strategy
-        .fetch_one(&state.db)
+    let timeout = state.portfolio_query_timeout;
+    let mut tx = begin_timed(&state.db, timeout).await?;
+    let strategy_info = sqlx::query_as::<_, crate::models::Strategy>(&sql_strategy)
+        .fetch_one(&mut *tx)
         .await
-        .map_err(|err| format!("Failed to find strategy in Database: {}", err))?;
+        .map_err(query_error("strategy", timeout))?;
 
-    let query_stock_transactions =
-        sqlx::query_as::<_, crate::models::StockTransactions>(&sql_stock_transactions);
-    let stock_transactions = query_stock_transactions
-        .fetch_all(&state.db)
-        .await
-        .map_err(|err| {
-            format!(
-                "Failed to find stock transactions for strategy in Database: {}",
-                err
-            )
-        })?;
+    let stock_transactions =
+        sqlx::query_as::<_, crate::models::StockTransactions>(&sql_stock_transactions)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(query_error("stock transactions for strategy", timeout))?;
 
-    let query_option_transactions =
-        sqlx::query_as::<_, crate::models::OptionTransactions>(&sql_option_transactions);
-    let option_transactions = query_option_transactions
-        .fetch_all(&state.db)
-        .await
-        .map_err(|err| {
-            format!(
-                "Failed to find option transactions for strategy in Database: {}",
-                err
-            )
-        })?;
+    let option_transactions =
+        sqlx::query_as::<_, crate::models::OptionTransactions>(&sql_option_transactions)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(query_error("option transactions for strategy", timeout))?;
 
-    let query_historical_stock_data =
-        sqlx::query_as::<_, crate::models::HistoricalData>(&sql_historical_stock_data);
-    let historical_stock_data = query_historical_stock_data
-        .fetch_all(&state.db)
-        .await
-        .map_err(|err| {
-            format!(
-                "Failed to find historical stock data for strategy in Database: {}",
-                err
-            )
-        })?;
+    let historical_stock_data =
+        sqlx::query_as::<_, crate::models::HistoricalData>(&sql_historical_stock_data)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(query_error("historical stock data for strategy", timeout))?;
 
-    let query_historical_options_data =
-        sqlx::query_as::<_, crate::models::HistoricalOptionsData>(&sql_historical_options_data);
-    let historical_options_data = query_historical_options_data
-        .fetch_all(&state.db)
-        .await
-        .map_err(|err| {
-            format!(
-                "Failed to find historical options data for strategy in Database: {}",
-                err
-            )
-        })?;
+    let historical_options_data =
+        sqlx::query_as::<_, crate::models::HistoricalOptionsData>(&sql_historical_options_data)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(query_error("historical options data for strategy", timeout))?;
+    // Nothing was written - the transaction only scopes the timeout, so it is simply rolled back
+    drop(tx);
 
     // Create a combined timeline of all transactions (both stocks and options)
     let mut all_transactions: Vec<(
@@ -729,7 +888,7 @@ pub async fn compute_portfolio_value_for_strategy(
         String,
         f64,
         f64,
-        f64,
+        Decimal,
         bool,
         Option<(String, f64, String, String)>,
     )> = Vec::new();
@@ -737,11 +896,11 @@ pub async fn compute_portfolio_value_for_strategy(
     // Add stock transactions to the timeline
     for txn in &stock_transactions {
         all_transactions.push((
-            txn.time.clone().unwrap(),
-            txn.stock.clone().unwrap(),
-            txn.price.clone().unwrap_or(0.0),
-            txn.quantity.clone().unwrap_or(0.0),
-            txn.fees.clone().unwrap_or(dec!(0.0)).to_f64().unwrap(),
+            txn.time,
+            txn.stock.clone(),
+            txn.price,
+            txn.quantity,
+            txn.fees,
             true, // is_stock
             None, // no option details
         ));
@@ -750,17 +909,17 @@ pub async fn compute_portfolio_value_for_strategy(
     // Add option transactions to the timeline
     for txn in &option_transactions {
         all_transactions.push((
-            txn.time.clone().unwrap(),
-            txn.stock.clone().unwrap(),
-            txn.price.clone().unwrap_or(0.0),
-            txn.quantity.clone().unwrap_or(0.0),
-            txn.fees.clone().unwrap_or(dec!(0.0)).to_f64().unwrap(),
+            txn.time,
+            txn.stock.clone(),
+            txn.price,
+            txn.quantity,
+            txn.fees,
             false, // is_option
             Some((
-                txn.expiry.clone().unwrap(),
-                txn.strike.unwrap(),
-                txn.multiplier.clone().unwrap(),
-                txn.option_type.as_ref().unwrap().to_string(),
+                txn.expiry.clone(),
+                txn.strike,
+                txn.multiplier.clone(),
+                txn.option_type.to_string(),
             )),
         ));
     }
@@ -772,19 +931,24 @@ pub async fn compute_portfolio_value_for_strategy(
     let mut portfolio_value: Vec<(chrono::DateTime<chrono::Utc>, f64)> = Vec::new();
 
     // Initialize portfolio state
-    let initial_capital = strategy_info.initial_capital.unwrap_or(0.0);
+    let initial_capital = to_decimal(strategy_info.initial_capital.unwrap_or(0.0));
     let mut capital = initial_capital;
     let mut stock_positions: HashMap<String, (f64, f64)> = HashMap::new(); // (avg_price, quantity)
     let mut option_positions: HashMap<String, (f64, f64, f64)> = HashMap::new(); // (avg_price, quantity, multiplier)
+    // symbol / option key -> last transacted price, for PricingFallback::CarryLast
+    let mut last_prices: HashMap<String, f64> = HashMap::new();
+    let mut unpriced_positions: std::collections::BTreeSet<String> =
+        std::collections::BTreeSet::new();
 
     for (time, symbol, price, quantity, fees, is_stock, option_details) in all_transactions {
         // Update positions and capital
         if is_stock {
+            last_prices.insert(symbol.clone(), price);
             // Process stock transaction
             if quantity > 0.0 {
                 // Buy stock
-                capital -= quantity * price + fees;
-                capital = capital.max(0.0);
+                capital -= to_decimal(quantity) * to_decimal(price) + fees;
+                capital = capital.max(Decimal::ZERO);
 
                 // Update position
                 let curr_position = stock_positions.get(&symbol).unwrap_or(&(0.0, 0.0));
@@ -797,7 +961,7 @@ pub async fn compute_portfolio_value_for_strategy(
                 stock_positions.insert(symbol.clone(), (new_avg_price, curr_position.1 + quantity));
             } else if quantity < 0.0 {
                 // Sell stock
-                capital += -quantity * price - fees;
+                capital += -to_decimal(quantity) * to_decimal(price) - fees;
 
                 // Update position
                 if let Some(curr_position) = stock_positions.get(&symbol) {
@@ -817,11 +981,13 @@ pub async fn compute_portfolio_value_for_strategy(
                 let multiplier = multiplier_str
                     .parse()
                     .expect("Expected multiplier to be parsable");
+                last_prices.insert(option_key.clone(), price);
 
                 if quantity > 0.0 {
                     // Buy option
-                    capital -= quantity * price * multiplier + fees;
-                    capital = capital.max(0.0);
+                    capital -=
+                        to_decimal(quantity) * to_decimal(price) * to_decimal(multiplier) + fees;
+                    capital = capital.max(Decimal::ZERO);
 
                     // Update position
                     let fallback_value = (0.0, 0.0, multiplier);
@@ -838,45 +1004,127 @@ pub async fn compute_portfolio_value_for_strategy(
                         (new_avg_price, curr_position.1 + quantity, multiplier),
                     );
                 } else if quantity < 0.0 {
-                    // Sell option
-                    capital += -quantity * price * multiplier - fees;
+                    // Sell option - the premium is cash, the short it opens is marked as a
+                    // liability below
+                    capital +=
+                        -to_decimal(quantity) * to_decimal(price) * to_decimal(multiplier) - fees;
 
                     // Update position
-                    if let Some(curr_position) = option_positions.get(&option_key) {
-                        option_positions.insert(
-                            option_key.clone(),
-                            (curr_position.0, curr_position.1 + quantity, curr_position.2),
-                        );
-                    }
+                    let fallback_value = (0.0, 0.0, multiplier);
+                    let curr_position =
+                        option_positions.get(&option_key).unwrap_or(&fallback_value);
+                    let new_avg_price = if curr_position.1 + quantity < 0.0 {
+                        let short_before = (-curr_position.1).max(0.0);
+                        ((curr_position.0 * short_before) - (price * quantity))
+                            / (short_before - quantity)
+                    } else {
+                        curr_position.0
+                    };
+                    option_positions.insert(
+                        option_key.clone(),
+                        (new_avg_price, curr_position.1 + quantity, curr_position.2),
+                    );
                 }
             }
         }
 
-        // Calculate current portfolio value
-        let mut stock_value = 0.0;
-        for (symbol, (avg_price, quantity)) in &stock_positions {
-            if *quantity > 0.0 {
-                // Use latest price or average price if no data available
-                let latest_price = historical_stock_data
-                    .iter()
-                    .filter(|data| &data.stock == symbol && data.time <= time)
-                    .last()
-                    .map(|data| {
-                        (data.open.unwrap_or(0.0)
-                            + data.high.unwrap_or(0.0)
-                            + data.low.unwrap_or(0.0)
-                            + data.close.unwrap_or(0.0))
-                            / 4.0
-                    })
-                    .unwrap_or(*avg_price);
+        // Settle options that expired before this transaction against the underlying's close on
+        // the expiry date - the same settlement the trading app snapshots equity with
+        let expired: Vec<String> = option_positions
+            .keys()
+            .filter(|option_key| {
+                option_key
+                    .split('_')
+                    .nth(1)
+                    .and_then(|expiry| chrono::NaiveDate::parse_from_str(expiry, "%Y%m%d").ok())
+                    .is_some_and(|expiry| expiry < time.date_naive())
+            })
+            .cloned()
+            .collect();
+        for option_key in expired {
+            let (_, quantity, multiplier) = option_positions.remove(&option_key).unwrap();
+            let parts: Vec<&str> = option_key.split('_').collect();
+            if quantity == 0.0 || parts.len() < 5 {
+                continue;
+            }
+            let symbol = parts[0];
+            let expiry = chrono::NaiveDate::parse_from_str(parts[1], "%Y%m%d").unwrap();
+            let strike = parts[2].parse::<f64>().unwrap_or(0.0);
+            let right = if parts[3] == "C" {
+                OptionRight::Call
+            } else {
+                OptionRight::Put
+            };
+            let settlement_cutoff = shared::options::settlement_cutoff(expiry);
+            let Some(underlying_price) = historical_stock_data
+                .iter()
+                .rev()
+                .find(|data| data.stock == symbol && data.time < settlement_cutoff)
+                .and_then(|data| data.close)
+            else {
+                tracing::warn!(
+                    "No bar of {} at expiry to settle {} against, dropping the position",
+                    symbol,
+                    option_key
+                );
+                continue;
+            };
+            let OptionSettlement {
+                stock_quantity: shares,
+                cash,
+            } = shared::options::settle_option(
+                right,
+                strike,
+                multiplier,
+                quantity,
+                underlying_price,
+            );
+            if shares == 0.0 {
+                continue;
+            }
+            capital += to_decimal(cash);
+            let curr_position = stock_positions.get(symbol).unwrap_or(&(0.0, 0.0));
+            let new_quantity = curr_position.1 + shares;
+            let new_avg_price = if curr_position.1 * new_quantity > 0.0 {
+                ((curr_position.0 * curr_position.1) + (strike * shares)) / new_quantity
+            } else {
+                strike
+            };
+            stock_positions.insert(symbol.to_string(), (new_avg_price, new_quantity));
+            last_prices.insert(symbol.to_string(), underlying_price);
+        }
 
-                stock_value += quantity * latest_price;
+        // Calculate current portfolio value - short positions count against it at their mark
+        let mut stock_value = Decimal::ZERO;
+        for (symbol, (avg_price, quantity)) in &stock_positions {
+            if *quantity != 0.0 {
+                // Mark at the last close as the trading app does (see shared::marks), or fall
+                // back according to strategy.pricing_fallback
+                let bar_price = shared::marks::close_as_of(
+                    historical_stock_data
+                        .iter()
+                        .filter(|data| &data.stock == symbol)
+                        .filter_map(|data| Some((data.time, data.close?))),
+                    time,
+                );
+                let fallback_price = last_prices.get(symbol).copied();
+                match strategy
+                    .pricing_fallback
+                    .resolve(bar_price, *avg_price, fallback_price)
+                {
+                    Some(latest_price) => {
+                        stock_value += to_decimal(*quantity) * to_decimal(latest_price)
+                    }
+                    None => {
+                        unpriced_positions.insert(symbol.clone());
+                    }
+                }
             }
         }
 
-        let mut option_value = 0.0;
+        let mut option_value = Decimal::ZERO;
         for (option_key, (avg_price, quantity, multiplier)) in &option_positions {
-            if *quantity > 0.0 {
+            if *quantity != 0.0 {
                 let parts: Vec<&str> = option_key.split('_').collect();
                 if parts.len() >= 5 {
                     let symbol = parts[0];
@@ -884,8 +1132,8 @@ pub async fn compute_portfolio_value_for_strategy(
                     let strike = parts[2].parse::<f64>().unwrap_or(0.0);
                     let option_type = parts[3];
 
-                    // Find latest option price
-                    let latest_price = historical_options_data
+                    // Find latest option price or fall back according to strategy.pricing_fallback
+                    let bar_price = historical_options_data
                         .iter()
                         .filter(|data| {
                             &data.stock == symbol
@@ -895,36 +1143,77 @@ pub async fn compute_portfolio_value_for_strategy(
                                 && data.time <= time
                         })
                         .last()
-                        .map(|data| data.close.unwrap_or(*avg_price))
-                        .unwrap_or(*avg_price);
-
-                    option_value += quantity * latest_price * multiplier;
+                        .and_then(|data| data.close);
+                    let fallback_price = last_prices.get(option_key).copied();
+                    match strategy
+                        .pricing_fallback
+                        .resolve(bar_price, *avg_price, fallback_price)
+                    {
+                        Some(latest_price) => {
+                            option_value += to_decimal(*quantity)
+                                * to_decimal(latest_price)
+                                * to_decimal(*multiplier)
+                        }
+                        None => {
+                            unpriced_positions.insert(option_key.clone());
+                        }
+                    }
                 }
             }
         }
 
         // Add entry to portfolio value timeline
-        let total_value = capital + stock_value + option_value;
-        portfolio_value.push((time, total_value));
+        let total_value =
+            (capital + stock_value + option_value).round_dp(state.money_decimal_places);
+        portfolio_value.push((time, total_value.to_f64().unwrap_or(0.0)));
+    }
+
+    if !unpriced_positions.is_empty() {
+        return Err(PortfolioValueError::MissingPricing(
+            unpriced_positions.into_iter().collect(),
+        ));
     }
 
     // If there are no transactions, just return the initial capital
-    if portfolio_value.is_empty() && initial_capital > 0.0 {
-        portfolio_value.push((chrono::offset::Utc::now(), initial_capital));
+    if portfolio_value.is_empty() && initial_capital > Decimal::ZERO {
+        portfolio_value.push((
+            chrono::offset::Utc::now(),
+            initial_capital.to_f64().unwrap_or(0.0),
+        ));
     }
 
     // Calculate portfolio metrics
-    let metrics =
-        compute_portfolio_metrics(&portfolio_value, &stock_transactions, &option_transactions);
+    let metrics = compute_portfolio_metrics(
+        &portfolio_value,
+        &stock_transactions,
+        &option_transactions,
+        strategy.risk_free_rate,
+        state.min_metrics_observations,
+    );
+
+    let status = status_or_inactive(strategy_info.status, &strategy.strategy);
 
     Ok(Json(PortfolioValueStrategy {
         strategy: strategy.strategy,
-        status: strategy_info.status.unwrap(),
+        status,
+        currency: state.currency.base_currency.clone(),
         portfolio: portfolio_value,
         metrics,
     }))
 }
 
+/// A null status (e.g. from a manual edit) shouldn't fail the whole portfolio view - it is
+/// reported as inactive
+fn status_or_inactive(status: Option<models::Status>, strategy: &str) -> models::Status {
+    status.unwrap_or_else(|| {
+        tracing::warn!(
+            "Strategy {} has no status, reporting it as inactive",
+            strategy
+        );
+        models::Status::Inactive
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioEntryWithStrategy {
     pub strategy: String,
@@ -937,6 +1226,9 @@ pub struct PortfolioEntryReturn {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioValue {
     pub strategies: Vec<PortfolioValueStrategy>,
+    /// Currency the overall portfolio values are in
+    pub currency: String,
+    #[serde(with = "crate::timestamps::series")]
     pub portfolio: Vec<(chrono::DateTime<chrono::Utc>, f64)>,
 }
 
@@ -1048,9 +1340,52 @@ pub struct PortfolioValue {
 //     }))
 // }
 
+/// Awaits tasks with at most limit of them running at once, returning their outputs in order
+/// - every strategy's portfolio value runs several large queries, so computing all of them at
+///   once can take every connection in the pool
+pub async fn run_bounded<F: Future>(
+    tasks: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> Vec<F::Output> {
+    stream::iter(tasks).buffered(limit.max(1)).collect().await
+}
+
+/// A strategy's value as it goes into the overall value
+/// - a timeout fails the whole overall value, so it is reported (and not cached) rather than
+///   silently dropping the strategy's curve
+/// - any other error leaves the strategy out with an empty curve
+fn strategy_value_or_placeholder(
+    result: Result<Json<PortfolioValueStrategy>, PortfolioValueError>,
+    strategy: String,
+    currency: String,
+) -> Result<Json<PortfolioValueStrategy>, PortfolioValueError> {
+    match result {
+        Ok(portfolio_value_for_strat) => Ok(portfolio_value_for_strat),
+        Err(err @ PortfolioValueError::Timeout(_)) => Err(err),
+        Err(_) => Ok(Json(PortfolioValueStrategy {
+            strategy,
+            status: models::Status::Inactive,
+            currency,
+            portfolio: vec![],
+            metrics: PortfolioMetrics {
+                cagr: 0.0,
+                sharpe_ratio: 0.0,
+                sortino_ratio: 0.0,
+                max_drawdown: 0.0,
+                calmar_ratio: 0.0,
+                profit_factor: 0.0,
+                win_rate: 0.0,
+                avg_trade_return: 0.0,
+                positions: HashMap::new(),
+                insufficient_data: true,
+            },
+        })),
+    }
+}
+
 pub async fn compute_overall_portfolio_value(
     state: crate::AppState,
-) -> Result<Json<PortfolioValue>, String> {
+) -> Result<Json<PortfolioValue>, PortfolioValueError> {
     let sql_strategy = "SELECT DISTINCT strategy FROM trading.strategy";
     let query_strategy = sqlx::query_as::<_, crate::models::StrategyPrimaryKeys>(&sql_strategy);
     let strategies = query_strategy
@@ -1058,41 +1393,30 @@ pub async fn compute_overall_portfolio_value(
         .await
         .map_err(|err| format!("Failed to find strategies in Database: {}", err))?;
 
-    let tasks = strategies.iter().map(|strat| {
+    let tasks = strategies.into_iter().map(|strat| {
         let state = state.clone();
-        let strategy_name = strat.strategy.clone();
+        let strategy_name = strat.strategy;
+        let currency = state.currency.base_currency.clone();
 
         async move {
-            match compute_portfolio_value_for_strategy(
+            let result = compute_portfolio_value_for_strategy(
                 state,
                 Strategy {
                     strategy: strategy_name.clone(),
+                    pricing_fallback: PricingFallback::default(),
+                    risk_free_rate: 0.0,
                 },
             )
-            .await
-            {
-                Ok(portfolio_value_for_strat) => portfolio_value_for_strat,
-                Err(_) => Json(PortfolioValueStrategy {
-                    strategy: strategy_name.clone(),
-                    status: models::Status::Inactive,
-                    portfolio: vec![],
-                    metrics: PortfolioMetrics {
-                        cagr: 0.0,
-                        sharpe_ratio: 0.0,
-                        max_drawdown: 0.0,
-                        calmar_ratio: 0.0,
-                        profit_factor: 0.0,
-                        win_rate: 0.0,
-                        avg_trade_return: 0.0,
-                        positions: HashMap::new(),
-                    },
-                }),
-            }
+            .await;
+            strategy_value_or_placeholder(result, strategy_name, currency)
         }
     });
 
     let portfolio_value_over_time_unmapped: Vec<Json<PortfolioValueStrategy>> =
-        join_all(tasks).await;
+        run_bounded(tasks, state.portfolio_concurrency)
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()?;
 
     let mut portfolio_value_over_time: Vec<PortfolioEntryWithStrategy> =
         portfolio_value_over_time_unmapped
@@ -1115,31 +1439,30 @@ pub async fn compute_overall_portfolio_value(
     portfolio_value_over_time.sort_by(|a, b| a.value.0.cmp(&b.value.0));
 
     let mut portfolio_value_overall = Vec::<PortfolioEntryReturn>::new();
-    let mut strategies = HashMap::<String, f64>::new();
+    let mut strategies = HashMap::<String, Decimal>::new();
+    let mut overall_value = Decimal::ZERO;
 
     for portfolio_value_at_t in portfolio_value_over_time {
-        let change = portfolio_value_at_t.value.1
+        let strategy_value = to_decimal(portfolio_value_at_t.value.1);
+        let change = strategy_value
             - strategies
                 .get(&portfolio_value_at_t.strategy)
-                .unwrap_or(&0.0);
-
-        let last_value = if portfolio_value_overall.is_empty() {
-            0.0
-        } else {
-            portfolio_value_overall.last().unwrap().value.1
-        };
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+        overall_value += change;
 
         portfolio_value_overall.push(PortfolioEntryReturn {
-            value: (portfolio_value_at_t.value.0, last_value + change),
+            value: (
+                portfolio_value_at_t.value.0,
+                overall_value.to_f64().unwrap_or(0.0),
+            ),
         });
 
-        strategies.insert(
-            portfolio_value_at_t.strategy.clone(),
-            portfolio_value_at_t.value.1,
-        );
+        strategies.insert(portfolio_value_at_t.strategy.clone(), strategy_value);
     }
 
     Ok(Json(PortfolioValue {
+        currency: state.currency.base_currency.clone(),
         portfolio: portfolio_value_overall
             .iter()
             .map(|val| val.value)
@@ -1149,9 +1472,419 @@ pub async fn compute_overall_portfolio_value(
             .map(|json_data| PortfolioValueStrategy {
                 strategy: json_data.strategy.clone(),
                 status: json_data.status.clone(),
+                currency: json_data.currency.clone(),
                 portfolio: json_data.portfolio.clone(),
                 metrics: json_data.metrics.clone(),
             })
             .collect(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn last_value(
+        result: Result<Json<PortfolioValueStrategy>, PortfolioValueError>,
+    ) -> Option<f64> {
+        result
+            .expect("Expected a portfolio value")
+            .0
+            .portfolio
+            .last()
+            .map(|(_, value)| *value)
+    }
+
+    /// values as a portfolio sampled every 5 minutes
+    fn five_min_series(values: &[f64]) -> Vec<(DateTime<Utc>, f64)> {
+        let start = DateTime::parse_from_rfc3339("2025-07-01T13:30:00Z")
+            .expect("Expected a valid start")
+            .with_timezone(&Utc);
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (start + chrono::Duration::minutes(5 * i as i64), *value))
+            .collect()
+    }
+
+    fn metrics_of(
+        values: &[f64],
+        risk_free_rate: f64,
+        min_observations: usize,
+    ) -> PortfolioMetrics {
+        compute_portfolio_metrics(
+            &five_min_series(values),
+            &vec![],
+            &vec![],
+            risk_free_rate,
+            min_observations,
+        )
+    }
+
+    /// Gains with a couple of pullbacks
+    const CHOPPY: [f64; 6] = [100.0, 101.0, 100.5, 101.5, 101.0, 102.0];
+
+    #[tokio::test]
+    async fn statement_past_the_timeout_is_cancelled_as_a_timeout() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let timeout = std::time::Duration::from_millis(50);
+        let mut tx = begin_timed(&db, timeout).await.expect("Expected to begin");
+        let err = sqlx::query("SELECT pg_sleep(5)")
+            .execute(&mut *tx)
+            .await
+            .map_err(query_error("sleep", timeout))
+            .expect_err("Expected the sleep to be cancelled");
+        assert!(matches!(err, PortfolioValueError::Timeout(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn timeout_applies_only_inside_its_transaction() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let tx = begin_timed(&db, std::time::Duration::from_millis(50))
+            .await
+            .expect("Expected to begin");
+        drop(tx);
+        let timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+            .fetch_one(&db)
+            .await
+            .expect("Expected to read statement_timeout");
+        assert_eq!(timeout, "0");
+    }
+
+    #[tokio::test]
+    async fn expired_short_put_settles_against_the_close_on_its_expiry_date() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let setup = r#"
+            DELETE FROM trading.strategy WHERE strategy = 'settle_backend_strat';
+            DELETE FROM market_data.historical_data WHERE stock = 'SETTLEBK';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('settle_backend_strat', 10000, 10000, 'inactive');
+            -- sold a 100 strike put for 2.00, expiring 2025-07-18
+            INSERT INTO trading.option_transactions
+                (strategy, execution_id, order_perm_id, time, stock, primary_exchange, price, fees,
+                 quantity, expiry, strike, multiplier, option_type)
+            VALUES ('settle_backend_strat', 'settle_backend_1', 1, '2025-07-10 14:00:00+00',
+                'SETTLEBK', 'NASDAQ', 2.0, 0, -1, '20250718', 100.0, '100', 'P');
+            -- closed at 95 on expiry, then rallied past the strike
+            INSERT INTO market_data.historical_data
+                (stock, primary_exchange, time, open, high, low, close, volume)
+            VALUES
+                ('SETTLEBK', 'NASDAQ', '2025-07-18 19:55:00+00', 95, 95, 95, 95, 1),
+                ('SETTLEBK', 'NASDAQ', '2025-07-21 19:55:00+00', 120, 120, 120, 120, 1);
+            -- a transaction after expiry, for the settlement to show up in
+            INSERT INTO trading.stock_transactions
+                (strategy, execution_id, order_perm_id, time, stock, primary_exchange, price, fees,
+                 quantity)
+            VALUES ('settle_backend_strat', 'settle_backend_2', 2, '2025-07-22 14:00:00+00',
+                'SETTLEBK', 'NASDAQ', 120.0, 0, 1);
+        "#;
+        sqlx::raw_sql(setup)
+            .execute(&db)
+            .await
+            .expect("Expected to set up the expired put");
+
+        let result = compute_portfolio_value_for_strategy(
+            test_support::app_state(db.clone()),
+            Strategy {
+                strategy: "settle_backend_strat".to_string(),
+                pricing_fallback: PricingFallback::default(),
+                risk_free_rate: 0.0,
+            },
+        )
+        .await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy = 'settle_backend_strat';
+            DELETE FROM market_data.historical_data WHERE stock = 'SETTLEBK';",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up the expired put");
+
+        let portfolio = result.expect("Expected a portfolio value").0.portfolio;
+        // assigned 100 shares at the 100 strike off the 95 close: 10000 + 200 premium - 10000,
+        // less the 120 share bought, plus 101 shares marked at 120
+        assert_eq!(portfolio.last().map(|(_, value)| *value), Some(12200.0));
+    }
+
+    #[tokio::test]
+    async fn stock_is_marked_at_the_last_close_as_the_trading_app_does() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let setup = r#"
+            DELETE FROM trading.strategy WHERE strategy = 'last_close_backend_strat';
+            DELETE FROM market_data.historical_data WHERE stock = 'LASTCLSBK';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('last_close_backend_strat', 10000, 10000, 'inactive');
+            -- the bar's OHLC average (100) differs from its close (104)
+            INSERT INTO market_data.historical_data
+                (stock, primary_exchange, time, open, high, low, close, volume)
+            VALUES ('LASTCLSBK', 'NASDAQ', '2025-07-01 13:55:00+00', 96, 104, 96, 104, 1);
+            INSERT INTO trading.stock_transactions
+                (strategy, execution_id, order_perm_id, time, stock, primary_exchange, price, fees,
+                 quantity)
+            VALUES ('last_close_backend_strat', 'last_close_backend_1', 1,
+                '2025-07-01 14:00:00+00', 'LASTCLSBK', 'NASDAQ', 100.0, 0, 10);
+        "#;
+        sqlx::raw_sql(setup)
+            .execute(&db)
+            .await
+            .expect("Expected to set up the position");
+
+        let result = compute_portfolio_value_for_strategy(
+            test_support::app_state(db.clone()),
+            Strategy {
+                strategy: "last_close_backend_strat".to_string(),
+                pricing_fallback: PricingFallback::default(),
+                risk_free_rate: 0.0,
+            },
+        )
+        .await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy = 'last_close_backend_strat';
+            DELETE FROM market_data.historical_data WHERE stock = 'LASTCLSBK';",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up the position");
+
+        let portfolio = result.expect("Expected a portfolio value").0.portfolio;
+        // 10000 - 1000 paid + 10 shares marked at the 104 close
+        assert_eq!(portfolio.last().map(|(_, value)| *value), Some(10040.0));
+    }
+
+    #[test]
+    fn timeout_fails_the_overall_value_other_errors_leave_the_strategy_out() {
+        let timed_out = strategy_value_or_placeholder(
+            Err(PortfolioValueError::Timeout("slow".to_string())),
+            "slow_strat".to_string(),
+            "USD".to_string(),
+        );
+        assert!(matches!(timed_out, Err(PortfolioValueError::Timeout(_))));
+
+        let failed = strategy_value_or_placeholder(
+            Err(PortfolioValueError::Database("gone".to_string())),
+            "broken_strat".to_string(),
+            "USD".to_string(),
+        )
+        .expect("Expected a placeholder for a failed strategy");
+        assert_eq!(failed.strategy, "broken_strat");
+        assert!(failed.portfolio.is_empty());
+        assert!(failed.metrics.insufficient_data);
+    }
+
+    #[test]
+    fn position_without_a_bar_is_priced_by_the_fallback() {
+        assert_eq!(
+            PricingFallback::Error.resolve(Some(101.0), 100.0, Some(99.0)),
+            Some(101.0)
+        );
+        assert_eq!(
+            PricingFallback::MarkToCost.resolve(None, 100.0, Some(99.0)),
+            Some(100.0)
+        );
+        assert_eq!(
+            PricingFallback::CarryLast.resolve(None, 100.0, Some(99.0)),
+            Some(99.0)
+        );
+        assert_eq!(
+            PricingFallback::CarryLast.resolve(None, 100.0, None),
+            Some(100.0)
+        );
+        assert_eq!(
+            PricingFallback::Error.resolve(None, 100.0, Some(99.0)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unpriced_position_is_reported_under_the_error_fallback() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let setup = r#"
+            DELETE FROM trading.strategy WHERE strategy = 'unpriced_backend_strat';
+            DELETE FROM market_data.historical_data WHERE stock = 'NOBARBK';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('unpriced_backend_strat', 10000, 10000, 'inactive');
+            -- bought twice with no bar to mark the position at
+            INSERT INTO trading.stock_transactions
+                (strategy, execution_id, order_perm_id, time, stock, primary_exchange, price, fees,
+                 quantity)
+            VALUES
+                ('unpriced_backend_strat', 'unpriced_backend_1', 1, '2025-07-01 14:00:00+00',
+                'NOBARBK', 'NASDAQ', 100.0, 0, 10),
+                ('unpriced_backend_strat', 'unpriced_backend_2', 2, '2025-07-01 15:00:00+00',
+                'NOBARBK', 'NASDAQ', 110.0, 0, 10);
+        "#;
+        sqlx::raw_sql(setup)
+            .execute(&db)
+            .await
+            .expect("Expected to set up the position");
+
+        let value_with = |pricing_fallback: PricingFallback| {
+            compute_portfolio_value_for_strategy(
+                test_support::app_state(db.clone()),
+                Strategy {
+                    strategy: "unpriced_backend_strat".to_string(),
+                    pricing_fallback,
+                    risk_free_rate: 0.0,
+                },
+            )
+        };
+        let refused = value_with(PricingFallback::Error).await;
+        let carried = value_with(PricingFallback::CarryLast).await;
+        let at_cost = value_with(PricingFallback::MarkToCost).await;
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'unpriced_backend_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up the position");
+
+        match refused {
+            Err(PortfolioValueError::MissingPricing(positions)) => {
+                assert_eq!(positions, vec!["NOBARBK".to_string()])
+            }
+            other => panic!(
+                "Expected the position to be reported, got {:?}",
+                other.err()
+            ),
+        }
+        // 10000 - 2100 paid + 20 shares at the last 110 fill
+        assert_eq!(last_value(carried), Some(10100.0));
+        // 20 shares at their 105 average cost
+        assert_eq!(last_value(at_cost), Some(10000.0));
+    }
+
+    #[tokio::test]
+    async fn portfolio_value_is_summed_exactly_and_rounded_to_the_money_precision() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        let setup = r#"
+            DELETE FROM trading.strategy WHERE strategy = 'precision_backend_strat';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('precision_backend_strat', 10000, 10000, 'inactive');
+            -- 0.1 + 0.2 paid, which isn't 0.3 in f64
+            INSERT INTO trading.stock_transactions
+                (strategy, execution_id, order_perm_id, time, stock, primary_exchange, price, fees,
+                 quantity)
+            VALUES
+                ('precision_backend_strat', 'precision_backend_1', 1, '2025-07-01 14:00:00+00',
+                'PRECISEBK', 'NASDAQ', 0.1, 0, 1),
+                ('precision_backend_strat', 'precision_backend_2', 2, '2025-07-01 15:00:00+00',
+                'PRECISEBK', 'NASDAQ', 0.2, 0, 1);
+        "#;
+        sqlx::raw_sql(setup)
+            .execute(&db)
+            .await
+            .expect("Expected to set up the position");
+
+        let value_at = |money_decimal_places: u32| {
+            let mut state = test_support::app_state(db.clone());
+            state.money_decimal_places = money_decimal_places;
+            compute_portfolio_value_for_strategy(
+                state,
+                Strategy {
+                    strategy: "precision_backend_strat".to_string(),
+                    pricing_fallback: PricingFallback::CarryLast,
+                    risk_free_rate: 0.0,
+                },
+            )
+        };
+        let exact = value_at(4).await;
+        let whole = value_at(0).await;
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'precision_backend_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up the position");
+
+        // 10000 - 0.3 paid + 2 shares carried at the last 0.2 fill
+        assert_eq!(last_value(exact), Some(10000.1));
+        assert_eq!(last_value(whole), Some(10000.0));
+    }
+
+    #[test]
+    fn risk_free_rate_lowers_sharpe() {
+        let without = metrics_of(&CHOPPY, 0.0, 1);
+        let with = metrics_of(&CHOPPY, 0.04, 1);
+
+        assert!(without.sharpe_ratio > 0.0);
+        assert!(
+            with.sharpe_ratio < without.sharpe_ratio,
+            "{} not below {}",
+            with.sharpe_ratio,
+            without.sharpe_ratio
+        );
+    }
+
+    #[test]
+    fn sortino_only_penalises_losing_periods() {
+        let choppy = metrics_of(&CHOPPY, 0.0, 1);
+        // downside deviation leaves out the gains, so it is below the standard deviation
+        assert!(
+            choppy.sortino_ratio > choppy.sharpe_ratio,
+            "{} not above {}",
+            choppy.sortino_ratio,
+            choppy.sharpe_ratio
+        );
+
+        let only_gains = metrics_of(&[100.0, 101.0, 103.0, 104.0], 0.0, 1);
+        assert!(only_gains.sharpe_ratio > 0.0);
+        assert_eq!(only_gains.sortino_ratio, 0.0);
+    }
+
+    #[test]
+    fn too_few_values_are_flagged_instead_of_measured() {
+        let short = metrics_of(&CHOPPY, 0.0, CHOPPY.len() + 1);
+        assert!(short.insufficient_data);
+        assert_eq!(short.cagr, 0.0);
+        assert_eq!(short.sharpe_ratio, 0.0);
+        assert_eq!(short.max_drawdown, 0.0);
+
+        let enough = metrics_of(&CHOPPY, 0.0, CHOPPY.len());
+        assert!(!enough.insufficient_data);
+        assert!(enough.sharpe_ratio > 0.0);
+        assert!(enough.max_drawdown > 0.0);
+    }
+
+    #[tokio::test]
+    async fn at_most_limit_tasks_run_at_once_and_outputs_keep_their_order() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let tasks = (0..6).map(|i| {
+            let running = running.clone();
+            let most_running = most_running.clone();
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most_running.fetch_max(now_running, Ordering::SeqCst);
+                // later tasks finish first
+                tokio::time::sleep(std::time::Duration::from_millis(30 - 5 * i)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        });
+
+        let outputs = run_bounded(tasks, 2).await;
+
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(most_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn strategy_without_a_status_is_reported_inactive() {
+        assert!(matches!(
+            status_or_inactive(None, "no_status_strat"),
+            models::Status::Inactive
+        ));
+        assert!(matches!(
+            status_or_inactive(Some(models::Status::Active), "active_strat"),
+            models::Status::Active
+        ));
+    }
+}
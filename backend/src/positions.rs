@@ -0,0 +1,124 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::models;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StrategyFilter {
+    pub strategy: String,
+}
+
+/// Current position of a strategy, tagged with its asset type so the frontend can tell stock and
+/// option rows apart without inspecting the fields
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "asset_type", rename_all = "lowercase")]
+pub enum StrategyPosition {
+    Stock(models::CurrentStockPositions),
+    Option(models::CurrentOptionPositions),
+}
+
+/// GET /current_positions?strategy=...
+/// - Returns both stock and option current positions for the strategy in one response
+pub async fn get_current_positions_for_strategy(
+    State(state): State<crate::AppState>,
+    Query(filter): Query<StrategyFilter>,
+) -> Result<(StatusCode, Json<Vec<StrategyPosition>>), (StatusCode, String)> {
+    let stock_positions = sqlx::query_as::<_, models::CurrentStockPositions>(
+        "SELECT * FROM trading.current_stock_positions WHERE strategy = $1",
+    )
+    .bind(&filter.strategy)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!(
+                "Failed to read current stock positions for strategy: {}",
+                err
+            ),
+        )
+    })?;
+
+    let option_positions = sqlx::query_as::<_, models::CurrentOptionPositions>(
+        "SELECT * FROM trading.current_option_positions WHERE strategy = $1",
+    )
+    .bind(&filter.strategy)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!(
+                "Failed to read current option positions for strategy: {}",
+                err
+            ),
+        )
+    })?;
+
+    let positions = stock_positions
+        .into_iter()
+        .map(StrategyPosition::Stock)
+        .chain(option_positions.into_iter().map(StrategyPosition::Option))
+        .collect();
+
+    Ok((StatusCode::OK, Json(positions)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn stock_and_option_positions_of_the_strategy_are_tagged_by_asset_type() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy IN ('positions_strat', 'other_strat');
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status) VALUES
+                ('positions_strat', 1000, 1000, 'active'),
+                ('other_strat', 1000, 1000, 'active');
+            INSERT INTO trading.current_stock_positions
+                (strategy, stock, primary_exchange, quantity, avg_price)
+            VALUES
+                ('positions_strat', 'QQQ', 'NASDAQ', 10, 450),
+                ('other_strat', 'QQQ', 'NASDAQ', 5, 450);
+            INSERT INTO trading.current_option_positions
+                (strategy, stock, primary_exchange, quantity, avg_price, expiry, strike,
+                multiplier, option_type)
+            VALUES ('positions_strat', 'QQQ', 'NASDAQ', -2, 3.5, '20250718', 500, '100', 'P');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert positions");
+
+        let result = get_current_positions_for_strategy(
+            State(test_support::app_state(db.clone())),
+            Query(StrategyFilter {
+                strategy: "positions_strat".to_string(),
+            }),
+        )
+        .await;
+
+        sqlx::query(
+            "DELETE FROM trading.strategy WHERE strategy IN ('positions_strat', 'other_strat')",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up strategies");
+        let (status, Json(positions)) = result.expect("Expected current positions");
+        assert_eq!(status, StatusCode::OK);
+        let positions = serde_json::to_value(&positions).expect("Expected positions to serialize");
+        let positions = positions.as_array().expect("Expected a list of positions");
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0]["asset_type"], "stock");
+        assert_eq!(positions[0]["quantity"], 10.0);
+        assert_eq!(positions[1]["asset_type"], "option");
+        assert_eq!(positions[1]["option_type"], "Put");
+        assert_eq!(positions[1]["quantity"], -2.0);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use axum::{Json, extract::State};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::Serialize;
+
+use crate::{
+    AppState,
+    currency::{FxRates, fx_rates_between},
+    portfolio_cache::cached_overall_portfolio_value,
+};
+
+/// Latest computed overall portfolio value against the broker's latest net liquidation
+/// - a discrepancy beyond the tolerance points to missing transactions, stale prices or an
+///   accounting bug rather than normal drift (commissions, marks taken at different times)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioReconciliation {
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub computed_time: DateTime<Utc>,
+    pub computed_value: f64,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub broker_time: DateTime<Utc>,
+    pub broker_net_liquidation: f64,
+    /// computed_value - broker_net_liquidation
+    pub discrepancy: f64,
+    /// discrepancy as a percentage of the broker's net liquidation - None if that is 0
+    pub discrepancy_pct: Option<f64>,
+    pub tolerance_pct: f64,
+    pub exceeds_tolerance: bool,
+}
+
+/// Compares the computed (time, value) against the broker's (time, net liquidation)
+/// - with nothing at the broker any computed value exceeds the tolerance
+pub fn reconcile(
+    computed: (DateTime<Utc>, f64),
+    broker: (DateTime<Utc>, f64),
+    tolerance_pct: f64,
+) -> PortfolioReconciliation {
+    let discrepancy = computed.1 - broker.1;
+    let discrepancy_pct = (broker.1 != 0.0).then(|| discrepancy / broker.1.abs() * 100.0);
+    let exceeds_tolerance = match discrepancy_pct {
+        Some(pct) => pct.abs() > tolerance_pct,
+        None => discrepancy != 0.0,
+    };
+    PortfolioReconciliation {
+        computed_time: computed.0,
+        computed_value: computed.1,
+        broker_time: broker.0,
+        broker_net_liquidation: broker.1,
+        discrepancy,
+        discrepancy_pct,
+        tolerance_pct,
+        exceeds_tolerance,
+    }
+}
+
+/// An account's net liquidation as of its latest account summary, in the account's currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountNetLiquidation {
+    pub time: DateTime<Utc>,
+    /// None if IBKR didn't report it - taken to be the base currency
+    pub currency: Option<String>,
+    pub net_liquidation: f64,
+}
+
+/// Net liquidation across accounts in base_currency, each converted at its currency's rate
+/// (base_currency per unit) as of its summary, with the time of the most recent one
+/// - None if there are no accounts, an error if an account's currency has no rate
+pub fn total_net_liquidation(
+    accounts: &[AccountNetLiquidation],
+    base_currency: &str,
+    rates: &HashMap<String, FxRates>,
+) -> Result<Option<(DateTime<Utc>, f64)>, String> {
+    let mut total = 0.0;
+    for account in accounts {
+        let currency = account.currency.as_deref().unwrap_or(base_currency);
+        total += if currency == base_currency {
+            account.net_liquidation
+        } else {
+            let rate = rates
+                .get(currency)
+                .ok_or_else(|| format!("No FX rate to convert {} to {}", currency, base_currency))?
+                .at(account.time);
+            account.net_liquidation * rate
+        };
+    }
+    Ok(accounts
+        .iter()
+        .map(|account| account.time)
+        .max()
+        .map(|time| (time, total)))
+}
+
+/// Net liquidation across every account as of its latest account summary, converted to
+/// base_currency, with the time of the most recent one - None if no summary has been recorded
+async fn latest_net_liquidation(
+    db: &sqlx::PgPool,
+    base_currency: &str,
+) -> Result<Option<(DateTime<Utc>, f64)>, String> {
+    let accounts = sqlx::query_as::<_, (DateTime<Utc>, Option<String>, f64)>(
+        r#"
+        SELECT DISTINCT ON (account) time, currency, net_liquidation
+        FROM trading.account_summary
+        WHERE net_liquidation IS NOT NULL
+        ORDER BY account, time DESC
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|err| format!("Failed to read account summaries: {}", err))?
+    .into_iter()
+    .map(|(time, currency, net_liquidation)| AccountNetLiquidation {
+        time,
+        currency: currency.map(|currency| currency.to_uppercase()),
+        net_liquidation,
+    })
+    .collect::<Vec<_>>();
+
+    let mut rates = HashMap::new();
+    for account in &accounts {
+        if let Some(currency) = &account.currency
+            && currency != base_currency
+            && !rates.contains_key(currency)
+        {
+            let rate = fx_rates_between(db, currency, base_currency).await?;
+            rates.insert(currency.clone(), rate);
+        }
+    }
+    total_net_liquidation(&accounts, base_currency, &rates)
+}
+
+/// GET /portfolio/reconcile
+/// - both values are compared in the base currency, each account's net liquidation converted from
+///   the currency IBKR reports it in
+pub async fn reconcile_portfolio(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, Json<PortfolioReconciliation>), (StatusCode, String)> {
+    let tolerance_pct = state.reconcile_tolerance_pct;
+    let broker = latest_net_liquidation(&state.db, &state.currency.base_currency)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "No account summary with a net liquidation recorded".to_string(),
+        ))?;
+    let computed = cached_overall_portfolio_value(state)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .portfolio
+        .last()
+        .copied()
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "No computed portfolio value to reconcile".to_string(),
+        ))?;
+
+    let reconciliation = reconcile(computed, broker, tolerance_pct);
+    if reconciliation.exceeds_tolerance {
+        tracing::warn!(
+            "Computed portfolio value {} differs from broker net liquidation {} by {}",
+            reconciliation.computed_value,
+            reconciliation.broker_net_liquidation,
+            reconciliation.discrepancy
+        );
+    }
+    Ok((StatusCode::OK, Json(reconciliation)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, 18, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn discrepancy_beyond_tolerance_is_flagged() {
+        let reconciliation = reconcile((time(20), 10_300.0), (time(19), 10_000.0), 1.0);
+
+        assert_eq!(reconciliation.discrepancy, 300.0);
+        assert_eq!(reconciliation.discrepancy_pct, Some(3.0));
+        assert!(reconciliation.exceeds_tolerance);
+        assert_eq!(reconciliation.computed_time, time(20));
+        assert_eq!(reconciliation.broker_time, time(19));
+
+        assert!(!reconcile((time(20), 10_050.0), (time(19), 10_000.0), 1.0).exceeds_tolerance);
+        // nothing at the broker - any computed value is a discrepancy
+        let empty_account = reconcile((time(20), 5.0), (time(19), 0.0), 1.0);
+        assert_eq!(empty_account.discrepancy_pct, None);
+        assert!(empty_account.exceeds_tolerance);
+    }
+
+    #[test]
+    fn accounts_are_totalled_in_the_base_currency() {
+        let accounts = [
+            AccountNetLiquidation {
+                time: time(19),
+                currency: Some("USD".to_string()),
+                net_liquidation: 1_000.0,
+            },
+            AccountNetLiquidation {
+                time: time(20),
+                currency: Some("EUR".to_string()),
+                net_liquidation: 1_000.0,
+            },
+            AccountNetLiquidation {
+                time: time(18),
+                currency: None,
+                net_liquidation: 500.0,
+            },
+        ];
+        let rates = HashMap::from([(
+            "EUR".to_string(),
+            FxRates::from_closes(vec![(time(12), 1.1), (time(20), 1.2)]).unwrap(),
+        )]);
+
+        assert_eq!(
+            total_net_liquidation(&accounts, "USD", &rates),
+            Ok(Some((time(20), 2_700.0)))
+        );
+        assert_eq!(
+            total_net_liquidation(&accounts, "USD", &HashMap::new()),
+            Err("No FX rate to convert EUR to USD".to_string())
+        );
+        assert_eq!(total_net_liquidation(&[], "USD", &rates), Ok(None));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use sqlx::PgPool;
+
+use crate::Insertable;
+
+/// A table, schema qualified (e.g. trading.strategy), with the columns its model reads / writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelTable {
+    pub table: String,
+    pub columns: Vec<&'static str>,
+}
+
+impl ModelTable {
+    pub fn new(table: impl Into<String>, columns: Vec<&'static str>) -> Self {
+        Self {
+            table: table.into(),
+            columns,
+        }
+    }
+
+    /// table with every column of T
+    pub fn of<T: Insertable>(table: &str) -> Self {
+        Self::new(table, T::column_names())
+    }
+
+    /// (schema, table name) - tables without a schema are in public
+    fn schema_and_name(&self) -> (&str, &str) {
+        self.table
+            .split_once('.')
+            .unwrap_or(("public", self.table.as_str()))
+    }
+}
+
+/// Where a model and the migrated schema disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaMismatch {
+    MissingTable { table: String },
+    MissingColumn { table: String, column: String },
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaMismatch::MissingTable { table } => write!(f, "{}: table does not exist", table),
+            SchemaMismatch::MissingColumn { table, column } => {
+                write!(f, "{}: column {} does not exist", table, column)
+            }
+        }
+    }
+}
+
+/// Mismatches of model against the columns its table actually has - a table with no columns is
+/// taken to not exist
+pub fn schema_mismatches(model: &ModelTable, table_columns: &[String]) -> Vec<SchemaMismatch> {
+    if table_columns.is_empty() {
+        return vec![SchemaMismatch::MissingTable {
+            table: model.table.clone(),
+        }];
+    }
+    model
+        .columns
+        .iter()
+        .filter(|column| !table_columns.iter().any(|existing| existing == *column))
+        .map(|column| SchemaMismatch::MissingColumn {
+            table: model.table.clone(),
+            column: column.to_string(),
+        })
+        .collect()
+}
+
+/// Columns the table has in the database, per information_schema
+async fn table_columns(db: &PgPool, model: &ModelTable) -> Result<Vec<String>, String> {
+    let (schema, name) = model.schema_and_name();
+    sqlx::query_scalar::<_, String>(
+        "SELECT column_name::text FROM information_schema.columns
+        WHERE table_schema = $1 AND table_name = $2",
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(db)
+    .await
+    .map_err(|e| format!("Failed to read the columns of {}: {}", model.table, e))
+}
+
+/// Fails with every mismatch listed if any model has drifted from the schema the trading app
+/// migrates - run at startup so a handler pointed at a missing table / column is caught before its
+/// first request
+pub async fn validate_schema(db: &PgPool, models: &[ModelTable]) -> Result<(), String> {
+    let mut mismatches = Vec::new();
+    for model in models {
+        let columns = table_columns(db, model).await?;
+        mismatches.extend(schema_mismatches(model, &columns));
+    }
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Models do not match the database schema:\n{}",
+        mismatches
+            .iter()
+            .map(|mismatch| format!("  - {}", mismatch))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    /// trading.strategy's model with a column no migration adds
+    fn drifted_strategy_model() -> ModelTable {
+        ModelTable::new(
+            "trading.strategy",
+            vec!["strategy", "capital", "initial_capital", "status", "max_drawdown"],
+        )
+    }
+
+    #[test]
+    fn missing_column_and_table_are_reported() {
+        let table_columns: Vec<String> = ["strategy", "capital", "initial_capital", "status"]
+            .iter()
+            .map(|column| column.to_string())
+            .collect();
+
+        assert_eq!(
+            schema_mismatches(&drifted_strategy_model(), &table_columns),
+            vec![SchemaMismatch::MissingColumn {
+                table: "trading.strategy".to_string(),
+                column: "max_drawdown".to_string(),
+            }]
+        );
+        assert_eq!(
+            schema_mismatches(&drifted_strategy_model(), &[]),
+            vec![SchemaMismatch::MissingTable {
+                table: "trading.strategy".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn validation_lists_every_mismatch() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+
+        let error = validate_schema(
+            &db,
+            &[
+                drifted_strategy_model(),
+                ModelTable::new("trading.no_such_table", vec!["strategy"]),
+            ],
+        )
+        .await
+        .expect_err("Expected drifted models to fail validation");
+
+        assert!(error.contains("trading.strategy: column max_drawdown does not exist"));
+        assert!(error.contains("trading.no_such_table: table does not exist"));
+        assert!(!error.contains("column capital"));
+    }
+
+    #[tokio::test]
+    async fn every_handler_table_matches_the_migrated_schema() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+
+        assert_eq!(
+            validate_schema(&db, &crate::registered_models()).await,
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{Json, extract::State};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    AppState, BrokerPositions,
+    models::{OptionType, option_contract_key},
+};
+
+/// Quantities closer than this are taken to match - sums of fractional fills drift by float error
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// A strategy's position in a contract, with the time of its latest transaction in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionActivity {
+    pub strategy: String,
+    /// Stock, or option_contract_key for an option
+    pub contract: String,
+    pub quantity: f64,
+    /// None if the position has no transactions, e.g. it was fixed by hand
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// Position that hasn't changed within the window while the strategies' total in its contract
+/// differs from the broker's quantity at the last sync
+/// - a position that is simply old but matches the broker isn't stale
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StalePosition {
+    pub strategy: String,
+    pub contract: String,
+    pub local: f64,
+    /// Every strategy's quantity in the contract together
+    pub local_total: f64,
+    pub broker: f64,
+    #[serde(serialize_with = "crate::timestamps::serialize")]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// Broker quantities (contract -> quantity) as of the last positions sync, recorded by
+/// POST /send/positions_mismatch
+#[derive(Clone, Default)]
+pub struct LastSync(Arc<Mutex<Option<HashMap<String, f64>>>>);
+
+impl LastSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn record(&self, broker_positions: &BrokerPositions) {
+        *self.0.lock().await = Some(broker_quantities(broker_positions));
+    }
+
+    pub async fn get(&self) -> Option<HashMap<String, f64>> {
+        self.0.lock().await.clone()
+    }
+}
+
+/// (strategy, contract) of the stale positions last alerted, so a position is only alerted once
+/// while it stays stale
+#[derive(Clone, Default)]
+pub struct AlertedStale(Arc<Mutex<HashSet<(String, String)>>>);
+
+impl AlertedStale {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The positions in stale not alerted since they last became stale - stale becomes the alerted
+    /// set, so one that resolves and goes stale again is alerted again
+    pub async fn newly_stale(&self, stale: Vec<StalePosition>) -> Vec<StalePosition> {
+        let mut alerted = self.0.lock().await;
+        let previously_alerted = std::mem::take(&mut *alerted);
+        alerted.extend(
+            stale
+                .iter()
+                .map(|position| (position.strategy.clone(), position.contract.clone())),
+        );
+        stale
+            .into_iter()
+            .filter(|position| {
+                !previously_alerted
+                    .contains(&(position.strategy.clone(), position.contract.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Contract -> quantity the broker holds, keyed as PositionActivity::contract
+pub fn broker_quantities(broker_positions: &BrokerPositions) -> HashMap<String, f64> {
+    let mut quantities = broker_positions.stocks.clone();
+    for option in &broker_positions.options {
+        *quantities
+            .entry(option_contract_key(
+                &option.stock,
+                &option.expiry,
+                option.strike,
+                &option.option_type,
+                &option.multiplier,
+            ))
+            .or_insert(0.0) += option.quantity;
+    }
+    quantities
+}
+
+/// Positions last updated before now - window (or never) in a contract whose local total doesn't
+/// match broker - a contract missing from broker is taken to be held at 0 there
+pub fn find_stale_positions(
+    positions: &[PositionActivity],
+    broker: &HashMap<String, f64>,
+    now: DateTime<Utc>,
+    window: Duration,
+) -> Vec<StalePosition> {
+    let cutoff = now - chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+    let mut local_totals: HashMap<&str, f64> = HashMap::new();
+    for position in positions {
+        *local_totals.entry(&position.contract).or_insert(0.0) += position.quantity;
+    }
+    positions
+        .iter()
+        .filter(|position| position.last_updated.is_none_or(|time| time < cutoff))
+        .filter_map(|position| {
+            let local_total = local_totals[position.contract.as_str()];
+            let broker = broker.get(&position.contract).copied().unwrap_or(0.0);
+            ((local_total - broker).abs() > QUANTITY_EPSILON).then(|| StalePosition {
+                strategy: position.strategy.clone(),
+                contract: position.contract.clone(),
+                local: position.quantity,
+                local_total,
+                broker,
+                last_updated: position.last_updated,
+            })
+        })
+        .collect()
+}
+
+/// Every stock and option position with the time of its latest transaction
+async fn position_activity(db: &sqlx::PgPool) -> Result<Vec<PositionActivity>, String> {
+    let stocks = sqlx::query_as::<_, (String, String, f64, Option<DateTime<Utc>>)>(
+        r#"
+        SELECT p.strategy, p.stock, p.quantity, MAX(t.time)
+        FROM trading.current_stock_positions p
+        LEFT JOIN trading.stock_transactions t
+            ON t.strategy = p.strategy AND t.stock = p.stock AND t.primary_exchange = p.primary_exchange
+        GROUP BY p.strategy, p.stock, p.primary_exchange, p.quantity
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|err| format!("Failed to read stock positions: {}", err))?;
+    let options = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            String,
+            f64,
+            String,
+            OptionType,
+            f64,
+            Option<DateTime<Utc>>,
+        ),
+    >(
+        r#"
+        SELECT p.strategy, p.stock, p.expiry, p.strike, p.multiplier, p.option_type, p.quantity,
+            MAX(t.time)
+        FROM trading.current_option_positions p
+        LEFT JOIN trading.option_transactions t
+            ON t.strategy = p.strategy AND t.stock = p.stock
+            AND t.primary_exchange = p.primary_exchange AND t.expiry = p.expiry
+            AND t.strike = p.strike AND t.multiplier = p.multiplier
+            AND t.option_type = p.option_type
+        GROUP BY p.strategy, p.stock, p.primary_exchange, p.expiry, p.strike, p.multiplier,
+            p.option_type, p.quantity
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|err| format!("Failed to read option positions: {}", err))?;
+
+    let stocks =
+        stocks.into_iter().map(
+            |(strategy, stock, quantity, last_updated)| PositionActivity {
+                strategy,
+                contract: stock,
+                quantity,
+                last_updated,
+            },
+        );
+    let options = options.into_iter().map(
+        |(strategy, stock, expiry, strike, multiplier, option_type, quantity, last_updated)| {
+            PositionActivity {
+                strategy,
+                contract: option_contract_key(&stock, &expiry, strike, &option_type, &multiplier),
+                quantity,
+                last_updated,
+            }
+        },
+    );
+    Ok(stocks.chain(options).collect())
+}
+
+/// Stale positions against the last sync - None if no sync has been recorded yet
+pub async fn detect_stale_positions(
+    state: &AppState,
+) -> Result<Option<Vec<StalePosition>>, String> {
+    let Some(broker) = state.last_sync.get().await else {
+        return Ok(None);
+    };
+    let positions = position_activity(&state.db).await?;
+    Ok(Some(find_stale_positions(
+        &positions,
+        &broker,
+        Utc::now(),
+        state.stale_position_window,
+    )))
+}
+
+/// Notifies the client of positions that have gone stale - called after each positions sync, a
+/// position already alerted isn't alerted again until it resolves
+pub async fn notify_stale_positions(state: &AppState) {
+    match detect_stale_positions(state).await {
+        Ok(Some(stale)) => {
+            let stale = state.alerted_stale.newly_stale(stale).await;
+            if stale.is_empty() {
+                return;
+            }
+            tracing::warn!("{} stale positions found", stale.len());
+            let alert = serde_json::json!({ "stale_positions": stale });
+            if let Err(err) = state.notifier.send(alert.to_string()).await {
+                tracing::error!("Failed to send stale positions alert: {}", err);
+            }
+        }
+        Ok(None) => {}
+        Err(err) => tracing::error!("Failed to detect stale positions: {}", err),
+    }
+}
+
+/// GET /positions/stale
+pub async fn get_stale_positions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<StalePosition>>, (StatusCode, String)> {
+    match detect_stale_positions(&state).await {
+        Ok(Some(stale)) => Ok(Json(stale)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            "No broker positions have been synced yet".to_string(),
+        )),
+        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, 18, 14, 30, 0).unwrap()
+    }
+
+    fn position(strategy: &str, quantity: f64, days_since_update: Option<i64>) -> PositionActivity {
+        PositionActivity {
+            strategy: strategy.to_string(),
+            contract: "QQQ".to_string(),
+            quantity,
+            last_updated: days_since_update.map(|days| now() - chrono::Duration::days(days)),
+        }
+    }
+
+    #[test]
+    fn only_old_positions_in_a_mismatched_contract_are_stale() {
+        let positions = [
+            position("old", 10.0, Some(5)),
+            position("recent", 5.0, Some(1)),
+            position("fixed_by_hand", 2.0, None),
+        ];
+
+        let stale = find_stale_positions(
+            &positions,
+            &HashMap::from([("QQQ".to_string(), 15.0)]),
+            now(),
+            WINDOW,
+        );
+        assert_eq!(
+            stale
+                .iter()
+                .map(|position| position.strategy.as_str())
+                .collect::<Vec<_>>(),
+            vec!["old", "fixed_by_hand"]
+        );
+        assert_eq!(stale[0].local, 10.0);
+        assert_eq!(stale[0].local_total, 17.0);
+        assert_eq!(stale[0].broker, 15.0);
+
+        // matching the broker, or missing from it at 0, is not stale however old
+        assert!(
+            find_stale_positions(
+                &positions,
+                &HashMap::from([("QQQ".to_string(), 17.0)]),
+                now(),
+                WINDOW,
+            )
+            .is_empty()
+        );
+        assert!(
+            find_stale_positions(
+                &[position("old", 0.0, Some(5))],
+                &HashMap::new(),
+                now(),
+                WINDOW
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn float_error_in_the_local_total_is_not_a_mismatch() {
+        let positions = [position("a", 0.1, Some(5)), position("b", 0.2, Some(5))];
+
+        assert!(
+            find_stale_positions(
+                &positions,
+                &HashMap::from([("QQQ".to_string(), 0.3)]),
+                now(),
+                WINDOW,
+            )
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_position_is_alerted_once_until_it_resolves() {
+        let alerted = AlertedStale::new();
+        let stale = find_stale_positions(
+            &[position("old", 10.0, Some(5))],
+            &HashMap::new(),
+            now(),
+            WINDOW,
+        );
+
+        assert_eq!(alerted.newly_stale(stale.clone()).await, stale);
+        assert!(alerted.newly_stale(stale.clone()).await.is_empty());
+        assert!(alerted.newly_stale(Vec::new()).await.is_empty());
+        assert_eq!(alerted.newly_stale(stale.clone()).await, stale);
+    }
+}
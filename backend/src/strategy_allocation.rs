@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Deserialize;
+
+use crate::models;
+
+/// Allocations are compared to the book's total capital with this much slack for float rounding
+const ALLOCATION_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RebalanceAllocations {
+    /// New capital for each strategy - strategies left out keep their current capital
+    pub allocations: HashMap<String, f64>,
+}
+
+fn internal_error(action: &str, err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!(
+            "Failed to {} while rebalancing allocations: {}",
+            action, err
+        ),
+    )
+}
+
+/// Checks allocations against the book before any capital is moved
+/// - current_capital: capital of every strategy in the book
+/// - open_notional: cost (|quantity| * avg_price * multiplier) of each strategy's open positions
+/// - the rebalanced book has to add up to the same total capital, and no strategy with open
+///   positions may be left with less capital than they cost
+pub fn validate_allocations(
+    current_capital: &HashMap<String, f64>,
+    open_notional: &HashMap<String, f64>,
+    allocations: &HashMap<String, f64>,
+) -> Result<(), String> {
+    for (strategy, allocation) in allocations {
+        if !current_capital.contains_key(strategy) {
+            return Err(format!("Strategy {} does not exist", strategy));
+        }
+        if !allocation.is_finite() || *allocation < 0.0 {
+            return Err(format!(
+                "Allocation {} for strategy {} has to be a non-negative number",
+                allocation, strategy
+            ));
+        }
+        let notional = open_notional.get(strategy).copied().unwrap_or(0.0);
+        if *allocation < notional {
+            return Err(format!(
+                "Allocation {:.2} for strategy {} would strand its open positions costing {:.2}",
+                allocation, strategy, notional
+            ));
+        }
+    }
+
+    let total: f64 = current_capital.values().sum();
+    let rebalanced_total: f64 = current_capital
+        .iter()
+        .map(|(strategy, capital)| allocations.get(strategy).copied().unwrap_or(*capital))
+        .sum();
+    if (rebalanced_total - total).abs() > ALLOCATION_TOLERANCE {
+        return Err(format!(
+            "Allocations add up to {:.2} but {:.2} is available across the book",
+            rebalanced_total, total
+        ));
+    }
+    Ok(())
+}
+
+/// Moves capital between strategies in one transaction - rows are locked so capital can't change
+/// between the check and the update
+pub async fn rebalance_allocations(
+    db: &sqlx::PgPool,
+    allocations: HashMap<String, f64>,
+) -> Result<Vec<models::Strategy>, (StatusCode, String)> {
+    let mut tx = db
+        .begin()
+        .await
+        .map_err(|err| internal_error("begin transaction", err))?;
+
+    let current_capital: HashMap<String, f64> = sqlx::query_as::<_, (String, Option<f64>)>(
+        "SELECT strategy, capital FROM trading.strategy FOR UPDATE",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|err| internal_error("read strategies", err))?
+    .into_iter()
+    .map(|(strategy, capital)| (strategy, capital.unwrap_or(0.0)))
+    .collect();
+
+    let open_notional: HashMap<String, f64> = sqlx::query_as::<_, (String, f64)>(
+        r#"
+        SELECT strategy, SUM(notional) FROM (
+            SELECT strategy, ABS(quantity) * avg_price AS notional
+            FROM trading.current_stock_positions
+            UNION ALL
+            SELECT strategy, ABS(quantity) * avg_price * multiplier::float8 AS notional
+            FROM trading.current_option_positions
+        ) AS positions
+        GROUP BY strategy
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|err| internal_error("read open positions", err))?
+    .into_iter()
+    .collect();
+
+    validate_allocations(&current_capital, &open_notional, &allocations)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+
+    let mut strategies = Vec::with_capacity(allocations.len());
+    for (strategy, allocation) in allocations {
+        let strategy = sqlx::query_as::<_, models::Strategy>(
+            "UPDATE trading.strategy SET capital = $2 WHERE strategy = $1 RETURNING *",
+        )
+        .bind(&strategy)
+        .bind(allocation)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| internal_error("update capital", err))?;
+        strategies.push(strategy);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|err| internal_error("commit transaction", err))?;
+
+    Ok(strategies)
+}
+
+/// POST /strategy/rebalance
+/// - Sets each listed strategy's capital to its allocation, returning the updated strategies
+/// - Rejects (400) allocations that don't add up to the book's total capital or that leave a
+///   strategy with less capital than its open positions cost
+pub async fn rebalance_strategies(
+    State(state): State<crate::AppState>,
+    Json(rebalance): Json<RebalanceAllocations>,
+) -> Result<(StatusCode, Json<Vec<models::Strategy>>), (StatusCode, String)> {
+    let strategies = rebalance_allocations(&state.db, rebalance.allocations).await?;
+    Ok((StatusCode::OK, Json(strategies)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn book(entries: &[(&str, f64)]) -> HashMap<String, f64> {
+        entries
+            .iter()
+            .map(|(strategy, amount)| (strategy.to_string(), *amount))
+            .collect()
+    }
+
+    #[test]
+    fn allocations_have_to_keep_the_total_and_cover_open_positions() {
+        let current_capital = book(&[("a", 600.0), ("b", 400.0)]);
+        let open_notional = book(&[("a", 300.0)]);
+
+        assert_eq!(
+            validate_allocations(
+                &current_capital,
+                &open_notional,
+                &book(&[("a", 300.0), ("b", 700.0)])
+            ),
+            Ok(())
+        );
+        // 100 more than the book holds
+        assert!(
+            validate_allocations(&current_capital, &open_notional, &book(&[("b", 500.0)])).is_err()
+        );
+        // strands a's open positions
+        assert!(
+            validate_allocations(
+                &current_capital,
+                &open_notional,
+                &book(&[("a", 200.0), ("b", 800.0)])
+            )
+            .is_err()
+        );
+        assert!(
+            validate_allocations(&current_capital, &open_notional, &book(&[("c", 0.0)])).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn rebalance_moves_capital_between_strategies() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy IN ('rebalance_a', 'rebalance_b');
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status) VALUES
+                ('rebalance_a', 600, 600, 'inactive'),
+                ('rebalance_b', 400, 400, 'inactive');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert strategies");
+
+        let rejected =
+            rebalance_allocations(&db, book(&[("rebalance_a", 900.0), ("rebalance_b", 400.0)]))
+                .await;
+        let rebalanced =
+            rebalance_allocations(&db, book(&[("rebalance_a", 300.0), ("rebalance_b", 700.0)]))
+                .await;
+
+        sqlx::query(
+            "DELETE FROM trading.strategy WHERE strategy IN ('rebalance_a', 'rebalance_b')",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up strategies");
+        let (status, _) = rejected.expect_err("Expected an unbalanced allocation to be rejected");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let capital: HashMap<String, Option<f64>> = rebalanced
+            .expect("Expected the allocation to go through")
+            .into_iter()
+            .map(|strategy| (strategy.strategy, strategy.capital))
+            .collect();
+        assert_eq!(capital["rebalance_a"], Some(300.0));
+        assert_eq!(capital["rebalance_b"], Some(700.0));
+    }
+}
@@ -0,0 +1,174 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Deserialize;
+
+use crate::models;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CloneStrategy {
+    pub source: String,
+    pub strategy: String,
+    /// Also copy the source's strategy params (and their schema)
+    #[serde(default)]
+    pub copy_params: bool,
+}
+
+fn internal_error(action: &str, err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Failed to {} while cloning strategy: {}", action, err),
+    )
+}
+
+/// POST /strategy/clone
+/// - Creates strategy as a copy of source with the same initial_capital, capital back at
+///   initial_capital and no positions, orders or transactions - Inactive until resumed
+/// - Copies source's strategy params too if copy_params is set
+/// - Refuses (409) if strategy already exists, 404 if source does not
+pub async fn clone_strategy(
+    State(state): State<crate::AppState>,
+    Json(clone_details): Json<CloneStrategy>,
+) -> Result<(StatusCode, Json<models::Strategy>), (StatusCode, String)> {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|err| internal_error("begin transaction", err))?;
+
+    let source_exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM trading.strategy WHERE strategy = $1)",
+    )
+    .bind(&clone_details.source)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| internal_error("read source strategy", err))?;
+    if !source_exists {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Strategy {} does not exist", clone_details.source),
+        ));
+    }
+
+    // ON CONFLICT so a concurrent clone to the same name also ends up as a 409
+    let strategy = sqlx::query_as::<_, models::Strategy>(
+        r#"
+        INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+        SELECT $2, initial_capital, initial_capital, 'inactive'
+        FROM trading.strategy
+        WHERE strategy = $1
+        ON CONFLICT (strategy) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(&clone_details.source)
+    .bind(&clone_details.strategy)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|err| internal_error("create strategy", err))?
+    .ok_or((
+        StatusCode::CONFLICT,
+        format!("Strategy {} already exists", clone_details.strategy),
+    ))?;
+
+    if clone_details.copy_params {
+        sqlx::query(
+            r#"
+            INSERT INTO trading.strategy_params (strategy, params, params_schema)
+            SELECT $2, params, params_schema
+            FROM trading.strategy_params
+            WHERE strategy = $1
+            "#,
+        )
+        .bind(&clone_details.source)
+        .bind(&clone_details.strategy)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| internal_error("copy strategy params", err))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|err| internal_error("commit transaction", err))?;
+
+    Ok((StatusCode::OK, Json(strategy)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn clone_of(source: &str) -> CloneStrategy {
+        CloneStrategy {
+            source: source.to_string(),
+            strategy: "clone_strat_copy".to_string(),
+            copy_params: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn clone_starts_inactive_at_initial_capital_with_the_source_params() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy IN ('clone_strat', 'clone_strat_copy');
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('clone_strat', 1500, 1000, 'active');
+            INSERT INTO trading.current_stock_positions
+                (strategy, stock, primary_exchange, quantity, avg_price)
+            VALUES ('clone_strat', 'QQQ', 'NASDAQ', 10, 450);
+            INSERT INTO trading.strategy_params (strategy, params, params_schema)
+            VALUES ('clone_strat', '{\"window\": 20}', '{\"window\": \"integer\"}');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert source strategy");
+
+        let cloned = clone_strategy(
+            State(test_support::app_state(db.clone())),
+            Json(clone_of("clone_strat")),
+        )
+        .await;
+        let duplicate = clone_strategy(
+            State(test_support::app_state(db.clone())),
+            Json(clone_of("clone_strat")),
+        )
+        .await;
+        let missing_source = clone_strategy(
+            State(test_support::app_state(db.clone())),
+            Json(clone_of("missing_clone_strat")),
+        )
+        .await;
+        let params = sqlx::query_scalar::<_, serde_json::Value>(
+            "SELECT params FROM trading.strategy_params WHERE strategy = 'clone_strat_copy'",
+        )
+        .fetch_optional(&db)
+        .await
+        .expect("Expected to read params");
+        let positions = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM trading.current_stock_positions
+            WHERE strategy = 'clone_strat_copy'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to count positions");
+
+        sqlx::query(
+            "DELETE FROM trading.strategy WHERE strategy IN ('clone_strat', 'clone_strat_copy')",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to clean up strategies");
+        let (status, Json(strategy)) = cloned.expect("Expected the clone to be created");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(strategy.strategy, "clone_strat_copy");
+        assert_eq!(strategy.capital, Some(1000.0));
+        assert_eq!(strategy.initial_capital, Some(1000.0));
+        assert!(matches!(strategy.status, Some(models::Status::Inactive)));
+        assert_eq!(params, Some(serde_json::json!({ "window": 20 })));
+        assert_eq!(positions, 0);
+        let (status, _) = duplicate.expect_err("Expected an existing strategy to be refused");
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = missing_source.expect_err("Expected a missing source to be rejected");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,187 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use shared::params::validate_params;
+
+use crate::crud::CRUDTrait as _;
+use crate::{crud, models};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateStrategyParams {
+    pub strategy: String,
+    pub params: Value,
+}
+
+fn get_strategy_params_crud(
+    state: &crate::AppState,
+) -> crud::CRUD<
+    models::StrategyParamsFullKeys,
+    models::StrategyParamsPrimaryKeys,
+    models::StrategyParamsUpdateKeys,
+> {
+    crud::CRUD::<
+        models::StrategyParamsFullKeys,
+        models::StrategyParamsPrimaryKeys,
+        models::StrategyParamsUpdateKeys,
+    >::new(state.db.clone(), "trading.strategy_params".to_string())
+}
+
+/// GET /strategy_params?strategy=...
+pub async fn read_strategy_params(
+    State(state): State<crate::AppState>,
+    Query(pk): Query<models::StrategyParamsPrimaryKeys>,
+) -> Result<(StatusCode, Json<models::StrategyParamsFullKeys>), (StatusCode, String)> {
+    match get_strategy_params_crud(&state).read(&pk).await {
+        Ok(Some(strategy_params)) => Ok((StatusCode::OK, Json(strategy_params))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            format!("No params registered for strategy {}", pk.strategy),
+        )),
+        Err(err) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read strategy params: {}", err),
+        )),
+    }
+}
+
+/// PUT /strategy_params
+/// - params are validated against the schema the trading app registered for the strategy and
+///   are picked up the next time the strategy loads its params
+pub async fn update_strategy_params(
+    State(state): State<crate::AppState>,
+    Json(update): Json<UpdateStrategyParams>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let strategy_params_crud = get_strategy_params_crud(&state);
+    let pk = models::StrategyParamsPrimaryKeys {
+        strategy: update.strategy,
+    };
+
+    let strategy_params = strategy_params_crud
+        .read(&pk)
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read strategy params: {}", err),
+            )
+        })?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!(
+                "No params registered for strategy {} - has it been run yet?",
+                pk.strategy
+            ),
+        ))?;
+
+    validate_params(&strategy_params.params_schema, &update.params)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err))?;
+
+    strategy_params_crud
+        .update(
+            &pk,
+            &models::StrategyParamsUpdateKeys {
+                params: Some(update.params),
+                params_schema: None,
+            },
+        )
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update strategy params: {}", err),
+            )
+        })?;
+
+    Ok((StatusCode::OK, "Updated".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support;
+
+    fn update_of(strategy: &str, params: Value) -> UpdateStrategyParams {
+        UpdateStrategyParams {
+            strategy: strategy.to_string(),
+            params,
+        }
+    }
+
+    #[tokio::test]
+    async fn update_stores_params_matching_the_schema() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy = 'params_strat';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('params_strat', 1000, 1000, 'active');
+            INSERT INTO trading.strategy_params (strategy, params, params_schema)
+            VALUES ('params_strat', '{\"window\": 20}',
+                '{\"window\": \"integer\", \"threshold\": \"number\"}');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to insert strategy params");
+
+        let updated = update_strategy_params(
+            State(test_support::app_state(db.clone())),
+            Json(update_of(
+                "params_strat",
+                json!({ "window": 30, "threshold": 0.5 }),
+            )),
+        )
+        .await;
+        let invalid = update_strategy_params(
+            State(test_support::app_state(db.clone())),
+            Json(update_of("params_strat", json!({ "window": "thirty" }))),
+        )
+        .await;
+        let unknown_param = update_strategy_params(
+            State(test_support::app_state(db.clone())),
+            Json(update_of("params_strat", json!({ "lookback": 5 }))),
+        )
+        .await;
+        let params = sqlx::query_scalar::<_, Value>(
+            "SELECT params FROM trading.strategy_params WHERE strategy = 'params_strat'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to read params");
+
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'params_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up strategy");
+        assert_eq!(
+            updated.expect("Expected params matching the schema to be stored"),
+            (StatusCode::OK, "Updated".to_string())
+        );
+        let (status, _) = invalid.expect_err("Expected a mistyped param to be rejected");
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = unknown_param.expect_err("Expected an undeclared param to be rejected");
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        // the rejected updates left the stored params alone
+        assert_eq!(params, json!({ "window": 30, "threshold": 0.5 }));
+    }
+
+    #[tokio::test]
+    async fn update_of_unknown_strategy_is_not_found() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+
+        let missing = update_strategy_params(
+            State(test_support::app_state(db.clone())),
+            Json(update_of("missing_params_strat", json!({ "window": 30 }))),
+        )
+        .await;
+
+        let (status, _) = missing.expect_err("Expected an unknown strategy to be rejected");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
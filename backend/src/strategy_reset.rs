@@ -0,0 +1,189 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Deserialize;
+
+use crate::models;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResetStrategy {
+    pub strategy: String,
+    /// Also delete the strategy's stock and option transactions
+    #[serde(default)]
+    pub purge_transactions: bool,
+    /// Allow resetting a strategy that is still Active
+    #[serde(default)]
+    pub force: bool,
+}
+
+fn internal_error(action: &str, err: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Failed to {} while resetting strategy: {}", action, err),
+    )
+}
+
+/// POST /strategy/reset
+/// - Zeroes the strategy's current positions, deletes its open orders, optionally purges its
+///   transactions and sets capital back to initial_capital - all in one transaction
+/// - Refuses (409) to reset an Active strategy unless force is set
+pub async fn reset_strategy(
+    State(state): State<crate::AppState>,
+    Json(reset_details): Json<ResetStrategy>,
+) -> Result<(StatusCode, Json<models::Strategy>), (StatusCode, String)> {
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|err| internal_error("begin transaction", err))?;
+
+    // Row lock so the status check holds until the reset commits
+    let status = sqlx::query_scalar::<_, Option<models::Status>>(
+        "SELECT status FROM trading.strategy WHERE strategy = $1 FOR UPDATE",
+    )
+    .bind(&reset_details.strategy)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|err| internal_error("read strategy", err))?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("Strategy {} does not exist", reset_details.strategy),
+    ))?;
+
+    if matches!(status, Some(models::Status::Active)) && !reset_details.force {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Strategy {} is Active - pause it first or pass force to reset anyway",
+                reset_details.strategy
+            ),
+        ));
+    }
+
+    let mut statements = vec![
+        "UPDATE trading.current_stock_positions SET quantity = 0, avg_price = 0 WHERE strategy = $1",
+        "UPDATE trading.current_option_positions SET quantity = 0, avg_price = 0 WHERE strategy = $1",
+        "DELETE FROM trading.open_stock_orders WHERE strategy = $1",
+        "DELETE FROM trading.open_option_orders WHERE strategy = $1",
+    ];
+    if reset_details.purge_transactions {
+        statements.push("DELETE FROM trading.stock_transactions WHERE strategy = $1");
+        statements.push("DELETE FROM trading.option_transactions WHERE strategy = $1");
+    }
+    for statement in statements {
+        sqlx::query(statement)
+            .bind(&reset_details.strategy)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| internal_error(statement, err))?;
+    }
+
+    let strategy = sqlx::query_as::<_, models::Strategy>(
+        "UPDATE trading.strategy SET capital = initial_capital WHERE strategy = $1 RETURNING *",
+    )
+    .bind(&reset_details.strategy)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| internal_error("reset capital", err))?;
+
+    tx.commit()
+        .await
+        .map_err(|err| internal_error("commit transaction", err))?;
+
+    Ok((StatusCode::OK, Json(strategy)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    const SETUP: &str = "DELETE FROM trading.strategy WHERE strategy = 'reset_strat';
+        INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+        VALUES ('reset_strat', 400, 1000, 'active');
+        INSERT INTO trading.current_stock_positions
+            (strategy, stock, primary_exchange, quantity, avg_price)
+        VALUES ('reset_strat', 'QQQ', 'NASDAQ', 10, 450);
+        INSERT INTO trading.open_stock_orders
+            (strategy, order_perm_id, order_id, time, stock, primary_exchange, quantity, filled,
+            executions)
+        VALUES ('reset_strat', 664001, 1, NOW(), 'QQQ', 'NASDAQ', 5, 0, '{}');";
+
+    fn reset(force: bool) -> ResetStrategy {
+        ResetStrategy {
+            strategy: "reset_strat".to_string(),
+            purge_transactions: false,
+            force,
+        }
+    }
+
+    #[tokio::test]
+    async fn active_strategy_is_only_reset_when_forced() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(SETUP)
+            .execute(&db)
+            .await
+            .expect("Expected to insert strategy");
+
+        let refused = reset_strategy(
+            State(test_support::app_state(db.clone())),
+            Json(reset(false)),
+        )
+        .await;
+        let untouched = sqlx::query_scalar::<_, f64>(
+            "SELECT capital FROM trading.strategy WHERE strategy = 'reset_strat'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to read capital");
+        let forced = reset_strategy(
+            State(test_support::app_state(db.clone())),
+            Json(reset(true)),
+        )
+        .await;
+        let position = sqlx::query_as::<_, (f64, f64)>(
+            "SELECT quantity, avg_price FROM trading.current_stock_positions
+            WHERE strategy = 'reset_strat'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to read position");
+        let open_orders = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM trading.open_stock_orders WHERE strategy = 'reset_strat'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to count open orders");
+
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'reset_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up strategy");
+        let (status, _) = refused.expect_err("Expected an Active strategy to be refused");
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(untouched, 400.0);
+        let (status, Json(strategy)) = forced.expect("Expected a forced reset to go through");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(strategy.capital, Some(1000.0));
+        assert_eq!(position, (0.0, 0.0));
+        assert_eq!(open_orders, 0);
+    }
+
+    #[tokio::test]
+    async fn missing_strategy_is_not_found() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+
+        let result = reset_strategy(
+            State(test_support::app_state(db)),
+            Json(ResetStrategy {
+                strategy: "missing_reset_strat".to_string(),
+                purge_transactions: false,
+                force: true,
+            }),
+        )
+        .await;
+
+        let (status, _) = result.expect_err("Expected a missing strategy to be rejected");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+use std::{sync::Arc, time::Duration};
+
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use tokio::sync::Mutex;
+
+use crate::{AppState, bearer_token, currency, notifier, portfolio_cache, stale_positions};
+
+/// Held by every test touching the database, so tests sharing rows don't interleave
+pub static TEST_MUTEX: Mutex<()> = Mutex::const_new(());
+
+/// Pool on the DATABASE_URL test database, migrated by the trading app
+pub async fn pool() -> PgPool {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect to Postgres")
+}
+
+/// AppState over db with main's defaults and no websocket client connected
+pub fn app_state(db: PgPool) -> AppState {
+    let client = Arc::new(Mutex::new(None));
+    AppState {
+        auth_token: Arc::new(
+            bearer_token::BearerToken::parse("0123456789abcdefghijklmnopqrstuv")
+                .expect("Expected test token to be valid"),
+        ),
+        db,
+        client: client.clone(),
+        notifier: notifier::WsNotifier::new(client, Duration::ZERO),
+        money_decimal_places: 2,
+        min_metrics_observations: 30,
+        portfolio_concurrency: 2,
+        portfolio_cache: portfolio_cache::PortfolioCache::new(Duration::from_secs(10)),
+        portfolio_query_timeout: Duration::from_secs(30),
+        currency: currency::CurrencyConfig {
+            base_currency: "USD".to_string(),
+            display_currency: "USD".to_string(),
+        },
+        reconcile_tolerance_pct: 1.0,
+        last_sync: stale_positions::LastSync::new(),
+        alerted_stale: stale_positions::AlertedStale::new(),
+        stale_position_window: Duration::from_secs(3 * 24 * 60 * 60),
+    }
+}
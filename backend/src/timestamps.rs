@@ -0,0 +1,114 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Every timestamp sent out by the backend, REST or websocket, is written the same way:
+/// UTC RFC3339 with millisecond precision and a Z suffix, e.g. 2025-07-15T13:30:00.000Z
+/// - the DB stores UTC, so nothing is converted to exchange time on the way out
+pub fn to_rfc3339(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Types a timestamp field can have on the models and their key structs
+pub trait UtcTimestamp {
+    fn serialize_utc<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+impl UtcTimestamp for DateTime<Utc> {
+    fn serialize_utc<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_rfc3339(self))
+    }
+}
+
+impl UtcTimestamp for Option<DateTime<Utc>> {
+    fn serialize_utc<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Some(time) => serializer.serialize_some(&to_rfc3339(time)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// For #[serde(serialize_with = "crate::timestamps::serialize")]
+/// - generic so the same attribute works on a model's Option field and on its FullKeys
+///   counterpart, where the derive strips the Option
+/// - deserializing is left to chrono, which accepts any RFC3339 offset
+pub fn serialize<T: UtcTimestamp, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.serialize_utc(serializer)
+}
+
+/// Point of a time series as it is sent for charting - the epoch_ms companion saves the
+/// frontend parsing every timestamp before plotting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SeriesPoint {
+    #[serde(serialize_with = "serialize")]
+    time: DateTime<Utc>,
+    time_epoch_ms: i64,
+    value: f64,
+}
+
+/// For #[serde(with = "crate::timestamps::series")] on (time, value) series
+/// - written as [{ "time": ..., "time_epoch_ms": ..., "value": ... }, ...]
+pub mod series {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        points: &[(DateTime<Utc>, f64)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(points.iter().map(|(time, value)| SeriesPoint {
+            time: *time,
+            time_epoch_ms: time.timestamp_millis(),
+            value: *value,
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>, D::Error> {
+        Ok(Vec::<SeriesPoint>::deserialize(deserializer)?
+            .into_iter()
+            .map(|point| (point.time, point.value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Chart {
+        #[serde(serialize_with = "serialize")]
+        updated: Option<DateTime<Utc>>,
+        #[serde(with = "series")]
+        portfolio: Vec<(DateTime<Utc>, f64)>,
+    }
+
+    #[test]
+    fn timestamps_go_out_as_utc_millis_and_series_carry_epoch_ms() {
+        // given in New York time, written out in UTC
+        let time = DateTime::parse_from_rfc3339("2025-07-15T09:30:00-04:00")
+            .expect("Expected a valid timestamp")
+            .with_timezone(&Utc);
+        let chart = Chart {
+            updated: Some(time),
+            portfolio: vec![(time, 10000.5)],
+        };
+
+        let json = serde_json::to_value(&chart).expect("Expected chart to serialize");
+        assert_eq!(json["updated"], "2025-07-15T13:30:00.000Z");
+        assert_eq!(json["portfolio"][0]["time"], "2025-07-15T13:30:00.000Z");
+        assert_eq!(
+            json["portfolio"][0]["time_epoch_ms"],
+            time.timestamp_millis()
+        );
+        assert_eq!(json["portfolio"][0]["value"], 10000.5);
+
+        let round_trip: Chart =
+            serde_json::from_value(json).expect("Expected chart to deserialize");
+        assert_eq!(round_trip.portfolio, vec![(time, 10000.5)]);
+    }
+}
@@ -0,0 +1,378 @@
+use std::fmt::Display;
+
+use serde::{Serialize, ser};
+
+/// Largest request body accepted unless MAX_REQUEST_BODY_BYTES says otherwise
+pub const DEFAULT_MAX_REQUEST_BODY_BYTES: usize = 1024 * 1024;
+
+/// Checks a deserialized payload before it is written - every float must be finite and every
+/// required (non-Option) string non-empty
+/// - works off the payload's Serialize impl so every model is covered without its own checks
+pub fn validate<T: Serialize + ?Sized>(payload: &T) -> Result<(), String> {
+    payload
+        .serialize(FieldValidator {
+            field: "",
+            optional: false,
+        })
+        .map_err(|err| err.0)
+}
+
+#[derive(Debug)]
+struct ValidationError(String);
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl ser::Error for ValidationError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ValidationError(msg.to_string())
+    }
+}
+
+/// Serializer that writes nothing and fails on the first invalid value
+/// - field is the name of the struct field being visited, optional whether it sits in a Some
+#[derive(Clone, Copy)]
+struct FieldValidator {
+    field: &'static str,
+    optional: bool,
+}
+
+impl FieldValidator {
+    fn element(self) -> Self {
+        FieldValidator {
+            optional: false,
+            ..self
+        }
+    }
+
+    fn float(self, value: f64) -> Result<(), ValidationError> {
+        if value.is_finite() {
+            Ok(())
+        } else {
+            Err(ValidationError(format!(
+                "{} must be a finite number, got {}",
+                self.field, value
+            )))
+        }
+    }
+}
+
+impl ser::Serializer for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_f32(self, v: f32) -> Result<(), ValidationError> {
+        self.float(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), ValidationError> {
+        self.float(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), ValidationError> {
+        if !self.optional && v.trim().is_empty() {
+            return Err(ValidationError(format!("{} must not be empty", self.field)));
+        }
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), ValidationError> {
+        value.serialize(FieldValidator {
+            optional: true,
+            ..self
+        })
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_i8(self, _v: i8) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_i16(self, _v: i16) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_i32(self, _v: i32) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_i64(self, _v: i64) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_u8(self, _v: u8) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_u16(self, _v: u16) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_u32(self, _v: u32) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_u64(self, _v: u64) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_char(self, _v: char) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_unit(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), ValidationError> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, ValidationError> {
+        Ok(self.element())
+    }
+}
+
+impl ser::SerializeSeq for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(*self)
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(*self)
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValidationError> {
+        value.serialize(*self)
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValidationError> {
+        value.serialize(*self)
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    // keys are whatever the map is keyed by, only the values are checked
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, _key: &T) -> Result<(), ValidationError> {
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValidationError> {
+        value.serialize(*self)
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(FieldValidator {
+            field: key,
+            optional: false,
+        })
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for FieldValidator {
+    type Ok = ();
+    type Error = ValidationError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        value.serialize(FieldValidator {
+            field: key,
+            optional: false,
+        })
+    }
+    fn end(self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Fill {
+        execution_id: String,
+        strategy: Option<String>,
+        price: Option<f64>,
+        quantity: f64,
+        legs: Vec<f64>,
+    }
+
+    fn fill() -> Fill {
+        Fill {
+            execution_id: "exec1".to_string(),
+            strategy: Some("strat".to_string()),
+            price: Some(101.5),
+            quantity: 10.0,
+            legs: vec![1.0, 2.0],
+        }
+    }
+
+    #[test]
+    fn finite_payload_with_keys_is_valid() {
+        assert_eq!(validate(&fill()), Ok(()));
+        assert_eq!(
+            validate(&Fill {
+                strategy: Some(String::new()),
+                price: None,
+                ..fill()
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers_are_rejected_wherever_they_sit() {
+        assert_eq!(
+            validate(&Fill {
+                price: Some(f64::NAN),
+                ..fill()
+            }),
+            Err("price must be a finite number, got NaN".to_string())
+        );
+        assert_eq!(
+            validate(&Fill {
+                quantity: f64::INFINITY,
+                ..fill()
+            }),
+            Err("quantity must be a finite number, got inf".to_string())
+        );
+        assert_eq!(
+            validate(&Fill {
+                legs: vec![1.0, f64::NEG_INFINITY],
+                ..fill()
+            }),
+            Err("legs must be a finite number, got -inf".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_required_string_is_rejected() {
+        assert_eq!(
+            validate(&Fill {
+                execution_id: "  ".to_string(),
+                ..fill()
+            }),
+            Err("execution_id must not be empty".to_string())
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use axum::{
+    Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{AppState, PauseStrategy, ResumeStrategy};
+
+/// Command the frontend sends over the websocket, tagged by "command" - e.g.
+/// {"command": "pause_strategy", "strategy": "strat_a", "graceful": true}
+/// - the connection was authenticated at the upgrade, so commands aren't re-authenticated
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum WsCommand {
+    PauseStrategy {
+        strategy: String,
+        graceful: bool,
+    },
+    ResumeStrategy {
+        strategy: String,
+    },
+    /// The overall portfolio value, as GET /get_portfolio returns it
+    Snapshot,
+}
+
+impl WsCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WsCommand::PauseStrategy { .. } => "pause_strategy",
+            WsCommand::ResumeStrategy { .. } => "resume_strategy",
+            WsCommand::Snapshot => "snapshot",
+        }
+    }
+}
+
+/// Runs command through the handler of the REST endpoint it mirrors
+pub async fn dispatch(state: AppState, command: WsCommand) -> Response {
+    match command {
+        WsCommand::PauseStrategy { strategy, graceful } => {
+            crate::pause_strategy(State(state), Json(PauseStrategy { strategy, graceful }))
+                .await
+                .into_response()
+        }
+        WsCommand::ResumeStrategy { strategy } => {
+            crate::resume_strategy(State(state), Json(ResumeStrategy { strategy }))
+                .await
+                .into_response()
+        }
+        WsCommand::Snapshot => crate::get_overall_portfolio_value(State(state))
+            .await
+            .into_response(),
+    }
+}
+
+/// Reply to an inbound websocket message: {"command", "status", "body"} with the handler's status
+/// and body (parsed if it is JSON), or a 400 if the message isn't a WsCommand
+pub async fn handle_message(state: AppState, text: &str) -> String {
+    let command = match serde_json::from_str::<WsCommand>(text) {
+        Ok(command) => command,
+        Err(err) => {
+            return serde_json::json!({
+                "command": null,
+                "status": 400,
+                "body": format!("Invalid websocket command: {}", err),
+            })
+            .to_string();
+        }
+    };
+    let name = command.name();
+    let response = dispatch(state, command).await;
+    let status = response.status().as_u16();
+    let body = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) => serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_else(|_| {
+            serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        }),
+        Err(err) => serde_json::Value::String(format!("Failed to read response: {}", err)),
+    };
+    serde_json::json!({ "command": name, "status": status, "body": body }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn pause_command_pauses_the_strategy_and_replies_with_its_status() {
+        let _lock = test_support::TEST_MUTEX.lock().await;
+        let db = test_support::pool().await;
+        sqlx::raw_sql(
+            "DELETE FROM trading.strategy WHERE strategy = 'ws_pause_strat';
+            INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('ws_pause_strat', 1000, 1000, 'active');",
+        )
+        .execute(&db)
+        .await
+        .expect("Expected to create strategy");
+
+        let reply = handle_message(
+            test_support::app_state(db.clone()),
+            r#"{"command": "pause_strategy", "strategy": "ws_pause_strat", "graceful": true}"#,
+        )
+        .await;
+        let status: String = sqlx::query_scalar(
+            "SELECT status::text FROM trading.strategy WHERE strategy = 'ws_pause_strat'",
+        )
+        .fetch_one(&db)
+        .await
+        .expect("Expected to read strategy status");
+        sqlx::query("DELETE FROM trading.strategy WHERE strategy = 'ws_pause_strat'")
+            .execute(&db)
+            .await
+            .expect("Expected to clean up strategy");
+
+        let reply: serde_json::Value =
+            serde_json::from_str(&reply).expect("Expected reply to be JSON");
+        assert_eq!(reply["command"], "pause_strategy");
+        // whatever the trading bot answered, the reply carries the handler's status
+        assert!(reply["status"].is_u64());
+        assert_eq!(status, "stopping");
+    }
+
+    #[tokio::test]
+    async fn unknown_command_is_a_bad_request() {
+        let db = sqlx::PgPool::connect_lazy("postgres://localhost/unused")
+            .expect("Expected lazy pool");
+
+        let reply = handle_message(
+            test_support::app_state(db),
+            r#"{"command": "liquidate_everything"}"#,
+        )
+        .await;
+
+        let reply: serde_json::Value =
+            serde_json::from_str(&reply).expect("Expected reply to be JSON");
+        assert_eq!(reply["command"], serde_json::Value::Null);
+        assert_eq!(reply["status"], 400);
+    }
+}
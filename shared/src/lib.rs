@@ -0,0 +1,5 @@
+//! Rules the trading app and the backend both apply, kept in one place so the equity the trading
+//! app snapshots and the portfolio value the backend reports can't drift apart
+pub mod marks;
+pub mod options;
+pub mod params;
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+
+/// (bar time, close)
+pub type BarClose = (DateTime<Utc>, f64);
+
+/// Close positions are marked at once bar is seen after latest: the newest bar's close - an older
+/// bar seen after it (e.g. one being backfilled) leaves it in place
+pub fn latest_close(latest: Option<BarClose>, bar: BarClose) -> BarClose {
+    match latest {
+        Some(latest) if latest.0 > bar.0 => latest,
+        _ => bar,
+    }
+}
+
+/// Close positions are marked at as of time, of bars in any order: the close of the newest bar
+/// starting at or before time
+pub fn close_as_of(bars: impl IntoIterator<Item = BarClose>, time: DateTime<Utc>) -> Option<f64> {
+    bars.into_iter()
+        .filter(|(bar_time, _)| *bar_time <= time)
+        .fold(None, |latest, bar| Some(latest_close(latest, bar)))
+        .map(|(_, close)| close)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn older_bar_keeps_the_newer_close() {
+        let bar_time = Utc.with_ymd_and_hms(2025, 7, 1, 14, 5, 0).unwrap();
+        let older = (bar_time - chrono::Duration::minutes(5), 99.0);
+
+        assert_eq!(
+            latest_close(Some((bar_time, 101.0)), older),
+            (bar_time, 101.0)
+        );
+        assert_eq!(
+            latest_close(Some(older), (bar_time, 101.0)),
+            (bar_time, 101.0)
+        );
+        assert_eq!(latest_close(None, older), older);
+    }
+
+    #[test]
+    fn close_as_of_ignores_bars_after_time() {
+        let time = Utc.with_ymd_and_hms(2025, 7, 1, 14, 5, 0).unwrap();
+        let bars = [
+            (time, 101.0),
+            (time - chrono::Duration::minutes(5), 99.0),
+            (time + chrono::Duration::minutes(5), 103.0),
+        ];
+
+        assert_eq!(close_as_of(bars, time), Some(101.0));
+        assert_eq!(
+            close_as_of(bars, time - chrono::Duration::minutes(1)),
+            Some(99.0)
+        );
+        assert_eq!(
+            close_as_of(bars, time - chrono::Duration::minutes(10)),
+            None
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Days, NaiveDate, TimeZone, Utc};
+use chrono_tz::America::New_York;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+/// Signed shares received when option_quantity (signed) contracts are exercised / assigned - a
+/// long call or a short put receives shares, a long put or a short call delivers them
+pub fn exercised_shares(right: OptionRight, multiplier: f64, option_quantity: f64) -> f64 {
+    let direction = match right {
+        OptionRight::Call => 1.0,
+        OptionRight::Put => -1.0,
+    };
+    direction * option_quantity * multiplier
+}
+
+/// Stock and cash an option position turns into when it is settled at expiry
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OptionSettlement {
+    /// Signed shares received - a short put assigned buys, a short call assigned sells
+    pub stock_quantity: f64,
+    /// Signed cash flow of buying / selling stock_quantity at the strike
+    pub cash: f64,
+}
+
+/// Settles quantity contracts of an option against the underlying's close on its expiry date, see
+/// settlement_cutoff
+/// - in the money positions are exercised (long) or assigned (short) at strike, anything else
+///   expires worthless and settles to nothing
+pub fn settle_option(
+    right: OptionRight,
+    strike: f64,
+    multiplier: f64,
+    quantity: f64,
+    underlying_close: f64,
+) -> OptionSettlement {
+    let in_the_money = match right {
+        OptionRight::Call => underlying_close > strike,
+        OptionRight::Put => underlying_close < strike,
+    };
+    if !in_the_money {
+        return OptionSettlement::default();
+    }
+    let stock_quantity = exercised_shares(right, multiplier, quantity);
+    OptionSettlement {
+        stock_quantity,
+        cash: -stock_quantity * strike,
+    }
+}
+
+/// End of an option's expiry date in New York - an option settles against the close of the
+/// underlying's last bar before it, not whatever the underlying traded at after expiry
+pub fn settlement_cutoff(expiry: NaiveDate) -> DateTime<Utc> {
+    let next_day = expiry
+        .checked_add_days(Days::new(1))
+        .unwrap_or(NaiveDate::MAX)
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default();
+    New_York
+        .from_local_datetime(&next_day)
+        .earliest()
+        .map(|cutoff| cutoff.with_timezone(&Utc))
+        .unwrap_or_else(|| next_day.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settlement_cutoff_is_midnight_in_new_york_after_expiry() {
+        let expiry = NaiveDate::from_ymd_opt(2025, 7, 18).unwrap();
+        assert_eq!(
+            settlement_cutoff(expiry),
+            Utc.with_ymd_and_hms(2025, 7, 19, 4, 0, 0).unwrap()
+        );
+        let winter_expiry = NaiveDate::from_ymd_opt(2025, 1, 17).unwrap();
+        assert_eq!(
+            settlement_cutoff(winter_expiry),
+            Utc.with_ymd_and_hms(2025, 1, 18, 5, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn short_put_in_the_money_is_assigned_at_strike() {
+        assert_eq!(
+            settle_option(OptionRight::Put, 100.0, 100.0, -1.0, 95.0),
+            OptionSettlement {
+                stock_quantity: 100.0,
+                cash: -10000.0,
+            }
+        );
+        assert_eq!(
+            settle_option(OptionRight::Put, 100.0, 100.0, -1.0, 100.0),
+            OptionSettlement::default()
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use serde_json::Value;
+
+/// Checks params against a params schema of { "name": "number" | "integer" | "string" | "boolean" }
+/// - the trading app checks stored params before load_params, the backend checks updates before
+///   storing them
+/// - params has to be an object and every key in it has to be declared in the schema with a
+///   value of the declared type
+/// - keys left out are fine: the strategy keeps its current value for them
+pub fn validate_params(schema: &Value, params: &Value) -> Result<(), String> {
+    let schema = schema
+        .as_object()
+        .ok_or("Params schema has to be a JSON object".to_string())?;
+    let params = params
+        .as_object()
+        .ok_or("Params have to be a JSON object".to_string())?;
+
+    for (name, value) in params {
+        let expected_type = schema
+            .get(name)
+            .ok_or(format!("Unknown param {}", name))?
+            .as_str()
+            .ok_or(format!("Schema type of param {} has to be a string", name))?;
+        let matches_type = match expected_type {
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            _ => {
+                return Err(format!(
+                    "Unsupported schema type {} for param {}",
+                    expected_type, name
+                ));
+            }
+        };
+        if !matches_type {
+            return Err(format!(
+                "Param {} has to be a {}, got {}",
+                name, expected_type, value
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn params_have_to_match_their_declared_types() {
+        let schema = json!({ "lookback": "integer", "enabled": "boolean" });
+        assert!(validate_params(&schema, &json!({ "lookback": 20, "enabled": true })).is_ok());
+        assert_eq!(
+            validate_params(&schema, &json!({ "enabled": "yes" })),
+            Err("Param enabled has to be a boolean, got \"yes\"".to_string())
+        );
+        assert_eq!(
+            validate_params(&json!({ "lookback": "decimal" }), &json!({ "lookback": 1 })),
+            Err("Unsupported schema type decimal for param lookback".to_string())
+        );
+    }
+}
@@ -2,9 +2,48 @@ extern crate proc_macro;
 use crud_insertable::DeriveInsertable;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{DeriveInput, Type, parse_macro_input};
+use syn::{DeriveInput, Field, Type, parse_macro_input};
 
-#[proc_macro_derive(ExtractPrimaryKeys)]
+/// Whether field is marked #[primary_key]
+fn is_primary_key(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("primary_key"))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Whether ty is a float, which isn't Hash / Eq on its own
+fn is_float(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .get_ident()
+            .is_some_and(|ident| ident == "f64" || ident == "f32"),
+        _ => false,
+    }
+}
+
+/// Whether any field is marked #[primary_key] - the primary key is then exactly the marked fields
+/// and every other field an update key, rather than the non-Option fields / the Option fields
+fn has_explicit_primary_keys(data: &syn::DataStruct) -> bool {
+    data.fields.iter().any(is_primary_key)
+}
+
+/// Primary keys are the fields marked #[primary_key] if any are, otherwise every non-Option field
+/// - #[primary_key] on an Option field is a compile error, a nullable primary key is almost
+///   always a mistake
+#[proc_macro_derive(ExtractPrimaryKeys, attributes(primary_key))]
 pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -16,9 +55,24 @@ pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
         _ => panic!("ExtractPrimaryKeys only works on Struct!"),
     };
 
+    if let Some(field) = data
+        .fields
+        .iter()
+        .find(|field| is_primary_key(field) && is_option(&field.ty))
+    {
+        return syn::Error::new_spanned(
+            &field.ty,
+            "#[primary_key] can't be placed on an Option field - primary keys can't be null",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let explicit = has_explicit_primary_keys(data);
+
     let primary_key_fields: Vec<_> = data
         .fields
         .iter()
+        .filter(|field| !explicit || is_primary_key(field))
         .filter_map(|field| {
             let serde_attrs: Vec<_> = field
                 .attrs
@@ -42,6 +96,28 @@ pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
         })
         .collect();
 
+    // the primary keys as a tuple in declaration order, floats wrapped in OrderedFloat so the
+    // tuple is Hash + Eq and can key a HashMap
+    let tuple_name = syn::Ident::new(&format!("{}KeyTuple", name), name.span());
+    let tuple_doc = format!("Primary keys of {} as returned by as_key_tuple", name);
+    let (tuple_types, tuple_values): (Vec<_>, Vec<_>) = data
+        .fields
+        .iter()
+        .filter(|field| !is_option(&field.ty) && (!explicit || is_primary_key(field)))
+        .map(|field| {
+            let field_name = &field.ident;
+            let ty = &field.ty;
+            if is_float(ty) {
+                (
+                    quote! { ::ordered_float::OrderedFloat<#ty> },
+                    quote! { ::ordered_float::OrderedFloat(self.#field_name) },
+                )
+            } else {
+                (quote! { #ty }, quote! { self.#field_name.clone() })
+            }
+        })
+        .unzip();
+
     quote! {
     #[derive(
         Debug, Clone, Serialize, Deserialize, FromRow, DeriveInsertable
@@ -49,11 +125,25 @@ pub fn extract_primary_keys(input: TokenStream) -> TokenStream {
             pub struct #new_name {
                #(#primary_key_fields),*
             }
+
+            #[doc = #tuple_doc]
+            pub type #tuple_name = (#(#tuple_types,)*);
+
+            impl #new_name {
+                /// The primary keys in declaration order, for keying HashMaps / HashSets
+                pub fn as_key_tuple(&self) -> #tuple_name {
+                    (#(#tuple_values,)*)
+                }
+            }
         }
     .into()
 }
 
-#[proc_macro_derive(ExtractFullKeys)]
+/// Full keys are every field with its Option stripped, so nullable columns are only those declared
+/// Option<Option<T>> - if any field is marked #[primary_key], fields are kept as declared instead
+/// (Option only for the nullable columns)
+/// - the marked fields stay marked, so DeriveInsertable on the full keys conflicts on them alone
+#[proc_macro_derive(ExtractFullKeys, attributes(primary_key))]
 pub fn extract_full_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -65,6 +155,8 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
         _ => panic!("ExtractFullKeys only works on Struct!"),
     };
 
+    let explicit = has_explicit_primary_keys(data);
+
     let full_key_fields: Vec<_> = data
         .fields
         .iter()
@@ -76,6 +168,16 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
                 .cloned()
                 .collect();
 
+            if explicit {
+                let field_name = &field.ident;
+                let ty = &field.ty;
+                let primary_key_attr = is_primary_key(field).then(|| quote! { #[primary_key] });
+                return Some(quote! {
+                    #(#serde_attrs)*
+                    #primary_key_attr
+                    pub #field_name : #ty
+                });
+            }
             if let Type::Path(ref type_path) = field.ty {
                 if let Some(segment) = type_path.path.segments.last() {
                     if segment.ident == "Option" {
@@ -112,7 +214,9 @@ pub fn extract_full_keys(input: TokenStream) -> TokenStream {
     .into()
 }
 
-#[proc_macro_derive(ExtractUpdateKeys)]
+/// Update keys are every field not marked #[primary_key] if any field is, as an Option, otherwise
+/// every Option field
+#[proc_macro_derive(ExtractUpdateKeys, attributes(primary_key))]
 pub fn extract_update_keys(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -123,10 +227,12 @@ pub fn extract_update_keys(input: TokenStream) -> TokenStream {
         syn::Data::Struct(ref s) => s,
         _ => panic!("ExtractUpdateKeys only works on Struct!"),
     };
+    let explicit = has_explicit_primary_keys(data);
 
     let update_key_fields: Vec<_> = data
         .fields
         .iter()
+        .filter(|field| !explicit || !is_primary_key(field))
         .filter_map(|field| {
             let serde_attrs: Vec<_> = field
                 .attrs
@@ -135,6 +241,15 @@ pub fn extract_update_keys(input: TokenStream) -> TokenStream {
                 .cloned()
                 .collect();
 
+            if explicit && !is_option(&field.ty) {
+                // still optional in an update, a data column is only set if it is given
+                let field_name = &field.ident;
+                let ty = &field.ty;
+                return Some(quote! {
+                    #(#serde_attrs)*
+                    pub #field_name : Option<#ty>
+                });
+            }
             if let Type::Path(ref type_path) = field.ty {
                 if let Some(segment) = type_path.path.segments.last() {
                     if segment.ident == "Option" {
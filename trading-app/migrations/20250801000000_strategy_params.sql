@@ -0,0 +1,8 @@
+-- Strategy params table
+-- - params: tunable parameters of the strategy, loaded through StrategyExecutor::load_params
+-- - params_schema: expected shape of params, registered by the trading app - { "name": "number" | "integer" | "string" | "boolean" }
+CREATE TABLE trading.strategy_params (
+    strategy VARCHAR(50) PRIMARY KEY REFERENCES trading.strategy(strategy) ON DELETE CASCADE,
+    params JSONB NOT NULL DEFAULT '{}'::jsonb,
+    params_schema JSONB NOT NULL DEFAULT '{}'::jsonb
+);
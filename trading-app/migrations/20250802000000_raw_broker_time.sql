@@ -0,0 +1,4 @@
+-- Untransformed IBKR execution time, stored alongside the normalized time for debugging timezone
+-- handling - only populated when the trading app runs with STORE_RAW_BROKER_TIME=true
+ALTER TABLE trading.stock_transactions ADD COLUMN raw_broker_time TEXT;
+ALTER TABLE trading.option_transactions ADD COLUMN raw_broker_time TEXT;
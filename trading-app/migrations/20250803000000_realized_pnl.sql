@@ -0,0 +1,8 @@
+-- Realized PnL of a fill, computed against the position it traded against when the execution is
+-- booked - realized_pnl stays NULL for fills opening / adding to a position
+ALTER TABLE trading.stock_transactions
+    ADD COLUMN closes_position BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN realized_pnl DOUBLE PRECISION;
+ALTER TABLE trading.option_transactions
+    ADD COLUMN closes_position BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN realized_pnl DOUBLE PRECISION;
@@ -0,0 +1,9 @@
+-- Point in time marked value of each strategy, written periodically by the trading app
+-- - value: cash (initial_capital less everything spent on transactions) + positions marked at
+--   their last bar close
+CREATE TABLE trading.equity_snapshots (
+    strategy VARCHAR(50) NOT NULL REFERENCES trading.strategy(strategy) ON DELETE CASCADE,
+    time TIMESTAMPTZ NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (strategy, time)
+);
@@ -0,0 +1,23 @@
+-- Every order placed by the trading app with the strategy / contract it was placed for, so
+-- OrderEngine's order_map can be rehydrated after a restart
+-- - kept after the order fills / cancels, unlike the open order tables
+-- - option_right: the contract's right (C / P) as placed, '' for anything but options
+CREATE TABLE trading.order_map (
+    order_id INTEGER PRIMARY KEY,
+    strategy VARCHAR(50) NOT NULL REFERENCES trading.strategy(strategy) ON DELETE CASCADE,
+    time TIMESTAMPTZ NOT NULL,
+
+    security_type VARCHAR(10) NOT NULL,
+    stock VARCHAR(50) NOT NULL,
+    primary_exchange VARCHAR(50) NOT NULL,
+    exchange VARCHAR(50) NOT NULL,
+    currency VARCHAR(10) NOT NULL,
+    expiry VARCHAR(50) NOT NULL,
+    strike DOUBLE PRECISION NOT NULL,
+    multiplier VARCHAR(50) NOT NULL,
+    option_right VARCHAR(10) NOT NULL,
+
+    action VARCHAR(10) NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX order_map_strategy ON trading.order_map(strategy);
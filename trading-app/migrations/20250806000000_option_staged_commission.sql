@@ -0,0 +1,19 @@
+-- apply_staged_commission_options matched on trading.stock_transactions, which isn't in the
+-- UPDATE, so every insert into trading.option_transactions failed
+CREATE OR REPLACE FUNCTION trading.apply_staged_commission_options()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Try to apply a matching staged commission
+    UPDATE trading.option_transactions
+    SET fees = sc.fees
+    FROM trading.staged_commissions sc
+    WHERE trading.option_transactions.execution_id = NEW.execution_id
+        AND sc.execution_id = NEW.execution_id;
+
+    -- Delete the staging row if matched
+    DELETE FROM trading.staged_commissions
+    WHERE execution_id = NEW.execution_id;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
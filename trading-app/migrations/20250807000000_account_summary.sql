@@ -0,0 +1,16 @@
+-- IBKR's account level figures, recorded by the trading app at the session open and close
+-- - to cross-check the computed portfolio values against the broker's net liquidation
+-- - amounts are in currency, the account's base currency at IBKR
+CREATE TABLE trading.account_summary (
+    account VARCHAR(20) NOT NULL,
+    time TIMESTAMPTZ NOT NULL,
+    currency VARCHAR(3),
+    net_liquidation DOUBLE PRECISION,
+    total_cash_value DOUBLE PRECISION,
+    buying_power DOUBLE PRECISION,
+    available_funds DOUBLE PRECISION,
+    excess_liquidity DOUBLE PRECISION,
+    init_margin_req DOUBLE PRECISION,
+    maint_margin_req DOUBLE PRECISION,
+    PRIMARY KEY (account, time)
+);
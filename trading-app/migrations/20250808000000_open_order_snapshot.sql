@@ -0,0 +1,6 @@
+-- Contract / Order of an open order serialized when it is recorded - as submitted for orders
+-- placed by the trading app, as reported by IBKR for ones picked up by sync_open_orders - so how
+-- an order was built can be recovered after the fact
+-- - NULL where serializing failed
+ALTER TABLE trading.open_stock_orders ADD COLUMN order_snapshot JSONB;
+ALTER TABLE trading.open_option_orders ADD COLUMN order_snapshot JSONB;
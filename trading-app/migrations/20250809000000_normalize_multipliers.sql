@@ -0,0 +1,82 @@
+-- Rows written before multipliers were normalised on the way in can spell one contract as both
+-- '100' and '100.0', splitting it across two primary keys. Rewrite every stored multiplier to
+-- normalize_multiplier's canonical form
+-- - numbers: trailing zeros (and a bare trailing '.') trimmed, anything else only trimmed
+-- - current positions under several spellings are merged: quantities summed, avg_price weighted
+-- - targets / bars under several spellings keep the canonical row (else the first) and drop the rest
+CREATE OR REPLACE FUNCTION pg_temp.normalize_multiplier(multiplier TEXT) RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN btrim(multiplier) ~ '^([0-9]+(\.[0-9]*)?|\.[0-9]+)$'
+            THEN trim_scale(btrim(multiplier)::NUMERIC)::TEXT
+        ELSE btrim(multiplier)
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Current option positions
+WITH denormalized AS (
+    DELETE FROM trading.current_option_positions
+    WHERE multiplier <> pg_temp.normalize_multiplier(multiplier)
+    RETURNING *
+)
+INSERT INTO trading.current_option_positions
+    (strategy, stock, primary_exchange, avg_price, quantity, expiry, strike, multiplier, option_type)
+SELECT
+    strategy, stock, primary_exchange,
+    COALESCE(SUM(avg_price * quantity) / NULLIF(SUM(quantity), 0), MAX(avg_price)),
+    SUM(quantity),
+    expiry, strike, pg_temp.normalize_multiplier(multiplier), option_type
+FROM denormalized
+GROUP BY strategy, stock, primary_exchange, expiry, strike, pg_temp.normalize_multiplier(multiplier), option_type
+ON CONFLICT (strategy, stock, primary_exchange, expiry, strike, multiplier, option_type) DO UPDATE SET
+    avg_price = COALESCE(
+        (current_option_positions.avg_price * current_option_positions.quantity
+            + EXCLUDED.avg_price * EXCLUDED.quantity)
+            / NULLIF(current_option_positions.quantity + EXCLUDED.quantity, 0),
+        EXCLUDED.avg_price
+    ),
+    quantity = current_option_positions.quantity + EXCLUDED.quantity;
+
+-- Target option positions
+DELETE FROM trading.target_option_positions a
+USING trading.target_option_positions b
+WHERE a.ctid <> b.ctid
+    AND a.strategy = b.strategy
+    AND a.stock = b.stock
+    AND a.primary_exchange = b.primary_exchange
+    AND a.expiry = b.expiry
+    AND a.strike = b.strike
+    AND a.option_type = b.option_type
+    AND pg_temp.normalize_multiplier(a.multiplier) = pg_temp.normalize_multiplier(b.multiplier)
+    AND a.multiplier <> pg_temp.normalize_multiplier(a.multiplier)
+    AND (b.multiplier = pg_temp.normalize_multiplier(b.multiplier) OR b.ctid < a.ctid);
+UPDATE trading.target_option_positions
+SET multiplier = pg_temp.normalize_multiplier(multiplier)
+WHERE multiplier <> pg_temp.normalize_multiplier(multiplier);
+
+-- Historical option bars
+DELETE FROM market_data.historical_options_data a
+USING market_data.historical_options_data b
+WHERE a.ctid <> b.ctid
+    AND a.stock = b.stock
+    AND a.primary_exchange = b.primary_exchange
+    AND a.time = b.time
+    AND a.expiry = b.expiry
+    AND a.strike = b.strike
+    AND a.option_type = b.option_type
+    AND pg_temp.normalize_multiplier(a.multiplier) = pg_temp.normalize_multiplier(b.multiplier)
+    AND a.multiplier <> pg_temp.normalize_multiplier(a.multiplier)
+    AND (b.multiplier = pg_temp.normalize_multiplier(b.multiplier) OR b.ctid < a.ctid);
+UPDATE market_data.historical_options_data
+SET multiplier = pg_temp.normalize_multiplier(multiplier)
+WHERE multiplier <> pg_temp.normalize_multiplier(multiplier);
+
+-- Tables without the multiplier in their primary key
+UPDATE trading.open_option_orders
+SET multiplier = pg_temp.normalize_multiplier(multiplier)
+WHERE multiplier <> pg_temp.normalize_multiplier(multiplier);
+UPDATE trading.option_transactions
+SET multiplier = pg_temp.normalize_multiplier(multiplier)
+WHERE multiplier <> pg_temp.normalize_multiplier(multiplier);
+UPDATE trading.order_map
+SET multiplier = pg_temp.normalize_multiplier(multiplier)
+WHERE multiplier <> pg_temp.normalize_multiplier(multiplier);
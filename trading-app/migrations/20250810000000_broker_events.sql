@@ -0,0 +1,15 @@
+-- Raw order / execution / commission events as IBKR sent them, kept for post-trade forensics
+-- - order_id: the order the event is about - a commission report carries only its execution_id,
+--   so it is filed under the order of that execution
+-- - execution_id: set on execution and commission events, '' on order events
+CREATE TABLE trading.broker_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type VARCHAR(20) NOT NULL CHECK (event_type IN ('execution', 'order', 'commission')),
+    order_id INTEGER,
+    execution_id VARCHAR(50) NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    payload JSONB NOT NULL
+);
+CREATE INDEX broker_events_time ON trading.broker_events(time);
+CREATE INDEX broker_events_order_id ON trading.broker_events(order_id);
+CREATE INDEX broker_events_execution_id ON trading.broker_events(execution_id);
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use ibapi::Client;
+use sqlx::PgPool;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{
+    execution::{
+        blocking_pool::BlockingPool,
+        cancel::{CancelRequest, CancelResponse, cancel_open_order},
+        preview::{PreviewOrder, RiskLimits, preview_target_stock_positions},
+    },
+    market_data::freshness::{MarketDataFreshness, MarketDataHealth},
+    strategy::backtest::{
+        BacktestComparison, ComparisonTolerance, Trade, compare_backtest_vs_live,
+    },
+};
+
+/// Endpoints the backend calls into the trading app with (TRADING_BOT_URL)
+#[derive(Clone)]
+struct ApiState {
+    pool: PgPool,
+    client: Arc<Client>,
+    blocking_pool: Arc<BlockingPool>,
+    risk_limits: Arc<RiskLimits>,
+    market_data_health: MarketDataHealth,
+}
+
+#[derive(Debug, Deserialize)]
+struct StrategyQuery {
+    strategy: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BacktestCompareRequest {
+    strategy: String,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    /// Trades the backtest generated over [from, to)
+    trades: Vec<Trade>,
+    #[serde(default)]
+    tolerance: ComparisonTolerance,
+}
+
+/// Serves the trading app's endpoints until the task is aborted - one per session, since the
+/// IBKR client only lives as long as the session
+pub async fn serve(
+    pool: PgPool,
+    client: Arc<Client>,
+    blocking_pool: Arc<BlockingPool>,
+    risk_limits: RiskLimits,
+    market_data_health: MarketDataHealth,
+    addr: String,
+) -> Result<(), String> {
+    let app = Router::new()
+        .route("/cancel-order", post(cancel_order))
+        .route(
+            "/target_stock_positions/validate",
+            post(validate_target_stock_positions),
+        )
+        .route("/backtest/compare", post(compare_backtest))
+        .route("/health/market_data", get(get_market_data_health))
+        .with_state(ApiState {
+            pool,
+            client,
+            blocking_pool,
+            risk_limits: Arc::new(risk_limits),
+            market_data_health,
+        });
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .map_err(|e| format!("Failed to bind trading app api to {}: {}", addr, e))?;
+    tracing::info!("Trading app api listening on {}", addr);
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| format!("Trading app api stopped: {}", e))
+}
+
+/// POST /cancel-order
+async fn cancel_order(
+    State(state): State<ApiState>,
+    Json(request): Json<CancelRequest>,
+) -> Result<Json<CancelResponse>, (StatusCode, String)> {
+    cancel_open_order(
+        state.pool,
+        state.blocking_pool.as_ref(),
+        state.client,
+        &request,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| {
+        tracing::error!("Error cancelling order: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e)
+    })
+}
+
+/// POST /target_stock_positions/validate?strategy=...
+/// - previews the orders the strategy's targets would place, nothing is sent to IBKR
+async fn validate_target_stock_positions(
+    State(state): State<ApiState>,
+    Query(query): Query<StrategyQuery>,
+) -> Result<Json<Vec<PreviewOrder>>, (StatusCode, String)> {
+    preview_target_stock_positions(state.pool, query.strategy, state.risk_limits.as_ref())
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Error previewing target stock positions: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e)
+        })
+}
+
+/// POST /backtest/compare
+/// - reports where the strategy's live trades diverge from the backtest trades posted
+async fn compare_backtest(
+    State(state): State<ApiState>,
+    Json(request): Json<BacktestCompareRequest>,
+) -> Result<Json<BacktestComparison>, (StatusCode, String)> {
+    compare_backtest_vs_live(
+        state.pool,
+        request.strategy,
+        request.from,
+        request.to,
+        &request.trades,
+        &request.tolerance,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| {
+        tracing::error!("Error comparing backtest against live trades: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e)
+    })
+}
+
+/// GET /health/market_data
+/// - degraded if the newest bar is older than the configured threshold during market hours
+async fn get_market_data_health(State(state): State<ApiState>) -> Json<MarketDataFreshness> {
+    Json(state.market_data_health.check())
+}
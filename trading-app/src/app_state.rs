@@ -0,0 +1,264 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        instance_lock::InstanceLockMode,
+        models_crud::historical_data::{DEFAULT_LAST_CLOSE_TTL, LastCloseCache},
+    },
+    execution::{
+        blocking_pool::BlockingPool, contract_validation::ContractValidationCache,
+        events::on_execution_updates::DEFAULT_UNKNOWN_STRATEGY, netting::PartialFillPolicy,
+        preview::RiskLimits, sync::SyncOptions,
+    },
+    ibc::LoginBackoff,
+    market_data::{
+        consolidator::DEFAULT_MAX_RETAINED_BARS,
+        freshness::{DEFAULT_MARKET_DATA_STALE_AFTER, LastBarTime},
+        market_hours::{Clock, SessionOpenGate, SystemClock},
+    },
+};
+
+/// Settings for a trading session, read once at startup
+#[derive(Debug, Clone)]
+pub struct TradingConfig {
+    pub database_url: String,
+    /// host:port of the IB Gateway the clients connect to
+    pub gateway_address: String,
+    /// Address the strategy API listens on
+    pub api_address: String,
+    pub sync_options: SyncOptions,
+    pub risk_limits: RiskLimits,
+    pub open_gate: SessionOpenGate,
+    pub equity_snapshot_interval: Duration,
+    /// Threads placing / cancelling orders at IBKR
+    pub blocking_threads: usize,
+    pub login_backoff: LoginBackoff,
+    /// Backend endpoint alerts are posted to
+    pub notification_url: String,
+    pub instance_lock: InstanceLockMode,
+    /// Minutes before the close flatten_at_close strategies have their targets zeroed
+    pub flatten_minutes_before_close: u32,
+    /// How long the session waits at the close for flatten_at_close strategies to go flat
+    pub flatten_fill_timeout: Duration,
+    /// Whether placed orders are written to trading.order_map and reloaded on the next start
+    pub persist_order_map: bool,
+    /// Most 5 second bars the consolidator keeps per live contract
+    pub max_retained_bars: usize,
+    /// How long a stock's last close is reused for marking positions before it is read again
+    pub last_close_ttl: Duration,
+    /// Strategy executions without an open order and unexplained position discrepancies are
+    /// booked to (e.g. unknown:acct1 to keep accounts apart)
+    pub unknown_strategy: String,
+    /// Age the newest bar may reach during market hours before market data is reported degraded
+    pub market_data_stale_after: Duration,
+    /// Fraction of their targets strategies are traded at, e.g. 0.1 to trade live at a tenth of
+    /// the paper size
+    pub order_size_multiplier: f64,
+    /// Whether each session starts by checking the 5 sec -> 5 min bar aggregation against a
+    /// synthetic session (see aggregation_self_test), for test / staging deployments
+    pub aggregation_self_test: bool,
+    /// Most orders each strategy may place in a session before the rest are suppressed - None
+    /// for no limit
+    pub max_orders_per_strategy: Option<u32>,
+    /// What happens to partially filled working orders when their diff is re-evaluated
+    pub partial_fill_policy: PartialFillPolicy,
+    /// Decimal places stock order quantities are rounded to - 0 unless the account allows
+    /// fractional shares
+    pub share_quantity_decimals: u32,
+}
+
+impl TradingConfig {
+    /// Config pointing at database_url with every other setting at its default
+    pub fn new(database_url: impl Into<String>) -> Self {
+        Self {
+            database_url: database_url.into(),
+            gateway_address: "127.0.0.1:4002".to_string(),
+            api_address: "0.0.0.0:8000".to_string(),
+            sync_options: SyncOptions::default(),
+            risk_limits: RiskLimits::default(),
+            open_gate: SessionOpenGate::default(),
+            // one snapshot per 5 minute bar
+            equity_snapshot_interval: Duration::from_secs(300),
+            blocking_threads: 4,
+            login_backoff: LoginBackoff::default(),
+            notification_url: "http://localhost:3000/send_notification".to_string(),
+            instance_lock: InstanceLockMode::default(),
+            flatten_minutes_before_close: 10,
+            flatten_fill_timeout: Duration::from_secs(120),
+            persist_order_map: true,
+            max_retained_bars: DEFAULT_MAX_RETAINED_BARS,
+            last_close_ttl: DEFAULT_LAST_CLOSE_TTL,
+            unknown_strategy: DEFAULT_UNKNOWN_STRATEGY.to_string(),
+            market_data_stale_after: DEFAULT_MARKET_DATA_STALE_AFTER,
+            order_size_multiplier: 1.0,
+            aggregation_self_test: false,
+            max_orders_per_strategy: None,
+            partial_fill_policy: PartialFillPolicy::default(),
+            share_quantity_decimals: 0,
+        }
+    }
+
+    /// Reads DATABASE_URL (required), IBKR_GATEWAY_ADDRESS, API_ADDRESS, NOTIFICATION_URL,
+    /// EQUITY_SNAPSHOT_INTERVAL_SECS, FLATTEN_MINUTES_BEFORE_CLOSE, FLATTEN_FILL_TIMEOUT_SECS,
+    /// PERSIST_ORDER_MAP, MAX_RETAINED_BARS, LAST_CLOSE_TTL_SECS, UNKNOWN_STRATEGY,
+    /// MARKET_DATA_STALE_AFTER_SECS, ORDER_SIZE_MULTIPLIER, AGGREGATION_SELF_TEST,
+    /// MAX_ORDERS_PER_STRATEGY, PARTIAL_FILL_POLICY (wait, chase or cancel_and_reprice) and
+    /// SHARE_QUANTITY_DECIMALS, plus the variables each section reads in its own from_env
+    pub fn from_env() -> Result<Self, String> {
+        let database_url = std::env::var("DATABASE_URL")
+            .map_err(|_| "DATABASE_URL environment variable must be set".to_string())?;
+        let mut config = Self::new(database_url);
+        if let Ok(address) = std::env::var("IBKR_GATEWAY_ADDRESS") {
+            config.gateway_address = address;
+        }
+        if let Ok(address) = std::env::var("API_ADDRESS") {
+            config.api_address = address;
+        }
+        if let Ok(url) = std::env::var("NOTIFICATION_URL") {
+            config.notification_url = url;
+        }
+        if let Ok(secs) = std::env::var("EQUITY_SNAPSHOT_INTERVAL_SECS") {
+            config.equity_snapshot_interval =
+                Duration::from_secs(secs.trim().parse::<u64>().map_err(|e| {
+                    format!(
+                        "EQUITY_SNAPSHOT_INTERVAL_SECS must be a number of seconds: {}",
+                        e
+                    )
+                })?);
+        }
+        if let Ok(minutes) = std::env::var("FLATTEN_MINUTES_BEFORE_CLOSE") {
+            config.flatten_minutes_before_close = minutes.trim().parse::<u32>().map_err(|e| {
+                format!(
+                    "FLATTEN_MINUTES_BEFORE_CLOSE must be a number of minutes: {}",
+                    e
+                )
+            })?;
+        }
+        if let Ok(secs) = std::env::var("FLATTEN_FILL_TIMEOUT_SECS") {
+            config.flatten_fill_timeout =
+                Duration::from_secs(secs.trim().parse::<u64>().map_err(|e| {
+                    format!(
+                        "FLATTEN_FILL_TIMEOUT_SECS must be a number of seconds: {}",
+                        e
+                    )
+                })?);
+        }
+        if let Ok(flag) = std::env::var("PERSIST_ORDER_MAP") {
+            config.persist_order_map = flag
+                .trim()
+                .parse::<bool>()
+                .map_err(|e| format!("PERSIST_ORDER_MAP must be true or false: {}", e))?;
+        }
+        if let Ok(bars) = std::env::var("MAX_RETAINED_BARS") {
+            config.max_retained_bars = bars
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("MAX_RETAINED_BARS must be a number of bars: {}", e))?;
+        }
+        if let Ok(secs) = std::env::var("LAST_CLOSE_TTL_SECS") {
+            config.last_close_ttl =
+                Duration::from_secs(secs.trim().parse::<u64>().map_err(|e| {
+                    format!("LAST_CLOSE_TTL_SECS must be a number of seconds: {}", e)
+                })?);
+        }
+        if let Ok(strategy) = std::env::var("UNKNOWN_STRATEGY") {
+            if strategy.trim().is_empty() {
+                return Err("UNKNOWN_STRATEGY must name a strategy".to_string());
+            }
+            config.unknown_strategy = strategy.trim().to_string();
+        }
+        if let Ok(secs) = std::env::var("MARKET_DATA_STALE_AFTER_SECS") {
+            config.market_data_stale_after =
+                Duration::from_secs(secs.trim().parse::<u64>().map_err(|e| {
+                    format!(
+                        "MARKET_DATA_STALE_AFTER_SECS must be a number of seconds: {}",
+                        e
+                    )
+                })?);
+        }
+        if let Ok(multiplier) = std::env::var("ORDER_SIZE_MULTIPLIER") {
+            let multiplier = multiplier
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("ORDER_SIZE_MULTIPLIER must be a number: {}", e))?;
+            if !(multiplier > 0.0 && multiplier.is_finite()) {
+                return Err("ORDER_SIZE_MULTIPLIER must be greater than 0".to_string());
+            }
+            config.order_size_multiplier = multiplier;
+        }
+        if let Ok(flag) = std::env::var("AGGREGATION_SELF_TEST") {
+            config.aggregation_self_test = flag
+                .trim()
+                .parse::<bool>()
+                .map_err(|e| format!("AGGREGATION_SELF_TEST must be true or false: {}", e))?;
+        }
+        if let Ok(max_orders) = std::env::var("MAX_ORDERS_PER_STRATEGY") {
+            let max_orders = max_orders.trim().parse::<u32>().map_err(|e| {
+                format!("MAX_ORDERS_PER_STRATEGY must be a number of orders: {}", e)
+            })?;
+            config.max_orders_per_strategy = Some(max_orders);
+        }
+        if let Ok(policy) = std::env::var("PARTIAL_FILL_POLICY") {
+            config.partial_fill_policy = policy.parse::<PartialFillPolicy>()?;
+        }
+        if let Ok(decimals) = std::env::var("SHARE_QUANTITY_DECIMALS") {
+            config.share_quantity_decimals = decimals.trim().parse::<u32>().map_err(|e| {
+                format!(
+                    "SHARE_QUANTITY_DECIMALS must be a number of decimal places: {}",
+                    e
+                )
+            })?;
+        }
+        config.sync_options = SyncOptions::from_env()?;
+        config.risk_limits = RiskLimits::from_env()?;
+        config.open_gate = SessionOpenGate::from_env()?;
+        config.blocking_threads = BlockingPool::size_from_env()?;
+        config.login_backoff = LoginBackoff::from_env()?;
+        config.instance_lock = InstanceLockMode::from_env()?;
+        Ok(config)
+    }
+}
+
+/// Everything shared across a trading session - built once per session and handed to the
+/// consolidator, order engine and strategies instead of cloning each piece around separately
+#[derive(Clone)]
+pub struct TradingAppState {
+    pub pool: PgPool,
+    pub config: Arc<TradingConfig>,
+    pub clock: Arc<dyn Clock + Send + Sync>,
+    pub blocking_pool: Arc<BlockingPool>,
+    /// Last close per stock, recorded by the consolidator and read when marking positions
+    pub last_closes: LastCloseCache,
+    /// Newest real time bar across the session's live contracts, for the market data health check
+    pub last_bar_time: LastBarTime,
+    /// Contracts validated against IBKR so far, shared by strategy registration and the
+    /// consolidator
+    pub validated_contracts: ContractValidationCache,
+}
+
+impl TradingAppState {
+    pub fn new(pool: PgPool, config: TradingConfig) -> Self {
+        Self::with_clock(pool, config, Arc::new(SystemClock))
+    }
+
+    /// State reading time from clock - for running a session against a fixed / simulated time
+    pub fn with_clock(
+        pool: PgPool,
+        config: TradingConfig,
+        clock: Arc<dyn Clock + Send + Sync>,
+    ) -> Self {
+        let blocking_pool = Arc::new(BlockingPool::new(config.blocking_threads));
+        let last_closes = LastCloseCache::new(config.last_close_ttl);
+        Self {
+            pool,
+            config: Arc::new(config),
+            clock,
+            blocking_pool,
+            last_closes,
+            last_bar_time: LastBarTime::new(),
+            validated_contracts: ContractValidationCache::new(),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{future::Future, sync::Arc};
+
+use tokio::{
+    sync::mpsc::{Sender, channel},
+    task::JoinHandle,
+    time::{Duration, Instant},
+};
+use tokio_postgres::NoTls;
+
+const BATCH_SIZE: usize = 200_000;
+const MAX_BATCH_WAIT_MS: u64 = 1000;
+
+/// Table written to in batches by the task init_batch_channel spawns
+pub trait BatchFlush {
+    type Row: Send + Sync + 'static;
+
+    /// Writes batch in one go (e.g. COPY into a staging table + merge)
+    fn flush_batch(
+        client: &mut tokio_postgres::Client,
+        batch: &[Self::Row],
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+}
+
+/// Flushes buffer through W and clears it - a failed flush is logged and its rows dropped
+async fn flush_buffer<W: BatchFlush>(
+    client: &mut tokio_postgres::Client,
+    buffer: &mut Vec<W::Row>,
+) {
+    if buffer.is_empty() {
+        return;
+    }
+    match W::flush_batch(client, buffer).await {
+        Ok(()) => tracing::info!("Flushed batch of {} rows", buffer.len()),
+        Err(e) => tracing::error!("Expected to be able to flush batch: \n{}", e),
+    }
+    buffer.clear();
+}
+
+/// Connects to the database and spawns the task batching rows sent on the returned sender into
+/// W::flush_batch - flushed every BATCH_SIZE rows or MAX_BATCH_WAIT_MS, whichever comes first
+/// - sending true on the shutdown sender flushes the rows queued so far and ends the task, as
+///   does dropping every handle to either sender
+pub async fn init_batch_channel<W: BatchFlush + 'static>()
+-> (Arc<Sender<W::Row>>, Arc<Sender<bool>>, JoinHandle<()>) {
+    let host = std::env::var("DATABASE_HOST")
+        .expect("Expected DATABASE_HOST environment variable to be set!");
+
+    let (mut client, connection) = tokio_postgres::connect(
+        &format!(
+            "host={} user=ryantan password=admin dbname=trading_system",
+            host
+        ),
+        NoTls,
+    )
+    .await
+    .expect("Expected to be able to make tokio_postgres connection");
+    tracing::info!("INIT CHANNEL");
+
+    // spawn connection task so client works
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("connection error: {e}");
+        }
+    });
+
+    let (sender, mut rx) = channel::<W::Row>(10_000);
+    let (shutdown_sender, mut shutdown_rx) = channel::<bool>(2);
+
+    let flush_task = tokio::spawn(async move {
+        let mut buffer = Vec::with_capacity(BATCH_SIZE);
+        let mut last_flush = Instant::now();
+
+        loop {
+            tokio::select! {
+                maybe_row = rx.recv() => {
+                    match maybe_row {
+                        Some(row) => {
+                            buffer.push(row);
+                            if buffer.len() >= BATCH_SIZE {
+                                flush_buffer::<W>(&mut client, &mut buffer).await;
+                                last_flush = Instant::now();
+                            }
+                        }
+                        None => {
+                            flush_buffer::<W>(&mut client, &mut buffer).await;
+                            break;
+                        }
+                    }
+                }
+                maybe_shutdown = shutdown_rx.recv() => {
+                    // None - every handle to the channel was dropped without closing it
+                    if maybe_shutdown != Some(false) {
+                        // rows already queued behind the shutdown still belong in this flush
+                        rx.close();
+                        while let Ok(row) = rx.try_recv() {
+                            buffer.push(row);
+                        }
+                        flush_buffer::<W>(&mut client, &mut buffer).await;
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep(Duration::from_millis(MAX_BATCH_WAIT_MS)) => {
+                    if !buffer.is_empty() && last_flush.elapsed().as_millis() as u64 >= MAX_BATCH_WAIT_MS {
+                        flush_buffer::<W>(&mut client, &mut buffer).await;
+                        last_flush = Instant::now();
+                    }
+                }
+            }
+        }
+    });
+
+    (Arc::new(sender), Arc::new(shutdown_sender), flush_task)
+}
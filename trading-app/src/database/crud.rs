@@ -1,11 +1,15 @@
-use std::usize;
+use std::{future::Future, usize};
 
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::{
+    FromRow, PgPool, Postgres, Transaction,
+    postgres::{PgArguments, PgQueryResult},
+    query::Query,
+};
 
-use crate::Insertable;
+use crate::{Insertable, database::schema_check::ModelTable};
 
 fn map_to_placeholder(key: usize, column_name: &str) -> String {
     match column_name {
@@ -16,13 +20,238 @@ fn map_to_placeholder(key: usize, column_name: &str) -> String {
     }
 }
 
+/// Postgres caps a single statement at 65535 bind parameters
+const MAX_BIND_PARAMS: usize = 65535;
+
+/// "($1, $2::status), ($3, $4::status), ..." - placeholders for n_rows rows of columns, numbered
+/// row by row in the order Insertable::bind_many_to_query binds them
+pub fn values_placeholders(columns: &[&str], n_rows: usize) -> String {
+    (0..n_rows)
+        .map(|row| {
+            let placeholders = columns
+                .iter()
+                .enumerate()
+                .map(|(index, col)| map_to_placeholder(row * columns.len() + index + 1, col))
+                .collect::<Vec<_>>();
+            format!("({})", placeholders.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// SQLSTATE codes Postgres raises when a transaction lost a race with a concurrent one and can
+/// simply be re-run: serialization_failure and deadlock_detected
+const RETRYABLE_SQLSTATES: [&str; 2] = ["40001", "40P01"];
+
+/// Number of times create_or_update / update are re-run on a serialization failure
+pub const MAX_SERIALIZATION_RETRIES: usize = 3;
+
+/// Isolation used by the write paths (create_or_update / update)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteIsolation {
+    /// Single statement on the pool - Postgres default of READ COMMITTED
+    #[default]
+    ReadCommitted,
+    /// Statement is wrapped in its own SERIALIZABLE transaction so concurrent read-modify-writes
+    /// fail loudly (and get retried) instead of losing updates
+    Serializable,
+}
+
+/// Whether the error is a Postgres serialization failure / deadlock that is safe to retry
+pub fn is_serialization_failure(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(db_error)) => db_error
+            .code()
+            .is_some_and(|code| RETRYABLE_SQLSTATES.contains(&code.as_ref())),
+        _ => false,
+    }
+}
+
+/// SQLSTATE class Postgres raises for a lost / refused connection (connection_exception)
+const CONNECTION_EXCEPTION_CLASS: &str = "08";
+
+/// Whether the error is transient - a serialization failure / deadlock or a dropped connection /
+/// exhausted pool - so the same write is likely to go through on a later attempt
+pub fn is_transient_error(error: &anyhow::Error) -> bool {
+    if is_serialization_failure(error) {
+        return true;
+    }
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut) => true,
+        Some(sqlx::Error::Database(db_error)) => db_error
+            .code()
+            .is_some_and(|code| code.starts_with(CONNECTION_EXCEPTION_CLASS)),
+        _ => false,
+    }
+}
+
+/// Re-runs op while it fails with a serialization failure, up to max_retries extra attempts
+/// - any other error is returned straight away
+pub async fn retry_on_serialization_failure<T, F, Fut>(max_retries: usize, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if attempt < max_retries && is_serialization_failure(&e) => {
+                attempt += 1;
+                tracing::warn!(
+                    "Serialization failure, retrying ({}/{}): {}",
+                    attempt,
+                    max_retries,
+                    e
+                );
+            }
+            res => return res,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CRUD<FK, PK, UK> {
     pub pool: PgPool,
     pub table: String,
+    pub isolation: WriteIsolation,
     pub _marker: std::marker::PhantomData<(FK, PK, UK)>, // Just to "use" the generics
 }
 
+impl<FK, PK, UK> CRUD<FK, PK, UK> {
+    pub fn with_isolation(mut self, isolation: WriteIsolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Table this reads / writes with every column of its model, for validate_schema
+    pub fn model_table(&self) -> ModelTable
+    where
+        FK: Insertable,
+    {
+        ModelTable::new(&self.table, FK::column_names())
+    }
+
+    /// Upserts every row with as few multi-row INSERTs as the bind parameter limit allows
+    /// - rows conflict on their pri columns and overwrite the optional columns they set, which
+    ///   every row has to set the same of (see Insertable::many_column_names)
+    pub async fn upsert_many<T: Insertable>(&self, rows: &[T]) -> Result<u64> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let columns = T::many_column_names(rows).map_err(|e| anyhow!(e))?;
+        let pri_columns = rows[0].pri_column_names();
+        let set_clause = columns[pri_columns.len()..]
+            .iter()
+            .map(|col| format!("{} = EXCLUDED.{}", col, col))
+            .collect::<Vec<_>>();
+        let conflict_action = if set_clause.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", set_clause.join(", "))
+        };
+
+        let mut rows_affected = 0;
+        for chunk in rows.chunks(MAX_BIND_PARAMS / columns.len()) {
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES {} ON CONFLICT ({}) {};",
+                &self.table,
+                columns.join(", "),
+                values_placeholders(&columns, chunk.len()),
+                pri_columns.join(", "),
+                conflict_action
+            );
+            let query = T::bind_many_to_query(chunk, sqlx::query(&sql));
+            rows_affected += self.execute_write(query).await?.rows_affected();
+        }
+        Ok(rows_affected)
+    }
+
+    async fn execute_write<'q>(
+        &self,
+        query: Query<'q, Postgres, PgArguments>,
+    ) -> Result<PgQueryResult> {
+        match self.isolation {
+            WriteIsolation::ReadCommitted => Ok(query.execute(&self.pool).await?),
+            WriteIsolation::Serializable => {
+                let mut tx = self.pool.begin().await?;
+                sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+                    .execute(&mut *tx)
+                    .await?;
+                let res = query.execute(&mut *tx).await?;
+                tx.commit().await?;
+                Ok(res)
+            }
+        }
+    }
+}
+
+impl<FK, PK: Insertable, UK: Insertable> CRUD<FK, PK, UK> {
+    fn create_sql(&self, full_keys: &FK) -> String
+    where
+        FK: Insertable,
+    {
+        let mut all_cols = full_keys.pri_column_names();
+        all_cols.extend(full_keys.opt_column_names());
+        let all_placeholders = all_cols
+            .iter()
+            .enumerate()
+            .map(|(index, col)| map_to_placeholder(index + 1, col))
+            .collect::<Vec<_>>();
+
+        format!(
+            "INSERT INTO {} ({}) VALUES ({});",
+            &self.table,
+            all_cols.join(", "),
+            all_placeholders.join(", ")
+        )
+    }
+
+    fn read_sql(&self, pk: &PK) -> String {
+        let conditions = pk
+            .pri_column_names()
+            .iter()
+            .enumerate()
+            .map(|(index, column)| format!("{} = ${}", column, index + 1))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        format!("SELECT * FROM {} WHERE {}", &self.table, conditions)
+    }
+
+    fn update_sql(&self, pk: &PK, update: &UK) -> String {
+        // Make Set clauses
+        let set_placeholders: Vec<String> = update
+            .opt_column_names()
+            .iter()
+            .enumerate()
+            .map(|(index, col)| format!("{} = {}", col, map_to_placeholder(index + 1, col)))
+            .collect();
+        let set_clause = set_placeholders.join(", ");
+
+        // Make Where clauses
+        let index_start_at = set_placeholders.len();
+        let where_placeholders: Vec<String> = pk
+            .pri_column_names()
+            .iter()
+            .enumerate()
+            .map(|(index, col)| {
+                format!(
+                    "{} = ${}",
+                    col,
+                    // map_to_placeholder(&index_start_at + index + 1, col)
+                    index_start_at + index + 1
+                )
+            })
+            .collect();
+        let where_clause = where_placeholders.join(" AND ");
+
+        format!(
+            "UPDATE {} SET {} WHERE {};",
+            &self.table, set_clause, where_clause
+        )
+    }
+}
+
 #[async_trait]
 pub trait CRUDTrait<FullKeys, PrimaryKeys, UpdateKeys>
 where
@@ -32,9 +261,21 @@ where
 {
     fn new(pool: PgPool, table: String) -> Self;
     async fn create(&self, raw_item: &FullKeys) -> Result<()>;
+    async fn create_in_tx(
+        &self,
+        raw_item: &FullKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()>;
     async fn create_or_ignore(&self, raw_item: &FullKeys) -> Result<()>;
     async fn create_or_update(&self, pk: &PrimaryKeys, uk: &UpdateKeys) -> Result<()>;
     async fn read(&self, raw_pk: &PrimaryKeys) -> Result<Option<FullKeys>>
+    where
+        FullKeys: Unpin + for<'r> FromRow<'r, sqlx::postgres::PgRow>;
+    async fn read_for_update(
+        &self,
+        raw_pk: &PrimaryKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<FullKeys>>
     where
         FullKeys: Unpin + for<'r> FromRow<'r, sqlx::postgres::PgRow>;
     async fn read_all(&self) -> Result<Option<Vec<FullKeys>>>
@@ -45,6 +286,12 @@ where
         raw_pk: &PrimaryKeys,
         raw_update: &UpdateKeys,
     ) -> Result<u64, anyhow::Error>;
+    async fn update_in_tx(
+        &self,
+        raw_pk: &PrimaryKeys,
+        raw_update: &UpdateKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<u64, anyhow::Error>;
     async fn delete(&self, raw_pk: &PrimaryKeys) -> Result<()>;
 }
 
@@ -59,29 +306,31 @@ impl<
         Self {
             pool,
             table,
+            isolation: WriteIsolation::default(),
             _marker: std::marker::PhantomData,
         }
     }
 
     /// A typical create function - pass in all FullKeys without Option<>
+    /// - nullable columns (Option<> in FullKeys) are only written when set
     async fn create(&self, full_keys: &FullKeys) -> Result<()> {
-        let all_cols = full_keys.pri_column_names();
-        let all_placeholders = all_cols
-            .iter()
-            .enumerate()
-            .map(|(index, col)| map_to_placeholder(index + 1, col))
-            .collect::<Vec<_>>();
+        let sql = self.create_sql(full_keys);
+        let query = full_keys.bind_opt_to_query(full_keys.bind_pri(&sql));
 
-        let sql = format!(
-            "INSERT INTO {} ({}) VALUES ({});",
-            &self.table,
-            all_cols.join(", "),
-            all_placeholders.join(", ")
-        );
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
 
-        let query = full_keys.bind_pri(&sql);
+    /// Same as create but within the caller's transaction
+    async fn create_in_tx(
+        &self,
+        full_keys: &FullKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<()> {
+        let sql = self.create_sql(full_keys);
+        let query = full_keys.bind_opt_to_query(full_keys.bind_pri(&sql));
 
-        query.execute(&self.pool).await?;
+        query.execute(&mut **tx).await?;
         Ok(())
     }
 
@@ -89,7 +338,8 @@ impl<
     /// - NOTE: the query uses inbuilt conflict in the table. i.e. if the conflict doesn't exist on
     /// any unique_index or primary key, it may raise an error with insertion
     async fn create_or_ignore(&self, full_keys: &FullKeys) -> Result<()> {
-        let all_cols = full_keys.pri_column_names();
+        let mut all_cols = full_keys.pri_column_names();
+        all_cols.extend(full_keys.opt_column_names());
         let all_placeholders = all_cols
             .iter()
             .enumerate()
@@ -103,7 +353,7 @@ impl<
             all_placeholders.join(", "),
         );
 
-        let query = full_keys.bind_pri(&sql);
+        let query = full_keys.bind_opt_to_query(full_keys.bind_pri(&sql));
 
         query.execute(&self.pool).await?;
         Ok(())
@@ -111,6 +361,7 @@ impl<
 
     /// A create_or_update function - upsert basically
     /// - function is split into 2 parameters for ease of processing for function
+    /// - retried on serialization failures (see retry_on_serialization_failure)
     async fn create_or_update(&self, pk: &PrimaryKeys, uk: &UpdateKeys) -> Result<()> {
         let mut all_cols = pk.pri_column_names();
         all_cols.extend(uk.opt_column_names());
@@ -135,10 +386,13 @@ impl<
             set_clause.join(", ")
         );
 
-        let mut query = pk.bind_pri(&sql);
-        query = uk.bind_opt_to_query(query);
-
-        query.execute(&self.pool).await?;
+        let sql = &sql;
+        retry_on_serialization_failure(MAX_SERIALIZATION_RETRIES, || async move {
+            let mut query = pk.bind_pri(sql);
+            query = uk.bind_opt_to_query(query);
+            self.execute_write(query).await
+        })
+        .await?;
         Ok(())
     }
 
@@ -147,15 +401,7 @@ impl<
     where
         FullKeys: Unpin + for<'r> FromRow<'r, sqlx::postgres::PgRow>,
     {
-        let conditions = pk
-            .pri_column_names()
-            .iter()
-            .enumerate()
-            .map(|(index, column)| format!("{} = ${}", column, index + 1))
-            .collect::<Vec<_>>()
-            .join(" AND ");
-
-        let sql = format!("SELECT * FROM {} WHERE {};", &self.table, conditions);
+        let sql = format!("{};", self.read_sql(pk));
         let mut query = sqlx::query_as::<_, FullKeys>(&sql);
         query = pk.bind_pri_to_query_as(query);
 
@@ -163,6 +409,35 @@ impl<
         Ok(result)
     }
 
+    /// Same as read but takes a row lock (SELECT ... FOR UPDATE) within the caller's transaction
+    /// - the lock is held until tx is committed / rolled back, so a read-modify-write of a row
+    /// is serialized against any other transaction doing the same for that row
+    /// - the write has to go through the same tx (update_in_tx) - writing through the pool would
+    /// wait on the lock held by tx itself
+    ///
+    /// ```ignore
+    /// let mut tx = pool.begin().await?;
+    /// let position = crud.read_for_update(&pk, &mut tx).await?;
+    /// // ... compute new quantity / avg_price from position ...
+    /// crud.update_in_tx(&pk, &uk, &mut tx).await?;
+    /// tx.commit().await?;
+    /// ```
+    async fn read_for_update(
+        &self,
+        pk: &PrimaryKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<FullKeys>>
+    where
+        FullKeys: Unpin + for<'r> FromRow<'r, sqlx::postgres::PgRow>,
+    {
+        let sql = format!("{} FOR UPDATE;", self.read_sql(pk));
+        let mut query = sqlx::query_as::<_, FullKeys>(&sql);
+        query = pk.bind_pri_to_query_as(query);
+
+        let result = query.fetch_optional(&mut **tx).await?;
+        Ok(result)
+    }
+
     /// Typical read_all function that returns all rows in DB
     /// - thus, could be a potentially taxing query
     async fn read_all(&self) -> Result<Option<Vec<FullKeys>>>
@@ -178,43 +453,33 @@ impl<
     /// Typical update function that updates the matching row in table
     /// - Primary keys should be passed without Option
     /// - Update keys should be passed as Option<>: If a key should not be updated, pass None
+    /// - retried on serialization failures (see retry_on_serialization_failure)
     async fn update(&self, pk: &PrimaryKeys, update: &UpdateKeys) -> Result<u64, anyhow::Error> {
-        // Make Set clauses
-        let set_placeholders: Vec<String> = update
-            .opt_column_names()
-            .iter()
-            .enumerate()
-            .map(|(index, col)| format!("{} = {}", col, map_to_placeholder(index + 1, col)))
-            .collect();
-        let set_clause = set_placeholders.join(", ");
-
-        // Make Where clauses
-        let index_start_at = set_placeholders.len();
-        let where_placeholders: Vec<String> = pk
-            .pri_column_names()
-            .iter()
-            .enumerate()
-            .map(|(index, col)| {
-                format!(
-                    "{} = ${}",
-                    col,
-                    // map_to_placeholder(&index_start_at + index + 1, col)
-                    index_start_at + index + 1
-                )
-            })
-            .collect();
-        let where_clause = where_placeholders.join(" AND ");
+        let sql = self.update_sql(pk, update);
+        let sql = &sql;
+        let res = retry_on_serialization_failure(MAX_SERIALIZATION_RETRIES, || async move {
+            let mut query = sqlx::query(sql);
+            query = update.bind_opt_to_query(query);
+            query = pk.bind_pri_to_query(query);
+            self.execute_write(query).await
+        })
+        .await?;
+        Ok(res.rows_affected())
+    }
 
-        let sql = format!(
-            "UPDATE {} SET {} WHERE {};",
-            &self.table, set_clause, where_clause
-        );
+    /// Same as update but within the caller's transaction - meant to follow read_for_update
+    /// - not retried: on failure the whole transaction has to be re-run by the caller
+    async fn update_in_tx(
+        &self,
+        pk: &PrimaryKeys,
+        update: &UpdateKeys,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<u64, anyhow::Error> {
+        let sql = self.update_sql(pk, update);
         let mut query = sqlx::query(&sql);
-
         query = update.bind_opt_to_query(query);
         query = pk.bind_pri_to_query(query);
-
-        let res = query.execute(&self.pool).await?;
+        let res = query.execute(&mut **tx).await?;
         Ok(res.rows_affected())
     }
 
@@ -243,6 +508,13 @@ macro_rules! delegate_all_crud_methods {
         pub async fn create(&self, raw_item: &$FullKeys) -> anyhow::Result<()> {
             self.$delegator.create(raw_item).await
         }
+        pub async fn create_in_tx(
+            &self,
+            raw_item: &$FullKeys,
+            tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        ) -> anyhow::Result<()> {
+            self.$delegator.create_in_tx(raw_item, tx).await
+        }
         pub async fn create_or_ignore(&self, raw_item: &$FullKeys) -> anyhow::Result<()> {
             self.$delegator.create_or_ignore(raw_item).await
         }
@@ -252,6 +524,16 @@ macro_rules! delegate_all_crud_methods {
         {
             self.$delegator.read(raw_pk).await
         }
+        pub async fn read_for_update(
+            &self,
+            raw_pk: &$PrimaryKeys,
+            tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        ) -> anyhow::Result<Option<$FullKeys>>
+        where
+            $FullKeys: Unpin + for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow>,
+        {
+            self.$delegator.read_for_update(raw_pk, tx).await
+        }
         pub async fn create_or_update(
             &self,
             pk: &$PrimaryKeys,
@@ -272,8 +554,19 @@ macro_rules! delegate_all_crud_methods {
         ) -> anyhow::Result<u64, anyhow::Error> {
             self.$delegator.update(raw_pk, raw_update).await
         }
+        pub async fn update_in_tx(
+            &self,
+            raw_pk: &$PrimaryKeys,
+            raw_update: &$UpdateKeys,
+            tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        ) -> anyhow::Result<u64, anyhow::Error> {
+            self.$delegator.update_in_tx(raw_pk, raw_update, tx).await
+        }
         pub async fn delete(&self, raw_pk: &$PrimaryKeys) -> anyhow::Result<()> {
             self.$delegator.delete(raw_pk).await
         }
+        pub async fn upsert_many<T: $crate::Insertable>(&self, rows: &[T]) -> anyhow::Result<u64> {
+            self.$delegator.upsert_many(rows).await
+        }
     };
 }
@@ -0,0 +1,98 @@
+use sqlx::{Connection, PgConnection};
+
+/// Advisory lock key every trading-app instance takes at startup - "rusty_tr" as bytes
+pub const INSTANCE_LOCK_KEY: i64 = 0x7275_7374_795f_7472;
+
+/// What a starting instance does when another one already holds the instance lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstanceLockMode {
+    /// Give up straight away with an error naming the lock
+    #[default]
+    Exit,
+    /// Block until the other instance releases it, e.g. for a standby instance
+    Wait,
+}
+
+impl InstanceLockMode {
+    /// Reads INSTANCE_LOCK_MODE ("exit" / "wait"), defaulting to Exit
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("INSTANCE_LOCK_MODE") {
+            Ok(mode) => match mode.trim().to_lowercase().as_str() {
+                "exit" => Ok(Self::Exit),
+                "wait" => Ok(Self::Wait),
+                other => Err(format!(
+                    "INSTANCE_LOCK_MODE must be either exit or wait, got {}",
+                    other
+                )),
+            },
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+/// Session level pg advisory lock guaranteeing a single instance places orders for the account
+/// - held on its own connection rather than the pool's, since returning a pooled connection
+///   would hand the lock to whichever query picks it up next
+/// - released by release, or by Postgres once the connection closes if the process dies
+pub struct InstanceLock {
+    connection: PgConnection,
+    key: i64,
+}
+
+impl InstanceLock {
+    pub async fn acquire(database_url: &str, mode: InstanceLockMode) -> Result<Self, String> {
+        Self::acquire_key(database_url, INSTANCE_LOCK_KEY, mode).await
+    }
+
+    pub async fn acquire_key(
+        database_url: &str,
+        key: i64,
+        mode: InstanceLockMode,
+    ) -> Result<Self, String> {
+        let mut connection = PgConnection::connect(database_url)
+            .await
+            .map_err(|e| format!("Failed to connect to take the instance lock: {}", e))?;
+        match mode {
+            InstanceLockMode::Exit => {
+                let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                    .bind(key)
+                    .fetch_one(&mut connection)
+                    .await
+                    .map_err(|e| format!("Failed to take instance lock {}: {}", key, e))?;
+                if !acquired {
+                    return Err(format!(
+                        "Instance lock {} is held by another trading-app instance - exiting so orders are not placed twice",
+                        key
+                    ));
+                }
+            }
+            InstanceLockMode::Wait => {
+                tracing::info!("Waiting for instance lock {}", key);
+                sqlx::query("SELECT pg_advisory_lock($1)")
+                    .bind(key)
+                    .execute(&mut connection)
+                    .await
+                    .map_err(|e| format!("Failed to take instance lock {}: {}", key, e))?;
+            }
+        }
+        tracing::info!("Acquired instance lock {}", key);
+        Ok(Self { connection, key })
+    }
+
+    pub fn key(&self) -> i64 {
+        self.key
+    }
+
+    pub async fn release(mut self) {
+        if let Err(e) = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .execute(&mut self.connection)
+            .await
+        {
+            tracing::error!("Error releasing instance lock {}: {}", self.key, e);
+        }
+        if let Err(e) = self.connection.close().await {
+            tracing::error!("Error closing instance lock connection: {}", e);
+        }
+    }
+}
@@ -1,3 +1,6 @@
+pub mod batch_writer;
 pub mod crud;
+pub mod instance_lock;
 pub mod models;
 pub mod models_crud;
+pub mod schema_check;
@@ -35,25 +35,57 @@ pub enum OptionType {
     Put,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecutionSide {
     Bought,
     Sold,
 }
 
 impl ExecutionSide {
-    pub fn from_str(side: &str) -> ExecutionSide {
+    /// Parses the side reported by IBKR on an execution
+    /// - anything other than BOT/SLD is an error so callers can flag and skip the execution
+    ///   instead of silently treating it as a sell
+    pub fn from_str(side: &str) -> Result<ExecutionSide, String> {
         match side {
-            "BOT" => ExecutionSide::Bought,
-            "SLD" => ExecutionSide::Sold,
-            _ => panic!(
+            "BOT" => Ok(ExecutionSide::Bought),
+            "SLD" => Ok(ExecutionSide::Sold),
+            _ => Err(format!(
                 "ExecutionSide from_str called with string that is not BOT/SLD: {}",
                 side
-            ),
+            )),
+        }
+    }
+
+    /// Applies the direction of the side to an unsigned quantity: +qty if bought, -qty if sold
+    pub fn signed(&self, quantity: f64) -> f64 {
+        match self {
+            ExecutionSide::Bought => quantity,
+            ExecutionSide::Sold => -quantity,
         }
     }
 }
 
+/// Canonical form of a contract multiplier used in every option key
+/// - IBKR reports the same multiplier as "100" or "100.0" depending on the message, whole
+///   multipliers are stored as integer strings so both map to the same position / order row
+/// - anything that isn't a number is kept as is (trimmed)
+pub fn normalize_multiplier(multiplier: &str) -> String {
+    let trimmed = multiplier.trim();
+    match trimmed.parse::<f64>() {
+        Ok(value) if value.is_finite() && value.fract() == 0.0 => format!("{}", value as i64),
+        Ok(value) if value.is_finite() => value.to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Numeric value of a stored multiplier, for portfolio math
+pub fn multiplier_value(multiplier: &str) -> Result<f64, String> {
+    multiplier
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("Invalid multiplier {}: {}", multiplier, e))
+}
+
 impl AssetType {
     /// NOTE: this is a different from_str from typical fmt::from_str
     /// Accepts ibapi's SecurityType and converts it to the local AssetType
@@ -94,6 +126,15 @@ impl OptionType {
     }
 }
 
+impl From<&OptionType> for shared::options::OptionRight {
+    fn from(option_type: &OptionType) -> Self {
+        match option_type {
+            OptionType::Call => shared::options::OptionRight::Call,
+            OptionType::Put => shared::options::OptionRight::Put,
+        }
+    }
+}
+
 impl fmt::Display for OptionType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -147,6 +188,97 @@ pub struct Strategy {
     pub status: Option<Status>,
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    ExtractFullKeys,
+    ExtractPrimaryKeys,
+    ExtractUpdateKeys,
+    DeriveInsertable,
+    FromRow,
+)]
+pub struct StrategyParams {
+    pub strategy: String,
+    pub params: Option<serde_json::Value>,
+    pub params_schema: Option<serde_json::Value>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    ExtractFullKeys,
+    ExtractPrimaryKeys,
+    ExtractUpdateKeys,
+    DeriveInsertable,
+    FromRow,
+)]
+pub struct EquitySnapshots {
+    pub strategy: String,
+    pub time: DateTime<Utc>,
+    pub value: Option<f64>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    ExtractFullKeys,
+    ExtractPrimaryKeys,
+    ExtractUpdateKeys,
+    DeriveInsertable,
+    FromRow,
+)]
+pub struct AccountSummary {
+    #[primary_key]
+    pub account: String,
+    #[primary_key]
+    pub time: DateTime<Utc>,
+    // None where IBKR didn't report the tag
+    pub currency: Option<String>,
+    pub net_liquidation: Option<f64>,
+    pub total_cash_value: Option<f64>,
+    pub buying_power: Option<f64>,
+    pub available_funds: Option<f64>,
+    pub excess_liquidity: Option<f64>,
+    pub init_margin_req: Option<f64>,
+    pub maint_margin_req: Option<f64>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    ExtractFullKeys,
+    ExtractPrimaryKeys,
+    ExtractUpdateKeys,
+    DeriveInsertable,
+    FromRow,
+)]
+pub struct OrderMap {
+    pub order_id: i32,
+    pub strategy: Option<String>,
+    pub time: Option<DateTime<Utc>>,
+
+    pub security_type: Option<String>,
+    pub stock: Option<String>,
+    pub primary_exchange: Option<String>,
+    pub exchange: Option<String>,
+    pub currency: Option<String>,
+    pub expiry: Option<String>,
+    pub strike: Option<f64>,
+    pub multiplier: Option<String>,
+    pub option_right: Option<String>,
+
+    pub action: Option<String>,
+    pub quantity: Option<f64>,
+}
+
 #[derive(
     Debug,
     Clone,
@@ -245,16 +377,20 @@ pub struct TargetOptionPositions {
     FromRow,
 )]
 pub struct OpenStockOrders {
+    #[primary_key]
     pub order_perm_id: i32,
+    #[primary_key]
     pub order_id: i32,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub time: Option<DateTime<Utc>>,
-    pub quantity: Option<f64>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub time: DateTime<Utc>,
+    pub quantity: f64,
 
-    pub executions: Option<Vec<String>>,
-    pub filled: Option<f64>,
+    pub executions: Vec<String>,
+    pub filled: f64,
+    // {"contract": .., "order": ..} the order was placed with (see order_map::order_snapshot)
+    pub order_snapshot: Option<serde_json::Value>,
 }
 
 #[derive(
@@ -269,20 +405,24 @@ pub struct OpenStockOrders {
     FromRow,
 )]
 pub struct OpenOptionOrders {
+    #[primary_key]
     pub order_perm_id: i32,
+    #[primary_key]
     pub order_id: i32,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub expiry: Option<String>,
-    pub strike: Option<f64>,
-    pub multiplier: Option<String>,
-    pub option_type: Option<OptionType>,
-    pub time: Option<DateTime<Utc>>,
-    pub quantity: Option<f64>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+    pub time: DateTime<Utc>,
+    pub quantity: f64,
 
-    pub executions: Option<Vec<String>>,
-    pub filled: Option<f64>,
+    pub executions: Vec<String>,
+    pub filled: f64,
+    // {"contract": .., "order": ..} the order was placed with (see order_map::order_snapshot)
+    pub order_snapshot: Option<serde_json::Value>,
 }
 
 #[derive(
@@ -297,15 +437,22 @@ pub struct OpenOptionOrders {
     FromRow,
 )]
 pub struct StockTransactions {
+    #[primary_key]
     pub execution_id: String,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub order_perm_id: Option<i32>,
-    pub time: Option<DateTime<Utc>>,
-    pub price: Option<f64>,
-    pub quantity: Option<f64>,
-    pub fees: Option<Decimal>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub order_perm_id: i32,
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub quantity: f64,
+    pub fees: Decimal,
+    // Untransformed IBKR execution time, only stored with STORE_RAW_BROKER_TIME=true
+    pub raw_broker_time: Option<String>,
+    // Set at execution time when the fill reduces the position - realized_pnl is None for fills
+    // opening / adding to a position
+    pub closes_position: bool,
+    pub realized_pnl: Option<f64>,
 }
 
 #[derive(
@@ -320,19 +467,26 @@ pub struct StockTransactions {
     FromRow,
 )]
 pub struct OptionTransactions {
+    #[primary_key]
     pub execution_id: String,
-    pub strategy: Option<String>,
-    pub stock: Option<String>,
-    pub primary_exchange: Option<String>,
-    pub expiry: Option<String>,
-    pub strike: Option<f64>,
-    pub multiplier: Option<String>,
-    pub option_type: Option<OptionType>,
-    pub order_perm_id: Option<i32>,
-    pub time: Option<DateTime<Utc>>,
-    pub price: Option<f64>,
-    pub quantity: Option<f64>,
-    pub fees: Option<rust_decimal::Decimal>,
+    pub strategy: String,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+    pub order_perm_id: i32,
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub quantity: f64,
+    pub fees: rust_decimal::Decimal,
+    // Untransformed IBKR execution time, only stored with STORE_RAW_BROKER_TIME=true
+    pub raw_broker_time: Option<String>,
+    // Set at execution time when the fill reduces the position - realized_pnl is None for fills
+    // opening / adding to a position
+    pub closes_position: bool,
+    pub realized_pnl: Option<f64>,
 }
 
 #[derive(
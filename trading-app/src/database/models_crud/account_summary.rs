@@ -0,0 +1,15 @@
+use sqlx::PgPool;
+
+use crate::database::{
+    crud::{CRUD, CRUDTrait},
+    models::{AccountSummaryFullKeys, AccountSummaryPrimaryKeys, AccountSummaryUpdateKeys},
+};
+
+pub fn get_account_summary_crud(
+    pool: PgPool,
+) -> CRUD<AccountSummaryFullKeys, AccountSummaryPrimaryKeys, AccountSummaryUpdateKeys> {
+    CRUD::<AccountSummaryFullKeys, AccountSummaryPrimaryKeys, AccountSummaryUpdateKeys>::new(
+        pool,
+        String::from("trading.account_summary"),
+    )
+}
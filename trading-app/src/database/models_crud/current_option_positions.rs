@@ -1,14 +1,24 @@
-use sqlx::{PgPool, prelude::FromRow};
+use crud_insertable::DeriveInsertable;
+use crud_models::ExtractPrimaryKeys;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    PgPool, Postgres,
+    postgres::PgArguments,
+    prelude::FromRow,
+    query::{Query, QueryAs},
+};
 
 use crate::{
+    Insertable,
     database::{
         crud::{CRUD, CRUDTrait},
         models::{
             CurrentOptionPositionsFullKeys, CurrentOptionPositionsPrimaryKeys,
-            CurrentOptionPositionsUpdateKeys, OptionType,
+            CurrentOptionPositionsUpdateKeys, OptionType, normalize_multiplier,
         },
     },
     delegate_all_crud_methods,
+    execution::events::on_execution_updates::DEFAULT_UNKNOWN_STRATEGY,
 };
 
 #[derive(Debug, Clone, FromRow)]
@@ -22,13 +32,20 @@ pub struct GroupedByContractOptional {
     pub quantity: Option<f64>,
 }
 
-#[derive(Debug, Clone, FromRow)]
+/// Option position summed over strategies - keyed on the contract alone, so the exchange it is
+/// listed on doesn't split it from the broker's position
+#[derive(Debug, Clone, FromRow, ExtractPrimaryKeys)]
 pub struct GroupedByContract {
+    #[primary_key]
     pub stock: String,
     pub primary_exchange: String,
+    #[primary_key]
     pub expiry: String,
+    #[primary_key]
     pub strike: f64,
+    #[primary_key]
     pub multiplier: String,
+    #[primary_key]
     pub option_type: OptionType,
     pub quantity: f64,
 }
@@ -106,6 +123,8 @@ impl CurrentOptionPositionsCRUD {
             .collect())
     }
 
+    /// Adds qty to the default unknown strategy's position in the option (see
+    /// update_strat_positions)
     pub async fn update_unknown_strat_positions(
         &self,
         stock: String,
@@ -115,6 +134,32 @@ impl CurrentOptionPositionsCRUD {
         multiplier: String,
         option_type: OptionType,
         qty: f64,
+    ) -> Result<(), String> {
+        self.update_strat_positions(
+            DEFAULT_UNKNOWN_STRATEGY,
+            stock,
+            primary_exchange,
+            expiry,
+            strike,
+            multiplier,
+            option_type,
+            qty,
+        )
+        .await
+    }
+
+    /// Adds qty to strategy's position in the option - strategy being the catch-all that
+    /// executions no strategy accounts for are booked to
+    pub async fn update_strat_positions(
+        &self,
+        strategy: &str,
+        stock: String,
+        primary_exchange: String,
+        expiry: String,
+        strike: f64,
+        multiplier: String,
+        option_type: OptionType,
+        qty: f64,
     ) -> Result<(), String> {
         sqlx::query!(
             "
@@ -135,10 +180,10 @@ impl CurrentOptionPositionsCRUD {
             ",
             stock,
             primary_exchange,
-            "unknown",
+            strategy,
             expiry,
             strike,
-            multiplier,
+            normalize_multiplier(&multiplier),
             option_type as OptionType,
             qty,
             0.0
@@ -153,6 +198,66 @@ impl CurrentOptionPositionsCRUD {
         })?;
         Ok(())
     }
+
+    /// Sets strategy's position in the option so the positions of every strategy in it add up
+    /// to broker_qty - the sync_positions counterpart of update_strat_positions, which
+    /// writes the same row however often it is re-run
+    pub async fn sync_unknown_strat_positions(
+        &self,
+        strategy: &str,
+        stock: String,
+        primary_exchange: String,
+        expiry: String,
+        strike: f64,
+        multiplier: String,
+        option_type: OptionType,
+        broker_qty: f64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO trading.current_option_positions (
+                stock,
+                primary_exchange,
+                strategy,
+                expiry,
+                strike,
+                multiplier,
+                option_type,
+                quantity,
+                avg_price
+            )
+            VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8 - (
+                    SELECT COALESCE(SUM(quantity), 0) FROM trading.current_option_positions
+                    WHERE stock = $1 AND expiry = $4 AND strike = $5 AND multiplier = $6
+                        AND option_type = $7
+                        AND NOT (strategy = $3 AND primary_exchange = $2)
+                ),
+                0.0
+            )
+            ON CONFLICT (stock, primary_exchange, strategy, expiry, strike, multiplier, option_type)
+            DO UPDATE SET quantity = EXCLUDED.quantity;
+            ",
+        )
+        .bind(stock)
+        .bind(primary_exchange)
+        .bind(strategy)
+        .bind(expiry)
+        .bind(strike)
+        .bind(normalize_multiplier(&multiplier))
+        .bind(option_type)
+        .bind(broker_qty)
+        .execute(&self.crud.pool)
+        .await?;
+        Ok(())
+    }
 }
 
 pub fn get_current_option_positions_crud(
@@ -9,6 +9,7 @@ use crate::{
         },
     },
     delegate_all_crud_methods,
+    execution::events::on_execution_updates::DEFAULT_UNKNOWN_STRATEGY,
 };
 
 #[derive(Debug, Clone, FromRow)]
@@ -59,6 +60,101 @@ impl CurrentStockPositionsCRUD {
         CurrentStockPositionsUpdateKeys
     );
 
+    /// Compile-time checked alternative to create - a column typo fails the build instead of
+    /// the insert
+    pub async fn create_checked(
+        &self,
+        position: &CurrentStockPositionsFullKeys,
+    ) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            INSERT INTO trading.current_stock_positions (
+                strategy,
+                stock,
+                primary_exchange,
+                quantity,
+                avg_price
+            )
+            VALUES ($1, $2, $3, $4, $5);
+            "#,
+            position.strategy,
+            position.stock,
+            position.primary_exchange,
+            position.quantity,
+            position.avg_price
+        )
+        .execute(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error when creating stock position for strategy {}: {}",
+                position.strategy, e
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Compile-time checked alternative to read
+    pub async fn read_checked(
+        &self,
+        pk: &CurrentStockPositionsPrimaryKeys,
+    ) -> Result<Option<CurrentStockPositionsFullKeys>, String> {
+        sqlx::query_as!(
+            CurrentStockPositionsFullKeys,
+            r#"
+            SELECT stock, primary_exchange, strategy, quantity, avg_price
+            FROM trading.current_stock_positions
+            WHERE strategy = $1
+            AND stock = $2
+            AND primary_exchange = $3;
+            "#,
+            pk.strategy,
+            pk.stock,
+            pk.primary_exchange
+        )
+        .fetch_optional(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error when reading stock position for strategy {}: {}",
+                pk.strategy, e
+            )
+        })
+    }
+
+    /// Compile-time checked alternative to update - None fields are left as they are
+    /// - returns the number of rows updated
+    pub async fn update_checked(
+        &self,
+        pk: &CurrentStockPositionsPrimaryKeys,
+        update: &CurrentStockPositionsUpdateKeys,
+    ) -> Result<u64, String> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE trading.current_stock_positions
+            SET quantity = COALESCE($4, quantity),
+                avg_price = COALESCE($5, avg_price)
+            WHERE strategy = $1
+            AND stock = $2
+            AND primary_exchange = $3;
+            "#,
+            pk.strategy,
+            pk.stock,
+            pk.primary_exchange,
+            update.quantity,
+            update.avg_price
+        )
+        .execute(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error when updating stock position for strategy {}: {}",
+                pk.strategy, e
+            )
+        })?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn get_pos_by_strat_and_stock(
         &self,
         strategy: &String,
@@ -195,10 +291,23 @@ impl CurrentStockPositionsCRUD {
             .collect())
     }
 
+    /// Adds qty to the default unknown strategy's position in stock (see update_strat_positions)
     pub async fn update_unknown_strat_positions(
         &self,
         stock: String,
         qty: f64,
+    ) -> Result<(), String> {
+        self.update_strat_positions(DEFAULT_UNKNOWN_STRATEGY, stock, qty)
+            .await
+    }
+
+    /// Adds qty to strategy's position in stock - strategy being the catch-all that executions no
+    /// strategy accounts for are booked to
+    pub async fn update_strat_positions(
+        &self,
+        strategy: &str,
+        stock: String,
+        qty: f64,
     ) -> Result<(), String> {
         sqlx::query!(
             r#"
@@ -212,7 +321,7 @@ impl CurrentStockPositionsCRUD {
             ON CONFLICT (stock, strategy)
             DO UPDATE SET quantity = current_stock_positions.quantity + EXCLUDED.quantity;
             "#,
-            "unknown",
+            strategy,
             stock,
             qty,
             0.0
@@ -228,6 +337,48 @@ impl CurrentStockPositionsCRUD {
 
         Ok(())
     }
+
+    /// Sets strategy's position in stock so the positions of every strategy in it add up to
+    /// broker_qty - the sync_positions counterpart of update_strat_positions, which
+    /// writes the same row however often it is re-run
+    pub async fn sync_unknown_strat_positions(
+        &self,
+        strategy: &str,
+        stock: String,
+        primary_exchange: String,
+        broker_qty: f64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO trading.current_stock_positions (
+                strategy,
+                stock,
+                primary_exchange,
+                quantity,
+                avg_price
+            )
+            VALUES (
+                $1,
+                $2,
+                $3,
+                $4 - (
+                    SELECT COALESCE(SUM(quantity), 0) FROM trading.current_stock_positions
+                    WHERE stock = $2 AND NOT (strategy = $1 AND primary_exchange = $3)
+                ),
+                0.0
+            )
+            ON CONFLICT (strategy, stock, primary_exchange)
+            DO UPDATE SET quantity = EXCLUDED.quantity;
+            ",
+        )
+        .bind(strategy)
+        .bind(stock)
+        .bind(primary_exchange)
+        .bind(broker_qty)
+        .execute(&self.crud.pool)
+        .await?;
+        Ok(())
+    }
 }
 
 pub fn get_current_stock_positions_crud(
@@ -4,14 +4,12 @@ use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
 use rand::{Rng, distr::Alphanumeric};
 use sqlx::PgPool;
-use tokio::{
-    sync::mpsc::{Sender, channel},
-    time::Instant,
-};
-use tokio_postgres::{NoTls, binary_copy::BinaryCopyInWriter};
+use tokio::{sync::mpsc::Sender, task::JoinHandle};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
 
 use crate::{
     database::{
+        batch_writer::{BatchFlush, init_batch_channel},
         crud::{CRUD, CRUDTrait},
         models::{
             DailyHistoricalDataFullKeys, DailyHistoricalDataPrimaryKeys,
@@ -30,6 +28,7 @@ pub struct DailyHistoricalDataCRUD {
     >,
     sender: Arc<Mutex<Option<Arc<Sender<DailyHistoricalDataFullKeys>>>>>,
     shutdown_sender: Arc<Mutex<Option<Arc<Sender<bool>>>>>,
+    flush_task: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 struct OptionDailyOC {
@@ -48,108 +47,13 @@ struct OptionVWAP {
     vwap: Option<f64>,
 }
 
-async fn init_channel() -> (Arc<Sender<DailyHistoricalDataFullKeys>>, Arc<Sender<bool>>) {
-    const BATCH_SIZE: usize = 200_000;
-    const MAX_BATCH_WAIT_MS: u64 = 1000;
-
-    let host = std::env::var("DATABASE_HOST")
-        .expect("Expected DATABASE_HOST environment variable to be set!");
-
-    let (mut client, connection) = tokio_postgres::connect(
-        &format!(
-            "host={} user=ryantan password=admin dbname=trading_system",
-            host
-        ),
-        NoTls,
-    )
-    .await
-    .expect("Expected to be able to make tokio_postgres connection");
-    tracing::info!("INIT CHANNEL");
-
-    // spawn connection task so client works
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {e}");
-        }
-    });
-
-    let (sender, mut rx) = channel::<DailyHistoricalDataFullKeys>(10_000);
-    let (shutdown_sender, mut shutdown_rx) = channel::<bool>(2);
-
-    tokio::spawn(async move {
-        let mut buffer = Vec::with_capacity(BATCH_SIZE);
-        let mut last_flush = Instant::now();
-
-        loop {
-            tokio::select! {
-                maybe_row = rx.recv() => {
-                    match maybe_row {
-                        Some(row) => {
-                            buffer.push(row);
-                            if buffer.len() >= BATCH_SIZE {
-                                if let Err(e) = DailyHistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                                buffer.clear();
-                                last_flush = Instant::now();
-                            }
-                        }
-                        None => {
-                            if !buffer.is_empty() {
-                                if let Err(e) = DailyHistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                            }
-                            break;
-                        }
-                    }
-                }
-                maybe_shutdown = shutdown_rx.recv() => {
-                    if let Some(to_shutdown) = maybe_shutdown {
-                        if to_shutdown {
-                            if !buffer.is_empty() {
-                                if let Err(e) = DailyHistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                            }
-                            drop(client);
-                            break;
-                        }
-                    }
-                }
-                _ = tokio::time::sleep(tokio::time::Duration::from_millis(MAX_BATCH_WAIT_MS)) => {
-                    if !buffer.is_empty() && last_flush.elapsed().as_millis() as u64 >= MAX_BATCH_WAIT_MS {
-                        if let Err(e) = DailyHistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                            tracing::error!("Expected to be able to flush batch: \n{}", e);
-                        }
-                        buffer.clear();
-                        last_flush = Instant::now();
-                    }
-                }
-            }
-        }
-    });
-
-    (Arc::new(sender), Arc::new(shutdown_sender))
-}
-
-impl DailyHistoricalDataCRUD {
-    async fn new(pool: PgPool) -> Self {
-        Self {
-            crud: CRUD::<
-                DailyHistoricalDataFullKeys,
-                DailyHistoricalDataPrimaryKeys,
-                DailyHistoricalDataUpdateKeys,
-            >::new(pool, String::from("market_data.daily_historical_data")),
-            sender: Arc::new(Mutex::new(None)),
-            shutdown_sender: Arc::new(Mutex::new(None)),
-        }
-    }
+impl BatchFlush for DailyHistoricalDataCRUD {
+    type Row = DailyHistoricalDataFullKeys;
 
     /// Flush one batch with COPY + staging + merge (like the function I showed you before)
     async fn flush_batch(
         client: &mut tokio_postgres::Client,
-        batch: &[DailyHistoricalDataFullKeys],
+        batch: &[Self::Row],
     ) -> Result<(), anyhow::Error> {
         let suffix: String = rand::rng()
             .sample_iter(&Alphanumeric)
@@ -230,9 +134,23 @@ impl DailyHistoricalDataCRUD {
         tx.batch_execute(&merge_sql).await?;
 
         tx.commit().await?;
-        println!("Flushed batch of {} rows", batch.len());
         Ok(())
     }
+}
+
+impl DailyHistoricalDataCRUD {
+    async fn new(pool: PgPool) -> Self {
+        Self {
+            crud: CRUD::<
+                DailyHistoricalDataFullKeys,
+                DailyHistoricalDataPrimaryKeys,
+                DailyHistoricalDataUpdateKeys,
+            >::new(pool, String::from("market_data.daily_historical_data")),
+            sender: Arc::new(Mutex::new(None)),
+            shutdown_sender: Arc::new(Mutex::new(None)),
+            flush_task: Arc::new(Mutex::new(None)),
+        }
+    }
 
     delegate_all_crud_methods!(
         crud,
@@ -242,7 +160,7 @@ impl DailyHistoricalDataCRUD {
     );
 
     pub async fn init_channel(&self) {
-        let (sender, shutdown_sender) = init_channel().await;
+        let (sender, shutdown_sender, flush_task) = init_batch_channel::<Self>().await;
         self.sender
             .lock()
             .expect("Expected to be able to acquire sender lock")
@@ -251,16 +169,40 @@ impl DailyHistoricalDataCRUD {
             .lock()
             .expect("Expected to be able to acquire shutdown_sender lock")
             .replace(shutdown_sender);
+        self.flush_task
+            .lock()
+            .expect("Expected to be able to acquire flush_task lock")
+            .replace(flush_task);
     }
 
+    /// Flushes every row sent before the call and closes the channel
+    /// - returns once the batch writer has written them and exited
     pub async fn close_channel(&self) {
+        self.request_close();
+        let flush_task = self
+            .flush_task
+            .lock()
+            .expect("Expected to be able to acquire lock for flush_task")
+            .take();
+        if let Some(flush_task) = flush_task
+            && let Err(e) = flush_task.await
+        {
+            tracing::error!("Batch writer stopped before flushing: {}", e);
+        }
+    }
+
+    /// Signals the batch writer to flush and exit without waiting for it, for callers that
+    /// cannot await (Drop)
+    pub fn request_close(&self) {
         let sender_guard = self
             .shutdown_sender
             .lock()
             .expect("Expected to be able to acquire lock for shutdown_sender")
             .take();
-        if let Some(sender) = sender_guard {
-            sender.send(true).await;
+        if let Some(sender) = sender_guard
+            && let Err(e) = sender.try_send(true)
+        {
+            tracing::error!("Error signalling batch writer to close: {}", e);
         }
     }
 
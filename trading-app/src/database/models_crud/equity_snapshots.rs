@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        crud::{CRUD, CRUDTrait},
+        models::{EquitySnapshotsFullKeys, EquitySnapshotsPrimaryKeys, EquitySnapshotsUpdateKeys},
+    },
+    delegate_all_crud_methods,
+};
+
+#[derive(Debug, Clone)]
+pub struct EquitySnapshotsCRUD {
+    crud: CRUD<EquitySnapshotsFullKeys, EquitySnapshotsPrimaryKeys, EquitySnapshotsUpdateKeys>,
+}
+
+impl EquitySnapshotsCRUD {
+    fn new(pool: PgPool) -> Self {
+        Self {
+            crud: get_equity_snapshots_crud(pool),
+        }
+    }
+
+    delegate_all_crud_methods!(
+        crud,
+        EquitySnapshotsFullKeys,
+        EquitySnapshotsPrimaryKeys,
+        EquitySnapshotsUpdateKeys
+    );
+
+    /// Net cash spent on stock and option transactions by each strategy, fees included
+    /// - sells count negatively, so initial_capital less this is the strategy's cash
+    pub async fn get_cash_spent_by_strategy(&self) -> Result<HashMap<String, f64>, String> {
+        let rows = sqlx::query_as::<_, (String, f64)>(
+            r#"
+            SELECT strategy, SUM(quantity * price + fees::float8)
+            FROM trading.stock_transactions
+            GROUP BY strategy
+            UNION ALL
+            SELECT strategy, SUM(quantity * price * multiplier::float8 + fees::float8)
+            FROM trading.option_transactions
+            GROUP BY strategy;
+            "#,
+        )
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when summing cash spent on transactions: {}", e))?;
+
+        let mut cash_spent: HashMap<String, f64> = HashMap::new();
+        for (strategy, spent) in rows {
+            *cash_spent.entry(strategy).or_default() += spent;
+        }
+        Ok(cash_spent)
+    }
+}
+
+pub fn get_equity_snapshots_crud(
+    pool: PgPool,
+) -> CRUD<EquitySnapshotsFullKeys, EquitySnapshotsPrimaryKeys, EquitySnapshotsUpdateKeys> {
+    CRUD::<EquitySnapshotsFullKeys, EquitySnapshotsPrimaryKeys, EquitySnapshotsUpdateKeys>::new(
+        pool,
+        String::from("trading.equity_snapshots"),
+    )
+}
+
+pub fn get_specific_equity_snapshots_crud(pool: PgPool) -> EquitySnapshotsCRUD {
+    EquitySnapshotsCRUD::new(pool)
+}
@@ -1,120 +1,72 @@
 use std::{
     cmp::max,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use chrono::{DateTime, Timelike, Utc};
 use chrono_tz::{America::New_York, Tz};
+use moka::sync::Cache;
 use ordered_float::OrderedFloat;
 use rand::{Rng, distr::Alphanumeric};
 use rust_decimal::prelude::ToPrimitive;
+use shared::marks::{BarClose, latest_close};
 use sqlx::PgPool;
-use tokio::{
-    sync::mpsc::{Sender, channel},
-    time::Instant,
-};
-use tokio_postgres::{NoTls, binary_copy::BinaryCopyInWriter};
+use tokio::{sync::mpsc::Sender, task::JoinHandle};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
 
 use crate::{
     database::{
+        batch_writer::{BatchFlush, init_batch_channel},
         crud::{CRUD, CRUDTrait},
         models::{HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys},
     },
     delegate_all_crud_methods,
 };
 
+/// How long latest_close reuses a close before reading the newest bar again
+pub const DEFAULT_LAST_CLOSE_TTL: Duration = Duration::from_secs(5);
+
+/// Close of the newest bar per (stock, primary_exchange), with that bar's time
+/// - clones share the same closes, so the consolidator recording its bars and everything marking
+///   positions off latest_close see the same price
+/// - the close kept is picked by shared::marks::latest_close, as the backend marks positions
 #[derive(Clone, Debug)]
-pub struct HistoricalDataCRUD {
-    crud: CRUD<HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys>,
-    sender: Arc<Mutex<Option<Arc<Sender<HistoricalDataFullKeys>>>>>,
-    shutdown_sender: Arc<Mutex<Option<Arc<Sender<bool>>>>>,
+pub struct LastCloseCache(Arc<Cache<(String, String), BarClose>>);
+
+impl Default for LastCloseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_LAST_CLOSE_TTL)
+    }
 }
 
-async fn init_channel() -> (Arc<Sender<HistoricalDataFullKeys>>, Arc<Sender<bool>>) {
-    const BATCH_SIZE: usize = 200_000;
-    const MAX_BATCH_WAIT_MS: u64 = 1000;
+impl LastCloseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self(Arc::new(Cache::builder().time_to_live(ttl).build()))
+    }
 
-    let host = std::env::var("DATABASE_HOST")
-        .expect("Expected DATABASE_HOST environment variable to be set!");
+    pub fn get(&self, stock: &str, primary_exchange: &str) -> Option<f64> {
+        self.0
+            .get(&(stock.to_string(), primary_exchange.to_string()))
+            .map(|(_, close)| close)
+    }
 
-    let (mut client, connection) = tokio_postgres::connect(
-        &format!(
-            "host={} user=ryantan password=admin dbname=trading_system",
-            host
-        ),
-        NoTls,
-    )
-    .await
-    .expect("Expected to be able to make tokio_postgres connection");
-    tracing::info!("INIT CHANNEL");
-
-    // spawn connection task so client works
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {e}");
-        }
-    });
-
-    let (sender, mut rx) = channel::<HistoricalDataFullKeys>(10_000);
-    let (shutdown_sender, mut shutdown_rx) = channel::<bool>(2);
-
-    tokio::spawn(async move {
-        let mut buffer = Vec::with_capacity(BATCH_SIZE);
-        let mut last_flush = Instant::now();
-        tracing::info!("Entered loop to receive goods");
-
-        loop {
-            tokio::select! {
-                maybe_row = rx.recv() => {
-                    match maybe_row {
-                        Some(row) => {
-                            buffer.push(row);
-                            if buffer.len() >= BATCH_SIZE {
-                                if let Err(e) = HistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                                buffer.clear();
-                                last_flush = Instant::now();
-                            }
-                        }
-                        None => {
-                            if !buffer.is_empty() {
-                                if let Err(e) = HistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                            }
-                            break;
-                        }
-                    }
-                }
-                maybe_shutdown = shutdown_rx.recv() => {
-                    if let Some(to_shutdown) = maybe_shutdown {
-                        if to_shutdown {
-                            if !buffer.is_empty() {
-                                if let Err(e) = HistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                                    tracing::error!("Expected to be able to flush batch: \n{}", e);
-                                }
-                            }
-                            drop(client);
-                            break;
-                        }
-                    }
-                }
-                _ = tokio::time::sleep(tokio::time::Duration::from_millis(MAX_BATCH_WAIT_MS)) => {
-                    if !buffer.is_empty() && last_flush.elapsed().as_millis() as u64 >= MAX_BATCH_WAIT_MS {
-                        if let Err(e) = HistoricalDataCRUD::flush_batch(&mut client, &buffer).await {
-                            tracing::error!("Expected to be able to flush batch: \n{}", e);
-                        }
-                        buffer.clear();
-                        last_flush = Instant::now();
-                    }
-                }
-            }
-        }
-        tracing::info!("loop to receive goods ended");
-    });
+    /// Keeps the close of the newest bar - an older bar recorded after it (e.g. one being
+    /// backfilled) leaves it in place
+    pub fn record(&self, stock: &str, primary_exchange: &str, time: DateTime<Utc>, close: f64) {
+        let key = (stock.to_string(), primary_exchange.to_string());
+        let latest = latest_close(self.0.get(&key), (time, close));
+        self.0.insert(key, latest);
+    }
+}
 
-    (Arc::new(sender), Arc::new(shutdown_sender))
+#[derive(Clone, Debug)]
+pub struct HistoricalDataCRUD {
+    crud: CRUD<HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys>,
+    sender: Arc<Mutex<Option<Arc<Sender<HistoricalDataFullKeys>>>>>,
+    shutdown_sender: Arc<Mutex<Option<Arc<Sender<bool>>>>>,
+    flush_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    last_closes: LastCloseCache,
 }
 
 struct OptionDailyOC {
@@ -133,22 +85,12 @@ struct OptionVWAP {
     vwap: Option<f64>,
 }
 
-impl HistoricalDataCRUD {
-    fn new(pool: PgPool) -> Self {
-        // let sender = GLOBAL_SENDER
-        //     .get_or_init(|| async { init_channel() })
-        //     .await
-        //     .clone();
-        Self {
-            crud: CRUD::<HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys>::new(pool, String::from("market_data.historical_data")),
-            sender: Arc::new(Mutex::new(None)),
-            shutdown_sender: Arc::new(Mutex::new(None)),
-        }
-    }
+impl BatchFlush for HistoricalDataCRUD {
+    type Row = HistoricalDataFullKeys;
 
     async fn flush_batch(
         client: &mut tokio_postgres::Client,
-        batch: &[HistoricalDataFullKeys],
+        batch: &[Self::Row],
     ) -> Result<(), anyhow::Error> {
         let suffix: String = rand::rng()
             .sample_iter(&Alphanumeric)
@@ -232,9 +174,65 @@ impl HistoricalDataCRUD {
         tx.batch_execute(&merge_sql).await?;
 
         tx.commit().await?;
-        println!("Flushed batch of {} rows", batch.len());
         Ok(())
     }
+}
+
+impl HistoricalDataCRUD {
+    fn new(pool: PgPool) -> Self {
+        // let sender = GLOBAL_SENDER
+        //     .get_or_init(|| async { init_channel() })
+        //     .await
+        //     .clone();
+        Self {
+            crud: CRUD::<HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys>::new(pool, String::from("market_data.historical_data")),
+            sender: Arc::new(Mutex::new(None)),
+            shutdown_sender: Arc::new(Mutex::new(None)),
+            flush_task: Arc::new(Mutex::new(None)),
+            last_closes: LastCloseCache::default(),
+        }
+    }
+
+    /// Cache latest_close reads from and record_close writes to - share the session's
+    /// (TradingAppState.last_closes) so every mark agrees with the consolidator's
+    pub fn set_last_close_cache(&mut self, last_closes: LastCloseCache) {
+        self.last_closes = last_closes;
+    }
+
+    pub fn get_last_close_cache(&self) -> &LastCloseCache {
+        &self.last_closes
+    }
+
+    /// Records the close of a bar just written, for latest_close
+    pub fn record_close(
+        &self,
+        stock: &str,
+        primary_exchange: &str,
+        time: DateTime<Utc>,
+        close: f64,
+    ) {
+        self.last_closes.record(stock, primary_exchange, time, close);
+    }
+
+    /// Close of the stock's newest bar, for marking positions to market
+    /// - served from last_closes while fresh, so marking many positions in the same stock reads
+    ///   the table once
+    pub async fn latest_close(
+        &self,
+        stock: String,
+        primary_exchange: String,
+    ) -> Result<Option<f64>, String> {
+        if let Some(close) = self.last_closes.get(&stock, &primary_exchange) {
+            return Ok(Some(close));
+        }
+        let last_bar = self
+            .read_last_bar_of_stock(stock.clone(), primary_exchange.clone())
+            .await?;
+        Ok(last_bar.map(|bar| {
+            self.last_closes.record(&stock, &primary_exchange, bar.time, bar.close);
+            bar.close
+        }))
+    }
 
     delegate_all_crud_methods!(
         crud,
@@ -244,7 +242,7 @@ impl HistoricalDataCRUD {
     );
 
     pub async fn init_channel(&self) {
-        let (sender, shutdown_sender) = init_channel().await;
+        let (sender, shutdown_sender, flush_task) = init_batch_channel::<Self>().await;
         self.sender
             .lock()
             .expect("Expected to be able to acquire sender lock")
@@ -253,16 +251,40 @@ impl HistoricalDataCRUD {
             .lock()
             .expect("Expected to be able to acquire shutdown_sender lock")
             .replace(shutdown_sender);
+        self.flush_task
+            .lock()
+            .expect("Expected to be able to acquire flush_task lock")
+            .replace(flush_task);
     }
 
+    /// Flushes every row sent before the call and closes the channel
+    /// - returns once the batch writer has written them and exited
     pub async fn close_channel(&self) {
+        self.request_close();
+        let flush_task = self
+            .flush_task
+            .lock()
+            .expect("Expected to be able to acquire lock for flush_task")
+            .take();
+        if let Some(flush_task) = flush_task
+            && let Err(e) = flush_task.await
+        {
+            tracing::error!("Batch writer stopped before flushing: {}", e);
+        }
+    }
+
+    /// Signals the batch writer to flush and exit without waiting for it, for callers that
+    /// cannot await (Drop)
+    pub fn request_close(&self) {
         let sender_guard = self
             .shutdown_sender
             .lock()
             .expect("Expected to be able to acquire lock for shutdown_sender")
             .take();
-        if let Some(sender) = sender_guard {
-            sender.send(true).await;
+        if let Some(sender) = sender_guard
+            && let Err(e) = sender.try_send(true)
+        {
+            tracing::error!("Error signalling batch writer to close: {}", e);
         }
     }
 
@@ -277,6 +299,95 @@ impl HistoricalDataCRUD {
         Ok(())
     }
 
+    /// Compile-time checked alternative to create - a column typo fails the build instead of
+    /// the insert
+    pub async fn create_checked(&self, bar: &HistoricalDataFullKeys) -> Result<(), String> {
+        sqlx::query!(
+            r#"
+            INSERT INTO market_data.historical_data (
+                stock,
+                primary_exchange,
+                time,
+                open,
+                high,
+                low,
+                close,
+                volume
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            "#,
+            bar.stock,
+            bar.primary_exchange,
+            bar.time,
+            bar.open,
+            bar.high,
+            bar.low,
+            bar.close,
+            bar.volume
+        )
+        .execute(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when creating bar for {}: {}", bar.stock, e))?;
+        Ok(())
+    }
+
+    /// Compile-time checked alternative to read
+    pub async fn read_checked(
+        &self,
+        pk: &HistoricalDataPrimaryKeys,
+    ) -> Result<Option<HistoricalDataFullKeys>, String> {
+        sqlx::query_as!(
+            HistoricalDataFullKeys,
+            r#"
+            SELECT stock, primary_exchange, time, open, high, low, close, volume
+            FROM market_data.historical_data
+            WHERE stock = $1
+                AND primary_exchange = $2
+                AND time = $3;
+            "#,
+            pk.stock,
+            pk.primary_exchange,
+            pk.time
+        )
+        .fetch_optional(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when reading bar for {}: {}", pk.stock, e))
+    }
+
+    /// Compile-time checked alternative to update - None fields are left as they are
+    /// - returns the number of rows updated
+    pub async fn update_checked(
+        &self,
+        pk: &HistoricalDataPrimaryKeys,
+        update: &HistoricalDataUpdateKeys,
+    ) -> Result<u64, String> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE market_data.historical_data
+            SET open = COALESCE($4, open),
+                high = COALESCE($5, high),
+                low = COALESCE($6, low),
+                close = COALESCE($7, close),
+                volume = COALESCE($8, volume)
+            WHERE stock = $1
+                AND primary_exchange = $2
+                AND time = $3;
+            "#,
+            pk.stock,
+            pk.primary_exchange,
+            pk.time,
+            update.open,
+            update.high,
+            update.low,
+            update.close,
+            update.volume
+        )
+        .execute(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when updating bar for {}: {}", pk.stock, e))?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn read_last_n_of_stock(
         &self,
         stock: String,
@@ -326,6 +437,35 @@ impl HistoricalDataCRUD {
         })
     }
 
+    /// Newest bar of the stock starting before time, e.g. the close an option expiring that day
+    /// settles against
+    pub async fn read_last_bar_of_stock_before(
+        &self,
+        stock: String,
+        primary_exchange: String,
+        time: DateTime<Utc>,
+    ) -> Result<Option<HistoricalDataFullKeys>, String> {
+        sqlx::query_as!(
+            HistoricalDataFullKeys,
+            r#"
+            SELECT * FROM market_data.historical_data
+            WHERE stock = $1
+                AND primary_exchange = $2
+                AND time < $3
+            ORDER BY time DESC
+            LIMIT 1;
+            "#,
+            stock,
+            primary_exchange,
+            time
+        )
+        .fetch_optional(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!("Error when fetching bar before {} from HistoricalData for {} in read_last_bar_of_stock_before: {}", time, stock, e)
+        })
+    }
+
     pub async fn read_vwap(&self, stock: String, primary_exchange: String) -> Result<f64, String> {
         let opt_vwap = sqlx::query_as!(
             OptionVWAP,
@@ -530,7 +670,11 @@ impl HistoricalDataCRUD {
         .to_f64().expect("Expected close and open of the daily opens/close to be valid in get_most_recent_daily_open"))
     }
 
-    pub async fn get_daily_vol(&self, stock: String, primary_exchange: String) -> Result<f64, String> {
+    pub async fn get_daily_vol(
+        &self,
+        stock: String,
+        primary_exchange: String,
+    ) -> Result<f64, String> {
         let daily_vol = sqlx::query_scalar!(
             r#"
             SELECT rolling_volatility
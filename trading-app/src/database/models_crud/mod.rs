@@ -1,15 +1,19 @@
+pub mod account_summary;
 pub mod current_option_positions;
 pub mod current_stock_positions;
 pub mod daily_historical_data;
+pub mod equity_snapshots;
 pub mod historical_data;
 pub mod historical_options_data;
 pub mod logs;
 pub mod notification;
 pub mod open_option_orders;
 pub mod open_stock_orders;
+pub mod order_map;
 pub mod option_transactions;
 pub mod staged_commissions;
 pub mod stock_transactions;
 pub mod strategy;
+pub mod strategy_params;
 pub mod target_option_positions;
 pub mod target_stock_positions;
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
@@ -27,6 +29,7 @@ pub struct OpenOptionOrdersFullKeysRes {
 
     pub executions: Option<Vec<String>>,
     pub filled: Option<f64>,
+    pub order_snapshot: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -71,7 +74,8 @@ impl OpenOptionOrdersCRUD {
                 time,
                 quantity,
                 executions,
-                filled
+                filled,
+                order_snapshot
             FROM trading.open_option_orders
             WHERE strategy = $1;
             "#,
@@ -128,9 +132,84 @@ impl OpenOptionOrdersCRUD {
                     .clone()
                     .expect("Expected to be able to parse executions"),
                 filled: order.filled.expect("Expected to be able to parse filled"),
+                order_snapshot: order.order_snapshot.clone(),
             })
             .collect())
     }
+
+    /// All open option orders keyed by (strategy, stock), oldest order first within each key
+    pub async fn get_open_orders_by_strategy_symbol(
+        &self,
+    ) -> Result<HashMap<(String, String), Vec<OpenOptionOrdersFullKeys>>, String> {
+        let orders = sqlx::query_as::<_, OpenOptionOrdersFullKeys>(
+            "SELECT * FROM trading.open_option_orders ORDER BY strategy, stock, time, order_id",
+        )
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error when reading open option orders by strategy and stock: {}",
+                e
+            )
+        })?;
+
+        let mut grouped: HashMap<(String, String), Vec<OpenOptionOrdersFullKeys>> = HashMap::new();
+        for order in orders {
+            grouped
+                .entry((order.strategy.clone(), order.stock.clone()))
+                .or_default()
+                .push(order);
+        }
+        Ok(grouped)
+    }
+
+    /// Deletes the open option orders placed before snapshot_time whose perm_id is not in
+    /// broker_perm_ids and returns their perm_ids
+    /// - orders placed after snapshot_time may be missing from the broker's snapshot only because
+    ///   they were placed after it was taken, so they are left alone
+    /// - a row is only deleted once its filled quantity is covered by the option_transactions booked
+    ///   against its perm_id, otherwise fills would be lost with it
+    pub async fn delete_absent_orders(
+        &self,
+        broker_perm_ids: &[i32],
+        snapshot_time: DateTime<Utc>,
+    ) -> Result<Vec<i32>, String> {
+        sqlx::query_scalar(
+            r#"
+            DELETE FROM trading.open_option_orders AS o
+            WHERE o.order_perm_id <> ALL($1)
+                AND o.time < $2
+                AND o.filled <= COALESCE((
+                    SELECT ABS(SUM(t.quantity))
+                    FROM trading.option_transactions AS t
+                    WHERE t.order_perm_id = o.order_perm_id
+                ), 0)
+            RETURNING o.order_perm_id
+            "#,
+        )
+        .bind(broker_perm_ids)
+        .bind(snapshot_time)
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when deleting absent open option orders: {}", e))
+    }
+
+    /// perm_ids of the open option orders placed before snapshot_time not in broker_perm_ids - run
+    /// after delete_absent_orders, these are the ones kept for having fills not booked yet
+    pub async fn get_absent_orders(
+        &self,
+        broker_perm_ids: &[i32],
+        snapshot_time: DateTime<Utc>,
+    ) -> Result<Vec<i32>, String> {
+        sqlx::query_scalar(
+            "SELECT order_perm_id FROM trading.open_option_orders WHERE order_perm_id <> ALL($1) AND time < $2",
+        )
+        .bind(broker_perm_ids)
+        .bind(snapshot_time)
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when reading absent open option orders: {}", e))
+    }
 }
 
 pub fn get_open_option_orders_crud(
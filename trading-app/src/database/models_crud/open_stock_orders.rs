@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
@@ -20,6 +22,7 @@ pub struct OpenStockOrdersFullKeysRes {
 
     pub executions: Option<Vec<String>>,
     pub filled: Option<f64>,
+    pub order_snapshot: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -60,7 +63,8 @@ impl OpenStockOrdersCRUD {
                 time,
                 quantity,
                 executions,
-                filled
+                filled,
+                order_snapshot
             FROM trading.open_stock_orders
             WHERE strategy = $1;
             "#,
@@ -104,9 +108,84 @@ impl OpenStockOrdersCRUD {
                     .clone()
                     .expect("Expected to be able to parse executions"),
                 filled: order.filled.expect("Expected to be able to parse filled"),
+                order_snapshot: order.order_snapshot.clone(),
             })
             .collect())
     }
+
+    /// All open stock orders keyed by (strategy, stock), oldest order first within each key
+    pub async fn get_open_orders_by_strategy_symbol(
+        &self,
+    ) -> Result<HashMap<(String, String), Vec<OpenStockOrdersFullKeys>>, String> {
+        let orders = sqlx::query_as::<_, OpenStockOrdersFullKeys>(
+            "SELECT * FROM trading.open_stock_orders ORDER BY strategy, stock, time, order_id",
+        )
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Error when reading open stock orders by strategy and stock: {}",
+                e
+            )
+        })?;
+
+        let mut grouped: HashMap<(String, String), Vec<OpenStockOrdersFullKeys>> = HashMap::new();
+        for order in orders {
+            grouped
+                .entry((order.strategy.clone(), order.stock.clone()))
+                .or_default()
+                .push(order);
+        }
+        Ok(grouped)
+    }
+
+    /// Deletes the open stock orders placed before snapshot_time whose perm_id is not in
+    /// broker_perm_ids and returns their perm_ids
+    /// - orders placed after snapshot_time may be missing from the broker's snapshot only because
+    ///   they were placed after it was taken, so they are left alone
+    /// - a row is only deleted once its filled quantity is covered by the stock_transactions booked
+    ///   against its perm_id, otherwise fills would be lost with it
+    pub async fn delete_absent_orders(
+        &self,
+        broker_perm_ids: &[i32],
+        snapshot_time: DateTime<Utc>,
+    ) -> Result<Vec<i32>, String> {
+        sqlx::query_scalar(
+            r#"
+            DELETE FROM trading.open_stock_orders AS o
+            WHERE o.order_perm_id <> ALL($1)
+                AND o.time < $2
+                AND o.filled <= COALESCE((
+                    SELECT ABS(SUM(t.quantity))
+                    FROM trading.stock_transactions AS t
+                    WHERE t.order_perm_id = o.order_perm_id
+                ), 0)
+            RETURNING o.order_perm_id
+            "#,
+        )
+        .bind(broker_perm_ids)
+        .bind(snapshot_time)
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when deleting absent open stock orders: {}", e))
+    }
+
+    /// perm_ids of the open stock orders placed before snapshot_time not in broker_perm_ids - run
+    /// after delete_absent_orders, these are the ones kept for having fills not booked yet
+    pub async fn get_absent_orders(
+        &self,
+        broker_perm_ids: &[i32],
+        snapshot_time: DateTime<Utc>,
+    ) -> Result<Vec<i32>, String> {
+        sqlx::query_scalar(
+            "SELECT order_perm_id FROM trading.open_stock_orders WHERE order_perm_id <> ALL($1) AND time < $2",
+        )
+        .bind(broker_perm_ids)
+        .bind(snapshot_time)
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error when reading absent open stock orders: {}", e))
+    }
 }
 
 pub fn get_open_stock_orders_crud(
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        crud::{CRUD, CRUDTrait},
+        models::{OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys},
+    },
+    delegate_all_crud_methods,
+};
+
+/// Orders still working at the broker - their order_id is in one of the open order tables
+const OPEN_ORDER_IDS: &str = "SELECT order_id FROM trading.open_stock_orders
+    UNION SELECT order_id FROM trading.open_option_orders";
+
+#[derive(Debug, Clone)]
+pub struct OrderMapCRUD {
+    crud: CRUD<OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys>,
+}
+impl OrderMapCRUD {
+    fn new(pool: PgPool) -> Self {
+        Self {
+            crud: CRUD::<OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys>::new(
+                pool,
+                String::from("trading.order_map"),
+            ),
+        }
+    }
+
+    delegate_all_crud_methods!(
+        crud,
+        OrderMapFullKeys,
+        OrderMapPrimaryKeys,
+        OrderMapUpdateKeys
+    );
+
+    /// Orders placed since cutoff or still open - the ones updates / executions can still
+    /// arrive for
+    pub async fn read_live(&self, cutoff: DateTime<Utc>) -> Result<Vec<OrderMapFullKeys>, String> {
+        sqlx::query_as::<_, OrderMapFullKeys>(&format!(
+            "SELECT * FROM trading.order_map WHERE time >= $1 OR order_id IN ({})",
+            OPEN_ORDER_IDS
+        ))
+        .bind(cutoff)
+        .fetch_all(&self.crud.pool)
+        .await
+        .map_err(|e| format!("Error reading live orders from order_map: {}", e))
+    }
+
+    /// Deletes the orders placed before cutoff that are no longer open - returns the number
+    /// deleted
+    pub async fn delete_settled_before(&self, cutoff: DateTime<Utc>) -> Result<u64, String> {
+        sqlx::query(&format!(
+            "DELETE FROM trading.order_map WHERE time < $1 AND order_id NOT IN ({})",
+            OPEN_ORDER_IDS
+        ))
+        .bind(cutoff)
+        .execute(&self.crud.pool)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|e| format!("Error pruning order_map: {}", e))
+    }
+}
+
+pub fn get_order_map_crud(
+    pool: PgPool,
+) -> CRUD<OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys> {
+    CRUD::<OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys>::new(
+        pool,
+        String::from("trading.order_map"),
+    )
+}
+
+pub fn get_specific_order_map_crud(pool: PgPool) -> OrderMapCRUD {
+    OrderMapCRUD::new(pool)
+}
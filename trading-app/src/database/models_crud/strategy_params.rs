@@ -0,0 +1,15 @@
+use sqlx::PgPool;
+
+use crate::database::{
+    crud::{CRUD, CRUDTrait},
+    models::{StrategyParamsFullKeys, StrategyParamsPrimaryKeys, StrategyParamsUpdateKeys},
+};
+
+pub fn get_strategy_params_crud(
+    pool: PgPool,
+) -> CRUD<StrategyParamsFullKeys, StrategyParamsPrimaryKeys, StrategyParamsUpdateKeys> {
+    CRUD::<StrategyParamsFullKeys, StrategyParamsPrimaryKeys, StrategyParamsUpdateKeys>::new(
+        pool,
+        String::from("trading.strategy_params"),
+    )
+}
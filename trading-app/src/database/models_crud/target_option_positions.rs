@@ -159,6 +159,37 @@ impl TargetOptionPositionsCRUD {
             })
             .collect())
     }
+
+    /// Sets every option target of strategy to a quantity of 0, adding a 0 target for each current
+    /// position it has no target for - returns the number of targets set
+    pub async fn zero_targets_for_strat(&self, strategy: &str) -> Result<u64, String> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH zeroed AS (
+                UPDATE trading.target_option_positions SET quantity = 0
+                WHERE strategy = $1
+                RETURNING 1
+            ), added AS (
+                INSERT INTO trading.target_option_positions
+                    (strategy, stock, primary_exchange, avg_price, quantity, expiry, strike,
+                    multiplier, option_type)
+                SELECT strategy, stock, primary_exchange, avg_price, 0, expiry, strike,
+                    multiplier, option_type
+                FROM trading.current_option_positions
+                WHERE strategy = $1
+                ON CONFLICT (stock, primary_exchange, strategy, expiry, strike, multiplier,
+                    option_type) DO NOTHING
+                RETURNING 1
+            )
+            SELECT (SELECT COUNT(*) FROM zeroed) + (SELECT COUNT(*) FROM added)
+            "#,
+        )
+        .bind(strategy)
+        .fetch_one(&self.crud.pool)
+        .await
+        .map(|count| count as u64)
+        .map_err(|e| format!("Error zeroing option targets of {}: {}", strategy, e))
+    }
 }
 
 pub fn get_specific_target_option_positions_crud(pool: PgPool) -> TargetOptionPositionsCRUD {
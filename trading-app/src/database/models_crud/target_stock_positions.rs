@@ -169,6 +169,34 @@ impl TargetStockPositionsCRUD {
             })
             .collect())
     }
+
+    /// Sets every stock target of strategy to a quantity of 0, adding a 0 target for each current
+    /// position it has no target for - returns the number of targets set
+    pub async fn zero_targets_for_strat(&self, strategy: &str) -> Result<u64, String> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH zeroed AS (
+                UPDATE trading.target_stock_positions SET quantity = 0
+                WHERE strategy = $1
+                RETURNING 1
+            ), added AS (
+                INSERT INTO trading.target_stock_positions
+                    (strategy, stock, primary_exchange, avg_price, quantity)
+                SELECT strategy, stock, primary_exchange, avg_price, 0
+                FROM trading.current_stock_positions
+                WHERE strategy = $1
+                ON CONFLICT (stock, primary_exchange, strategy) DO NOTHING
+                RETURNING 1
+            )
+            SELECT (SELECT COUNT(*) FROM zeroed) + (SELECT COUNT(*) FROM added)
+            "#,
+        )
+        .bind(strategy)
+        .fetch_one(&self.crud.pool)
+        .await
+        .map(|count| count as u64)
+        .map_err(|e| format!("Error zeroing stock targets of {}: {}", strategy, e))
+    }
 }
 
 pub fn get_target_stock_positions_crud(
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use sqlx::PgPool;
+
+use crate::database::models_crud::{
+    account_summary::get_account_summary_crud,
+    current_option_positions::get_current_option_positions_crud,
+    current_stock_positions::get_current_stock_positions_crud,
+    daily_historical_data::get_daily_historical_data_crud,
+    equity_snapshots::get_equity_snapshots_crud, historical_data::get_historical_data_crud,
+    historical_options_data::get_historical_options_data_crud, logs::get_logs_crud,
+    notification::get_notification_crud, open_option_orders::get_open_option_orders_crud,
+    open_stock_orders::get_open_stock_orders_crud,
+    option_transactions::get_option_transactions_crud, order_map::get_order_map_crud,
+    staged_commissions::get_staged_commissions_crud,
+    stock_transactions::get_stock_transactions_crud, strategy::get_strategy_crud,
+    strategy_params::get_strategy_params_crud,
+    target_option_positions::get_target_option_positions_crud,
+    target_stock_positions::get_target_stock_positions_crud,
+};
+
+/// A table, schema qualified (e.g. trading.strategy), with the columns its model reads / writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelTable {
+    pub table: String,
+    pub columns: Vec<&'static str>,
+}
+
+impl ModelTable {
+    pub fn new(table: impl Into<String>, columns: Vec<&'static str>) -> Self {
+        Self {
+            table: table.into(),
+            columns,
+        }
+    }
+
+    /// (schema, table name) - tables without a schema are in public
+    fn schema_and_name(&self) -> (&str, &str) {
+        self.table
+            .split_once('.')
+            .unwrap_or(("public", self.table.as_str()))
+    }
+}
+
+/// Where a model and the migrated schema disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaMismatch {
+    MissingTable { table: String },
+    MissingColumn { table: String, column: String },
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaMismatch::MissingTable { table } => write!(f, "{}: table does not exist", table),
+            SchemaMismatch::MissingColumn { table, column } => {
+                write!(f, "{}: column {} does not exist", table, column)
+            }
+        }
+    }
+}
+
+/// Mismatches of model against the columns its table actually has - a table with no columns is
+/// taken to not exist
+pub fn schema_mismatches(model: &ModelTable, table_columns: &[String]) -> Vec<SchemaMismatch> {
+    if table_columns.is_empty() {
+        return vec![SchemaMismatch::MissingTable {
+            table: model.table.clone(),
+        }];
+    }
+    model
+        .columns
+        .iter()
+        .filter(|column| !table_columns.iter().any(|existing| existing == *column))
+        .map(|column| SchemaMismatch::MissingColumn {
+            table: model.table.clone(),
+            column: column.to_string(),
+        })
+        .collect()
+}
+
+/// Columns the table has in the database, per information_schema
+pub async fn table_columns(pool: &PgPool, model: &ModelTable) -> Result<Vec<String>, String> {
+    let (schema, name) = model.schema_and_name();
+    sqlx::query_scalar::<_, String>(
+        "SELECT column_name::text FROM information_schema.columns
+        WHERE table_schema = $1 AND table_name = $2",
+    )
+    .bind(schema)
+    .bind(name)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read the columns of {}: {}", model.table, e))
+}
+
+/// Every mismatch between models and the tables they are registered against
+pub async fn check_schema(
+    pool: &PgPool,
+    models: &[ModelTable],
+) -> Result<Vec<SchemaMismatch>, String> {
+    let mut mismatches = Vec::new();
+    for model in models {
+        let columns = table_columns(pool, model).await?;
+        mismatches.extend(schema_mismatches(model, &columns));
+    }
+    Ok(mismatches)
+}
+
+/// Fails with every mismatch listed if any model has drifted from the migrated schema - run after
+/// the migrations so a drifted model is caught at startup rather than by its first query
+pub async fn validate_schema(pool: &PgPool, models: &[ModelTable]) -> Result<(), String> {
+    let mismatches = check_schema(pool, models).await?;
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Models do not match the database schema:\n{}",
+        mismatches
+            .iter()
+            .map(|mismatch| format!("  - {}", mismatch))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// Every model with the table its CRUD in models_crud is built on
+pub fn registered_models(pool: PgPool) -> Vec<ModelTable> {
+    vec![
+        get_strategy_crud(pool.clone()).model_table(),
+        get_strategy_params_crud(pool.clone()).model_table(),
+        get_current_stock_positions_crud(pool.clone()).model_table(),
+        get_current_option_positions_crud(pool.clone()).model_table(),
+        get_target_stock_positions_crud(pool.clone()).model_table(),
+        get_target_option_positions_crud(pool.clone()).model_table(),
+        get_open_stock_orders_crud(pool.clone()).model_table(),
+        get_open_option_orders_crud(pool.clone()).model_table(),
+        get_stock_transactions_crud(pool.clone()).model_table(),
+        get_option_transactions_crud(pool.clone()).model_table(),
+        get_staged_commissions_crud(pool.clone()).model_table(),
+        get_order_map_crud(pool.clone()).model_table(),
+        get_equity_snapshots_crud(pool.clone()).model_table(),
+        get_account_summary_crud(pool.clone()).model_table(),
+        get_notification_crud(pool.clone()).model_table(),
+        get_historical_data_crud(pool.clone()).model_table(),
+        get_daily_historical_data_crud(pool.clone()).model_table(),
+        get_historical_options_data_crud(pool.clone()).model_table(),
+        get_logs_crud(pool).model_table(),
+    ]
+}
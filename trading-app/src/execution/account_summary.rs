@@ -0,0 +1,122 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use ibapi::{Client, accounts::AccountSummaries};
+use sqlx::PgPool;
+
+use crate::database::{
+    crud::CRUDTrait, models::AccountSummaryFullKeys,
+    models_crud::account_summary::get_account_summary_crud,
+};
+
+/// Account summary tags requested from IBKR, one per column of trading.account_summary
+pub const ACCOUNT_SUMMARY_TAGS: [&str; 7] = [
+    "NetLiquidation",
+    "TotalCashValue",
+    "BuyingPower",
+    "AvailableFunds",
+    "ExcessLiquidity",
+    "InitMarginReq",
+    "MaintMarginReq",
+];
+
+/// A single tag of an account's summary as IBKR reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSummaryValue {
+    pub account: String,
+    pub tag: String,
+    pub value: String,
+    pub currency: String,
+}
+
+/// One row per account of the tags reported for it, recorded at time
+/// - a tag that isn't reported or doesn't parse as a number is left empty
+pub fn account_summaries(
+    time: DateTime<Utc>,
+    values: &[AccountSummaryValue],
+) -> Vec<AccountSummaryFullKeys> {
+    let mut summaries: BTreeMap<String, AccountSummaryFullKeys> = BTreeMap::new();
+    for value in values {
+        let summary =
+            summaries
+                .entry(value.account.clone())
+                .or_insert_with(|| AccountSummaryFullKeys {
+                    account: value.account.clone(),
+                    time,
+                    currency: None,
+                    net_liquidation: None,
+                    total_cash_value: None,
+                    buying_power: None,
+                    available_funds: None,
+                    excess_liquidity: None,
+                    init_margin_req: None,
+                    maint_margin_req: None,
+                });
+        let column = match value.tag.as_str() {
+            "NetLiquidation" => &mut summary.net_liquidation,
+            "TotalCashValue" => &mut summary.total_cash_value,
+            "BuyingPower" => &mut summary.buying_power,
+            "AvailableFunds" => &mut summary.available_funds,
+            "ExcessLiquidity" => &mut summary.excess_liquidity,
+            "InitMarginReq" => &mut summary.init_margin_req,
+            "MaintMarginReq" => &mut summary.maint_margin_req,
+            _ => continue,
+        };
+        match value.value.trim().parse::<f64>() {
+            Ok(amount) => *column = Some(amount),
+            Err(e) => tracing::warn!(
+                "Ignoring {} of {} for account {}: {}",
+                value.tag,
+                value.value,
+                value.account,
+                e
+            ),
+        }
+        if summary.currency.is_none() && !value.currency.is_empty() {
+            summary.currency = Some(value.currency.clone());
+        }
+    }
+    summaries.into_values().collect()
+}
+
+/// Every account's summary tags (ACCOUNT_SUMMARY_TAGS) - blocks until IBKR has sent them all
+pub fn request_account_summary(client: &Client) -> Result<Vec<AccountSummaryValue>, String> {
+    let subscription = client
+        .account_summary("All", &ACCOUNT_SUMMARY_TAGS)
+        .map_err(|e| format!("Error requesting account summary: {}", e))?;
+    let mut values = Vec::new();
+    for update in subscription.iter() {
+        match update {
+            AccountSummaries::Summary(summary) => values.push(AccountSummaryValue {
+                account: summary.account,
+                tag: summary.tag,
+                value: summary.value,
+                currency: summary.currency,
+            }),
+            AccountSummaries::End => break,
+        }
+    }
+    Ok(values)
+}
+
+/// Requests the account summary from IBKR and stores it in trading.account_summary as of time -
+/// called at the session open and close
+/// - returns the number of accounts recorded
+pub async fn record_account_summary(
+    pool: PgPool,
+    client: Arc<Client>,
+    time: DateTime<Utc>,
+) -> Result<usize, String> {
+    let values = tokio::task::spawn_blocking(move || request_account_summary(&client))
+        .await
+        .map_err(|e| format!("Account summary request panicked: {}", e))??;
+    let summaries = account_summaries(time, &values);
+    let account_summary_crud = get_account_summary_crud(pool);
+    for summary in &summaries {
+        account_summary_crud
+            .create_or_ignore(summary)
+            .await
+            .map_err(|e| format!("Failed to insert into AccountSummary: {}", e))?;
+    }
+    Ok(summaries.len())
+}
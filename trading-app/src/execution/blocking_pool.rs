@@ -0,0 +1,114 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread,
+};
+
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Fixed set of OS threads the blocking IBKR calls (placing / cancelling orders) run on
+/// - jobs beyond the pool size queue up instead of each getting a new thread
+pub struct BlockingPool {
+    sender: Mutex<Sender<Job>>,
+    size: usize,
+}
+
+impl BlockingPool {
+    /// Starts size worker threads (at least 1)
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for worker in 0..size {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("ibkr-blocking-{}", worker))
+                .spawn(move || run_worker(receiver))
+                .expect("Expected to be able to spawn BlockingPool worker thread");
+        }
+        Self {
+            sender: Mutex::new(sender),
+            size,
+        }
+    }
+
+    /// Reads IBKR_BLOCKING_THREADS - unset keeps the default of 4 threads
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self::new(Self::size_from_env()?))
+    }
+
+    /// Pool size IBKR_BLOCKING_THREADS asks for, 4 if unset
+    pub fn size_from_env() -> Result<usize, String> {
+        match std::env::var("IBKR_BLOCKING_THREADS") {
+            Ok(size) => size
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| format!("IBKR_BLOCKING_THREADS must be a number of threads: {}", e)),
+            Err(_) => Ok(4),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Queues job to run on the next free worker
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        let sender = self
+            .sender
+            .lock()
+            .expect("Expected to be able to acquire lock for BlockingPool.sender");
+        if sender.send(Box::new(job)).is_err() {
+            tracing::error!("BlockingPool workers have stopped, dropping job");
+        }
+    }
+
+    /// Runs job on the pool and waits for its result without blocking the async runtime
+    pub async fn run<F, T>(&self, job: F) -> Result<T, String>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.execute(move || {
+            // caller having stopped waiting just means nobody needs the result
+            let _ = result_sender.send(job());
+        });
+        result_receiver
+            .await
+            .map_err(|_| "BlockingPool job panicked before returning".to_string())
+    }
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        Self::new(4)
+    }
+}
+
+fn run_worker(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = {
+            let receiver = match receiver.lock() {
+                Ok(receiver) => receiver,
+                Err(e) => {
+                    tracing::error!("BlockingPool worker failed to acquire receiver: {}", e);
+                    return;
+                }
+            };
+            match receiver.recv() {
+                Ok(job) => job,
+                // pool was dropped
+                Err(_) => return,
+            }
+        };
+        // a panicking job only takes itself down, not the worker
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).is_err() {
+            tracing::error!("BlockingPool job panicked");
+        }
+    }
+}
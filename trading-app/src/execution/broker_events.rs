@@ -0,0 +1,151 @@
+use ibapi::orders::OrderUpdate;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+use crate::execution::order_map::order_snapshot;
+
+/// Kind of raw event kept in trading.broker_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrokerEventType {
+    Execution,
+    Order,
+    Commission,
+}
+
+impl BrokerEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrokerEventType::Execution => "execution",
+            BrokerEventType::Order => "order",
+            BrokerEventType::Commission => "commission",
+        }
+    }
+}
+
+/// An order update as it is kept in trading.broker_events
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerEvent {
+    pub event_type: BrokerEventType,
+    pub order_id: Option<i32>,
+    pub execution_id: String,
+    pub payload: Value,
+}
+
+impl BrokerEvent {
+    /// The event logged for order_update - None for notices, which handle_broker_notice acts on
+    pub fn from_order_update(order_update: &OrderUpdate) -> Option<BrokerEvent> {
+        match order_update {
+            OrderUpdate::OrderStatus(status) => Some(BrokerEvent {
+                event_type: BrokerEventType::Order,
+                order_id: Some(status.order_id),
+                execution_id: String::new(),
+                payload: json!({
+                    "order_id": status.order_id,
+                    "perm_id": status.perm_id,
+                    "parent_id": status.parent_id,
+                    "client_id": status.client_id,
+                    "status": status.status,
+                    "filled": status.filled,
+                    "remaining": status.remaining,
+                    "average_fill_price": status.average_fill_price,
+                    "last_fill_price": status.last_fill_price,
+                    "why_held": status.why_held,
+                    "market_cap_price": status.market_cap_price,
+                }),
+            }),
+            OrderUpdate::OpenOrder(open_order) => {
+                let mut payload = order_snapshot(&open_order.contract, &open_order.order)
+                    .unwrap_or_else(|e| json!({ "snapshot_error": e }));
+                payload["order_id"] = json!(open_order.order_id);
+                payload["order_state"] = json!({
+                    "status": open_order.order_state.status,
+                    "warning_text": open_order.order_state.warning_text,
+                    "completed_time": open_order.order_state.completed_time,
+                    "completed_status": open_order.order_state.completed_status,
+                });
+                Some(BrokerEvent {
+                    event_type: BrokerEventType::Order,
+                    order_id: Some(open_order.order_id),
+                    execution_id: String::new(),
+                    payload,
+                })
+            }
+            OrderUpdate::ExecutionData(execution_data) => {
+                let execution = &execution_data.execution;
+                Some(BrokerEvent {
+                    event_type: BrokerEventType::Execution,
+                    order_id: Some(execution.order_id),
+                    execution_id: execution.execution_id.clone(),
+                    payload: json!({
+                        "request_id": execution_data.request_id,
+                        "contract": execution_data.contract,
+                        "order_id": execution.order_id,
+                        "perm_id": execution.perm_id,
+                        "client_id": execution.client_id,
+                        "execution_id": execution.execution_id,
+                        "time": execution.time,
+                        "account_number": execution.account_number,
+                        "exchange": execution.exchange,
+                        "side": execution.side,
+                        "shares": execution.shares,
+                        "price": execution.price,
+                        "cumulative_quantity": execution.cumulative_quantity,
+                        "average_price": execution.average_price,
+                        "order_reference": execution.order_reference,
+                        "liquidation": execution.liquidation,
+                        "last_liquidity": execution.last_liquidity,
+                    }),
+                })
+            }
+            OrderUpdate::CommissionReport(commission_report) => Some(BrokerEvent {
+                event_type: BrokerEventType::Commission,
+                order_id: None,
+                execution_id: commission_report.execution_id.clone(),
+                payload: json!({
+                    "execution_id": commission_report.execution_id,
+                    "commission": commission_report.commission,
+                    "currency": commission_report.currency,
+                    "realized_pnl": commission_report.realized_pnl,
+                    "yields": commission_report.yields,
+                    "yield_redemption_date": commission_report.yield_redemption_date,
+                }),
+            }),
+            OrderUpdate::Message(_) => None,
+        }
+    }
+}
+
+/// Appends event to trading.broker_events
+/// - an event without an order_id (a commission report) is filed under the order of the
+///   execution it reports on, which IBKR sends before it
+pub async fn record_broker_event(pool: &PgPool, event: &BrokerEvent) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO trading.broker_events (event_type, order_id, execution_id, payload)
+        VALUES (
+            $1,
+            COALESCE(
+                $2,
+                (SELECT order_id FROM trading.broker_events
+                WHERE execution_id = $3 AND $3 <> '' AND order_id IS NOT NULL
+                LIMIT 1)
+            ),
+            $3,
+            $4
+        )",
+    )
+    .bind(event.event_type.as_str())
+    .bind(event.order_id)
+    .bind(&event.execution_id)
+    .bind(&event.payload)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        format!(
+            "Failed to record {} event of order {:?}: {}",
+            event.event_type.as_str(),
+            event.order_id,
+            e
+        )
+    })?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use ibapi::{Client, orders::CancelOrder};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys, OpenStockOrdersFullKeys,
+            OpenStockOrdersPrimaryKeys,
+        },
+        models_crud::{
+            open_option_orders::get_open_option_orders_crud,
+            open_stock_orders::get_open_stock_orders_crud,
+        },
+    },
+    execution::{
+        blocking_pool::BlockingPool,
+        notices::{BrokerNotice, NoticeKind},
+    },
+};
+
+/// Order statuses IBKR reports for an order that is no longer working after a cancel
+const CANCELLED_STATUSES: [&str; 2] = ["Cancelled", "ApiCancelled"];
+
+/// Identifies a single open order - either id is enough
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelRequest {
+    pub order_id: Option<i32>,
+    pub perm_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelResponse {
+    pub order_id: i32,
+    pub perm_id: i32,
+    /// Status reported by IBKR for the cancel, e.g. Cancelled / PendingCancel
+    pub status: String,
+    /// Whether the open order row was removed, i.e. IBKR confirmed the cancel
+    pub removed: bool,
+}
+
+/// Sends a cancel for an order to the broker and returns the status it reports
+/// - implemented for ibapi::Client, stubbed out in tests
+pub trait OrderCanceller {
+    fn cancel_order(&self, order_id: i32) -> Result<String, String>;
+}
+
+impl OrderCanceller for Client {
+    fn cancel_order(&self, order_id: i32) -> Result<String, String> {
+        let subscription = Client::cancel_order(self, order_id, "").map_err(|e| {
+            format!(
+                "Failed to send cancel for order {} to IBKR: {}",
+                order_id, e
+            )
+        })?;
+        match subscription.next() {
+            Some(CancelOrder::OrderStatus(order_status)) => Ok(order_status.status),
+            // the cancel subscription is per order, so its notices are about order_id
+            Some(CancelOrder::Notice(notice)) => {
+                let notice = BrokerNotice::new(Some(order_id), notice.code, notice.message);
+                match notice.kind() {
+                    NoticeKind::OrderCancelled => Ok("Cancelled".to_string()),
+                    _ => Err(format!(
+                        "IBKR rejected cancel for order {}: {}",
+                        order_id, notice.message
+                    )),
+                }
+            }
+            None => Err(format!(
+                "No response from IBKR for cancel of order {}",
+                order_id
+            )),
+        }
+    }
+}
+
+/// Sends the cancel for order_id on the blocking pool - cancel_order waits on IBKR's reply, which
+/// would otherwise hold up the async runtime
+async fn dispatch_cancel<C: OrderCanceller + Send + Sync + 'static>(
+    blocking_pool: &BlockingPool,
+    canceller: &Arc<C>,
+    order_id: i32,
+) -> Result<String, String> {
+    let canceller = canceller.clone();
+    blocking_pool
+        .run(move || canceller.cancel_order(order_id))
+        .await?
+}
+
+/// Cancels a single open order of any strategy
+/// - looked up in OpenStockOrders then OpenOptionOrders by order_id / perm_id
+/// - the open order row is only removed once IBKR confirms the cancel - a PendingCancel is left
+///   for the order update stream to clean up
+pub async fn cancel_open_order<C: OrderCanceller + Send + Sync + 'static>(
+    pool: PgPool,
+    blocking_pool: &BlockingPool,
+    canceller: Arc<C>,
+    request: &CancelRequest,
+) -> Result<CancelResponse, String> {
+    if request.order_id.is_none() && request.perm_id.is_none() {
+        return Err("Either order_id or perm_id has to be given to cancel an order".to_string());
+    }
+
+    let open_stock_order = sqlx::query_as::<_, OpenStockOrdersFullKeys>(
+        "SELECT * FROM trading.open_stock_orders WHERE order_id = $1 OR order_perm_id = $2",
+    )
+    .bind(request.order_id)
+    .bind(request.perm_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| format!("Failed to read OpenStockOrders: {}", e))?;
+    if let Some(open_order) = open_stock_order {
+        let status = dispatch_cancel(blocking_pool, &canceller, open_order.order_id).await?;
+        let removed = CANCELLED_STATUSES.contains(&status.as_str());
+        if removed {
+            get_open_stock_orders_crud(pool)
+                .delete(&OpenStockOrdersPrimaryKeys {
+                    order_perm_id: open_order.order_perm_id,
+                    order_id: open_order.order_id,
+                })
+                .await
+                .map_err(|e| format!("Failed to delete entry in OpenStockOrders: {}", e))?;
+        }
+        return Ok(CancelResponse {
+            order_id: open_order.order_id,
+            perm_id: open_order.order_perm_id,
+            status,
+            removed,
+        });
+    }
+
+    let open_option_order = sqlx::query_as::<_, OpenOptionOrdersFullKeys>(
+        "SELECT * FROM trading.open_option_orders WHERE order_id = $1 OR order_perm_id = $2",
+    )
+    .bind(request.order_id)
+    .bind(request.perm_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| format!("Failed to read OpenOptionOrders: {}", e))?;
+    if let Some(open_order) = open_option_order {
+        let status = dispatch_cancel(blocking_pool, &canceller, open_order.order_id).await?;
+        let removed = CANCELLED_STATUSES.contains(&status.as_str());
+        if removed {
+            get_open_option_orders_crud(pool)
+                .delete(&OpenOptionOrdersPrimaryKeys {
+                    order_perm_id: open_order.order_perm_id,
+                    order_id: open_order.order_id,
+                })
+                .await
+                .map_err(|e| format!("Failed to delete entry in OpenOptionOrders: {}", e))?;
+        }
+        return Ok(CancelResponse {
+            order_id: open_order.order_id,
+            perm_id: open_order.order_perm_id,
+            status,
+            removed,
+        });
+    }
+
+    Err(format!(
+        "No open order found for order_id {:?} / perm_id {:?}",
+        request.order_id, request.perm_id
+    ))
+}
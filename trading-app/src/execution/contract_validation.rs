@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ibapi::{Client, prelude::Contract};
+
+use crate::{strategy::strategy::StrategyExecutor, unlock};
+
+/// Resolves a contract against the broker - implemented for ibapi::Client, stubbed out in tests
+pub trait ContractValidator {
+    /// The broker's contract matching contract, None if there is no such contract
+    fn validate_contract(&self, contract: &Contract) -> Option<Contract>;
+}
+
+impl ContractValidator for Client {
+    fn validate_contract(&self, contract: &Contract) -> Option<Contract> {
+        match self.contract_details(contract) {
+            Ok(validated_contracts) => validated_contracts
+                .first()
+                .map(|details| details.contract.clone()),
+            Err(e) => {
+                tracing::error!(
+                    "Error occurred requesting contract details for {}: {}",
+                    contract.symbol,
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Identifies a contract as declared, before the broker has filled in its details
+pub fn validation_key(contract: &Contract) -> String {
+    format!(
+        "{:?}|{}|{}|{}|{}|{}",
+        contract.security_type,
+        contract.symbol,
+        contract.primary_exchange,
+        contract.last_trade_date_or_contract_month,
+        contract.strike,
+        contract.right
+    )
+}
+
+/// Result of validating each contract, so contract details are only requested once per contract
+/// - clones share the same results
+#[derive(Debug, Clone, Default)]
+pub struct ContractValidationCache(Arc<Mutex<HashMap<String, Option<Contract>>>>);
+
+impl ContractValidationCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached validation of contract, requested from validator if not cached yet
+    /// - a contract that failed validation stays invalid for as long as the cache lives
+    pub fn validate<V: ContractValidator + ?Sized>(
+        &self,
+        validator: &V,
+        contract: &Contract,
+    ) -> Result<Option<Contract>, String> {
+        let key = validation_key(contract);
+        {
+            let validated = unlock!(
+                self.0,
+                "validated_contracts",
+                "ContractValidationCache.validate"
+            );
+            if let Some(validated_contract) = validated.get(&key) {
+                return Ok(validated_contract.clone());
+            }
+        }
+
+        // requested without holding the lock, as MinTickCache does
+        let validated_contract = validator.validate_contract(contract);
+        let mut validated = unlock!(
+            self.0,
+            "validated_contracts",
+            "ContractValidationCache.validate"
+        );
+        validated.insert(key, validated_contract.clone());
+        Ok(validated_contract)
+    }
+}
+
+/// Contract a strategy declared that the broker doesn't know of
+#[derive(Debug, Clone)]
+pub struct InvalidContract {
+    pub strategy: String,
+    pub contract: Contract,
+}
+
+/// Every contract declared by strategies (get_contracts) that fails validation, each logged
+pub fn invalid_strategy_contracts<T: StrategyExecutor, V: ContractValidator + ?Sized>(
+    strategies: &[T],
+    validator: &V,
+    validated_contracts: &ContractValidationCache,
+) -> Vec<InvalidContract> {
+    let mut invalid_contracts = Vec::new();
+    for strategy in strategies {
+        for contract in strategy.get_contracts() {
+            if !matches!(
+                validated_contracts.validate(validator, &contract),
+                Ok(Some(_))
+            ) {
+                tracing::error!(
+                    "Contract {} ({:?}) of strategy {} failed validation - it won't be traded",
+                    contract.symbol,
+                    contract.security_type,
+                    strategy.get_name()
+                );
+                invalid_contracts.push(InvalidContract {
+                    strategy: strategy.get_name(),
+                    contract,
+                });
+            }
+        }
+    }
+    invalid_contracts
+}
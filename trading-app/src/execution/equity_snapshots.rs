@@ -0,0 +1,287 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::database::{
+    crud::CRUDTrait,
+    models::{EquitySnapshotsPrimaryKeys, EquitySnapshotsUpdateKeys, OptionType, multiplier_value},
+    models_crud::{
+        current_option_positions::get_current_option_positions_crud,
+        current_stock_positions::get_current_stock_positions_crud,
+        equity_snapshots::get_specific_equity_snapshots_crud,
+        historical_data::{LastCloseCache, get_specific_historical_data_crud},
+        historical_options_data::get_specific_historical_options_data_crud,
+        strategy::get_strategy_crud,
+    },
+};
+
+/// A position as it is marked in an equity snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionMark {
+    pub quantity: f64,
+    pub avg_price: f64,
+    /// Close of the contract's last bar - None marks the position at avg_price instead
+    pub last_price: Option<f64>,
+    /// 1 for stocks, the contract multiplier for options
+    pub multiplier: f64,
+}
+
+impl PositionMark {
+    /// Signed value of the position - negative for a short, which owes its mark to close
+    pub fn value(&self) -> f64 {
+        self.quantity * self.last_price.unwrap_or(self.avg_price) * self.multiplier
+    }
+}
+
+/// Cash plus every position marked at its last price
+pub fn marked_value(cash: f64, positions: &[PositionMark]) -> f64 {
+    net_liquidation(cash, positions).value()
+}
+
+/// Net liquidation value split into what it is made of
+/// - premium received for a short option sits in cash, and what it would cost to buy the option
+///   back is carried as a liability, so selling an option alone does not raise the value
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetLiquidation {
+    pub cash: f64,
+    pub long_value: f64,
+    /// What closing every short position would cost at its mark, as a positive amount
+    pub short_liability: f64,
+}
+
+impl NetLiquidation {
+    pub fn value(&self) -> f64 {
+        self.cash + self.long_value - self.short_liability
+    }
+}
+
+pub fn net_liquidation(cash: f64, positions: &[PositionMark]) -> NetLiquidation {
+    positions.iter().fold(
+        NetLiquidation {
+            cash,
+            ..Default::default()
+        },
+        |mut net, position| {
+            let value = position.value();
+            if value < 0.0 {
+                net.short_liability -= value;
+            } else {
+                net.long_value += value;
+            }
+            net
+        },
+    )
+}
+
+pub use shared::options::OptionSettlement;
+
+/// Settles quantity contracts of an option against the underlying's close on its expiry date
+/// - in the money positions are exercised (long) or assigned (short) at strike, anything else
+///   expires worthless and settles to nothing
+/// - the same settlement the backend values expired options with, see shared::options
+pub fn settle_option(
+    option_type: &OptionType,
+    strike: f64,
+    multiplier: f64,
+    quantity: f64,
+    underlying_close: f64,
+) -> OptionSettlement {
+    shared::options::settle_option(
+        option_type.into(),
+        strike,
+        multiplier,
+        quantity,
+        underlying_close,
+    )
+}
+
+/// Whether an option with an IBKR expiry (YYYYMMDD) has expired by time
+/// - an expiry that does not parse is never treated as expired
+pub fn is_expired(expiry: &str, time: DateTime<Utc>) -> bool {
+    NaiveDate::parse_from_str(expiry.trim(), "%Y%m%d")
+        .map(|expiry| expiry < time.date_naive())
+        .unwrap_or(false)
+}
+
+/// Writes a trading.equity_snapshots row at time for every strategy
+/// - cash is the strategy's initial_capital less the net cash spent on its transactions
+/// - expired option positions are settled into stock at their strike against the underlying's
+///   close on the expiry date, see settle_option
+/// - stocks are marked at their close in last_closes, read from their last bar once it expires
+/// - returns the value written for each strategy
+pub async fn write_equity_snapshots(
+    pool: PgPool,
+    last_closes: LastCloseCache,
+    time: DateTime<Utc>,
+) -> Result<HashMap<String, f64>, String> {
+    let strategies = get_strategy_crud(pool.clone())
+        .read_all()
+        .await
+        .map_err(|e| format!("Failed to read strategies for equity snapshots: {}", e))?
+        .unwrap_or_default();
+    let stock_positions = get_current_stock_positions_crud(pool.clone())
+        .read_all()
+        .await
+        .map_err(|e| format!("Failed to read stock positions for equity snapshots: {}", e))?
+        .unwrap_or_default();
+    let option_positions = get_current_option_positions_crud(pool.clone())
+        .read_all()
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to read option positions for equity snapshots: {}",
+                e
+            )
+        })?
+        .unwrap_or_default();
+    let equity_snapshots_crud = get_specific_equity_snapshots_crud(pool.clone());
+    let cash_spent = equity_snapshots_crud.get_cash_spent_by_strategy().await?;
+
+    let mut marks: HashMap<String, Vec<PositionMark>> = HashMap::new();
+    let mut historical_data_crud = get_specific_historical_data_crud(pool.clone());
+    historical_data_crud.set_last_close_cache(last_closes);
+    for position in stock_positions {
+        let last_price = historical_data_crud
+            .latest_close(position.stock.clone(), position.primary_exchange.clone())
+            .await?;
+        marks
+            .entry(position.strategy)
+            .or_default()
+            .push(PositionMark {
+                quantity: position.quantity,
+                avg_price: position.avg_price,
+                last_price,
+                multiplier: 1.0,
+            });
+    }
+    let historical_options_data_crud = get_specific_historical_options_data_crud(pool.clone());
+    let mut settlement_cash: HashMap<String, f64> = HashMap::new();
+    for position in option_positions {
+        let multiplier = multiplier_value(&position.multiplier)
+            .map_err(|e| format!("Option position in {}: {}", position.stock, e))?;
+        // until the broker books the exercise / assignment, an expired position is modelled as
+        // settled against the underlying's close on the expiry date
+        if is_expired(&position.expiry, time) {
+            let underlying_price = match NaiveDate::parse_from_str(position.expiry.trim(), "%Y%m%d")
+            {
+                Ok(expiry) => historical_data_crud
+                    .read_last_bar_of_stock_before(
+                        position.stock.clone(),
+                        position.primary_exchange.clone(),
+                        shared::options::settlement_cutoff(expiry),
+                    )
+                    .await?
+                    .map(|bar| bar.close),
+                Err(_) => None,
+            };
+            if let Some(underlying_price) = underlying_price {
+                let settlement = settle_option(
+                    &position.option_type,
+                    position.strike,
+                    multiplier,
+                    position.quantity,
+                    underlying_price,
+                );
+                *settlement_cash
+                    .entry(position.strategy.clone())
+                    .or_default() += settlement.cash;
+                if settlement.stock_quantity != 0.0 {
+                    marks
+                        .entry(position.strategy)
+                        .or_default()
+                        .push(PositionMark {
+                            quantity: settlement.stock_quantity,
+                            avg_price: position.strike,
+                            last_price: Some(underlying_price),
+                            multiplier: 1.0,
+                        });
+                }
+                continue;
+            }
+            tracing::warn!(
+                "No bar of {} to settle its expired {} option against, marking the option instead",
+                position.stock,
+                position.expiry
+            );
+        }
+        let last_price = historical_options_data_crud
+            .read_last_bar_of_contract(
+                position.stock.clone(),
+                position.primary_exchange.clone(),
+                position.expiry.clone(),
+                position.strike,
+                position.multiplier.clone(),
+                position.option_type.clone(),
+            )
+            .await?
+            .map(|bar| bar.close);
+        marks
+            .entry(position.strategy)
+            .or_default()
+            .push(PositionMark {
+                quantity: position.quantity,
+                avg_price: position.avg_price,
+                last_price,
+                multiplier,
+            });
+    }
+
+    let mut values = HashMap::new();
+    for strategy in strategies {
+        let cash = strategy.initial_capital
+            - cash_spent.get(&strategy.strategy).copied().unwrap_or(0.0)
+            + settlement_cash
+                .get(&strategy.strategy)
+                .copied()
+                .unwrap_or(0.0);
+        let value = marked_value(
+            cash,
+            marks
+                .get(&strategy.strategy)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+        );
+        equity_snapshots_crud
+            .create_or_update(
+                &EquitySnapshotsPrimaryKeys {
+                    strategy: strategy.strategy.clone(),
+                    time,
+                },
+                &EquitySnapshotsUpdateKeys { value: Some(value) },
+            )
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to write equity snapshot for {}: {}",
+                    strategy.strategy, e
+                )
+            })?;
+        values.insert(strategy.strategy, value);
+    }
+    Ok(values)
+}
+
+/// Spawns a task writing equity snapshots every interval until it is aborted
+/// - snapshot times are truncated to the interval so they line up with bar times
+pub fn spawn_equity_snapshot_writer(
+    pool: PgPool,
+    last_closes: LastCloseCache,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            let interval_secs = interval.as_secs().max(1) as i64;
+            let now = Utc::now().timestamp();
+            let time = DateTime::from_timestamp(now - now.rem_euclid(interval_secs), 0)
+                .unwrap_or_else(Utc::now);
+            if let Err(e) = write_equity_snapshots(pool.clone(), last_closes.clone(), time).await {
+                tracing::error!("Error writing equity snapshots: {}", e);
+            }
+        }
+    })
+}
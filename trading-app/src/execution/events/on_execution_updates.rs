@@ -5,23 +5,33 @@
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use ibapi::orders::ExecutionData;
 use rust_decimal::dec;
-use tracing::info;
-
-use crate::database::{
-    crud::{CRUD, CRUDTrait},
-    models::{
-        CurrentOptionPositionsFullKeys, CurrentOptionPositionsPrimaryKeys,
-        CurrentOptionPositionsUpdateKeys, CurrentStockPositionsFullKeys,
-        CurrentStockPositionsPrimaryKeys, CurrentStockPositionsUpdateKeys, ExecutionSide,
-        OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys, OpenOptionOrdersUpdateKeys,
-        OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, OpenStockOrdersUpdateKeys,
-        OptionTransactionsFullKeys, OptionTransactionsPrimaryKeys, OptionTransactionsUpdateKeys,
-        OptionType, StockTransactionsFullKeys, StockTransactionsPrimaryKeys,
-        StockTransactionsUpdateKeys,
+use tracing::{Instrument, Span, info};
+
+use crate::{
+    database::{
+        crud::{CRUD, CRUDTrait},
+        models::{
+            CurrentOptionPositionsFullKeys, CurrentOptionPositionsPrimaryKeys,
+            CurrentOptionPositionsUpdateKeys, CurrentStockPositionsFullKeys,
+            CurrentStockPositionsPrimaryKeys, CurrentStockPositionsUpdateKeys, ExecutionSide,
+            OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys, OpenOptionOrdersUpdateKeys,
+            OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, OpenStockOrdersUpdateKeys,
+            OptionTransactionsFullKeys, OptionTransactionsPrimaryKeys,
+            OptionTransactionsUpdateKeys, OptionType, StockTransactionsFullKeys,
+            StockTransactionsPrimaryKeys, StockTransactionsUpdateKeys, multiplier_value,
+            normalize_multiplier,
+        },
+        models_crud::{
+            current_option_positions::CurrentOptionPositionsCRUD,
+            current_stock_positions::CurrentStockPositionsCRUD,
+        },
     },
-    models_crud::{
-        current_option_positions::CurrentOptionPositionsCRUD,
-        current_stock_positions::CurrentStockPositionsCRUD,
+    execution::{
+        exercise::{
+            OptionExecutionKind, OptionExercise, book_option_exercise, option_execution_kind,
+        },
+        fills::{ExecutionSummary, FillSender, notify_fill},
+        sync::CORRECTIVE_ORDER_REF,
     },
 };
 
@@ -38,6 +48,124 @@ use crate::database::{
 //     }
 // }
 
+/// The execution time exactly as sent by IBKR, kept next to the parsed time when
+/// STORE_RAW_BROKER_TIME=true so the timezone handling below can be checked against it
+pub fn raw_broker_time(execution_time: &str) -> Option<String> {
+    std::env::var("STORE_RAW_BROKER_TIME")
+        .is_ok_and(|flag| flag == "true")
+        .then(|| execution_time.to_string())
+}
+
+/// Catch-all strategy executions without an open order and unexplained position discrepancies
+/// are booked to, unless TradingConfig.unknown_strategy names another
+pub const DEFAULT_UNKNOWN_STRATEGY: &str = "unknown";
+
+/// Span every log of one execution is emitted in, from the open order update through the
+/// transaction to the position update
+/// - strategy is recorded once the open order the execution fills is read (the unknown strategy
+///   without one)
+pub fn execution_span(execution_id: &str, order_id: i32, symbol: &str) -> Span {
+    tracing::info_span!(
+        "execution",
+        execution_id,
+        order_id,
+        symbol,
+        strategy = tracing::field::Empty
+    )
+}
+
+/// tokio::spawn with the task's logs kept in the current span
+pub(crate) fn spawn_in_span<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.in_current_span())
+}
+
+/// New (quantity, avg_price) of a position after an execution of shares at price
+/// - adding to the position averages the price in, reducing it keeps the current avg_price and
+///   flipping it starts from the execution price
+/// - a position left flat gets an avg_price of 0 rather than averaging over 0 shares
+pub fn apply_execution_to_position(
+    current_qty: f64,
+    current_avg_price: f64,
+    side: ExecutionSide,
+    shares: f64,
+    price: f64,
+) -> (f64, f64) {
+    let abs_current_qty = current_qty.abs();
+    // ==== If dir(trade) == Current Position
+    if (side == ExecutionSide::Bought && current_qty > 0.0)
+        || (side == ExecutionSide::Sold && current_qty < 0.0)
+    {
+        let new_qty = abs_current_qty + shares;
+        if new_qty == 0.0 {
+            return (0.0, 0.0);
+        }
+        (
+            new_qty,
+            (abs_current_qty * current_avg_price + shares * price) / new_qty,
+        )
+    } else if shares > abs_current_qty {
+        (shares - abs_current_qty, price)
+    } else if shares == abs_current_qty {
+        (0.0, 0.0)
+    } else {
+        (abs_current_qty - shares, current_avg_price)
+    }
+}
+
+/// Signed (quantity, avg_price) of a position after an execution of signed_quantity (negative for
+/// a sale) at price - positions are stored signed, apply_execution_to_position only gives the
+/// size
+/// - a sale larger than a long position leaves it net short, and vice versa
+pub fn position_after_execution(
+    current_qty: f64,
+    current_avg_price: f64,
+    signed_quantity: f64,
+    price: f64,
+) -> (f64, f64) {
+    let side = if signed_quantity < 0.0 {
+        ExecutionSide::Sold
+    } else {
+        ExecutionSide::Bought
+    };
+    let (_, avg_price) = apply_execution_to_position(
+        current_qty,
+        current_avg_price,
+        side,
+        signed_quantity.abs(),
+        price,
+    );
+    (current_qty + signed_quantity, avg_price)
+}
+
+/// (closes_position, realized_pnl) of an execution of shares at price against the current position
+/// - only the part of a fill that reduces the position realizes PnL - opening / adding to the
+///   position gives (false, None)
+/// - closes_position once the fill takes the position flat or through zero
+/// - multiplier is 1 for stocks and the contract multiplier for options
+pub fn realized_pnl_of_execution(
+    current_qty: f64,
+    current_avg_price: f64,
+    side: ExecutionSide,
+    shares: f64,
+    price: f64,
+    multiplier: f64,
+) -> (bool, Option<f64>) {
+    let is_reducing = (side == ExecutionSide::Sold && current_qty > 0.0)
+        || (side == ExecutionSide::Bought && current_qty < 0.0);
+    if !is_reducing {
+        return (false, None);
+    }
+
+    let abs_current_qty = current_qty.abs();
+    let closed_qty = shares.min(abs_current_qty);
+    let realized_pnl = closed_qty * (price - current_avg_price) * current_qty.signum() * multiplier;
+    (shares >= abs_current_qty, Some(realized_pnl))
+}
+
 /// Called by on_new_execution event defined in order_events
 /// - Performs ALL the necessary DB operations
 /// - Updates OpenOrders, if OpenOrder is filled, the entry is deleted
@@ -62,6 +190,8 @@ pub fn on_new_stock_execution(
     >,
     specific_current_stock_positions_crud: CurrentStockPositionsCRUD,
     execution_data: ExecutionData,
+    unknown_strategy: String,
+    fill_sender: Option<FillSender>,
 ) {
     // let (execution_id, revision) = parse_exec_id(&execution_data.execution.execution_id);
     // if revision.is_some() {
@@ -74,7 +204,27 @@ pub fn on_new_stock_execution(
     //         execution_id.clone(),
     //     );
     // }
-    tokio::spawn(async move {
+    let span = execution_span(
+        &execution_data.execution.execution_id,
+        execution_data.execution.order_id,
+        &execution_data.contract.symbol,
+    );
+    let _entered = span.enter();
+    let side = match ExecutionSide::from_str(&execution_data.execution.side) {
+        Ok(side) => side,
+        Err(e) => {
+            tracing::error!(
+                "Skipping execution {} for {}: {}",
+                &execution_data.execution.execution_id,
+                &execution_data.contract.symbol,
+                e
+            );
+            return;
+        }
+    };
+    // IBKR reports shares unsigned, the side gives the direction
+    let signed_shares = side.signed(execution_data.execution.shares);
+    spawn_in_span(async move {
         info!(
             "Execution: Looking for order with order_id {}",
             &execution_data.execution.order_id
@@ -88,6 +238,7 @@ pub fn on_new_stock_execution(
         {
             Ok(open_order_unwrapped) => {
                 if let Some(mut open_order) = open_order_unwrapped {
+                    Span::current().record("strategy", open_order.strategy.as_str());
                     // If the execution is a new execution recorded
                     if !open_order
                         .executions
@@ -111,7 +262,7 @@ pub fn on_new_stock_execution(
                         }
                         let cloned_execution_data = execution_data.clone();
                         let cloned_open_order = open_order.clone();
-                        tokio::spawn(async move {
+                        spawn_in_span(async move {
                             if &cloned_execution_data.execution.cumulative_quantity
                                 == &cloned_open_order.quantity.abs()
                             {
@@ -147,6 +298,7 @@ pub fn on_new_stock_execution(
                                                 cloned_open_order.filled.clone()
                                                     + &cloned_execution_data.execution.shares,
                                             ),
+                                            order_snapshot: None,
                                         },
                                     )
                                     .await
@@ -174,10 +326,40 @@ pub fn on_new_stock_execution(
                             .single()
                             .expect("Ambiguous or invalid datetime in New York timezone");
 
+                        // the position is read before it is updated below so the fill's
+                        // realized PnL is against the position it actually traded against
+                        let current_pos = current_stock_positions_crud
+                            .read(&CurrentStockPositionsPrimaryKeys {
+                                stock: open_order.stock.clone(),
+                                primary_exchange: open_order.primary_exchange.clone(),
+                                strategy: open_order.strategy.clone(),
+                            })
+                            .await;
+                        let (closes_position, realized_pnl) = match &current_pos {
+                            Ok(Some(pos)) => realized_pnl_of_execution(
+                                pos.quantity,
+                                pos.avg_price,
+                                side,
+                                execution_data.execution.shares,
+                                execution_data.execution.price,
+                                1.0,
+                            ),
+                            _ => (false, None),
+                        };
+
                         let cloned_open_order = open_order.clone();
                         let cloned_execution_data = execution_data.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = stock_transactions_crud
+                        let execution_summary = ExecutionSummary {
+                            strategy: open_order.strategy.clone(),
+                            contract: execution_data.contract.clone(),
+                            execution_id: execution_data.execution.execution_id.clone(),
+                            order_id: execution_data.execution.order_id,
+                            quantity: signed_shares,
+                            price: execution_data.execution.price,
+                            time: execution_time.with_timezone(&Utc),
+                        };
+                        spawn_in_span(async move {
+                            match stock_transactions_crud
                                 .create(&StockTransactionsFullKeys {
                                     strategy: cloned_open_order.strategy.clone(),
                                     execution_id: cloned_execution_data.execution.execution_id,
@@ -186,64 +368,36 @@ pub fn on_new_stock_execution(
                                     primary_exchange: cloned_open_order.primary_exchange.clone(),
                                     time: execution_time.with_timezone(&Utc),
                                     price: cloned_execution_data.execution.price.clone(),
-                                    quantity: if cloned_execution_data.execution.side == "BOT" {
-                                        cloned_execution_data.execution.shares.clone()
-                                    } else {
-                                        -cloned_execution_data.execution.shares.clone()
-                                    },
+                                    quantity: signed_shares,
                                     fees: dec!(0),
+                                    raw_broker_time: raw_broker_time(
+                                        &cloned_execution_data.execution.time,
+                                    ),
+                                    closes_position,
+                                    realized_pnl,
                                 })
                                 .await
                             {
-                                tracing::error!(
+                                Ok(()) => notify_fill(&fill_sender, execution_summary),
+                                Err(e) => tracing::error!(
                                     "Error occured while inserting into StockTransactions: {}",
                                     e
-                                )
+                                ),
                             };
                         });
 
                         // ===== Update Positions =====
                         // Final CRUD operation in alr spawned thread so unnecessary to spawn
                         // another thread
-                        match current_stock_positions_crud
-                            .read(&CurrentStockPositionsPrimaryKeys {
-                                stock: open_order.stock.clone(),
-                                primary_exchange: open_order.primary_exchange.clone(),
-                                strategy: open_order.strategy.clone(),
-                            })
-                            .await
-                        {
+                        match current_pos {
                             Ok(optional_pos) => {
                                 if let Some(pos) = optional_pos {
-                                    #[allow(unused_assignments)]
-                                    let (mut new_qty, mut new_avg_price) = (0.0, 0.0);
-                                    // ==== If dir(trade) == Current Position
-                                    if (matches!(
-                                        ExecutionSide::from_str(&execution_data.execution.side,),
-                                        ExecutionSide::Bought
-                                    ) && pos.quantity > 0.0)
-                                        || (matches!(
-                                            ExecutionSide::from_str(&execution_data.execution.side,),
-                                            ExecutionSide::Sold
-                                        ) && pos.quantity < 0.0)
-                                    {
-                                        let abs_current_qty = pos.quantity.abs();
-                                        new_qty = abs_current_qty + execution_data.execution.shares;
-                                        new_avg_price = (abs_current_qty * pos.avg_price
-                                            + &execution_data.execution.shares
-                                                * &execution_data.execution.price)
-                                            / new_qty;
-                                    } else {
-                                        if &execution_data.execution.shares > &pos.quantity.abs() {
-                                            new_qty = &execution_data.execution.shares
-                                                - &pos.quantity.abs();
-                                            new_avg_price = execution_data.execution.price.clone();
-                                        } else {
-                                            new_qty = &pos.quantity.abs()
-                                                - &execution_data.execution.shares;
-                                            new_avg_price = pos.avg_price.clone();
-                                        }
-                                    }
+                                    let (new_qty, new_avg_price) = position_after_execution(
+                                        pos.quantity,
+                                        pos.avg_price,
+                                        signed_shares,
+                                        execution_data.execution.price,
+                                    );
 
                                     if let Err(e) = current_stock_positions_crud
                                         .update(
@@ -272,7 +426,7 @@ pub fn on_new_stock_execution(
                                             stock: open_order.stock,
                                             primary_exchange: open_order.primary_exchange.clone(),
                                             strategy: open_order.strategy,
-                                            quantity: execution_data.execution.shares,
+                                            quantity: signed_shares,
                                             avg_price: execution_data.execution.price,
                                         })
                                         .await
@@ -300,6 +454,7 @@ pub fn on_new_stock_execution(
                         current_stock_positions_crud,
                         specific_current_stock_positions_crud,
                         execution_data,
+                        unknown_strategy,
                     );
                     tracing::error!("OpenStockOrders does not contain required row!");
                 }
@@ -335,6 +490,8 @@ pub fn on_new_option_execution(
     >,
     specific_current_option_positions_crud: CurrentOptionPositionsCRUD,
     execution_data: ExecutionData,
+    unknown_strategy: String,
+    fill_sender: Option<FillSender>,
 ) {
     // let (execution_id, revision) = parse_exec_id(&execution_data.execution.execution_id);
     // if revision.is_some() {
@@ -347,7 +504,25 @@ pub fn on_new_option_execution(
     //         execution_id.clone(),
     //     );
     // }
-    tokio::spawn(async move {
+    let span = execution_span(
+        &execution_data.execution.execution_id,
+        execution_data.execution.order_id,
+        &execution_data.contract.symbol,
+    );
+    let _entered = span.enter();
+    let side = match ExecutionSide::from_str(&execution_data.execution.side) {
+        Ok(side) => side,
+        Err(e) => {
+            tracing::error!(
+                "Skipping execution {} for {}: {}",
+                &execution_data.execution.execution_id,
+                &execution_data.contract.symbol,
+                e
+            );
+            return;
+        }
+    };
+    spawn_in_span(async move {
         match open_option_orders_crud
             .read(&OpenOptionOrdersPrimaryKeys {
                 order_perm_id: execution_data.execution.perm_id,
@@ -357,6 +532,7 @@ pub fn on_new_option_execution(
         {
             Ok(open_order_unwrapped) => {
                 if let Some(mut open_order) = open_order_unwrapped {
+                    Span::current().record("strategy", open_order.strategy.as_str());
                     // If the execution is a new execution recorded
                     if !open_order
                         .executions
@@ -380,7 +556,7 @@ pub fn on_new_option_execution(
 
                         let cloned_execution_data = execution_data.clone();
                         let cloned_open_order = open_order.clone();
-                        tokio::spawn(async move {
+                        spawn_in_span(async move {
                             if &cloned_execution_data.execution.cumulative_quantity
                                 == &cloned_open_order.quantity.abs()
                             {
@@ -418,6 +594,7 @@ pub fn on_new_option_execution(
                                                 cloned_open_order.filled.clone()
                                                     + &cloned_execution_data.execution.shares,
                                             ),
+                                            order_snapshot: None,
                                         },
                                     )
                                     .await
@@ -445,10 +622,56 @@ pub fn on_new_option_execution(
                             .single()
                             .expect("Ambiguous or invalid datetime in New York timezone");
 
+                        // the position is read before it is updated below so the fill's
+                        // realized PnL is against the position it actually traded against
+                        let current_pos = current_option_positions_crud
+                            .read(&CurrentOptionPositionsPrimaryKeys {
+                                stock: open_order.stock.clone(),
+                                primary_exchange: open_order.primary_exchange.clone(),
+                                strategy: open_order.strategy.clone(),
+                                expiry: open_order.expiry.clone(),
+                                strike: open_order.strike.clone(),
+                                multiplier: open_order.multiplier.clone(),
+                                option_type: open_order.option_type.clone(),
+                            })
+                            .await;
+                        let (closes_position, realized_pnl) = match (
+                            &current_pos,
+                            multiplier_value(&open_order.multiplier),
+                        ) {
+                            (Ok(Some(pos)), Ok(multiplier)) => realized_pnl_of_execution(
+                                pos.quantity,
+                                pos.avg_price,
+                                side,
+                                execution_data.execution.shares,
+                                execution_data.execution.price,
+                                multiplier,
+                            ),
+                            (Ok(Some(_)), Err(e)) => {
+                                tracing::error!(
+                                    "Not recording realized PnL for {}, invalid multiplier {}: {}",
+                                    open_order.stock,
+                                    open_order.multiplier,
+                                    e
+                                );
+                                (false, None)
+                            }
+                            _ => (false, None),
+                        };
+
                         let cloned_open_order = open_order.clone();
                         let cloned_execution_data = execution_data.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = option_transactions_crud
+                        let execution_summary = ExecutionSummary {
+                            strategy: open_order.strategy.clone(),
+                            contract: execution_data.contract.clone(),
+                            execution_id: execution_data.execution.execution_id.clone(),
+                            order_id: execution_data.execution.order_id,
+                            quantity: side.signed(execution_data.execution.shares),
+                            price: execution_data.execution.price,
+                            time: execution_time.with_timezone(&Utc),
+                        };
+                        spawn_in_span(async move {
+                            match option_transactions_crud
                                 .create(&OptionTransactionsFullKeys {
                                     strategy: cloned_open_order.strategy.clone(),
                                     execution_id: cloned_execution_data.execution.execution_id,
@@ -461,66 +684,34 @@ pub fn on_new_option_execution(
                                     option_type: cloned_open_order.option_type.clone(),
                                     time: execution_time.with_timezone(&Utc),
                                     price: cloned_execution_data.execution.price.clone(),
-                                    quantity: if cloned_execution_data.execution.side == "BOT" {
-                                        cloned_execution_data.execution.shares.clone()
-                                    } else {
-                                        -cloned_execution_data.execution.shares.clone()
-                                    },
+                                    quantity: side.signed(cloned_execution_data.execution.shares),
                                     fees: dec!(0),
+                                    raw_broker_time: raw_broker_time(
+                                        &cloned_execution_data.execution.time,
+                                    ),
+                                    closes_position,
+                                    realized_pnl,
                                 })
                                 .await
                             {
-                                tracing::error!(
+                                Ok(()) => notify_fill(&fill_sender, execution_summary),
+                                Err(e) => tracing::error!(
                                     "Error occured while inserting into OptionTransactions: {}",
                                     e
-                                )
+                                ),
                             };
                         });
 
                         // ===== Update Positions =====
-                        match current_option_positions_crud
-                            .read(&CurrentOptionPositionsPrimaryKeys {
-                                stock: open_order.stock.clone(),
-                                primary_exchange: open_order.primary_exchange.clone(),
-                                strategy: open_order.strategy.clone(),
-                                expiry: open_order.expiry.clone(),
-                                strike: open_order.strike.clone(),
-                                multiplier: open_order.multiplier.clone(),
-                                option_type: open_order.option_type.clone(),
-                            })
-                            .await
-                        {
+                        match current_pos {
                             Ok(optional_pos) => {
                                 if let Some(pos) = optional_pos {
-                                    #[allow(unused_assignments)]
-                                    let (mut new_qty, mut new_avg_price) = (0.0, 0.0);
-                                    // ==== If dir(trade) == Current Position
-                                    if (matches!(
-                                        ExecutionSide::from_str(&execution_data.execution.side,),
-                                        ExecutionSide::Bought
-                                    ) && pos.quantity > 0.0)
-                                        || (matches!(
-                                            ExecutionSide::from_str(&execution_data.execution.side,),
-                                            ExecutionSide::Sold
-                                        ) && pos.quantity < 0.0)
-                                    {
-                                        let abs_current_qty = pos.quantity.abs();
-                                        new_qty = abs_current_qty + execution_data.execution.shares;
-                                        new_avg_price = (abs_current_qty * pos.avg_price
-                                            + &execution_data.execution.shares
-                                                * &execution_data.execution.price)
-                                            / new_qty;
-                                    } else {
-                                        if &execution_data.execution.shares > &pos.quantity.abs() {
-                                            new_qty = &execution_data.execution.shares
-                                                - &pos.quantity.abs();
-                                            new_avg_price = execution_data.execution.price.clone();
-                                        } else {
-                                            new_qty = &pos.quantity.abs()
-                                                - &execution_data.execution.shares;
-                                            new_avg_price = pos.avg_price.clone();
-                                        }
-                                    }
+                                    let (new_qty, new_avg_price) = position_after_execution(
+                                        pos.quantity,
+                                        pos.avg_price,
+                                        side.signed(execution_data.execution.shares),
+                                        execution_data.execution.price,
+                                    );
 
                                     if let Err(e) = current_option_positions_crud
                                         .update(
@@ -557,11 +748,7 @@ pub fn on_new_option_execution(
                                             strike: open_order.strike,
                                             multiplier: open_order.multiplier,
                                             option_type: open_order.option_type,
-                                            quantity: if execution_data.execution.side == "BOT" {
-                                                execution_data.execution.shares.clone()
-                                            } else {
-                                                -execution_data.execution.shares.clone()
-                                            },
+                                            quantity: side.signed(execution_data.execution.shares),
                                             avg_price: execution_data.execution.price,
                                         })
                                         .await
@@ -588,6 +775,7 @@ pub fn on_new_option_execution(
                         current_option_positions_crud,
                         specific_current_option_positions_crud,
                         execution_data,
+                        unknown_strategy,
                     );
                     tracing::error!("OpenOptionOrders does not contain required row!");
                 }
@@ -601,10 +789,11 @@ pub fn on_new_option_execution(
 
 /// No open order -> Execution event comes in
 /// Assumption: Our server measures everything properly
-/// - Dumps the unknown execution event to "unknown" strategy
-/// - "unknown" strategy should ideally be set up in the beginning and be subscribed to a timestep
-/// set by the user (up to the max timestep the user wants before "unknown" should try to offload
-/// the position via Market Orders)
+/// - Dumps the unknown execution event to unknown_strategy ("unknown" by default)
+/// - unknown_strategy should ideally be set up in the beginning and be subscribed to a timestep
+///   set by the user (up to the max timestep the user wants before it should try to offload
+///   the position via Market Orders)
+/// - Runs in the execution_span of the on_new_stock_execution it is called from
 pub fn on_new_stock_execution_no_open_order(
     stock_transactions_crud: CRUD<
         StockTransactionsFullKeys,
@@ -618,7 +807,28 @@ pub fn on_new_stock_execution_no_open_order(
     >,
     specific_current_stock_positions_crud: CurrentStockPositionsCRUD,
     execution_data: ExecutionData,
+    unknown_strategy: String,
 ) {
+    Span::current().record("strategy", unknown_strategy.as_str());
+    if execution_data.execution.order_reference == CORRECTIVE_ORDER_REF {
+        info!(
+            "Execution {} of corrective order for {} not booked - local positions already have it",
+            &execution_data.execution.execution_id, &execution_data.contract.symbol
+        );
+        return;
+    }
+    let side = match ExecutionSide::from_str(&execution_data.execution.side) {
+        Ok(side) => side,
+        Err(e) => {
+            tracing::error!(
+                "Skipping execution {} for {}: {}",
+                &execution_data.execution.execution_id,
+                &execution_data.contract.symbol,
+                e
+            );
+            return;
+        }
+    };
     let naive_dt =
         NaiveDateTime::parse_from_str(&execution_data.execution.time, "%Y%m%d  %H:%M:%S").expect(
             &format!(
@@ -631,10 +841,11 @@ pub fn on_new_stock_execution_no_open_order(
         .single()
         .expect("Ambiguous or invalid datetime in New York timezone");
     let cloned_execution_data = execution_data.clone();
-    tokio::spawn(async move {
+    let cloned_unknown_strategy = unknown_strategy.clone();
+    spawn_in_span(async move {
         if let Err(e) = stock_transactions_crud
             .create(&StockTransactionsFullKeys {
-                strategy: "unknown".to_string(),
+                strategy: cloned_unknown_strategy,
                 execution_id: cloned_execution_data.execution.execution_id,
                 order_perm_id: cloned_execution_data.execution.perm_id,
                 stock: cloned_execution_data.contract.symbol.clone(),
@@ -642,12 +853,12 @@ pub fn on_new_stock_execution_no_open_order(
                 time: execution_time.to_utc(),
 
                 price: cloned_execution_data.execution.average_price,
-                quantity: if cloned_execution_data.execution.side == "BOT" {
-                    cloned_execution_data.execution.shares.clone()
-                } else {
-                    -cloned_execution_data.execution.shares.clone()
-                },
+                quantity: side.signed(cloned_execution_data.execution.shares),
                 fees: dec!(0),
+                raw_broker_time: raw_broker_time(&cloned_execution_data.execution.time),
+                // booked against the unknown strategy, which has no position to realize against
+                closes_position: false,
+                realized_pnl: None,
             })
             .await
         {
@@ -658,11 +869,12 @@ pub fn on_new_stock_execution_no_open_order(
         };
     });
     let cloned_execution_data = execution_data.clone();
-    tokio::spawn(async move {
+    spawn_in_span(async move {
         if let Err(e) = specific_current_stock_positions_crud
-            .update_unknown_strat_positions(
+            .update_strat_positions(
+                &unknown_strategy,
                 cloned_execution_data.contract.symbol,
-                cloned_execution_data.execution.shares,
+                side.signed(cloned_execution_data.execution.shares),
             )
             .await
         {
@@ -676,10 +888,13 @@ pub fn on_new_stock_execution_no_open_order(
 
 /// No open order -> Execution event comes in
 /// Assumption: Our server measures everything properly
-/// - Dumps the unknown execution event to "unknown" strategy
-/// - "unknown" strategy should ideally be set up in the beginning and be subscribed to a timestep
-/// set by the user (up to the max timestep the user wants before "unknown" should try to offload
-/// the position via Market Orders)
+/// - Dumps the unknown execution event to unknown_strategy ("unknown" by default)
+/// - unknown_strategy should ideally be set up in the beginning and be subscribed to a timestep
+///   set by the user (up to the max timestep the user wants before it should try to offload
+///   the position via Market Orders)
+/// - Runs in the execution_span of the on_new_option_execution it is called from
+/// - Exercises / assignments (see option_execution_kind) are booked with the shares they deliver,
+///   to the strategy holding the option if it can be told - see book_option_exercise
 pub fn on_new_option_execution_no_open_order(
     option_transactions_crud: CRUD<
         OptionTransactionsFullKeys,
@@ -693,7 +908,28 @@ pub fn on_new_option_execution_no_open_order(
     >,
     specific_current_option_positions_crud: CurrentOptionPositionsCRUD,
     execution_data: ExecutionData,
+    unknown_strategy: String,
 ) {
+    Span::current().record("strategy", unknown_strategy.as_str());
+    if execution_data.execution.order_reference == CORRECTIVE_ORDER_REF {
+        info!(
+            "Execution {} of corrective order for {} not booked - local positions already have it",
+            &execution_data.execution.execution_id, &execution_data.contract.symbol
+        );
+        return;
+    }
+    let side = match ExecutionSide::from_str(&execution_data.execution.side) {
+        Ok(side) => side,
+        Err(e) => {
+            tracing::error!(
+                "Skipping execution {} for {}: {}",
+                &execution_data.execution.execution_id,
+                &execution_data.contract.symbol,
+                e
+            );
+            return;
+        }
+    };
     let naive_dt =
         NaiveDateTime::parse_from_str(&execution_data.execution.time, "%Y%m%d  %H:%M:%S").expect(
             &format!(
@@ -705,11 +941,52 @@ pub fn on_new_option_execution_no_open_order(
         .from_local_datetime(&naive_dt)
         .single()
         .expect("Ambiguous or invalid datetime in New York timezone");
+    let kind = option_execution_kind(side, execution_data.execution.price);
+    if kind != OptionExecutionKind::Trade {
+        let exercise = OptionExercise {
+            execution_id: execution_data.execution.execution_id.clone(),
+            order_perm_id: execution_data.execution.perm_id,
+            stock: execution_data.contract.symbol.clone(),
+            primary_exchange: execution_data.contract.primary_exchange.clone(),
+            expiry: execution_data
+                .contract
+                .last_trade_date_or_contract_month
+                .clone(),
+            strike: execution_data.contract.strike,
+            multiplier: normalize_multiplier(&execution_data.contract.multiplier),
+            option_type: OptionType::from_str(&execution_data.contract.right)
+                .expect("Error parsing OptionType from contract right in option exercise"),
+            side,
+            contracts: execution_data.execution.shares,
+            time: execution_time.to_utc(),
+            raw_broker_time: raw_broker_time(&execution_data.execution.time),
+        };
+        let pool = option_transactions_crud.pool.clone();
+        spawn_in_span(async move {
+            match book_option_exercise(pool, &exercise, &unknown_strategy).await {
+                Ok(strategy) => {
+                    Span::current().record("strategy", strategy.as_str());
+                    info!(
+                        "{:?} of {} {} contracts booked to {} with the shares delivered",
+                        kind, exercise.contracts, exercise.stock, strategy
+                    );
+                }
+                Err(e) => tracing::error!(
+                    "Error booking {:?} of {} contracts: {}",
+                    kind,
+                    exercise.stock,
+                    e
+                ),
+            }
+        });
+        return;
+    }
     let cloned_execution_data = execution_data.clone();
-    tokio::spawn(async move {
+    let cloned_unknown_strategy = unknown_strategy.clone();
+    spawn_in_span(async move {
         if let Err(e) = option_transactions_crud
             .create(&OptionTransactionsFullKeys {
-                strategy: "unknown".to_string(),
+                strategy: cloned_unknown_strategy,
                 execution_id: cloned_execution_data.execution.execution_id,
                 order_perm_id: cloned_execution_data.execution.perm_id,
                 stock: cloned_execution_data.contract.symbol.clone(),
@@ -719,19 +996,18 @@ pub fn on_new_option_execution_no_open_order(
                     .last_trade_date_or_contract_month
                     .clone(),
                 strike: cloned_execution_data.contract.strike.clone(),
-                multiplier: cloned_execution_data.contract.multiplier.clone(),
+                multiplier: normalize_multiplier(&cloned_execution_data.contract.multiplier),
                 option_type: OptionType::from_str(&cloned_execution_data.contract.right).expect(
                     "Error parsing OptionType from contract right in update_option_execution",
                 ),
                 time: execution_time.to_utc(),
 
                 price: cloned_execution_data.execution.average_price,
-                quantity: if cloned_execution_data.execution.side == "BOT" {
-                    cloned_execution_data.execution.shares.clone()
-                } else {
-                    -cloned_execution_data.execution.shares.clone()
-                },
+                quantity: side.signed(cloned_execution_data.execution.shares),
                 fees: dec!(0),
+                raw_broker_time: raw_broker_time(&cloned_execution_data.execution.time),
+                closes_position: false,
+                realized_pnl: None,
             })
             .await
         {
@@ -742,9 +1018,10 @@ pub fn on_new_option_execution_no_open_order(
         };
     });
     let cloned_execution_data = execution_data.clone();
-    tokio::spawn(async move {
+    spawn_in_span(async move {
         if let Err(e) = specific_current_option_positions_crud
-            .update_unknown_strat_positions(
+            .update_strat_positions(
+                &unknown_strategy,
                 cloned_execution_data.contract.symbol,
                 cloned_execution_data.contract.primary_exchange,
                 cloned_execution_data
@@ -752,11 +1029,11 @@ pub fn on_new_option_execution_no_open_order(
                     .last_trade_date_or_contract_month
                     .clone(),
                 cloned_execution_data.contract.strike.clone(),
-                cloned_execution_data.contract.multiplier.clone(),
+                normalize_multiplier(&cloned_execution_data.contract.multiplier),
                 OptionType::from_str(&cloned_execution_data.contract.right).expect(
                     "Error parsing OptionType from contract right in update_option_execution",
                 ),
-                cloned_execution_data.execution.shares,
+                side.signed(cloned_execution_data.execution.shares),
             )
             .await
         {
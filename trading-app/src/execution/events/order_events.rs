@@ -1,13 +1,7 @@
-// NOTE: Mutex here is only used to pass to place_order
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    thread::{self},
-};
+use std::sync::Arc;
 
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use ibapi::{
-    Client,
     orders::{Action, CommissionReport, ExecutionData, Order, OrderStatus, order_builder},
     prelude::{Contract, SecurityType},
 };
@@ -20,10 +14,11 @@ use crate::{
     database::{
         crud::CRUDTrait,
         models::{
-            AssetType, OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys,
+            AssetType, CurrentOptionPositionsPrimaryKeys, CurrentStockPositionsPrimaryKeys,
+            OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys,
             OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, OptionTransactionsPrimaryKeys,
             OptionTransactionsUpdateKeys, OptionType, StagedCommissionsPrimaryKeys,
-            StockTransactionsPrimaryKeys, StockTransactionsUpdateKeys,
+            StockTransactionsPrimaryKeys, StockTransactionsUpdateKeys, normalize_multiplier,
         },
         models_crud::{
             current_option_positions::{
@@ -40,20 +35,43 @@ use crate::{
         },
     },
     execution::{
-        events::on_execution_updates::{on_new_option_execution, on_new_stock_execution},
-        place_order::place_order,
+        blocking_pool::BlockingPool,
+        cancel::OrderCanceller,
+        events::on_execution_updates::{
+            on_new_option_execution, on_new_stock_execution, spawn_in_span,
+        },
+        fills::FillSender,
+        netting::{
+            NettingDecision, is_partially_filled, net_against_working, on_partial_fill,
+            round_quantity, working_remaining,
+        },
+        order_limit::reduces_position,
+        order_map::order_snapshot,
+        place_order::{OrderContext, OrderSubmitter, place_order},
     },
     unlock,
 };
 
+/// Span the open order updates of order_id are logged in
+pub fn order_span(order_id: i32, perm_id: i32, strategy: &str) -> tracing::Span {
+    tracing::info_span!("order", order_id, perm_id, strategy)
+}
+
 /// Should be triggered by Submitted and PreSubmitted Order Events to update the local OpenOrders
 /// table
+/// - logs under an order span carrying the order_id, perm_id and strategy
 pub fn on_new_order_submitted(
     pool: PgPool,
     order_id: i32,
     perm_id: i32,
     strategy_order: (String, Contract, Order),
 ) -> Result<tokio::task::JoinHandle<()>, String> {
+    let span = order_span(order_id, perm_id, &strategy_order.0);
+    let _entered = span.enter();
+    // the open order is still recorded without its snapshot if that can't be serialized
+    let snapshot = order_snapshot(&strategy_order.1, &strategy_order.2)
+        .inspect_err(|e| tracing::error!("{}", e))
+        .ok();
     if strategy_order.1.security_type == SecurityType::Stock
         || strategy_order.1.security_type == SecurityType::Future
         || strategy_order.1.security_type == SecurityType::ForexPair
@@ -66,7 +84,7 @@ pub fn on_new_order_submitted(
                 1.0
             }
         } * strategy_order.2.total_quantity;
-        Ok(tokio::spawn(async move {
+        Ok(spawn_in_span(async move {
             if let Err(e) = open_stock_orders_crud
                 .create_or_ignore(&OpenStockOrdersFullKeys {
                     order_perm_id: perm_id.clone(),
@@ -78,6 +96,7 @@ pub fn on_new_order_submitted(
                     quantity: qty,
                     filled: 0.0,
                     executions: Vec::new(),
+                    order_snapshot: snapshot,
                 })
                 .await
             {
@@ -93,7 +112,7 @@ pub fn on_new_order_submitted(
                 1.0
             }
         } * strategy_order.2.total_quantity;
-        Ok(tokio::spawn(async move {
+        Ok(spawn_in_span(async move {
             if let Err(e) = open_option_orders_crud
                 .create_or_ignore(&OpenOptionOrdersFullKeys {
                     order_id: order_id.clone(),
@@ -103,7 +122,7 @@ pub fn on_new_order_submitted(
                     primary_exchange: strategy_order.1.primary_exchange.clone(),
                     expiry: strategy_order.1.last_trade_date_or_contract_month,
                     strike: strategy_order.1.strike,
-                    multiplier: strategy_order.1.multiplier,
+                    multiplier: normalize_multiplier(&strategy_order.1.multiplier),
                     option_type: crate::database::models::OptionType::from_str(
                         &strategy_order.1.right,
                     )
@@ -113,6 +132,7 @@ pub fn on_new_order_submitted(
 
                     filled: 0.0,
                     executions: Vec::new(),
+                    order_snapshot: snapshot,
                 })
                 .await
             {
@@ -136,12 +156,14 @@ pub fn on_order_cancelled(
     status: OrderStatus,
     strategy_order: (String, Contract, Order),
 ) {
+    let span = order_span(status.order_id, status.perm_id, &strategy_order.0);
+    let _entered = span.enter();
     if strategy_order.1.security_type == SecurityType::Stock
         || strategy_order.1.security_type == SecurityType::Future
     {
         let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
 
-        tokio::spawn(async move {
+        spawn_in_span(async move {
             if let Err(e) = open_stock_orders_crud
                 .delete(&OpenStockOrdersPrimaryKeys {
                     order_perm_id: status.perm_id.clone(),
@@ -155,7 +177,7 @@ pub fn on_order_cancelled(
     } else if strategy_order.1.security_type == SecurityType::Option {
         let open_option_orders_crud = get_open_option_orders_crud(pool.clone());
 
-        tokio::spawn(async move {
+        spawn_in_span(async move {
             if let Err(e) = open_option_orders_crud
                 .delete(&OpenOptionOrdersPrimaryKeys {
                     order_perm_id: status.perm_id.clone(),
@@ -178,7 +200,14 @@ pub fn on_order_cancelled(
 /// Should be triggered by ExecutionUpdate(ExecutionData) events
 /// - calls the relevant on_execution events in on_execution_update: see there for what the
 /// function actally does
-pub fn on_execution_update(pool: PgPool, execution_data: ExecutionData) {
+/// - fills booked for a strategy are reported on fill_sender (see StrategyExecutor::on_fill)
+/// - executions without an open order are booked to unknown_strategy
+pub fn on_execution_update(
+    pool: PgPool,
+    execution_data: ExecutionData,
+    unknown_strategy: String,
+    fill_sender: Option<FillSender>,
+) {
     if execution_data.contract.security_type == SecurityType::Stock
         || execution_data.contract.security_type == SecurityType::Future
         || execution_data.contract.security_type == SecurityType::ForexPair
@@ -195,6 +224,8 @@ pub fn on_execution_update(pool: PgPool, execution_data: ExecutionData) {
             current_stock_positions_crud,
             specific_current_stock_positions_crud,
             execution_data.clone(),
+            unknown_strategy,
+            fill_sender,
         );
     } else if execution_data.contract.security_type == SecurityType::Option {
         let open_option_orders_crud = get_open_option_orders_crud(pool.clone());
@@ -209,6 +240,8 @@ pub fn on_execution_update(pool: PgPool, execution_data: ExecutionData) {
             current_option_positions_crud,
             specific_current_option_positions_crud,
             execution_data.clone(),
+            unknown_strategy,
+            fill_sender,
         );
     } else {
         tracing::error!(
@@ -404,20 +437,42 @@ pub fn on_commission_update(
 
 /// Provides the logic to handle open order
 /// - i.e. cancelling and placing orders efficiently
-pub async fn on_new_stock_qty_diff_for_strat(
-    pool: PgPool,
+/// - working orders for the same strategy and contract are netted against qty_diff according to
+///   the NettingPolicy (see execution::netting)
+/// - if any of them is partially filled, the PartialFillPolicy then decides whether it is left,
+///   chased or cancelled
+/// - qty_diff is first rounded to ctx.share_quantity_decimals places (0 for whole shares, see
+///   netting::round_quantity)
+pub async fn on_new_stock_qty_diff_for_strat<C>(
+    ctx: OrderContext,
     contract: Contract,
-    client: Arc<Client>,
-    order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
+    client: Arc<C>,
     strategy: String,
     qty_diff: f64,
     avg_price: f64,
-) {
-    let open_stock_orders_crud = get_specific_open_stock_orders_crud(pool.clone());
-    let open_orders = open_stock_orders_crud
+) where
+    C: OrderSubmitter + OrderCanceller + Send + Sync + 'static,
+{
+    let rounded_qty_diff = round_quantity(qty_diff, ctx.share_quantity_decimals);
+    if rounded_qty_diff != qty_diff {
+        info!(
+            "Rounded qty diff of {} for {} in {} to {}",
+            qty_diff, &strategy, &contract.symbol, rounded_qty_diff
+        );
+    }
+    let qty_diff = rounded_qty_diff;
+
+    let open_stock_orders_crud = get_specific_open_stock_orders_crud(ctx.pool.clone());
+    let open_orders: Vec<OpenStockOrdersFullKeys> = open_stock_orders_crud
         .get_orders_for_strat(&strategy)
         .await
-        .expect("Expected to be able to get open orders from OpenStockOrders"); // this should only
+        .expect("Expected to be able to get open orders from OpenStockOrders") // this should only
+        .into_iter()
+        .filter(|open_order| {
+            open_order.stock == contract.symbol
+                && open_order.primary_exchange == contract.primary_exchange
+        })
+        .collect();
 
     let tot_qty_dir = open_orders
         .iter()
@@ -430,118 +485,78 @@ pub async fn on_new_stock_qty_diff_for_strat(
             &strategy, &contract.symbol
         );
     };
-    let (curr_open_orders_filled, curr_open_orders_quantity): (f64, f64) = (
-        open_orders.iter().map(|open_order| open_order.filled).sum(),
-        open_orders
-            .iter()
-            .map(|open_order| open_order.quantity)
-            .sum(),
-    );
-
-    // return 1 entry
-    let current_qty_diff = (curr_open_orders_quantity - curr_open_orders_filled)
-        * (curr_open_orders_quantity.signum());
-
-    if qty_diff == 0.0 {
-        open_orders.iter().for_each(|open_order| {
-            let order_id = open_order.order_id.clone();
-            let cloned_client = client.clone();
-            thread::spawn(move || {
-                cloned_client.cancel_order(order_id, "");
-            });
-            let (perm_id, order_id) = (
-                open_order.order_perm_id.clone(),
-                open_order.order_id.clone(),
-            );
-            let pool = pool.clone();
-            tokio::spawn(async move {
-                let open_option_orders_crud = get_open_stock_orders_crud(pool);
-                if let Err(e) = open_option_orders_crud
-                    .delete(&OpenStockOrdersPrimaryKeys {
-                        order_perm_id: perm_id,
-                        order_id: order_id,
-                    })
-                    .await
-                {
-                    tracing::error!("Error trying to delete OpenOptionOrder entry: {}", e)
-                };
-            });
-        });
-        return;
+    let working_orders = open_orders
+        .iter()
+        .map(|open_order| (open_order.quantity, open_order.filled))
+        .collect::<Vec<(f64, f64)>>();
+    let mut decision =
+        net_against_working(ctx.netting_policy, qty_diff, working_remaining(&working_orders));
+    if is_partially_filled(&working_orders) {
+        decision = on_partial_fill(ctx.partial_fill_policy, decision, qty_diff);
     }
 
-    // Cancel the order if qty_diff is in wrong direction / open order qty too high
-    if current_qty_diff.signum() != qty_diff.signum()
-        || (current_qty_diff.signum() == qty_diff.signum()
-            && current_qty_diff.abs() > qty_diff.abs())
-    {
-        open_orders.iter().for_each(|open_order| {
-            let order_id = open_order.order_id.clone();
-            let cloned_client = client.clone();
-            thread::spawn(move || {
-                cloned_client.cancel_order(order_id, "");
-            });
-        });
-        thread::spawn(move || {
-            let action = if qty_diff > 0.0 {
-                Action::Buy
-            } else {
-                Action::Sell
-            };
-            place_order(
-                order_map,
-                strategy,
-                client,
-                contract,
-                if avg_price == 0.0 {
-                    order_builder::market_order(action, qty_diff.abs())
-                } else {
-                    order_builder::limit_order(action, qty_diff.abs(), avg_price)
-                },
-                false,
-            )
-        });
-
-        open_orders.iter().for_each(|open_order| {
-            let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
-            let (perm_id, order_id) = (open_order.order_perm_id, open_order.order_id);
-            tokio::spawn(async move {
-                if let Err(e) = open_stock_orders_crud
-                    .delete(&OpenStockOrdersPrimaryKeys {
-                        order_perm_id: perm_id,
-                        order_id: order_id,
-                    })
-                    .await
-                {
-                    tracing::error!("Error trying to delete entry in OpenStockOrders: {}", e)
+    let qty_to_place = match decision {
+        NettingDecision::Hold => None,
+        NettingDecision::CancelAll => {
+            cancel_open_stock_orders(
+                ctx.pool.clone(),
+                client.clone(),
+                &ctx.blocking_pool,
+                &open_orders,
+            );
+            None
+        }
+        NettingDecision::Place(qty) => Some(qty),
+        NettingDecision::CancelAndPlace(qty) => {
+            cancel_open_stock_orders(
+                ctx.pool.clone(),
+                client.clone(),
+                &ctx.blocking_pool,
+                &open_orders,
+            );
+            Some(qty)
+        }
+    };
+    if let Some(qty) = qty_to_place {
+        // whether the order was counted against the session limit, to give back if not placed
+        let mut counted = false;
+        if ctx.order_limit.get_max_orders().is_some() {
+            let current_qty = get_current_stock_positions_crud(ctx.pool.clone())
+                .read(&CurrentStockPositionsPrimaryKeys {
+                    stock: contract.symbol.clone(),
+                    primary_exchange: contract.primary_exchange.clone(),
+                    strategy: strategy.clone(),
+                })
+                .await
+                .map(|position| position.map_or(0.0, |position| position.quantity))
+                .unwrap_or_else(|e| {
+                    error!("Error reading current position of {}: {}", &strategy, e);
+                    0.0
+                });
+            // orders only taking the position towards flat (e.g. flattening) are never suppressed
+            if !reduces_position(current_qty, qty) {
+                if !ctx.order_limit.admit(&strategy) {
+                    return;
                 }
-            });
-        });
-        return;
-    }
-    if current_qty_diff.abs() < qty_diff.abs() {
-        thread::spawn(move || {
-            let action = if qty_diff > 0.0 {
-                Action::Buy
-            } else {
-                Action::Sell
-            };
-            place_order(
-                order_map,
-                strategy,
+                counted = true;
+            }
+        }
+        let blocking_pool = ctx.blocking_pool.clone();
+        blocking_pool.execute(move || {
+            // failures are already logged by place_order
+            if place_order(
+                &ctx,
+                strategy.clone(),
                 client,
                 contract,
-                if avg_price == 0.0 {
-                    order_builder::market_order(action, (qty_diff - current_qty_diff).abs())
-                } else {
-                    order_builder::limit_order(
-                        action,
-                        (qty_diff - current_qty_diff).abs(),
-                        avg_price,
-                    )
-                },
+                build_order(qty, avg_price),
                 false,
             )
+            .is_err()
+                && counted
+            {
+                ctx.order_limit.release(&strategy);
+            }
         });
     }
 }
@@ -549,20 +564,31 @@ pub async fn on_new_stock_qty_diff_for_strat(
 /// Provides the logic to handle open order
 /// - i.e. cancelling and placing orders efficiently
 /// - essentially the same as on_new_stock_qty_diff_for_strat
-pub async fn on_new_option_qty_diff_for_strat(
-    pool: PgPool,
+pub async fn on_new_option_qty_diff_for_strat<C>(
+    ctx: OrderContext,
     contract: Contract,
-    client: Arc<Client>,
-    order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
+    client: Arc<C>,
     strategy: String,
     qty_diff: f64,
     avg_price: f64,
-) {
-    let open_option_orders_crud = get_specific_option_orders_crud(pool.clone());
-    let open_orders = open_option_orders_crud
+) where
+    C: OrderSubmitter + OrderCanceller + Send + Sync + 'static,
+{
+    let open_option_orders_crud = get_specific_option_orders_crud(ctx.pool.clone());
+    let open_orders: Vec<OpenOptionOrdersFullKeys> = open_option_orders_crud
         .get_orders_for_strat(&strategy)
         .await
-        .expect("Expected to be able to get open orders from OpenOptionOrders"); // this should only
+        .expect("Expected to be able to get open orders from OpenOptionOrders") // this should only
+        .into_iter()
+        .filter(|open_order| {
+            open_order.stock == contract.symbol
+                && open_order.primary_exchange == contract.primary_exchange
+                && open_order.expiry == contract.last_trade_date_or_contract_month
+                && open_order.strike == contract.strike
+                && open_order.multiplier == normalize_multiplier(&contract.multiplier)
+                && OptionType::from_str(&contract.right).as_ref() == Ok(&open_order.option_type)
+        })
+        .collect();
 
     let tot_qty_dir = open_orders
         .iter()
@@ -575,117 +601,165 @@ pub async fn on_new_option_qty_diff_for_strat(
             &strategy, &contract.symbol
         );
     };
-    let (curr_open_orders_filled, curr_open_orders_quantity): (f64, f64) = (
-        open_orders.iter().map(|open_order| open_order.filled).sum(),
-        open_orders
-            .iter()
-            .map(|open_order| open_order.quantity)
-            .sum(),
-    );
-
-    // return 1 entry
-    let current_qty_diff = (curr_open_orders_quantity - curr_open_orders_filled)
-        * (curr_open_orders_quantity.signum());
+    let working_orders = open_orders
+        .iter()
+        .map(|open_order| (open_order.quantity, open_order.filled))
+        .collect::<Vec<(f64, f64)>>();
+    let mut decision =
+        net_against_working(ctx.netting_policy, qty_diff, working_remaining(&working_orders));
+    if is_partially_filled(&working_orders) {
+        decision = on_partial_fill(ctx.partial_fill_policy, decision, qty_diff);
+    }
 
-    if qty_diff == 0.0 {
-        open_orders.iter().for_each(|open_order| {
-            let order_id = open_order.order_id.clone();
-            let cloned_client = client.clone();
-            thread::spawn(move || {
-                cloned_client.cancel_order(order_id, "");
-            });
-            let (perm_id, order_id) = (
-                open_order.order_perm_id.clone(),
-                open_order.order_id.clone(),
+    let qty_to_place = match decision {
+        NettingDecision::Hold => None,
+        NettingDecision::CancelAll => {
+            cancel_open_option_orders(
+                ctx.pool.clone(),
+                client.clone(),
+                &ctx.blocking_pool,
+                &open_orders,
             );
-            let pool = pool.clone();
-            tokio::spawn(async move {
-                let open_option_orders_crud = get_open_option_orders_crud(pool);
-                if let Err(e) = open_option_orders_crud
-                    .delete(&OpenOptionOrdersPrimaryKeys {
-                        order_perm_id: perm_id,
-                        order_id: order_id,
-                    })
-                    .await
-                {
-                    tracing::error!("Error trying to delete OpenOptionOrder entry: {}", e)
-                };
+            None
+        }
+        NettingDecision::Place(qty) => Some(qty),
+        NettingDecision::CancelAndPlace(qty) => {
+            cancel_open_option_orders(
+                ctx.pool.clone(),
+                client.clone(),
+                &ctx.blocking_pool,
+                &open_orders,
+            );
+            Some(qty)
+        }
+    };
+    if let Some(qty) = qty_to_place {
+        // whether the order was counted against the session limit, to give back if not placed
+        let mut counted = false;
+        if ctx.order_limit.get_max_orders().is_some() {
+            let position_pk = OptionType::from_str(&contract.right).map(|option_type| {
+                CurrentOptionPositionsPrimaryKeys {
+                    stock: contract.symbol.clone(),
+                    primary_exchange: contract.primary_exchange.clone(),
+                    strategy: strategy.clone(),
+                    expiry: contract.last_trade_date_or_contract_month.clone(),
+                    strike: contract.strike,
+                    multiplier: normalize_multiplier(&contract.multiplier),
+                    option_type,
+                }
             });
-        });
-        return;
-    }
-    // Cancel the order if qty_diff is in wrong direction / open order qty too high
-    if current_qty_diff.signum() != qty_diff.signum()
-        || (current_qty_diff.signum() == qty_diff.signum()
-            && current_qty_diff.abs() > qty_diff.abs())
-    {
-        open_orders.iter().for_each(|open_order| {
-            let order_id = open_order.order_id.clone();
-            let cloned_client = client.clone();
-            thread::spawn(move || {
-                cloned_client.cancel_order(order_id, "");
+            let current_qty = match position_pk {
+                Ok(position_pk) => get_current_option_positions_crud(ctx.pool.clone())
+                    .read(&position_pk)
+                    .await
+                    .map(|position| position.map_or(0.0, |position| position.quantity))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            }
+            .unwrap_or_else(|e| {
+                error!("Error reading current option position of {}: {}", &strategy, e);
+                0.0
             });
-        });
-        thread::spawn(move || {
-            let action = if qty_diff > 0.0 {
-                Action::Buy
-            } else {
-                Action::Sell
-            };
-            place_order(
-                order_map,
-                strategy,
+            // orders only taking the position towards flat (e.g. flattening) are never suppressed
+            if !reduces_position(current_qty, qty) {
+                if !ctx.order_limit.admit(&strategy) {
+                    return;
+                }
+                counted = true;
+            }
+        }
+        let blocking_pool = ctx.blocking_pool.clone();
+        blocking_pool.execute(move || {
+            // failures are already logged by place_order
+            if place_order(
+                &ctx,
+                strategy.clone(),
                 client,
                 contract,
-                if avg_price == 0.0 {
-                    order_builder::market_order(action, qty_diff.abs())
-                } else {
-                    order_builder::limit_order(action, qty_diff.abs(), avg_price)
-                },
+                build_order(qty, avg_price),
                 false,
             )
+            .is_err()
+                && counted
+            {
+                ctx.order_limit.release(&strategy);
+            }
         });
+    }
+}
 
-        open_orders.iter().for_each(|open_order| {
-            let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
-            let (perm_id, order_id) = (open_order.order_perm_id, open_order.order_id);
-            tokio::spawn(async move {
-                if let Err(e) = open_stock_orders_crud
-                    .delete(&OpenStockOrdersPrimaryKeys {
-                        order_perm_id: perm_id,
-                        order_id: order_id,
-                    })
-                    .await
-                {
-                    tracing::error!("Error trying to delete entry in OpenStockOrders: {}", e)
-                }
-            });
-        });
-        return;
+/// Market order if no reference price is available, else limit order at avg_price
+fn build_order(signed_qty: f64, avg_price: f64) -> Order {
+    let action = if signed_qty > 0.0 {
+        Action::Buy
+    } else {
+        Action::Sell
+    };
+    if avg_price == 0.0 {
+        order_builder::market_order(action, signed_qty.abs())
+    } else {
+        order_builder::limit_order(action, signed_qty.abs(), avg_price)
     }
-    if current_qty_diff < qty_diff {
-        thread::spawn(move || {
-            let action = if qty_diff > 0.0 {
-                Action::Buy
-            } else {
-                Action::Sell
-            };
-            place_order(
-                order_map,
-                strategy,
-                client,
-                contract,
-                if avg_price == 0.0 {
-                    order_builder::market_order(action, (qty_diff - current_qty_diff).abs())
-                } else {
-                    order_builder::limit_order(
-                        action,
-                        (qty_diff - current_qty_diff).abs(),
-                        avg_price,
-                    )
-                },
-                false,
-            )
+}
+
+fn cancel_open_stock_orders<C: OrderCanceller + Send + Sync + 'static>(
+    pool: PgPool,
+    client: Arc<C>,
+    blocking_pool: &BlockingPool,
+    open_orders: &Vec<OpenStockOrdersFullKeys>,
+) {
+    open_orders.iter().for_each(|open_order| {
+        let order_id = open_order.order_id.clone();
+        let cloned_client = client.clone();
+        blocking_pool.execute(move || {
+            if let Err(e) = cloned_client.cancel_order(order_id) {
+                tracing::error!("{}", e);
+            }
         });
-    }
+
+        let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
+        let (perm_id, order_id) = (open_order.order_perm_id, open_order.order_id);
+        tokio::spawn(async move {
+            if let Err(e) = open_stock_orders_crud
+                .delete(&OpenStockOrdersPrimaryKeys {
+                    order_perm_id: perm_id,
+                    order_id: order_id,
+                })
+                .await
+            {
+                tracing::error!("Error trying to delete entry in OpenStockOrders: {}", e)
+            }
+        });
+    });
+}
+
+fn cancel_open_option_orders<C: OrderCanceller + Send + Sync + 'static>(
+    pool: PgPool,
+    client: Arc<C>,
+    blocking_pool: &BlockingPool,
+    open_orders: &Vec<OpenOptionOrdersFullKeys>,
+) {
+    open_orders.iter().for_each(|open_order| {
+        let order_id = open_order.order_id.clone();
+        let cloned_client = client.clone();
+        blocking_pool.execute(move || {
+            if let Err(e) = cloned_client.cancel_order(order_id) {
+                tracing::error!("{}", e);
+            }
+        });
+
+        let open_option_orders_crud = get_open_option_orders_crud(pool.clone());
+        let (perm_id, order_id) = (open_order.order_perm_id, open_order.order_id);
+        tokio::spawn(async move {
+            if let Err(e) = open_option_orders_crud
+                .delete(&OpenOptionOrdersPrimaryKeys {
+                    order_perm_id: perm_id,
+                    order_id: order_id,
+                })
+                .await
+            {
+                tracing::error!("Error trying to delete entry in OpenOptionOrders: {}", e)
+            }
+        });
+    });
 }
@@ -0,0 +1,311 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::dec;
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            CurrentOptionPositionsFullKeys, CurrentOptionPositionsPrimaryKeys,
+            CurrentOptionPositionsUpdateKeys, CurrentStockPositionsFullKeys,
+            CurrentStockPositionsPrimaryKeys, CurrentStockPositionsUpdateKeys, ExecutionSide,
+            OptionTransactionsFullKeys, OptionType, StockTransactionsFullKeys, multiplier_value,
+        },
+        models_crud::{
+            current_option_positions::get_current_option_positions_crud,
+            current_stock_positions::get_current_stock_positions_crud,
+            option_transactions::get_option_transactions_crud,
+            stock_transactions::get_stock_transactions_crud,
+        },
+    },
+    execution::events::on_execution_updates::{
+        apply_execution_to_position, realized_pnl_of_execution,
+    },
+};
+
+/// How an option execution came about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionExecutionKind {
+    /// Bought / sold in the market
+    Trade,
+    /// A long option given up for the underlying at the strike
+    Exercise,
+    /// A short option taken away, the underlying delivered at the strike
+    Assignment,
+}
+
+/// IBKR reports an exercise / assignment as an execution of the option at a price of 0 - a long
+/// position sold off is an exercise, a short position bought back an assignment
+pub fn option_execution_kind(side: ExecutionSide, price: f64) -> OptionExecutionKind {
+    if price != 0.0 {
+        return OptionExecutionKind::Trade;
+    }
+    match side {
+        ExecutionSide::Sold => OptionExecutionKind::Exercise,
+        ExecutionSide::Bought => OptionExecutionKind::Assignment,
+    }
+}
+
+/// Signed shares received when option_quantity (signed) contracts are exercised / assigned - a
+/// long call or a short put receives shares, a long put or a short call delivers them
+pub fn exercised_shares(option_type: &OptionType, multiplier: f64, option_quantity: f64) -> f64 {
+    shared::options::exercised_shares(option_type.into(), multiplier, option_quantity)
+}
+
+/// Strategy whose position an exercise / assignment of signed_quantity contracts closes out, of
+/// the (strategy, quantity) holding the option
+/// - the only strategy holding at least as much of the opposite position, None if no strategy or
+///   several could have been exercised / assigned
+pub fn exercised_position_owner(holders: &[(String, f64)], signed_quantity: f64) -> Option<String> {
+    let mut owners = holders.iter().filter(|(_, quantity)| {
+        quantity * signed_quantity < 0.0 && quantity.abs() >= signed_quantity.abs()
+    });
+    match (owners.next(), owners.next()) {
+        (Some((strategy, _)), None) => Some(strategy.clone()),
+        _ => None,
+    }
+}
+
+/// execution_id the shares delivered by the exercise / assignment reported as execution_id are
+/// booked under - kept apart from the option's so its commission is only applied once
+pub fn delivery_execution_id(execution_id: &str) -> String {
+    format!("{}.delivery", execution_id)
+}
+
+/// Option execution IBKR reported an exercise / assignment as
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionExercise {
+    pub execution_id: String,
+    pub order_perm_id: i32,
+    pub stock: String,
+    pub primary_exchange: String,
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+    pub side: ExecutionSide,
+    /// Contracts exercised / assigned
+    pub contracts: f64,
+    pub time: DateTime<Utc>,
+    pub raw_broker_time: Option<String>,
+}
+
+/// Books an exercise / assignment to the strategy holding the option (see
+/// exercised_position_owner), or to unknown_strategy if it can't be told which one
+/// - the option is closed out at a price of 0 as any other execution would be
+/// - the shares delivered are booked as a stock transaction at the strike and added to the
+///   strategy's stock position
+/// - both legs are written in one transaction, so an exercise is either booked whole or not at all
+/// - returns the strategy it was booked to
+pub async fn book_option_exercise(
+    pool: PgPool,
+    exercise: &OptionExercise,
+    unknown_strategy: &str,
+) -> Result<String, String> {
+    let multiplier = multiplier_value(&exercise.multiplier)
+        .map_err(|e| format!("Exercise of {}: {}", exercise.stock, e))?;
+    let option_quantity = exercise.side.signed(exercise.contracts);
+    let current_option_positions_crud = get_current_option_positions_crud(pool.clone());
+    let holders: Vec<(String, f64)> = current_option_positions_crud
+        .read_all()
+        .await
+        .map_err(|e| format!("Failed to read option positions: {}", e))?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|position| {
+            position.stock == exercise.stock
+                && position.primary_exchange == exercise.primary_exchange
+                && position.expiry == exercise.expiry
+                && position.strike == exercise.strike
+                && position.multiplier == exercise.multiplier
+                && position.option_type == exercise.option_type
+        })
+        .map(|position| (position.strategy, position.quantity))
+        .collect();
+    let strategy = exercised_position_owner(&holders, option_quantity)
+        .unwrap_or_else(|| unknown_strategy.to_string());
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin exercise transaction: {}", e))?;
+
+    // ===== Option leg: closed out at 0 =====
+    let option_pk = CurrentOptionPositionsPrimaryKeys {
+        stock: exercise.stock.clone(),
+        primary_exchange: exercise.primary_exchange.clone(),
+        strategy: strategy.clone(),
+        expiry: exercise.expiry.clone(),
+        strike: exercise.strike,
+        multiplier: exercise.multiplier.clone(),
+        option_type: exercise.option_type.clone(),
+    };
+    let option_pos = current_option_positions_crud
+        .read_for_update(&option_pk, &mut tx)
+        .await
+        .map_err(|e| format!("Failed to read option position: {}", e))?;
+    let (closes_position, realized_pnl) = option_pos.as_ref().map_or((false, None), |pos| {
+        realized_pnl_of_execution(
+            pos.quantity,
+            pos.avg_price,
+            exercise.side,
+            exercise.contracts,
+            0.0,
+            multiplier,
+        )
+    });
+    get_option_transactions_crud(pool.clone())
+        .create_in_tx(
+            &OptionTransactionsFullKeys {
+                strategy: strategy.clone(),
+                execution_id: exercise.execution_id.clone(),
+                order_perm_id: exercise.order_perm_id,
+                stock: exercise.stock.clone(),
+                primary_exchange: exercise.primary_exchange.clone(),
+                expiry: exercise.expiry.clone(),
+                strike: exercise.strike,
+                multiplier: exercise.multiplier.clone(),
+                option_type: exercise.option_type.clone(),
+                time: exercise.time,
+                price: 0.0,
+                quantity: option_quantity,
+                fees: dec!(0),
+                raw_broker_time: exercise.raw_broker_time.clone(),
+                closes_position,
+                realized_pnl,
+            },
+            &mut tx,
+        )
+        .await
+        .map_err(|e| format!("Failed to insert into OptionTransactions: {}", e))?;
+    match option_pos {
+        Some(pos) => {
+            // apply_execution_to_position gives the size, positions are stored signed
+            let (_, avg_price) = apply_execution_to_position(
+                pos.quantity,
+                pos.avg_price,
+                exercise.side,
+                exercise.contracts,
+                0.0,
+            );
+            current_option_positions_crud
+                .update_in_tx(
+                    &option_pk,
+                    &CurrentOptionPositionsUpdateKeys {
+                        quantity: Some(pos.quantity + option_quantity),
+                        avg_price: Some(avg_price),
+                    },
+                    &mut tx,
+                )
+                .await
+                .map_err(|e| format!("Failed to update CurrentOptionPositions: {}", e))?;
+        }
+        None => {
+            current_option_positions_crud
+                .create_in_tx(
+                    &CurrentOptionPositionsFullKeys {
+                        stock: option_pk.stock,
+                        primary_exchange: option_pk.primary_exchange,
+                        strategy: option_pk.strategy,
+                        expiry: option_pk.expiry,
+                        strike: option_pk.strike,
+                        multiplier: option_pk.multiplier,
+                        option_type: option_pk.option_type,
+                        quantity: option_quantity,
+                        avg_price: 0.0,
+                    },
+                    &mut tx,
+                )
+                .await
+                .map_err(|e| format!("Failed to insert into CurrentOptionPositions: {}", e))?;
+        }
+    }
+
+    // ===== Stock leg: the shares delivered at the strike =====
+    let shares = exercised_shares(&exercise.option_type, multiplier, -option_quantity);
+    let stock_side = if shares > 0.0 {
+        ExecutionSide::Bought
+    } else {
+        ExecutionSide::Sold
+    };
+    let current_stock_positions_crud = get_current_stock_positions_crud(pool.clone());
+    let stock_pk = CurrentStockPositionsPrimaryKeys {
+        stock: exercise.stock.clone(),
+        primary_exchange: exercise.primary_exchange.clone(),
+        strategy: strategy.clone(),
+    };
+    let stock_pos = current_stock_positions_crud
+        .read_for_update(&stock_pk, &mut tx)
+        .await
+        .map_err(|e| format!("Failed to read stock position: {}", e))?;
+    let (closes_position, realized_pnl) = stock_pos.as_ref().map_or((false, None), |pos| {
+        realized_pnl_of_execution(
+            pos.quantity,
+            pos.avg_price,
+            stock_side,
+            shares.abs(),
+            exercise.strike,
+            1.0,
+        )
+    });
+    get_stock_transactions_crud(pool)
+        .create_in_tx(
+            &StockTransactionsFullKeys {
+                strategy: strategy.clone(),
+                execution_id: delivery_execution_id(&exercise.execution_id),
+                order_perm_id: exercise.order_perm_id,
+                stock: exercise.stock.clone(),
+                primary_exchange: exercise.primary_exchange.clone(),
+                time: exercise.time,
+                price: exercise.strike,
+                quantity: shares,
+                fees: dec!(0),
+                raw_broker_time: exercise.raw_broker_time.clone(),
+                closes_position,
+                realized_pnl,
+            },
+            &mut tx,
+        )
+        .await
+        .map_err(|e| format!("Failed to insert into StockTransactions: {}", e))?;
+    match stock_pos {
+        Some(pos) => {
+            let (_, avg_price) = apply_execution_to_position(
+                pos.quantity,
+                pos.avg_price,
+                stock_side,
+                shares.abs(),
+                exercise.strike,
+            );
+            current_stock_positions_crud
+                .update_in_tx(
+                    &stock_pk,
+                    &CurrentStockPositionsUpdateKeys {
+                        quantity: Some(pos.quantity + shares),
+                        avg_price: Some(avg_price),
+                    },
+                    &mut tx,
+                )
+                .await
+                .map_err(|e| format!("Failed to update CurrentStockPositions: {}", e))?;
+        }
+        None => {
+            current_stock_positions_crud
+                .create_in_tx(
+                    &CurrentStockPositionsFullKeys {
+                        stock: stock_pk.stock,
+                        primary_exchange: stock_pk.primary_exchange,
+                        strategy: stock_pk.strategy,
+                        quantity: shares,
+                        avg_price: exercise.strike,
+                    },
+                    &mut tx,
+                )
+                .await
+                .map_err(|e| format!("Failed to insert into CurrentStockPositions: {}", e))?;
+        }
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit exercise transaction: {}", e))?;
+    Ok(strategy)
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use ibapi::prelude::Contract;
+use tokio::sync::mpsc::{self, Sender};
+
+use crate::strategy::strategy::StrategyExecutor;
+
+/// Fills that queue up behind a slow on_fill before the execution pipeline drops them
+const FILL_CHANNEL_CAPACITY: usize = 100;
+
+/// A fill as booked into the strategy's transactions - passed to StrategyExecutor::on_fill
+#[derive(Debug, Clone)]
+pub struct ExecutionSummary {
+    pub strategy: String,
+    pub contract: Contract,
+    pub execution_id: String,
+    pub order_id: i32,
+    /// Signed - positive for a buy, negative for a sell
+    pub quantity: f64,
+    pub price: f64,
+    pub time: DateTime<Utc>,
+}
+
+/// Sender the execution pipeline reports booked fills to (see OrderEngine::begin_fill_listening)
+pub type FillSender = Sender<ExecutionSummary>;
+
+/// Calls on_fill of the strategy the fill was booked for, returning how many strategies were
+/// notified
+/// - errors from on_fill are logged, the fill is already booked either way
+pub async fn dispatch_fill<T: StrategyExecutor>(
+    strategies: &[T],
+    execution: &ExecutionSummary,
+) -> usize {
+    let mut notified = 0;
+    for strategy in strategies
+        .iter()
+        .filter(|strategy| strategy.get_name() == execution.strategy)
+    {
+        if let Err(e) = strategy.on_fill(execution).await {
+            tracing::error!(
+                "on_fill of {} failed for execution {}: {}",
+                execution.strategy,
+                execution.execution_id,
+                e
+            );
+        }
+        notified += 1;
+    }
+    notified
+}
+
+/// Spawns the task dispatching fills to strategies, returning the sender fills are reported on
+/// - runs until every sender is dropped
+pub fn spawn_fill_listener<T>(strategies: Vec<T>) -> FillSender
+where
+    T: StrategyExecutor + 'static,
+{
+    let (fill_sender, mut fill_receiver) = mpsc::channel::<ExecutionSummary>(FILL_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        while let Some(execution) = fill_receiver.recv().await {
+            if dispatch_fill(&strategies, &execution).await == 0 {
+                tracing::warn!(
+                    "Fill {} booked for {} which is not a running strategy",
+                    execution.execution_id,
+                    execution.strategy
+                );
+            }
+        }
+    });
+    fill_sender
+}
+
+/// Reports a booked fill if fills are being listened for
+/// - never waits on the strategies, a full or closed channel drops the notification
+pub fn notify_fill(fill_sender: &Option<FillSender>, execution: ExecutionSummary) {
+    let sent = fill_sender
+        .as_ref()
+        .map(|fill_sender| fill_sender.try_send(execution));
+    if let Some(Err(e)) = sent {
+        tracing::error!("Dropped on_fill notification: {}", e);
+    }
+}
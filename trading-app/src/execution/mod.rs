@@ -1,5 +1,19 @@
 pub mod order_engine;
+pub mod order_limit;
 mod on_full_open_order_received;
 pub mod place_order;
+pub mod order_map;
+pub mod fills;
 pub mod events;
 pub mod order_update_stream;
+pub mod netting;
+pub mod cancel;
+pub mod sync;
+pub mod preview;
+pub mod equity_snapshots;
+pub mod notices;
+pub mod broker_events;
+pub mod blocking_pool;
+pub mod exercise;
+pub mod account_summary;
+pub mod contract_validation;
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+/// Decides how a new position diff for a (strategy, contract) is reconciled against the orders
+/// that are still working at the broker for that same (strategy, contract)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NettingPolicy {
+    /// Only the part of the diff not already covered by working orders is placed
+    /// - working orders in the wrong direction / for too much are still cancelled and replaced
+    #[default]
+    Net,
+    /// Every working order is cancelled and a fresh order for the full diff is placed
+    ReplaceByCancel,
+}
+
+/// What the order engine should do with the working orders and the diff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NettingDecision {
+    /// Working orders already cover the diff
+    Hold,
+    /// Cancel every working order, nothing new to place
+    CancelAll,
+    /// Leave working orders alone and place an additional order for the signed qty
+    Place(f64),
+    /// Cancel every working order and place a new order for the signed qty
+    CancelAndPlace(f64),
+}
+
+/// Signed quantity still working across the given orders
+/// - orders are (signed quantity, unsigned filled) as stored in OpenStockOrders / OpenOptionOrders
+pub fn working_remaining(orders: &[(f64, f64)]) -> f64 {
+    orders
+        .iter()
+        .map(|(quantity, filled)| (quantity.abs() - filled).max(0.0) * quantity.signum())
+        .sum()
+}
+
+/// effective_diff = target - current_position - working_remaining
+/// - qty_diff is expected to already be target - current_position
+pub fn effective_diff(qty_diff: f64, working_remaining: f64) -> f64 {
+    qty_diff - working_remaining
+}
+
+/// Rounds an order quantity to decimals places - 0 for whole shares
+/// - applied to qty_diff before netting, so a diff left with float residue from averaging (e.g.
+///   99.9999) is ordered as 100 and the -0.0001 left once it fills rounds to no diff at all
+pub fn round_quantity(qty: f64, decimals: u32) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    let rounded = (qty * scale).round() / scale;
+    // -0.0 compares equal to 0.0 but has a signum of -1
+    if rounded == 0.0 { 0.0 } else { rounded }
+}
+
+/// qty_diff (target - current_position) once the target is scaled by order_size_multiplier (e.g.
+/// 0.1 to trade a tenth of the size live)
+/// - the target is scaled rather than the diff, so the position settles at target * multiplier
+///   instead of creeping up to the full target one scaled diff at a time
+pub fn scale_qty_diff(qty_diff: f64, current_position: f64, order_size_multiplier: f64) -> f64 {
+    (qty_diff + current_position) * order_size_multiplier - current_position
+}
+
+/// Nets qty_diff (target - current_position) against the working orders according to the policy
+pub fn net_against_working(
+    policy: NettingPolicy,
+    qty_diff: f64,
+    working_remaining: f64,
+) -> NettingDecision {
+    if qty_diff == 0.0 {
+        return if working_remaining == 0.0 {
+            NettingDecision::Hold
+        } else {
+            NettingDecision::CancelAll
+        };
+    }
+    if working_remaining == 0.0 {
+        return NettingDecision::Place(qty_diff);
+    }
+
+    match policy {
+        NettingPolicy::ReplaceByCancel => NettingDecision::CancelAndPlace(qty_diff),
+        NettingPolicy::Net => {
+            // Working orders in the wrong direction or overshooting the target cannot be netted
+            if working_remaining.signum() != qty_diff.signum()
+                || working_remaining.abs() > qty_diff.abs()
+            {
+                return NettingDecision::CancelAndPlace(qty_diff);
+            }
+            let effective_diff = effective_diff(qty_diff, working_remaining);
+            if effective_diff == 0.0 {
+                NettingDecision::Hold
+            } else {
+                NettingDecision::Place(effective_diff)
+            }
+        }
+    }
+}
+
+/// What happens to a partially filled working order when the diff for its (strategy, contract)
+/// is re-evaluated, e.g. on the next bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartialFillPolicy {
+    /// Leave the remainder working at its original price - the NettingPolicy decides as usual
+    #[default]
+    Wait,
+    /// Cancel the remainder and immediately place the outstanding diff at the new price
+    Chase,
+    /// Cancel the remainder only - the next re-evaluation places a fresh order at its price, so
+    /// nothing new is sent before the cancel is confirmed
+    CancelAndReprice,
+}
+
+impl FromStr for PartialFillPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "wait" => Ok(PartialFillPolicy::Wait),
+            "chase" => Ok(PartialFillPolicy::Chase),
+            "cancel_and_reprice" => Ok(PartialFillPolicy::CancelAndReprice),
+            other => Err(format!("Unknown partial fill policy: {}", other)),
+        }
+    }
+}
+
+/// Whether any of the orders (signed quantity, unsigned filled) has filled but is still working
+pub fn is_partially_filled(orders: &[(f64, f64)]) -> bool {
+    orders
+        .iter()
+        .any(|(quantity, filled)| *filled > 0.0 && *filled < quantity.abs())
+}
+
+/// Applies the policy to the NettingDecision for qty_diff (target - current_position) when the
+/// working orders are partially filled
+/// - decisions that already cancel every working order are left as they are
+pub fn on_partial_fill(
+    policy: PartialFillPolicy,
+    decision: NettingDecision,
+    qty_diff: f64,
+) -> NettingDecision {
+    match decision {
+        NettingDecision::CancelAll | NettingDecision::CancelAndPlace(_) => decision,
+        NettingDecision::Hold | NettingDecision::Place(_) => match policy {
+            PartialFillPolicy::Wait => decision,
+            PartialFillPolicy::Chase if qty_diff != 0.0 => {
+                NettingDecision::CancelAndPlace(qty_diff)
+            }
+            PartialFillPolicy::Chase | PartialFillPolicy::CancelAndReprice => {
+                NettingDecision::CancelAll
+            }
+        },
+    }
+}
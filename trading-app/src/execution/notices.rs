@@ -0,0 +1,187 @@
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+
+/// IBKR error code for an order rejected by IBKR / the exchange
+const ORDER_REJECTED_CODE: i32 = 201;
+/// IBKR error code sent back once an order has been cancelled
+const ORDER_CANCELLED_CODE: i32 = 202;
+/// IBKR error code for exceeding the max rate of messages per second
+const MAX_MESSAGE_RATE_CODE: i32 = 100;
+/// IBKR error code for a pacing violation on a real time bar / market data request
+const REALTIME_PACING_CODE: i32 = 420;
+/// IBKR error code for historical data errors - only a pacing violation if the message says so
+const HISTORICAL_DATA_ERROR_CODE: i32 = 162;
+/// IBKR sends connectivity / farm status messages (2100 - 2199) as errors, they need no action
+const INFORMATIONAL_CODES: std::ops::RangeInclusive<i32> = 2100..=2199;
+
+/// What a BrokerNotice asks the engine to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoticeKind {
+    /// Order is no longer working - its open order row has to go
+    OrderRejected,
+    OrderCancelled,
+    /// Too many requests were sent - requests are held back for a while
+    PacingViolation,
+    /// Status messages, e.g. market data farm connected
+    Informational,
+    Other,
+}
+
+/// Notice / error message from IBKR with its code kept, instead of only its text
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerNotice {
+    /// Order the notice is about - None when it didn't come through a per order request (ibapi
+    /// notices on the sync / order update streams don't carry the request id)
+    pub order_id: Option<i32>,
+    pub code: i32,
+    pub message: String,
+}
+
+impl BrokerNotice {
+    pub fn new(order_id: Option<i32>, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            order_id,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> NoticeKind {
+        match self.code {
+            ORDER_REJECTED_CODE => NoticeKind::OrderRejected,
+            ORDER_CANCELLED_CODE => NoticeKind::OrderCancelled,
+            MAX_MESSAGE_RATE_CODE | REALTIME_PACING_CODE => NoticeKind::PacingViolation,
+            HISTORICAL_DATA_ERROR_CODE
+                if self.message.to_lowercase().contains("pacing violation") =>
+            {
+                NoticeKind::PacingViolation
+            }
+            code if INFORMATIONAL_CODES.contains(&code) => NoticeKind::Informational,
+            _ => NoticeKind::Other,
+        }
+    }
+}
+
+/// Holds back order placement for a while after IBKR reports a pacing violation
+#[derive(Debug)]
+pub struct PacingBackoff {
+    delay: Duration,
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl Default for PacingBackoff {
+    /// IBKR allows 50 messages a second - a second of quiet lets the limit reset
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+impl PacingBackoff {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// Pauses requests until delay from now - a later violation extends the pause
+    pub fn trigger_at(&self, now: Instant) {
+        let mut paused_until = self
+            .paused_until
+            .lock()
+            .expect("Expected to be able to acquire lock for PacingBackoff.paused_until");
+        *paused_until = Some(now + self.delay);
+    }
+
+    pub fn trigger(&self) {
+        self.trigger_at(Instant::now());
+    }
+
+    /// How much longer requests are paused for as of now
+    pub fn remaining_at(&self, now: Instant) -> Duration {
+        let paused_until = self
+            .paused_until
+            .lock()
+            .expect("Expected to be able to acquire lock for PacingBackoff.paused_until");
+        paused_until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now))
+    }
+
+    /// Blocks the current thread until the pause is over
+    pub fn wait(&self) {
+        let remaining = self.remaining_at(Instant::now());
+        if !remaining.is_zero() {
+            thread::sleep(remaining);
+        }
+    }
+}
+
+/// Routes a notice to whatever has to act on it
+/// - rejected / cancelled orders have their open order row removed
+/// - pacing violations pause order placement through backoff
+/// - returns whether an open order row was removed
+pub async fn handle_broker_notice(
+    pool: PgPool,
+    backoff: &PacingBackoff,
+    notice: &BrokerNotice,
+) -> Result<bool, String> {
+    match notice.kind() {
+        NoticeKind::OrderRejected | NoticeKind::OrderCancelled => match notice.order_id {
+            Some(order_id) => {
+                tracing::warn!(
+                    "Order {} no longer working ({}): {}",
+                    order_id,
+                    notice.code,
+                    notice.message
+                );
+                remove_open_order(pool, order_id).await
+            }
+            None => {
+                tracing::warn!(
+                    "Order notice {} without an order id, open orders are left for the next sync: {}",
+                    notice.code,
+                    notice.message
+                );
+                Ok(false)
+            }
+        },
+        NoticeKind::PacingViolation => {
+            tracing::warn!(
+                "Pacing violation from IBKR ({}), backing off: {}",
+                notice.code,
+                notice.message
+            );
+            backoff.trigger();
+            Ok(false)
+        }
+        NoticeKind::Informational => {
+            tracing::info!("IBKR notice {}: {}", notice.code, notice.message);
+            Ok(false)
+        }
+        NoticeKind::Other => {
+            tracing::warn!("IBKR notice {}: {}", notice.code, notice.message);
+            Ok(false)
+        }
+    }
+}
+
+/// Deletes the open stock / option order placed under order_id
+async fn remove_open_order(pool: PgPool, order_id: i32) -> Result<bool, String> {
+    let mut removed = false;
+    for statement in [
+        "DELETE FROM trading.open_stock_orders WHERE order_id = $1",
+        "DELETE FROM trading.open_option_orders WHERE order_id = $1",
+    ] {
+        let result = sqlx::query(statement)
+            .bind(order_id)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to remove open order {}: {}", order_id, e))?;
+        removed |= result.rows_affected() > 0;
+    }
+    Ok(removed)
+}
@@ -7,13 +7,16 @@ use ibapi::{
 };
 use sqlx::PgPool;
 
-use crate::database::{
-    crud::{CRUD, CRUDTrait},
-    models::{
-        AssetType, OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys,
-        OpenOptionOrdersUpdateKeys, OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys,
-        OpenStockOrdersUpdateKeys, OptionType,
+use crate::{
+    database::{
+        crud::{CRUD, CRUDTrait},
+        models::{
+            AssetType, OpenOptionOrdersFullKeys, OpenOptionOrdersPrimaryKeys,
+            OpenOptionOrdersUpdateKeys, OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys,
+            OpenStockOrdersUpdateKeys, OptionType, normalize_multiplier,
+        },
     },
+    execution::order_map::order_snapshot,
 };
 
 // In conjunction with sync_open_orders
@@ -65,6 +68,7 @@ pub fn on_full_open_order_received(
                                                 quantity: None,
                                                 executions: None,
                                                 filled: Some(order_status.filled.clone()),
+                                                order_snapshot: None,
                                             },
                                         )
                                         .await
@@ -82,12 +86,15 @@ pub fn on_full_open_order_received(
                                         order_perm_id: order.perm_id.clone(),
                                         order_id: order.order_id.clone(),
                                         strategy: strategy.clone(),
-                                        stock: contract.symbol,
+                                        stock: contract.symbol.clone(),
                                         primary_exchange: contract.primary_exchange.clone(),
                                         time: Utc::now(),
                                         quantity: order.total_quantity,
                                         executions: Vec::new(),
                                         filled: order.filled_quantity,
+                                        order_snapshot: order_snapshot(&contract, &order)
+                                            .inspect_err(|e| tracing::error!("{}", e))
+                                            .ok(),
                                     })
                                     .await
                                 {
@@ -146,6 +153,7 @@ pub fn on_full_open_order_received(
                                                 quantity: None,
                                                 executions: None,
                                                 filled: Some(order_status.filled.clone()),
+                                                order_snapshot: None,
                                             },
                                         )
                                         .await
@@ -163,16 +171,19 @@ pub fn on_full_open_order_received(
                                         order_perm_id: order.perm_id.clone(),
                                         order_id: order.order_id.clone(),
                                         strategy: strategy.clone(),
-                                        stock: contract.symbol,
+                                        stock: contract.symbol.clone(),
                                         primary_exchange: contract.primary_exchange.clone(),
-                                        expiry: contract.last_trade_date_or_contract_month,
+                                        expiry: contract.last_trade_date_or_contract_month.clone(),
                                         strike: contract.strike,
-                                        multiplier: contract.multiplier,
+                                        multiplier: normalize_multiplier(&contract.multiplier),
                                         option_type: OptionType::from_str(&contract.right).expect("Expected valid contract right to be passed to OptionType for sync_open_orders"),
                                         time: Utc::now(),
                                         quantity: order.total_quantity,
                                         executions: Vec::new(),
                                         filled: order.filled_quantity,
+                                        order_snapshot: order_snapshot(&contract, &order)
+                                            .inspect_err(|e| tracing::error!("{}", e))
+                                            .ok(),
                                     })
                                     .await
                                 {
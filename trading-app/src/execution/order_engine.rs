@@ -19,25 +19,31 @@ use core::str;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
-    thread::{self, scope},
+    thread,
 };
 
+use chrono::Utc;
 use ibapi::{
     Client,
     orders::{ExecutionFilter, Executions, Order, OrderStatus, OrderUpdate},
     prelude::{Contract, PositionUpdate, SecurityType},
 };
-use ordered_float::OrderedFloat;
 use sqlx::PgPool;
-use tokio::sync::mpsc::channel;
+use tokio::{sync::mpsc::channel, task::JoinHandle};
 use tracing::info;
 
 use crate::{
+    app_state::TradingAppState,
     database::{
         crud::CRUDTrait,
-        models::{AssetType, OptionType},
+        models::{
+            AssetType, CurrentOptionPositionsPrimaryKeys, CurrentStockPositionsPrimaryKeys,
+            OptionType, normalize_multiplier,
+        },
         models_crud::{
-            current_option_positions::get_specific_current_option_positions_crud,
+            current_option_positions::{
+                get_current_option_positions_crud, get_specific_current_option_positions_crud,
+            },
             current_stock_positions::{
                 get_current_stock_positions_crud, get_specific_current_stock_positions_crud,
             },
@@ -46,15 +52,38 @@ use crate::{
         },
     },
     execution::{
-        events::order_events::{
-            on_commission_update, on_execution_update, on_new_option_qty_diff_for_strat,
-            on_new_stock_qty_diff_for_strat,
+        blocking_pool::BlockingPool,
+        cancel::OrderCanceller,
+        contract_validation::{
+            ContractValidationCache, ContractValidator, InvalidContract,
+            invalid_strategy_contracts, validation_key,
+        },
+        events::{
+            on_execution_updates::DEFAULT_UNKNOWN_STRATEGY,
+            order_events::{
+                on_commission_update, on_execution_update, on_new_option_qty_diff_for_strat,
+                on_new_stock_qty_diff_for_strat,
+            },
         },
+        fills::{FillSender, spawn_fill_listener},
+        netting::{NettingPolicy, PartialFillPolicy, round_quantity, scale_qty_diff},
+        notices::{BrokerNotice, PacingBackoff, handle_broker_notice},
         on_full_open_order_received,
-        order_update_stream::on_order_update_received,
-        place_order::place_order,
+        order_limit::SessionOrderLimit,
+        order_map::{OrderMapStore, load_order_map, order_map_retention, prune_order_map},
+        order_update_stream::{on_order_update_received, strategy_for_order},
+        place_order::{
+            DEFAULT_ORDER_ROUTING, MinTickCache, OrderContext, OrderSubmitter, place_order,
+        },
+        sync::{
+            OptionKey, PositionReconciliation, PositionSyncSummary, ReconcileDirection,
+            SyncOptions, SyncStep, SyncWriteRetry, await_sync_writes, broker_option_key,
+            local_option_key, reconcile_position, remove_stale_open_orders, retry_sync_write,
+            submit_corrective_orders, trust_local_corrections,
+        },
     },
     strategy::strategy::StrategyExecutor,
+    supervisor::{SupervisorOptions, ThreadStatus, supervise},
     unlock,
 };
 
@@ -88,13 +117,66 @@ impl StatusOfOrderStatus {
     }
 }
 
+/// Contracts (with the asset path they are reconciled through) to place orders for when contract
+/// gets a bar update - contract itself, then the strategy's dependents of it
+/// - lets a strategy trading an underlying and its options reconcile both off the underlying's bar
+pub fn bar_update_targets<T: StrategyExecutor>(
+    strategy: &T,
+    contract: &Contract,
+) -> Vec<(Contract, AssetType)> {
+    std::iter::once(contract.clone())
+        .chain(strategy.get_dependent_contracts(contract))
+        .map(|target| {
+            let asset_type = AssetType::from_str(target.security_type.clone());
+            (target, asset_type)
+        })
+        .collect()
+}
+
+/// (Security Type, Symbol) a strategy trades
+pub type ContractKey = (String, String);
+
 pub struct OrderEngine {
     pub pool: PgPool,
     // order_id
     // - Gotten in many places, but inserts ONLY during place_order()
     order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
+    // min_tick of every contract ordered so far, limit prices are rounded to it
+    min_ticks: Arc<MinTickCache>,
+    // Paused after IBKR reports a pacing violation, order placement waits it out
+    pacing_backoff: Arc<PacingBackoff>,
+    // Bounded set of threads orders are placed / cancelled on
+    blocking_pool: Arc<BlockingPool>,
     // Security Type, Symbol
-    contract_to_strategy: HashMap<(String, String), String>,
+    contract_to_strategy: HashMap<ContractKey, String>,
+    // Contracts claimed by more than one strategy, with all the claiming strategies
+    conflicts: Vec<(ContractKey, Vec<String>)>,
+    // Contracts strategies declared that failed validation at registration, left unregistered
+    invalid_contracts: Vec<InvalidContract>,
+    // How new position diffs are netted against orders still working at the broker
+    netting_policy: NettingPolicy,
+    // What happens to partially filled working orders when the diff is re-evaluated
+    partial_fill_policy: PartialFillPolicy,
+    // Decimal places stock order quantities are rounded to - 0 unless the account allows
+    // fractional shares
+    share_quantity_decimals: u32,
+    // Exchange orders are routed through - positions and data stay keyed on the contract's
+    // primary_exchange regardless
+    order_routing: String,
+    // Whether placed orders are written to trading.order_map, so order_map can be rehydrated
+    // after a restart
+    persist_order_map: bool,
+    // Fills booked from executions are reported here for the strategies' on_fill, once
+    // begin_fill_listening is called
+    fill_sender: Option<FillSender>,
+    // Catch-all strategy executions without an open order and unexplained position
+    // discrepancies are booked to
+    unknown_strategy: String,
+    // Targets are scaled by this when orders are placed (e.g. 0.1 to trade a tenth of the size
+    // live) - the stored targets are left as they are
+    order_size_multiplier: f64,
+    // Caps the orders each strategy places in the session
+    order_limit: SessionOrderLimit,
 }
 
 // Dummy implementations since in the app, only 1 should live at any point in time
@@ -121,9 +203,38 @@ impl Ord for OrderEngine {
 impl OrderEngine {
     // Active Strategies passed for deconflicting of executions in cases where it occurs
     pub fn new<T: StrategyExecutor>(pool: PgPool, active_strategies: Vec<T>) -> Self {
-        let mut contract_to_full_strategy: HashMap<(String, String), T> = HashMap::new();
+        Self::register(pool, active_strategies, Vec::new())
+    }
+
+    /// new, with every strategy's contracts validated through validated_contracts first - the
+    /// contracts that fail validation aren't registered and are reported by invalid_contracts
+    pub fn new_validated<T: StrategyExecutor, V: ContractValidator + ?Sized>(
+        pool: PgPool,
+        active_strategies: Vec<T>,
+        validator: &V,
+        validated_contracts: &ContractValidationCache,
+    ) -> Self {
+        let invalid_contracts =
+            invalid_strategy_contracts(&active_strategies, validator, validated_contracts);
+        Self::register(pool, active_strategies, invalid_contracts)
+    }
+
+    fn register<T: StrategyExecutor>(
+        pool: PgPool,
+        active_strategies: Vec<T>,
+        invalid_contracts: Vec<InvalidContract>,
+    ) -> Self {
+        let mut contract_to_full_strategy: HashMap<ContractKey, T> = HashMap::new();
+        let mut contract_claimants: HashMap<ContractKey, Vec<String>> = HashMap::new();
         for strategy in active_strategies {
             for contract in strategy.get_contracts() {
+                let is_invalid = invalid_contracts.iter().any(|invalid| {
+                    invalid.strategy == strategy.get_name()
+                        && validation_key(&invalid.contract) == validation_key(&contract)
+                });
+                if is_invalid {
+                    continue;
+                }
                 let symbol = if contract.security_type == SecurityType::Future {
                     format!("FUT:{}", contract.symbol.clone())
                 } else if contract.security_type == SecurityType::Stock {
@@ -135,25 +246,17 @@ impl OrderEngine {
                 } else {
                     String::from("Unknown")
                 };
-                if contract_to_full_strategy
-                    .contains_key(&(contract.security_type.to_string().clone(), symbol.clone()))
-                {
-                    // Update contract_to_strategy
-                    let current_strategy = contract_to_full_strategy
-                        .get(&(contract.security_type.to_string().clone(), symbol.clone()))
-                        .unwrap();
-                    if &strategy > current_strategy {
-                        contract_to_full_strategy.insert(
-                            (contract.security_type.to_string().clone(), symbol.clone()),
-                            strategy.clone(),
-                        );
+                let contract_key: ContractKey = (contract.security_type.to_string(), symbol);
+                let claimants = contract_claimants.entry(contract_key.clone()).or_default();
+                if !claimants.contains(&strategy.get_name()) {
+                    claimants.push(strategy.get_name());
+                }
+                match contract_to_full_strategy.get(&contract_key) {
+                    // Higher Ord priority wins the contract
+                    Some(current_strategy) if &strategy <= current_strategy => {}
+                    _ => {
+                        contract_to_full_strategy.insert(contract_key, strategy.clone());
                     }
-                } else {
-                    // Update contract_to_strategy
-                    contract_to_full_strategy.insert(
-                        (contract.security_type.to_string().clone(), symbol.clone()),
-                        strategy.clone(),
-                    );
                 }
             }
         }
@@ -161,13 +264,234 @@ impl OrderEngine {
         for (contract, full_strategy) in contract_to_full_strategy.iter() {
             contract_to_strategy.insert(contract.clone(), full_strategy.get_name());
         }
+        let mut conflicts: Vec<(ContractKey, Vec<String>)> = contract_claimants
+            .into_iter()
+            .filter(|(_, strategies)| strategies.len() > 1)
+            .collect();
+        conflicts.sort();
+        for (contract, strategies) in conflicts.iter() {
+            tracing::warn!(
+                "Contract {:?} claimed by strategies {:?} - assigned to {}",
+                contract,
+                strategies,
+                contract_to_strategy[contract]
+            );
+        }
         Self {
             pool,
             order_map: Arc::new(Mutex::new(HashMap::new())),
+            min_ticks: Arc::new(MinTickCache::new()),
+            pacing_backoff: Arc::new(PacingBackoff::default()),
+            blocking_pool: Arc::new(BlockingPool::default()),
             contract_to_strategy,
+            conflicts,
+            invalid_contracts,
+            netting_policy: NettingPolicy::default(),
+            partial_fill_policy: PartialFillPolicy::default(),
+            share_quantity_decimals: 0,
+            order_routing: DEFAULT_ORDER_ROUTING.to_string(),
+            persist_order_map: false,
+            fill_sender: None,
+            unknown_strategy: DEFAULT_UNKNOWN_STRATEGY.to_string(),
+            order_size_multiplier: 1.0,
+            order_limit: SessionOrderLimit::default(),
         }
     }
 
+    /// Contracts claimed by more than one strategy with every claiming strategy - the contract
+    /// is owned by the highest priority one (see contract_to_strategy)
+    pub fn conflicts(&self) -> Vec<(ContractKey, Vec<String>)> {
+        self.conflicts.clone()
+    }
+
+    /// Contracts (with the strategy declaring them) that failed validation at registration -
+    /// always empty unless built through new_validated / from_state_validated
+    pub fn invalid_contracts(&self) -> Vec<InvalidContract> {
+        self.invalid_contracts.clone()
+    }
+
+    pub fn set_netting_policy(&mut self, netting_policy: NettingPolicy) {
+        self.netting_policy = netting_policy;
+    }
+
+    pub fn get_netting_policy(&self) -> NettingPolicy {
+        self.netting_policy
+    }
+
+    pub fn set_partial_fill_policy(&mut self, partial_fill_policy: PartialFillPolicy) {
+        self.partial_fill_policy = partial_fill_policy;
+    }
+
+    pub fn get_partial_fill_policy(&self) -> PartialFillPolicy {
+        self.partial_fill_policy
+    }
+
+    pub fn set_share_quantity_decimals(&mut self, share_quantity_decimals: u32) {
+        self.share_quantity_decimals = share_quantity_decimals;
+    }
+
+    pub fn get_share_quantity_decimals(&self) -> u32 {
+        self.share_quantity_decimals
+    }
+
+    /// Exchange orders are submitted to (SMART by default) - a directed exchange or an empty
+    /// string to submit each contract on its own exchange
+    pub fn set_order_routing(&mut self, order_routing: impl Into<String>) {
+        self.order_routing = order_routing.into();
+    }
+
+    pub fn get_order_routing(&self) -> &str {
+        &self.order_routing
+    }
+
+    /// Off unless set - the session turns it on through TradingConfig.persist_order_map
+    pub fn set_persist_order_map(&mut self, persist_order_map: bool) {
+        self.persist_order_map = persist_order_map;
+    }
+
+    pub fn get_persist_order_map(&self) -> bool {
+        self.persist_order_map
+    }
+
+    /// Catch-all strategy ("unknown" unless set) orphaned executions and discrepancies found by
+    /// sync_positions are booked to - must exist in trading.strategy
+    pub fn set_unknown_strategy(&mut self, unknown_strategy: impl Into<String>) {
+        self.unknown_strategy = unknown_strategy.into();
+    }
+
+    pub fn get_unknown_strategy(&self) -> &str {
+        &self.unknown_strategy
+    }
+
+    /// Fraction of its targets every strategy is traded at (1.0 unless set) - positions are
+    /// driven towards target * order_size_multiplier, see netting::scale_qty_diff
+    pub fn set_order_size_multiplier(&mut self, order_size_multiplier: f64) {
+        self.order_size_multiplier = order_size_multiplier;
+    }
+
+    pub fn get_order_size_multiplier(&self) -> f64 {
+        self.order_size_multiplier
+    }
+
+    /// Limit on the orders each strategy may place in the session (none unless set) - orders
+    /// past it are dropped by place_orders_for_strategy
+    pub fn set_order_limit(&mut self, order_limit: SessionOrderLimit) {
+        self.order_limit = order_limit;
+    }
+
+    pub fn get_order_limit(&self) -> &SessionOrderLimit {
+        &self.order_limit
+    }
+
+    /// Store orders placed from here are persisted through, if persist_order_map is on
+    fn order_map_store(&self) -> Option<OrderMapStore> {
+        if !self.persist_order_map {
+            return None;
+        }
+        let order_map_store = OrderMapStore::from_current(self.pool.clone());
+        if order_map_store.is_none() {
+            tracing::warn!("Not in an async runtime, orders placed won't be persisted");
+        }
+        order_map_store
+    }
+
+    /// Context orders are placed from here with - shares this engine's order_map, caches and
+    /// blocking pool
+    pub fn order_context(&self) -> OrderContext {
+        OrderContext {
+            pool: self.pool.clone(),
+            order_map: self.order_map.clone(),
+            min_ticks: self.min_ticks.clone(),
+            pacing_backoff: self.pacing_backoff.clone(),
+            order_routing: self.order_routing.clone(),
+            order_map_store: self.order_map_store(),
+            blocking_pool: self.blocking_pool.clone(),
+            netting_policy: self.netting_policy,
+            partial_fill_policy: self.partial_fill_policy,
+            share_quantity_decimals: self.share_quantity_decimals,
+            order_limit: self.order_limit.clone(),
+        }
+    }
+
+    /// Loads the orders placed in previous sessions from trading.order_map into order_map - call
+    /// before the order update stream starts so updates / executions for them are attributed to
+    /// their strategies
+    /// - only live orders are loaded: those still open or placed within order_map_retention -
+    ///   the settled ones older than that are pruned from trading.order_map first
+    /// - orders already in order_map are kept as they are
+    /// - returns the number of orders loaded
+    pub async fn rehydrate_order_map(&self) -> Result<usize, String> {
+        let cutoff = Utc::now() - order_map_retention();
+        let pruned = prune_order_map(self.pool.clone(), cutoff).await?;
+        if pruned > 0 {
+            info!("Pruned {} settled orders from order_map", pruned);
+        }
+        let persisted = load_order_map(self.pool.clone(), cutoff).await?;
+        let mut order_map = unlock!(
+            self.order_map,
+            "order_map",
+            "OrderEngine.rehydrate_order_map"
+        );
+        let mut loaded = 0;
+        for (order_id, strategy_order) in persisted {
+            if let std::collections::hash_map::Entry::Vacant(entry) = order_map.entry(order_id) {
+                entry.insert(strategy_order);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Starts calling on_fill of strategies for the fills booked for them - call before the
+    /// order update stream starts and before sync_executions, fills booked before are not
+    /// reported
+    /// - must be called from within the async runtime
+    pub fn begin_fill_listening<T: StrategyExecutor + 'static>(&mut self, strategies: Vec<T>) {
+        self.fill_sender = Some(spawn_fill_listener(strategies));
+    }
+
+    pub fn set_blocking_pool(&mut self, blocking_pool: BlockingPool) {
+        self.blocking_pool = Arc::new(blocking_pool);
+    }
+
+    /// Engine on the session's pool, placing orders on the session's blocking pool
+    pub fn from_state<T: StrategyExecutor>(
+        state: &TradingAppState,
+        active_strategies: Vec<T>,
+    ) -> Self {
+        Self::new(state.pool.clone(), active_strategies).configured_from(state)
+    }
+
+    /// from_state, with the strategies' contracts validated through the session's
+    /// validated_contracts (see new_validated)
+    pub fn from_state_validated<T: StrategyExecutor, V: ContractValidator + ?Sized>(
+        state: &TradingAppState,
+        active_strategies: Vec<T>,
+        validator: &V,
+    ) -> Self {
+        Self::new_validated(
+            state.pool.clone(),
+            active_strategies,
+            validator,
+            &state.validated_contracts,
+        )
+        .configured_from(state)
+    }
+
+    fn configured_from(mut self, state: &TradingAppState) -> Self {
+        self.blocking_pool = state.blocking_pool.clone();
+        self.persist_order_map = state.config.persist_order_map;
+        self.unknown_strategy = state.config.unknown_strategy.clone();
+        self.order_size_multiplier = state.config.order_size_multiplier;
+        self.partial_fill_policy = state.config.partial_fill_policy;
+        self.share_quantity_decimals = state.config.share_quantity_decimals;
+        self.order_limit = SessionOrderLimit::new(
+            state.config.max_orders_per_strategy,
+            Some(state.config.notification_url.clone()),
+        );
+        self
+    }
+
     // Call before sync_positions - tries its best to sync all missed orders since last session
     // - but may miss some position updates -> Have to reconcile manually and via sync_positions
     pub fn sync_executions(&self, client: &Client) -> Result<(), String> {
@@ -180,17 +504,7 @@ impl OrderEngine {
         for execution in subscription {
             match execution {
                 Executions::ExecutionData(execution_data) => {
-                    let strategy = {
-                        let order_map =
-                            unlock!(self.order_map, "order_map", "OrderEngine.sync_executions");
-                        order_map
-                            .get(&execution_data.execution.order_id)
-                            .map_or(
-                                "Unknown strategy: not recorded in order_map".to_string(),
-                                |v| v.0.clone(),
-                            )
-                            .clone()
-                    };
+                    let strategy = self.strategy_for_order(execution_data.execution.order_id)?;
                     tracing::info!(
                         "Syncing Executions: New Execution recorded with id: {} for strategy: {}",
                         &execution_data.request_id,
@@ -209,7 +523,12 @@ impl OrderEngine {
                     //     );
                     // }
 
-                    on_execution_update(self.pool.clone(), execution_data);
+                    on_execution_update(
+                        self.pool.clone(),
+                        execution_data,
+                        self.unknown_strategy.clone(),
+                        self.fill_sender.clone(),
+                    );
                 }
 
                 Executions::CommissionReport(commission_report) => {
@@ -239,8 +558,8 @@ impl OrderEngine {
                     };
                 }
 
-                Executions::Notice(message) => {
-                    tracing::warn!("Message from OrderEngine.sync_executions: {}", message);
+                Executions::Notice(notice) => {
+                    self.on_broker_notice(BrokerNotice::new(None, notice.code, notice.message));
                 }
             }
         }
@@ -249,7 +568,10 @@ impl OrderEngine {
     }
 
     // Tries to reconcile via strategy priority in cases of conflict
-    pub fn sync_open_orders(&self, client: &Client) {
+    // - remove_stale: also deletes local open orders the broker did not return (see
+    // sync::remove_stale_open_orders), awaited before returning
+    pub async fn sync_open_orders(&self, client: &Client, remove_stale: bool) {
+        let snapshot_time = Utc::now();
         let mut open_orders: HashMap<i32, (Option<Contract>, Option<Order>, Option<OrderStatus>)> =
             HashMap::new();
         let subscription = client
@@ -304,63 +626,129 @@ impl OrderEngine {
                     }
                 }
                 ibapi::orders::Orders::Notice(notice) => {
-                    tracing::warn!("Notice from OrderEngine.sync_open_orders: {}", notice);
+                    self.on_broker_notice(BrokerNotice::new(None, notice.code, notice.message));
                 }
             }
         }
+
+        if remove_stale {
+            let broker_perm_ids: Vec<i32> = open_orders.keys().copied().collect();
+            match remove_stale_open_orders(self.pool.clone(), &broker_perm_ids, snapshot_time).await
+            {
+                Ok(removed) if !removed.is_empty() => {
+                    tracing::info!("Removed stale open orders: {:?}", removed)
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Error removing stale open orders: {}", e),
+            }
+        }
     }
 
-    pub fn sync_positions(&self, client: &Client) {
-        let mut stock_map: HashMap<String, f64> = HashMap::new();
-        let mut option_map: HashMap<(String, OrderedFloat<f64>, String, String, OptionType), f64> =
-            HashMap::new();
-        scope(|s| {
-            s.spawn(|| async {
-                let current_stock_positions_crud =
-                    get_specific_current_stock_positions_crud(self.pool.clone());
-                let current_stock_positions_res = &current_stock_positions_crud
-                    .get_all_positions_by_stock()
-                    .await;
-
-                match current_stock_positions_res {
-                    Ok(current_stock_positions) => {
-                        for position in current_stock_positions {
-                            stock_map.insert(position.stock.clone(), position.quantity.clone());
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error trying to read all stock positions in DB: {}", e)
+    /// Acts on a notice IBKR sent during a sync (see notices::handle_broker_notice)
+    /// - handled asynchronously via tokio::spawn since the syncs block
+    fn on_broker_notice(&self, notice: BrokerNotice) {
+        let pool = self.pool.clone();
+        let pacing_backoff = self.pacing_backoff.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_broker_notice(pool, &pacing_backoff, &notice).await {
+                tracing::error!("Error handling IBKR notice {}: {}", notice.code, e);
+            }
+        });
+    }
+
+    /// Runs the syncs enabled in options in their configured order (see SyncOptions for the
+    /// ordering constraint)
+    /// - a failed sync_executions is logged and the remaining steps still run
+    /// - Err if any position could not be reconciled in the DB, after every step has run
+    pub async fn sync_all(&self, client: &Client, options: &SyncOptions) -> Result<(), String> {
+        let mut failed_positions = 0;
+        for step in options.steps()? {
+            tracing::info!("Syncing {:?}", step);
+            match step {
+                SyncStep::Executions => {
+                    if let Err(e) = self.sync_executions(client) {
+                        tracing::error!("Error syncing executions: {}", e);
                     }
                 }
-            });
-            s.spawn(|| async {
-                let current_option_positions_crud =
-                    get_specific_current_option_positions_crud(self.pool.clone());
-                let current_option_positions_res = current_option_positions_crud
-                    .get_all_positions_by_contract()
-                    .await;
-
-                match current_option_positions_res {
-                    Ok(current_option_positions) => {
-                        for position in current_option_positions {
-                            option_map.insert(
-                                (
-                                    position.stock,
-                                    OrderedFloat::from(position.strike),
-                                    position.expiry,
-                                    position.multiplier,
-                                    position.option_type,
-                                ),
-                                position.quantity.clone(),
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error trying to read all option positions in DB: {}", e)
+                SyncStep::OpenOrders => {
+                    self.sync_open_orders(client, options.remove_stale_open_orders)
+                        .await
+                }
+                SyncStep::Positions => {
+                    let (corrective_orders, summary) = self
+                        .sync_positions(client, options.reconcile_direction, options.write_retry)
+                        .await;
+                    tracing::info!(
+                        "Reconciled {} positions, {} failed",
+                        summary.reconciled,
+                        summary.failed
+                    );
+                    failed_positions += summary.failed;
+                    if !corrective_orders.is_empty() {
+                        let order_ids = submit_corrective_orders(
+                            client,
+                            &corrective_orders,
+                            &self.order_routing,
+                            &self.pacing_backoff,
+                        );
+                        tracing::warn!(
+                            "Submitted {} of {} corrective orders: {:?}",
+                            order_ids.len(),
+                            corrective_orders.len(),
+                            order_ids
+                        );
                     }
                 }
-            });
+            }
+        }
+        if failed_positions > 0 {
+            return Err(format!(
+                "{} positions could not be reconciled",
+                failed_positions
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reconciles local positions with the broker's according to direction
+    /// - TrustBroker: discrepancies are written to the DB (see below)
+    /// - TrustLocal: the DB is left alone, returns the (contract, signed quantity) orders that
+    ///   bring the broker position to the local one
+    /// - every DB write is retried as per write_retry and awaited before returning, the summary
+    ///   counts the positions written / failed
+    pub async fn sync_positions(
+        &self,
+        client: &Client,
+        direction: ReconcileDirection,
+        write_retry: SyncWriteRetry,
+    ) -> (Vec<(Contract, f64)>, PositionSyncSummary) {
+        let mut corrective_orders = Vec::new();
+        let mut writes: Vec<JoinHandle<Result<(), String>>> = Vec::new();
+        let current_stock_positions_crud =
+            get_specific_current_stock_positions_crud(self.pool.clone());
+        let current_option_positions_crud =
+            get_specific_current_option_positions_crud(self.pool.clone());
+        let (local_stocks, local_options) = tokio::join!(
+            current_stock_positions_crud.get_all_positions_by_stock(),
+            current_option_positions_crud.get_all_positions_by_contract(),
+        );
+        let local_stocks = local_stocks.unwrap_or_else(|e| {
+            tracing::error!("Error trying to read all stock positions in DB: {}", e);
+            Vec::new()
+        });
+        let local_options = local_options.unwrap_or_else(|e| {
+            tracing::error!("Error trying to read all option positions in DB: {}", e);
+            Vec::new()
         });
+        let stock_map: HashMap<String, f64> = local_stocks
+            .iter()
+            .map(|position| (position.stock.clone(), position.quantity))
+            .collect();
+        let option_map: HashMap<OptionKey, f64> = local_options
+            .iter()
+            .map(|position| (local_option_key(position), position.quantity))
+            .collect();
+        let mut broker_positions = Vec::new();
 
         let subscription = client
             .positions()
@@ -368,11 +756,17 @@ impl OrderEngine {
         for position_response in subscription.iter() {
             match position_response {
                 PositionUpdate::Position(position) => {
+                    if direction == ReconcileDirection::TrustLocal {
+                        broker_positions.push(position);
+                        continue;
+                    }
                     match position.contract.security_type {
                         SecurityType::Stock | SecurityType::Future | SecurityType::ForexPair => {
                             match &stock_map.get(&position.contract.symbol) {
                                 Some(local_pos) => {
-                                    if **local_pos != position.position {
+                                    if reconcile_position(direction, **local_pos, position.position)
+                                        != PositionReconciliation::InSync
+                                    {
                                         tracing::warn!(
                                             "Reconciling current stock position according to broker position (Local: {}, Broker: {})",
                                             local_pos,
@@ -390,30 +784,30 @@ impl OrderEngine {
                                         } else {
                                             position.contract.symbol.clone()
                                         };
-                                        let discrepancy = (position.position - **local_pos).clone();
-                                        tokio::spawn(async move {
-                                            match current_stock_positions_crud
-                                                .update_unknown_strat_positions(
-                                                    symbol.clone(),
-                                                    discrepancy,
-                                                )
-                                                .await
-                                            {
-                                                Ok(_) => {
-                                                    tracing::warn!(
-                                                        "Discrepancy in stock positions, allocated to strategy unknown: {} for qty of {}",
-                                                        symbol,
-                                                        position.position
-                                                    )
-                                                }
-                                                Err(e) => {
-                                                    tracing::error!(
-                                                        "Error trying to reconcile Discrepancy in stock positions: {}",
-                                                        e
-                                                    )
-                                                }
-                                            };
-                                        });
+                                        let unknown_strategy = self.unknown_strategy.clone();
+                                        writes.push(tokio::spawn(async move {
+                                            retry_sync_write(
+                                                write_retry,
+                                                "reconcile Discrepancy in stock positions",
+                                                || {
+                                                    current_stock_positions_crud
+                                                        .sync_unknown_strat_positions(
+                                                            &unknown_strategy,
+                                                            symbol.clone(),
+                                                            position.contract.primary_exchange.clone(),
+                                                            position.position,
+                                                        )
+                                                },
+                                            )
+                                            .await?;
+                                            tracing::warn!(
+                                                "Discrepancy in stock positions, allocated to strategy {}: {} for qty of {}",
+                                                unknown_strategy,
+                                                symbol,
+                                                position.position
+                                            );
+                                            Ok(())
+                                        }));
                                     }
                                 }
                                 None => {
@@ -430,8 +824,8 @@ impl OrderEngine {
                                             position.contract.security_type.clone().to_string(),
                                             position.contract.symbol.clone(),
                                         ))
-                                        .map_or(String::from("unknown"), |v| v.to_string());
-                                    tokio::spawn(async move {
+                                        .map_or(self.unknown_strategy.clone(), |v| v.to_string());
+                                    writes.push(tokio::spawn(async move {
                                         let symbol = if position.contract.security_type
                                             == SecurityType::Future
                                         {
@@ -439,113 +833,122 @@ impl OrderEngine {
                                         } else {
                                             position.contract.symbol.clone()
                                         };
-                                        if let Err(e) = current_stock_positions_crud.create(&crate::database::models::CurrentStockPositionsFullKeys {
-                                        stock: symbol,
-                                        primary_exchange: position.contract.primary_exchange,
-                                        strategy: strategy,
-                                        quantity: position.position.clone(),
-                                        avg_price: position.average_cost.clone()
-                                    }).await {
-                                        tracing::error!("Error inserting into Current Stock Positions when reconciling stock positions (Local: {}, Broker: {}): {}", 0.0, &position.position, e)
-                                    }
-                                    });
+                                        let crud = &current_stock_positions_crud;
+                                        let row = &crate::database::models::CurrentStockPositionsFullKeys {
+                                            stock: symbol,
+                                            primary_exchange: position.contract.primary_exchange,
+                                            strategy: strategy,
+                                            quantity: position.position.clone(),
+                                            avg_price: position.average_cost.clone()
+                                        };
+                                        retry_sync_write(
+                                            write_retry,
+                                            &format!("insert into Current Stock Positions when reconciling stock positions (Local: {}, Broker: {})", 0.0, &position.position),
+                                            || crud.create(row),
+                                        )
+                                        .await
+                                    }));
                                 }
                             }
                         }
-                        SecurityType::Option => match &stock_map.get(&position.contract.symbol) {
-                            Some(local_pos) => {
-                                if **local_pos != position.position {
+                        SecurityType::Option => {
+                            let key = match broker_option_key(&position.contract) {
+                                Ok(key) => key,
+                                Err(e) => {
+                                    tracing::error!(
+                                        "Skipping broker option position of {} while reconciling: {}",
+                                        position.contract.symbol,
+                                        e
+                                    );
+                                    continue;
+                                }
+                            };
+                            let local_pos = option_map.get(&key).copied();
+                            let (symbol, expiry, strike, multiplier, option_type) = key;
+                            let primary_exchange = position.contract.primary_exchange.clone();
+                            match local_pos {
+                                Some(local_pos) => {
+                                    if reconcile_position(direction, local_pos, position.position)
+                                        != PositionReconciliation::InSync
+                                    {
+                                        tracing::warn!(
+                                            "Reconciling current option position according to broker position (Local: {}, Broker: {})",
+                                            local_pos,
+                                            &position.position
+                                        );
+
+                                        let current_option_positions_crud =
+                                            get_specific_current_option_positions_crud(
+                                                self.pool.clone(),
+                                            );
+                                        let unknown_strategy = self.unknown_strategy.clone();
+                                        writes.push(tokio::spawn(async move {
+                                            retry_sync_write(
+                                                write_retry,
+                                                "reconcile Discrepancy in option positions",
+                                                || {
+                                                    current_option_positions_crud
+                                                        .sync_unknown_strat_positions(
+                                                            &unknown_strategy,
+                                                            symbol.clone(),
+                                                            primary_exchange.clone(),
+                                                            expiry.clone(),
+                                                            strike.into_inner(),
+                                                            multiplier.clone(),
+                                                            option_type.clone(),
+                                                            position.position,
+                                                        )
+                                                },
+                                            )
+                                            .await?;
+                                            tracing::warn!(
+                                                "Discrepancy in option positions, allocated to strategy {}: {} for qty of {}",
+                                                unknown_strategy,
+                                                symbol,
+                                                position.position
+                                            );
+                                            Ok(())
+                                        }));
+                                    }
+                                }
+                                None => {
                                     tracing::warn!(
                                         "Reconciling current option position according to broker position (Local: {}, Broker: {})",
-                                        local_pos,
+                                        0.0,
                                         &position.position
                                     );
-
                                     let current_option_positions_crud =
-                                        get_specific_current_option_positions_crud(
-                                            self.pool.clone(),
-                                        );
-                                    let symbol = if position.contract.security_type
-                                        == SecurityType::Future
-                                    {
-                                        format!("FUT:{}", position.contract.symbol.clone())
-                                    } else {
-                                        position.contract.symbol.clone()
-                                    };
-                                    let primary_exchange =
-                                        position.contract.primary_exchange.clone();
-                                    let (expiry, strike, multiplier, option_type) = (
-                                        position.contract.last_trade_date_or_contract_month.clone(),
-                                        position.contract.strike.clone(),
-                                        position.contract.multiplier.clone(),
-                                        OptionType::from_str(&position.contract.right).expect("Error decoding contract right to OptionType while Reconciling options positions"),
-                                    );
-                                    let discrepancy = (position.position - **local_pos).clone();
-                                    tokio::spawn(async move {
-                                        match current_option_positions_crud
-                                            .update_unknown_strat_positions(
-                                                symbol.clone(),
-                                                primary_exchange,
-                                                expiry,
-                                                strike,
-                                                multiplier,
-                                                option_type,
-                                                discrepancy,
-                                            )
-                                            .await
-                                        {
-                                            Ok(_) => {
-                                                tracing::warn!(
-                                                    "Discrepancy in stock positions, allocated to strategy unknown: {} for qty of {}",
-                                                    symbol,
-                                                    position.position
-                                                )
-                                            }
-                                            Err(e) => {
-                                                tracing::error!(
-                                                    "Error trying to reconcile Discrepancy in stock positions: {}",
-                                                    e
-                                                )
-                                            }
+                                        get_current_option_positions_crud(self.pool.clone());
+                                    let strategy = self
+                                        .contract_to_strategy
+                                        .get(&(
+                                            position.contract.security_type.clone().to_string(),
+                                            position.contract.symbol.clone(),
+                                        ))
+                                        .map_or(self.unknown_strategy.clone(), |v| v.to_string());
+                                    writes.push(tokio::spawn(async move {
+                                        let crud = &current_option_positions_crud;
+                                        let row = &crate::database::models::CurrentOptionPositionsFullKeys {
+                                            stock: symbol,
+                                            primary_exchange,
+                                            strategy,
+                                            expiry,
+                                            strike: strike.into_inner(),
+                                            multiplier,
+                                            option_type,
+                                            quantity: position.position,
+                                            avg_price: position.average_cost,
                                         };
-                                    });
+                                        retry_sync_write(
+                                            write_retry,
+                                            &format!("insert into Current Option Positions when reconciling option positions (Local: {}, Broker: {})", 0.0, &position.position),
+                                            || crud.create(row),
+                                        )
+                                        .await
+                                    }));
                                 }
                             }
-                            None => {
-                                tracing::warn!(
-                                    "Reconciling current stock position according to broker position (Local: {}, Broker: {})",
-                                    0.0,
-                                    &position.position
-                                );
-                                let current_stock_positions_crud =
-                                    get_current_stock_positions_crud(self.pool.clone());
-                                let strategy = self
-                                    .contract_to_strategy
-                                    .get(&(
-                                        position.contract.security_type.clone().to_string(),
-                                        position.contract.symbol.clone(),
-                                    ))
-                                    .map_or(String::from("unknown"), |v| v.to_string());
-                                tokio::spawn(async move {
-                                    let symbol = if position.contract.security_type
-                                        == SecurityType::Future
-                                    {
-                                        format!("FUT:{}", position.contract.symbol.clone())
-                                    } else {
-                                        position.contract.symbol.clone()
-                                    };
-                                    if let Err(e) = current_stock_positions_crud.create(&crate::database::models::CurrentStockPositionsFullKeys {
-                                        stock: symbol,
-                                        primary_exchange: position.contract.primary_exchange,
-                                        strategy: strategy,
-                                        quantity: position.position.clone(),
-                                        avg_price: position.average_cost.clone()
-                                    }).await {
-                                        tracing::error!("Error inserting into Current Stock Positions when reconciling stock positions (Local: {}, Broker: {}): {}", 0.0, &position.position, e)
-                                    }
-                                });
-                            }
-                        },
+                        }
                         _ => {
                             tracing::error!(
                                 "New Security Type encountered when reconciling current positions: {}",
@@ -555,11 +958,16 @@ impl OrderEngine {
                     }
                 }
                 PositionUpdate::PositionEnd => {
-                    println!("initial set of positions received");
+                    info!("Initial set of positions received");
                     break;
                 }
             }
         }
+        if direction == ReconcileDirection::TrustLocal {
+            corrective_orders =
+                trust_local_corrections(&local_stocks, &local_options, &broker_positions);
+        }
+        (corrective_orders, await_sync_writes(writes).await)
     }
 
     /// Initialises the Order Update Stream to listen for all order events for the client
@@ -573,37 +981,67 @@ impl OrderEngine {
 
         // spawn a new os blocking thread to await for updates synchronously - send updates via
         // channel back to app
-        thread::spawn(move || {
-            let event_subscription = {
-                assert!(client.client_id() == 0);
-                let event_subscription = client
-                    .order_update_stream()
-                    .map_err(|e| {
-                        format!("Failed to begin order_update_stream in OrderEngine: {}", e)
-                    })
-                    .expect("Expected to be able to subscribe to order updates from client");
-                event_subscription
-            };
-            info!("Subscribed for updates for orders!");
-
-            while let Some(event) = event_subscription.next() {
-                info!("New order event received!");
-                let cloned_sender = sender.clone();
-                thread::spawn(move || {
-                    cloned_sender.blocking_send(event);
-                });
+        // - the thread reports back to the supervisor when the subscription fails or ends, and
+        // is restarted until it has failed too often
+        tokio::spawn(async move {
+            let supervised = supervise(
+                "Order update stream",
+                SupervisorOptions::default(),
+                |reporter| {
+                    let client = client.clone();
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        assert!(client.client_id() == 0);
+                        let event_subscription = match client.order_update_stream() {
+                            Ok(event_subscription) => event_subscription,
+                            Err(e) => {
+                                reporter.report(ThreadStatus::Fatal(format!(
+                                    "Failed to begin order_update_stream in OrderEngine: {}",
+                                    e
+                                )));
+                                return;
+                            }
+                        };
+                        info!("Subscribed for updates for orders!");
+
+                        while let Some(event) = event_subscription.next() {
+                            info!("New order event received!");
+                            let cloned_sender = sender.clone();
+                            thread::spawn(move || {
+                                cloned_sender.blocking_send(event);
+                            });
+                        }
+                        info!("Order event subscription ended!");
+                        reporter.report(ThreadStatus::Fatal(
+                            "Order event subscription ended".to_string(),
+                        ));
+                    });
+                },
+            )
+            .await;
+            if let Err(e) = supervised {
+                tracing::error!("No longer receiving order updates: {}", e);
             }
-            info!("Order event subscription ended!");
         });
 
         // async reciever that asynchronously awaits for updates
         let order_map = self.order_map.clone();
         let pool = self.pool.clone();
+        let pacing_backoff = self.pacing_backoff.clone();
+        let unknown_strategy = self.unknown_strategy.clone();
+        let fill_sender = self.fill_sender.clone();
         tokio::spawn(async move {
             while let Some(order_update) = rx.recv().await {
                 // all awaitable events within this is spawned asynchronously
-                if let Err(e) =
-                    on_order_update_received(order_map.clone(), pool.clone(), order_update).await
+                if let Err(e) = on_order_update_received(
+                    order_map.clone(),
+                    pool.clone(),
+                    pacing_backoff.clone(),
+                    unknown_strategy.clone(),
+                    fill_sender.clone(),
+                    order_update,
+                )
+                .await
                 {
                     tracing::error!("on_order_update_received error: {}", e)
                 };
@@ -611,42 +1049,83 @@ impl OrderEngine {
         });
     }
 
-    pub async fn place_order(
+    /// Places the order off the async runtime and returns the order_id it was submitted with
+    /// - the order is in the order map by the time this returns, so it can be tracked / cancelled
+    pub async fn place_order<C: OrderSubmitter + Send + Sync + 'static>(
         &self,
         strategy: String,
-        client: Arc<Client>,
+        client: Arc<C>,
         contract: Contract,
         order: Order,
         override_others: bool,
-    ) -> Result<(), String> {
-        let cloned_order_map = self.order_map.clone();
-        tokio::spawn(async move {
-            place_order(
-                cloned_order_map,
-                strategy,
-                client,
-                contract,
-                order,
-                override_others,
-            )
-        });
-        Ok(())
+    ) -> Result<i32, String> {
+        let ctx = self.order_context();
+        self.blocking_pool
+            .run(move || place_order(&ctx, strategy, client, contract, order, override_others))
+            .await
+            .map_err(|e| format!("Order placement task failed: {}", e))?
     }
 
-    pub fn place_orders_for_strategy<T: StrategyExecutor + 'static>(
+    /// Strategy, contract and order placed under order_id by this OrderEngine
+    pub fn get_placed_order(
+        &self,
+        order_id: i32,
+    ) -> Result<Option<(String, Contract, Order)>, String> {
+        let order_map = unlock!(self.order_map, "order_map", "OrderEngine.get_placed_order");
+        Ok(order_map.get(&order_id).cloned())
+    }
+
+    /// Strategy order_id was placed for, as executions for it are logged
+    pub fn strategy_for_order(&self, order_id: i32) -> Result<String, String> {
+        let order_map = unlock!(
+            self.order_map,
+            "order_map",
+            "OrderEngine.strategy_for_order"
+        );
+        Ok(strategy_for_order(&order_map, order_id))
+    }
+
+    /// Reconciles every contract affected by a bar update of contract for the strategy - the
+    /// contract itself and its dependents (see bar_update_targets)
+    pub fn place_orders_for_bar_update<T, C>(
         &self,
         strategy: T,
         contract: Contract,
-        client: Arc<Client>,
+        client: Arc<C>,
+        ignore_contract_for_strategy: bool,
+    ) where
+        T: StrategyExecutor + 'static,
+        C: OrderSubmitter + OrderCanceller + Send + Sync + 'static,
+    {
+        for (target_contract, asset_type) in bar_update_targets(&strategy, &contract) {
+            self.place_orders_for_strategy(
+                strategy.clone(),
+                target_contract,
+                client.clone(),
+                asset_type,
+                ignore_contract_for_strategy,
+            );
+        }
+    }
+
+    pub fn place_orders_for_strategy<T, C>(
+        &self,
+        strategy: T,
+        contract: Contract,
+        client: Arc<C>,
         asset_type: AssetType,
         ignore_contract_for_strategy: bool,
-    ) {
+    ) where
+        T: StrategyExecutor + 'static,
+        C: OrderSubmitter + OrderCanceller + Send + Sync + 'static,
+    {
         info!("Placing orders for {}", strategy.get_name());
+        let order_size_multiplier = self.order_size_multiplier;
+        let ctx = self.order_context();
         match asset_type {
             AssetType::Stock => {
-                let pool = self.pool.clone();
+                let ctx = ctx.clone();
                 let client = client.clone();
-                let order_map = self.order_map.clone();
                 let target_stock_positions_crud =
                     get_specific_target_stock_positions_crud(self.pool.clone());
                 let strategy = strategy.clone();
@@ -668,9 +1147,8 @@ impl OrderEngine {
                                 &pos_diffs.len()
                             );
                             pos_diffs.iter().for_each(|pos_diff| {
-                                let pool = pool.clone();
+                                let ctx = ctx.clone();
                                 let client = client.clone();
-                                let order_map = order_map.clone();
                                 let strategy = strategy.clone();
                                 let contract_opt = strategy.get_contract(
                                     pos_diff.stock.clone(),
@@ -686,12 +1164,41 @@ impl OrderEngine {
                                 }
                                 let contract = contract_opt.unwrap();
                                 let (qty_diff, avg_price) = (pos_diff.qty_diff, pos_diff.avg_price);
+                                let position_pk = CurrentStockPositionsPrimaryKeys {
+                                    stock: pos_diff.stock.clone(),
+                                    primary_exchange: pos_diff.primary_exchange.clone(),
+                                    strategy: strategy.get_name(),
+                                };
                                 tokio::spawn(async move {
+                                    // rounded to whole / fractional shares by
+                                    // on_new_stock_qty_diff_for_strat
+                                    let qty_diff = if order_size_multiplier == 1.0 {
+                                        qty_diff
+                                    } else {
+                                        match get_current_stock_positions_crud(ctx.pool.clone())
+                                            .read(&position_pk)
+                                            .await
+                                        {
+                                            Ok(position) => scale_qty_diff(
+                                                qty_diff,
+                                                position.map_or(0.0, |position| position.quantity),
+                                                order_size_multiplier,
+                                            ),
+                                            Err(e) => {
+                                                tracing::error!(
+                                                    "Error reading current position in {} for {}: {}",
+                                                    position_pk.stock,
+                                                    position_pk.strategy,
+                                                    e
+                                                );
+                                                return;
+                                            }
+                                        }
+                                    };
                                     on_new_stock_qty_diff_for_strat(
-                                        pool,
+                                        ctx,
                                         contract,
                                         client,
-                                        order_map,
                                         strategy.get_name(),
                                         qty_diff,
                                         avg_price,
@@ -711,9 +1218,8 @@ impl OrderEngine {
                 });
             }
             AssetType::Option => {
-                let pool = self.pool.clone();
+                let ctx = ctx.clone();
                 let client = client.clone();
-                let order_map = self.order_map.clone();
                 let target_option_positions_crud =
                     get_specific_target_option_positions_crud(self.pool.clone());
                 let strategy = strategy.clone();
@@ -725,7 +1231,7 @@ impl OrderEngine {
                             contract.primary_exchange,
                             contract.last_trade_date_or_contract_month,
                             contract.strike,
-                            contract.multiplier,
+                            normalize_multiplier(&contract.multiplier),
                             OptionType::from_str(&contract.right).expect(
                                 "Expected to be able to parse contract right for options contract",
                             ),
@@ -734,9 +1240,8 @@ impl OrderEngine {
                     {
                         Ok(pos_diffs) => {
                             pos_diffs.iter().for_each(|pos_diff| {
-                                let pool = pool.clone();
+                                let ctx = ctx.clone();
                                 let client = client.clone();
-                                let order_map = order_map.clone();
                                 let strategy = strategy.clone();
                                 let contract_opt = strategy.get_contract(
                                     pos_diff.stock.clone(),
@@ -747,12 +1252,47 @@ impl OrderEngine {
                                 }
                                 let contract = contract_opt.unwrap();
                                 let (qty_diff, avg_price) = (pos_diff.qty_diff, pos_diff.avg_price);
+                                let position_pk = CurrentOptionPositionsPrimaryKeys {
+                                    stock: pos_diff.stock.clone(),
+                                    primary_exchange: pos_diff.primary_exchange.clone(),
+                                    strategy: strategy.get_name(),
+                                    expiry: pos_diff.expiry.clone(),
+                                    strike: pos_diff.strike,
+                                    multiplier: pos_diff.multiplier.clone(),
+                                    option_type: pos_diff.option_type.clone(),
+                                };
                                 tokio::spawn(async move {
+                                    let qty_diff = if order_size_multiplier == 1.0 {
+                                        qty_diff
+                                    } else {
+                                        match get_current_option_positions_crud(ctx.pool.clone())
+                                            .read(&position_pk)
+                                            .await
+                                        {
+                                            // whole contracts only
+                                            Ok(position) => round_quantity(
+                                                scale_qty_diff(
+                                                    qty_diff,
+                                                    position.map_or(0.0, |position| position.quantity),
+                                                    order_size_multiplier,
+                                                ),
+                                                0,
+                                            ),
+                                            Err(e) => {
+                                                tracing::error!(
+                                                    "Error reading current option position in {} for {}: {}",
+                                                    position_pk.stock,
+                                                    position_pk.strategy,
+                                                    e
+                                                );
+                                                return;
+                                            }
+                                        }
+                                    };
                                     on_new_option_qty_diff_for_strat(
-                                        pool,
+                                        ctx,
                                         contract,
                                         client,
-                                        order_map,
                                         strategy.get_name(),
                                         qty_diff,
                                         avg_price,
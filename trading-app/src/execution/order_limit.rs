@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Outcome of counting one of a strategy's orders against SessionOrderLimit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderLimitCheck {
+    Allowed,
+    /// The strategy has placed all the orders it may this session - first is true only for the
+    /// first order turned away, so the limit is alerted once per strategy
+    Suppressed {
+        first: bool,
+    },
+}
+
+/// Orders each strategy has placed this session, capped at max_orders per strategy so a
+/// misbehaving strategy can't churn orders all day
+/// - only orders that are actually placed are counted, and orders only reducing a position (see
+///   reduces_position) are never counted nor suppressed, so a strategy at its limit can still
+///   flatten
+/// - an admitted order whose placement fails is given back with release
+/// - counts only live in memory - a new OrderEngine is built every session, so they start from
+///   0 at each open
+/// - clones share the same counts
+#[derive(Debug, Clone, Default)]
+pub struct SessionOrderLimit {
+    max_orders: Option<u32>,
+    // Backend endpoint the first suppression of each strategy is alerted to
+    notification_url: Option<String>,
+    counts: Arc<Mutex<HashMap<String, StrategyOrderCount>>>,
+}
+
+#[derive(Debug, Default)]
+struct StrategyOrderCount {
+    placed: u32,
+    // Whether the strategy's first suppressed order was already turned away
+    suppressed: bool,
+}
+
+impl SessionOrderLimit {
+    /// No limit for None - nothing is alerted without a notification_url
+    pub fn new(max_orders: Option<u32>, notification_url: Option<String>) -> Self {
+        Self {
+            max_orders,
+            notification_url,
+            counts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get_max_orders(&self) -> Option<u32> {
+        self.max_orders
+    }
+
+    /// Counts an order of strategy, unless it is already at the limit
+    pub fn check(&self, strategy: &str) -> OrderLimitCheck {
+        let Some(max_orders) = self.max_orders else {
+            return OrderLimitCheck::Allowed;
+        };
+        let mut counts = self
+            .counts
+            .lock()
+            .expect("Expected SessionOrderLimit counts not to be poisoned");
+        let count = counts.entry(strategy.to_string()).or_default();
+        if count.placed < max_orders {
+            count.placed += 1;
+            return OrderLimitCheck::Allowed;
+        }
+        let first = !count.suppressed;
+        count.suppressed = true;
+        OrderLimitCheck::Suppressed { first }
+    }
+
+    /// Gives back an order of strategy counted by check that was never placed
+    pub fn release(&self, strategy: &str) {
+        if self.max_orders.is_none() {
+            return;
+        }
+        let mut counts = self
+            .counts
+            .lock()
+            .expect("Expected SessionOrderLimit counts not to be poisoned");
+        if let Some(count) = counts.get_mut(strategy) {
+            count.placed = count.placed.saturating_sub(1);
+        }
+    }
+
+    /// check, logging a suppressed order and alerting notification_url the first time strategy
+    /// hits the limit - whether the order may be placed
+    pub fn admit(&self, strategy: &str) -> bool {
+        match self.check(strategy) {
+            OrderLimitCheck::Allowed => true,
+            OrderLimitCheck::Suppressed { first: true } => {
+                let max_orders = self.max_orders.unwrap_or_default();
+                tracing::error!(
+                    "{} has placed its {} orders for the session, suppressing any more",
+                    strategy,
+                    max_orders
+                );
+                if let Some(notification_url) = self.notification_url.clone() {
+                    let strategy = strategy.to_string();
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            send_order_limit_alert(&notification_url, &strategy, max_orders).await
+                        {
+                            tracing::error!("{}", e);
+                        }
+                    });
+                }
+                false
+            }
+            OrderLimitCheck::Suppressed { first: false } => {
+                tracing::debug!("Suppressed order of {} past its session limit", strategy);
+                false
+            }
+        }
+    }
+}
+
+/// Whether an order of signed qty only takes a position of current_qty towards flat - never past it
+pub fn reduces_position(current_qty: f64, qty: f64) -> bool {
+    current_qty != 0.0 && qty.signum() == -current_qty.signum() && qty.abs() <= current_qty.abs()
+}
+
+/// Posts an alert to the backend's /send_notification that strategy's orders are suppressed for
+/// the rest of the session
+async fn send_order_limit_alert(
+    notification_url: &str,
+    strategy: &str,
+    max_orders: u32,
+) -> Result<(), String> {
+    let notification = serde_json::json!({
+        "title": format!("{} hit its order limit", strategy),
+        "body": format!(
+            "{} placed {} orders this session, further orders are suppressed until the next session",
+            strategy, max_orders
+        ),
+        "alert_type": "error",
+    });
+    reqwest::Client::new()
+        .post(notification_url)
+        .json(&notification)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to send order limit alert for {}: {}", strategy, e))?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use ibapi::{
+    orders::{Action, Order},
+    prelude::{Contract, SecurityType},
+};
+use sqlx::PgPool;
+use tokio::runtime::Handle;
+
+use crate::database::{
+    crud::CRUDTrait,
+    models::{OrderMapFullKeys, OrderMapPrimaryKeys, OrderMapUpdateKeys},
+    models_crud::order_map::{get_order_map_crud, get_specific_order_map_crud},
+};
+
+/// How long a settled (filled / cancelled) order is kept in trading.order_map - late executions
+/// / commission reports for it can still arrive within it, e.g. when they are replayed on
+/// reconnect
+pub fn order_map_retention() -> Duration {
+    Duration::days(1)
+}
+
+fn security_type_label(security_type: &SecurityType) -> Result<&'static str, String> {
+    match security_type {
+        SecurityType::Stock => Ok("STK"),
+        SecurityType::Future => Ok("FUT"),
+        SecurityType::ForexPair => Ok("CASH"),
+        SecurityType::Option => Ok("OPT"),
+        _ => Err(format!(
+            "Security type {} is not traded by the order engine",
+            security_type
+        )),
+    }
+}
+
+fn security_type_from_label(label: &str) -> Result<SecurityType, String> {
+    match label {
+        "STK" => Ok(SecurityType::Stock),
+        "FUT" => Ok(SecurityType::Future),
+        "CASH" => Ok(SecurityType::ForexPair),
+        "OPT" => Ok(SecurityType::Option),
+        _ => Err(format!("Unknown security type in order_map: {}", label)),
+    }
+}
+
+fn action_label(action: &Action) -> Result<&'static str, String> {
+    match action {
+        Action::Buy => Ok("BUY"),
+        Action::Sell => Ok("SELL"),
+        _ => Err(format!(
+            "Order action {} is not placed by the order engine",
+            action
+        )),
+    }
+}
+
+fn action_from_label(label: &str) -> Result<Action, String> {
+    match label {
+        "BUY" => Ok(Action::Buy),
+        "SELL" => Ok(Action::Sell),
+        _ => Err(format!("Unknown order action in order_map: {}", label)),
+    }
+}
+
+/// trading.order_map row of an order placed under order_id - only the parts of the contract /
+/// order the order updates are booked off are kept
+pub fn order_map_row(
+    order_id: i32,
+    strategy: &str,
+    contract: &Contract,
+    order: &Order,
+) -> Result<OrderMapFullKeys, String> {
+    Ok(OrderMapFullKeys {
+        order_id,
+        strategy: strategy.to_string(),
+        time: Utc::now(),
+        security_type: security_type_label(&contract.security_type)?.to_string(),
+        stock: contract.symbol.clone(),
+        primary_exchange: contract.primary_exchange.clone(),
+        exchange: contract.exchange.clone(),
+        currency: contract.currency.clone(),
+        expiry: contract.last_trade_date_or_contract_month.clone(),
+        strike: contract.strike,
+        multiplier: contract.multiplier.clone(),
+        option_right: contract.right.clone(),
+        action: action_label(&order.action)?.to_string(),
+        quantity: order.total_quantity,
+    })
+}
+
+/// {"contract": .., "order": ..} with every field of the contract / order as submitted, stored
+/// on the open order row so the exact parameters an order was placed with can be recovered
+/// - order_map_row only keeps what order updates are booked off
+pub fn order_snapshot(contract: &Contract, order: &Order) -> Result<serde_json::Value, String> {
+    let contract = serde_json::to_value(contract)
+        .map_err(|e| format!("Failed to serialize contract {}: {}", contract.symbol, e))?;
+    let order = serde_json::to_value(order)
+        .map_err(|e| format!("Failed to serialize order {}: {}", order.order_id, e))?;
+    Ok(serde_json::json!({ "contract": contract, "order": order }))
+}
+
+/// order_map entry a trading.order_map row was written from (see order_map_row)
+pub fn placed_order_from_row(
+    row: OrderMapFullKeys,
+) -> Result<(i32, (String, Contract, Order)), String> {
+    let contract = Contract {
+        symbol: row.stock,
+        security_type: security_type_from_label(&row.security_type)?,
+        primary_exchange: row.primary_exchange,
+        exchange: row.exchange,
+        currency: row.currency,
+        last_trade_date_or_contract_month: row.expiry,
+        strike: row.strike,
+        multiplier: row.multiplier,
+        right: row.option_right,
+        ..Contract::default()
+    };
+    let order = Order {
+        action: action_from_label(&row.action)?,
+        total_quantity: row.quantity,
+        ..Order::default()
+    };
+    Ok((row.order_id, (row.strategy, contract, order)))
+}
+
+/// Writes an order to trading.order_map - an order_id already in it (ids reset at the broker) is
+/// overwritten by the newer order
+pub async fn persist_placed_order(pool: PgPool, row: OrderMapFullKeys) -> Result<(), String> {
+    get_order_map_crud(pool)
+        .create_or_update(
+            &OrderMapPrimaryKeys {
+                order_id: row.order_id,
+            },
+            &OrderMapUpdateKeys {
+                strategy: Some(row.strategy),
+                time: Some(row.time),
+                security_type: Some(row.security_type),
+                stock: Some(row.stock),
+                primary_exchange: Some(row.primary_exchange),
+                exchange: Some(row.exchange),
+                currency: Some(row.currency),
+                expiry: Some(row.expiry),
+                strike: Some(row.strike),
+                multiplier: Some(row.multiplier),
+                option_right: Some(row.option_right),
+                action: Some(row.action),
+                quantity: Some(row.quantity),
+            },
+        )
+        .await
+        .map_err(|e| format!("Error writing order {} to order_map: {}", row.order_id, e))
+}
+
+/// Deletes the orders in trading.order_map placed before cutoff that are no longer open, so the
+/// table only grows with the orders still working - returns the number deleted
+pub async fn prune_order_map(pool: PgPool, cutoff: DateTime<Utc>) -> Result<u64, String> {
+    get_specific_order_map_crud(pool)
+        .delete_settled_before(cutoff)
+        .await
+}
+
+/// Orders in trading.order_map placed since cutoff or still open, keyed on order_id as in
+/// OrderEngine's order_map
+/// - rows that can't be read back are logged and skipped
+pub async fn load_order_map(
+    pool: PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<HashMap<i32, (String, Contract, Order)>, String> {
+    let rows = get_specific_order_map_crud(pool).read_live(cutoff).await?;
+    let mut order_map = HashMap::new();
+    for row in rows {
+        let order_id = row.order_id;
+        match placed_order_from_row(row) {
+            Ok((order_id, strategy_order)) => {
+                order_map.insert(order_id, strategy_order);
+            }
+            Err(e) => tracing::warn!("Skipping order {} in order_map: {}", order_id, e),
+        }
+    }
+    Ok(order_map)
+}
+
+/// Persists orders from the threads place_order runs on (see BlockingPool), which are outside the
+/// async runtime - each write is run to completion on runtime before the order is submitted
+#[derive(Debug, Clone)]
+pub struct OrderMapStore {
+    pool: PgPool,
+    runtime: Handle,
+}
+
+impl OrderMapStore {
+    pub fn new(pool: PgPool, runtime: Handle) -> Self {
+        Self { pool, runtime }
+    }
+
+    /// Store writing to pool on the runtime this is called from, None outside of one
+    pub fn from_current(pool: PgPool) -> Option<Self> {
+        Handle::try_current()
+            .ok()
+            .map(|runtime| Self::new(pool, runtime))
+    }
+
+    /// Blocks until the order is written - must not be called from an async task
+    pub fn persist(
+        &self,
+        order_id: i32,
+        strategy: &str,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<(), String> {
+        let row = order_map_row(order_id, strategy, contract, order)?;
+        self.runtime
+            .block_on(persist_placed_order(self.pool.clone(), row))
+    }
+}
@@ -13,8 +13,13 @@ use sqlx::PgPool;
 use tracing::info;
 
 use crate::{
-    execution::events::order_events::{
-        on_commission_update, on_execution_update, on_new_order_submitted, on_order_cancelled,
+    execution::{
+        broker_events::{BrokerEvent, record_broker_event},
+        events::order_events::{
+            on_commission_update, on_execution_update, on_new_order_submitted, on_order_cancelled,
+        },
+        fills::FillSender,
+        notices::{BrokerNotice, PacingBackoff, handle_broker_notice},
     },
     unlock,
 };
@@ -81,10 +86,28 @@ impl StatusOfOrderStatus {
 //     Ok(())
 // }
 
+/// Strategy order_id was placed for - orders placed outside the app (or before order_map was
+/// persisted) are logged under an unknown strategy
+pub fn strategy_for_order(
+    order_map: &HashMap<i32, (String, Contract, Order)>,
+    order_id: i32,
+) -> String {
+    order_map.get(&order_id).map_or(
+        "Unknown strategy: not recorded in order_map".to_string(),
+        |v| v.0.clone(),
+    )
+}
+
 /// Async only because it has to await open order handle
+/// - every order / execution / commission event is appended to trading.broker_events first
+/// - fills booked from execution updates are reported on fill_sender
+/// - executions without an open order are booked to unknown_strategy
 pub async fn on_order_update_received(
     order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
     pool: PgPool,
+    pacing_backoff: Arc<PacingBackoff>,
+    unknown_strategy: String,
+    fill_sender: Option<FillSender>,
     order_update: OrderUpdate,
 ) -> Result<(), String> {
     macro_rules! simple_update_log {
@@ -101,6 +124,11 @@ pub async fn on_order_update_received(
             );
         }};
     }
+    if let Some(event) = BrokerEvent::from_order_update(&order_update)
+        && let Err(e) = record_broker_event(&pool, &event).await
+    {
+        tracing::error!("{}", e);
+    }
     match order_update {
         OrderUpdate::OrderStatus(status) => {
             match StatusOfOrderStatus::from_str(status.status.as_str()) {
@@ -225,10 +253,7 @@ pub async fn on_order_update_received(
         OrderUpdate::ExecutionData(execution_data) => {
             let strategy = {
                 let order_map = unlock!(order_map, "order_map", "OrderEngine.order_update_stream");
-                order_map.get(&execution_data.execution.order_id).map_or(
-                    "Unknown strategy: not recorded in order_map".to_string(),
-                    |v| v.0.clone(),
-                )
+                strategy_for_order(&order_map, execution_data.execution.order_id)
             };
             tracing::info!(
                 "New Execution recorded with id: {} for strategy: {}",
@@ -246,7 +271,7 @@ pub async fn on_order_update_received(
             //     execution_data.clone(),
             // );
 
-            on_execution_update(pool.clone(), execution_data);
+            on_execution_update(pool.clone(), execution_data, unknown_strategy, fill_sender);
         }
 
         OrderUpdate::CommissionReport(commission_report) => {
@@ -281,8 +306,13 @@ pub async fn on_order_update_received(
             };
         }
 
-        OrderUpdate::Message(message) => {
-            tracing::warn!("Message from OrderEngine.order_update_stream: {}", message);
+        OrderUpdate::Message(notice) => {
+            handle_broker_notice(
+                pool.clone(),
+                &pacing_backoff,
+                &BrokerNotice::new(None, notice.code, notice.message),
+            )
+            .await?;
         }
     }
 
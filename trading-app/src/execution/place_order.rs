@@ -4,36 +4,251 @@ use std::{
 };
 
 use ibapi::{Client, orders::Order, prelude::Contract};
+use sqlx::PgPool;
 // use tokio::sync::Mutex;
 use tracing::info;
 
-use crate::unlock;
+use crate::{
+    execution::{
+        blocking_pool::BlockingPool,
+        netting::{NettingPolicy, PartialFillPolicy},
+        notices::PacingBackoff,
+        order_limit::SessionOrderLimit,
+        order_map::OrderMapStore,
+    },
+    unlock,
+};
+
+/// Submits orders to the broker - implemented for ibapi::Client, stubbed out in tests
+pub trait OrderSubmitter {
+    fn next_order_id(&self) -> i32;
+    fn submit_order(&self, order_id: i32, contract: &Contract, order: &Order)
+    -> Result<(), String>;
+    /// Minimum price increment of the contract
+    fn min_tick(&self, contract: &Contract) -> Result<f64, String>;
+}
+
+impl OrderSubmitter for Client {
+    fn next_order_id(&self) -> i32 {
+        Client::next_order_id(self)
+    }
+
+    fn submit_order(
+        &self,
+        order_id: i32,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<(), String> {
+        Client::submit_order(self, order_id, contract, order).map_err(|e| e.to_string())
+    }
+
+    fn min_tick(&self, contract: &Contract) -> Result<f64, String> {
+        let details = self.contract_details(contract).map_err(|e| e.to_string())?;
+        details
+            .first()
+            .map(|details| details.min_tick)
+            .ok_or(format!("No contract details found for {}", contract.symbol))
+    }
+}
+
+/// Caches each contract's min_tick so contract details are only requested on the first order
+#[derive(Debug, Default)]
+pub struct MinTickCache {
+    min_ticks: Mutex<HashMap<String, f64>>,
+}
+
+impl MinTickCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(contract: &Contract) -> String {
+        format!(
+            "{:?}|{}|{}|{}|{}|{}",
+            contract.security_type,
+            contract.symbol,
+            contract.primary_exchange,
+            contract.last_trade_date_or_contract_month,
+            contract.strike,
+            contract.right
+        )
+    }
+
+    /// Cached min_tick of contract, requested from client if not cached yet
+    pub fn get_or_fetch<C: OrderSubmitter>(
+        &self,
+        client: &C,
+        contract: &Contract,
+    ) -> Result<f64, String> {
+        let key = Self::key(contract);
+        {
+            let min_ticks = unlock!(self.min_ticks, "min_ticks", "MinTickCache.get_or_fetch");
+            if let Some(min_tick) = min_ticks.get(&key) {
+                return Ok(*min_tick);
+            }
+        }
+
+        // requested without holding the lock - a concurrent first order for the same contract
+        // at worst requests it twice
+        let min_tick = client.min_tick(contract)?;
+        if min_tick <= 0.0 {
+            return Err(format!(
+                "Invalid min_tick {} for {}",
+                min_tick, contract.symbol
+            ));
+        }
+        let mut min_ticks = unlock!(self.min_ticks, "min_ticks", "MinTickCache.get_or_fetch");
+        min_ticks.insert(key, min_tick);
+        Ok(min_tick)
+    }
+}
+
+/// Rounds price to the nearest multiple of min_tick
+/// - the result is also rounded to min_tick's decimal places so float error doesn't leave it a
+///   hair off the tick (e.g. 100.12000000000001)
+pub fn round_to_tick(price: f64, min_tick: f64) -> f64 {
+    let rounded = (price / min_tick).round() * min_tick;
+    let decimals = (0..=10)
+        .find(|decimals| {
+            let scaled = min_tick * 10f64.powi(*decimals);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(10);
+    let scale = 10f64.powi(decimals);
+    (rounded * scale).round() / scale
+}
+
+/// Exchange orders are routed through unless OrderEngine is configured otherwise
+pub const DEFAULT_ORDER_ROUTING: &str = "SMART";
+
+/// contract as submitted to the broker - routed through routing (e.g. SMART) instead of its own
+/// exchange
+/// - positions, open orders and market data stay keyed on the contract's primary_exchange, which
+///   is carried over unchanged so IBKR can still tell listings apart under SMART
+/// - a contract without a primary_exchange takes its listing exchange as one, so the two can
+///   differ on the order but still reconcile to the same position
+pub fn routed_contract(contract: &Contract, routing: &str) -> Contract {
+    let mut routed = contract.clone();
+    if routing.is_empty() {
+        return routed;
+    }
+    if routed.primary_exchange.is_empty() && routed.exchange != routing {
+        routed.primary_exchange = routed.exchange.clone();
+    }
+    routed.exchange = routing.to_string();
+    routed
+}
+
+/// Everything orders are placed with besides the strategy, contract and order themselves -
+/// OrderEngine hands a clone of its own (see OrderEngine::order_context) to each placement
+#[derive(Clone)]
+pub struct OrderContext {
+    pub pool: PgPool,
+    // order_id -> (strategy, contract, order), inserted into by place_order
+    pub order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
+    pub min_ticks: Arc<MinTickCache>,
+    pub pacing_backoff: Arc<PacingBackoff>,
+    // Exchange orders are routed through, see routed_contract
+    pub order_routing: String,
+    // Set when placed orders are also written to trading.order_map
+    pub order_map_store: Option<OrderMapStore>,
+    pub blocking_pool: Arc<BlockingPool>,
+    pub netting_policy: NettingPolicy,
+    pub partial_fill_policy: PartialFillPolicy,
+    // Decimal places stock order quantities are rounded to
+    pub share_quantity_decimals: u32,
+    pub order_limit: SessionOrderLimit,
+}
+
+impl OrderContext {
+    /// Context with OrderEngine's defaults on pool - orders aren't persisted and are routed
+    /// through DEFAULT_ORDER_ROUTING
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            order_map: Arc::new(Mutex::new(HashMap::new())),
+            min_ticks: Arc::new(MinTickCache::new()),
+            pacing_backoff: Arc::new(PacingBackoff::default()),
+            order_routing: DEFAULT_ORDER_ROUTING.to_string(),
+            order_map_store: None,
+            blocking_pool: Arc::new(BlockingPool::default()),
+            netting_policy: NettingPolicy::default(),
+            partial_fill_policy: PartialFillPolicy::default(),
+            share_quantity_decimals: 0,
+            order_limit: SessionOrderLimit::default(),
+        }
+    }
+}
 
 /// Always place orders with the same client - for coordination of order ids
 /// - As long as the instance for OrderEngine is the same used to place_order (same for client as
-/// well), this should work well
+///   well), this should work well
 /// - To meld with consolidator, consolidator preferably subscribes to market data from a client id
-/// other than this one (ideal would be consolidator: 1, order_engine: 0)
+///   other than this one (ideal would be consolidator: 1, order_engine: 0)
 ///     - in this case, any strategy should be able to use the same order_engine and consolidator
-///     instance
-pub fn place_order(
-    order_map: Arc<Mutex<HashMap<i32, (String, Contract, Order)>>>,
+///       instance
+/// - Returns the order_id the order was submitted with (already in order_map) - the perm_id is
+///   only assigned by IBKR afterwards and arrives through the order update stream
+/// - A limit price off the contract's tick is rounded to the nearest valid tick - if the min_tick
+///   can't be fetched the order is submitted as is
+/// - Blocks while pacing_backoff is paused after a pacing violation
+/// - Logs under a place_order span carrying the strategy, symbol and, once assigned, the order_id
+/// - The order is submitted on the contract routed through ctx.order_routing (see
+///   routed_contract), while order_map keeps contract as given so fills are booked against the
+///   position it is keyed on
+/// - With ctx.order_map_store set, the order is also written to trading.order_map before it is
+///   submitted, so it can still be attributed to strategy after a restart - a failed write is
+///   logged and the order submitted regardless
+pub fn place_order<C: OrderSubmitter>(
+    ctx: &OrderContext,
     strategy: String,
-    client: Arc<Client>,
+    client: Arc<C>,
     contract: Contract,
-    order: Order,
+    mut order: Order,
     override_others: bool,
-) -> Result<(), String> {
+) -> Result<i32, String> {
+    let span = tracing::info_span!(
+        "place_order",
+        strategy = strategy.as_str(),
+        symbol = contract.symbol.as_str(),
+        order_id = tracing::field::Empty
+    );
+    let _entered = span.enter();
+    if let Some(limit_price) = order.limit_price {
+        match ctx.min_ticks.get_or_fetch(client.as_ref(), &contract) {
+            Ok(min_tick) => order.limit_price = Some(round_to_tick(limit_price, min_tick)),
+            Err(e) => tracing::warn!(
+                "Could not get min_tick for {}, submitting limit price {} unrounded: {}",
+                contract.symbol,
+                limit_price,
+                e
+            ),
+        }
+    }
+
+    ctx.pacing_backoff.wait();
     let order_id = client.next_order_id();
+    span.record("order_id", order_id);
     {
-        let mut order_map = unlock!(order_map, "order_map", "OrderEngine.place_order");
+        let mut order_map = unlock!(ctx.order_map, "order_map", "OrderEngine.place_order");
         order_map.insert(
             order_id,
             (strategy.clone(), contract.clone(), order.clone()),
         );
     }
+    let persisted = ctx
+        .order_map_store
+        .as_ref()
+        .map(|order_map_store| order_map_store.persist(order_id, &strategy, &contract, &order));
+    if let Some(Err(e)) = persisted {
+        tracing::error!("Order {} not persisted: {}", order_id, e);
+    }
     client
-        .submit_order(order_id, &contract, &order)
+        .submit_order(
+            order_id,
+            &routed_contract(&contract, &ctx.order_routing),
+            &order,
+        )
         .map_err(|e| {
             tracing::error!(
                 "Failed to place order for {}, order: {}, Error: {}",
@@ -48,5 +263,5 @@ pub fn place_order(
         })?;
     info!("Order submitted to IBKR");
 
-    Ok(())
+    Ok(order_id)
 }
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::database::{
+    crud::CRUDTrait,
+    models::StrategyPrimaryKeys,
+    models_crud::{
+        historical_data::get_specific_historical_data_crud,
+        strategy::get_strategy_crud,
+        target_stock_positions::{QtyDiff, get_specific_target_stock_positions_crud},
+    },
+};
+
+/// Limits a would-be order is checked against before a strategy is activated
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskLimits {
+    /// Largest notional a single order may have - None for no absolute limit
+    pub max_order_notional: Option<f64>,
+    /// Largest notional a single order may have as a fraction of the strategy's capital
+    pub max_capital_fraction: f64,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        Self {
+            max_order_notional: None,
+            max_capital_fraction: 1.0,
+        }
+    }
+}
+
+impl RiskLimits {
+    /// Reads MAX_ORDER_NOTIONAL and MAX_ORDER_CAPITAL_FRACTION - unset keeps default
+    pub fn from_env() -> Result<Self, String> {
+        let read_f64 = |var: &str| -> Result<Option<f64>, String> {
+            match std::env::var(var) {
+                Ok(value) => value
+                    .trim()
+                    .parse::<f64>()
+                    .map(Some)
+                    .map_err(|e| format!("{} must be a number: {}", var, e)),
+                Err(_) => Ok(None),
+            }
+        };
+        let default = RiskLimits::default();
+        Ok(Self {
+            max_order_notional: read_f64("MAX_ORDER_NOTIONAL")?,
+            max_capital_fraction: read_f64("MAX_ORDER_CAPITAL_FRACTION")?
+                .unwrap_or(default.max_capital_fraction),
+        })
+    }
+}
+
+/// An order that would be placed for a strategy if it was Active
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewOrder {
+    pub stock: String,
+    pub primary_exchange: String,
+    pub quantity: f64,
+    /// Latest bar close, or the target's avg_price when there is no bar for the stock
+    pub price: f64,
+    pub estimated_notional: f64,
+    /// Reason for every risk limit the order exceeds - empty when the order is within limits
+    pub violations: Vec<String>,
+}
+
+impl PreviewOrder {
+    pub fn exceeds_limits(&self) -> bool {
+        !self.violations.is_empty()
+    }
+}
+
+/// Turns target position diffs into the orders they would place, flagging those over limits
+/// - prices are keyed by stock, diffs with no quantity difference are dropped
+pub fn preview_orders(
+    diffs: &[QtyDiff],
+    prices: &HashMap<String, f64>,
+    capital: f64,
+    limits: &RiskLimits,
+) -> Vec<PreviewOrder> {
+    diffs
+        .iter()
+        .filter(|diff| diff.qty_diff != 0.0)
+        .map(|diff| {
+            let price = prices.get(&diff.stock).copied().unwrap_or(diff.avg_price);
+            let estimated_notional = diff.qty_diff.abs() * price;
+
+            let mut violations = Vec::new();
+            if let Some(max_order_notional) = limits.max_order_notional
+                && estimated_notional > max_order_notional
+            {
+                violations.push(format!(
+                    "Notional {:.2} exceeds max order notional {:.2}",
+                    estimated_notional, max_order_notional
+                ));
+            }
+            let max_capital_notional = capital * limits.max_capital_fraction;
+            if estimated_notional > max_capital_notional {
+                violations.push(format!(
+                    "Notional {:.2} exceeds {:.0}% of strategy capital {:.2}",
+                    estimated_notional,
+                    limits.max_capital_fraction * 100.0,
+                    capital
+                ));
+            }
+
+            PreviewOrder {
+                stock: diff.stock.clone(),
+                primary_exchange: diff.primary_exchange.clone(),
+                quantity: diff.qty_diff,
+                price,
+                estimated_notional,
+                violations,
+            }
+        })
+        .collect()
+}
+
+/// Dry run of the strategy's TargetStockPositions - returns the orders that would be placed
+/// without placing any
+pub async fn preview_target_stock_positions(
+    pool: PgPool,
+    strategy: String,
+    limits: &RiskLimits,
+) -> Result<Vec<PreviewOrder>, String> {
+    let strategy_info = get_strategy_crud(pool.clone())
+        .read(&StrategyPrimaryKeys {
+            strategy: strategy.clone(),
+        })
+        .await
+        .map_err(|e| format!("Failed to read strategy {}: {}", strategy, e))?
+        .ok_or(format!("Strategy {} does not exist", strategy))?;
+
+    let diffs = get_specific_target_stock_positions_crud(pool.clone())
+        .get_target_pos_diff_strat(strategy)
+        .await?;
+
+    let historical_data_crud = get_specific_historical_data_crud(pool);
+    let mut prices = HashMap::new();
+    for diff in &diffs {
+        let last_bar = historical_data_crud
+            .read_last_bar_of_stock(diff.stock.clone(), diff.primary_exchange.clone())
+            .await?;
+        if let Some(last_bar) = last_bar {
+            prices.insert(diff.stock.clone(), last_bar.close);
+        }
+    }
+
+    Ok(preview_orders(
+        &diffs,
+        &prices,
+        strategy_info.capital,
+        limits,
+    ))
+}
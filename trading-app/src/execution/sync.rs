@@ -0,0 +1,548 @@
+use std::{collections::HashSet, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use ibapi::{
+    accounts::Position,
+    orders::{Action, Order, order_builder},
+    prelude::{Contract, SecurityType},
+};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    database::{
+        crud::is_transient_error,
+        models::{OptionType, normalize_multiplier},
+        models_crud::{
+            current_option_positions::{
+                GroupedByContract, GroupedByContractKeyTuple, GroupedByContractPrimaryKeys,
+            },
+            current_stock_positions::GroupedByStock,
+            open_option_orders::get_specific_option_orders_crud,
+            open_stock_orders::get_specific_open_stock_orders_crud,
+        },
+    },
+    execution::{
+        notices::PacingBackoff,
+        place_order::{OrderSubmitter, routed_contract},
+    },
+};
+
+/// order_ref of orders placed to bring the broker position to the local one - their fills are
+/// not booked, the local book already has them
+pub const CORRECTIVE_ORDER_REF: &str = "reconcile";
+
+/// One of the broker -> DB syncs OrderEngine runs at startup and close
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStep {
+    Executions,
+    OpenOrders,
+    Positions,
+}
+
+impl FromStr for SyncStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "executions" => Ok(SyncStep::Executions),
+            "open_orders" => Ok(SyncStep::OpenOrders),
+            "positions" => Ok(SyncStep::Positions),
+            other => Err(format!("Unknown sync step: {}", other)),
+        }
+    }
+}
+
+/// Which side sync_positions treats as correct when local and broker positions differ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReconcileDirection {
+    /// Discrepancies are booked to the unknown strategy (TradingConfig.unknown_strategy) in the DB
+    #[default]
+    TrustBroker,
+    /// Corrective orders are placed to bring the broker position to the local one, the DB is
+    /// left alone - for recovering when the local book is known to be right
+    TrustLocal,
+}
+
+impl FromStr for ReconcileDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "broker" => Ok(ReconcileDirection::TrustBroker),
+            "local" => Ok(ReconcileDirection::TrustLocal),
+            other => Err(format!("Unknown reconcile direction: {}", other)),
+        }
+    }
+}
+
+/// What sync_positions does about a single position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionReconciliation {
+    InSync,
+    /// Broker - local quantity, booked to the unknown strategy
+    BookDiscrepancy(f64),
+    /// Signed quantity to order so the broker position matches the local one
+    CorrectiveOrder(f64),
+}
+
+/// Quantities closer than this are taken to match - sums of fractional fills drift by float error
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+pub fn reconcile_position(
+    direction: ReconcileDirection,
+    local_qty: f64,
+    broker_qty: f64,
+) -> PositionReconciliation {
+    if (local_qty - broker_qty).abs() <= QUANTITY_EPSILON {
+        return PositionReconciliation::InSync;
+    }
+    match direction {
+        ReconcileDirection::TrustBroker => {
+            PositionReconciliation::BookDiscrepancy(broker_qty - local_qty)
+        }
+        ReconcileDirection::TrustLocal => {
+            PositionReconciliation::CorrectiveOrder(local_qty - broker_qty)
+        }
+    }
+}
+
+/// Symbol a broker position is booked under locally - futures are kept as FUT:<symbol>
+fn local_symbol(contract: &Contract) -> String {
+    if contract.security_type == SecurityType::Future {
+        format!("FUT:{}", contract.symbol)
+    } else {
+        contract.symbol.clone()
+    }
+}
+
+/// Key an option position is matched on between the broker and the DB - (symbol, expiry, strike,
+/// multiplier, right)
+pub type OptionKey = GroupedByContractKeyTuple;
+
+pub fn local_option_key(position: &GroupedByContract) -> OptionKey {
+    GroupedByContractPrimaryKeys {
+        stock: position.stock.clone(),
+        expiry: position.expiry.clone(),
+        strike: position.strike,
+        multiplier: position.multiplier.clone(),
+        option_type: position.option_type.clone(),
+    }
+    .as_key_tuple()
+}
+
+pub fn broker_option_key(contract: &Contract) -> Result<OptionKey, String> {
+    Ok(GroupedByContractPrimaryKeys {
+        stock: contract.symbol.clone(),
+        expiry: contract.last_trade_date_or_contract_month.clone(),
+        strike: contract.strike,
+        multiplier: normalize_multiplier(&contract.multiplier),
+        option_type: OptionType::from_str(&contract.right)?,
+    }
+    .as_key_tuple())
+}
+
+/// Orders TrustLocal places so the broker positions match the local ones, as (contract, signed
+/// quantity)
+/// - stocks are matched on (symbol, primary_exchange) - on symbol alone when the broker leaves
+///   primary_exchange empty - options on (symbol, strike, expiry, multiplier, right)
+/// - local positions the broker has none of are ordered too, on a contract rebuilt from the
+///   local row (listed on its primary_exchange, USD) - futures can't be rebuilt and are only
+///   logged
+pub fn trust_local_corrections(
+    local_stocks: &[GroupedByStock],
+    local_options: &[GroupedByContract],
+    broker_positions: &[Position],
+) -> Vec<(Contract, f64)> {
+    let mut corrections = Vec::new();
+    let mut matched_stocks: HashSet<(String, String)> = HashSet::new();
+    let mut matched_options: HashSet<OptionKey> = HashSet::new();
+
+    for position in broker_positions {
+        let local_qty = match position.contract.security_type {
+            SecurityType::Option => {
+                let key = match broker_option_key(&position.contract) {
+                    Ok(key) => key,
+                    Err(e) => {
+                        tracing::error!(
+                            "Skipping broker option position of {}: {}",
+                            position.contract.symbol,
+                            e
+                        );
+                        continue;
+                    }
+                };
+                let local_qty = local_options
+                    .iter()
+                    .find(|local| local_option_key(local) == key)
+                    .map_or(0.0, |local| local.quantity);
+                matched_options.insert(key);
+                local_qty
+            }
+            _ => {
+                let symbol = local_symbol(&position.contract);
+                let primary_exchange = &position.contract.primary_exchange;
+                let mut local_qty = 0.0;
+                for local in local_stocks.iter().filter(|local| {
+                    local.stock == symbol
+                        && (primary_exchange.is_empty()
+                            || &local.primary_exchange == primary_exchange)
+                }) {
+                    local_qty += local.quantity;
+                    matched_stocks.insert((local.stock.clone(), local.primary_exchange.clone()));
+                }
+                local_qty
+            }
+        };
+        if let PositionReconciliation::CorrectiveOrder(quantity) =
+            reconcile_position(ReconcileDirection::TrustLocal, local_qty, position.position)
+        {
+            tracing::warn!(
+                "Correcting broker position of {} towards local position (Local: {}, Broker: {})",
+                position.contract.symbol,
+                local_qty,
+                position.position
+            );
+            corrections.push((position.contract.clone(), quantity));
+        }
+    }
+
+    for local in local_stocks {
+        if local.quantity.abs() <= QUANTITY_EPSILON
+            || matched_stocks.contains(&(local.stock.clone(), local.primary_exchange.clone()))
+        {
+            continue;
+        }
+        if local.stock.starts_with("FUT:") {
+            tracing::error!(
+                "Local position of {} is missing at the broker, futures have to be corrected by hand",
+                local.stock
+            );
+            continue;
+        }
+        tracing::warn!(
+            "Correcting broker position of {} towards local position (Local: {}, Broker: 0)",
+            local.stock,
+            local.quantity
+        );
+        let contract = Contract {
+            symbol: local.stock.clone(),
+            security_type: SecurityType::Stock,
+            primary_exchange: local.primary_exchange.clone(),
+            exchange: local.primary_exchange.clone(),
+            currency: "USD".to_string(),
+            ..Contract::default()
+        };
+        corrections.push((contract, local.quantity));
+    }
+    for local in local_options {
+        if local.quantity.abs() <= QUANTITY_EPSILON
+            || matched_options.contains(&local_option_key(local))
+        {
+            continue;
+        }
+        tracing::warn!(
+            "Correcting broker position of {} {} {} {} towards local position (Local: {}, Broker: 0)",
+            local.stock,
+            local.expiry,
+            local.strike,
+            local.option_type,
+            local.quantity
+        );
+        let contract = Contract {
+            symbol: local.stock.clone(),
+            security_type: SecurityType::Option,
+            primary_exchange: local.primary_exchange.clone(),
+            exchange: local.primary_exchange.clone(),
+            currency: "USD".to_string(),
+            last_trade_date_or_contract_month: local.expiry.clone(),
+            strike: local.strike,
+            multiplier: local.multiplier.clone(),
+            right: local.option_type.to_string(),
+            ..Contract::default()
+        };
+        corrections.push((contract, local.quantity));
+    }
+    corrections
+}
+
+/// Market order for a corrective quantity, tagged with CORRECTIVE_ORDER_REF
+pub fn corrective_order(quantity: f64) -> Order {
+    let action = if quantity > 0.0 {
+        Action::Buy
+    } else {
+        Action::Sell
+    };
+    let mut order = order_builder::market_order(action, quantity.abs());
+    order.order_ref = CORRECTIVE_ORDER_REF.to_string();
+    order
+}
+
+/// Submits a corrective order for each (contract, signed quantity) and returns the order ids
+/// submitted - a failed submission is logged and the rest are still submitted
+/// - each order is routed through order_routing (see routed_contract), as place_order does - the
+///   contracts IBKR returns positions on usually have no exchange to submit to
+/// - blocks while pacing_backoff is paused after a pacing violation
+pub fn submit_corrective_orders<C: OrderSubmitter>(
+    client: &C,
+    orders: &[(Contract, f64)],
+    order_routing: &str,
+    pacing_backoff: &PacingBackoff,
+) -> Vec<i32> {
+    let mut order_ids = Vec::new();
+    for (contract, quantity) in orders {
+        let mut routed = routed_contract(contract, order_routing);
+        if routed.exchange.is_empty() {
+            routed.exchange = routed.primary_exchange.clone();
+        }
+        pacing_backoff.wait();
+        let order_id = client.next_order_id();
+        match client.submit_order(order_id, &routed, &corrective_order(*quantity)) {
+            Ok(()) => order_ids.push(order_id),
+            Err(e) => tracing::error!(
+                "Failed to submit corrective order for {} of {}: {}",
+                contract.symbol,
+                quantity,
+                e
+            ),
+        }
+    }
+    order_ids
+}
+
+/// How a failed sync_positions write is retried - attempts includes the first try
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncWriteRetry {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for SyncWriteRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Runs write until it succeeds or retry.attempts are used up, waiting retry.delay in between
+/// - only transient errors (serialization failure, dropped connection) are retried, they usually
+///   go through on a later attempt - any other error is returned straight away
+/// - write is run again from scratch, so it has to be idempotent
+pub async fn retry_sync_write<F, Fut>(
+    retry: SyncWriteRetry,
+    description: &str,
+    mut write: F,
+) -> Result<(), String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let attempts = retry.attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match write().await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < attempts && is_transient_error(&e) => {
+                tracing::warn!(
+                    "Failed to {} ({}/{}), retrying: {}",
+                    description,
+                    attempt,
+                    attempts,
+                    e
+                );
+                attempt += 1;
+                tokio::time::sleep(retry.delay).await;
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to {} after {} attempts: {}",
+                    description, attempt, e
+                ));
+            }
+        }
+    }
+}
+
+/// How many of the positions sync_positions wrote to the DB were reconciled / failed for good
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PositionSyncSummary {
+    pub reconciled: usize,
+    pub failed: usize,
+}
+
+/// Awaits every reconciliation write sync_positions spawned and tallies them up - a write that
+/// panicked counts as failed
+pub async fn await_sync_writes(writes: Vec<JoinHandle<Result<(), String>>>) -> PositionSyncSummary {
+    let mut summary = PositionSyncSummary::default();
+    for write in writes {
+        match write.await {
+            Ok(Ok(())) => summary.reconciled += 1,
+            Ok(Err(e)) => {
+                tracing::error!("{}", e);
+                summary.failed += 1;
+            }
+            Err(e) => {
+                tracing::error!("Reconciliation write did not complete: {}", e);
+                summary.failed += 1;
+            }
+        }
+    }
+    summary
+}
+
+/// Which syncs OrderEngine.sync_all runs and in what order
+/// - Executions has to run before Positions: sync_executions books missed fills against the
+///   strategies, sync_positions then only reconciles what is left over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncOptions {
+    pub executions: bool,
+    pub open_orders: bool,
+    pub positions: bool,
+    pub order: Vec<SyncStep>,
+    pub reconcile_direction: ReconcileDirection,
+    /// Whether sync_open_orders deletes local open orders the broker no longer has
+    pub remove_stale_open_orders: bool,
+    /// How sync_positions retries its DB writes
+    pub write_retry: SyncWriteRetry,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            executions: true,
+            open_orders: true,
+            positions: true,
+            order: vec![
+                SyncStep::Executions,
+                SyncStep::OpenOrders,
+                SyncStep::Positions,
+            ],
+            reconcile_direction: ReconcileDirection::default(),
+            remove_stale_open_orders: true,
+            write_retry: SyncWriteRetry::default(),
+        }
+    }
+}
+
+impl SyncOptions {
+    /// Reads SYNC_EXECUTIONS / SYNC_OPEN_ORDERS / SYNC_POSITIONS ("false" to disable) and
+    /// SYNC_ORDER (comma separated, e.g. "open_orders,executions,positions") and
+    /// SYNC_RECONCILE_DIRECTION ("broker" / "local") and SYNC_REMOVE_STALE_OPEN_ORDERS ("false"
+    /// to keep them) and SYNC_WRITE_ATTEMPTS / SYNC_WRITE_RETRY_DELAY_MS - unset keeps default
+    pub fn from_env() -> Result<Self, String> {
+        let is_enabled = |var: &str| {
+            std::env::var(var)
+                .map(|value| value.trim() != "false")
+                .unwrap_or(true)
+        };
+        let order = match std::env::var("SYNC_ORDER") {
+            Ok(order) => order
+                .split(',')
+                .map(SyncStep::from_str)
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => SyncOptions::default().order,
+        };
+        let reconcile_direction = match std::env::var("SYNC_RECONCILE_DIRECTION") {
+            Ok(direction) => ReconcileDirection::from_str(&direction)?,
+            Err(_) => ReconcileDirection::default(),
+        };
+        let mut write_retry = SyncWriteRetry::default();
+        if let Ok(attempts) = std::env::var("SYNC_WRITE_ATTEMPTS") {
+            write_retry.attempts = attempts
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("SYNC_WRITE_ATTEMPTS must be a number of attempts: {}", e))?;
+        }
+        if let Ok(millis) = std::env::var("SYNC_WRITE_RETRY_DELAY_MS") {
+            write_retry.delay =
+                Duration::from_millis(millis.trim().parse::<u64>().map_err(|e| {
+                    format!(
+                        "SYNC_WRITE_RETRY_DELAY_MS must be a number of milliseconds: {}",
+                        e
+                    )
+                })?);
+        }
+
+        Ok(Self {
+            executions: is_enabled("SYNC_EXECUTIONS"),
+            open_orders: is_enabled("SYNC_OPEN_ORDERS"),
+            positions: is_enabled("SYNC_POSITIONS"),
+            order,
+            reconcile_direction,
+            remove_stale_open_orders: is_enabled("SYNC_REMOVE_STALE_OPEN_ORDERS"),
+            write_retry,
+        })
+    }
+
+    fn is_enabled(&self, step: SyncStep) -> bool {
+        match step {
+            SyncStep::Executions => self.executions,
+            SyncStep::OpenOrders => self.open_orders,
+            SyncStep::Positions => self.positions,
+        }
+    }
+
+    /// Enabled steps in the configured order
+    /// - Err if Positions is ordered before Executions while both are enabled
+    pub fn steps(&self) -> Result<Vec<SyncStep>, String> {
+        let mut steps: Vec<SyncStep> = Vec::new();
+        for step in &self.order {
+            if self.is_enabled(*step) && !steps.contains(step) {
+                steps.push(*step);
+            }
+        }
+
+        let position_of = |step| steps.iter().position(|s| *s == step);
+        if let (Some(executions), Some(positions)) = (
+            position_of(SyncStep::Executions),
+            position_of(SyncStep::Positions),
+        ) && positions < executions
+        {
+            return Err("Executions have to be synced before positions".to_string());
+        }
+        Ok(steps)
+    }
+}
+
+/// Deletes the local open stock / option orders whose perm_id is not among broker_perm_ids, the
+/// orders the broker returned as open in its snapshot taken at snapshot_time, and returns the
+/// perm_ids deleted
+/// - these were filled or cancelled while the app was down, so nothing will update them again
+/// - orders placed at or after snapshot_time are kept, the snapshot could not have included them
+/// - orders with fills not yet booked to transactions are kept and logged instead, so
+///   their fills can still be attributed to the strategy once the executions come in
+pub async fn remove_stale_open_orders(
+    pool: PgPool,
+    broker_perm_ids: &[i32],
+    snapshot_time: DateTime<Utc>,
+) -> Result<Vec<i32>, String> {
+    let stock_orders_crud = get_specific_open_stock_orders_crud(pool.clone());
+    let option_orders_crud = get_specific_option_orders_crud(pool);
+
+    let mut removed = stock_orders_crud
+        .delete_absent_orders(broker_perm_ids, snapshot_time)
+        .await?;
+    removed.extend(
+        option_orders_crud
+            .delete_absent_orders(broker_perm_ids, snapshot_time)
+            .await?,
+    );
+
+    let mut kept = stock_orders_crud
+        .get_absent_orders(broker_perm_ids, snapshot_time)
+        .await?;
+    kept.extend(
+        option_orders_crud
+            .get_absent_orders(broker_perm_ids, snapshot_time)
+            .await?,
+    );
+    if !kept.is_empty() {
+        tracing::warn!(
+            "Kept open orders {:?} missing from the broker as their fills are not booked yet",
+            kept
+        );
+    }
+    Ok(removed)
+}
@@ -90,3 +90,110 @@ impl IBGateway {
         Ok(())
     }
 }
+
+/// What to do after a failed gateway login
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFailureAction {
+    /// Try logging in again after the delay
+    Retry(Duration),
+    /// Threshold of consecutive failures just reached - alert, then wait the (longer) delay
+    Alert(Duration),
+}
+
+/// Backs off between failed IB Gateway logins instead of retrying straight away
+/// - delay doubles from base_delay up to max_delay with each consecutive failure
+/// - after alert_after consecutive failures an alert goes out once, and retries drop to once
+///   per alert_delay until a login succeeds
+#[derive(Debug, Clone)]
+pub struct LoginBackoff {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub alert_after: u32,
+    pub alert_delay: Duration,
+    consecutive_failures: u32,
+}
+
+impl Default for LoginBackoff {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_secs(30),
+            Duration::from_secs(10 * 60),
+            5,
+            Duration::from_secs(60 * 60),
+        )
+    }
+}
+
+impl LoginBackoff {
+    pub fn new(
+        base_delay: Duration,
+        max_delay: Duration,
+        alert_after: u32,
+        alert_delay: Duration,
+    ) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            alert_after: alert_after.max(1),
+            alert_delay,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Reads MAX_LOGIN_FAILURES (failures before alerting) - unset keeps default
+    pub fn from_env() -> Result<Self, String> {
+        let mut backoff = Self::default();
+        if let Ok(max_failures) = std::env::var("MAX_LOGIN_FAILURES") {
+            backoff.alert_after = max_failures
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("MAX_LOGIN_FAILURES must be a number: {}", e))?
+                .max(1);
+        }
+        Ok(backoff)
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn on_success(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    pub fn on_failure(&mut self) -> LoginFailureAction {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures == self.alert_after {
+            return LoginFailureAction::Alert(self.alert_delay);
+        }
+        if self.consecutive_failures > self.alert_after {
+            return LoginFailureAction::Retry(self.alert_delay);
+        }
+        let doublings = (self.consecutive_failures - 1).min(16);
+        LoginFailureAction::Retry((self.base_delay * 2u32.pow(doublings)).min(self.max_delay))
+    }
+}
+
+/// Posts a login failure alert to the backend's /send_notification, which forwards it to the
+/// connected dashboard
+pub async fn send_login_failure_alert(
+    notification_url: &str,
+    consecutive_failures: u32,
+) -> Result<(), String> {
+    let notification = serde_json::json!({
+        "title": "IB Gateway login failing",
+        "body": format!(
+            "IB Gateway login failed {} times in a row, retrying less often until it succeeds",
+            consecutive_failures
+        ),
+        "alert_type": "error",
+    });
+    reqwest::Client::new()
+        .post(notification_url)
+        .json(&notification)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to send login failure alert: {}", e))?;
+    Ok(())
+}
@@ -1,11 +1,15 @@
 use async_trait::async_trait;
 use sqlx::{Postgres, postgres::PgArguments, query::QueryAs};
+pub mod api;
+pub mod app_state;
 pub mod database;
 pub mod execution;
+pub mod ibc;
 pub mod init;
 pub mod logger;
 pub mod market_data;
 pub mod strategy;
+pub mod supervisor;
 
 #[macro_export]
 macro_rules! unlock {
@@ -29,6 +33,8 @@ macro_rules! unlock {
 pub trait Insertable {
     fn table_name() -> &'static str;
     fn pri_column_names(&self) -> Vec<&'static str>;
+    /// Every column of the model - its pri columns then its optional columns
+    fn column_names() -> Vec<&'static str>;
     fn opt_column_names(&self) -> Vec<&'static str>;
     fn bind_pri<'q>(&'q self, sql: &'q str) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments>;
     fn bind_pri_to_query<'q>(
@@ -48,4 +54,17 @@ pub trait Insertable {
         &'q self,
         query: QueryAs<'q, Postgres, T, PgArguments>,
     ) -> QueryAs<'q, Postgres, T, PgArguments>;
+    /// Columns of a multi-row VALUES clause for rows - pri columns, then the optional columns,
+    /// which every row has to set the same of
+    fn many_column_names(rows: &[Self]) -> Result<Vec<&'static str>, String>
+    where
+        Self: Sized;
+    /// Binds every row in order, each as its pri then its set optional columns - lines up with
+    /// the placeholders of many_column_names repeated once per row
+    fn bind_many_to_query<'q>(
+        rows: &'q [Self],
+        query: sqlx::query::Query<'q, sqlx::Postgres, PgArguments>,
+    ) -> sqlx::query::Query<'q, sqlx::Postgres, PgArguments>
+    where
+        Self: Sized;
 }
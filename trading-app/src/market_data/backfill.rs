@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+
+const SECONDS_PER_DAY: u64 = 86400;
+const BAR_SECS: i64 = 300;
+
+/// Longest duration, in days, IBKR serves in a single historical data request for bars of
+/// bar_minutes - from IBKR's duration / bar size table (1 D down to 1 min bars, 2 D down to 2
+/// min bars, 1 W down to 3 min bars, 1 M down to 30 min bars)
+pub fn max_request_days(bar_minutes: u32) -> u32 {
+    match bar_minutes {
+        0..=1 => 1,
+        2 => 2,
+        3..=29 => 7,
+        _ => 30,
+    }
+}
+
+/// One historical data request of a backfill - duration_secs of bars ending at end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoricalChunk {
+    pub end: DateTime<Utc>,
+    pub duration_secs: u64,
+}
+
+impl HistoricalChunk {
+    /// Duration as sent to IBKR - "N S" up to a day, past that rounded up to whole days as "N D"
+    /// since IBKR takes at most a day in seconds
+    pub fn ibkr_duration(&self) -> String {
+        if self.duration_secs > SECONDS_PER_DAY {
+            format!("{} D", self.duration_secs.div_ceil(SECONDS_PER_DAY))
+        } else {
+            format!("{} S", self.duration_secs)
+        }
+    }
+}
+
+/// Splits a backfill of [start, end) into back to back requests of at most max_days each,
+/// latest first - each chunk ends where the previous one starts, so the bars stitch together
+/// without gaps or overlap
+pub fn chunk_backfill(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_days: u32,
+) -> Vec<HistoricalChunk> {
+    let max_secs = max_days.max(1) as u64 * SECONDS_PER_DAY;
+    let mut chunks = Vec::new();
+    let mut chunk_end = end;
+    // whole seconds only, a sub-second remainder is not worth a request of its own
+    while (chunk_end - start).num_seconds() > 0 {
+        let duration_secs = ((chunk_end - start).num_seconds() as u64).min(max_secs);
+        chunks.push(HistoricalChunk {
+            end: chunk_end,
+            duration_secs,
+        });
+        chunk_end -= chrono::Duration::seconds(duration_secs as i64);
+    }
+    chunks
+}
+
+/// Start times of the 5 minute bars after last_stored up to and including last_available - from
+/// session_open when nothing has been stored since the open
+/// - compared by 5 minute bucket, as stored bar times can be seconds off
+pub fn missing_tail_bars(
+    last_stored: Option<DateTime<Utc>>,
+    last_available: DateTime<Utc>,
+    session_open: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let bucket = |time: DateTime<Utc>| time.timestamp().div_euclid(BAR_SECS);
+    let first_missing = match last_stored {
+        Some(last_stored) if last_stored >= session_open => bucket(last_stored) + 1,
+        _ => bucket(session_open),
+    };
+    (first_missing..=bucket(last_available))
+        .filter_map(|bucket| DateTime::from_timestamp(bucket * BAR_SECS, 0))
+        .collect()
+}
@@ -16,23 +16,27 @@ use ibapi::{
     prelude::{Contract, HistoricalWhatToShow, RealtimeWhatToShow, SecurityType, TickTypes},
 };
 use moka::sync::Cache;
-use nyse_holiday_cal::HolidayCal;
 use rust_decimal::{Decimal, prelude::FromPrimitive};
 use sqlx::PgPool;
-use tokio::sync::mpsc::{Sender, channel};
+use tokio::{
+    sync::mpsc::{Sender, channel},
+    task::JoinHandle,
+};
 use tracing::info;
 
 use crate::{
+    app_state::TradingAppState,
     database::{
         crud::{CRUD, CRUDTrait},
         models::{
-            AssetType, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys,
-            HistoricalOptionsDataPrimaryKeys,
-            HistoricalOptionsDataUpdateKeys, OptionType,
+            AssetType, HistoricalData, HistoricalDataFullKeys, HistoricalDataPrimaryKeys,
+            HistoricalDataUpdateKeys, HistoricalOptionsData, HistoricalOptionsDataFullKeys,
+            HistoricalOptionsDataPrimaryKeys, HistoricalOptionsDataUpdateKeys, OptionType,
+            normalize_multiplier,
         },
         models_crud::{
             historical_data::{
-                HistoricalDataCRUD, get_specific_historical_data_crud,
+                HistoricalDataCRUD, LastCloseCache, get_specific_historical_data_crud,
             },
             historical_options_data::{
                 HistoricalOptionsDataCRUD, 
@@ -40,22 +44,236 @@ use crate::{
             },
         },
     },
-    execution::order_engine::OrderEngine,
-    strategy::strategy::StrategyExecutor,
+    execution::{
+        cancel::OrderCanceller, contract_validation::ContractValidationCache,
+        order_engine::OrderEngine, place_order::OrderSubmitter,
+    },
+    market_data::{
+        backfill::{chunk_backfill, max_request_days, missing_tail_bars},
+        freshness::LastBarTime,
+        history_gate::HistoryCheck,
+        in_flight::InFlightRequests,
+        latest_price::LatestPrice,
+        market_hours::{Clock, MarketHours, SessionOpenGate},
+        pacing::{MarketDataPacer, PacingConfig},
+        scheduler::EventSchedule,
+    },
+    strategy::{
+        flatten::{FLATTEN_AT_CLOSE, FlattenedStrategies, flatten_targets, strategy_schedule},
+        strategy::StrategyExecutor,
+    },
+    supervisor::{SupervisorOptions, ThreadStatus, supervise},
     unlock,
 };
 
+/// (bar start time, open, high, low, close, volume) as sent to on_bar_update
+pub type ConsolidatedBar = (DateTime<Utc>, f64, f64, f64, f64, f64);
+
+/// (unix timestamp, open, high, low, close, volume) of a 5 second bar as collected for a live
+/// contract
+pub type FiveSecBar = (i64, f64, f64, f64, f64, f64);
+
+/// Builds a single 5 minute bar out of the 5 second bars given
+/// - bar time is the start of the 5 minute bucket the first bar falls in
+/// - used at session close to force out the bucket that never saw a bar cross its boundary
+pub fn consolidate_partial_bucket(bars: &[FiveSecBar]) -> Option<ConsolidatedBar> {
+    let first_bar = bars.first()?;
+    let last_bar = bars.last()?;
+    let bucket_start = first_bar.0 - (first_bar.0 % 300);
+    Some((
+        Utc.timestamp_opt(bucket_start, 0).unwrap(),
+        first_bar.1,
+        bars.iter().map(|bar| bar.2).fold(f64::MIN, f64::max),
+        bars.iter().map(|bar| bar.3).fold(f64::MAX, f64::min),
+        last_bar.4,
+        bars.iter().map(|bar| bar.5).sum(),
+    ))
+}
+
+/// Takes the bars of every 5 minute bucket before that of the newest bar off the front of bars,
+/// returning one 5 minute bar per bucket, oldest first
+/// - the newest bar's bucket is still forming so its bars are left for the next call
+/// - a bucket no bar fell in (a gap in the feed) gives no bar rather than an empty one
+pub fn drain_completed_buckets(bars: &mut VecDeque<FiveSecBar>) -> Vec<ConsolidatedBar> {
+    let bucket_of = |bar: &FiveSecBar| bar.0.div_euclid(300);
+    let Some(forming_bucket) = bars.back().map(bucket_of) else {
+        return Vec::new();
+    };
+    let mut completed = Vec::new();
+    while let Some(bucket) = bars.front().map(bucket_of).filter(|bucket| *bucket != forming_bucket) {
+        let bucket_len = bars.iter().take_while(|bar| bucket_of(bar) == bucket).count();
+        let bucket_bars: Vec<FiveSecBar> = bars.drain(..bucket_len).collect();
+        completed.extend(consolidate_partial_bucket(&bucket_bars));
+    }
+    completed
+}
+
+/// Volume of a bar as stored - IBKR sends stock volume in lots of 100 shares
+/// - a volume that isn't representable as a Decimal (NaN / inf from a malformed bar) is stored as
+///   0 with a warning, so one bad bar doesn't stop ingestion for the whole contract
+pub fn bar_volume(volume: f64, symbol: &str) -> Decimal {
+    Decimal::from_f64(volume * 100.0).unwrap_or_else(|| {
+        tracing::warn!(
+            "Malformed volume {} in bar for {}, storing a volume of 0",
+            volume,
+            symbol
+        );
+        Decimal::ZERO
+    })
+}
+
+/// Time a bar returned by a historical data request starts at
+fn historical_bar_time(bar: &ibapi::market_data::historical::Bar) -> DateTime<Utc> {
+    DateTime::from_timestamp(bar.date.unix_timestamp(), bar.date.nanosecond())
+        .expect("Expected to be able to convert bar time to DateTime<Utc>")
+}
+
+/// 5 second bars kept per live contract by default - the forming 5 minute bucket's 60 bars, plus
+/// as many again while a bucket is being consolidated
+pub const DEFAULT_MAX_RETAINED_BARS: usize = 120;
+
+/// Pushes bar onto bars, evicting the oldest bars beyond max_bars (at least 1 is kept)
+/// - only the forming bucket is consolidated from, so bars only pile up past it if buckets stop
+///   completing
+/// - returns the number of bars evicted
+pub fn push_capped<B>(bars: &mut VecDeque<B>, bar: B, max_bars: usize) -> usize {
+    bars.push_back(bar);
+    let evicted = bars.len().saturating_sub(max_bars.max(1));
+    bars.drain(..evicted);
+    evicted
+}
+
+/// Whether two bar times fall in the same 5 minute bucket
+/// - buckets are [hh:m0, hh:m5) aligned to the unix epoch - New York is a whole number of hours
+///   off UTC so these line up with the exchange's 5 minute bars
+/// - used instead of exact equality since stored bar times can be seconds off / differently
+///   rounded from the bar time computed off the clock
+pub fn is_same_bar_bucket(a: DateTime<Utc>, b: DateTime<Utc>) -> bool {
+    a.timestamp().div_euclid(300) == b.timestamp().div_euclid(300)
+}
+
+/// OHLCV of consecutive bars taken together - time is that of the first bar
+pub fn aggregate_bars(bars: &[ConsolidatedBar]) -> Option<ConsolidatedBar> {
+    let first_bar = bars.first()?;
+    let last_bar = bars.last()?;
+    Some((
+        first_bar.0,
+        first_bar.1,
+        bars.iter().map(|bar| bar.2).fold(f64::MIN, f64::max),
+        bars.iter().map(|bar| bar.3).fold(f64::MAX, f64::min),
+        last_bar.4,
+        bars.iter().map(|bar| bar.5).sum(),
+    ))
+}
+
+/// Every (contract, timestep) subscribe_strategy subscribes strategy to - each of its contracts at
+/// its timestep
+pub fn strategy_subscriptions<T: StrategyExecutor>(strategy: &T) -> Vec<(Contract, u32)> {
+    let timestep = strategy.get_timestep();
+    strategy
+        .get_contracts()
+        .into_iter()
+        .map(|contract| (contract, timestep))
+        .collect()
+}
+
+/// Minutes from the 09:30 New York open to time - negative before the open
+fn minutes_since_open(time: DateTime<Utc>) -> i64 {
+    let time_ny = time.with_timezone(&New_York);
+    let market_open = time_ny
+        .date_naive()
+        .and_time(NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+    (time_ny.naive_local() - market_open).num_minutes()
+}
+
+/// Builds timestep minute bars out of the 5 minute bars of one contract
+/// - buckets are aligned to the 09:30 open, e.g. 09:30 - 09:45 for a timestep of 15
+/// - a bucket is emitted once its last 5 minute bar arrives; bars left over from a bucket that
+///   never completed are dropped rather than merged into the next one
+pub struct TimestepAggregator {
+    timestep: u32,
+    pending: Vec<ConsolidatedBar>,
+}
+
+impl TimestepAggregator {
+    pub fn new(timestep: u32) -> Self {
+        Self {
+            timestep: timestep.max(5),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds the 5 minute bar starting at bar.0, returning the timestep bar it completes
+    /// - the returned bar's time is the start of its bucket
+    pub fn push(&mut self, bar: ConsolidatedBar) -> Option<ConsolidatedBar> {
+        let elapsed = minutes_since_open(bar.0);
+        if elapsed < 0 {
+            return None;
+        }
+        let timestep = self.timestep as i64;
+        let bucket_start = bar.0 - chrono::Duration::minutes(elapsed % timestep);
+        if self
+            .pending
+            .first()
+            .is_some_and(|pending| pending.0 < bucket_start)
+        {
+            tracing::warn!(
+                "Dropping incomplete {} minute bar from {}",
+                self.timestep,
+                self.pending[0].0
+            );
+            self.pending.clear();
+        }
+        self.pending.push(bar);
+
+        if (elapsed + 5) % timestep != 0 {
+            return None;
+        }
+        let mut timestep_bar = aggregate_bars(&self.pending)?;
+        timestep_bar.0 = bucket_start;
+        self.pending.clear();
+        Some(timestep_bar)
+    }
+}
+
 pub struct Consolidator<T: StrategyExecutor> {
     pub pool: PgPool,
     client: Arc<Client>,
     // Stock, Primary Exchange
     subscriptions: Arc<Mutex<HashMap<(String, String), HashMap<u32, BTreeSet<T>>>>>,
 
-    live_data: Arc<Mutex<HashMap<(String, String), Arc<Mutex<VecDeque<Bar>>>>>>,
+    live_data: Arc<Mutex<HashMap<(String, String), Arc<Mutex<VecDeque<FiveSecBar>>>>>>,
+    // Close of the newest 5 sec bar in live_data, read without locking the contract's bars
+    latest_prices: Arc<Mutex<HashMap<(String, String), LatestPrice>>>,
+    // Time of the newest 5 sec bar across every live contract, for the market data health check
+    last_bar_time: LastBarTime,
     past_data: Arc<Cache<(String, String), f64>>,
     past_data_vwap: Arc<Cache<(String, String), f64>>,
 
-    contract_update_sender: Arc<Mutex<Option<Sender<(Contract, DateTime<Utc>)>>>>,
+    // completed 5 min bars, once persisted, for the strategies subscribed to the contract
+    contract_update_sender: Arc<Mutex<Option<Sender<(Contract, ConsolidatedBar)>>>>,
+    // Stock, Primary Exchange -> sender for completed 5 min bars of that contract
+    bar_senders: Arc<Mutex<HashMap<(String, String), Sender<ConsolidatedBar>>>>,
+    flush_partial_bar_on_close: bool,
+    // Longest duration, in days, of a single historical data request when backfilling
+    max_historical_request_days: u32,
+    // Session the bars required when warming up are counted from
+    market_hours: MarketHours,
+    // Security type -> session of instruments not trading the equity session, see market_hours_for
+    instrument_market_hours: HashMap<String, MarketHours>,
+    // Minutes before the close flatten_at_close strategies are flattened at
+    flatten_minutes_before_close: u32,
+    // Strategies flattened this session, whose bar updates are skipped until the next
+    flattened_strategies: FlattenedStrategies,
+    // Most 5 sec bars kept in live_data per contract, the oldest are evicted beyond it
+    max_retained_bars: usize,
+    pacer: Arc<MarketDataPacer>,
+    // Result of validate_contract per contract
+    validated_contracts: ContractValidationCache,
+    // (contract, what_to_show, days) -> update_at_least_n_days_data currently running for it
+    warmups: Arc<InFlightRequests<(String, String, u32)>>,
+    // Contracts with the required_history_days of their strategies ingested
+    history_check: HistoryCheck,
 
     historical_data_crud: HistoricalDataCRUD,
     historical_options_data_crud: HistoricalOptionsDataCRUD,
@@ -63,7 +281,76 @@ pub struct Consolidator<T: StrategyExecutor> {
     is_historical_options_data_crud_channel_opened: Arc<tokio::sync::Mutex<bool>>,
 }
 
+/// Passes a completed timestep bar of contract to strategy and places orders for the targets it
+/// updated, as begin_bar_listening does for every subscribed strategy
+/// - nothing happens until contract has the strategy's required_history_days stored, see
+///   HistoryCheck
+pub async fn act_on_timestep_bar<T, C>(
+    strategy: T,
+    contract: Contract,
+    timestep: u32,
+    timestep_bar: ConsolidatedBar,
+    history_check: HistoryCheck,
+    order_engine: Arc<OrderEngine>,
+    client: Arc<C>,
+) where
+    T: StrategyExecutor + 'static,
+    C: OrderSubmitter + OrderCanceller + Send + Sync + 'static,
+{
+    let days = strategy.required_history_days();
+    match history_check.has_required_history(&contract, days).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!(
+                "Deferring {} on {} until {} days of history are stored",
+                strategy.get_name(),
+                contract.symbol,
+                days
+            );
+            return;
+        }
+        Err(e) => {
+            tracing::error!(
+                "Error checking history of {} for {}: {}",
+                contract.symbol,
+                strategy.get_name(),
+                e
+            );
+            return;
+        }
+    }
+
+    let bar_update_res = strategy
+        .on_timestep_bar(&contract, timestep, &timestep_bar)
+        .await;
+    if let Ok(updated) = bar_update_res {
+        if !updated.0 {
+            return;
+        }
+    }
+
+    order_engine.place_orders_for_bar_update(
+        strategy,
+        contract,
+        client,
+        bar_update_res.is_ok_and(|res| res.1),
+    );
+}
+
 impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
+    /// Consolidator on the session's pool and configured market hours, requesting data through
+    /// client
+    pub fn from_state(state: &TradingAppState, client: Arc<Client>) -> Self {
+        let mut consolidator = Self::new(state.pool.clone(), client);
+        consolidator.set_market_hours(state.config.open_gate.market_hours);
+        consolidator.set_flatten_minutes_before_close(state.config.flatten_minutes_before_close);
+        consolidator.set_max_retained_bars(state.config.max_retained_bars);
+        consolidator.set_last_close_cache(state.last_closes.clone());
+        consolidator.set_last_bar_time(state.last_bar_time.clone());
+        consolidator.set_validated_contracts(state.validated_contracts.clone());
+        consolidator
+    }
+
     pub fn new(pool: PgPool, client: Arc<Client>) -> Self {
         let ttl = Duration::from_secs(20);
         let max_capacity = 10;
@@ -74,6 +361,8 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
             subscriptions: Arc::new(Mutex::new(HashMap::new())),
 
             live_data: Arc::new(Mutex::new(HashMap::new())),
+            latest_prices: Arc::new(Mutex::new(HashMap::new())),
+            last_bar_time: LastBarTime::new(),
             past_data: Arc::new(
                 Cache::builder()
                     .time_to_live(ttl)
@@ -87,6 +376,21 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                     .build(),
             ),
             contract_update_sender: Arc::new(Mutex::new(None)),
+            bar_senders: Arc::new(Mutex::new(HashMap::new())),
+            flush_partial_bar_on_close: true,
+            max_historical_request_days: max_request_days(5),
+            market_hours: MarketHours::default(),
+            instrument_market_hours: HashMap::from([
+                (SecurityType::Future.to_string(), MarketHours::futures()),
+                (SecurityType::ForexPair.to_string(), MarketHours::forex()),
+            ]),
+            flatten_minutes_before_close: 10,
+            flattened_strategies: FlattenedStrategies::new(),
+            max_retained_bars: DEFAULT_MAX_RETAINED_BARS,
+            pacer: Arc::new(MarketDataPacer::default()),
+            validated_contracts: ContractValidationCache::new(),
+            warmups: Arc::new(InFlightRequests::new()),
+            history_check: HistoryCheck::new(pool.clone(), MarketHours::default()),
 
             historical_data_crud: get_specific_historical_data_crud(pool.clone()),
             historical_options_data_crud: get_specific_historical_options_data_crud(pool),
@@ -130,28 +434,23 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
     }
 
     /// Gets the current price of the contract from IBKR
-    /// - if currently subscribed to their live data - unlocks and returns it
-    ///     - Note: Each live_data subscription is wrapped behind a std::sync::Mutex so this
-    ///     function could be potentially blocking for a longer period of time than expected
+    /// - if currently subscribed to their live data - returns the close of the latest bar
+    ///     - read from latest_prices, so it never waits on the contract's bars being consolidated
+    ///       behind their std::sync::Mutex
     /// - if requested the data in the last 20s, returns that
     /// - else, requests from IBKR
     pub fn get_current_price(&self, contract: Contract, vwap: bool) -> Result<f64, String> {
-        {
+        if !vwap {
             // If currently tracking, then j return latest data
-            let live_data = unlock!(
-                self.live_data,
-                "live_data",
+            let latest_price = unlock!(
+                self.latest_prices,
+                "latest_prices",
                 "Consolidator.get_current_price"
-            );
-            if !vwap && live_data.contains_key(&(contract.symbol.clone(), contract.primary_exchange.clone())) {
-                let live_data_for_contract = unlock!(
-                    live_data.get(&(contract.symbol.clone(), contract.primary_exchange.clone())).unwrap(),
-                    format!("live_data.{}", &contract.symbol),
-                    "Consolidator"
-                );
-                if let Some(latest_bar) = live_data_for_contract.back() {
-                    return Ok(latest_bar.close);
-                }
+            )
+            .get(&(contract.symbol.clone(), contract.primary_exchange.clone()))
+            .and_then(|latest_price| latest_price.get());
+            if let Some(latest_price) = latest_price {
+                return Ok(latest_price);
             }
         }
 
@@ -172,6 +471,7 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         }
 
         // Request data as last resort
+        self.pacer.before_market_data();
         let subscription = self
             .client
             .market_data(&contract, if vwap { &["233"] } else { &[] }, true, false)
@@ -198,17 +498,122 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         ))
     }
 
+    /// Broker's contract matching contract, None if it doesn't exist - only requested the first
+    /// time contract is validated
     pub fn validate_contract(&self, contract: &Contract) -> Option<Contract> {
-        match self.client.contract_details(contract) {
-            Ok(validated_contracts) => {
-                if validated_contracts.len() == 0 { return None; }
-                return Some(validated_contracts.first().unwrap().contract.clone());
+        self.validated_contracts
+            .validate(&*self.client, contract)
+            .unwrap_or_else(|e| {
+                tracing::error!("Error validating contract {}: {}", contract.symbol, e);
+                None
+            })
+    }
+
+    /// Rates market_data / historical_data requests to IBKR are paced at (default: IBKR's limits)
+    pub fn set_pacing(&mut self, pacing: PacingConfig) {
+        self.pacer = Arc::new(MarketDataPacer::new(pacing));
+    }
+
+    /// Whether flush_partial_bars emits the in-progress bucket at session close (default: true)
+    pub fn set_flush_partial_bar_on_close(&mut self, flush_partial_bar_on_close: bool) {
+        self.flush_partial_bar_on_close = flush_partial_bar_on_close;
+    }
+
+    /// Longest duration, in days, a single historical data request of a backfill may cover
+    /// (default: IBKR's limit for 5 minute bars, see backfill::max_request_days)
+    pub fn set_max_historical_request_days(&mut self, max_historical_request_days: u32) {
+        self.max_historical_request_days = max_historical_request_days;
+    }
+
+    /// Session warm ups count the bars they need from, and check the latest bar against
+    /// (default: the NYSE 09:30 - 16:00 session)
+    pub fn set_market_hours(&mut self, market_hours: MarketHours) {
+        self.market_hours = market_hours;
+    }
+
+    /// Session warm ups of security_type's contracts count their bars over instead of
+    /// market_hours (default: MarketHours::futures for futures, MarketHours::forex for FX)
+    pub fn set_instrument_market_hours(
+        &mut self,
+        security_type: SecurityType,
+        market_hours: MarketHours,
+    ) {
+        self.instrument_market_hours
+            .insert(security_type.to_string(), market_hours);
+    }
+
+    /// Session contract trades in - its security type's if one is set, market_hours otherwise
+    pub fn market_hours_for(&self, contract: &Contract) -> MarketHours {
+        self.instrument_market_hours
+            .get(&contract.security_type.to_string())
+            .copied()
+            .unwrap_or(self.market_hours)
+    }
+
+    /// Minutes before the close flatten_at_close strategies have their targets zeroed at
+    /// (default: 10)
+    pub fn set_flatten_minutes_before_close(&mut self, flatten_minutes_before_close: u32) {
+        self.flatten_minutes_before_close = flatten_minutes_before_close;
+    }
+
+    /// Most 5 second bars kept per live contract while its 5 minute bar forms, the oldest
+    /// evicted beyond it (default: DEFAULT_MAX_RETAINED_BARS)
+    /// - anything under a bucket's 60 bars cuts short the 5 minute bars consolidated
+    pub fn set_max_retained_bars(&mut self, max_retained_bars: usize) {
+        self.max_retained_bars = max_retained_bars;
+    }
+
+    /// Cache the close of every stock bar stored is recorded in, for latest_close
+    pub fn set_last_close_cache(&mut self, last_closes: LastCloseCache) {
+        self.historical_data_crud.set_last_close_cache(last_closes);
+    }
+
+    /// Cache contracts are validated through - shared with the order engine's registration
+    pub fn set_validated_contracts(&mut self, validated_contracts: ContractValidationCache) {
+        self.validated_contracts = validated_contracts;
+    }
+
+    /// Time every 5 second bar received is recorded in, for the market data health check
+    pub fn set_last_bar_time(&mut self, last_bar_time: LastBarTime) {
+        self.last_bar_time = last_bar_time;
+    }
+
+    /// Should be called once the session has closed
+    /// - the last bucket of the day never sees a 5 second bar cross its boundary, so it is never
+    ///   emitted by on_new_5sec_bar - this forces it out as a final bar through the usual
+    ///   on_bar_update path (i.e. it is persisted and strategies are notified)
+    pub async fn flush_partial_bars(&self) -> Result<(), String> {
+        if !self.flush_partial_bar_on_close {
+            return Ok(());
+        }
+
+        let mut final_bars = Vec::new();
+        {
+            let live_data = unlock!(self.live_data, "live_data", "Consolidator.flush_partial_bars");
+            let bar_senders = unlock!(self.bar_senders, "bar_senders", "Consolidator.flush_partial_bars");
+            for (contract_key, collected_bars_arc) in live_data.iter() {
+                let mut collected_bars = unlock!(
+                    collected_bars_arc,
+                    format!("live_data.{}", &contract_key.0),
+                    "Consolidator.flush_partial_bars"
+                );
+                let partial_bucket: Vec<FiveSecBar> = collected_bars.drain(..).collect();
+                if let (Some(final_bar), Some(bar_sender)) = (
+                    consolidate_partial_bucket(&partial_bucket),
+                    bar_senders.get(contract_key),
+                ) {
+                    final_bars.push((contract_key.clone(), bar_sender.clone(), final_bar));
+                }
             }
-            Err(e) => {
-                tracing::error!("Error occurred requesting contract details for {}: {}", contract.symbol, e);
-                return None;
+        }
+
+        for (contract_key, bar_sender, final_bar) in final_bars {
+            info!("Flushing partial bar for {} at {}", contract_key.0, final_bar.0);
+            if let Err(e) = bar_sender.send(final_bar).await {
+                tracing::error!("Error occurred while trying to flush partial 5 min bar for {}: {}", contract_key.0, e);
             }
         }
+        Ok(())
     }
 
     pub async fn open_historical_data_crud_channel(&self) {
@@ -227,10 +632,36 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         }
     }
 
+    pub async fn open_historical_options_data_crud_channel(&self) {
+        let mut is_opened = self.is_historical_options_data_crud_channel_opened.lock().await;
+        if !*is_opened {
+            self.historical_options_data_crud.init_channel().await;
+            *is_opened = true;
+        }
+    }
+
+    pub async fn close_historical_options_data_crud_channel(&self) {
+        let mut is_opened = self.is_historical_options_data_crud_channel_opened.lock().await;
+        if *is_opened {
+            self.historical_options_data_crud.close_channel().await;
+            *is_opened = false;
+        }
+    }
+
+    /// Flushes whatever is still queued on the batching channels and closes them
+    /// - await this at session close: Drop can only signal the writers, not wait on them
+    pub async fn shutdown(&self) {
+        self.close_historical_data_crud_channel().await;
+        self.close_historical_options_data_crud_channel().await;
+    }
+
     /// Assumes that each day has 78 5-min bars
     /// - today inclusive: 1 refers to just today/most recent trading days
     ///      - Note: if days == 1 and time now is before 9:30, nth will be updated
-    /// - gives leeway of one half day before requesting full data: 39 bars less
+    /// - gives leeway of half a session's bars (MarketHours::half_session_bars) before requesting
+    ///   full data: stored history routinely falls a little short of the exact count (early
+    ///   closes, bars IBKR never sends for illiquid periods), which would otherwise re-request
+    ///   every day in full on each startup
     /// - Always checks for most recent trading day at least
     /// - apply_batching bool should ONLY be set to true if you have opened the relevant crud
     /// channels beforehand - it WILL fail otherwise. After usage, remember to close the channel to
@@ -241,6 +672,8 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
     /// for me
     ///
     /// NOTE: Requests always for 5 minute data
+    /// - Concurrent calls for the same contract, what_to_show and days (e.g. two strategies on QQQ
+    ///   warming up together) share one request - later callers wait on the running one
     pub async fn update_at_least_n_days_data(
         &self,
         contract: &Contract,
@@ -248,51 +681,84 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         days: u32,
         apply_batching: bool,
     ) -> Result<(), String> {
-        let mut required_num_bars = 0;
-        let mut days_counter = 0;
-        let mut earliest_datetime = Utc::now().with_timezone(&New_York);
-        let naive_date_tdy = Utc::now().with_timezone(&New_York).date_naive();
-        let mut is_trading_day_tdy = false;
-        for day in (Utc::now().with_timezone(&New_York) + chrono::Duration::days(1))
-            .date_naive()
-            .busday_iter()
-            .rev()
-        {
-            days_counter += 1;
-            if days_counter == 1 {
-                if naive_date_tdy == day {
-                    is_trading_day_tdy = true;
-                    required_num_bars += (Utc::now().with_timezone(&New_York)
-                        - Utc::now()
-                            .with_timezone(&New_York)
-                            .with_hour(9)
-                            .unwrap()
-                            .with_minute(0)
-                            .unwrap()
-                            .with_second(0)
-                            .unwrap()
-                            .with_nanosecond(0)
-                            .unwrap())
-                    .num_minutes()
-                        / 5;
-                }
-            }
-            if days_counter == days {
-                let naive_earliest_datetime =
-                    &day.and_time(NaiveTime::from_hms_opt(9, 0, 0).expect(
-                        "Expected supplied datetime in update_at_least_n_days_data to be valid",
-                    ));
-                let earliest_datetime_opt = New_York
-                    .from_local_datetime(naive_earliest_datetime)
-                    .single();
-                earliest_datetime = earliest_datetime_opt.expect(
-                    "Could not convert NaiveDateTime to TzDateTime in update_at_least_n_days_data",
-                );
-                break;
-            }
+        let warmup_key = (
+            format!(
+                "{}:{}:{}:{}:{}:{}",
+                contract.security_type,
+                contract.symbol,
+                contract.primary_exchange,
+                contract.last_trade_date_or_contract_month,
+                contract.strike,
+                contract.right
+            ),
+            what_to_show.to_string(),
+            days,
+        );
+        self.warmups
+            .run_once(warmup_key, || {
+                self.fetch_at_least_n_days_data(contract, what_to_show, days, apply_batching)
+            })
+            .await
+    }
+
+    /// Requests the 5 minute bars of contract from start until now, split into requests IBKR
+    /// accepts (see backfill::chunk_backfill) sent one after the other through the pacer
+    /// - returns the bars of every request stitched together in time order
+    async fn request_historical_bars(
+        &self,
+        contract: &Contract,
+        what_to_show: HistoricalWhatToShow,
+        start: DateTime<Utc>,
+    ) -> Result<Vec<ibapi::market_data::historical::Bar>, String> {
+        let mut bars = Vec::new();
+        for chunk in chunk_backfill(start, Utc::now(), self.max_historical_request_days) {
+            info!(
+                "Requesting {} duration of data ending {} for {}",
+                chunk.ibkr_duration(),
+                chunk.end,
+                contract.symbol
+            );
+            let end = time::OffsetDateTime::from_unix_timestamp(chunk.end.timestamp())
+                .map_err(|e| format!("Invalid end {} for historical data request: {}", chunk.end, e))?;
+            let duration = ibapi::market_data::historical::Duration::from_str(&chunk.ibkr_duration())
+                .expect("Expected Duration passed to historical_data method to be correct!");
 
-            required_num_bars += 78;
+            self.pacer.before_historical_data().await;
+            let historical_data = self
+                .client
+                .historical_data(
+                    contract,
+                    Some(end),
+                    duration,
+                    ibapi::prelude::HistoricalBarSize::Min5,
+                    what_to_show,
+                    true,
+                )
+                .map_err(|e| format!(
+                    "Expected Historical Data Request to TWS to succeed for {}: {}",
+                    contract.symbol.clone(),
+                    e
+                ))?;
+            bars.extend(historical_data.bars);
         }
+        // a bar on the boundary between two requests can come back in both
+        bars.sort_by_key(|bar| bar.date);
+        bars.dedup_by_key(|bar| bar.date);
+        Ok(bars)
+    }
+
+    /// update_at_least_n_days_data without the deduplication
+    async fn fetch_at_least_n_days_data(
+        &self,
+        contract: &Contract,
+        what_to_show: HistoricalWhatToShow,
+        days: u32,
+        apply_batching: bool,
+    ) -> Result<(), String> {
+        // bars are counted over the contract's own session - a futures day has far more than 78
+        let market_hours = self.market_hours_for(contract);
+        let (earliest_datetime, required_num_bars_with_leeway, is_trading_day_tdy) =
+            market_hours.history_requirement(days, Utc::now());
 
         match AssetType::from_str(contract.security_type.clone()) {
             AssetType::Stock => {
@@ -303,7 +769,7 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                         contract.symbol.clone(),
                         contract.primary_exchange.clone(),
                         earliest_datetime.clone(),
-                        (required_num_bars - 39).max(0) as u32,
+                        required_num_bars_with_leeway,
                     )
                     .await;
 
@@ -324,110 +790,40 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                                 last_bar_available_time
                             );
                             if last_bar_available_time
-                                > Utc::now()
-                                    .with_timezone(&New_York)
-                                    .with_hour(9)
-                                    .unwrap()
-                                    .with_minute(30)
-                                    .unwrap()
-                                    .with_second(0)
-                                    .unwrap()
-                                    .with_nanosecond(0)
-                                    .unwrap()
+                                > self.market_hours.open_of_day(Utc::now())
                             {
                                 match historical_data_crud
                                     .read_last_bar_of_stock(contract.symbol.clone(), contract.primary_exchange.clone())
                                     .await
                                 {
                                     Ok(last_bar) => {
-                                        if let Some(bar) = last_bar {
-                                            info!(
-                                                "Local bar time: {} and last_bar_available_time: {}, Equal: {}",
-                                                bar.time,
-                                                last_bar_available_time,
-                                                bar.time == last_bar_available_time
-                                            );
-                                            if bar.time == last_bar_available_time {
-                                                return Ok(());
-                                            }
-                                        }
-                                        let historical_data = self
-                                            .client
-                                            .historical_data(
-                                                &contract,
-                                                None,
-                                                ibapi::market_data::historical::Duration::from_str("1 D").expect("Expected to be able to parse 1 D for market data historical data"),
-                                                ibapi::prelude::HistoricalBarSize::Min5,
-                                                what_to_show,
-                                                true,
-                                            )
-                                            .expect(&format!(
-                                                "Expected Historical Data Request to TWS to succeed for {}",
-                                                contract.symbol.clone()
-                                        ));
-                                        for bar in &historical_data.bars {
-                                            let bar = bar.clone();
-                                            let historical_data_crud =
-                                                self.historical_data_crud.clone();
-                                            let stock = contract.symbol.clone();
-                                            let primary_exchange = contract.primary_exchange.clone();
-                                            tokio::spawn(async move {
-                                                if apply_batching {
-                                                    if let Err(e) = historical_data_crud
-                                                        .batch_create_or_update(&crate::database::models::HistoricalDataFullKeys {
-                                                            stock: stock.clone(),
-                                                            primary_exchange: primary_exchange.clone(),
-                                                            time: DateTime::from_timestamp(
-                                                                bar.date.unix_timestamp(),
-                                                                bar.date.nanosecond() as u32,
-                                                            )
-                                                            .expect("Expected to be able to convert bar time to DateTime<Utc>"),
-                                                            open: bar.open,
-                                                            high: bar.high,
-                                                            low: bar.low,
-                                                            close: bar.close,
-                                                            volume: Decimal::from_f64(
-                                                                bar.volume * 100.0
-                                                            ).expect("Expected to be able to parse f64 to Decimal"),
-                                                    })
-                                                        .await
-                                                    {
-                                                        tracing::error!(
-                                                            "Error occurred while upserting bars into historical data for {}: {}",
-                                                            stock.clone(),
-                                                            e
-                                                        )
-                                                    }
-                                                } else {
-                                                    if let Err(e) = historical_data_crud
-                                                        .create_or_update(&crate::database::models::HistoricalDataPrimaryKeys {
-                                                            stock: stock.clone(),
-                                                            primary_exchange: primary_exchange.clone(),
-                                                            time: DateTime::from_timestamp(
-                                                                bar.date.unix_timestamp(),
-                                                                bar.date.nanosecond() as u32,
-                                                            )
-                                                            .expect("Expected to be able to convert bar time to DateTime<Utc>")
-                                                    }, &HistoricalDataUpdateKeys {
-                                                            open: Some(bar.open),
-                                                            high: Some(bar.high),
-                                                            low: Some(bar.low),
-                                                            close: Some(bar.close),
-                                                            volume: Some(Decimal::from_f64(
-                                                                bar.volume * 100.0
-                                                            ).expect("Expected to be able to parse f64 to Decimal")),
-                                                    })
-                                                        .await
-                                                    {
-                                                        tracing::error!(
-                                                            "Error occurred while upserting bars into historical data for {}: {}",
-                                                            stock.clone(),
-                                                            e
-                                                        )
-                                                    }
-                                                }
-                                            });
+                                        // any number of bars after the last stored one can be
+                                        // missing, not just the latest
+                                        let missing_bars = missing_tail_bars(
+                                            last_bar.map(|bar| bar.time),
+                                            last_bar_available_time.with_timezone(&Utc),
+                                            self.market_hours.open_of_day(Utc::now()).with_timezone(&Utc),
+                                        );
+                                        if missing_bars.is_empty() {
+                                            return Ok(());
                                         }
+                                        info!(
+                                            "{} bars missing at the end of today for {}, re-requesting them",
+                                            missing_bars.len(),
+                                            contract.symbol
+                                        );
+                                        let historical_bars = self.request_historical_bars(
+                                            contract,
+                                            what_to_show,
+                                            missing_bars[0],
+                                        )
+                                        .await?;
+                                        self.store_stock_bars(
+                                            contract,
+                                            &historical_bars,
+                                            apply_batching,
+                                        )
+                                        .await?;
                                     }
                                     Err(e) => tracing::error!(
                                         "Expected to be able to select from market_data.historical_data: {}",
@@ -441,114 +837,14 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                 }
 
                 // Else, request all data required
-                let duration_in_sec =
-                    (Utc::now().with_timezone(&New_York) - earliest_datetime).num_seconds() as u64;
-
-                let duration = if duration_in_sec > 86400 {
-                    ibapi::market_data::historical::Duration::from_str(&format!(
-                        "{} D",
-                        (duration_in_sec / 60 / 60 / 24) as u32
-                    ))
-                    .expect("Expected Duration passed to historical_data method to be correct!")
-                } else {
-                    ibapi::market_data::historical::Duration::from_str(&format!(
-                        "{} S",
-                        duration_in_sec
-                    ))
-                    .expect("Expected Duration passed to historical_data method to be correct!")
-                };
-                info!("Requesting {} duration of data", duration.to_string());
-
-                let historical_data = self
-                    .client
-                    .historical_data(
-                        &contract,
-                        None,
-                        duration,
-                        ibapi::prelude::HistoricalBarSize::Min5,
-                        what_to_show,
-                        true,
-                    )
-                    .map_err(|e| format!(
-                        "Expected Historical Data Request to TWS to succeed for {}: {}",
-                        contract.symbol.clone(),
-                        e
-                    ))?;
-
-                for bar in &historical_data.bars {
-                    let bar = bar.clone();
-                    let historical_data_crud = self.historical_data_crud.clone();
-                    let stock = contract.symbol.clone();
-                    let primary_exchange = contract.primary_exchange.clone();
-                    tokio::spawn(async move {
-                        if apply_batching {
-                            if let Err(e) = historical_data_crud
-                                .batch_create_or_update(
-                                    &crate::database::models::HistoricalDataFullKeys{
-                                        stock: stock.clone(),
-                                        primary_exchange: primary_exchange.clone(),
-                                        time: DateTime::from_timestamp(
-                                            bar.date.unix_timestamp(),
-                                            bar.date.nanosecond() as u32,
-                                        )
-                                        .expect(
-                                            "Expected to be able to convert bar time to DateTime<Utc>",
-                                        ),
-                                        open: bar.open,
-                                        high: bar.high,
-                                        low: bar.low,
-                                        close: bar.close,
-                                        volume: 
-                                            Decimal::from_f64(bar.volume * 100.0)
-                                                .expect("Expected to be able to parse f64 to Decimal"),
-                                    },
-                                )
-                                .await
-                            {
-                                tracing::error!(
-                                    "Error occurred while upserting bars into historical data for {}: {}",
-                                    stock.clone(),
-                                    e
-                                )
-                            }
-                        } else {
-                            if let Err(e) = historical_data_crud
-                                .create_or_update(
-                                    &crate::database::models::HistoricalDataPrimaryKeys {
-                                        stock: stock.clone(),
-                                        primary_exchange: primary_exchange.clone(),
-                                        time: DateTime::from_timestamp(
-                                            bar.date.unix_timestamp(),
-                                            bar.date.nanosecond() as u32,
-                                        )
-                                        .expect(
-                                            "Expected to be able to convert bar time to DateTime<Utc>",
-                                        ),
-                                    },
-                                    &HistoricalDataUpdateKeys {
-                                        open: Some(bar.open),
-                                        high: Some(bar.high),
-                                        low: Some(bar.low),
-                                        close: Some(bar.close),
-                                        volume: Some(
-                                            Decimal::from_f64(bar.volume * 100.0)
-                                                .expect("Expected to be able to parse f64 to Decimal"),
-                                        ),
-                                    },
-                                )
-                                .await
-                            {
-                                tracing::error!(
-                                    "Error occurred while upserting bars into historical data for {}: {}",
-                                    stock.clone(),
-                                    e
-                                )
-                            }
-                        }
-                    });
-                }
+                let bars = self.request_historical_bars(
+                    contract,
+                    what_to_show,
+                    earliest_datetime.with_timezone(&Utc),
+                )
+                .await?;
 
-                Ok(())
+                self.store_stock_bars(contract, &bars, apply_batching).await
             }
             AssetType::Option => {
                 let historical_data_crud = self.historical_options_data_crud.clone();
@@ -559,11 +855,11 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                         contract.primary_exchange.clone(),
                         contract.last_trade_date_or_contract_month.clone(),
                         contract.strike.clone(),
-                        contract.multiplier.clone(),
+                        normalize_multiplier(&contract.multiplier),
                         OptionType::from_str(&contract.right)
                             .expect("Expected to be able to parse contract right"),
                         earliest_datetime.clone(),
-                        (required_num_bars - 39).max(0) as u32,
+                        required_num_bars_with_leeway,
                     )
                     .await;
 
@@ -579,16 +875,7 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                                 - chrono::Duration::minutes(5);
 
                             if last_bar_available_time
-                                > Utc::now()
-                                    .with_timezone(&New_York)
-                                    .with_hour(9)
-                                    .unwrap()
-                                    .with_minute(0)
-                                    .unwrap()
-                                    .with_second(0)
-                                    .unwrap()
-                                    .with_nanosecond(0)
-                                    .unwrap()
+                                > self.market_hours.open_of_day(Utc::now())
                             {
                                 match historical_data_crud
                                     .read_last_bar_of_contract(
@@ -596,102 +883,40 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                                         contract.primary_exchange.clone(),
                                         contract.last_trade_date_or_contract_month.clone(),
                                         contract.strike.clone(),
-                                        contract.multiplier.clone(),
+                                        normalize_multiplier(&contract.multiplier),
                                         OptionType::from_str(&contract.right)
                                             .expect("Expected to be able to parse contract right")
                                     )
                                     .await
                                 {
                                     Ok(last_bar) => {
-                                        if let Some(bar) = last_bar {
-                                            if bar.time == last_bar_available_time {
-                                                return Ok(());
-                                            }
-                                        }
-                                        let historical_data = self
-                                            .client
-                                            .historical_data(
-                                                &contract,
-                                                None,
-                                                ibapi::market_data::historical::Duration::from_str("1 D").expect("Expected to be able to parse 1 D for market data historical data"),
-                                                ibapi::prelude::HistoricalBarSize::Min5,
-                                                what_to_show,
-                                                true,
-                                            )
-                                            .expect(&format!(
-                                                "Expected Historical Data Request to TWS to succeed for {}",
-                                                contract.symbol.clone()
-                                        ));
-                                        for bar in &historical_data.bars {
-                                            let bar = bar.clone();
-                                            let historical_data_crud =
-                                                self.historical_options_data_crud.clone();
-                                            let cloned_contract = contract.clone();
-                                            tokio::spawn(async move {
-                                                if apply_batching {
-                                                    if let Err(e) = historical_data_crud
-                                                        .batch_create_or_update(&crate::database::models::HistoricalOptionsDataFullKeys{
-                                                            stock: cloned_contract.symbol.clone(),
-                                                            primary_exchange: cloned_contract.primary_exchange.clone(),
-                                                            expiry: cloned_contract.last_trade_date_or_contract_month.clone(),
-                                                            strike: cloned_contract.strike.clone(),
-                                                            multiplier: cloned_contract.multiplier.clone(),
-                                                            option_type: OptionType::from_str(&cloned_contract.right).expect("Expected to be able to parse contract right in update_at_least_n_days_data for option contract"),
-                                                            time: DateTime::from_timestamp(
-                                                                bar.date.unix_timestamp(),
-                                                                bar.date.nanosecond() as u32,
-                                                            )
-                                                            .expect("Expected to be able to convert bar time to DateTime<Utc>"),
-                                                            open: bar.open,
-                                                            high: bar.high,
-                                                            low: bar.low,
-                                                            close: bar.close,
-                                                            volume: Decimal::from_f64(
-                                                                bar.volume * 100.0
-                                                            ).expect("Expected to be able to parse f64 to Decimal")
-                                                        })
-                                                        .await
-                                                    {
-                                                        tracing::error!(
-                                                            "Error occurred while upserting bars into historical data for {}: {}",
-                                                            cloned_contract.symbol.clone(),
-                                                            e
-                                                        )
-                                                    }
-                                                } else {
-                                                    if let Err(e) = historical_data_crud
-                                                        .create_or_update(&crate::database::models::HistoricalOptionsDataPrimaryKeys {
-                                                            stock: cloned_contract.symbol.clone(),
-                                                            primary_exchange: cloned_contract.primary_exchange.clone(),
-                                                            expiry: cloned_contract.last_trade_date_or_contract_month.clone(),
-                                                            strike: cloned_contract.strike.clone(),
-                                                            multiplier: cloned_contract.multiplier.clone(),
-                                                            option_type: OptionType::from_str(&cloned_contract.right).expect("Expected to be able to parse contract right in update_at_least_n_days_data for option contract"),
-                                                            time: DateTime::from_timestamp(
-                                                                bar.date.unix_timestamp(),
-                                                                bar.date.nanosecond() as u32,
-                                                            )
-                                                            .expect("Expected to be able to convert bar time to DateTime<Utc>"),
-                                                        }, &crate::database::models::HistoricalOptionsDataUpdateKeys {
-                                                            open: Some(bar.open),
-                                                            high: Some(bar.high),
-                                                            low: Some(bar.low),
-                                                            close: Some(bar.close),
-                                                            volume: Some(Decimal::from_f64(
-                                                                bar.volume * 100.0
-                                                            ).expect("Expected to be able to parse f64 to Decimal")),
-                                                        })
-                                                        .await
-                                                    {
-                                                        tracing::error!(
-                                                            "Error occurred while upserting bars into historical data for {}: {}",
-                                                            cloned_contract.symbol.clone(),
-                                                            e
-                                                        )
-                                                    }
-                                                }
-                                            });
+                                        // any number of bars after the last stored one can be
+                                        // missing, not just the latest
+                                        let missing_bars = missing_tail_bars(
+                                            last_bar.map(|bar| bar.time),
+                                            last_bar_available_time.with_timezone(&Utc),
+                                            self.market_hours.open_of_day(Utc::now()).with_timezone(&Utc),
+                                        );
+                                        if missing_bars.is_empty() {
+                                            return Ok(());
                                         }
+                                        info!(
+                                            "{} bars missing at the end of today for {}, re-requesting them",
+                                            missing_bars.len(),
+                                            contract.symbol
+                                        );
+                                        let historical_bars = self.request_historical_bars(
+                                            contract,
+                                            what_to_show,
+                                            missing_bars[0],
+                                        )
+                                        .await?;
+                                        self.store_option_bars(
+                                            contract,
+                                            &historical_bars,
+                                            apply_batching,
+                                        )
+                                        .await?;
                                     }
                                     Err(e) => tracing::error!(
                                         "Expected to be able to select from market_data.historical_data: {}",
@@ -705,115 +930,138 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                 }
 
                 // Else, request all data required
-                let duration_in_sec =
-                    (Utc::now().with_timezone(&New_York) - earliest_datetime).num_seconds() as u64;
-                let duration = if duration_in_sec > 86400 {
-                    ibapi::market_data::historical::Duration::from_str(&format!(
-                        "{} D",
-                        (duration_in_sec / 60 / 60 / 24) as u32
-                    ))
-                    .expect("Expected Duration passed to historical_data method to be correct!")
-                } else {
-                    ibapi::market_data::historical::Duration::from_str(&format!(
-                        "{} S",
-                        duration_in_sec
-                    ))
-                    .expect("Expected Duration passed to historical_data method to be correct!")
-                };
+                let bars = self.request_historical_bars(
+                    contract,
+                    what_to_show,
+                    earliest_datetime.with_timezone(&Utc),
+                )
+                .await?;
 
-                let historical_data = self
-                    .client
-                    .historical_data(
-                        &contract,
-                        None,
-                        duration,
-                        ibapi::prelude::HistoricalBarSize::Min5,
-                        what_to_show,
-                        true,
-                    )
-                    .map_err(|e| format!(
-                        "Expected Historical Data Request to TWS to succeed for {}: {}",
-                        contract.symbol.clone(),
-                        e
-                    ))?;
-
-                for bar in &historical_data.bars {
-                    let bar = bar.clone();
-                    let historical_data_crud = self.historical_options_data_crud.clone();
-                    let cloned_contract = contract.clone();
-                    tokio::spawn(async move {
-                        if apply_batching {
-                            if let Err(e) = historical_data_crud
-                                .batch_create_or_update(&crate::database::models::HistoricalOptionsDataFullKeys {
-                                    stock: cloned_contract.symbol.clone(),
-                                    primary_exchange: cloned_contract.primary_exchange.clone(),
-                                    expiry: cloned_contract.last_trade_date_or_contract_month.clone(),
-                                    strike: cloned_contract.strike.clone(),
-                                    multiplier: cloned_contract.multiplier.clone(),
-                                    option_type: OptionType::from_str(&cloned_contract.right).expect("Expected to be able to parse contract right in update_at_least_n_days_data for option contract"),
-                                    time: DateTime::from_timestamp(
-                                        bar.date.unix_timestamp(),
-                                        bar.date.nanosecond() as u32,
-                                    )
-                                    .expect("Expected to be able to convert bar time to DateTime<Utc>"),
-                                    open: bar.open,
-                                    high: bar.high,
-                                    low: bar.low,
-                                    close: bar.close,
-                                    volume: Decimal::from_f64(
-                                        bar.volume * 100.0
-                                    ).expect("Expected to be able to parse f64 to Decimal")
-                                })
-                                .await
-                            {
-                                tracing::error!(
-                                    "Error occurred while upserting bars into historical data for {}: {}",
-                                    cloned_contract.symbol.clone(),
-                                    e
-                                )
-                            }
-                        } else {
-                            if let Err(e) = historical_data_crud
-                                .create_or_update(&crate::database::models::HistoricalOptionsDataPrimaryKeys {
-                                    stock: cloned_contract.symbol.clone(),
-                                    primary_exchange: cloned_contract.primary_exchange.clone(),
-                                    expiry: cloned_contract.last_trade_date_or_contract_month.clone(),
-                                    strike: cloned_contract.strike.clone(),
-                                    multiplier: cloned_contract.multiplier.clone(),
-                                    option_type: OptionType::from_str(&cloned_contract.right).expect("Expected to be able to parse contract right in update_at_least_n_days_data for option contract"),
-                                    time: DateTime::from_timestamp(
-                                        bar.date.unix_timestamp(),
-                                        bar.date.nanosecond() as u32,
-                                    )
-                                    .expect("Expected to be able to convert bar time to DateTime<Utc>"),
-                                }, &crate::database::models::HistoricalOptionsDataUpdateKeys {
-                                    open: Some(bar.open),
-                                    high: Some(bar.high),
-                                    low: Some(bar.low),
-                                    close: Some(bar.close),
-                                    volume: Some(Decimal::from_f64(
-                                        bar.volume * 100.0
-                                    ).expect("Expected to be able to parse f64 to Decimal")),
-                                })
-                                .await
-                            {
-                                tracing::error!(
-                                    "Error occurred while upserting bars into historical data for {}: {}",
-                                    cloned_contract.symbol.clone(),
-                                    e
-                                )
-                            }
-                        }
-                    });
-                }
-                Ok(())
+                self.store_option_bars(contract, &bars, apply_batching).await
+            }
+        }
+    }
+
+    /// Stores the historical bars of a stock fetched by fetch_at_least_n_days_data - through the
+    /// batch writer with apply_batching, otherwise upserted together with upsert_many rather than
+    /// one statement per bar
+    async fn store_stock_bars(
+        &self,
+        contract: &Contract,
+        bars: &[ibapi::market_data::historical::Bar],
+        apply_batching: bool,
+    ) -> Result<(), String> {
+        if apply_batching {
+            for bar in bars {
+                self.historical_data_crud
+                    .batch_create_or_update(&HistoricalDataFullKeys {
+                        stock: contract.symbol.clone(),
+                        primary_exchange: contract.primary_exchange.clone(),
+                        time: historical_bar_time(bar),
+                        open: bar.open,
+                        high: bar.high,
+                        low: bar.low,
+                        close: bar.close,
+                        volume: bar_volume(bar.volume, &contract.symbol),
+                    })
+                    .await?;
             }
+            return Ok(());
         }
+        let rows: Vec<HistoricalData> = bars
+            .iter()
+            .map(|bar| HistoricalData {
+                stock: contract.symbol.clone(),
+                primary_exchange: contract.primary_exchange.clone(),
+                time: historical_bar_time(bar),
+                open: Some(bar.open),
+                high: Some(bar.high),
+                low: Some(bar.low),
+                close: Some(bar.close),
+                volume: Some(bar_volume(bar.volume, &contract.symbol)),
+            })
+            .collect();
+        self.historical_data_crud
+            .upsert_many(&rows)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Error occurred while upserting bars into historical data for {}: {}",
+                    contract.symbol, e
+                )
+            })?;
+        Ok(())
+    }
+
+    /// store_stock_bars for the historical bars of an option contract
+    async fn store_option_bars(
+        &self,
+        contract: &Contract,
+        bars: &[ibapi::market_data::historical::Bar],
+        apply_batching: bool,
+    ) -> Result<(), String> {
+        let multiplier = normalize_multiplier(&contract.multiplier);
+        let option_type = OptionType::from_str(&contract.right).expect(
+            "Expected to be able to parse contract right in update_at_least_n_days_data for option contract",
+        );
+        if apply_batching {
+            for bar in bars {
+                self.historical_options_data_crud
+                    .batch_create_or_update(&HistoricalOptionsDataFullKeys {
+                        stock: contract.symbol.clone(),
+                        primary_exchange: contract.primary_exchange.clone(),
+                        expiry: contract.last_trade_date_or_contract_month.clone(),
+                        strike: contract.strike,
+                        multiplier: multiplier.clone(),
+                        option_type: option_type.clone(),
+                        time: historical_bar_time(bar),
+                        open: bar.open,
+                        high: bar.high,
+                        low: bar.low,
+                        close: bar.close,
+                        volume: bar_volume(bar.volume, &contract.symbol),
+                    })
+                    .await?;
+            }
+            return Ok(());
+        }
+        let rows: Vec<HistoricalOptionsData> = bars
+            .iter()
+            .map(|bar| HistoricalOptionsData {
+                stock: contract.symbol.clone(),
+                primary_exchange: contract.primary_exchange.clone(),
+                expiry: contract.last_trade_date_or_contract_month.clone(),
+                strike: contract.strike,
+                multiplier: multiplier.clone(),
+                option_type: option_type.clone(),
+                time: historical_bar_time(bar),
+                open: Some(bar.open),
+                high: Some(bar.high),
+                low: Some(bar.low),
+                close: Some(bar.close),
+                volume: Some(bar_volume(bar.volume, &contract.symbol)),
+            })
+            .collect();
+        self.historical_options_data_crud
+            .upsert_many(&rows)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Error occurred while upserting bars into historical data for {}: {}",
+                    contract.symbol, e
+                )
+            })?;
+        Ok(())
     }
 
     /// Opens a channel to asynchronously accept (Bar, Contract) data updates and perform upserts
     /// - for each timestep (in minutes) u subscribe to, the timestep will be triggered for each
-    /// timing past 9:30am for the strategy
+    ///   timing past 9:30am for the strategy, with the 5 minute bars since the last trigger
+    ///   aggregated into a single timestep minute bar (see TimestepAggregator)
+    /// - a strategy with an active_window only gets the bars starting within it
+    /// - a strategy only gets the bars of a contract once the contract has its
+    ///   required_history_days stored (see HistoryGate) - e.g. a symbol added mid-session isn't
+    ///   traded until its warm up has caught up
     /// - accordingly, this handles subscribe_to_data() updates such that the strategy
     /// on_bar_update() function ONLY has to handle updates to the TargetPosition in the database
     /// - Ideally, the order_engine is initialised with client id 0, consolidator with any other
@@ -829,19 +1077,20 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         let subscriptions = self.subscriptions.clone();
         let order_engine = order_engine.clone();
         let client = client.clone();
+        let market_hours = self.market_hours;
+        let instrument_market_hours = self.instrument_market_hours.clone();
+        let history_check = self.history_check.clone();
+        let flattened_strategies = self.flattened_strategies.clone();
         tokio::spawn(async move {
+            // (Stock, Primary Exchange, timestep) -> timestep bar being built
+            let mut aggregators: HashMap<(String, String, u32), TimestepAggregator> =
+                HashMap::new();
             while let Some(update) = receiver.recv().await {
-                let (contract, bar_time) = update;
-
-                let bar_ny = bar_time.with_timezone(&New_York);
-                let market_open = bar_ny
-                    .date_naive()
-                    .and_time(NaiveTime::from_hms_opt(9, 30, 0).unwrap());
-                let elapsed = bar_ny.naive_local() - market_open;
-                let elapsed_min = elapsed.num_minutes() as u32;
-                if elapsed.num_minutes() < 0 {
-                    continue;
-                }
+                let (contract, bar) = update;
+                let contract_market_hours = instrument_market_hours
+                    .get(&contract.security_type.to_string())
+                    .copied()
+                    .unwrap_or(market_hours);
 
                 let subscription = subscriptions.lock().expect(
                     "Expected Subscription guard not to be poisoned in begin_bar_listening",
@@ -850,35 +1099,147 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                     .get(&(contract.symbol.clone(), contract.primary_exchange.clone()))
                     .expect("Expected Subscription for contract to be updated in hashmap!");
                 for (timestep, strategies) in contract_subscription.iter() {
-                    if elapsed_min % timestep == 0 {
+                    let timestep_bar = aggregators
+                        .entry((
+                            contract.symbol.clone(),
+                            contract.primary_exchange.clone(),
+                            *timestep,
+                        ))
+                        .or_insert_with(|| TimestepAggregator::new(*timestep))
+                        .push(bar);
+                    if let Some(timestep_bar) = timestep_bar {
                         for strategy in strategies.iter() {
+                            if !market_hours.is_within_window(strategy.active_window(), timestep_bar.0) {
+                                tracing::debug!(
+                                    "Skipping bar at {} for strategy {} outside its active window",
+                                    timestep_bar.0,
+                                    strategy.get_name()
+                                );
+                                continue;
+                            }
+                            if flattened_strategies.is_flattened(&strategy.get_name(), timestep_bar.0) {
+                                tracing::debug!(
+                                    "Skipping bar at {} for strategy {} flattened for the close",
+                                    timestep_bar.0,
+                                    strategy.get_name()
+                                );
+                                continue;
+                            }
                             tracing::info!("Updating for strategy: {}", strategy.get_name());
-                            let order_engine = order_engine.clone();
-                            let strategy = strategy.clone();
-                            let contract = contract.clone();
-                            let client = client.clone();
-                            tokio::spawn(async move {
-                                let bar_update_res = strategy.on_bar_update(&contract).await;
-                                if let Ok(updated) = bar_update_res {
-                                    if !updated.0 {
-                                        return;
-                                    }
-                                }
+                            tokio::spawn(act_on_timestep_bar(
+                                strategy.clone(),
+                                contract.clone(),
+                                *timestep,
+                                timestep_bar,
+                                history_check.with_market_hours(contract_market_hours),
+                                order_engine.clone(),
+                                client.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+        });
+    }
 
-                                let asset_type = AssetType::from_str(contract.security_type.clone());
-                                order_engine.place_orders_for_strategy(
-                                    strategy,
+    /// Spawns a task calling strategy.on_scheduled_event for each of its scheduled_events (in New
+    /// York time) once the clock reaches it, placing orders for its contracts as for a bar update
+    /// - each event fires once per session - abort the returned handle when the session ends
+    /// - a flatten_at_close strategy also has its targets zeroed flatten_minutes_before_close
+    ///   before the close, then orders placed for all of its contracts - its bar updates are
+    ///   skipped from then until the next session
+    /// - None if the strategy schedules no events
+    pub fn schedule_events(
+        &self,
+        strategy: T,
+        order_engine: Arc<OrderEngine>,
+        client: Arc<Client>,
+        clock: Arc<dyn Clock + Send + Sync>,
+    ) -> Option<JoinHandle<()>> {
+        let mut schedule = EventSchedule::new(
+            New_York,
+            strategy_schedule(&strategy, &self.market_hours, self.flatten_minutes_before_close),
+        );
+        if schedule.is_empty() {
+            return None;
+        }
+        let pool = self.pool.clone();
+        let flattened_strategies = self.flattened_strategies.clone();
+        let poll_interval = Duration::from_secs(60);
+        Some(tokio::spawn(async move {
+            loop {
+                for event in schedule.due(&clock) {
+                    tracing::info!(
+                        "Running scheduled event {} for strategy: {}",
+                        event.name,
+                        strategy.get_name()
+                    );
+                    let event_res = if event.name == FLATTEN_AT_CLOSE {
+                        // gated first, so a bar update can't race the zeroed targets
+                        flattened_strategies.flatten(&strategy.get_name(), clock.now());
+                        flatten_targets(pool.clone(), &strategy.get_name())
+                            .await
+                            .map(|_| (true, true))
+                    } else {
+                        strategy.on_scheduled_event(&event).await
+                    };
+                    match event_res {
+                        Ok((true, ignore_contract_for_strategy)) => {
+                            for contract in strategy.get_contracts() {
+                                order_engine.place_orders_for_bar_update(
+                                    strategy.clone(),
                                     contract,
-                                    client,
-                                    asset_type,
-                                    bar_update_res.is_ok_and(|res| res.1)
+                                    client.clone(),
+                                    ignore_contract_for_strategy,
                                 );
-                            });
+                            }
                         }
+                        Ok(_) => {}
+                        Err(e) => tracing::error!(
+                            "Error running scheduled event {} for {}: {}",
+                            event.name,
+                            strategy.get_name(),
+                            e
+                        ),
                     }
                 }
+                let until_next = schedule
+                    .duration_until_next(&clock)
+                    .and_then(|until_next| until_next.to_std().ok())
+                    .unwrap_or(poll_interval);
+                tokio::time::sleep(until_next.min(poll_interval)).await;
             }
-        });
+        }))
+    }
+
+    /// Warms up strategy, waits for open_gate, then subscribes it to every contract in its
+    /// get_contracts at its get_timestep - so a strategy trading several symbols needs no per
+    /// contract wiring
+    /// - NOTE: as with subscribe_to_data, only call this after begin_bar_listening
+    pub async fn subscribe_strategy(
+        self: &Arc<Self>,
+        strategy: T,
+        open_gate: &SessionOpenGate,
+        clock: &impl Clock,
+    ) -> Result<(), String> {
+        let start = std::time::Instant::now();
+        strategy.warm_up_data(self.clone()).await?;
+        info!(
+            "{} took {:?} to warm up fully",
+            strategy.get_name(),
+            start.elapsed()
+        );
+        open_gate.wait_for_open(clock).await;
+
+        for (contract, timestep) in strategy_subscriptions(&strategy) {
+            self.subscribe_to_data(
+                strategy.clone(),
+                contract,
+                timestep,
+                RealtimeWhatToShow::Trades,
+            );
+        }
+        Ok(())
     }
 
     /// Opens a channel, spawns an async task to await bar updates,
@@ -932,14 +1293,23 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         info!("Initiating subscription to market data for new contract in a new blocking thread.");
 
         // Highest Granularity - 5 min
-        let collected_bars_arc = Arc::new(Mutex::new(VecDeque::<Bar>::new()));
+        let collected_bars_arc = Arc::new(Mutex::new(VecDeque::<FiveSecBar>::new()));
         {
             let mut live_data = self.live_data.lock().unwrap();
             live_data.insert((contract.symbol.clone(), contract.primary_exchange.clone()), collected_bars_arc.clone());
         }
+        let latest_price = LatestPrice::new();
+        {
+            let mut latest_prices = self.latest_prices.lock().unwrap();
+            latest_prices.insert((contract.symbol.clone(), contract.primary_exchange.clone()), latest_price.clone());
+        }
 
         // let (bar_update_sender)
-        let (bar_sender, mut rcx) = channel::<(DateTime<Utc>, f64, f64, f64, f64, f64)>(100);
+        let (bar_sender, mut rcx) = channel::<ConsolidatedBar>(100);
+        {
+            let mut bar_senders = self.bar_senders.lock().expect("Expected to be able to acquire lock for bar_senders in Consolidator.subscribe_to_data");
+            bar_senders.insert((contract.symbol.clone(), contract.primary_exchange.clone()), bar_sender.clone());
+        }
         let contract_update_sender = {
             self.contract_update_sender
                 .lock()
@@ -969,62 +1339,99 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
             }
         });
 
-        let cloned_collected_bars_arc = collected_bars_arc.clone();
+        // the thread reports back to the supervisor when the real time bars request fails, and is
+        // restarted until it has failed too often
         let client = self.client.clone();
-        let contract = contract.clone();
-        let cloned_bar_sender = bar_sender.clone();
-        thread::spawn(move || {
-            match client.realtime_bars(
-                &contract,
-                ibapi::prelude::RealtimeBarSize::Sec5,
-                data_type,
-                true,
-            ) {
-                Ok(mut subscription) => loop {
-                    match subscription.next_timeout(Duration::from_secs(20)) {
-                        Some(bar) => {
-                            Self::on_new_5sec_bar(
-                                cloned_collected_bars_arc.clone(),
-                                bar,
-                                cloned_bar_sender.clone(),
-                            );
-                        }
-                        None => {
-                            if let Some(e) = subscription.error() {
-                                if format!("{}", e).contains("no security definition has been found") {
-                                    tracing::warn!("Real time bars for {} cancelled", contract.symbol);
-                                    break;
-                                }
-                            }
-                            tracing::warn!(
-                                "timed out waiting for next bar for contract: {} - Trying a re-subscription",
-                                contract.symbol.clone()
-                            );
-                            subscription.cancel();
-                            subscription = match client.realtime_bars(
-                                &contract,
-                                ibapi::prelude::RealtimeBarSize::Sec5,
-                                data_type,
-                                true,
-                            ) {
-                                Ok(sub) => sub,
-                                Err(e) => {
-                                    tracing::error!(
-                                        "Real time request for {} failed:\n{}",
-                                        contract.symbol,
-                                        e
-                                    );
-                                    break;
-                                }
-                            }
+        let max_retained_bars = self.max_retained_bars;
+        let last_bar_time = self.last_bar_time.clone();
+        tokio::spawn(async move {
+            let name = format!("Real time bars for {}", contract.symbol);
+            let supervised = supervise(&name, SupervisorOptions::default(), |reporter| {
+                let client = client.clone();
+                let contract = contract.clone();
+                let collected_bars_arc = collected_bars_arc.clone();
+                let latest_price = latest_price.clone();
+                let last_bar_time = last_bar_time.clone();
+                let bar_sender = bar_sender.clone();
+                thread::spawn(move || {
+                    let status = Self::stream_realtime_bars(client, contract, data_type, collected_bars_arc, max_retained_bars, latest_price, last_bar_time, bar_sender);
+                    reporter.report(status);
+                });
+            })
+            .await;
+            if let Err(e) = supervised {
+                tracing::error!("No longer receiving market data: {}", e);
+            }
+        });
+    }
+
+    /// Blocks on the real time bars subscription for contract, passing every 5 second bar on
+    /// - re-subscribes if no bar is received for 20 seconds
+    /// - returns how the subscription finished for the supervisor to act on
+    fn stream_realtime_bars(
+        client: Arc<Client>,
+        contract: Contract,
+        data_type: RealtimeWhatToShow,
+        collected_bars_arc: Arc<Mutex<VecDeque<FiveSecBar>>>,
+        max_retained_bars: usize,
+        latest_price: LatestPrice,
+        last_bar_time: LastBarTime,
+        bar_sender: Sender<ConsolidatedBar>,
+    ) -> ThreadStatus {
+        let mut subscription = match client.realtime_bars(
+            &contract,
+            ibapi::prelude::RealtimeBarSize::Sec5,
+            data_type,
+            true,
+        ) {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                return ThreadStatus::Fatal(format!("Real time request for {} failed:\n{}", contract.symbol, e));
+            }
+        };
+        loop {
+            match subscription.next_timeout(Duration::from_secs(20)) {
+                Some(bar) => {
+                    // set before the bar waits on the lock of the bars collected so far
+                    latest_price.set(bar.close);
+                    last_bar_time.record(bar.date.unix_timestamp());
+                    Self::on_new_5sec_bar(
+                        collected_bars_arc.clone(),
+                        max_retained_bars,
+                        contract.symbol.clone(),
+                        bar,
+                        bar_sender.clone(),
+                    );
+                }
+                None => {
+                    if let Some(e) = subscription.error()
+                        && format!("{}", e).contains("no security definition has been found")
+                    {
+                        tracing::warn!("Real time bars for {} cancelled", contract.symbol);
+                        return ThreadStatus::Ended;
+                    }
+                    tracing::warn!(
+                        "timed out waiting for next bar for contract: {} - Trying a re-subscription",
+                        contract.symbol.clone()
+                    );
+                    subscription.cancel();
+                    subscription = match client.realtime_bars(
+                        &contract,
+                        ibapi::prelude::RealtimeBarSize::Sec5,
+                        data_type,
+                        true,
+                    ) {
+                        Ok(sub) => sub,
+                        Err(e) => {
+                            return ThreadStatus::Fatal(format!(
+                                "Real time request for {} failed:\n{}",
+                                contract.symbol, e
+                            ));
                         }
                     }
-                },
-                Err(e) => {
-                    tracing::error!("Real time request for {} failed:\n{}", contract.symbol, e)
                 }
             }
-        });
+        }
     }
 
     /// Spawns a new OS thread to process the 5 second bars from the subscription
@@ -1032,68 +1439,37 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
     /// separate OS kernel thread which doesn't have a tokio runtime
     /// - Note: multithreading should be fine because each bar for each contract is separated by 5
     /// sec times which should be sufficient time for this whole check to complete
+    /// - at most max_retained_bars are kept, the oldest are evicted (and logged) beyond it
+    /// - buckets are consolidated by drain_completed_buckets, checked at startup by
+    ///   aggregation_self_test when AGGREGATION_SELF_TEST is set
     fn on_new_5sec_bar(
-        collected_bars_arc: Arc<Mutex<VecDeque<Bar>>>,
+        collected_bars_arc: Arc<Mutex<VecDeque<FiveSecBar>>>,
+        max_retained_bars: usize,
+        symbol: String,
         bar: Bar,
-        bar_sender: Sender<(DateTime<Utc>, f64, f64, f64, f64, f64)>,
+        bar_sender: Sender<ConsolidatedBar>,
     ) {
         thread::spawn(move || {
             let mut collected_bars = collected_bars_arc
                 .lock()
                 .expect("Did not expect lock for collected_bars_arc to be poisoned");
 
-            collected_bars.push_back(bar.clone());
-            let latest_bar_timestamp = &bar.date.unix_timestamp();
-            let latest_bar_no = latest_bar_timestamp - (latest_bar_timestamp % 300);
-            let first_bar_timestamp = collected_bars.front().unwrap().date.unix_timestamp();
-            let mut first_bar_no = first_bar_timestamp - (first_bar_timestamp % 300);
-
-            if latest_bar_no == first_bar_no {
-                return;
-            }
-
-            while first_bar_no != latest_bar_no {
-                let bar_to_be_built = first_bar_no;
-
-                // Process first bar first
-                let inner_first_bar = &collected_bars.pop_front().unwrap();
-                let (open, mut high, mut low, mut close, mut volume) = (
-                    inner_first_bar.open,
-                    inner_first_bar.high,
-                    inner_first_bar.low,
-                    inner_first_bar.close,
-                    inner_first_bar.volume,
+            let bar = (bar.date.unix_timestamp(), bar.open, bar.high, bar.low, bar.close, bar.volume);
+            let evicted = push_capped(&mut collected_bars, bar, max_retained_bars);
+            if evicted > 0 {
+                tracing::warn!(
+                    "Evicted {} oldest 5 sec bars of {}, over the {} retained",
+                    evicted,
+                    symbol,
+                    max_retained_bars
                 );
+            }
 
-                // Process rest of bars
-                let inner_first_bar = &collected_bars.front().unwrap();
-                let mut inner_first_bar_no = inner_first_bar.date.unix_timestamp()
-                    - (inner_first_bar.date.unix_timestamp() % 300);
-                while inner_first_bar_no == bar_to_be_built {
-                    let inner_first_bar = &collected_bars.pop_front().unwrap();
-                    high = f64::max(high, inner_first_bar.high);
-                    low = f64::min(low, inner_first_bar.low);
-                    close = inner_first_bar.close;
-                    volume += inner_first_bar.volume;
-
-                    let inner_first_bar = &collected_bars.front().unwrap();
-                    inner_first_bar_no = inner_first_bar.date.unix_timestamp()
-                        - (inner_first_bar.date.unix_timestamp() % 300);
-                }
-
+            for new_5min_bar in drain_completed_buckets(&mut collected_bars) {
                 // This stays blocking since across time we don't really want to muddy the waters
-                if let Err(e ) = bar_sender.blocking_send((
-                    Utc.timestamp_opt(bar_to_be_built, 0).unwrap(),
-                    open,
-                    high,
-                    low,
-                    close,
-                    volume,
-                )) {
+                if let Err(e) = bar_sender.blocking_send(new_5min_bar) {
                     tracing::error!("Error occurred while trying to send new 5 min bar: {}", e);
                 };
-
-                first_bar_no = inner_first_bar_no;
             }
         });
     }
@@ -1104,7 +1480,7 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
     async fn on_bar_update(
         historical_data_crud: HistoricalDataCRUD,
         historical_options_data_crud: HistoricalOptionsDataCRUD,
-        sender: Sender<(Contract, DateTime<chrono::Utc>)>,
+        sender: Sender<(Contract, ConsolidatedBar)>,
         contract: Contract,
         time: DateTime<chrono::Utc>,
         open: f64,
@@ -1120,7 +1496,7 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                     primary_exchange: contract.primary_exchange.clone(),
                     expiry: contract.last_trade_date_or_contract_month.clone(),
                     strike: contract.strike.clone(),
-                    multiplier: contract.multiplier.clone(),
+                    multiplier: normalize_multiplier(&contract.multiplier),
                     option_type: OptionType::from_str(&contract.right)
                         .unwrap_or_else(|e| panic!("{}", e)),
                     time: time,
@@ -1129,14 +1505,13 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                     high: Some(high),
                     low: Some(low),
                     close: Some(close),
-                    volume: Some(Decimal::from_f64(volume * 100.0)
-                        .expect("Expected to be able to parse f64 to Decimal")),
+                    volume: Some(bar_volume(volume, &contract.symbol)),
                 })
                 .await
             {
                 Ok(_) => {
                     if let Err(e) = sender
-                        .send((contract.clone(), time + chrono::Duration::minutes(5)))
+                        .send((contract.clone(), (time, open, high, low, close, volume)))
                         .await
                     {
                         tracing::error!(
@@ -1165,14 +1540,19 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
                     high: Some(high),
                     low: Some(low),
                     close: Some(close),
-                    volume: Some(Decimal::from_f64(volume * 100.0)
-                        .expect("Expected to be able to parse f64 to Decimal")),
+                    volume: Some(bar_volume(volume, &contract.symbol)),
                 })
                 .await
             {
                 Ok(_) => {
+                    historical_data_crud.record_close(
+                        &contract.symbol,
+                        &contract.primary_exchange,
+                        time,
+                        close,
+                    );
                     if let Err(e) = sender
-                        .send((contract.clone(), time + chrono::Duration::minutes(5)))
+                        .send((contract.clone(), (time, open, high, low, close, volume)))
                         .await
                     {
                         tracing::error!(
@@ -1192,3 +1572,12 @@ impl<'a, T: StrategyExecutor + 'static> Consolidator<T> {
         }
     }
 }
+
+impl<T: StrategyExecutor> Drop for Consolidator<T> {
+    /// Last resort for a consolidator dropped without shutdown - the batch writers still drain
+    /// and flush their queues, but only if the runtime outlives them
+    fn drop(&mut self) {
+        self.historical_data_crud.request_close();
+        self.historical_options_data_crud.request_close();
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::TradingAppState,
+    market_data::market_hours::{Clock, MarketHours},
+};
+
+/// Default for how old the newest bar may get during market hours before market data counts as
+/// stalled - real time bars arrive every 5 seconds
+pub const DEFAULT_MARKET_DATA_STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Time of the newest 5 second bar received across every live contract
+/// - clones share the same time, each real time bars thread records into its own clone
+#[derive(Debug, Clone)]
+pub struct LastBarTime(Arc<AtomicI64>);
+
+impl Default for LastBarTime {
+    fn default() -> Self {
+        Self(Arc::new(AtomicI64::new(i64::MIN)))
+    }
+}
+
+impl LastBarTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the later of the time recorded so far and unix_timestamp (in seconds)
+    pub fn record(&self, unix_timestamp: i64) {
+        self.0.fetch_max(unix_timestamp, Ordering::AcqRel);
+    }
+
+    /// None until the first bar arrives
+    pub fn get(&self) -> Option<DateTime<Utc>> {
+        match self.0.load(Ordering::Acquire) {
+            i64::MIN => None,
+            unix_timestamp => DateTime::from_timestamp(unix_timestamp, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketDataStatus {
+    Ok,
+    Degraded,
+}
+
+/// Whether market data is flowing, as served on GET /health/market_data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketDataFreshness {
+    pub status: MarketDataStatus,
+    pub market_open: bool,
+    pub last_bar_time: Option<DateTime<Utc>>,
+    pub stale_after_secs: u64,
+}
+
+/// Degraded if the market is open and no bar has arrived, or the newest one is older than
+/// stale_after - outside market hours no bars are expected so it is always ok
+pub fn market_data_freshness(
+    market_hours: &MarketHours,
+    clock: &impl Clock,
+    last_bar_time: Option<DateTime<Utc>>,
+    stale_after: Duration,
+) -> MarketDataFreshness {
+    let market_open = market_hours.is_market_open_now(clock);
+    let stale = match last_bar_time {
+        Some(last_bar_time) => (clock.now() - last_bar_time)
+            .to_std()
+            .is_ok_and(|age| age > stale_after),
+        None => true,
+    };
+    MarketDataFreshness {
+        status: if market_open && stale {
+            MarketDataStatus::Degraded
+        } else {
+            MarketDataStatus::Ok
+        },
+        market_open,
+        last_bar_time,
+        stale_after_secs: stale_after.as_secs(),
+    }
+}
+
+/// Everything the freshness of the session's market data is checked against
+#[derive(Clone)]
+pub struct MarketDataHealth {
+    pub last_bar_time: LastBarTime,
+    pub market_hours: MarketHours,
+    pub stale_after: Duration,
+    pub clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl MarketDataHealth {
+    /// Checks the bars recorded in the session's last_bar_time against its configured market
+    /// hours and threshold
+    pub fn from_state(state: &TradingAppState) -> Self {
+        Self {
+            last_bar_time: state.last_bar_time.clone(),
+            market_hours: state.config.open_gate.market_hours,
+            stale_after: state.config.market_data_stale_after,
+            clock: state.clock.clone(),
+        }
+    }
+
+    pub fn check(&self) -> MarketDataFreshness {
+        market_data_freshness(
+            &self.market_hours,
+            &self.clock,
+            self.last_bar_time.get(),
+            self.stale_after,
+        )
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use ibapi::prelude::Contract;
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        models::{AssetType, OptionType, normalize_multiplier},
+        models_crud::{
+            historical_data::{HistoricalDataCRUD, get_specific_historical_data_crud},
+            historical_options_data::{
+                HistoricalOptionsDataCRUD, get_specific_historical_options_data_crud,
+            },
+        },
+    },
+    market_data::market_hours::MarketHours,
+    unlock,
+};
+
+/// (contract, days) - a contract as identified by history_key and the days of history asked of it
+pub type HistoryKey = (String, u32);
+
+/// HistoryKey of days of contract's history
+/// - keyed on the full contract, so a stock and the options on it (or two options on the same
+///   underlying) each need their own history
+pub fn history_key(contract: &Contract, days: u32) -> HistoryKey {
+    (
+        format!(
+            "{:?}|{}|{}|{}|{}|{}|{}",
+            contract.security_type,
+            contract.symbol,
+            contract.primary_exchange,
+            contract.last_trade_date_or_contract_month,
+            contract.strike,
+            contract.right,
+            normalize_multiplier(&contract.multiplier)
+        ),
+        days,
+    )
+}
+
+/// Contracts known to have the days of history a strategy needs ingested, so bars of a symbol
+/// added mid-session aren't traded off a stub of history
+/// - once met a requirement stays met for the session, so the history is only read until then
+/// - clones share the same contracts
+#[derive(Debug, Clone, Default)]
+pub struct HistoryGate(Arc<Mutex<HashSet<HistoryKey>>>);
+
+impl HistoryGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ready(&self, key: &HistoryKey) -> bool {
+        self.0
+            .lock()
+            .map(|ready| key.1 == 0 || ready.contains(key))
+            .unwrap_or(false)
+    }
+
+    /// Whether the bar of key's contract may be acted on - has_history (whether the ingested
+    /// history is enough) is only awaited while the requirement hasn't been met yet
+    /// - a requirement of 0 days is always met
+    pub async fn check<F, Fut>(&self, key: HistoryKey, has_history: F) -> Result<bool, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<bool, String>>,
+    {
+        if self.is_ready(&key) {
+            return Ok(true);
+        }
+        if !has_history().await? {
+            return Ok(false);
+        }
+        unlock!(self.0, "ready", "HistoryGate.check").insert(key);
+        Ok(true)
+    }
+}
+
+/// HistoryGate over the stored bars, counted over market_hours
+/// - clones share the same gate
+#[derive(Debug, Clone)]
+pub struct HistoryCheck {
+    gate: HistoryGate,
+    historical_data_crud: HistoricalDataCRUD,
+    historical_options_data_crud: HistoricalOptionsDataCRUD,
+    market_hours: MarketHours,
+}
+
+impl HistoryCheck {
+    pub fn new(pool: PgPool, market_hours: MarketHours) -> Self {
+        Self {
+            gate: HistoryGate::new(),
+            historical_data_crud: get_specific_historical_data_crud(pool.clone()),
+            historical_options_data_crud: get_specific_historical_options_data_crud(pool),
+            market_hours,
+        }
+    }
+
+    /// Same gate, with the history counted over market_hours - e.g. for an instrument not
+    /// trading the equity session
+    pub fn with_market_hours(&self, market_hours: MarketHours) -> Self {
+        Self {
+            market_hours,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the days of history update_at_least_n_days_data would fetch for contract are
+    /// stored (with the same leeway) - see HistoryGate::check
+    pub async fn has_required_history(
+        &self,
+        contract: &Contract,
+        days: u32,
+    ) -> Result<bool, String> {
+        self.gate
+            .check(history_key(contract, days), || {
+                self.read_has_required_history(contract, days)
+            })
+            .await
+    }
+
+    async fn read_has_required_history(
+        &self,
+        contract: &Contract,
+        days: u32,
+    ) -> Result<bool, String> {
+        let (earliest_datetime, required_num_bars_with_leeway, _) =
+            self.market_hours.history_requirement(days, Utc::now());
+        match AssetType::from_str(contract.security_type.clone()) {
+            AssetType::Stock => {
+                self.historical_data_crud
+                    .has_at_least_n_rows_since(
+                        contract.symbol.clone(),
+                        contract.primary_exchange.clone(),
+                        earliest_datetime,
+                        required_num_bars_with_leeway,
+                    )
+                    .await
+            }
+            AssetType::Option => {
+                self.historical_options_data_crud
+                    .has_at_least_n_rows_since(
+                        contract.symbol.clone(),
+                        contract.primary_exchange.clone(),
+                        contract.last_trade_date_or_contract_month.clone(),
+                        contract.strike,
+                        normalize_multiplier(&contract.multiplier),
+                        OptionType::from_str(&contract.right)?,
+                        earliest_datetime,
+                        required_num_bars_with_leeway,
+                    )
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, hash::Hash, sync::Mutex};
+
+use tokio::sync::watch;
+
+use crate::unlock;
+
+type RequestResult = Result<(), String>;
+
+/// Deduplicates concurrent requests by key - while a request for a key is running, later callers
+/// for the same key wait for its result instead of running their own
+/// - the key is only held while the request runs, a call after it finishes runs the request again
+pub struct InFlightRequests<K> {
+    in_flight: Mutex<HashMap<K, watch::Receiver<Option<RequestResult>>>>,
+}
+
+impl<K: Eq + Hash + Clone> Default for InFlightRequests<K> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Frees the key once the running request finishes or is dropped, so a cancelled request doesn't
+/// leave later callers waiting on a result that never comes
+struct InFlightGuard<'a, K: Eq + Hash> {
+    in_flight: &'a Mutex<HashMap<K, watch::Receiver<Option<RequestResult>>>>,
+    key: K,
+}
+
+impl<K: Eq + Hash> Drop for InFlightGuard<'_, K> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> InFlightRequests<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs request for key, or waits for the result of the request already running for key
+    pub async fn run_once<F, Fut>(&self, key: K, request: F) -> RequestResult
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = RequestResult>,
+    {
+        let result_sender = {
+            let mut in_flight = unlock!(self.in_flight, "in_flight", "InFlightRequests.run_once");
+            match in_flight.get(&key) {
+                Some(result_receiver) => Err(result_receiver.clone()),
+                None => {
+                    let (result_sender, result_receiver) = watch::channel(None);
+                    in_flight.insert(key.clone(), result_receiver);
+                    Ok(result_sender)
+                }
+            }
+        };
+
+        match result_sender {
+            Ok(result_sender) => {
+                let guard = InFlightGuard {
+                    in_flight: &self.in_flight,
+                    key,
+                };
+                let result = request().await;
+                drop(guard);
+                result_sender.send_replace(Some(result.clone()));
+                result
+            }
+            Err(mut result_receiver) => {
+                let result = result_receiver
+                    .wait_for(|result| result.is_some())
+                    .await
+                    .map_err(|_| "In flight request was dropped before finishing".to_string())?;
+                result
+                    .clone()
+                    .expect("Expected in flight result to be set after wait_for")
+            }
+        }
+    }
+}
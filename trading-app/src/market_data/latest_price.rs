@@ -0,0 +1,33 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+/// Close of a live contract's newest 5 second bar, kept outside the lock its bars are collected
+/// behind - reading it never waits on a bar being consolidated
+/// - clones share the same price, one is handed to the real time bars thread and one kept for
+///   readers
+#[derive(Debug, Clone)]
+pub struct LatestPrice(Arc<AtomicU64>);
+
+impl Default for LatestPrice {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU64::new(f64::NAN.to_bits())))
+    }
+}
+
+impl LatestPrice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, price: f64) {
+        self.0.store(price.to_bits(), Ordering::Release);
+    }
+
+    /// None until the first bar arrives
+    pub fn get(&self) -> Option<f64> {
+        let price = f64::from_bits(self.0.load(Ordering::Acquire));
+        (!price.is_nan()).then_some(price)
+    }
+}
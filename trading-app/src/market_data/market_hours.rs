@@ -0,0 +1,393 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Bound,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::{America::New_York, Tz};
+use nyse_holiday_cal::HolidayCal;
+
+/// Source of the current time - swapped out for a fixed clock in tests
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+impl<C: Clock + ?Sized> Clock for std::sync::Arc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+static NYSE_CALENDAR: LazyLock<TradingCalendar> = LazyLock::new(|| TradingCalendar::new(New_York));
+
+/// NYSE trading days, worked out from nyse_holiday_cal a year at a time and cached - hot loops
+/// (history_requirement, waiting for the open) look dates up instead of redoing the holiday rules
+/// for every date
+/// - dates are local to timezone, see trading_date
+#[derive(Debug)]
+pub struct TradingCalendar {
+    pub timezone: Tz,
+    years: Mutex<HashMap<i32, Arc<BTreeSet<NaiveDate>>>>,
+}
+
+impl TradingCalendar {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            timezone,
+            years: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Calendar shared by every MarketHours by default, so the cache is filled once per process
+    pub fn nyse() -> &'static Self {
+        &NYSE_CALENDAR
+    }
+
+    /// Trading days of year, computed on first use
+    fn trading_days_in(&self, year: i32) -> Arc<BTreeSet<NaiveDate>> {
+        let mut years = self
+            .years
+            .lock()
+            .expect("Expected TradingCalendar cache not to be poisoned");
+        years
+            .entry(year)
+            .or_insert_with(|| {
+                let first_day = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+                Arc::new(
+                    first_day
+                        .iter_days()
+                        .take_while(|date| date.year() == year)
+                        .filter(|date| {
+                            date.is_busday()
+                                .expect("Expected to be able to check if date is a business day")
+                        })
+                        .collect(),
+                )
+            })
+            .clone()
+    }
+
+    /// Date of time in the calendar's timezone
+    pub fn trading_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        time.with_timezone(&self.timezone).date_naive()
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.trading_days_in(date.year()).contains(&date)
+    }
+
+    /// First trading day after date
+    pub fn next_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut year = date.year();
+        loop {
+            let trading_days = self.trading_days_in(year);
+            if let Some(day) = trading_days
+                .range((Bound::Excluded(date), Bound::Unbounded))
+                .next()
+            {
+                return *day;
+            }
+            year += 1;
+        }
+    }
+
+    /// Last trading day before date
+    pub fn prev_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut year = date.year();
+        loop {
+            if let Some(day) = self.trading_days_in(year).range(..date).next_back() {
+                return *day;
+            }
+            year -= 1;
+        }
+    }
+
+    /// Trading days from start to end, both included, in order - empty if end is before start
+    pub fn trading_days_between(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        if end < start {
+            return vec![];
+        }
+        (start.year()..=end.year())
+            .flat_map(|year| {
+                self.trading_days_in(year)
+                    .range(start..=end)
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Regular trading session for an exchange, in the exchange's local timezone
+/// - a close at or before the open is on the next day, for sessions trading through midnight
+///   (futures, FX)
+#[derive(Debug, Clone, Copy)]
+pub struct MarketHours {
+    pub timezone: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    /// Trading days the session runs on
+    pub calendar: &'static TradingCalendar,
+}
+
+impl Default for MarketHours {
+    /// NYSE regular session: 09:30 - 16:00 New York time
+    fn default() -> Self {
+        Self {
+            timezone: New_York,
+            open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            calendar: TradingCalendar::nyse(),
+        }
+    }
+}
+
+impl MarketHours {
+    /// CME Globex futures session: 18:00 - 17:00 New York time the next day
+    pub fn futures() -> Self {
+        Self {
+            timezone: New_York,
+            open: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            calendar: TradingCalendar::nyse(),
+        }
+    }
+
+    /// IDEALPRO FX session: around the clock from 17:00 New York time
+    pub fn forex() -> Self {
+        Self {
+            timezone: New_York,
+            open: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            calendar: TradingCalendar::nyse(),
+        }
+    }
+
+    /// Whether the session closes on the day after it opens
+    fn wraps_midnight(&self) -> bool {
+        self.close <= self.open
+    }
+
+    fn session_minutes(&self) -> i64 {
+        let minutes = (self.close - self.open).num_minutes();
+        if self.wraps_midnight() {
+            minutes + 24 * 60
+        } else {
+            minutes
+        }
+    }
+
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.calendar.is_trading_day(date)
+    }
+
+    fn open_on(&self, date: NaiveDate) -> DateTime<Tz> {
+        self.timezone
+            .with_ymd_and_hms(
+                date.year(),
+                date.month(),
+                date.day(),
+                self.open.hour(),
+                self.open.minute(),
+                0,
+            )
+            .unwrap()
+    }
+
+    /// Open of the session trading on date - the evening before for a session through midnight
+    pub fn session_open_on(&self, date: NaiveDate) -> DateTime<Tz> {
+        if self.wraps_midnight() {
+            self.open_on(date.pred_opt().unwrap())
+        } else {
+            self.open_on(date)
+        }
+    }
+
+    /// Session open on the exchange-local date of time
+    pub fn open_of_day(&self, time: DateTime<Utc>) -> DateTime<Tz> {
+        self.open_on(time.with_timezone(&self.timezone).date_naive())
+    }
+
+    /// Bars of bar_minutes in a full session - 78 five minute bars for NYSE, 276 for futures and
+    /// 288 for FX
+    pub fn bars_per_session(&self, bar_minutes: u32) -> i64 {
+        self.session_minutes() / bar_minutes as i64
+    }
+
+    /// Half of bars_per_session - 39 five minute bars for NYSE
+    pub fn half_session_bars(&self, bar_minutes: u32) -> i64 {
+        self.bars_per_session(bar_minutes) / 2
+    }
+
+    /// Bars of bar_minutes completed between the open and time on time's date - 0 before the
+    /// open and a full session after the close
+    /// - a session through midnight is counted from the previous day's open until its own opens
+    pub fn bars_since_open(&self, time: DateTime<Utc>, bar_minutes: u32) -> i64 {
+        let mut open = self.open_of_day(time).with_timezone(&Utc);
+        if self.wraps_midnight() && open > time {
+            open -= chrono::Duration::days(1);
+        }
+        let since_open = (time - open).num_minutes();
+        (since_open / bar_minutes as i64).clamp(0, self.bars_per_session(bar_minutes))
+    }
+
+    /// Open of the earliest of the last days trading days (today included if it trades) and the
+    /// 5 minute bars those sessions should have stored by now, less half a session of leeway
+    /// (see half_session_bars), plus whether today is a trading day
+    /// - only the bars since the open are counted for today
+    pub fn history_requirement(&self, days: u32, now: DateTime<Utc>) -> (DateTime<Tz>, u32, bool) {
+        let mut required_num_bars = 0;
+        let mut days_counter = 0;
+        let mut earliest_datetime = now.with_timezone(&self.timezone);
+        let naive_date_tdy = now.with_timezone(&self.timezone).date_naive();
+        let mut is_trading_day_tdy = false;
+        let tomorrow = naive_date_tdy.succ_opt().unwrap();
+        let trading_days_back =
+            std::iter::successors(Some(self.calendar.prev_trading_day(tomorrow)), |day| {
+                Some(self.calendar.prev_trading_day(*day))
+            });
+        for day in trading_days_back {
+            days_counter += 1;
+            if days_counter == 1 && naive_date_tdy == day {
+                is_trading_day_tdy = true;
+                required_num_bars += self.bars_since_open(now, 5);
+            }
+            if days_counter == days {
+                earliest_datetime = self.session_open_on(day);
+                break;
+            }
+
+            required_num_bars += self.bars_per_session(5);
+        }
+        let required_num_bars_with_leeway =
+            (required_num_bars - self.half_session_bars(5)).max(0) as u32;
+        (
+            earliest_datetime,
+            required_num_bars_with_leeway,
+            is_trading_day_tdy,
+        )
+    }
+
+    /// Whether time falls in the [start, end) window of the exchange-local day - always true
+    /// without a window
+    pub fn is_within_window(
+        &self,
+        window: Option<(NaiveTime, NaiveTime)>,
+        time: DateTime<Utc>,
+    ) -> bool {
+        let Some((start, end)) = window else {
+            return true;
+        };
+        let time_of_day = time.with_timezone(&self.timezone).time();
+        time_of_day >= start && time_of_day < end
+    }
+
+    /// Date of the session time falls in, None outside [open, close) - the inverse of
+    /// session_open_on, so a session through midnight opened in the evening trades on the next
+    /// day, whether or not that day trades
+    fn session_date_at(&self, time: DateTime<Tz>) -> Option<NaiveDate> {
+        let date = time.date_naive();
+        let time_of_day = time.time();
+        if !self.wraps_midnight() {
+            return (time_of_day >= self.open && time_of_day < self.close).then_some(date);
+        }
+        if time_of_day >= self.open {
+            date.succ_opt()
+        } else if time_of_day < self.close {
+            Some(date)
+        } else {
+            None
+        }
+    }
+
+    /// True only within [open, close) of a session trading on a trading day - pre-open on a
+    /// trading day is NOT open, nor is a session through midnight opening into a weekend / holiday
+    pub fn is_market_open_now(&self, clock: &impl Clock) -> bool {
+        let now = clock.now().with_timezone(&self.timezone);
+        self.session_date_at(now)
+            .is_some_and(|date| self.is_trading_day(date))
+    }
+
+    /// Time until the next regular session open, None if the market is currently open
+    pub fn duration_until_next_open(&self, clock: &impl Clock) -> Option<chrono::Duration> {
+        if self.is_market_open_now(clock) {
+            return None;
+        }
+        let now = clock.now().with_timezone(&self.timezone);
+        let mut date = now.date_naive();
+        if !self.is_trading_day(date) {
+            date = self.calendar.next_trading_day(date);
+        }
+        while self.session_open_on(date) <= now {
+            date = self.calendar.next_trading_day(date);
+        }
+        Some(self.session_open_on(date) - now)
+    }
+}
+
+/// Whether the NYSE regular session is open at the clock's current time
+pub fn is_market_open_now(clock: &impl Clock) -> bool {
+    MarketHours::default().is_market_open_now(clock)
+}
+
+/// Holds strategies back after warmup until the regular session opens, so the first bar they
+/// process is the first real session bar instead of a pre-open one
+#[derive(Debug, Clone, Copy)]
+pub struct SessionOpenGate {
+    pub enabled: bool,
+    pub market_hours: MarketHours,
+    /// Longest single sleep before the clock is checked again
+    pub poll_interval: std::time::Duration,
+}
+
+impl Default for SessionOpenGate {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            market_hours: MarketHours::default(),
+            poll_interval: std::time::Duration::from_secs(60),
+        }
+    }
+}
+
+impl SessionOpenGate {
+    /// Reads WAIT_FOR_OPEN_AFTER_WARMUP - unset keeps the gate on, "false" subscribes straight
+    /// after warmup
+    pub fn from_env() -> Result<Self, String> {
+        let enabled = match std::env::var("WAIT_FOR_OPEN_AFTER_WARMUP") {
+            Ok(flag) => flag
+                .trim()
+                .parse::<bool>()
+                .map_err(|e| format!("WAIT_FOR_OPEN_AFTER_WARMUP must be true or false: {}", e))?,
+            Err(_) => true,
+        };
+        Ok(Self {
+            enabled,
+            ..Self::default()
+        })
+    }
+
+    /// Returns once the market is open according to clock (straight away if disabled)
+    pub async fn wait_for_open(&self, clock: &impl Clock) {
+        if !self.enabled {
+            return;
+        }
+        while let Some(until_open) = self.market_hours.duration_until_next_open(clock) {
+            let until_open = until_open.to_std().unwrap_or(std::time::Duration::ZERO);
+            tracing::info!(
+                "Waiting {} seconds for the session to open before subscribing",
+                until_open.as_secs()
+            );
+            tokio::time::sleep(until_open.min(self.poll_interval)).await;
+        }
+    }
+}
@@ -1 +1,10 @@
+pub mod aggregation_self_test;
+pub mod backfill;
 pub mod consolidator;
+pub mod freshness;
+pub mod history_gate;
+pub mod in_flight;
+pub mod latest_price;
+pub mod market_hours;
+pub mod pacing;
+pub mod scheduler;
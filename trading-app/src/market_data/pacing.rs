@@ -0,0 +1,123 @@
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::time::sleep;
+
+/// Token bucket - holds up to capacity tokens, refilled continuously at refill_per_sec
+/// - each request takes one token, waiting for it to be refilled if the bucket is empty
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    // (tokens available, as of instant)
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// Starts full
+    pub fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            capacity,
+            refill_per_sec,
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Takes a token as of now and returns how long the caller has to wait before sending
+    /// - tokens can go negative: each waiting caller reserves its own slot, so a burst is spread
+    ///   out at 1 / refill_per_sec instead of all waking at once
+    pub fn reserve_at(&self, now: Instant) -> Duration {
+        let mut state = self
+            .state
+            .lock()
+            .expect("Expected to be able to acquire lock for TokenBucket.state");
+        let (tokens, last_refill) = *state;
+        let elapsed = now.saturating_duration_since(last_refill).as_secs_f64();
+        let tokens = (tokens + elapsed * self.refill_per_sec).min(self.capacity) - 1.0;
+        *state = (tokens, now.max(last_refill));
+
+        if tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-tokens / self.refill_per_sec)
+        }
+    }
+
+    /// Waits until a token is available without holding up the runtime's worker thread
+    pub async fn acquire(&self) {
+        let wait = self.reserve_at(Instant::now());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// Blocks the current thread until a token is available - for callers outside the runtime
+    pub fn blocking_acquire(&self) {
+        let wait = self.reserve_at(Instant::now());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Rates the Consolidator paces its IBKR market data requests at
+#[derive(Debug, Clone, Copy)]
+pub struct PacingConfig {
+    /// Messages of any kind sent to TWS per second
+    pub messages_per_sec: f64,
+    /// Historical data requests that may be sent back to back
+    pub historical_burst: f64,
+    /// Historical data requests per second once the burst is used up
+    pub historical_per_sec: f64,
+}
+
+impl Default for PacingConfig {
+    /// IBKR documented limits
+    /// - 50 messages / second
+    /// - historical data: at most 6 requests within 2 seconds and 60 within any 10 minutes - a
+    ///   burst of 6 refilled at 54 / 600s keeps any 10 minute window at or below 60
+    fn default() -> Self {
+        Self {
+            messages_per_sec: 50.0,
+            historical_burst: 6.0,
+            historical_per_sec: 54.0 / 600.0,
+        }
+    }
+}
+
+/// Throttles requests to TWS so they stay under IBKR's pacing limits instead of failing with
+/// pacing violations
+#[derive(Debug)]
+pub struct MarketDataPacer {
+    messages: TokenBucket,
+    historical: TokenBucket,
+}
+
+impl MarketDataPacer {
+    pub fn new(config: PacingConfig) -> Self {
+        Self {
+            messages: TokenBucket::new(config.messages_per_sec, config.messages_per_sec),
+            historical: TokenBucket::new(config.historical_burst, config.historical_per_sec),
+        }
+    }
+
+    /// Blocks until a market data (tick) request can be sent
+    pub fn before_market_data(&self) {
+        self.messages.blocking_acquire();
+    }
+
+    /// Waits until a historical data request can be sent
+    pub async fn before_historical_data(&self) {
+        self.historical.acquire().await;
+        self.messages.acquire().await;
+    }
+}
+
+impl Default for MarketDataPacer {
+    fn default() -> Self {
+        Self::new(PacingConfig::default())
+    }
+}
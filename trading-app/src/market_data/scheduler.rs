@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+
+use crate::market_data::market_hours::Clock;
+
+/// Time of day, in the exchange's timezone, a strategy wants on_scheduled_event called at
+/// - e.g. ScheduledEvent::new("flatten", 15, 50) to flatten 10 minutes before the close
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    /// Passed back to the strategy to tell its events apart - unique per strategy
+    pub name: String,
+    pub time: NaiveTime,
+}
+
+impl ScheduledEvent {
+    pub fn new(name: impl Into<String>, hour: u32, minute: u32) -> Self {
+        Self {
+            name: name.into(),
+            time: NaiveTime::from_hms_opt(hour, minute, 0)
+                .expect("Expected scheduled event time to be a valid time of day"),
+        }
+    }
+}
+
+/// A strategy's scheduled events and the exchange-local date each last fired on, so every event
+/// fires once per session
+#[derive(Debug, Clone)]
+pub struct EventSchedule {
+    timezone: Tz,
+    events: Vec<ScheduledEvent>,
+    // event name -> date it last fired
+    fired_on: HashMap<String, NaiveDate>,
+}
+
+impl EventSchedule {
+    pub fn new(timezone: Tz, events: Vec<ScheduledEvent>) -> Self {
+        Self {
+            timezone,
+            events,
+            fired_on: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events whose time has been reached today and have not fired yet today - they are marked as
+    /// fired, so each is only returned once per day
+    /// - an event whose time already passed when the schedule started fires on the first call
+    pub fn due(&mut self, clock: &impl Clock) -> Vec<ScheduledEvent> {
+        let now = clock.now().with_timezone(&self.timezone);
+        let today = now.date_naive();
+        let mut due = Vec::new();
+        for event in &self.events {
+            if now.time() >= event.time && self.fired_on.get(&event.name) != Some(&today) {
+                self.fired_on.insert(event.name.clone(), today);
+                due.push(event.clone());
+            }
+        }
+        due
+    }
+
+    /// Time until the next event due later today, None if every event has fired today
+    pub fn duration_until_next(&self, clock: &impl Clock) -> Option<chrono::Duration> {
+        let now = clock.now().with_timezone(&self.timezone);
+        self.events
+            .iter()
+            .filter(|event| event.time > now.time())
+            .map(|event| event.time - now.time())
+            .min()
+    }
+}
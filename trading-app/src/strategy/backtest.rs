@@ -0,0 +1,275 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, prelude::FromRow};
+
+use crate::database::models::OptionType;
+
+/// Option a trade was made in - None on Trade for the stock itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionContract {
+    pub expiry: String,
+    pub strike: f64,
+    pub multiplier: String,
+    pub option_type: OptionType,
+}
+
+/// A synthetic transaction generated by a backtest, or the fills of one live order aggregated
+/// into a single trade at their average price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub time: DateTime<Utc>,
+    pub stock: String,
+    #[serde(default)]
+    pub option: Option<OptionContract>,
+    /// Signed, positive for a buy
+    pub quantity: f64,
+    pub price: f64,
+}
+
+impl Trade {
+    fn same_contract(&self, other: &Trade) -> bool {
+        self.stock == other.stock && self.option == other.option
+    }
+}
+
+/// How far a live trade may be from a backtest trade and still be the same trade
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComparisonTolerance {
+    /// Largest gap between the backtest and the live trade time - one 5 minute bar by default
+    pub time_secs: i64,
+    /// Largest per unit price difference not reported as a divergence
+    pub price: f64,
+}
+
+impl Default for ComparisonTolerance {
+    fn default() -> Self {
+        Self {
+            time_secs: 300,
+            price: 0.01,
+        }
+    }
+}
+
+/// Where the live trades of a strategy went a different way from its backtest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum TradeDivergence {
+    /// Backtest trade with no live trade for it
+    Missing {
+        backtest: Trade,
+    },
+    /// Live trade with no backtest trade for it
+    Extra {
+        live: Trade,
+    },
+    QuantityDifference {
+        backtest: Trade,
+        live: Trade,
+    },
+    /// live.price - backtest.price, beyond the price tolerance
+    PriceDifference {
+        backtest: Trade,
+        live: Trade,
+        difference: f64,
+    },
+}
+
+/// Backtest trades of a strategy lined up against its live transactions over [from, to)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestComparison {
+    pub strategy: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Backtest trades a live trade was found for, whether or not they diverge in size / price
+    pub matched: usize,
+    pub divergences: Vec<TradeDivergence>,
+}
+
+/// Pairs each backtest trade with the earliest unpaired live trade in the same contract and
+/// direction within the time tolerance, returning the number paired and every divergence
+/// - paired trades are still reported if their quantity or price differs
+pub fn compare_trades(
+    backtest: &[Trade],
+    live: &[Trade],
+    tolerance: &ComparisonTolerance,
+) -> (usize, Vec<TradeDivergence>) {
+    let mut backtest = backtest.to_vec();
+    backtest.sort_by_key(|trade| trade.time);
+    let mut live = live.to_vec();
+    live.sort_by_key(|trade| trade.time);
+    let max_gap = Duration::seconds(tolerance.time_secs);
+
+    let mut paired = vec![false; live.len()];
+    let mut matched = 0;
+    let mut divergences = Vec::new();
+    for backtest_trade in backtest {
+        let live_index = live.iter().zip(&paired).position(|(live_trade, paired)| {
+            !paired
+                && live_trade.same_contract(&backtest_trade)
+                && live_trade.quantity.signum() == backtest_trade.quantity.signum()
+                && (live_trade.time - backtest_trade.time).abs() <= max_gap
+        });
+        let Some(live_index) = live_index else {
+            divergences.push(TradeDivergence::Missing {
+                backtest: backtest_trade,
+            });
+            continue;
+        };
+        paired[live_index] = true;
+        matched += 1;
+
+        let live_trade = &live[live_index];
+        if (live_trade.quantity - backtest_trade.quantity).abs() > 1e-9 {
+            divergences.push(TradeDivergence::QuantityDifference {
+                backtest: backtest_trade.clone(),
+                live: live_trade.clone(),
+            });
+        }
+        let difference = live_trade.price - backtest_trade.price;
+        if difference.abs() > tolerance.price {
+            divergences.push(TradeDivergence::PriceDifference {
+                backtest: backtest_trade,
+                live: live_trade.clone(),
+                difference,
+            });
+        }
+    }
+
+    for (live_trade, _) in live.into_iter().zip(paired).filter(|(_, paired)| !paired) {
+        divergences.push(TradeDivergence::Extra { live: live_trade });
+    }
+    (matched, divergences)
+}
+
+#[derive(Debug, FromRow)]
+struct LiveOrder {
+    time: DateTime<Utc>,
+    stock: String,
+    quantity: f64,
+    price: f64,
+}
+
+#[derive(Debug, FromRow)]
+struct LiveOptionOrder {
+    time: DateTime<Utc>,
+    stock: String,
+    expiry: String,
+    strike: f64,
+    multiplier: String,
+    option_type: OptionType,
+    quantity: f64,
+    price: f64,
+}
+
+/// Live stock and option transactions of strategy in [from, to), one Trade per order
+async fn read_live_trades(
+    pool: &PgPool,
+    strategy: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Trade>, String> {
+    let stock_orders = sqlx::query_as::<_, LiveOrder>(
+        r#"
+        SELECT
+            MIN(time) AS time,
+            stock,
+            SUM(quantity) AS quantity,
+            SUM(price * quantity) / SUM(quantity) AS price
+        FROM trading.stock_transactions
+        WHERE strategy = $1 AND time >= $2 AND time < $3
+        GROUP BY order_perm_id, stock
+        HAVING SUM(quantity) <> 0
+        "#,
+    )
+    .bind(strategy)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        format!(
+            "Error when reading live stock trades of {}: {}",
+            strategy, e
+        )
+    })?;
+
+    let option_orders = sqlx::query_as::<_, LiveOptionOrder>(
+        r#"
+        SELECT
+            MIN(time) AS time,
+            stock,
+            expiry,
+            strike,
+            multiplier,
+            option_type,
+            SUM(quantity) AS quantity,
+            SUM(price * quantity) / SUM(quantity) AS price
+        FROM trading.option_transactions
+        WHERE strategy = $1 AND time >= $2 AND time < $3
+        GROUP BY order_perm_id, stock, expiry, strike, multiplier, option_type
+        HAVING SUM(quantity) <> 0
+        "#,
+    )
+    .bind(strategy)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        format!(
+            "Error when reading live option trades of {}: {}",
+            strategy, e
+        )
+    })?;
+
+    let mut trades: Vec<Trade> = stock_orders
+        .into_iter()
+        .map(|order| Trade {
+            time: order.time,
+            stock: order.stock,
+            option: None,
+            quantity: order.quantity,
+            price: order.price,
+        })
+        .collect();
+    trades.extend(option_orders.into_iter().map(|order| Trade {
+        time: order.time,
+        stock: order.stock,
+        option: Some(OptionContract {
+            expiry: order.expiry,
+            strike: order.strike,
+            multiplier: order.multiplier,
+            option_type: order.option_type,
+        }),
+        quantity: order.quantity,
+        price: order.price,
+    }));
+    Ok(trades)
+}
+
+/// Compares the trades a backtest of strategy generated against its live transactions over
+/// [from, to) - backtest trades outside the window are ignored
+pub async fn compare_backtest_vs_live(
+    pool: PgPool,
+    strategy: String,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    backtest_trades: &[Trade],
+    tolerance: &ComparisonTolerance,
+) -> Result<BacktestComparison, String> {
+    let live_trades = read_live_trades(&pool, &strategy, from, to).await?;
+    let backtest_trades: Vec<Trade> = backtest_trades
+        .iter()
+        .filter(|trade| trade.time >= from && trade.time < to)
+        .cloned()
+        .collect();
+    let (matched, divergences) = compare_trades(&backtest_trades, &live_trades, tolerance);
+    Ok(BacktestComparison {
+        strategy,
+        from,
+        to,
+        matched,
+        divergences,
+    })
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::America::New_York;
+use sqlx::PgPool;
+
+use crate::{
+    database::models_crud::{
+        target_option_positions::get_specific_target_option_positions_crud,
+        target_stock_positions::get_specific_target_stock_positions_crud,
+    },
+    market_data::{market_hours::MarketHours, scheduler::ScheduledEvent},
+    strategy::strategy::StrategyExecutor,
+};
+
+/// Name of the scheduled event flatten_at_close strategies have their targets zeroed on
+pub const FLATTEN_AT_CLOSE: &str = "flatten_at_close";
+
+/// How often wait_until_flat checks the strategies' positions
+const FLAT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Event minutes_before_close before the close of market_hours
+pub fn flatten_at_close_event(
+    market_hours: &MarketHours,
+    minutes_before_close: u32,
+) -> ScheduledEvent {
+    ScheduledEvent {
+        name: FLATTEN_AT_CLOSE.to_string(),
+        time: market_hours.close - chrono::Duration::minutes(minutes_before_close as i64),
+    }
+}
+
+/// Events schedule_events runs for strategy - its own scheduled_events, plus the flatten at close
+/// event if the strategy has to be flat overnight
+pub fn strategy_schedule<T: StrategyExecutor>(
+    strategy: &T,
+    market_hours: &MarketHours,
+    minutes_before_close: u32,
+) -> Vec<ScheduledEvent> {
+    let mut events = strategy.scheduled_events();
+    if strategy.flatten_at_close() {
+        events.push(flatten_at_close_event(market_hours, minutes_before_close));
+    }
+    events
+}
+
+/// Sets every stock and option target of strategy to 0 - including positions it has no target
+/// for - so the next reconcile closes out all of its positions - returns the number of targets
+/// zeroed
+pub async fn flatten_targets(pool: PgPool, strategy: &str) -> Result<u64, String> {
+    let stocks = get_specific_target_stock_positions_crud(pool.clone())
+        .zero_targets_for_strat(strategy)
+        .await?;
+    let options = get_specific_target_option_positions_crud(pool)
+        .zero_targets_for_strat(strategy)
+        .await?;
+    Ok(stocks + options)
+}
+
+/// Strategies flattened at the close, with the (New York) session date they were flattened on
+/// - their bar updates are skipped for the rest of that session, so they don't write new targets
+///   and reopen positions before the close
+/// - clones share the same set
+#[derive(Debug, Clone, Default)]
+pub struct FlattenedStrategies(Arc<Mutex<HashMap<String, NaiveDate>>>);
+
+impl FlattenedStrategies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks strategy flattened for the session of at
+    pub fn flatten(&self, strategy: &str, at: DateTime<Utc>) {
+        self.0
+            .lock()
+            .expect("Expected FlattenedStrategies not to be poisoned")
+            .insert(strategy.to_string(), session_date(at));
+    }
+
+    /// Whether strategy was flattened in the session of at - false again from the next session
+    pub fn is_flattened(&self, strategy: &str, at: DateTime<Utc>) -> bool {
+        self.0
+            .lock()
+            .expect("Expected FlattenedStrategies not to be poisoned")
+            .get(strategy)
+            .is_some_and(|flattened_on| session_date(at) <= *flattened_on)
+    }
+}
+
+fn session_date(at: DateTime<Utc>) -> NaiveDate {
+    at.with_timezone(&New_York).date_naive()
+}
+
+/// Number of open stock / option positions the strategies still hold
+async fn open_positions(pool: &PgPool, strategies: &[String]) -> Result<i64, String> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM trading.current_stock_positions
+                WHERE strategy = ANY($1) AND quantity != 0)
+            + (SELECT COUNT(*) FROM trading.current_option_positions
+                WHERE strategy = ANY($1) AND quantity != 0)
+        "#,
+    )
+    .bind(strategies)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Error counting open positions of {:?}: {}", strategies, e))
+}
+
+/// Waits until every one of strategies is flat, for at most timeout
+/// - called at the close so the flattening orders' fills are booked before the gateway stops
+/// - Err with the positions still open if they aren't all closed by then
+pub async fn wait_until_flat(
+    pool: &PgPool,
+    strategies: &[String],
+    timeout: Duration,
+) -> Result<(), String> {
+    if strategies.is_empty() {
+        return Ok(());
+    }
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let open = open_positions(pool, strategies).await?;
+        if open == 0 {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(format!(
+                "{} positions of {:?} still open {} seconds after the close",
+                open,
+                strategies,
+                timeout.as_secs()
+            ));
+        }
+        tokio::time::sleep(FLAT_POLL_INTERVAL).await;
+    }
+}
@@ -1 +1,4 @@
+pub mod backtest;
+pub mod flatten;
+pub mod params;
 pub mod strategy;
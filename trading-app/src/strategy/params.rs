@@ -0,0 +1,81 @@
+use serde_json::json;
+pub use shared::params::validate_params;
+use sqlx::PgPool;
+
+use crate::{
+    database::{
+        crud::CRUDTrait,
+        models::{StrategyParamsFullKeys, StrategyParamsPrimaryKeys, StrategyParamsUpdateKeys},
+        models_crud::strategy_params::get_strategy_params_crud,
+    },
+    strategy::strategy::StrategyExecutor,
+};
+
+/// Registers the strategy's params schema and hands its stored params to load_params
+/// - first run for a strategy creates the row with no params, i.e. compiled in defaults are kept
+/// - params that no longer match the schema are NOT loaded
+pub async fn load_strategy_params<T: StrategyExecutor>(
+    pool: PgPool,
+    strategy: &T,
+) -> Result<(), String> {
+    let strategy_params_crud = get_strategy_params_crud(pool);
+    let pk = StrategyParamsPrimaryKeys {
+        strategy: strategy.get_name(),
+    };
+    let params_schema = strategy.params_schema();
+
+    let stored_params = strategy_params_crud.read(&pk).await.map_err(|e| {
+        format!(
+            "Failed to read params for strategy {}: {}",
+            strategy.get_name(),
+            e
+        )
+    })?;
+    let params = match stored_params {
+        Some(stored_params) => {
+            strategy_params_crud
+                .update(
+                    &pk,
+                    &StrategyParamsUpdateKeys {
+                        params: None,
+                        params_schema: Some(params_schema.clone()),
+                    },
+                )
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to register params schema for strategy {}: {}",
+                        strategy.get_name(),
+                        e
+                    )
+                })?;
+            stored_params.params
+        }
+        None => {
+            strategy_params_crud
+                .create_or_ignore(&StrategyParamsFullKeys {
+                    strategy: strategy.get_name(),
+                    params: json!({}),
+                    params_schema: params_schema.clone(),
+                })
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to create params for strategy {}: {}",
+                        strategy.get_name(),
+                        e
+                    )
+                })?;
+            json!({})
+        }
+    };
+
+    validate_params(&params_schema, &params).map_err(|e| {
+        format!(
+            "Stored params for strategy {} are invalid: {}",
+            strategy.get_name(),
+            e
+        )
+    })?;
+    strategy.load_params(params)
+}
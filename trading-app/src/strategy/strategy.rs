@@ -1,9 +1,16 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::NaiveTime;
 use ibapi::prelude::Contract;
 
-use crate::market_data::consolidator::Consolidator;
+use crate::{
+    execution::fills::ExecutionSummary,
+    market_data::{
+        consolidator::{ConsolidatedBar, Consolidator},
+        scheduler::ScheduledEvent,
+    },
+};
 
 #[async_trait]
 pub trait StrategyExecutor: Ord + PartialOrd + Eq + PartialEq + Clone + Send + Sync {
@@ -15,16 +22,82 @@ pub trait StrategyExecutor: Ord + PartialOrd + Eq + PartialEq + Clone + Send + S
     /// Should update all relevant TargetPositions for the strategy
     /// - assume always that data in DB is fully updated
     async fn on_bar_update(&self, contract: &Contract) -> Result<(bool, bool), String>;
+    /// Called once a bar of the timestep (in minutes) the strategy subscribed with completes, with
+    /// that bar's OHLCV built from the 5 minute bars in it
+    /// - defaults to on_bar_update for strategies that read their bars from the DB
+    async fn on_timestep_bar(
+        &self,
+        contract: &Contract,
+        _timestep: u32,
+        _bar: &ConsolidatedBar,
+    ) -> Result<(bool, bool), String> {
+        self.on_bar_update(contract).await
+    }
+    /// Times of day, in the exchange timezone, on_scheduled_event should be called at once per
+    /// session - for actions not tied to a bar, e.g. flattening shortly before the close
+    fn scheduled_events(&self) -> Vec<ScheduledEvent> {
+        vec![]
+    }
+    /// Called once per session when a scheduled event's time is reached
+    /// - returns as on_bar_update does, with orders then placed for every contract in
+    ///   get_contracts
+    async fn on_scheduled_event(&self, _event: &ScheduledEvent) -> Result<(bool, bool), String> {
+        Ok((false, false))
+    }
+    /// Whether the strategy has to be flat overnight - its targets are zeroed shortly before the
+    /// close (see strategy::flatten) and the session waits for the fills before shutting down
+    fn flatten_at_close(&self) -> bool {
+        false
+    }
+    /// [start, end) time of day, in the exchange timezone, the strategy trades in - bars starting
+    /// outside it are skipped for the strategy, e.g. to sit out the first 15 minutes after the open
+    /// - None trades every bar
+    fn active_window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        None
+    }
+    /// Days of 5 minute history a contract needs stored before the strategy acts on its bars -
+    /// bars arriving before then (e.g. for a symbol added mid-session) are skipped for the
+    /// strategy, so no orders are placed off a stub of history
+    /// - 0 acts on every bar
+    fn required_history_days(&self) -> u32 {
+        0
+    }
+    /// Called once a fill of the strategy's orders has been recorded in its transactions
+    /// - for reacting to fills as they happen, e.g. placing a protective order; positions are
+    ///   still reconciled from TargetPositions as usual
+    async fn on_fill(&self, _execution: &ExecutionSummary) -> Result<(), String> {
+        Ok(())
+    }
     /// Should return all associated contracts with this strategy
     fn get_contracts(&self) -> Vec<Contract>;
+    /// Minutes of the bars subscribe_strategy subscribes each contract at - a multiple of 5
+    fn get_timestep(&self) -> u32 {
+        5
+    }
     /// Should return the associated contract given by the stock - used when determining contracts
     /// to place orders for in TargetPositions
     fn get_contract(&self, stock: String, primary_exchange: String) -> Option<Contract>;
+    /// Contracts whose TargetPositions should also be reconciled when contract gets a bar update
+    /// - e.g. options traded off the underlying's bars, which get no bar updates of their own
+    fn get_dependent_contracts(&self, _contract: &Contract) -> Vec<Contract> {
+        vec![]
+    }
     /// Warm up the data given the consolidator - get all data required up till now for the
     /// strategy
     async fn warm_up_data<T>(&self, consolidator: Arc<Consolidator<T>>) -> Result<(), String>
     where
         T: StrategyExecutor + 'static;
+    /// Tunable params of the strategy as { "name": "number" | "integer" | "string" | "boolean" }
+    /// - stored alongside the params so writes from the backend can be validated
+    fn params_schema(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+    /// Should apply the params stored for the strategy (already validated against params_schema)
+    /// - only params that were set are passed, the rest keep their compiled in defaults
+    /// - strategies are cloned around, so params should live behind a shared Arc
+    fn load_params(&self, _params: serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 #[derive(Clone, PartialOrd, Ord, PartialEq, Eq)]
@@ -55,6 +128,53 @@ impl StrategyExecutor for StrategyEnum {
             StrategyEnum::StratB(s) => s.on_bar_update(contract).await,
         }
     }
+    async fn on_timestep_bar(
+        &self,
+        contract: &Contract,
+        timestep: u32,
+        bar: &ConsolidatedBar,
+    ) -> Result<(bool, bool), String> {
+        match self {
+            StrategyEnum::StratA(s) => s.on_timestep_bar(contract, timestep, bar).await,
+            StrategyEnum::StratB(s) => s.on_timestep_bar(contract, timestep, bar).await,
+        }
+    }
+    fn scheduled_events(&self) -> Vec<ScheduledEvent> {
+        match self {
+            StrategyEnum::StratA(s) => s.scheduled_events(),
+            StrategyEnum::StratB(s) => s.scheduled_events(),
+        }
+    }
+    async fn on_scheduled_event(&self, event: &ScheduledEvent) -> Result<(bool, bool), String> {
+        match self {
+            StrategyEnum::StratA(s) => s.on_scheduled_event(event).await,
+            StrategyEnum::StratB(s) => s.on_scheduled_event(event).await,
+        }
+    }
+    fn flatten_at_close(&self) -> bool {
+        match self {
+            StrategyEnum::StratA(s) => s.flatten_at_close(),
+            StrategyEnum::StratB(s) => s.flatten_at_close(),
+        }
+    }
+    fn active_window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        match self {
+            StrategyEnum::StratA(s) => s.active_window(),
+            StrategyEnum::StratB(s) => s.active_window(),
+        }
+    }
+    fn required_history_days(&self) -> u32 {
+        match self {
+            StrategyEnum::StratA(s) => s.required_history_days(),
+            StrategyEnum::StratB(s) => s.required_history_days(),
+        }
+    }
+    async fn on_fill(&self, execution: &ExecutionSummary) -> Result<(), String> {
+        match self {
+            StrategyEnum::StratA(s) => s.on_fill(execution).await,
+            StrategyEnum::StratB(s) => s.on_fill(execution).await,
+        }
+    }
     /// Should return all associated contracts with this strategy
     fn get_contracts(&self) -> Vec<Contract> {
         match self {
@@ -62,6 +182,12 @@ impl StrategyExecutor for StrategyEnum {
             StrategyEnum::StratB(s) => s.get_contracts(),
         }
     }
+    fn get_timestep(&self) -> u32 {
+        match self {
+            StrategyEnum::StratA(s) => s.get_timestep(),
+            StrategyEnum::StratB(s) => s.get_timestep(),
+        }
+    }
     /// Should return the associated contract given by the stock - used when determining contracts
     /// to place orders for in TargetPositions
     fn get_contract(&self, stock: String, primary_exchange: String) -> Option<Contract> {
@@ -70,6 +196,12 @@ impl StrategyExecutor for StrategyEnum {
             StrategyEnum::StratB(s) => s.get_contract(stock, primary_exchange),
         }
     }
+    fn get_dependent_contracts(&self, contract: &Contract) -> Vec<Contract> {
+        match self {
+            StrategyEnum::StratA(s) => s.get_dependent_contracts(contract),
+            StrategyEnum::StratB(s) => s.get_dependent_contracts(contract),
+        }
+    }
     /// Warm up the data given the consolidator - get all data required up till now for the
     /// strategy
     async fn warm_up_data<T>(&self, consolidator: Arc<Consolidator<T>>) -> Result<(), String>
@@ -81,4 +213,16 @@ impl StrategyExecutor for StrategyEnum {
             StrategyEnum::StratB(s) => s.warm_up_data(consolidator).await,
         }
     }
+    fn params_schema(&self) -> serde_json::Value {
+        match self {
+            StrategyEnum::StratA(s) => s.params_schema(),
+            StrategyEnum::StratB(s) => s.params_schema(),
+        }
+    }
+    fn load_params(&self, params: serde_json::Value) -> Result<(), String> {
+        match self {
+            StrategyEnum::StratA(s) => s.load_params(params),
+            StrategyEnum::StratB(s) => s.load_params(params),
+        }
+    }
 }
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+/// How a blocking OS thread finished, as reported back to its supervisor
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThreadStatus {
+    /// Thread stopped because of an error - the supervisor restarts it
+    Fatal(String),
+    /// Thread stopped on purpose (e.g. the subscription was cancelled) - not restarted
+    Ended,
+}
+
+/// Handed to a blocking OS thread to report how it finished
+/// - a thread that returns or panics without reporting is reported as Fatal on drop, so the
+///   supervisor never waits on a thread that is already gone
+pub struct ThreadStatusReporter {
+    sender: UnboundedSender<ThreadStatus>,
+    reported: bool,
+}
+
+impl ThreadStatusReporter {
+    pub fn new(sender: UnboundedSender<ThreadStatus>) -> Self {
+        Self {
+            sender,
+            reported: false,
+        }
+    }
+
+    pub fn report(mut self, status: ThreadStatus) {
+        self.reported = true;
+        // supervisor having stopped already just means nobody is left to restart this thread
+        let _ = self.sender.send(status);
+    }
+}
+
+impl Drop for ThreadStatusReporter {
+    fn drop(&mut self) {
+        if !self.reported {
+            let _ = self.sender.send(ThreadStatus::Fatal(
+                "Thread exited without reporting its status".to_string(),
+            ));
+        }
+    }
+}
+
+/// Restart policy for a supervised thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupervisorOptions {
+    /// Restarts allowed before the supervisor gives up and escalates
+    pub max_restarts: u32,
+    /// Wait before each restart, so a thread failing straight away doesn't spin
+    pub restart_delay: Duration,
+}
+
+impl Default for SupervisorOptions {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            restart_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Spawns a blocking thread with spawn_thread and restarts it whenever it reports Fatal
+/// - Ok once the thread reports Ended
+/// - Err with the last error once the thread has failed more than options.max_restarts times -
+///   the caller decides how to escalate
+pub async fn supervise<F>(
+    name: &str,
+    options: SupervisorOptions,
+    mut spawn_thread: F,
+) -> Result<(), String>
+where
+    F: FnMut(ThreadStatusReporter),
+{
+    let (status_sender, mut status_receiver) = unbounded_channel::<ThreadStatus>();
+
+    spawn_thread(ThreadStatusReporter::new(status_sender.clone()));
+    let mut restarts = 0;
+    while let Some(status) = status_receiver.recv().await {
+        match status {
+            ThreadStatus::Ended => {
+                tracing::info!("{} thread ended", name);
+                return Ok(());
+            }
+            ThreadStatus::Fatal(e) => {
+                if restarts >= options.max_restarts {
+                    tracing::error!(
+                        "{} thread failed {} times, giving up: {}",
+                        name,
+                        restarts + 1,
+                        e
+                    );
+                    return Err(format!("{} thread failed: {}", name, e));
+                }
+                restarts += 1;
+                tracing::warn!(
+                    "{} thread failed, restarting ({}/{}): {}",
+                    name,
+                    restarts,
+                    options.max_restarts,
+                    e
+                );
+                tokio::time::sleep(options.restart_delay).await;
+                spawn_thread(ThreadStatusReporter::new(status_sender.clone()));
+            }
+        }
+    }
+    Err(format!("{} supervisor channel closed", name))
+}
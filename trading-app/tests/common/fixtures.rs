@@ -0,0 +1,270 @@
+use std::{
+    cmp::Ordering,
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicI32, AtomicUsize},
+    },
+};
+
+use async_trait::async_trait;
+use ibapi::{
+    contracts::ContractBuilder,
+    orders::Order,
+    prelude::{Contract, SecurityType},
+};
+use serde_json::{Value, json};
+use trading_app::{
+    execution::{cancel::OrderCanceller, fills::ExecutionSummary, place_order::OrderSubmitter},
+    market_data::consolidator::Consolidator,
+    strategy::strategy::StrategyExecutor,
+};
+
+/// SMART routed USD stock
+pub fn stock(symbol: &str) -> Contract {
+    ContractBuilder::new()
+        .symbol(symbol)
+        .security_type(SecurityType::Stock)
+        .exchange("SMART")
+        .currency("USD")
+        .build()
+        .expect("Expected to be able to build stock contract")
+}
+
+pub fn qqq() -> Contract {
+    stock("QQQ")
+}
+
+/// QQQ listed on primary_exchange, submitted through exchange
+pub fn qqq_on(exchange: &str, primary_exchange: &str) -> Contract {
+    let mut contract = ContractBuilder::new()
+        .symbol("QQQ")
+        .security_type(SecurityType::Stock)
+        .exchange(exchange)
+        .currency("USD")
+        .build()
+        .expect("Expected to be able to build QQQ contract");
+    contract.primary_exchange = primary_exchange.to_string();
+    contract
+}
+
+/// SMART routed USD option on a NASDAQ listed underlying, 100 multiplier
+pub fn option(symbol: &str, expiry: &str, strike: f64, right: &str) -> Contract {
+    let mut contract = ContractBuilder::new()
+        .symbol(symbol)
+        .security_type(SecurityType::Option)
+        .exchange("SMART")
+        .currency("USD")
+        .build()
+        .expect("Expected to be able to build option contract");
+    contract.primary_exchange = "NASDAQ".to_string();
+    contract.last_trade_date_or_contract_month = expiry.to_string();
+    contract.strike = strike;
+    contract.multiplier = "100".to_string();
+    contract.right = right.to_string();
+    contract
+}
+
+pub fn qqq_put() -> Contract {
+    option("QQQ", "20250718", 500.0, "P")
+}
+
+/// Strategy whose StrategyExecutor answers are all set by the test, built as
+/// TestStrategy { .., ..TestStrategy::new(name) }
+/// - ordered by priority then name, as OrderEngine orders strategies claiming the same contract
+/// - clones share loaded params and recorded fills
+#[derive(Clone)]
+pub struct TestStrategy {
+    pub name: String,
+    pub priority: i32,
+    pub contracts: Vec<Contract>,
+    pub timestep: u32,
+    pub flatten_at_close: bool,
+    pub required_history_days: u32,
+    /// Returned by get_dependent_contracts for bars of any of contracts
+    pub dependent_contracts: Vec<Contract>,
+    /// What on_bar_update returns
+    pub bar_update: (bool, bool),
+    pub params_schema: Value,
+    /// Params as loaded by load_params, over the defaults set here
+    pub params: Arc<Mutex<Value>>,
+    /// (symbol, quantity, price) of every fill on_fill is called with
+    pub fills: Arc<Mutex<Vec<(String, f64, f64)>>>,
+}
+
+impl TestStrategy {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            priority: 0,
+            contracts: vec![],
+            timestep: 5,
+            flatten_at_close: false,
+            required_history_days: 0,
+            dependent_contracts: vec![],
+            bar_update: (false, false),
+            params_schema: json!({}),
+            params: Arc::new(Mutex::new(json!({}))),
+            fills: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Value {
+        self.params.lock().unwrap()[name].clone()
+    }
+
+    pub fn fills(&self) -> Vec<(String, f64, f64)> {
+        self.fills.lock().unwrap().clone()
+    }
+}
+
+impl PartialEq for TestStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TestStrategy {}
+
+impl PartialOrd for TestStrategy {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TestStrategy {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+#[async_trait]
+impl StrategyExecutor for TestStrategy {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    async fn on_bar_update(&self, _contract: &Contract) -> Result<(bool, bool), String> {
+        Ok(self.bar_update)
+    }
+    fn flatten_at_close(&self) -> bool {
+        self.flatten_at_close
+    }
+    fn required_history_days(&self) -> u32 {
+        self.required_history_days
+    }
+    async fn on_fill(&self, execution: &ExecutionSummary) -> Result<(), String> {
+        self.fills.lock().unwrap().push((
+            execution.contract.symbol.clone(),
+            execution.quantity,
+            execution.price,
+        ));
+        Ok(())
+    }
+    fn get_contracts(&self) -> Vec<Contract> {
+        self.contracts.clone()
+    }
+    fn get_timestep(&self) -> u32 {
+        self.timestep
+    }
+    fn get_contract(&self, stock: String, _primary_exchange: String) -> Option<Contract> {
+        self.contracts
+            .iter()
+            .find(|contract| contract.symbol == stock)
+            .cloned()
+    }
+    fn get_dependent_contracts(&self, contract: &Contract) -> Vec<Contract> {
+        let is_own = self.contracts.iter().any(|own| {
+            own.symbol == contract.symbol && own.security_type == contract.security_type
+        });
+        if is_own {
+            self.dependent_contracts.clone()
+        } else {
+            vec![]
+        }
+    }
+    async fn warm_up_data<T>(&self, _consolidator: Arc<Consolidator<T>>) -> Result<(), String>
+    where
+        T: StrategyExecutor + 'static,
+    {
+        Ok(())
+    }
+    fn params_schema(&self) -> Value {
+        self.params_schema.clone()
+    }
+    fn load_params(&self, params: Value) -> Result<(), String> {
+        let mut loaded = self.params.lock().unwrap();
+        if let (Some(loaded), Some(params)) = (loaded.as_object_mut(), params.as_object()) {
+            loaded.extend(params.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Hands out increasing order ids and records every order submitted / cancelled instead of
+/// sending it to IBKR
+pub struct RecordingSubmitter {
+    pub next_order_id: AtomicI32,
+    pub min_tick: f64,
+    /// Times min_tick was asked for, i.e. contract details requested
+    pub min_tick_requests: AtomicUsize,
+    pub submitted: Mutex<Vec<(i32, Contract, Order)>>,
+    pub cancelled: Mutex<Vec<i32>>,
+}
+
+impl RecordingSubmitter {
+    pub fn new(first_order_id: i32) -> Self {
+        Self::with_min_tick(first_order_id, 0.01)
+    }
+
+    pub fn with_min_tick(first_order_id: i32, min_tick: f64) -> Self {
+        Self {
+            next_order_id: AtomicI32::new(first_order_id),
+            min_tick,
+            min_tick_requests: AtomicUsize::new(0),
+            submitted: Mutex::new(vec![]),
+            cancelled: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn submitted(&self) -> Vec<(i32, Contract, Order)> {
+        self.submitted.lock().unwrap().clone()
+    }
+}
+
+impl Default for RecordingSubmitter {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl OrderSubmitter for RecordingSubmitter {
+    fn next_order_id(&self) -> i32 {
+        self.next_order_id.fetch_add(1, atomic::Ordering::SeqCst)
+    }
+
+    fn submit_order(
+        &self,
+        order_id: i32,
+        contract: &Contract,
+        order: &Order,
+    ) -> Result<(), String> {
+        self.submitted
+            .lock()
+            .unwrap()
+            .push((order_id, contract.clone(), order.clone()));
+        Ok(())
+    }
+
+    fn min_tick(&self, _contract: &Contract) -> Result<f64, String> {
+        self.min_tick_requests.fetch_add(1, atomic::Ordering::SeqCst);
+        Ok(self.min_tick)
+    }
+}
+
+impl OrderCanceller for RecordingSubmitter {
+    fn cancel_order(&self, order_id: i32) -> Result<String, String> {
+        self.cancelled.lock().unwrap().push(order_id);
+        Ok("Cancelled".to_string())
+    }
+}
@@ -1,6 +1,10 @@
-use std::sync::{LazyLock};
+use std::{future::Future, sync::LazyLock};
 
-use sqlx::{PgPool, Postgres, Transaction, migrate::Migrator, postgres::PgPoolOptions};
+use sqlx::{
+    PgPool, TransactionManager,
+    migrate::Migrator,
+    postgres::{PgPoolOptions, PgTransactionManager},
+};
 use tokio::sync::{Mutex, OnceCell};
 use trading_app::logger::init_logger;
 
@@ -11,6 +15,12 @@ static LOGGER: OnceCell<()> = OnceCell::const_new();
 pub static TEST_MUTEX: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
 pub async fn setup_test_db() -> PgPool {
+    setup_test_db_with_connections(1).await
+}
+
+/// setup_test_db for tests that need several connections in flight at once, e.g. to contend on
+/// a row lock
+pub async fn setup_test_db_with_connections(max_connections: u32) -> PgPool {
     LOGGER
         .get_or_init(|| async {
             if let Err(e) = init_logger() {
@@ -22,7 +32,7 @@ pub async fn setup_test_db() -> PgPool {
         .expect("Expected DATABASE_URL environment variable to be set!");
 
     let pool = PgPoolOptions::new()
-        .max_connections(1)
+        .max_connections(max_connections)
         .connect(&database_url)
         .await
         .expect("Failed to connect to test database");
@@ -39,15 +49,26 @@ pub async fn setup_test_db() -> PgPool {
     pool
 }
 
-/// Runs the test inside a rollbackable transaction.
-/// This ensures changes are not persisted after the test.
-pub async fn with_rollback<T, F, Fut>(pool: &PgPool, test: F)
+/// Runs the test against a pool whose only connection is held in a transaction that is rolled
+/// back once the test returns, so nothing the test writes is persisted
+/// - transactions begun on the pool by the code under test become savepoints of it
+/// - a failed statement outside such a savepoint aborts the transaction for the rest of the test
+pub async fn with_rollback<F, Fut>(pool: &PgPool, test: F)
 where
-    F: FnOnce(Transaction<'_, Postgres>) -> Fut,
-    Fut: std::future::Future<Output = ()>,
+    F: FnOnce(PgPool) -> Fut,
+    Fut: Future<Output = ()>,
 {
-    let tx = pool.begin().await.expect("Failed to begin transaction");
-    test(tx).await;
+    let rollback_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .after_connect(|conn, _meta| {
+            Box::pin(async move { PgTransactionManager::begin(conn, None).await })
+        })
+        .connect_with(pool.connect_options().as_ref().clone())
+        .await
+        .expect("Failed to begin transaction");
+    test(rollback_pool.clone()).await;
+    // closing the connection rolls back its open transaction
+    rollback_pool.close().await;
 }
 
 #[macro_export]
@@ -0,0 +1,5 @@
+// Shared by every test group - not every group uses all of it
+#![allow(dead_code)]
+
+pub mod fixtures;
+pub mod init;
@@ -0,0 +1,17 @@
+mod common;
+
+mod database {
+    pub mod test_account_summary;
+    pub mod test_batch_channel_flush;
+    pub mod test_checked_queries;
+    pub mod test_crud_retry;
+    pub mod test_crud_row_lock;
+    pub mod test_execution_side;
+    pub mod test_instance_lock;
+    pub mod test_last_close_cache;
+    pub mod test_last_option_bar;
+    pub mod test_open_orders_grouping;
+    pub mod test_raw_broker_time;
+    pub mod test_schema_check;
+    pub mod test_upsert_many;
+}
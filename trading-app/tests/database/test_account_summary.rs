@@ -0,0 +1,99 @@
+use chrono::{TimeZone, Utc};
+use trading_app::{
+    database::{
+        crud::CRUDTrait, models::AccountSummaryPrimaryKeys,
+        models_crud::account_summary::get_account_summary_crud,
+    },
+    execution::account_summary::{AccountSummaryValue, account_summaries},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const ACCOUNT: &str = "DU0000001";
+
+fn value(tag: &str, value: &str) -> AccountSummaryValue {
+    AccountSummaryValue {
+        account: ACCOUNT.to_string(),
+        tag: tag.to_string(),
+        value: value.to_string(),
+        currency: "USD".to_string(),
+    }
+}
+
+#[test]
+fn test_tags_are_collected_per_account() {
+    let time = Utc.with_ymd_and_hms(2025, 7, 15, 13, 30, 0).unwrap();
+
+    let summaries = account_summaries(
+        time,
+        &[
+            value("NetLiquidation", "100250.5"),
+            value("BuyingPower", "not a number"),
+            value("Cushion", "0.98"),
+        ],
+    );
+
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].net_liquidation, Some(100250.5));
+    assert_eq!(summaries[0].buying_power, None);
+    assert_eq!(summaries[0].currency.as_deref(), Some("USD"));
+}
+
+#[tokio::test]
+async fn test_stored_summary_round_trips() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let time = Utc.with_ymd_and_hms(2025, 7, 15, 20, 0, 0).unwrap();
+        let summary = account_summaries(
+            time,
+            &[
+                value("NetLiquidation", "100250.5"),
+                value("TotalCashValue", "40000"),
+                value("BuyingPower", "400000"),
+                value("AvailableFunds", "90000.25"),
+                value("ExcessLiquidity", "91000"),
+                value("InitMarginReq", "10250.25"),
+            ],
+        )
+        .remove(0);
+        let account_summary_crud = get_account_summary_crud(pool.clone());
+
+        account_summary_crud
+            .create(&summary)
+            .await
+            .expect("Expected to store summary");
+        let stored = account_summary_crud
+            .read(&AccountSummaryPrimaryKeys {
+                account: ACCOUNT.to_string(),
+                time,
+            })
+            .await
+            .expect("Expected to read summary")
+            .expect("Expected summary to be stored");
+
+        assert_eq!(stored.currency.as_deref(), Some("USD"));
+        // MaintMarginReq wasn't reported, so it is stored as NULL
+        assert_eq!(
+            (
+                stored.net_liquidation,
+                stored.total_cash_value,
+                stored.buying_power,
+                stored.available_funds,
+                stored.excess_liquidity,
+                stored.init_margin_req,
+                stored.maint_margin_req,
+            ),
+            (
+                Some(100250.5),
+                Some(40000.0),
+                Some(400000.0),
+                Some(90000.25),
+                Some(91000.0),
+                Some(10250.25),
+                None,
+            )
+        );
+    })
+    .await;
+}
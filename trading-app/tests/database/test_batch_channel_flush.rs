@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use rust_decimal::dec;
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use trading_app::database::{
+    models::{HistoricalDataFullKeys, HistoricalDataPrimaryKeys},
+    models_crud::historical_data::{HistoricalDataCRUD, get_specific_historical_data_crud},
+};
+
+async fn setup_pool() -> PgPool {
+    let database_url = std::env::var("DATABASE_URL")
+        .expect("Expected DATABASE_URL environment variable to be set!");
+    PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect to test database")
+}
+
+/// 5 min bars for stock starting 10:00 ET on 2025-07-01
+fn bars(stock: &str, n: u32) -> Vec<HistoricalDataFullKeys> {
+    (0..n)
+        .map(|i| HistoricalDataFullKeys {
+            stock: stock.to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+            time: Utc.with_ymd_and_hms(2025, 7, 1, 14, 0, 0).unwrap()
+                + chrono::Duration::minutes(5 * i as i64),
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: dec!(100),
+        })
+        .collect()
+}
+
+async fn count_written(crud: &HistoricalDataCRUD, bars: &[HistoricalDataFullKeys]) -> usize {
+    let mut written = 0;
+    for bar in bars {
+        let pk = HistoricalDataPrimaryKeys {
+            stock: bar.stock.clone(),
+            primary_exchange: bar.primary_exchange.clone(),
+            time: bar.time,
+        };
+        if let Ok(Some(_)) = crud.read_checked(&pk).await {
+            written += 1;
+        }
+    }
+    written
+}
+
+async fn clean_up(crud: &HistoricalDataCRUD, bars: &[HistoricalDataFullKeys]) {
+    for bar in bars {
+        crud.delete(&HistoricalDataPrimaryKeys {
+            stock: bar.stock.clone(),
+            primary_exchange: bar.primary_exchange.clone(),
+            time: bar.time,
+        })
+        .await
+        .expect("Expected to clean up bar");
+    }
+}
+
+#[tokio::test]
+async fn test_close_channel_flushes_queued_rows_before_returning() {
+    let pool = setup_pool().await;
+    let crud = get_specific_historical_data_crud(pool.clone());
+    let bars = bars("BATCHCLOSE", 20);
+    crud.init_channel().await;
+    for bar in &bars {
+        crud.batch_create_or_update(bar)
+            .await
+            .expect("Expected to queue bar");
+    }
+
+    // no waiting on the 1s flush interval - close_channel alone has to get them written
+    crud.close_channel().await;
+    let written = count_written(&crud, &bars).await;
+    clean_up(&crud, &bars).await;
+
+    assert_eq!(written, bars.len());
+}
+
+#[tokio::test]
+async fn test_dropping_every_handle_flushes_queued_rows() {
+    let pool = setup_pool().await;
+    let crud = get_specific_historical_data_crud(pool.clone());
+    let reader = get_specific_historical_data_crud(pool.clone());
+    let bars = bars("BATCHDROP", 20);
+    crud.init_channel().await;
+    for bar in &bars {
+        crud.batch_create_or_update(bar)
+            .await
+            .expect("Expected to queue bar");
+    }
+
+    // what a Consolidator dropped without shutdown leaves behind - no close requested, the
+    // writer only sees its channel handles go away
+    drop(crud);
+    let mut written = 0;
+    for _ in 0..50 {
+        written = count_written(&reader, &bars).await;
+        if written == bars.len() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    clean_up(&reader, &bars).await;
+
+    assert_eq!(written, bars.len());
+}
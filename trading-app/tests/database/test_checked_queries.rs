@@ -0,0 +1,128 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal::dec;
+use trading_app::database::{
+    crud::CRUDTrait,
+    models::{
+        CurrentStockPositionsFullKeys, CurrentStockPositionsPrimaryKeys,
+        CurrentStockPositionsUpdateKeys, HistoricalDataFullKeys, HistoricalDataPrimaryKeys,
+        HistoricalDataUpdateKeys, Status, StrategyFullKeys,
+    },
+    models_crud::{
+        current_stock_positions::get_specific_current_stock_positions_crud,
+        historical_data::get_specific_historical_data_crud, strategy::get_strategy_crud,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "checked_query_strat";
+
+#[tokio::test]
+async fn test_checked_current_stock_position_round_trip() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        let crud = get_specific_current_stock_positions_crud(pool.clone());
+        let pk = CurrentStockPositionsPrimaryKeys {
+            strategy: STRATEGY.to_string(),
+            stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+        };
+        crud.create_checked(&CurrentStockPositionsFullKeys {
+            strategy: pk.strategy.clone(),
+            stock: pk.stock.clone(),
+            primary_exchange: pk.primary_exchange.clone(),
+            quantity: 5.0,
+            avg_price: 100.0,
+        })
+        .await
+        .expect("Expected to create position with checked query");
+
+        let updated = crud
+            .update_checked(
+                &pk,
+                &CurrentStockPositionsUpdateKeys {
+                    quantity: Some(8.0),
+                    avg_price: None,
+                },
+            )
+            .await;
+        let checked = crud.read_checked(&pk).await;
+        let dynamic = crud.read(&pk).await;
+
+        assert_eq!(updated, Ok(1));
+        let checked = checked
+            .expect("Expected checked read to succeed")
+            .expect("Expected position to exist");
+        assert_eq!(checked.quantity, 8.0);
+        assert_eq!(checked.avg_price, 100.0);
+
+        // both paths have to agree on the row
+        let dynamic = dynamic
+            .expect("Expected dynamic read to succeed")
+            .expect("Expected position to exist");
+        assert_eq!(checked.quantity, dynamic.quantity);
+        assert_eq!(checked.avg_price, dynamic.avg_price);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_checked_historical_data_round_trip() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let crud = get_specific_historical_data_crud(pool.clone());
+        let pk = HistoricalDataPrimaryKeys {
+            stock: "CHECKED".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+            time: Utc.with_ymd_and_hms(2025, 7, 1, 14, 35, 0).unwrap(),
+        };
+        crud.create_checked(&HistoricalDataFullKeys {
+            stock: pk.stock.clone(),
+            primary_exchange: pk.primary_exchange.clone(),
+            time: pk.time,
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: dec!(100),
+        })
+        .await
+        .expect("Expected to create bar with checked query");
+
+        let updated = crud
+            .update_checked(
+                &pk,
+                &HistoricalDataUpdateKeys {
+                    open: None,
+                    high: Some(3.0),
+                    low: None,
+                    close: Some(2.5),
+                    volume: None,
+                },
+            )
+            .await;
+        let checked = crud.read_checked(&pk).await;
+
+        assert_eq!(updated, Ok(1));
+        let checked = checked
+            .expect("Expected checked read to succeed")
+            .expect("Expected bar to exist");
+        assert_eq!(checked.open, 1.0);
+        assert_eq!(checked.high, 3.0);
+        assert_eq!(checked.close, 2.5);
+        assert_eq!(checked.volume, dec!(100));
+    })
+    .await;
+}
@@ -0,0 +1,104 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use sqlx::error::{DatabaseError, ErrorKind};
+use trading_app::database::crud::{
+    MAX_SERIALIZATION_RETRIES, is_serialization_failure, retry_on_serialization_failure,
+};
+
+/// Stand-in for the error Postgres returns when a SERIALIZABLE transaction loses a race
+#[derive(Debug)]
+struct FakePgError(&'static str);
+
+impl fmt::Display for FakePgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fake postgres error {}", self.0)
+    }
+}
+
+impl std::error::Error for FakePgError {}
+
+impl DatabaseError for FakePgError {
+    fn message(&self) -> &str {
+        "could not serialize access due to concurrent update"
+    }
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.0))
+    }
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+fn pg_error(code: &'static str) -> anyhow::Error {
+    sqlx::Error::Database(Box::new(FakePgError(code))).into()
+}
+
+#[test]
+fn detects_serialization_failures() {
+    assert!(is_serialization_failure(&pg_error("40001")));
+    assert!(is_serialization_failure(&pg_error("40P01")));
+    assert!(!is_serialization_failure(&pg_error("23505")));
+    assert!(!is_serialization_failure(&anyhow::anyhow!(
+        "not a db error"
+    )));
+}
+
+#[tokio::test]
+async fn transient_serialization_failure_succeeds_on_retry() {
+    let attempts = AtomicUsize::new(0);
+    let res = retry_on_serialization_failure(MAX_SERIALIZATION_RETRIES, || async {
+        if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+            Err(pg_error("40001"))
+        } else {
+            Ok(1u64)
+        }
+    })
+    .await;
+
+    assert_eq!(res.unwrap(), 1);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retries_are_bounded() {
+    let attempts = AtomicUsize::new(0);
+    let res: anyhow::Result<()> =
+        retry_on_serialization_failure(MAX_SERIALIZATION_RETRIES, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(pg_error("40001"))
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_eq!(
+        attempts.load(Ordering::SeqCst),
+        MAX_SERIALIZATION_RETRIES + 1
+    );
+}
+
+#[tokio::test]
+async fn other_errors_are_not_retried() {
+    let attempts = AtomicUsize::new(0);
+    let res: anyhow::Result<()> =
+        retry_on_serialization_failure(MAX_SERIALIZATION_RETRIES, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(pg_error("23505"))
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use trading_app::database::{
+    crud::CRUDTrait,
+    models::{Status, StrategyFullKeys, StrategyPrimaryKeys, StrategyUpdateKeys},
+    models_crud::strategy::get_strategy_crud,
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db_with_connections};
+
+const STRATEGY: &str = "row_lock_strat";
+
+/// Read-modify-write of capital for STRATEGY inside a transaction holding the row lock
+/// - sleeps between the read and the write so an unlocked read would lose the other update
+async fn add_capital(pool: PgPool, amount: f64) {
+    let crud = get_strategy_crud(pool.clone());
+    let pk = StrategyPrimaryKeys {
+        strategy: STRATEGY.to_string(),
+    };
+
+    let mut tx = pool.begin().await.expect("Expected to begin transaction");
+    let strategy = crud
+        .read_for_update(&pk, &mut tx)
+        .await
+        .expect("Expected to read strategy for update")
+        .expect("Expected strategy to exist");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    crud.update_in_tx(
+        &pk,
+        &StrategyUpdateKeys {
+            capital: Some(strategy.capital + amount),
+            initial_capital: None,
+            status: None,
+        },
+        &mut tx,
+    )
+    .await
+    .expect("Expected to update strategy in transaction");
+    tx.commit().await.expect("Expected to commit transaction");
+}
+
+#[tokio::test]
+async fn test_concurrent_read_for_update_serializes_on_row_lock() {
+    let _lock = TEST_MUTEX.lock().await;
+    // 2 connections so both transactions can be in flight at once - they have to commit for the
+    // second to see the first's write, so this can't run in with_rollback
+    let pool = setup_test_db_with_connections(2).await;
+    let crud = get_strategy_crud(pool.clone());
+    let pk = StrategyPrimaryKeys {
+        strategy: STRATEGY.to_string(),
+    };
+    crud.create_or_ignore(&StrategyFullKeys {
+        strategy: STRATEGY.to_string(),
+        capital: 10.0,
+        initial_capital: 10.0,
+        status: Status::Inactive,
+    })
+    .await
+    .expect("Expected to create strategy");
+
+    let (first, second) = tokio::join!(
+        tokio::spawn(add_capital(pool.clone(), 5.0)),
+        tokio::spawn(add_capital(pool.clone(), 7.0)),
+    );
+    first.expect("Expected first execution to complete");
+    second.expect("Expected second execution to complete");
+
+    let strategy = crud
+        .read(&pk)
+        .await
+        .expect("Expected to read strategy")
+        .expect("Expected strategy to exist");
+    crud.delete(&pk).await.expect("Expected to delete strategy");
+
+    assert_eq!(strategy.capital, 22.0);
+}
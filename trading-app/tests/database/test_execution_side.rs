@@ -0,0 +1,19 @@
+use trading_app::database::models::ExecutionSide;
+
+#[test]
+fn parses_ibkr_sides() {
+    assert_eq!(ExecutionSide::from_str("BOT"), Ok(ExecutionSide::Bought));
+    assert_eq!(ExecutionSide::from_str("SLD"), Ok(ExecutionSide::Sold));
+}
+
+#[test]
+fn unexpected_side_is_flagged_not_treated_as_sell() {
+    assert!(ExecutionSide::from_str("BUY").is_err());
+    assert!(ExecutionSide::from_str("").is_err());
+}
+
+#[test]
+fn signed_quantity_follows_side() {
+    assert_eq!(ExecutionSide::Bought.signed(10.0), 10.0);
+    assert_eq!(ExecutionSide::Sold.signed(10.0), -10.0);
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use trading_app::database::instance_lock::{InstanceLock, InstanceLockMode};
+
+fn database_url() -> String {
+    std::env::var("DATABASE_URL").expect("Expected DATABASE_URL environment variable to be set!")
+}
+
+#[tokio::test]
+async fn test_second_instance_exits_while_lock_is_held() {
+    // own key so a running trading-app instance does not interfere
+    let key = 7_030_001;
+    let first = InstanceLock::acquire_key(&database_url(), key, InstanceLockMode::Exit)
+        .await
+        .expect("Expected first instance to take the lock");
+
+    let second = InstanceLock::acquire_key(&database_url(), key, InstanceLockMode::Exit).await;
+    assert!(second.is_err());
+
+    first.release().await;
+    let after_release = InstanceLock::acquire_key(&database_url(), key, InstanceLockMode::Exit)
+        .await
+        .expect("Expected lock to be free once released");
+    after_release.release().await;
+}
+
+#[tokio::test]
+async fn test_second_instance_waits_until_lock_is_released() {
+    let key = 7_030_002;
+    let first = InstanceLock::acquire_key(&database_url(), key, InstanceLockMode::Exit)
+        .await
+        .expect("Expected first instance to take the lock");
+
+    let waiting = tokio::spawn(async move {
+        InstanceLock::acquire_key(&database_url(), key, InstanceLockMode::Wait).await
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!waiting.is_finished());
+
+    first.release().await;
+    let second = tokio::time::timeout(Duration::from_secs(5), waiting)
+        .await
+        .expect("Expected waiting instance to take the lock once released")
+        .expect("Expected waiting task not to panic")
+        .expect("Expected waiting instance to take the lock");
+    assert_eq!(second.key(), key);
+    second.release().await;
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use rust_decimal::dec;
+use trading_app::database::{
+    models::{HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys},
+    models_crud::historical_data::{LastCloseCache, get_specific_historical_data_crud},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STOCK: &str = "LASTCLS";
+const PRIMARY_EXCHANGE: &str = "NASDAQ";
+
+#[test]
+fn test_older_bar_does_not_replace_newer_close() {
+    let last_closes = LastCloseCache::default();
+    let bar_time = Utc.with_ymd_and_hms(2025, 7, 1, 14, 5, 0).unwrap();
+
+    last_closes.record(STOCK, PRIMARY_EXCHANGE, bar_time, 101.0);
+    last_closes.record(
+        STOCK,
+        PRIMARY_EXCHANGE,
+        bar_time - chrono::Duration::minutes(5),
+        99.0,
+    );
+
+    assert_eq!(last_closes.get(STOCK, PRIMARY_EXCHANGE), Some(101.0));
+}
+
+#[tokio::test]
+async fn test_latest_close_within_ttl_is_served_from_cache() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let ttl = Duration::from_millis(500);
+        let mut historical_data_crud = get_specific_historical_data_crud(pool);
+        historical_data_crud.set_last_close_cache(LastCloseCache::new(ttl));
+        let pk = HistoricalDataPrimaryKeys {
+            stock: STOCK.to_string(),
+            primary_exchange: PRIMARY_EXCHANGE.to_string(),
+            time: Utc.with_ymd_and_hms(2025, 7, 1, 14, 0, 0).unwrap(),
+        };
+        historical_data_crud
+            .create_or_ignore(&HistoricalDataFullKeys {
+                stock: pk.stock.clone(),
+                primary_exchange: pk.primary_exchange.clone(),
+                time: pk.time,
+                open: 100.0,
+                high: 101.0,
+                low: 99.0,
+                close: 100.0,
+                volume: dec!(100),
+            })
+            .await
+            .expect("Expected to create bar");
+
+        let first = historical_data_crud
+            .latest_close(STOCK.to_string(), PRIMARY_EXCHANGE.to_string())
+            .await;
+        // written behind the cache's back, only seen once the cached close expires
+        historical_data_crud
+            .update(
+                &pk,
+                &HistoricalDataUpdateKeys {
+                    open: None,
+                    high: None,
+                    low: None,
+                    close: Some(100.5),
+                    volume: None,
+                },
+            )
+            .await
+            .expect("Expected to update bar");
+        let second = historical_data_crud
+            .latest_close(STOCK.to_string(), PRIMARY_EXCHANGE.to_string())
+            .await;
+        tokio::time::sleep(ttl + Duration::from_millis(100)).await;
+        let after_ttl = historical_data_crud
+            .latest_close(STOCK.to_string(), PRIMARY_EXCHANGE.to_string())
+            .await;
+
+        assert_eq!(first, Ok(Some(100.0)));
+        assert_eq!(second, Ok(Some(100.0)));
+        assert_eq!(after_ttl, Ok(Some(100.5)));
+    })
+    .await;
+}
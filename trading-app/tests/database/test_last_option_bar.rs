@@ -0,0 +1,60 @@
+use chrono::{Duration, TimeZone, Utc};
+use rust_decimal::dec;
+use trading_app::database::{
+    crud::CRUDTrait,
+    models::{HistoricalOptionsDataFullKeys, OptionType},
+    models_crud::historical_options_data::{
+        get_historical_options_data_crud, get_specific_historical_options_data_crud,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+fn bar(minutes_after_open: i64, close: f64) -> HistoricalOptionsDataFullKeys {
+    HistoricalOptionsDataFullKeys {
+        stock: "LASTBAR".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        expiry: "20250718".to_string(),
+        strike: 100.0,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Call,
+        time: Utc.with_ymd_and_hms(2025, 7, 1, 13, 30, 0).unwrap()
+            + Duration::minutes(minutes_after_open),
+        open: close,
+        high: close,
+        low: close,
+        close,
+        volume: dec!(10),
+    }
+}
+
+#[tokio::test]
+async fn test_last_bar_of_contract_is_the_latest_bar() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let crud = get_historical_options_data_crud(pool.clone());
+        for bar in [bar(0, 1.0), bar(10, 3.0), bar(5, 2.0)] {
+            crud.create(&bar)
+                .await
+                .expect("Expected to create option bar");
+        }
+
+        let last_bar = get_specific_historical_options_data_crud(pool.clone())
+            .read_last_bar_of_contract(
+                "LASTBAR".to_string(),
+                "NASDAQ".to_string(),
+                "20250718".to_string(),
+                100.0,
+                "100".to_string(),
+                OptionType::Call,
+            )
+            .await
+            .expect("Expected to read last option bar")
+            .expect("Expected an option bar");
+
+        assert_eq!(last_bar.time, bar(10, 3.0).time);
+        assert_eq!(last_bar.close, 3.0);
+    })
+    .await;
+}
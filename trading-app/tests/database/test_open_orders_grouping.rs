@@ -0,0 +1,85 @@
+use chrono::{Duration, Utc};
+use trading_app::database::{
+    crud::CRUDTrait,
+    models::{OpenStockOrdersFullKeys, Status, StrategyFullKeys},
+    models_crud::{
+        open_stock_orders::{get_open_stock_orders_crud, get_specific_open_stock_orders_crud},
+        strategy::get_strategy_crud,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY_A: &str = "grouping_strat_a";
+const STRATEGY_B: &str = "grouping_strat_b";
+
+fn open_order(
+    order_id: i32,
+    strategy: &str,
+    stock: &str,
+    seconds_ago: i64,
+) -> OpenStockOrdersFullKeys {
+    OpenStockOrdersFullKeys {
+        order_perm_id: 673_000 + order_id,
+        order_id: 673_000 + order_id,
+        strategy: strategy.to_string(),
+        stock: stock.to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        time: Utc::now() - Duration::seconds(seconds_ago),
+        quantity: 10.0,
+        executions: vec![],
+        filled: 0.0,
+        order_snapshot: None,
+    }
+}
+
+#[tokio::test]
+async fn test_open_orders_grouped_by_strategy_and_stock() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let strategy_crud = get_strategy_crud(pool.clone());
+        for strategy in [STRATEGY_A, STRATEGY_B] {
+            strategy_crud
+                .create_or_ignore(&StrategyFullKeys {
+                    strategy: strategy.to_string(),
+                    capital: 10.0,
+                    initial_capital: 10.0,
+                    status: Status::Inactive,
+                })
+                .await
+                .expect("Expected to create strategy");
+        }
+
+        let orders = vec![
+            open_order(1, STRATEGY_A, "QQQ", 10),
+            open_order(2, STRATEGY_A, "QQQ", 20),
+            open_order(3, STRATEGY_A, "SPY", 10),
+            open_order(4, STRATEGY_B, "QQQ", 10),
+        ];
+        let open_orders_crud = get_open_stock_orders_crud(pool.clone());
+        for order in &orders {
+            open_orders_crud
+                .create_or_ignore(order)
+                .await
+                .expect("Expected to create open order");
+        }
+
+        let grouped = get_specific_open_stock_orders_crud(pool.clone())
+            .get_open_orders_by_strategy_symbol()
+            .await
+            .expect("Expected to read grouped open orders");
+        let order_ids = |strategy: &str, stock: &str| -> Vec<i32> {
+            grouped
+                .get(&(strategy.to_string(), stock.to_string()))
+                .map(|orders| orders.iter().map(|order| order.order_id).collect())
+                .unwrap_or_default()
+        };
+        // Oldest first within each group
+        assert_eq!(order_ids(STRATEGY_A, "QQQ"), vec![673_002, 673_001]);
+        assert_eq!(order_ids(STRATEGY_A, "SPY"), vec![673_003]);
+        assert_eq!(order_ids(STRATEGY_B, "QQQ"), vec![673_004]);
+        assert_eq!(order_ids(STRATEGY_B, "SPY"), Vec::<i32>::new());
+    })
+    .await;
+}
@@ -0,0 +1,86 @@
+use chrono::Utc;
+use rust_decimal::dec;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            Status, StockTransactionsFullKeys, StockTransactionsPrimaryKeys, StrategyFullKeys,
+        },
+        models_crud::{
+            stock_transactions::get_stock_transactions_crud, strategy::get_strategy_crud,
+        },
+    },
+    execution::events::on_execution_updates::raw_broker_time,
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "raw_time_strat";
+// Double space and trailing timezone as IBKR sends it
+const RAW_BROKER_TIME: &str = "20250701  10:35:07 US/Eastern";
+
+#[test]
+fn test_raw_broker_time_only_kept_with_env_flag() {
+    // SAFETY: no other test in this binary reads or writes STORE_RAW_BROKER_TIME
+    unsafe { std::env::remove_var("STORE_RAW_BROKER_TIME") };
+    let unset = raw_broker_time(RAW_BROKER_TIME);
+    unsafe { std::env::set_var("STORE_RAW_BROKER_TIME", "false") };
+    let disabled = raw_broker_time(RAW_BROKER_TIME);
+    unsafe { std::env::set_var("STORE_RAW_BROKER_TIME", "true") };
+    let enabled = raw_broker_time(RAW_BROKER_TIME);
+    unsafe { std::env::remove_var("STORE_RAW_BROKER_TIME") };
+
+    assert_eq!(unset, None);
+    assert_eq!(disabled, None);
+    assert_eq!(enabled.as_deref(), Some(RAW_BROKER_TIME));
+}
+
+#[tokio::test]
+async fn test_raw_broker_time_is_stored_verbatim() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        let transactions_crud = get_stock_transactions_crud(pool.clone());
+        let pk = StockTransactionsPrimaryKeys {
+            execution_id: "raw_time_exec".to_string(),
+        };
+        transactions_crud
+            .create(&StockTransactionsFullKeys {
+                execution_id: pk.execution_id.clone(),
+                strategy: STRATEGY.to_string(),
+                stock: "QQQ".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                order_perm_id: 1,
+                time: Utc::now(),
+                price: 1.0,
+                quantity: 2.0,
+                fees: dec!(0),
+                raw_broker_time: Some(RAW_BROKER_TIME.to_string()),
+                closes_position: false,
+                realized_pnl: None,
+            })
+            .await
+            .expect("Expected to create stock transaction");
+
+        let transaction = transactions_crud
+            .read(&pk)
+            .await
+            .expect("Expected to read stock transaction")
+            .expect("Expected stock transaction to exist");
+        assert_eq!(
+            transaction.raw_broker_time.as_deref(),
+            Some(RAW_BROKER_TIME)
+        );
+    })
+    .await;
+}
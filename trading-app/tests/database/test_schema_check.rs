@@ -0,0 +1,71 @@
+use trading_app::database::schema_check::{
+    ModelTable, SchemaMismatch, check_schema, registered_models, schema_mismatches, validate_schema,
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db};
+
+/// trading.strategy's model with a column no migration adds
+fn drifted_strategy_model() -> ModelTable {
+    ModelTable::new(
+        "trading.strategy",
+        vec![
+            "strategy",
+            "capital",
+            "initial_capital",
+            "status",
+            "max_drawdown",
+        ],
+    )
+}
+
+#[test]
+fn test_missing_column_and_table_are_reported() {
+    let table_columns: Vec<String> = ["strategy", "capital", "initial_capital", "status"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+
+    assert_eq!(
+        schema_mismatches(&drifted_strategy_model(), &table_columns),
+        vec![SchemaMismatch::MissingColumn {
+            table: "trading.strategy".to_string(),
+            column: "max_drawdown".to_string(),
+        }]
+    );
+    assert_eq!(
+        schema_mismatches(&drifted_strategy_model(), &[]),
+        vec![SchemaMismatch::MissingTable {
+            table: "trading.strategy".to_string(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_validation_lists_every_mismatch() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+
+    let result = validate_schema(
+        &pool,
+        &[
+            drifted_strategy_model(),
+            ModelTable::new("trading.no_such_table", vec!["strategy"]),
+        ],
+    )
+    .await;
+
+    let error = result.expect_err("Expected drifted models to fail validation");
+    assert!(error.contains("trading.strategy: column max_drawdown does not exist"));
+    assert!(error.contains("trading.no_such_table: table does not exist"));
+    assert!(!error.contains("column capital"));
+}
+
+#[tokio::test]
+async fn test_registered_models_match_migrated_schema() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+
+    let mismatches = check_schema(&pool, &registered_models(pool.clone())).await;
+
+    assert_eq!(mismatches, Ok(vec![]));
+}
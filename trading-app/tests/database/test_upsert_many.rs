@@ -0,0 +1,170 @@
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use sqlx::{Arguments, Execute, PgPool};
+use trading_app::{
+    Insertable,
+    database::{
+        crud::{CRUD, CRUDTrait, values_placeholders},
+        models::{
+            HistoricalData, HistoricalDataFullKeys, HistoricalDataPrimaryKeys,
+            HistoricalDataUpdateKeys, Status, Strategy, StrategyPrimaryKeys,
+        },
+        models_crud::strategy::get_strategy_crud,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+fn strategies(capital: f64) -> Vec<Strategy> {
+    ["upsert_many_a", "upsert_many_b", "upsert_many_c"]
+        .iter()
+        .map(|strategy| Strategy {
+            strategy: strategy.to_string(),
+            capital: Some(capital),
+            initial_capital: None,
+            status: Some(Status::Inactive),
+        })
+        .collect()
+}
+
+#[test]
+fn test_three_rows_are_numbered_row_by_row() {
+    let rows = strategies(1000.0);
+    let columns = Strategy::many_column_names(&rows).expect("Expected rows to share columns");
+    // initial_capital is unset on every row so it is skipped, not bound as NULL
+    assert_eq!(columns, vec!["strategy", "capital", "status"]);
+    assert_eq!(
+        values_placeholders(&columns, rows.len()),
+        "($1, $2, $3::status), ($4, $5, $6::status), ($7, $8, $9::status)"
+    );
+
+    let mut query = Strategy::bind_many_to_query(&rows, sqlx::query("SELECT 1"));
+    let arguments = query
+        .take_arguments()
+        .expect("Expected rows to encode")
+        .expect("Expected rows to be bound");
+    assert_eq!(arguments.len(), 9);
+}
+
+#[test]
+fn test_rows_setting_different_optional_columns_are_rejected() {
+    let mut rows = strategies(1000.0);
+    rows[1].initial_capital = Some(1000.0);
+    assert!(Strategy::many_column_names(&rows).is_err());
+    assert!(Strategy::many_column_names(&[]).is_err());
+}
+
+#[tokio::test]
+async fn test_upsert_many_inserts_then_updates() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let crud = get_strategy_crud(pool.clone());
+        // every NOT NULL column has to be set for the insert half of the upsert
+        let with_initial_capital = |capital: f64| {
+            strategies(capital)
+                .into_iter()
+                .map(|strategy| Strategy {
+                    initial_capital: Some(1000.0),
+                    ..strategy
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let inserted = crud.upsert_many(&with_initial_capital(1000.0)).await;
+        let updated = crud.upsert_many(&with_initial_capital(2500.0)).await;
+        assert_eq!(inserted.expect("Expected rows to be inserted"), 3);
+        assert_eq!(updated.expect("Expected rows to be updated"), 3);
+
+        for row in strategies(0.0) {
+            let strategy = crud
+                .read(&StrategyPrimaryKeys {
+                    strategy: row.strategy,
+                })
+                .await
+                .expect("Expected to read strategy");
+            assert_eq!(strategy.map(|strategy| strategy.capital), Some(2500.0));
+        }
+    })
+    .await;
+}
+
+type BarsCrud = CRUD<HistoricalDataFullKeys, HistoricalDataPrimaryKeys, HistoricalDataUpdateKeys>;
+
+/// CRUD on a temp copy of market_data.historical_data - with_rollback's pool has a single
+/// connection so the temp table stays visible to every query
+async fn temp_bars_crud(pool: &PgPool) -> BarsCrud {
+    sqlx::query(
+        "CREATE TEMP TABLE upsert_many_bars (LIKE market_data.historical_data INCLUDING ALL)",
+    )
+    .execute(pool)
+    .await
+    .expect("Expected to create temp table");
+    BarsCrud::new(pool.clone(), "pg_temp.upsert_many_bars".to_string())
+}
+
+fn bars(n: usize, close: f64) -> Vec<HistoricalData> {
+    let start = DateTime::<Utc>::from_timestamp(1_752_586_200, 0).expect("Expected valid time");
+    (0..n)
+        .map(|i| HistoricalData {
+            stock: "UPSERT".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+            time: start + Duration::minutes(5 * i as i64),
+            open: Some(100.0),
+            high: Some(101.0),
+            low: Some(99.0),
+            close: Some(close),
+            volume: Some(Decimal::from(1000)),
+        })
+        .collect()
+}
+
+async fn closes(pool: &PgPool) -> (i64, f64, f64) {
+    sqlx::query_as("SELECT COUNT(*), MIN(close), MAX(close) FROM pg_temp.upsert_many_bars")
+        .fetch_one(pool)
+        .await
+        .expect("Expected to read temp table")
+}
+
+#[tokio::test]
+async fn test_upsert_many_writes_a_backfill_in_one_call() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let crud = temp_bars_crud(&pool).await;
+
+        let inserted = crud
+            .upsert_many(&bars(1000, 100.5))
+            .await
+            .expect("Expected bars to be inserted");
+        assert_eq!(inserted, 1000);
+        assert_eq!(closes(&pool).await, (1000, 100.5, 100.5));
+
+        // re-fetching the same bars overwrites them rather than failing on the primary key
+        let updated = crud
+            .upsert_many(&bars(1000, 100.75))
+            .await
+            .expect("Expected bars to be updated");
+        assert_eq!(updated, 1000);
+        assert_eq!(closes(&pool).await, (1000, 100.75, 100.75));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_upsert_many_splits_rows_past_the_bind_parameter_limit() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let crud = temp_bars_crud(&pool).await;
+
+        // 9000 rows of 8 columns is 72000 parameters - more than one statement takes
+        let inserted = crud
+            .upsert_many(&bars(9000, 100.5))
+            .await
+            .expect("Expected bars to be inserted");
+        assert_eq!(inserted, 9000);
+        assert_eq!(closes(&pool).await.0, 9000);
+    })
+    .await;
+}
@@ -0,0 +1,37 @@
+mod common;
+
+mod execution {
+    pub mod test_app_state;
+    pub mod test_bar_update_targets;
+    pub mod test_blocking_pool;
+    pub mod test_broker_events;
+    pub mod test_broker_notices;
+    pub mod test_cancel_order;
+    pub mod test_contract_conflicts;
+    pub mod test_contract_validation;
+    pub mod test_equity_snapshots;
+    pub mod test_login_backoff;
+    pub mod test_multiplier_normalization;
+    pub mod test_netting;
+    pub mod test_option_exercise;
+    pub mod test_option_settlement;
+    pub mod test_order_limit;
+    pub mod test_order_map_persistence;
+    pub mod test_order_routing;
+    pub mod test_order_size_multiplier;
+    pub mod test_order_snapshot;
+    pub mod test_partial_fill_policy;
+    pub mod test_place_order;
+    pub mod test_position_averaging;
+    pub mod test_preview;
+    pub mod test_quantity_rounding;
+    pub mod test_realized_pnl;
+    pub mod test_reconcile_direction;
+    pub mod test_stale_open_orders;
+    pub mod test_sync_options;
+    pub mod test_sync_write_retry;
+    pub mod test_thread_supervisor;
+    pub mod test_tick_rounding;
+    pub mod test_tracing_spans;
+    pub mod test_unknown_strategy;
+}
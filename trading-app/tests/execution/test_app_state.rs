@@ -0,0 +1,56 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{
+    app_state::{TradingAppState, TradingConfig},
+    execution::{preview::RiskLimits, sync::ReconcileDirection},
+    market_data::market_hours::Clock,
+};
+
+struct FixedClock(DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[tokio::test]
+async fn test_state_is_built_from_config() {
+    let mut config = TradingConfig::new("postgres://localhost/trading_app_state");
+    config.blocking_threads = 2;
+    config.equity_snapshot_interval = Duration::from_secs(60);
+    config.risk_limits = RiskLimits {
+        max_order_notional: Some(5000.0),
+        max_capital_fraction: 0.5,
+    };
+    config.sync_options.reconcile_direction = ReconcileDirection::TrustLocal;
+    // the state never connects on its own
+    let pool = PgPoolOptions::new()
+        .connect_lazy(&config.database_url)
+        .expect("Expected a valid database url");
+    let session_start = Utc.with_ymd_and_hms(2025, 7, 15, 13, 30, 0).unwrap();
+
+    let state =
+        TradingAppState::with_clock(pool, config.clone(), Arc::new(FixedClock(session_start)));
+    let shared = state.clone();
+
+    assert_eq!(state.config.database_url, config.database_url);
+    assert_eq!(state.config.gateway_address, "127.0.0.1:4002");
+    assert_eq!(
+        state.config.equity_snapshot_interval,
+        Duration::from_secs(60)
+    );
+    assert_eq!(state.config.risk_limits, config.risk_limits);
+    assert_eq!(
+        state.config.sync_options.reconcile_direction,
+        ReconcileDirection::TrustLocal
+    );
+    assert!(state.config.open_gate.enabled);
+    assert_eq!(state.blocking_pool.size(), 2);
+    assert_eq!(state.clock.now(), session_start);
+    // clones share the same config and handles rather than copying them
+    assert!(Arc::ptr_eq(&state.config, &shared.config));
+    assert!(Arc::ptr_eq(&state.blocking_pool, &shared.blocking_pool));
+}
@@ -0,0 +1,40 @@
+use ibapi::prelude::Contract;
+use trading_app::{database::models::AssetType, execution::order_engine::bar_update_targets};
+
+use crate::common::fixtures::{TestStrategy, option};
+
+fn qqq_call() -> Contract {
+    option("QQQ", "20250718", 450.0, "C")
+}
+
+/// Trades QQQ and a QQQ call off QQQ's bars
+fn underlying_and_option_strategy() -> TestStrategy {
+    TestStrategy {
+        contracts: vec![Contract::stock("QQQ")],
+        dependent_contracts: vec![qqq_call()],
+        bar_update: (true, false),
+        ..TestStrategy::new("underlying_and_option_strat")
+    }
+}
+
+#[test]
+fn test_stock_bar_triggers_option_reconciliation() {
+    let targets = bar_update_targets(&underlying_and_option_strategy(), &Contract::stock("QQQ"));
+
+    let asset_types: Vec<AssetType> = targets
+        .iter()
+        .map(|(_, asset_type)| asset_type.clone())
+        .collect();
+    assert_eq!(asset_types, vec![AssetType::Stock, AssetType::Option]);
+    assert_eq!(targets[1].0.symbol, "QQQ");
+    assert_eq!(targets[1].0.strike, 450.0);
+    assert_eq!(targets[1].0.right, "C");
+}
+
+#[test]
+fn test_option_bar_has_no_dependents() {
+    let targets = bar_update_targets(&underlying_and_option_strategy(), &qqq_call());
+
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].1, AssetType::Option);
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI32, AtomicUsize, Ordering},
+    },
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use ibapi::{
+    orders::{Action, Order, order_builder},
+    prelude::Contract,
+};
+use trading_app::execution::{
+    blocking_pool::BlockingPool,
+    place_order::{OrderContext, OrderSubmitter, place_order},
+};
+
+use crate::common::{
+    fixtures::qqq,
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const POOL_SIZE: usize = 3;
+const ORDERS: usize = 12;
+
+/// Blocks in submit_order like the IBKR client does - records which threads submitted and how
+/// many were submitting at once
+#[derive(Default)]
+struct SlowSubmitter {
+    next_order_id: AtomicI32,
+    submitting: AtomicUsize,
+    max_submitting: AtomicUsize,
+    threads: Mutex<HashSet<ThreadId>>,
+}
+
+impl OrderSubmitter for SlowSubmitter {
+    fn next_order_id(&self) -> i32 {
+        self.next_order_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn submit_order(
+        &self,
+        _order_id: i32,
+        _contract: &Contract,
+        _order: &Order,
+    ) -> Result<(), String> {
+        let submitting = self.submitting.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_submitting.fetch_max(submitting, Ordering::SeqCst);
+        self.threads.lock().unwrap().insert(thread::current().id());
+        thread::sleep(Duration::from_millis(20));
+        self.submitting.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn min_tick(&self, _contract: &Contract) -> Result<f64, String> {
+        Ok(0.01)
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_orders_use_at_most_pool_size_threads() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let ctx = OrderContext {
+            blocking_pool: Arc::new(BlockingPool::new(POOL_SIZE)),
+            ..OrderContext::new(pool)
+        };
+        let client = Arc::new(SlowSubmitter::default());
+
+        let placements = (0..ORDERS).map(|_| {
+            let client = client.clone();
+            let placing_ctx = ctx.clone();
+            ctx.blocking_pool.run(move || {
+                place_order(
+                    &placing_ctx,
+                    "pool_strat".to_string(),
+                    client,
+                    qqq(),
+                    order_builder::limit_order(Action::Buy, 1.0, 100.0),
+                    false,
+                )
+            })
+        });
+        let results = join_all(placements).await;
+
+        assert!(
+            results
+                .into_iter()
+                .all(|result| matches!(result, Ok(Ok(_))))
+        );
+        assert_eq!(ctx.order_map.lock().unwrap().len(), ORDERS);
+        assert!(client.max_submitting.load(Ordering::SeqCst) <= POOL_SIZE);
+        assert!(client.threads.lock().unwrap().len() <= POOL_SIZE);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_panicking_job_does_not_take_down_pool() {
+    let blocking_pool = BlockingPool::new(1);
+    let panicked = blocking_pool.run(|| -> i32 { panic!("job failed") }).await;
+    let value = blocking_pool.run(|| 42).await;
+
+    assert!(panicked.is_err());
+    assert_eq!(value, Ok(42));
+}
@@ -0,0 +1,82 @@
+use ibapi::{
+    messages::Notice,
+    orders::{CommissionReport, Execution, ExecutionData, OrderStatus, OrderUpdate},
+};
+use trading_app::execution::broker_events::{BrokerEvent, BrokerEventType, record_broker_event};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+fn execution(order_id: i32, execution_id: &str) -> OrderUpdate {
+    OrderUpdate::ExecutionData(ExecutionData {
+        execution: Execution {
+            order_id,
+            execution_id: execution_id.to_string(),
+            side: "BOT".to_string(),
+            shares: 10.0,
+            price: 450.0,
+            ..Execution::default()
+        },
+        ..ExecutionData::default()
+    })
+}
+
+fn commission(execution_id: &str) -> OrderUpdate {
+    OrderUpdate::CommissionReport(CommissionReport {
+        execution_id: execution_id.to_string(),
+        commission: 1.25,
+        currency: "USD".to_string(),
+        ..CommissionReport::default()
+    })
+}
+
+#[test]
+fn test_order_updates_are_typed_and_notices_skipped() {
+    let event = BrokerEvent::from_order_update(&execution(7, "exec.1"))
+        .expect("Expected an execution event");
+    assert_eq!(event.event_type, BrokerEventType::Execution);
+    assert_eq!(event.order_id, Some(7));
+    assert_eq!(event.payload["shares"], 10.0);
+
+    let status = OrderUpdate::OrderStatus(OrderStatus {
+        order_id: 7,
+        status: "Submitted".to_string(),
+        ..OrderStatus::default()
+    });
+    let event = BrokerEvent::from_order_update(&status).expect("Expected an order event");
+    assert_eq!(event.event_type, BrokerEventType::Order);
+    assert_eq!(event.payload["status"], "Submitted");
+
+    let notice = OrderUpdate::Message(Notice {
+        code: 2104,
+        message: "Market data farm connection is OK".to_string(),
+    });
+    assert_eq!(BrokerEvent::from_order_update(&notice), None);
+}
+
+#[tokio::test]
+async fn test_commission_is_filed_under_the_order_of_its_execution() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        for order_update in [execution(7, "exec.1"), commission("exec.1")] {
+            let event =
+                BrokerEvent::from_order_update(&order_update).expect("Expected an event to log");
+            record_broker_event(&pool, &event)
+                .await
+                .expect("Expected event to be recorded");
+        }
+
+        let events = sqlx::query_as::<_, (String, Option<i32>, serde_json::Value)>(
+            "SELECT event_type, order_id, payload FROM trading.broker_events ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .expect("Expected to read broker events");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, "execution");
+        assert_eq!(events[1].0, "commission");
+        assert_eq!(events[1].1, Some(7));
+        assert_eq!(events[1].2["commission"], 1.25);
+    })
+    .await;
+}
@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, Status, StrategyFullKeys},
+        models_crud::{open_stock_orders::get_open_stock_orders_crud, strategy::get_strategy_crud},
+    },
+    execution::notices::{BrokerNotice, NoticeKind, PacingBackoff, handle_broker_notice},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "notice_strat";
+
+#[test]
+fn test_notice_codes_are_classified() {
+    let kind = |code, message| BrokerNotice::new(None, code, message).kind();
+    assert_eq!(kind(201, "Order rejected"), NoticeKind::OrderRejected);
+    assert_eq!(kind(202, "Order Canceled"), NoticeKind::OrderCancelled);
+    assert_eq!(
+        kind(100, "Max rate of messages"),
+        NoticeKind::PacingViolation
+    );
+    assert_eq!(
+        kind(
+            162,
+            "Historical Market Data Service error message:Pacing violation"
+        ),
+        NoticeKind::PacingViolation
+    );
+    assert_eq!(
+        kind(
+            162,
+            "Historical Market Data Service error message:HMDS query returned no data"
+        ),
+        NoticeKind::Other
+    );
+    assert_eq!(
+        kind(2104, "Market data farm connection is OK"),
+        NoticeKind::Informational
+    );
+}
+
+#[test]
+fn test_pacing_backoff_pauses_for_its_delay() {
+    let backoff = PacingBackoff::new(Duration::from_secs(2));
+    let now = Instant::now();
+    assert_eq!(backoff.remaining_at(now), Duration::ZERO);
+
+    backoff.trigger_at(now);
+    assert_eq!(backoff.remaining_at(now), Duration::from_secs(2));
+    assert_eq!(
+        backoff.remaining_at(now + Duration::from_secs(1)),
+        Duration::from_secs(1)
+    );
+    assert_eq!(
+        backoff.remaining_at(now + Duration::from_secs(3)),
+        Duration::ZERO
+    );
+}
+
+#[tokio::test]
+async fn test_rejection_notice_removes_open_order() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+        let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
+        let pk = OpenStockOrdersPrimaryKeys {
+            order_perm_id: 2001,
+            order_id: 21,
+        };
+        open_stock_orders_crud
+            .create(&OpenStockOrdersFullKeys {
+                order_perm_id: pk.order_perm_id,
+                order_id: pk.order_id,
+                strategy: STRATEGY.to_string(),
+                stock: "QQQ".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                time: Utc::now(),
+                quantity: 10.0,
+                executions: vec![],
+                filled: 0.0,
+                order_snapshot: None,
+            })
+            .await
+            .expect("Expected to create open order");
+
+        let backoff = PacingBackoff::default();
+        let removed = handle_broker_notice(
+            pool.clone(),
+            &backoff,
+            &BrokerNotice::new(
+                Some(21),
+                201,
+                "Order rejected - reason: insufficient margin",
+            ),
+        )
+        .await;
+        let open_order = open_stock_orders_crud
+            .read(&pk)
+            .await
+            .expect("Expected to read open order");
+
+        assert_eq!(removed, Ok(true));
+        assert!(open_order.is_none());
+        assert_eq!(backoff.remaining_at(Instant::now()), Duration::ZERO);
+    })
+    .await;
+}
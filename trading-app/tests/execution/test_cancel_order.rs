@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, Status, StrategyFullKeys},
+        models_crud::{open_stock_orders::get_open_stock_orders_crud, strategy::get_strategy_crud},
+    },
+    execution::{
+        blocking_pool::BlockingPool,
+        cancel::{CancelRequest, OrderCanceller, cancel_open_order},
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "cancel_strat";
+
+/// Records the order ids cancels were dispatched for instead of sending them to IBKR
+struct StubCanceller {
+    status: String,
+    dispatched: Mutex<Vec<i32>>,
+}
+
+impl OrderCanceller for StubCanceller {
+    fn cancel_order(&self, order_id: i32) -> Result<String, String> {
+        self.dispatched.lock().unwrap().push(order_id);
+        Ok(self.status.clone())
+    }
+}
+
+fn open_order(order_perm_id: i32, order_id: i32) -> OpenStockOrdersFullKeys {
+    OpenStockOrdersFullKeys {
+        order_perm_id,
+        order_id,
+        strategy: STRATEGY.to_string(),
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        time: Utc::now(),
+        quantity: 10.0,
+        executions: vec![],
+        filled: 0.0,
+        order_snapshot: None,
+    }
+}
+
+#[tokio::test]
+async fn test_cancel_is_dispatched_for_the_requested_order() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+        let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
+        for order in [open_order(1001, 11), open_order(1002, 12)] {
+            open_stock_orders_crud
+                .create(&order)
+                .await
+                .expect("Expected to create open order");
+        }
+
+        let canceller = Arc::new(StubCanceller {
+            status: "Cancelled".to_string(),
+            dispatched: Mutex::new(vec![]),
+        });
+        let response = cancel_open_order(
+            pool.clone(),
+            &BlockingPool::new(1),
+            canceller.clone(),
+            &CancelRequest {
+                order_id: None,
+                perm_id: Some(1002),
+            },
+        )
+        .await
+        .expect("Expected cancel to succeed");
+
+        let cancelled_order = open_stock_orders_crud
+            .read(&OpenStockOrdersPrimaryKeys {
+                order_perm_id: 1002,
+                order_id: 12,
+            })
+            .await
+            .expect("Expected to read open order");
+        let other_order = open_stock_orders_crud
+            .read(&OpenStockOrdersPrimaryKeys {
+                order_perm_id: 1001,
+                order_id: 11,
+            })
+            .await
+            .expect("Expected to read open order");
+        assert_eq!(*canceller.dispatched.lock().unwrap(), vec![12]);
+        assert_eq!(response.order_id, 12);
+        assert!(response.removed);
+        assert!(cancelled_order.is_none());
+        assert!(other_order.is_some());
+    })
+    .await;
+}
@@ -0,0 +1,39 @@
+use sqlx::postgres::PgPoolOptions;
+use trading_app::execution::order_engine::OrderEngine;
+
+use crate::common::fixtures::{TestStrategy, stock};
+
+#[tokio::test]
+async fn test_contested_contract_is_recorded() {
+    // OrderEngine::new never touches the DB
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost/unused")
+        .expect("Expected lazy pool");
+    let strategies = vec![
+        TestStrategy {
+            priority: 1,
+            contracts: ["QQQ", "SPY"].map(stock).to_vec(),
+            ..TestStrategy::new("low_priority")
+        },
+        TestStrategy {
+            priority: 2,
+            contracts: ["QQQ", "IWM"].map(stock).to_vec(),
+            ..TestStrategy::new("high_priority")
+        },
+    ];
+
+    let order_engine = OrderEngine::new(pool, strategies);
+
+    let conflicts = order_engine.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    let ((security_type, symbol), claimants) = &conflicts[0];
+    assert_eq!(
+        security_type,
+        &ibapi::prelude::SecurityType::Stock.to_string()
+    );
+    assert_eq!(symbol, "QQQ");
+    assert_eq!(
+        claimants,
+        &vec!["low_priority".to_string(), "high_priority".to_string()]
+    );
+}
@@ -0,0 +1,83 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use ibapi::prelude::Contract;
+use sqlx::postgres::PgPoolOptions;
+use trading_app::execution::{
+    contract_validation::{ContractValidationCache, ContractValidator},
+    order_engine::OrderEngine,
+};
+
+use crate::common::fixtures::{TestStrategy, stock};
+
+/// Knows every symbol but the typo'd one, counting the symbols it is asked about
+struct StubValidator {
+    invalid_symbols: HashSet<&'static str>,
+    requested: Mutex<Vec<String>>,
+}
+
+impl ContractValidator for StubValidator {
+    fn validate_contract(&self, contract: &Contract) -> Option<Contract> {
+        self.requested
+            .lock()
+            .unwrap()
+            .push(contract.symbol.to_string());
+        if self.invalid_symbols.contains(contract.symbol.as_str()) {
+            None
+        } else {
+            Some(contract.clone())
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_contract_is_flagged_at_registration() {
+    // OrderEngine::new_validated never touches the DB
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost/unused")
+        .expect("Expected lazy pool");
+    let validator = StubValidator {
+        invalid_symbols: HashSet::from(["QQQQ"]),
+        requested: Mutex::new(Vec::new()),
+    };
+    let strategies = vec![
+        TestStrategy {
+            priority: 1,
+            contracts: ["QQQQ", "SPY"].map(stock).to_vec(),
+            ..TestStrategy::new("typo_strategy")
+        },
+        TestStrategy {
+            priority: 2,
+            contracts: ["QQQQ", "SPY"].map(stock).to_vec(),
+            ..TestStrategy::new("other_strategy")
+        },
+    ];
+
+    let order_engine = OrderEngine::new_validated(
+        pool,
+        strategies,
+        &validator,
+        &ContractValidationCache::new(),
+    );
+
+    let invalid_contracts: Vec<(String, String)> = order_engine
+        .invalid_contracts()
+        .into_iter()
+        .map(|invalid| (invalid.strategy, invalid.contract.symbol.to_string()))
+        .collect();
+    assert_eq!(
+        invalid_contracts,
+        vec![
+            ("typo_strategy".to_string(), "QQQQ".to_string()),
+            ("other_strategy".to_string(), "QQQQ".to_string()),
+        ]
+    );
+    // QQQQ was never registered so only SPY is contested
+    let conflicts = order_engine.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].0.1, "SPY");
+    // each contract is only validated once across both strategies
+    assert_eq!(
+        *validator.requested.lock().unwrap(),
+        vec!["QQQQ".to_string(), "SPY".to_string()]
+    );
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::dec;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            CurrentStockPositionsFullKeys, EquitySnapshotsPrimaryKeys, ExecutionSide,
+            HistoricalDataFullKeys, Status, StockTransactionsFullKeys, StrategyFullKeys,
+        },
+        models_crud::{
+            current_stock_positions::get_current_stock_positions_crud,
+            equity_snapshots::get_equity_snapshots_crud,
+            historical_data::{LastCloseCache, get_historical_data_crud},
+            stock_transactions::get_stock_transactions_crud,
+            strategy::get_strategy_crud,
+        },
+    },
+    execution::equity_snapshots::{PositionMark, marked_value, write_equity_snapshots},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "equity_snapshot_strat";
+const STOCK: &str = "EQSNAP";
+
+#[test]
+fn test_marked_value_marks_positions_at_last_price() {
+    let positions = [
+        PositionMark {
+            quantity: 10.0,
+            avg_price: 100.0,
+            last_price: Some(110.0),
+            multiplier: 1.0,
+        },
+        // short option leg, marked with its multiplier
+        PositionMark {
+            quantity: -2.0,
+            avg_price: 1.5,
+            last_price: Some(2.0),
+            multiplier: 100.0,
+        },
+    ];
+    assert_eq!(marked_value(500.0, &positions), 500.0 + 1100.0 - 400.0);
+}
+
+#[test]
+fn test_marked_value_falls_back_to_avg_price_without_bar() {
+    let positions = [PositionMark {
+        quantity: 5.0,
+        avg_price: 20.0,
+        last_price: None,
+        multiplier: 1.0,
+    }];
+    assert_eq!(marked_value(0.0, &positions), 100.0);
+    assert_eq!(marked_value(250.0, &[]), 250.0);
+}
+
+#[tokio::test]
+async fn test_snapshot_writer_records_marked_value_of_position() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        // bought 10 @ 100 and the last bar closed at 110
+        get_stock_transactions_crud(pool.clone())
+            .create(&StockTransactionsFullKeys {
+                execution_id: "equity_snapshot_exec".to_string(),
+                strategy: STRATEGY.to_string(),
+                stock: STOCK.to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                order_perm_id: 1,
+                time: Utc::now(),
+                price: 100.0,
+                quantity: ExecutionSide::Bought.signed(10.0),
+                fees: dec!(0),
+                raw_broker_time: None,
+                closes_position: false,
+                realized_pnl: None,
+            })
+            .await
+            .expect("Expected to create stock transaction");
+        get_current_stock_positions_crud(pool.clone())
+            .create(&CurrentStockPositionsFullKeys {
+                stock: STOCK.to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                strategy: STRATEGY.to_string(),
+                quantity: 10.0,
+                avg_price: 100.0,
+            })
+            .await
+            .expect("Expected to create stock position");
+        let bar_time = DateTime::from_timestamp(1_700_000_000, 0).expect("Expected valid bar time");
+        get_historical_data_crud(pool.clone())
+            .create(&HistoricalDataFullKeys {
+                stock: STOCK.to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                time: bar_time,
+                open: 100.0,
+                high: 111.0,
+                low: 99.0,
+                close: 110.0,
+                volume: dec!(1000),
+            })
+            .await
+            .expect("Expected to create historical bar");
+
+        let snapshot_time = Utc::now();
+        let values =
+            write_equity_snapshots(pool.clone(), LastCloseCache::default(), snapshot_time).await;
+        let snapshot = get_equity_snapshots_crud(pool.clone())
+            .read(&EquitySnapshotsPrimaryKeys {
+                strategy: STRATEGY.to_string(),
+                time: snapshot_time,
+            })
+            .await;
+
+        let values = values.expect("Expected to write equity snapshots");
+        let snapshot = snapshot
+            .expect("Expected to read equity snapshot")
+            .expect("Expected equity snapshot to exist");
+        // 10000 initial capital - 1000 spent + 10 marked at 110
+        assert_eq!(values.get(STRATEGY), Some(&10100.0));
+        assert_eq!(snapshot.value, 10100.0);
+    })
+    .await;
+}
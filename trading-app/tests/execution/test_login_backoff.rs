@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use trading_app::ibc::{LoginBackoff, LoginFailureAction};
+
+fn backoff() -> LoginBackoff {
+    LoginBackoff::new(
+        Duration::from_secs(10),
+        Duration::from_secs(60),
+        4,
+        Duration::from_secs(3600),
+    )
+}
+
+#[test]
+fn test_failures_back_off_until_max_delay() {
+    let mut backoff = backoff();
+    let delays: Vec<_> = (0..3).map(|_| backoff.on_failure()).collect();
+    assert_eq!(
+        delays,
+        vec![
+            LoginFailureAction::Retry(Duration::from_secs(10)),
+            LoginFailureAction::Retry(Duration::from_secs(20)),
+            LoginFailureAction::Retry(Duration::from_secs(40)),
+        ]
+    );
+
+    let mut backoff = LoginBackoff::new(
+        Duration::from_secs(10),
+        Duration::from_secs(60),
+        10,
+        Duration::from_secs(3600),
+    );
+    for _ in 0..3 {
+        backoff.on_failure();
+    }
+    assert_eq!(
+        backoff.on_failure(),
+        LoginFailureAction::Retry(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn test_repeated_failures_alert_once_at_threshold() {
+    let mut backoff = backoff();
+    let actions: Vec<_> = (0..6).map(|_| backoff.on_failure()).collect();
+
+    let alerts: Vec<usize> = actions
+        .iter()
+        .enumerate()
+        .filter(|(_, action)| matches!(action, LoginFailureAction::Alert(_)))
+        .map(|(failure, _)| failure + 1)
+        .collect();
+    assert_eq!(alerts, vec![4]);
+    assert_eq!(
+        actions[3],
+        LoginFailureAction::Alert(Duration::from_secs(3600))
+    );
+    // past the threshold the app sleeps longer instead of spinning
+    assert_eq!(
+        actions[5],
+        LoginFailureAction::Retry(Duration::from_secs(3600))
+    );
+    assert_eq!(backoff.consecutive_failures(), 6);
+}
+
+#[test]
+fn test_successful_login_resets_the_count() {
+    let mut backoff = backoff();
+    for _ in 0..3 {
+        backoff.on_failure();
+    }
+    backoff.on_success();
+    assert_eq!(backoff.consecutive_failures(), 0);
+    assert_eq!(
+        backoff.on_failure(),
+        LoginFailureAction::Retry(Duration::from_secs(10))
+    );
+}
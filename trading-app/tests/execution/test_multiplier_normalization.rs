@@ -0,0 +1,157 @@
+use trading_app::database::{
+    crud::CRUDTrait,
+    models::{
+        CurrentOptionPositionsFullKeys, OptionType, Status, StrategyFullKeys,
+        TargetOptionPositionsFullKeys, multiplier_value, normalize_multiplier,
+    },
+    models_crud::{
+        current_option_positions::{
+            get_current_option_positions_crud, get_specific_current_option_positions_crud,
+        },
+        strategy::get_strategy_crud,
+        target_option_positions::get_target_option_positions_crud,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STOCK: &str = "MULTNORM";
+const NORMALIZE_MIGRATION: &str =
+    include_str!("../../migrations/20250809000000_normalize_multipliers.sql");
+
+#[test]
+fn test_whole_multipliers_have_one_canonical_form() {
+    assert_eq!(normalize_multiplier("100"), "100");
+    assert_eq!(normalize_multiplier("100.0"), "100");
+    assert_eq!(normalize_multiplier(" 100.00 "), "100");
+    assert_eq!(normalize_multiplier("12.5"), "12.5");
+    assert_eq!(normalize_multiplier(""), "");
+    assert_eq!(multiplier_value("100.0"), Ok(100.0));
+    assert!(multiplier_value("").is_err());
+}
+
+#[tokio::test]
+async fn test_executions_with_100_and_100_0_aggregate_into_one_position() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: "unknown".to_string(),
+                capital: 0.0,
+                initial_capital: 0.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create unknown strategy");
+
+        let positions_crud = get_specific_current_option_positions_crud(pool.clone());
+        for (multiplier, qty) in [("100", 2.0), ("100.0", 3.0)] {
+            positions_crud
+                .update_unknown_strat_positions(
+                    STOCK.to_string(),
+                    "NASDAQ".to_string(),
+                    "20301220".to_string(),
+                    400.0,
+                    multiplier.to_string(),
+                    OptionType::Call,
+                    qty,
+                )
+                .await
+                .expect("Expected to book execution to unknown strategy");
+        }
+        let positions: Vec<_> = positions_crud
+            .get_all_positions_by_contract()
+            .await
+            .expect("Expected to read positions by contract")
+            .into_iter()
+            .filter(|position| position.stock == STOCK)
+            .collect();
+
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].multiplier, "100");
+        assert_eq!(positions[0].quantity, 5.0);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_migration_merges_rows_stored_under_both_spellings() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: "multnorm_strat".to_string(),
+                capital: 0.0,
+                initial_capital: 0.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        // Rows as written before multipliers were normalised on the way in
+        let positions_crud = get_current_option_positions_crud(pool.clone());
+        let targets_crud = get_target_option_positions_crud(pool.clone());
+        for (multiplier, quantity, avg_price) in [("100", 2.0, 1.0), ("100.0", 3.0, 2.0)] {
+            positions_crud
+                .create(&CurrentOptionPositionsFullKeys {
+                    stock: STOCK.to_string(),
+                    primary_exchange: "NASDAQ".to_string(),
+                    strategy: "multnorm_strat".to_string(),
+                    expiry: "20301220".to_string(),
+                    strike: 400.0,
+                    multiplier: multiplier.to_string(),
+                    option_type: OptionType::Call,
+                    quantity,
+                    avg_price,
+                })
+                .await
+                .expect("Expected to create position");
+        }
+        targets_crud
+            .create(&TargetOptionPositionsFullKeys {
+                stock: STOCK.to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                strategy: "multnorm_strat".to_string(),
+                expiry: "20301220".to_string(),
+                strike: 400.0,
+                multiplier: "100.00".to_string(),
+                option_type: OptionType::Call,
+                quantity: 5.0,
+                avg_price: 0.0,
+            })
+            .await
+            .expect("Expected to create target");
+
+        sqlx::raw_sql(NORMALIZE_MIGRATION)
+            .execute(&pool)
+            .await
+            .expect("Expected the normalisation migration to run");
+
+        let positions: Vec<_> = positions_crud
+            .read_all()
+            .await
+            .expect("Expected to read positions")
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|position| position.stock == STOCK)
+            .collect();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].multiplier, "100");
+        assert_eq!(positions[0].quantity, 5.0);
+        assert!((positions[0].avg_price - 1.6).abs() < 1e-9);
+
+        let targets: Vec<_> = targets_crud
+            .read_all()
+            .await
+            .expect("Expected to read targets")
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|target| target.stock == STOCK)
+            .collect();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].multiplier, "100");
+    })
+    .await;
+}
@@ -0,0 +1,65 @@
+use trading_app::execution::netting::{
+    NettingDecision, NettingPolicy, effective_diff, net_against_working, working_remaining,
+};
+
+#[test]
+fn working_buy_is_netted_against_diff() {
+    // Working buy of 50 with nothing filled yet
+    let working = working_remaining(&[(50.0, 0.0)]);
+    assert_eq!(working, 50.0);
+    assert_eq!(effective_diff(100.0, working), 50.0);
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 100.0, working),
+        NettingDecision::Place(50.0)
+    );
+}
+
+#[test]
+fn partially_filled_orders_only_count_remaining() {
+    assert_eq!(working_remaining(&[(50.0, 20.0), (10.0, 0.0)]), 40.0);
+    assert_eq!(working_remaining(&[(-50.0, 20.0)]), -30.0);
+}
+
+#[test]
+fn covered_diff_is_held() {
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 50.0, 50.0),
+        NettingDecision::Hold
+    );
+}
+
+#[test]
+fn opposite_or_overshooting_orders_are_replaced() {
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 100.0, -50.0),
+        NettingDecision::CancelAndPlace(100.0)
+    );
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 20.0, 50.0),
+        NettingDecision::CancelAndPlace(20.0)
+    );
+}
+
+#[test]
+fn replace_by_cancel_always_replaces_working_orders() {
+    assert_eq!(
+        net_against_working(NettingPolicy::ReplaceByCancel, 100.0, 50.0),
+        NettingDecision::CancelAndPlace(100.0)
+    );
+    assert_eq!(
+        net_against_working(NettingPolicy::ReplaceByCancel, 100.0, 0.0),
+        NettingDecision::Place(100.0)
+    );
+}
+
+#[test]
+fn zero_diff_cancels_working_orders() {
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 0.0, 50.0),
+        NettingDecision::CancelAll
+    );
+    assert_eq!(
+        net_against_working(NettingPolicy::Net, 0.0, 0.0),
+        NettingDecision::Hold
+    );
+}
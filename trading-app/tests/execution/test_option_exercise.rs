@@ -0,0 +1,225 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal::dec;
+use sqlx::PgPool;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            CurrentOptionPositionsFullKeys, CurrentOptionPositionsPrimaryKeys,
+            CurrentStockPositionsPrimaryKeys, ExecutionSide, OptionTransactionsPrimaryKeys,
+            OptionType, Status, StockTransactionsFullKeys, StockTransactionsPrimaryKeys,
+            StrategyFullKeys,
+        },
+        models_crud::{
+            current_option_positions::get_current_option_positions_crud,
+            current_stock_positions::get_current_stock_positions_crud,
+            option_transactions::get_option_transactions_crud,
+            stock_transactions::get_stock_transactions_crud, strategy::get_strategy_crud,
+        },
+    },
+    execution::exercise::{
+        OptionExecutionKind, OptionExercise, book_option_exercise, delivery_execution_id,
+        exercised_position_owner, exercised_shares, option_execution_kind,
+    },
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "assigned_strat";
+const STOCK: &str = "ASSIGNED";
+const EXECUTION_ID: &str = "0000e0d5.assigned.01.01";
+
+#[test]
+fn test_zero_priced_option_executions_are_exercises_or_assignments() {
+    assert_eq!(
+        option_execution_kind(ExecutionSide::Bought, 0.0),
+        OptionExecutionKind::Assignment
+    );
+    assert_eq!(
+        option_execution_kind(ExecutionSide::Sold, 0.0),
+        OptionExecutionKind::Exercise
+    );
+    assert_eq!(
+        option_execution_kind(ExecutionSide::Bought, 1.25),
+        OptionExecutionKind::Trade
+    );
+
+    // short 2 puts assigned buy 200 shares, long 1 put exercised sells 100
+    assert_eq!(exercised_shares(&OptionType::Put, 100.0, -2.0), 200.0);
+    assert_eq!(exercised_shares(&OptionType::Put, 100.0, 1.0), -100.0);
+    assert_eq!(exercised_shares(&OptionType::Call, 100.0, -1.0), -100.0);
+}
+
+#[test]
+fn test_assignment_is_owned_by_the_only_strategy_short_enough() {
+    let holders = vec![
+        ("long_strat".to_string(), 3.0),
+        ("short_strat".to_string(), -2.0),
+        ("small_short_strat".to_string(), -1.0),
+    ];
+
+    // 2 contracts bought back by assignment - only short_strat was short 2
+    assert_eq!(
+        exercised_position_owner(&holders, 2.0),
+        Some("short_strat".to_string())
+    );
+    // either short could have been assigned 1
+    assert_eq!(exercised_position_owner(&holders, 1.0), None);
+}
+
+/// assigned_strat, short 2 of the 50 puts sold @ 1.50
+async fn create_short_put_position(pool: &PgPool) {
+    get_strategy_crud(pool.clone())
+        .create_or_ignore(&StrategyFullKeys {
+            strategy: STRATEGY.to_string(),
+            capital: 10000.0,
+            initial_capital: 10000.0,
+            status: Status::Inactive,
+        })
+        .await
+        .expect("Expected to create strategy");
+    get_current_option_positions_crud(pool.clone())
+        .create(&CurrentOptionPositionsFullKeys {
+            stock: STOCK.to_string(),
+            primary_exchange: "NYSE".to_string(),
+            strategy: STRATEGY.to_string(),
+            expiry: "20250718".to_string(),
+            strike: 50.0,
+            multiplier: "100".to_string(),
+            option_type: OptionType::Put,
+            quantity: -2.0,
+            avg_price: 1.5,
+        })
+        .await
+        .expect("Expected to create short put position");
+}
+
+fn assignment_of_2_puts() -> OptionExercise {
+    OptionExercise {
+        execution_id: EXECUTION_ID.to_string(),
+        order_perm_id: 0,
+        stock: STOCK.to_string(),
+        primary_exchange: "NYSE".to_string(),
+        expiry: "20250718".to_string(),
+        strike: 50.0,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Put,
+        side: ExecutionSide::Bought,
+        contracts: 2.0,
+        time: Utc.with_ymd_and_hms(2025, 7, 19, 0, 0, 0).unwrap(),
+        raw_broker_time: None,
+    }
+}
+
+fn option_pk() -> CurrentOptionPositionsPrimaryKeys {
+    CurrentOptionPositionsPrimaryKeys {
+        stock: STOCK.to_string(),
+        primary_exchange: "NYSE".to_string(),
+        strategy: STRATEGY.to_string(),
+        expiry: "20250718".to_string(),
+        strike: 50.0,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Put,
+    }
+}
+
+#[tokio::test]
+async fn test_short_put_assignment_books_long_stock() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        create_short_put_position(&pool).await;
+
+        let booked_to =
+            book_option_exercise(pool.clone(), &assignment_of_2_puts(), "unknown").await;
+
+        let option_position = get_current_option_positions_crud(pool.clone())
+            .read(&option_pk())
+            .await
+            .expect("Expected to read option position");
+        let stock_position = get_current_stock_positions_crud(pool.clone())
+            .read(&CurrentStockPositionsPrimaryKeys {
+                stock: STOCK.to_string(),
+                primary_exchange: "NYSE".to_string(),
+                strategy: STRATEGY.to_string(),
+            })
+            .await
+            .expect("Expected to read stock position");
+        let option_transaction = get_option_transactions_crud(pool.clone())
+            .read(&OptionTransactionsPrimaryKeys {
+                execution_id: EXECUTION_ID.to_string(),
+            })
+            .await
+            .expect("Expected to read option transaction");
+        let stock_transaction = get_stock_transactions_crud(pool.clone())
+            .read(&StockTransactionsPrimaryKeys {
+                execution_id: delivery_execution_id(EXECUTION_ID),
+            })
+            .await
+            .expect("Expected to read stock transaction");
+
+        assert_eq!(booked_to, Ok(STRATEGY.to_string()));
+        let option_position = option_position.expect("Expected option position to be kept");
+        assert_eq!(option_position.quantity, 0.0);
+        let stock_position = stock_position.expect("Expected stock position from the assignment");
+        assert_eq!(stock_position.quantity, 200.0);
+        assert_eq!(stock_position.avg_price, 50.0);
+        let option_transaction = option_transaction.expect("Expected option transaction");
+        assert_eq!(option_transaction.quantity, 2.0);
+        // premium kept
+        assert_eq!(option_transaction.realized_pnl, Some(300.0));
+        let stock_transaction = stock_transaction.expect("Expected stock transaction");
+        assert_eq!(
+            (stock_transaction.quantity, stock_transaction.price),
+            (200.0, 50.0)
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_failed_stock_leg_leaves_the_option_leg_unbooked() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        create_short_put_position(&pool).await;
+        // the delivery's execution_id is already taken, so the stock leg fails to insert
+        get_stock_transactions_crud(pool.clone())
+            .create(&StockTransactionsFullKeys {
+                strategy: STRATEGY.to_string(),
+                execution_id: delivery_execution_id(EXECUTION_ID),
+                order_perm_id: 0,
+                stock: STOCK.to_string(),
+                primary_exchange: "NYSE".to_string(),
+                time: Utc.with_ymd_and_hms(2025, 7, 18, 0, 0, 0).unwrap(),
+                price: 50.0,
+                quantity: 1.0,
+                fees: dec!(0),
+                raw_broker_time: None,
+                closes_position: false,
+                realized_pnl: None,
+            })
+            .await
+            .expect("Expected to create conflicting stock transaction");
+
+        let booked_to =
+            book_option_exercise(pool.clone(), &assignment_of_2_puts(), "unknown").await;
+
+        let option_position = get_current_option_positions_crud(pool.clone())
+            .read(&option_pk())
+            .await
+            .expect("Expected to read option position")
+            .expect("Expected option position to be kept");
+        let option_transaction = get_option_transactions_crud(pool.clone())
+            .read(&OptionTransactionsPrimaryKeys {
+                execution_id: EXECUTION_ID.to_string(),
+            })
+            .await
+            .expect("Expected to read option transaction");
+
+        assert!(booked_to.is_err());
+        assert_eq!(option_position.quantity, -2.0);
+        assert!(option_transaction.is_none());
+    })
+    .await;
+}
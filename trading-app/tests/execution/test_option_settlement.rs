@@ -0,0 +1,89 @@
+use chrono::{TimeZone, Utc};
+use trading_app::{
+    database::models::OptionType,
+    execution::equity_snapshots::{
+        NetLiquidation, OptionSettlement, PositionMark, is_expired, net_liquidation, settle_option,
+    },
+};
+
+#[test]
+fn test_short_put_premium_is_a_liability() {
+    // sold 1 put @ 2.00 against 10000 of cash, now marked at 5.00
+    let cash = 10000.0 + 2.0 * 100.0;
+    let short_put = PositionMark {
+        quantity: -1.0,
+        avg_price: 2.0,
+        last_price: Some(5.0),
+        multiplier: 100.0,
+    };
+
+    let net = net_liquidation(cash, &[short_put]);
+    assert_eq!(
+        net,
+        NetLiquidation {
+            cash: 10200.0,
+            long_value: 0.0,
+            short_liability: 500.0,
+        }
+    );
+    assert_eq!(net.value(), 9700.0);
+}
+
+#[test]
+fn test_short_put_assigned_buys_stock_at_strike() {
+    let expiry = "20250718";
+    assert!(!is_expired(
+        expiry,
+        Utc.with_ymd_and_hms(2025, 7, 18, 19, 0, 0).unwrap()
+    ));
+    assert!(is_expired(
+        expiry,
+        Utc.with_ymd_and_hms(2025, 7, 19, 0, 0, 0).unwrap()
+    ));
+
+    // short 1 100 strike put, underlying closed at 95 on expiry
+    let cash = 10000.0 + 2.0 * 100.0;
+    let settlement = settle_option(&OptionType::Put, 100.0, 100.0, -1.0, 95.0);
+    assert_eq!(
+        settlement,
+        OptionSettlement {
+            stock_quantity: 100.0,
+            cash: -10000.0,
+        }
+    );
+
+    // left holding 100 shares bought at 100 and the premium, worth what the put was marked at
+    let stock = PositionMark {
+        quantity: settlement.stock_quantity,
+        avg_price: 100.0,
+        last_price: Some(95.0),
+        multiplier: 1.0,
+    };
+    let net = net_liquidation(cash + settlement.cash, &[stock]);
+    assert_eq!(net.cash, 200.0);
+    assert_eq!(net.value(), 9700.0);
+}
+
+#[test]
+fn test_out_of_the_money_options_expire_worthless() {
+    assert_eq!(
+        settle_option(&OptionType::Put, 100.0, 100.0, -1.0, 105.0),
+        OptionSettlement::default()
+    );
+    assert_eq!(
+        settle_option(&OptionType::Call, 100.0, 100.0, 2.0, 100.0),
+        OptionSettlement::default()
+    );
+    // long call exercised buys, short call assigned sells
+    assert_eq!(
+        settle_option(&OptionType::Call, 100.0, 100.0, 2.0, 110.0).stock_quantity,
+        200.0
+    );
+    assert_eq!(
+        settle_option(&OptionType::Call, 100.0, 100.0, -1.0, 110.0),
+        OptionSettlement {
+            stock_quantity: -100.0,
+            cash: 10000.0,
+        }
+    );
+}
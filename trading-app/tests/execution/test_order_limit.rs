@@ -0,0 +1,316 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicI32, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use ibapi::{
+    orders::{Action, Order},
+    prelude::Contract,
+};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            AssetType, CurrentStockPositionsFullKeys, OpenStockOrdersFullKeys, Status,
+            StrategyFullKeys, TargetStockPositionsFullKeys,
+        },
+        models_crud::{
+            current_stock_positions::get_current_stock_positions_crud,
+            open_stock_orders::get_open_stock_orders_crud, strategy::get_strategy_crud,
+            target_stock_positions::get_target_stock_positions_crud,
+        },
+    },
+    execution::{
+        cancel::OrderCanceller,
+        order_engine::OrderEngine,
+        order_limit::{OrderLimitCheck, SessionOrderLimit, reduces_position},
+        place_order::OrderSubmitter,
+    },
+};
+
+use crate::common::{
+    fixtures::{RecordingSubmitter, TestStrategy, stock},
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const STRATEGY: &str = "order_limit_strat";
+
+/// IBKR turning down every order submitted
+#[derive(Default)]
+struct RejectingSubmitter {
+    next_order_id: AtomicI32,
+    attempts: AtomicUsize,
+}
+
+impl OrderSubmitter for RejectingSubmitter {
+    fn next_order_id(&self) -> i32 {
+        self.next_order_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn submit_order(
+        &self,
+        _order_id: i32,
+        _contract: &Contract,
+        _order: &Order,
+    ) -> Result<(), String> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Err("Order rejected".to_string())
+    }
+
+    fn min_tick(&self, _contract: &Contract) -> Result<f64, String> {
+        Ok(0.01)
+    }
+}
+
+impl OrderCanceller for RejectingSubmitter {
+    fn cancel_order(&self, _order_id: i32) -> Result<String, String> {
+        Ok("Cancelled".to_string())
+    }
+}
+
+fn nasdaq_stock(symbol: &str) -> Contract {
+    let mut contract = stock(symbol);
+    contract.primary_exchange = "NASDAQ".to_string();
+    contract
+}
+
+#[test]
+fn order_past_the_limit_is_suppressed() {
+    let limit = SessionOrderLimit::new(Some(3), None);
+    for _ in 0..3 {
+        assert_eq!(limit.check("strat_a"), OrderLimitCheck::Allowed);
+    }
+
+    // the 4th order is the first suppressed - the one alerted on
+    assert_eq!(
+        limit.check("strat_a"),
+        OrderLimitCheck::Suppressed { first: true }
+    );
+    assert_eq!(
+        limit.check("strat_a"),
+        OrderLimitCheck::Suppressed { first: false }
+    );
+    assert!(!limit.admit("strat_a"));
+}
+
+#[test]
+fn limit_is_counted_per_strategy() {
+    let limit = SessionOrderLimit::new(Some(1), None);
+    assert!(limit.admit("strat_a"));
+    assert!(!limit.admit("strat_a"));
+
+    assert!(limit.admit("strat_b"));
+}
+
+#[test]
+fn clones_share_the_session_counts() {
+    let limit = SessionOrderLimit::new(Some(2), None);
+    let cloned = limit.clone();
+    assert!(limit.admit("strat_a"));
+    assert!(cloned.admit("strat_a"));
+    assert!(!limit.admit("strat_a"));
+}
+
+#[test]
+fn released_order_frees_its_slot() {
+    let limit = SessionOrderLimit::new(Some(1), None);
+    assert!(limit.admit("strat_a"));
+    limit.release("strat_a");
+
+    assert!(limit.admit("strat_a"));
+    assert!(!limit.admit("strat_a"));
+}
+
+#[test]
+fn limit_is_alerted_once_even_after_a_release() {
+    let limit = SessionOrderLimit::new(Some(1), None);
+    assert!(limit.admit("strat_a"));
+    assert_eq!(
+        limit.check("strat_a"),
+        OrderLimitCheck::Suppressed { first: true }
+    );
+
+    limit.release("strat_a");
+    assert_eq!(limit.check("strat_a"), OrderLimitCheck::Allowed);
+    assert_eq!(
+        limit.check("strat_a"),
+        OrderLimitCheck::Suppressed { first: false }
+    );
+}
+
+#[test]
+fn no_limit_allows_every_order() {
+    let limit = SessionOrderLimit::default();
+    for _ in 0..1000 {
+        assert!(limit.admit("strat_a"));
+    }
+}
+
+#[test]
+fn only_orders_towards_flat_reduce_the_position() {
+    assert!(reduces_position(8.0, -8.0));
+    assert!(reduces_position(-5.0, 2.0));
+    assert!(!reduces_position(8.0, -10.0));
+    assert!(!reduces_position(8.0, 2.0));
+    assert!(!reduces_position(0.0, -1.0));
+}
+
+#[tokio::test]
+async fn only_placed_orders_that_add_risk_count_towards_the_limit() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Active,
+            })
+            .await
+            .expect("Expected to create strategy");
+        // QQQ and DIA are new positions, SPY is already covered by its working order and IWM
+        // is flattened
+        for (symbol, quantity) in [("QQQ", 10.0), ("DIA", 3.0), ("SPY", 5.0), ("IWM", 0.0)] {
+            get_target_stock_positions_crud(pool.clone())
+                .create(&TargetStockPositionsFullKeys {
+                    strategy: STRATEGY.to_string(),
+                    stock: symbol.to_string(),
+                    primary_exchange: "NASDAQ".to_string(),
+                    avg_price: 0.0,
+                    quantity,
+                })
+                .await
+                .expect("Expected to create target");
+        }
+        get_open_stock_orders_crud(pool.clone())
+            .create(&OpenStockOrdersFullKeys {
+                order_perm_id: 752_001,
+                order_id: 752_001,
+                strategy: STRATEGY.to_string(),
+                stock: "SPY".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                time: Utc::now(),
+                quantity: 5.0,
+                executions: vec![],
+                filled: 0.0,
+                order_snapshot: None,
+            })
+            .await
+            .expect("Expected to create open order");
+        get_current_stock_positions_crud(pool.clone())
+            .create(&CurrentStockPositionsFullKeys {
+                strategy: STRATEGY.to_string(),
+                stock: "IWM".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                avg_price: 200.0,
+                quantity: 8.0,
+            })
+            .await
+            .expect("Expected to create position");
+
+        let strategy = TestStrategy {
+            contracts: ["QQQ", "DIA", "SPY", "IWM"].map(nasdaq_stock).to_vec(),
+            ..TestStrategy::new(STRATEGY)
+        };
+        let mut order_engine = OrderEngine::new(pool.clone(), vec![strategy.clone()]);
+        let order_limit = SessionOrderLimit::new(Some(2), None);
+        order_engine.set_order_limit(order_limit.clone());
+        let client = Arc::new(RecordingSubmitter::default());
+
+        order_engine.place_orders_for_strategy(
+            strategy,
+            nasdaq_stock("QQQ"),
+            client.clone(),
+            AssetType::Stock,
+            true,
+        );
+        for _ in 0..200 {
+            if client.submitted().len() >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // nothing else trickles in
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut submitted: Vec<(String, Action, f64)> = client
+            .submitted()
+            .into_iter()
+            .map(|(_, contract, order)| (contract.symbol, order.action, order.total_quantity))
+            .collect();
+        submitted.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            submitted,
+            vec![
+                ("DIA".to_string(), Action::Buy, 3.0),
+                ("IWM".to_string(), Action::Sell, 8.0),
+                ("QQQ".to_string(), Action::Buy, 10.0),
+            ]
+        );
+        // QQQ and DIA used up the session's orders
+        assert!(!order_limit.admit(STRATEGY));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn orders_that_fail_to_place_do_not_count_towards_the_limit() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Active,
+            })
+            .await
+            .expect("Expected to create strategy");
+        get_target_stock_positions_crud(pool.clone())
+            .create(&TargetStockPositionsFullKeys {
+                strategy: STRATEGY.to_string(),
+                stock: "QQQ".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                avg_price: 0.0,
+                quantity: 10.0,
+            })
+            .await
+            .expect("Expected to create target");
+
+        let strategy = TestStrategy {
+            contracts: vec![nasdaq_stock("QQQ")],
+            ..TestStrategy::new(STRATEGY)
+        };
+        let mut order_engine = OrderEngine::new(pool.clone(), vec![strategy.clone()]);
+        let order_limit = SessionOrderLimit::new(Some(1), None);
+        order_engine.set_order_limit(order_limit.clone());
+        let client = Arc::new(RejectingSubmitter::default());
+
+        order_engine.place_orders_for_strategy(
+            strategy,
+            nasdaq_stock("QQQ"),
+            client.clone(),
+            AssetType::Stock,
+            true,
+        );
+        for _ in 0..200 {
+            if client.attempts.load(Ordering::SeqCst) >= 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // the slot is given back once place_order returns
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(client.attempts.load(Ordering::SeqCst), 1);
+        assert!(order_limit.admit(STRATEGY));
+        assert!(!order_limit.admit(STRATEGY));
+    })
+    .await;
+}
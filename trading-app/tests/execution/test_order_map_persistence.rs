@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use ibapi::{
+    orders::{Action, Order},
+    prelude::SecurityType,
+};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersFullKeys, OrderMapPrimaryKeys, Status, StrategyFullKeys},
+        models_crud::{
+            open_stock_orders::get_open_stock_orders_crud, order_map::get_order_map_crud,
+            strategy::get_strategy_crud,
+        },
+    },
+    execution::{
+        order_engine::OrderEngine,
+        order_map::{order_map_row, persist_placed_order, placed_order_from_row},
+    },
+    strategy::strategy::StrategyEnum,
+};
+
+use crate::common::{
+    fixtures::{RecordingSubmitter, qqq, qqq_put},
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const STRATEGY: &str = "order_map_strat";
+
+fn sell(quantity: f64) -> Order {
+    Order {
+        action: Action::Sell,
+        total_quantity: quantity,
+        ..Order::default()
+    }
+}
+
+async fn create_strategy(pool: &sqlx::PgPool) {
+    get_strategy_crud(pool.clone())
+        .create_or_ignore(&StrategyFullKeys {
+            strategy: STRATEGY.to_string(),
+            capital: 10000.0,
+            initial_capital: 10000.0,
+            status: Status::Inactive,
+        })
+        .await
+        .expect("Expected to create strategy");
+}
+
+#[test]
+fn test_row_round_trips_placed_order() {
+    let row = order_map_row(7, STRATEGY, &qqq_put(), &sell(2.0)).expect("Expected row");
+    let (order_id, (strategy, contract, order)) =
+        placed_order_from_row(row).expect("Expected placed order");
+
+    assert_eq!((order_id, strategy.as_str()), (7, STRATEGY));
+    assert_eq!(contract.security_type, SecurityType::Option);
+    assert_eq!(
+        (
+            contract.symbol.as_str(),
+            contract.primary_exchange.as_str(),
+            contract.last_trade_date_or_contract_month.as_str(),
+            contract.strike,
+            contract.multiplier.as_str(),
+            contract.right.as_str(),
+        ),
+        ("QQQ", "NASDAQ", "20250718", 500.0, "100", "P")
+    );
+    assert_eq!((order.action, order.total_quantity), (Action::Sell, 2.0));
+}
+
+#[tokio::test]
+async fn test_execution_after_restart_maps_to_strategy() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        create_strategy(&pool).await;
+
+        let order_id = {
+            let mut order_engine = OrderEngine::new(pool.clone(), Vec::<StrategyEnum>::new());
+            order_engine.set_persist_order_map(true);
+            order_engine
+                .place_order(
+                    STRATEGY.to_string(),
+                    Arc::new(RecordingSubmitter::new(727_001)),
+                    qqq_put(),
+                    sell(2.0),
+                    false,
+                )
+                .await
+                .expect("Expected order to be placed")
+        };
+
+        // restarted engine, order_map only as persisted
+        let order_engine = OrderEngine::new(pool.clone(), Vec::<StrategyEnum>::new());
+        assert_eq!(
+            order_engine
+                .strategy_for_order(order_id)
+                .expect("Expected to read order map"),
+            "Unknown strategy: not recorded in order_map"
+        );
+        let loaded = order_engine
+            .rehydrate_order_map()
+            .await
+            .expect("Expected to load order_map");
+        assert!(loaded >= 1);
+        assert_eq!(
+            order_engine
+                .strategy_for_order(order_id)
+                .expect("Expected to read order map"),
+            STRATEGY
+        );
+        let (_, contract, order) = order_engine
+            .get_placed_order(order_id)
+            .expect("Expected to read order map")
+            .expect("Expected order in rehydrated order map");
+        assert_eq!(contract.security_type, SecurityType::Option);
+        assert_eq!((contract.strike, contract.right.as_str()), (500.0, "P"));
+        assert_eq!((order.action, order.total_quantity), (Action::Sell, 2.0));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_rehydrating_prunes_settled_orders() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        create_strategy(&pool).await;
+        let (settled, still_open, recent) = (727_101, 727_102, 727_103);
+        for (order_id, days_ago) in [(settled, 2), (still_open, 2), (recent, 0)] {
+            let mut row =
+                order_map_row(order_id, STRATEGY, &qqq(), &sell(1.0)).expect("Expected row");
+            row.time = Utc::now() - Duration::days(days_ago);
+            persist_placed_order(pool.clone(), row)
+                .await
+                .expect("Expected order to be persisted");
+        }
+        get_open_stock_orders_crud(pool.clone())
+            .create(&OpenStockOrdersFullKeys {
+                order_perm_id: still_open,
+                order_id: still_open,
+                strategy: STRATEGY.to_string(),
+                stock: "QQQ".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                time: Utc::now() - Duration::days(2),
+                quantity: -1.0,
+                executions: vec![],
+                filled: 0.0,
+                order_snapshot: None,
+            })
+            .await
+            .expect("Expected to create open order");
+
+        let order_engine = OrderEngine::new(pool.clone(), Vec::<StrategyEnum>::new());
+        order_engine
+            .rehydrate_order_map()
+            .await
+            .expect("Expected to load order_map");
+
+        for (order_id, live) in [(settled, false), (still_open, true), (recent, true)] {
+            assert_eq!(
+                order_engine
+                    .get_placed_order(order_id)
+                    .expect("Expected to read order map")
+                    .is_some(),
+                live,
+                "order {} loaded",
+                order_id
+            );
+            let persisted = get_order_map_crud(pool.clone())
+                .read(&OrderMapPrimaryKeys { order_id })
+                .await
+                .expect("Expected to read order_map");
+            assert_eq!(persisted.is_some(), live, "order {} kept", order_id);
+        }
+    })
+    .await;
+}
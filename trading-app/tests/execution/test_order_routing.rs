@@ -0,0 +1,76 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ibapi::orders::Order;
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{
+    database::models::ExecutionSide,
+    execution::{
+        events::on_execution_updates::apply_execution_to_position, order_engine::OrderEngine,
+        place_order::routed_contract,
+    },
+    strategy::strategy::StrategyEnum,
+};
+
+use crate::common::fixtures::{RecordingSubmitter, qqq_on};
+
+#[tokio::test]
+async fn test_smart_routed_order_fills_primary_exchange_position() {
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost/unused")
+        .expect("Expected lazy pool");
+    let order_engine = OrderEngine::new(pool, Vec::<StrategyEnum>::new());
+    assert_eq!(order_engine.get_order_routing(), "SMART");
+    let submitter = Arc::new(RecordingSubmitter::default());
+
+    let order_id = order_engine
+        .place_order(
+            "routing_strat".to_string(),
+            submitter.clone(),
+            qqq_on("NASDAQ", "NASDAQ"),
+            Order::default(),
+            false,
+        )
+        .await
+        .expect("Expected order to be placed");
+
+    // routed SMART, still pinned to the NASDAQ listing
+    let (_, submitted, _) = submitter.submitted()[0].clone();
+    assert_eq!(submitted.exchange, "SMART");
+    assert_eq!(submitted.primary_exchange, "NASDAQ");
+
+    // the fill is booked against the order map's contract, keyed on the primary exchange
+    let (_, placed_contract, _) = order_engine
+        .get_placed_order(order_id)
+        .expect("Expected to read order map")
+        .expect("Expected placed order in order map");
+    let mut positions: HashMap<(String, String), (f64, f64)> =
+        HashMap::from([(("QQQ".to_string(), "NASDAQ".to_string()), (10.0, 500.0))]);
+    let position = positions
+        .get_mut(&(placed_contract.symbol, placed_contract.primary_exchange))
+        .expect("Expected order to key onto the existing position");
+    *position =
+        apply_execution_to_position(position.0, position.1, ExecutionSide::Bought, 10.0, 510.0);
+    assert_eq!(
+        positions[&("QQQ".to_string(), "NASDAQ".to_string())],
+        (20.0, 505.0)
+    );
+}
+
+#[test]
+fn test_routing_keeps_listing_exchange_as_primary() {
+    // listed exchange only, carried over as the primary exchange once routed
+    let routed = routed_contract(&qqq_on("NASDAQ", ""), "SMART");
+    assert_eq!(routed.exchange, "SMART");
+    assert_eq!(routed.primary_exchange, "NASDAQ");
+
+    // already SMART with no primary exchange to carry over
+    let routed = routed_contract(&qqq_on("SMART", ""), "SMART");
+    assert_eq!(routed.primary_exchange, "");
+
+    // no routing submits the contract as is
+    let unrouted = routed_contract(&qqq_on("NASDAQ", ""), "");
+    assert_eq!(
+        (unrouted.exchange, unrouted.primary_exchange),
+        ("NASDAQ".to_string(), String::new())
+    );
+}
@@ -0,0 +1,25 @@
+use trading_app::execution::netting::{round_quantity, scale_qty_diff};
+
+#[test]
+fn test_half_multiplier_halves_placed_quantity() {
+    // target of 100 against a flat position
+    let target = 100.0;
+    assert_eq!(scale_qty_diff(target - 0.0, 0.0, 0.5), 50.0);
+
+    // once the 50 fill the unchanged target of 100 is already met at half size
+    assert_eq!(scale_qty_diff(target - 50.0, 50.0, 0.5), 0.0);
+    assert_eq!(scale_qty_diff(0.0 - 50.0, 50.0, 0.5), -50.0);
+}
+
+#[test]
+fn test_full_size_position_is_cut_to_scaled_target() {
+    // built up at full size on paper, now trading live at a tenth
+    assert_eq!(scale_qty_diff(0.0, 200.0, 0.1), -180.0);
+    assert_eq!(scale_qty_diff(1.0, 1.0, 1.0), 1.0);
+}
+
+#[test]
+fn test_scaled_option_diff_is_whole_contracts() {
+    assert_eq!(round_quantity(scale_qty_diff(3.0, 0.0, 0.5), 0), 2.0);
+    assert_eq!(round_quantity(scale_qty_diff(-5.0, 0.0, 0.1), 0), -1.0);
+}
@@ -0,0 +1,85 @@
+use ibapi::{
+    contracts::ContractBuilder,
+    orders::{Action, Order, order_builder},
+    prelude::{Contract, SecurityType},
+};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersPrimaryKeys, Status, StrategyFullKeys},
+        models_crud::{open_stock_orders::get_open_stock_orders_crud, strategy::get_strategy_crud},
+    },
+    execution::{events::order_events::on_new_order_submitted, order_map::order_snapshot},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "order_snapshot_strat";
+
+fn aapl() -> Contract {
+    let mut contract = ContractBuilder::new()
+        .symbol("AAPL")
+        .security_type(SecurityType::Stock)
+        .exchange("SMART")
+        .currency("USD")
+        .build()
+        .expect("Expected to be able to build AAPL contract");
+    contract.primary_exchange = "NASDAQ".to_string();
+    contract
+}
+
+fn limit_buy() -> Order {
+    order_builder::limit_order(Action::Buy, 10.0, 212.37)
+}
+
+#[test]
+fn test_snapshot_keeps_every_parameter_submitted() {
+    let snapshot = order_snapshot(&aapl(), &limit_buy()).expect("Expected snapshot");
+
+    assert_eq!(snapshot["contract"]["symbol"], "AAPL");
+    assert_eq!(snapshot["contract"]["primary_exchange"], "NASDAQ");
+    assert_eq!(snapshot["order"]["total_quantity"], 10.0);
+    assert_eq!(snapshot["order"]["limit_price"], 212.37);
+}
+
+#[tokio::test]
+async fn test_placed_order_stores_its_snapshot() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        let pk = OpenStockOrdersPrimaryKeys {
+            order_perm_id: 2,
+            order_id: 1,
+        };
+        on_new_order_submitted(
+            pool.clone(),
+            pk.order_id,
+            pk.order_perm_id,
+            (STRATEGY.to_string(), aapl(), limit_buy()),
+        )
+        .expect("Expected open order to be recorded")
+        .await
+        .expect("Expected open order task to finish");
+
+        let open_order = get_open_stock_orders_crud(pool.clone())
+            .read(&pk)
+            .await
+            .expect("Expected to read open order")
+            .expect("Expected open order to be recorded");
+        assert_eq!(
+            open_order.order_snapshot,
+            Some(order_snapshot(&aapl(), &limit_buy()).expect("Expected snapshot"))
+        );
+    })
+    .await;
+}
@@ -0,0 +1,101 @@
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{
+    app_state::{TradingAppState, TradingConfig},
+    execution::{
+        netting::{
+            NettingDecision, NettingPolicy, PartialFillPolicy, is_partially_filled,
+            net_against_working, on_partial_fill, working_remaining,
+        },
+        order_engine::OrderEngine,
+    },
+    strategy::strategy::StrategyEnum,
+};
+
+/// Buy of 100 with 50 filled, re-evaluated with the target still 50 away from the position
+fn half_filled_buy(policy: PartialFillPolicy) -> NettingDecision {
+    let orders = [(100.0, 50.0)];
+    let qty_diff = 50.0;
+    assert!(is_partially_filled(&orders));
+    let decision = net_against_working(NettingPolicy::Net, qty_diff, working_remaining(&orders));
+    assert_eq!(decision, NettingDecision::Hold);
+    on_partial_fill(policy, decision, qty_diff)
+}
+
+#[test]
+fn wait_leaves_the_remainder_working() {
+    assert_eq!(
+        half_filled_buy(PartialFillPolicy::Wait),
+        NettingDecision::Hold
+    );
+}
+
+#[test]
+fn chase_replaces_the_remainder_at_the_new_price() {
+    assert_eq!(
+        half_filled_buy(PartialFillPolicy::Chase),
+        NettingDecision::CancelAndPlace(50.0)
+    );
+}
+
+#[test]
+fn cancel_and_reprice_only_cancels_the_remainder() {
+    assert_eq!(
+        half_filled_buy(PartialFillPolicy::CancelAndReprice),
+        NettingDecision::CancelAll
+    );
+}
+
+#[test]
+fn unfilled_and_fully_filled_orders_are_not_partial_fills() {
+    assert!(!is_partially_filled(&[(100.0, 0.0)]));
+    assert!(!is_partially_filled(&[(-100.0, 100.0)]));
+    assert!(is_partially_filled(&[(-100.0, 50.0)]));
+}
+
+#[test]
+fn decisions_that_already_cancel_are_kept() {
+    for policy in [
+        PartialFillPolicy::Wait,
+        PartialFillPolicy::Chase,
+        PartialFillPolicy::CancelAndReprice,
+    ] {
+        assert_eq!(
+            on_partial_fill(policy, NettingDecision::CancelAndPlace(-20.0), -20.0),
+            NettingDecision::CancelAndPlace(-20.0)
+        );
+        assert_eq!(
+            on_partial_fill(policy, NettingDecision::CancelAll, 0.0),
+            NettingDecision::CancelAll
+        );
+    }
+}
+
+#[test]
+fn policy_is_parsed_from_its_config_name() {
+    assert_eq!("wait".parse(), Ok(PartialFillPolicy::Wait));
+    assert_eq!(" chase ".parse(), Ok(PartialFillPolicy::Chase));
+    assert_eq!(
+        "cancel_and_reprice".parse(),
+        Ok(PartialFillPolicy::CancelAndReprice)
+    );
+    assert!("reprice".parse::<PartialFillPolicy>().is_err());
+}
+
+#[tokio::test]
+async fn engine_takes_the_policy_from_config() {
+    let mut config = TradingConfig::new("postgres://localhost/unused");
+    assert_eq!(config.partial_fill_policy, PartialFillPolicy::Wait);
+    config.partial_fill_policy = PartialFillPolicy::Chase;
+    // the engine never connects on its own
+    let pool = PgPoolOptions::new()
+        .connect_lazy(&config.database_url)
+        .expect("Expected lazy pool");
+    let state = TradingAppState::new(pool, config);
+
+    let order_engine = OrderEngine::from_state(&state, Vec::<StrategyEnum>::new());
+
+    assert_eq!(
+        order_engine.get_partial_fill_policy(),
+        PartialFillPolicy::Chase
+    );
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use ibapi::orders::Order;
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{execution::order_engine::OrderEngine, strategy::strategy::StrategyEnum};
+
+use crate::common::fixtures::{RecordingSubmitter, qqq};
+
+#[tokio::test]
+async fn test_place_order_returns_id_stored_in_order_map() {
+    // OrderEngine only touches the DB from order updates
+    let pool = PgPoolOptions::new()
+        .connect_lazy("postgres://localhost/unused")
+        .expect("Expected lazy pool");
+    let order_engine = OrderEngine::new(pool, Vec::<StrategyEnum>::new());
+    let submitter = Arc::new(RecordingSubmitter::new(41));
+    let contract = qqq();
+
+    let first_id = order_engine
+        .place_order(
+            "place_order_strat".to_string(),
+            submitter.clone(),
+            contract.clone(),
+            Order::default(),
+            false,
+        )
+        .await
+        .expect("Expected order to be placed");
+    let second_id = order_engine
+        .place_order(
+            "place_order_strat".to_string(),
+            submitter.clone(),
+            contract,
+            Order::default(),
+            false,
+        )
+        .await
+        .expect("Expected order to be placed");
+
+    assert_eq!((first_id, second_id), (41, 42));
+    let submitted_ids: Vec<i32> = submitter
+        .submitted()
+        .into_iter()
+        .map(|(order_id, _, _)| order_id)
+        .collect();
+    assert_eq!(submitted_ids, vec![41, 42]);
+    let (strategy, placed_contract, _) = order_engine
+        .get_placed_order(first_id)
+        .expect("Expected to read order map")
+        .expect("Expected placed order in order map");
+    assert_eq!(strategy, "place_order_strat");
+    assert_eq!(placed_contract.symbol, "QQQ");
+}
@@ -0,0 +1,67 @@
+use trading_app::{
+    database::models::ExecutionSide,
+    execution::events::on_execution_updates::{
+        apply_execution_to_position, position_after_execution,
+    },
+};
+
+#[test]
+fn test_flattening_execution_stores_no_nan() {
+    let (qty, avg_price) =
+        apply_execution_to_position(100.0, 450.0, ExecutionSide::Sold, 100.0, 455.0);
+    assert_eq!(qty, 0.0);
+    assert_eq!(avg_price, 0.0);
+
+    let (qty, avg_price) =
+        apply_execution_to_position(-50.0, 450.0, ExecutionSide::Bought, 50.0, 440.0);
+    assert_eq!(qty, 0.0);
+    assert_eq!(avg_price, 0.0);
+}
+
+#[test]
+fn test_zero_share_execution_on_flat_position_stores_no_nan() {
+    let (qty, avg_price) = apply_execution_to_position(0.0, 0.0, ExecutionSide::Bought, 0.0, 455.0);
+    assert_eq!(qty, 0.0);
+    assert!(avg_price.is_finite());
+}
+
+#[test]
+fn test_adding_to_position_averages_price() {
+    let (qty, avg_price) =
+        apply_execution_to_position(100.0, 400.0, ExecutionSide::Bought, 100.0, 500.0);
+    assert_eq!(qty, 200.0);
+    assert_eq!(avg_price, 450.0);
+}
+
+#[test]
+fn test_reducing_and_flipping_position() {
+    // Reducing keeps the current avg_price
+    assert_eq!(
+        apply_execution_to_position(100.0, 400.0, ExecutionSide::Sold, 40.0, 500.0),
+        (60.0, 400.0)
+    );
+    // Flipping starts from the execution price
+    assert_eq!(
+        apply_execution_to_position(100.0, 400.0, ExecutionSide::Sold, 150.0, 500.0),
+        (50.0, 500.0)
+    );
+}
+
+#[test]
+fn test_sale_larger_than_long_position_goes_net_short() {
+    // sold 150 against 100 long - the 50 left over open a short at the execution price
+    assert_eq!(
+        position_after_execution(100.0, 400.0, ExecutionSide::Sold.signed(150.0), 500.0),
+        (-50.0, 500.0)
+    );
+    // a short sale from flat is stored as a short
+    assert_eq!(
+        position_after_execution(0.0, 0.0, ExecutionSide::Sold.signed(100.0), 500.0),
+        (-100.0, 500.0)
+    );
+    // and buying back more than the short flips it long
+    assert_eq!(
+        position_after_execution(-50.0, 500.0, ExecutionSide::Bought.signed(80.0), 490.0),
+        (30.0, 490.0)
+    );
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use trading_app::{
+    database::models_crud::target_stock_positions::QtyDiff,
+    execution::preview::{RiskLimits, preview_orders},
+};
+
+fn diff(stock: &str, qty_diff: f64, avg_price: f64) -> QtyDiff {
+    QtyDiff {
+        stock: stock.to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        strategy: "preview_strat".to_string(),
+        qty_diff,
+        avg_price,
+    }
+}
+
+#[test]
+fn test_preview_returns_diffs_and_flags_oversized_order() {
+    let diffs = vec![
+        diff("QQQ", 10.0, 400.0),
+        diff("SPY", -500.0, 500.0),
+        diff("IWM", 0.0, 200.0),
+    ];
+    let prices = HashMap::from([("QQQ".to_string(), 450.0)]);
+    let limits = RiskLimits {
+        max_order_notional: Some(100_000.0),
+        max_capital_fraction: 1.0,
+    };
+
+    let orders = preview_orders(&diffs, &prices, 50_000.0, &limits);
+
+    // Nothing to place for IWM
+    assert_eq!(orders.len(), 2);
+
+    assert_eq!(orders[0].stock, "QQQ");
+    assert_eq!(orders[0].quantity, 10.0);
+    assert_eq!(orders[0].price, 450.0);
+    assert_eq!(orders[0].estimated_notional, 4_500.0);
+    assert!(!orders[0].exceeds_limits());
+
+    // No bar for SPY - priced at the target's avg_price
+    assert_eq!(orders[1].stock, "SPY");
+    assert_eq!(orders[1].quantity, -500.0);
+    assert_eq!(orders[1].price, 500.0);
+    assert_eq!(orders[1].estimated_notional, 250_000.0);
+    assert!(orders[1].exceeds_limits());
+    assert_eq!(orders[1].violations.len(), 2);
+}
+
+#[test]
+fn test_preview_uses_capital_fraction() {
+    let limits = RiskLimits {
+        max_order_notional: None,
+        max_capital_fraction: 0.25,
+    };
+
+    let orders = preview_orders(
+        &[diff("QQQ", 10.0, 400.0)],
+        &HashMap::new(),
+        10_000.0,
+        &limits,
+    );
+    assert!(orders[0].exceeds_limits());
+
+    let orders = preview_orders(
+        &[diff("QQQ", 5.0, 400.0)],
+        &HashMap::new(),
+        10_000.0,
+        &limits,
+    );
+    assert!(!orders[0].exceeds_limits());
+}
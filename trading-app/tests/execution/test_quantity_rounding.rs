@@ -0,0 +1,44 @@
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{
+    app_state::{TradingAppState, TradingConfig},
+    execution::{
+        netting::{NettingDecision, NettingPolicy, net_against_working, round_quantity},
+        order_engine::OrderEngine,
+    },
+    strategy::strategy::StrategyEnum,
+};
+
+#[test]
+fn test_diff_with_residue_places_whole_shares_without_looping() {
+    // target of 99.9999 from averaging against a flat position
+    let target = 99.9999;
+    let decision = net_against_working(NettingPolicy::Net, round_quantity(target, 0), 0.0);
+    assert_eq!(decision, NettingDecision::Place(100.0));
+
+    // once the 100 shares fill, the -0.0001 left over must not place another order
+    let decision = net_against_working(NettingPolicy::Net, round_quantity(target - 100.0, 0), 0.0);
+    assert_eq!(decision, NettingDecision::Hold);
+}
+
+#[test]
+fn test_fractional_precision_is_kept_to_decimals() {
+    assert_eq!(round_quantity(10.12345, 2), 10.12);
+    assert_eq!(round_quantity(-10.126, 2), -10.13);
+    assert_eq!(round_quantity(-0.0001, 0).signum(), 1.0);
+}
+
+#[tokio::test]
+async fn test_engine_takes_share_decimals_from_config() {
+    let mut config = TradingConfig::new("postgres://localhost/unused");
+    assert_eq!(config.share_quantity_decimals, 0);
+    config.share_quantity_decimals = 4;
+    // the engine never connects on its own
+    let pool = PgPoolOptions::new()
+        .connect_lazy(&config.database_url)
+        .expect("Expected lazy pool");
+    let state = TradingAppState::new(pool, config);
+
+    let order_engine = OrderEngine::from_state(&state, Vec::<StrategyEnum>::new());
+
+    assert_eq!(order_engine.get_share_quantity_decimals(), 4);
+}
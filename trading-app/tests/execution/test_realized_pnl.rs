@@ -0,0 +1,119 @@
+use chrono::Utc;
+use rust_decimal::dec;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            ExecutionSide, Status, StockTransactionsFullKeys, StockTransactionsPrimaryKeys,
+            StrategyFullKeys,
+        },
+        models_crud::{
+            stock_transactions::get_stock_transactions_crud, strategy::get_strategy_crud,
+        },
+    },
+    execution::events::on_execution_updates::realized_pnl_of_execution,
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "realized_pnl_strat";
+
+#[test]
+fn test_opening_or_adding_realizes_nothing() {
+    assert_eq!(
+        realized_pnl_of_execution(0.0, 0.0, ExecutionSide::Bought, 10.0, 100.0, 1.0),
+        (false, None)
+    );
+    assert_eq!(
+        realized_pnl_of_execution(10.0, 100.0, ExecutionSide::Bought, 10.0, 110.0, 1.0),
+        (false, None)
+    );
+}
+
+#[test]
+fn test_partial_close_realizes_closed_shares() {
+    assert_eq!(
+        realized_pnl_of_execution(10.0, 100.0, ExecutionSide::Sold, 4.0, 105.0, 1.0),
+        (false, Some(20.0))
+    );
+}
+
+#[test]
+fn test_closing_fill_realizes_whole_position() {
+    assert_eq!(
+        realized_pnl_of_execution(10.0, 100.0, ExecutionSide::Sold, 10.0, 95.0, 1.0),
+        (true, Some(-50.0))
+    );
+    // covering a short profits when the price fell
+    assert_eq!(
+        realized_pnl_of_execution(-10.0, 100.0, ExecutionSide::Bought, 10.0, 95.0, 1.0),
+        (true, Some(50.0))
+    );
+}
+
+#[test]
+fn test_flipping_fill_only_realizes_closed_part() {
+    assert_eq!(
+        realized_pnl_of_execution(10.0, 100.0, ExecutionSide::Sold, 15.0, 110.0, 1.0),
+        (true, Some(100.0))
+    );
+}
+
+#[test]
+fn test_option_pnl_uses_multiplier() {
+    assert_eq!(
+        realized_pnl_of_execution(2.0, 1.5, ExecutionSide::Sold, 2.0, 2.0, 100.0),
+        (true, Some(100.0))
+    );
+}
+
+#[tokio::test]
+async fn test_closing_fill_records_realized_pnl_on_transaction() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        // long 10 @ 100, closed out by selling 10 @ 103
+        let (closes_position, realized_pnl) =
+            realized_pnl_of_execution(10.0, 100.0, ExecutionSide::Sold, 10.0, 103.0, 1.0);
+        let transactions_crud = get_stock_transactions_crud(pool.clone());
+        let pk = StockTransactionsPrimaryKeys {
+            execution_id: "realized_pnl_exec".to_string(),
+        };
+        transactions_crud
+            .create(&StockTransactionsFullKeys {
+                execution_id: pk.execution_id.clone(),
+                strategy: STRATEGY.to_string(),
+                stock: "QQQ".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                order_perm_id: 1,
+                time: Utc::now(),
+                price: 103.0,
+                quantity: ExecutionSide::Sold.signed(10.0),
+                fees: dec!(0),
+                raw_broker_time: None,
+                closes_position,
+                realized_pnl,
+            })
+            .await
+            .expect("Expected to create stock transaction");
+
+        let transaction = transactions_crud
+            .read(&pk)
+            .await
+            .expect("Expected to read stock transaction")
+            .expect("Expected stock transaction to exist");
+        assert!(transaction.closes_position);
+        assert_eq!(transaction.realized_pnl, Some(30.0));
+    })
+    .await;
+}
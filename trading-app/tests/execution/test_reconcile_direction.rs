@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use ibapi::{
+    accounts::Position,
+    orders::Action,
+    prelude::{Contract, SecurityType},
+};
+use trading_app::{
+    database::{
+        models::OptionType,
+        models_crud::{
+            current_option_positions::GroupedByContract, current_stock_positions::GroupedByStock,
+        },
+    },
+    execution::{
+        notices::PacingBackoff,
+        place_order::DEFAULT_ORDER_ROUTING,
+        sync::{
+            CORRECTIVE_ORDER_REF, PositionReconciliation, ReconcileDirection, SyncOptions,
+            reconcile_position, submit_corrective_orders, trust_local_corrections,
+        },
+    },
+};
+
+use crate::common::fixtures::{RecordingSubmitter, option, qqq, qqq_on};
+
+#[test]
+fn test_default_trusts_broker() {
+    assert_eq!(
+        SyncOptions::default().reconcile_direction,
+        ReconcileDirection::TrustBroker
+    );
+    assert_eq!(
+        ReconcileDirection::from_str("local"),
+        Ok(ReconcileDirection::TrustLocal)
+    );
+    assert!(ReconcileDirection::from_str("both").is_err());
+}
+
+#[test]
+fn test_trust_broker_books_discrepancy() {
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustBroker, 10.0, 7.0),
+        PositionReconciliation::BookDiscrepancy(-3.0)
+    );
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustBroker, 10.0, 10.0),
+        PositionReconciliation::InSync
+    );
+}
+
+#[test]
+fn test_trust_local_produces_corrective_order_instead_of_db_write() {
+    // local long 10, broker only has 7 - buy 3 at the broker
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustLocal, 10.0, 7.0),
+        PositionReconciliation::CorrectiveOrder(3.0)
+    );
+    // broker has a position local doesn't - sell it off
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustLocal, 0.0, 5.0),
+        PositionReconciliation::CorrectiveOrder(-5.0)
+    );
+}
+
+#[test]
+fn test_corrective_orders_are_tagged_market_orders() {
+    let client = RecordingSubmitter::default();
+    let order_ids = submit_corrective_orders(
+        &client,
+        &[(qqq(), 3.0), (qqq(), -5.0)],
+        DEFAULT_ORDER_ROUTING,
+        &PacingBackoff::default(),
+    );
+
+    let submitted = client.submitted();
+    assert_eq!(order_ids, vec![1, 2]);
+    assert_eq!(submitted.len(), 2);
+    assert!(matches!(submitted[0].2.action, Action::Buy));
+    assert_eq!(submitted[0].2.total_quantity, 3.0);
+    assert!(matches!(submitted[1].2.action, Action::Sell));
+    assert_eq!(submitted[1].2.total_quantity, 5.0);
+    assert!(
+        submitted
+            .iter()
+            .all(|(_, contract, order)| contract.symbol == "QQQ"
+                && order.order_ref == CORRECTIVE_ORDER_REF)
+    );
+}
+
+fn broker_position(contract: Contract, position: f64) -> Position {
+    Position {
+        account: "DU1".to_string(),
+        contract,
+        position,
+        average_cost: 0.0,
+    }
+}
+
+fn local_call(strike: f64, quantity: f64) -> GroupedByContract {
+    GroupedByContract {
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        expiry: "20301220".to_string(),
+        strike,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Call,
+        quantity,
+    }
+}
+
+#[test]
+fn test_trust_local_matches_options_on_their_contract_not_their_symbol() {
+    let local_stocks = vec![GroupedByStock {
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        quantity: 10.0,
+    }];
+    // the 400 call is short one at the broker, the 410 call is missing there entirely
+    let local_options = vec![local_call(400.0, 2.0), local_call(410.0, 1.0)];
+    let broker_positions = vec![
+        broker_position(qqq(), 10.0),
+        broker_position(option("QQQ", "20301220", 400.0, "C"), 1.0),
+    ];
+
+    let corrections = trust_local_corrections(&local_stocks, &local_options, &broker_positions);
+
+    assert_eq!(corrections.len(), 2);
+    assert_eq!(corrections[0].0.security_type, SecurityType::Option);
+    assert_eq!(corrections[0].0.strike, 400.0);
+    assert_eq!(corrections[0].1, 1.0);
+    assert_eq!(corrections[1].0.security_type, SecurityType::Option);
+    assert_eq!(corrections[1].0.strike, 410.0);
+    assert_eq!(corrections[1].0.right, "C");
+    assert_eq!(corrections[1].0.multiplier, "100");
+    assert_eq!(corrections[1].1, 1.0);
+}
+
+#[test]
+fn test_trust_local_orders_local_stock_missing_at_broker() {
+    let local_stocks = vec![GroupedByStock {
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        quantity: 10.0,
+    }];
+
+    let corrections = trust_local_corrections(&local_stocks, &[], &[]);
+
+    assert_eq!(corrections.len(), 1);
+    assert_eq!(corrections[0].0.symbol, "QQQ");
+    assert_eq!(corrections[0].0.security_type, SecurityType::Stock);
+    assert_eq!(corrections[0].0.primary_exchange, "NASDAQ");
+    assert_eq!(corrections[0].1, 10.0);
+}
+
+#[test]
+fn test_float_residue_of_fractional_fills_is_in_sync() {
+    // 0.1 + 0.2 shares filled locally, 0.3 held at the broker
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustLocal, 0.1 + 0.2, 0.3),
+        PositionReconciliation::InSync
+    );
+    assert_eq!(
+        reconcile_position(ReconcileDirection::TrustBroker, 0.3, 0.1 + 0.2),
+        PositionReconciliation::InSync
+    );
+}
+
+#[test]
+fn test_trust_local_matches_stocks_on_their_listing() {
+    let local_stocks = vec![
+        GroupedByStock {
+            stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
+            quantity: 10.0,
+        },
+        GroupedByStock {
+            stock: "QQQ".to_string(),
+            primary_exchange: "ARCA".to_string(),
+            quantity: 4.0,
+        },
+    ];
+    let corrections = trust_local_corrections(
+        &local_stocks,
+        &[],
+        &[
+            broker_position(qqq_on("SMART", "NASDAQ"), 10.0),
+            broker_position(qqq_on("SMART", "ARCA"), 1.0),
+        ],
+    );
+
+    assert_eq!(corrections.len(), 1);
+    assert_eq!(corrections[0].0.primary_exchange, "ARCA");
+    assert_eq!(corrections[0].1, 3.0);
+}
+
+#[test]
+fn test_correction_of_broker_position_without_exchange_follows_the_configured_route() {
+    let local_stocks = vec![GroupedByStock {
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        quantity: 10.0,
+    }];
+    // IBKR returns positions on contracts with no exchange
+    let corrections = trust_local_corrections(
+        &local_stocks,
+        &[],
+        &[broker_position(qqq_on("", "NASDAQ"), 7.0)],
+    );
+    let client = RecordingSubmitter::default();
+
+    let order_ids =
+        submit_corrective_orders(&client, &corrections, "IEX", &PacingBackoff::default());
+
+    let submitted = client.submitted();
+    assert_eq!(order_ids, vec![1]);
+    assert_eq!(submitted[0].1.exchange, "IEX");
+    assert_eq!(submitted[0].1.primary_exchange, "NASDAQ");
+    assert_eq!(submitted[0].2.total_quantity, 3.0);
+}
+
+#[test]
+fn test_rebuilt_correction_follows_the_configured_route() {
+    let local_stocks = vec![GroupedByStock {
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        quantity: 10.0,
+    }];
+    let corrections = trust_local_corrections(&local_stocks, &[], &[]);
+    let client = RecordingSubmitter::default();
+
+    submit_corrective_orders(&client, &corrections, "IEX", &PacingBackoff::default());
+
+    let submitted = client.submitted();
+    assert_eq!(submitted[0].1.exchange, "IEX");
+    assert_eq!(submitted[0].1.primary_exchange, "NASDAQ");
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Duration, Utc};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersFullKeys, OpenStockOrdersPrimaryKeys, Status, StrategyFullKeys},
+        models_crud::{
+            open_option_orders::get_open_option_orders_crud,
+            open_stock_orders::get_open_stock_orders_crud, strategy::get_strategy_crud,
+        },
+    },
+    execution::sync::remove_stale_open_orders,
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const STRATEGY: &str = "stale_orders_strat";
+
+fn open_order(order_perm_id: i32, filled: f64, time: DateTime<Utc>) -> OpenStockOrdersFullKeys {
+    OpenStockOrdersFullKeys {
+        order_perm_id,
+        order_id: order_perm_id,
+        strategy: STRATEGY.to_string(),
+        stock: "QQQ".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        time,
+        quantity: 10.0,
+        executions: vec![],
+        filled,
+        order_snapshot: None,
+    }
+}
+
+#[tokio::test]
+async fn test_open_order_absent_from_broker_is_removed() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+        let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
+        let snapshot_time = Utc::now();
+        let before_snapshot = snapshot_time - Duration::seconds(1);
+        // still open at the broker, gone from the broker, gone with a fill that was never booked,
+        // placed after the broker's snapshot was taken
+        let still_open = open_order(707_001, 0.0, before_snapshot);
+        let stale = open_order(707_002, 0.0, before_snapshot);
+        let unbooked_fill = open_order(707_003, 5.0, before_snapshot);
+        let after_snapshot = open_order(707_004, 0.0, snapshot_time + Duration::seconds(1));
+        for order in [&still_open, &stale, &unbooked_fill, &after_snapshot] {
+            open_stock_orders_crud
+                .create_or_ignore(order)
+                .await
+                .expect("Expected to create open order");
+        }
+
+        // every other order already in the DB is treated as open at the broker so it is left
+        // alone
+        let test_perm_ids = [
+            stale.order_perm_id,
+            unbooked_fill.order_perm_id,
+            after_snapshot.order_perm_id,
+        ];
+        let mut broker_perm_ids: Vec<i32> = open_stock_orders_crud
+            .read_all()
+            .await
+            .expect("Expected to read open orders")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|order| order.order_perm_id)
+            .filter(|perm_id| !test_perm_ids.contains(perm_id))
+            .collect();
+        broker_perm_ids.extend(
+            get_open_option_orders_crud(pool.clone())
+                .read_all()
+                .await
+                .expect("Expected to read open option orders")
+                .unwrap_or_default()
+                .into_iter()
+                .map(|order| order.order_perm_id),
+        );
+        broker_perm_ids.push(still_open.order_perm_id);
+        let removed = remove_stale_open_orders(pool.clone(), &broker_perm_ids, snapshot_time)
+            .await
+            .expect("Expected stale orders to be removed");
+
+        let mut remaining = Vec::new();
+        for order in [&still_open, &stale, &unbooked_fill, &after_snapshot] {
+            let pk = OpenStockOrdersPrimaryKeys {
+                order_perm_id: order.order_perm_id,
+                order_id: order.order_id,
+            };
+            if open_stock_orders_crud
+                .read(&pk)
+                .await
+                .expect("Expected to read open order")
+                .is_some()
+            {
+                remaining.push(order.order_perm_id);
+            }
+        }
+
+        assert_eq!(removed, vec![stale.order_perm_id]);
+        assert_eq!(
+            remaining,
+            vec![
+                still_open.order_perm_id,
+                unbooked_fill.order_perm_id,
+                after_snapshot.order_perm_id
+            ]
+        );
+    })
+    .await;
+}
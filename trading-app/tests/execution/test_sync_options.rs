@@ -0,0 +1,49 @@
+use trading_app::execution::sync::{SyncOptions, SyncStep};
+
+#[test]
+fn test_default_runs_all_steps_executions_first() {
+    let steps = SyncOptions::default()
+        .steps()
+        .expect("Expected default order to be valid");
+    assert_eq!(
+        steps,
+        vec![
+            SyncStep::Executions,
+            SyncStep::OpenOrders,
+            SyncStep::Positions
+        ]
+    );
+}
+
+#[test]
+fn test_disabling_positions_skips_only_positions() {
+    let options = SyncOptions {
+        positions: false,
+        ..Default::default()
+    };
+    let steps = options.steps().expect("Expected order to be valid");
+    assert_eq!(steps, vec![SyncStep::Executions, SyncStep::OpenOrders]);
+}
+
+#[test]
+fn test_positions_before_executions_is_rejected() {
+    let options = SyncOptions {
+        order: vec![
+            SyncStep::Positions,
+            SyncStep::OpenOrders,
+            SyncStep::Executions,
+        ],
+        ..Default::default()
+    };
+    assert!(options.steps().is_err());
+
+    // only an issue while both are enabled
+    let options = SyncOptions {
+        executions: false,
+        ..options
+    };
+    assert_eq!(
+        options.steps().expect("Expected order to be valid"),
+        vec![SyncStep::Positions, SyncStep::OpenOrders]
+    );
+}
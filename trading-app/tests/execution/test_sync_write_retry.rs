@@ -0,0 +1,172 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{CurrentStockPositionsFullKeys, CurrentStockPositionsPrimaryKeys},
+        models_crud::current_stock_positions::{
+            get_current_stock_positions_crud, get_specific_current_stock_positions_crud,
+        },
+    },
+    execution::sync::{PositionSyncSummary, SyncWriteRetry, await_sync_writes, retry_sync_write},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+const RETRY: SyncWriteRetry = SyncWriteRetry {
+    attempts: 3,
+    delay: Duration::from_millis(1),
+};
+
+fn connection_reset() -> anyhow::Error {
+    sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()).into()
+}
+
+/// Write failing its first failures attempts with error, counting every attempt
+fn failing_write(
+    attempts: Arc<AtomicU32>,
+    failures: u32,
+    error: fn() -> anyhow::Error,
+) -> tokio::task::JoinHandle<Result<(), String>> {
+    tokio::spawn(async move {
+        retry_sync_write(RETRY, "write position", || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if attempt <= failures {
+                    Err(error())
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await
+    })
+}
+
+/// Write failing its first failures attempts on a dropped connection, counting every attempt
+fn flaky_write(
+    attempts: Arc<AtomicU32>,
+    failures: u32,
+) -> tokio::task::JoinHandle<Result<(), String>> {
+    failing_write(attempts, failures, connection_reset)
+}
+
+#[tokio::test]
+async fn test_failed_write_is_retried_and_summarised() {
+    let in_sync_attempts = Arc::new(AtomicU32::new(0));
+    let transient_attempts = Arc::new(AtomicU32::new(0));
+    let failing_attempts = Arc::new(AtomicU32::new(0));
+
+    let summary = await_sync_writes(vec![
+        flaky_write(in_sync_attempts.clone(), 0),
+        flaky_write(transient_attempts.clone(), 2),
+        flaky_write(failing_attempts.clone(), u32::MAX),
+    ])
+    .await;
+
+    assert_eq!(
+        summary,
+        PositionSyncSummary {
+            reconciled: 2,
+            failed: 1,
+        }
+    );
+    assert_eq!(in_sync_attempts.load(Ordering::SeqCst), 1);
+    // went through on the last attempt
+    assert_eq!(transient_attempts.load(Ordering::SeqCst), 3);
+    // gave up after the configured attempts
+    assert_eq!(failing_attempts.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_panicked_write_counts_as_failed() {
+    let summary = await_sync_writes(vec![tokio::spawn(async {
+        panic!("write panicked");
+    })])
+    .await;
+
+    assert_eq!(
+        summary,
+        PositionSyncSummary {
+            reconciled: 0,
+            failed: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_non_transient_error_is_not_retried() {
+    let attempts = Arc::new(AtomicU32::new(0));
+
+    let summary = await_sync_writes(vec![failing_write(attempts.clone(), 1, || {
+        sqlx::Error::RowNotFound.into()
+    })])
+    .await;
+
+    assert_eq!(
+        summary,
+        PositionSyncSummary {
+            reconciled: 0,
+            failed: 1,
+        }
+    );
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_rerun_sync_write_leaves_the_same_position() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        sqlx::query(
+            "INSERT INTO trading.strategy (strategy, capital, initial_capital, status)
+            VALUES ('retry_strat', 0, 0, 'inactive'), ('retry_unknown', 0, 0, 'inactive')",
+        )
+        .execute(&pool)
+        .await
+        .expect("Expected to create strategies");
+        let positions_crud = get_current_stock_positions_crud(pool.clone());
+        positions_crud
+            .create(&CurrentStockPositionsFullKeys {
+                stock: "RETRY".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                strategy: "retry_strat".to_string(),
+                quantity: 6.0,
+                avg_price: 10.0,
+            })
+            .await
+            .expect("Expected to create position");
+
+        // a write whose commit was reported lost is run again
+        let sync_crud = get_specific_current_stock_positions_crud(pool.clone());
+        for _ in 0..2 {
+            sync_crud
+                .sync_unknown_strat_positions(
+                    "retry_unknown",
+                    "RETRY".to_string(),
+                    "NASDAQ".to_string(),
+                    10.0,
+                )
+                .await
+                .expect("Expected to sync position");
+        }
+
+        let unknown = positions_crud
+            .read(&CurrentStockPositionsPrimaryKeys {
+                stock: "RETRY".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                strategy: "retry_unknown".to_string(),
+            })
+            .await
+            .expect("Expected to read position")
+            .expect("Expected unknown strategy to hold the discrepancy");
+        assert_eq!(unknown.quantity, 4.0);
+    })
+    .await;
+}
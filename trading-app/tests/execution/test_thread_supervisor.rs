@@ -0,0 +1,76 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use trading_app::supervisor::{SupervisorOptions, ThreadStatus, supervise};
+
+fn options(max_restarts: u32) -> SupervisorOptions {
+    SupervisorOptions {
+        max_restarts,
+        restart_delay: Duration::ZERO,
+    }
+}
+
+#[tokio::test]
+async fn test_fatal_error_restarts_thread() {
+    let spawned = Arc::new(AtomicUsize::new(0));
+
+    let result = supervise("test", options(3), |reporter| {
+        let attempt = spawned.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(move || {
+            if attempt == 0 {
+                reporter.report(ThreadStatus::Fatal("Subscription failed".to_string()));
+            } else {
+                reporter.report(ThreadStatus::Ended);
+            }
+        });
+    })
+    .await;
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(spawned.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_repeated_fatal_errors_escalate() {
+    let spawned = Arc::new(AtomicUsize::new(0));
+
+    let result = supervise("test", options(2), |reporter| {
+        spawned.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(move || {
+            reporter.report(ThreadStatus::Fatal("Subscription failed".to_string()));
+        });
+    })
+    .await;
+
+    assert_eq!(
+        result,
+        Err("test thread failed: Subscription failed".to_string())
+    );
+    assert_eq!(spawned.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_panicking_thread_is_restarted() {
+    let spawned = Arc::new(AtomicUsize::new(0));
+
+    let result = supervise("test", options(1), |reporter| {
+        let attempt = spawned.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(move || {
+            if attempt == 0 {
+                let _reporter = reporter;
+                panic!("Expected to be able to subscribe");
+            }
+            reporter.report(ThreadStatus::Ended);
+        });
+    })
+    .await;
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(spawned.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,65 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use ibapi::orders::{Action, order_builder};
+use trading_app::execution::place_order::{OrderContext, place_order, round_to_tick};
+
+use crate::common::{
+    fixtures::{RecordingSubmitter, qqq},
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+#[test]
+fn test_price_between_ticks_rounds_to_nearest_tick() {
+    assert_eq!(round_to_tick(100.123, 0.01), 100.12);
+    assert_eq!(round_to_tick(100.126, 0.01), 100.13);
+    assert_eq!(round_to_tick(1.23, 0.05), 1.25);
+    assert_eq!(round_to_tick(4312.1, 0.25), 4312.0);
+    assert_eq!(round_to_tick(4312.2, 0.25), 4312.25);
+}
+
+#[test]
+fn test_price_on_tick_is_unchanged() {
+    assert_eq!(round_to_tick(100.12, 0.01), 100.12);
+    assert_eq!(round_to_tick(55.0, 1.0), 55.0);
+}
+
+#[tokio::test]
+async fn test_limit_price_is_rounded_and_min_tick_cached() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        let ctx = OrderContext::new(pool);
+        let client = Arc::new(RecordingSubmitter::with_min_tick(1, 0.05));
+
+        for limit_price in [101.02, 101.08] {
+            place_order(
+                &ctx,
+                "tick_strat".to_string(),
+                client.clone(),
+                qqq(),
+                order_builder::limit_order(Action::Buy, 1.0, limit_price),
+                false,
+            )
+            .expect("Expected order to be placed");
+        }
+        // market orders have no limit price to round
+        place_order(
+            &ctx,
+            "tick_strat".to_string(),
+            client.clone(),
+            qqq(),
+            order_builder::market_order(Action::Buy, 1.0),
+            false,
+        )
+        .expect("Expected order to be placed");
+
+        let limit_prices: Vec<Option<f64>> = client
+            .submitted()
+            .into_iter()
+            .map(|(_, _, order)| order.limit_price)
+            .collect();
+        assert_eq!(limit_prices, vec![Some(101.0), Some(101.1), None]);
+        assert_eq!(client.min_tick_requests.load(Ordering::SeqCst), 1);
+    })
+    .await;
+}
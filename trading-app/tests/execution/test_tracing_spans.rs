@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use ibapi::orders::Order;
+use tracing::{
+    Event, Span, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, layer::Context, prelude::*, registry::LookupSpan};
+use trading_app::execution::{
+    events::{on_execution_updates::execution_span, order_events::order_span},
+    place_order::{OrderContext, place_order},
+};
+
+use crate::common::{
+    fixtures::{RecordingSubmitter, qqq},
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+#[derive(Default)]
+struct FieldVisitor(HashMap<String, String>);
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// Log message -> fields of the span it was logged in
+#[derive(Default, Clone)]
+struct SpanCapture {
+    span_fields: Arc<Mutex<HashMap<u64, HashMap<String, String>>>>,
+    events: Arc<Mutex<HashMap<String, HashMap<String, String>>>>,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanCapture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        self.span_fields
+            .lock()
+            .unwrap()
+            .insert(id.into_u64(), visitor.0);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        if let Some(fields) = self.span_fields.lock().unwrap().get_mut(&id.into_u64()) {
+            fields.extend(visitor.0);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let message = visitor.0.remove("message").unwrap_or_default();
+        let span_fields = ctx
+            .event_span(event)
+            .and_then(|span| {
+                self.span_fields
+                    .lock()
+                    .unwrap()
+                    .get(&span.id().into_u64())
+                    .cloned()
+            })
+            .unwrap_or_default();
+        self.events.lock().unwrap().insert(message, span_fields);
+    }
+}
+
+impl SpanCapture {
+    fn fields_of(&self, message: &str) -> HashMap<String, String> {
+        self.events
+            .lock()
+            .unwrap()
+            .get(message)
+            .cloned()
+            .unwrap_or_else(|| panic!("Expected \"{}\" to be logged", message))
+    }
+}
+
+fn field<'a>(fields: &'a HashMap<String, String>, name: &str) -> &'a str {
+    fields
+        .get(name)
+        .unwrap_or_else(|| panic!("Expected span field {} in {:?}", name, fields))
+}
+
+#[test]
+fn test_execution_logs_share_the_execution_span() {
+    let capture = SpanCapture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = execution_span("0001f4e8.6745b2c1.01.01", 41, "QQQ");
+        let _entered = span.enter();
+        tracing::info!("open order read");
+        Span::current().record("strategy", "span_strat");
+        tracing::info!("position updated");
+    });
+
+    let fields = capture.fields_of("position updated");
+    assert_eq!(field(&fields, "execution_id"), "0001f4e8.6745b2c1.01.01");
+    assert_eq!(field(&fields, "order_id"), "41");
+    assert_eq!(field(&fields, "symbol"), "QQQ");
+    assert_eq!(field(&fields, "strategy"), "span_strat");
+    // logged before the open order named the strategy
+    assert_eq!(
+        field(&capture.fields_of("open order read"), "execution_id"),
+        "0001f4e8.6745b2c1.01.01"
+    );
+}
+
+#[tokio::test]
+async fn test_order_logs_carry_strategy_and_order_id() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    let capture = SpanCapture::default();
+    with_rollback(&pool, |pool| async move {
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let ctx = OrderContext::new(pool);
+
+        tracing::subscriber::with_default(subscriber, || {
+            place_order(
+                &ctx,
+                "span_strat".to_string(),
+                Arc::new(RecordingSubmitter::new(7)),
+                qqq(),
+                Order::default(),
+                false,
+            )
+            .expect("Expected order to be placed");
+
+            let span = order_span(7, 900, "span_strat");
+            let _entered = span.enter();
+            tracing::info!("open order stored");
+        });
+
+        let fields = capture.fields_of("Order submitted to IBKR");
+        assert_eq!(field(&fields, "strategy"), "span_strat");
+        assert_eq!(field(&fields, "symbol"), "QQQ");
+        assert_eq!(field(&fields, "order_id"), "7");
+
+        let fields = capture.fields_of("open order stored");
+        assert_eq!(field(&fields, "order_id"), "7");
+        assert_eq!(field(&fields, "perm_id"), "900");
+        assert_eq!(field(&fields, "strategy"), "span_strat");
+    })
+    .await;
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use ibapi::orders::{Execution, ExecutionData};
+use sqlx::postgres::PgPoolOptions;
+use trading_app::{
+    app_state::{TradingAppState, TradingConfig},
+    database::{
+        crud::CRUDTrait,
+        models::{CurrentOptionPositionsPrimaryKeys, OptionType, Status, StrategyFullKeys},
+        models_crud::{
+            current_option_positions::{
+                get_current_option_positions_crud, get_specific_current_option_positions_crud,
+            },
+            option_transactions::get_option_transactions_crud,
+            strategy::get_strategy_crud,
+        },
+    },
+    execution::{
+        events::on_execution_updates::{
+            DEFAULT_UNKNOWN_STRATEGY, on_new_option_execution_no_open_order,
+        },
+        order_engine::OrderEngine,
+    },
+    strategy::strategy::StrategyEnum,
+};
+
+use crate::common::{
+    fixtures::option,
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const UNKNOWN_STRATEGY: &str = "unknown:acct1";
+const STOCK: &str = "ORPHANED";
+
+fn orphaned_position_pk(strategy: &str) -> CurrentOptionPositionsPrimaryKeys {
+    CurrentOptionPositionsPrimaryKeys {
+        stock: STOCK.to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        strategy: strategy.to_string(),
+        expiry: "20301220".to_string(),
+        strike: 250.0,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Put,
+    }
+}
+
+#[tokio::test]
+async fn test_engine_takes_unknown_strategy_from_config() {
+    let mut config = TradingConfig::new("postgres://localhost/unused");
+    assert_eq!(config.unknown_strategy, DEFAULT_UNKNOWN_STRATEGY);
+    config.unknown_strategy = UNKNOWN_STRATEGY.to_string();
+    let pool = PgPoolOptions::new()
+        .connect_lazy(&config.database_url)
+        .expect("Expected lazy pool");
+    let state = TradingAppState::new(pool, config);
+
+    let order_engine = OrderEngine::from_state(&state, Vec::<StrategyEnum>::new());
+
+    assert_eq!(order_engine.get_unknown_strategy(), UNKNOWN_STRATEGY);
+}
+
+/// Sell of 2 contracts IBKR reports for an order it has no open order for
+fn orphaned_execution() -> ExecutionData {
+    ExecutionData {
+        contract: option(STOCK, "20301220", 250.0, "P"),
+        execution: Execution {
+            order_id: 733,
+            perm_id: 7330,
+            execution_id: "0000e0d5.6581c8d4.01.02".to_string(),
+            time: "20250715  14:30:00".to_string(),
+            side: "SLD".to_string(),
+            shares: 2.0,
+            price: 3.5,
+            average_price: 3.5,
+            cumulative_quantity: 2.0,
+            ..Execution::default()
+        },
+        ..ExecutionData::default()
+    }
+}
+
+#[tokio::test]
+async fn test_orphaned_execution_is_filed_under_configured_strategy() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: UNKNOWN_STRATEGY.to_string(),
+                capital: 0.0,
+                initial_capital: 0.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create unknown strategy");
+
+        let positions_crud = get_current_option_positions_crud(pool.clone());
+        on_new_option_execution_no_open_order(
+            get_option_transactions_crud(pool.clone()),
+            positions_crud.clone(),
+            get_specific_current_option_positions_crud(pool.clone()),
+            orphaned_execution(),
+            UNKNOWN_STRATEGY.to_string(),
+        );
+        // the execution is booked on spawned tasks
+        let mut filed = None;
+        for _ in 0..200 {
+            filed = positions_crud
+                .read(&orphaned_position_pk(UNKNOWN_STRATEGY))
+                .await
+                .expect("Expected to read position");
+            if filed.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let default_filed = positions_crud
+            .read(&orphaned_position_pk(DEFAULT_UNKNOWN_STRATEGY))
+            .await
+            .expect("Expected to read position");
+
+        assert_eq!(filed.map(|position| position.quantity), Some(-2.0));
+        assert!(default_filed.is_none());
+    })
+    .await;
+}
@@ -0,0 +1,20 @@
+mod common;
+
+mod market_data {
+    pub mod test_aggregation;
+    pub mod test_backfill_chunks;
+    pub mod test_bar_alignment;
+    pub mod test_bar_volume;
+    pub mod test_consolidation;
+    pub mod test_history_gate;
+    pub mod test_latest_price;
+    pub mod test_market_data_freshness;
+    pub mod test_market_hours;
+    pub mod test_pacing;
+    pub mod test_retained_bars;
+    pub mod test_scheduled_events;
+    pub mod test_strategy_subscriptions;
+    pub mod test_timestep_bars;
+    pub mod test_trading_calendar;
+    pub mod test_warmup_dedup;
+}
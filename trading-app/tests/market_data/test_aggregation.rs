@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use chrono::{TimeZone, Utc};
+use trading_app::market_data::{
+    aggregation_self_test::{expected_5min_bars, run_aggregation_self_test, synthetic_session},
+    consolidator::{FiveSecBar, drain_completed_buckets},
+};
+
+// 2025-07-15 09:30 New York
+const OPEN: i64 = 1_752_586_200;
+
+fn flat_bar(timestamp: i64, price: f64) -> FiveSecBar {
+    (timestamp, price, price, price, price, 1.0)
+}
+
+#[test]
+fn forming_bucket_is_kept_until_a_bar_crosses_its_boundary() {
+    let mut bars = VecDeque::from([flat_bar(OPEN, 100.0), flat_bar(OPEN + 295, 101.0)]);
+    assert!(drain_completed_buckets(&mut bars).is_empty());
+    assert_eq!(bars.len(), 2);
+
+    // 09:35:00 is the first bar of the next bucket
+    bars.push_back(flat_bar(OPEN + 300, 102.0));
+    let completed = drain_completed_buckets(&mut bars);
+
+    assert_eq!(
+        completed,
+        vec![(Utc.timestamp_opt(OPEN, 0).unwrap(), 100.0, 101.0, 100.0, 101.0, 2.0)]
+    );
+    assert_eq!(bars, VecDeque::from([flat_bar(OPEN + 300, 102.0)]));
+}
+
+#[test]
+fn bucket_is_ohlcv_of_its_5sec_bars() {
+    let mut bars = VecDeque::from([
+        (OPEN, 100.0, 101.0, 99.5, 100.5, 10.0),
+        (OPEN + 5, 100.5, 102.0, 100.0, 101.5, 20.0),
+        (OPEN + 10, 101.5, 101.75, 98.0, 99.0, 5.0),
+        flat_bar(OPEN + 300, 99.0),
+    ]);
+
+    assert_eq!(
+        drain_completed_buckets(&mut bars),
+        vec![(Utc.timestamp_opt(OPEN, 0).unwrap(), 100.0, 102.0, 98.0, 99.0, 35.0)]
+    );
+}
+
+#[test]
+fn gap_in_the_feed_gives_no_bar_for_the_missing_bucket() {
+    // nothing between 09:35 and 09:40, then a bar at 09:45
+    let mut bars = VecDeque::from([
+        flat_bar(OPEN, 100.0),
+        flat_bar(OPEN + 600, 101.0),
+        flat_bar(OPEN + 900, 102.0),
+    ]);
+
+    let completed = drain_completed_buckets(&mut bars);
+
+    let times: Vec<_> = completed.iter().map(|bar| bar.0.timestamp()).collect();
+    assert_eq!(times, vec![OPEN, OPEN + 600]);
+    assert_eq!(bars, VecDeque::from([flat_bar(OPEN + 900, 102.0)]));
+}
+
+#[test]
+fn no_bars_complete_nothing() {
+    assert!(drain_completed_buckets(&mut VecDeque::new()).is_empty());
+}
+
+#[test]
+fn synthetic_session_consolidates_to_expected_bars() {
+    let session = synthetic_session();
+    let expected = expected_5min_bars(&session);
+    // one bar per bucket with bars, the gap at 09:40 included
+    let times: Vec<_> = expected.iter().map(|bar| bar.0.timestamp() - OPEN).collect();
+    assert_eq!(times, vec![0, 300, 900, 1200]);
+
+    assert_eq!(run_aggregation_self_test(), Ok(()));
+}
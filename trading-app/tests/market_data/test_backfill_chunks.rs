@@ -0,0 +1,75 @@
+use chrono::{Duration, TimeZone, Utc};
+use trading_app::market_data::backfill::{chunk_backfill, max_request_days, missing_tail_bars};
+
+#[test]
+fn test_60_day_backfill_of_5_minute_bars_is_chunked_into_weeks() {
+    let end = Utc.with_ymd_and_hms(2025, 7, 15, 14, 0, 0).unwrap();
+    let start = end - Duration::days(60);
+    assert_eq!(max_request_days(5), 7);
+
+    let chunks = chunk_backfill(start, end, max_request_days(5));
+    // 8 full weeks and the 4 days left over
+    assert_eq!(chunks.len(), 9);
+    assert_eq!(chunks[0].end, end);
+    assert!(
+        chunks[..8]
+            .iter()
+            .all(|chunk| chunk.ibkr_duration() == "7 D")
+    );
+    assert_eq!(chunks[8].ibkr_duration(), "4 D");
+
+    // back to back with no gaps, all the way back to start
+    for pair in chunks.windows(2) {
+        assert_eq!(
+            pair[1].end,
+            pair[0].end - Duration::seconds(pair[0].duration_secs as i64)
+        );
+    }
+    let last = chunks.last().unwrap();
+    assert_eq!(
+        last.end - Duration::seconds(last.duration_secs as i64),
+        start
+    );
+}
+
+#[test]
+fn test_partial_day_backfill_is_a_single_request_in_seconds() {
+    let end = Utc.with_ymd_and_hms(2025, 7, 15, 14, 0, 0).unwrap();
+    let chunks = chunk_backfill(end - Duration::hours(5), end, 7);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].ibkr_duration(), "18000 S");
+
+    // rounded up to whole days once over a day, IBKR takes at most a day in seconds
+    let chunks = chunk_backfill(end - Duration::hours(30), end, 7);
+    assert_eq!(chunks[0].ibkr_duration(), "2 D");
+    assert!(chunk_backfill(end, end, 7).is_empty());
+}
+
+#[test]
+fn test_every_missing_tail_bar_is_rerequested() {
+    let open = Utc.with_ymd_and_hms(2025, 7, 15, 13, 30, 0).unwrap();
+    let at = |minutes| open + Duration::minutes(minutes);
+    // stored up to 10:00, the 10:05, 10:10 and 10:15 bars never made it in
+    let last_stored = at(30) + Duration::seconds(2);
+    let last_available = at(45);
+
+    let missing = missing_tail_bars(Some(last_stored), last_available, open);
+    assert_eq!(missing, vec![at(35), at(40), at(45)]);
+
+    // one request from the first missing bar covers all three
+    let chunks = chunk_backfill(missing[0], at(50), max_request_days(5));
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].ibkr_duration(), "900 S");
+}
+
+#[test]
+fn test_no_tail_bars_missing_when_up_to_date() {
+    let open = Utc.with_ymd_and_hms(2025, 7, 15, 13, 30, 0).unwrap();
+    let last_available = open + Duration::minutes(45);
+    assert!(missing_tail_bars(Some(last_available), last_available, open).is_empty());
+
+    // a bar from yesterday counts for nothing today
+    let missing = missing_tail_bars(Some(open - Duration::hours(18)), last_available, open);
+    assert_eq!(missing.len(), 10);
+    assert_eq!(missing[0], open);
+}
@@ -0,0 +1,32 @@
+use chrono::{TimeZone, Utc};
+use chrono_tz::America::New_York;
+use trading_app::market_data::consolidator::is_same_bar_bucket;
+
+#[test]
+fn test_bar_offset_by_seconds_is_current_bar() {
+    let last_bar_available_time = New_York
+        .with_ymd_and_hms(2025, 7, 1, 10, 35, 0)
+        .unwrap()
+        .with_timezone(&Utc);
+    let stored_bar_time = Utc.with_ymd_and_hms(2025, 7, 1, 14, 35, 7).unwrap();
+
+    assert!(is_same_bar_bucket(stored_bar_time, last_bar_available_time));
+}
+
+#[test]
+fn test_adjacent_bars_are_different_buckets() {
+    let bar_time = Utc.with_ymd_and_hms(2025, 7, 1, 14, 35, 0).unwrap();
+
+    assert!(!is_same_bar_bucket(
+        bar_time,
+        Utc.with_ymd_and_hms(2025, 7, 1, 14, 34, 59).unwrap()
+    ));
+    assert!(!is_same_bar_bucket(
+        bar_time,
+        Utc.with_ymd_and_hms(2025, 7, 1, 14, 40, 0).unwrap()
+    ));
+    assert!(is_same_bar_bucket(
+        bar_time,
+        Utc.with_ymd_and_hms(2025, 7, 1, 14, 39, 59).unwrap()
+    ));
+}
@@ -0,0 +1,53 @@
+use std::sync::{Arc, Mutex};
+
+use rust_decimal::{Decimal, dec};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{Layer, layer::Context, prelude::*};
+use trading_app::market_data::consolidator::{bar_volume, consolidate_partial_bucket};
+
+/// Counts the warnings logged
+#[derive(Default, Clone)]
+struct WarningCount(Arc<Mutex<usize>>);
+
+impl<S: Subscriber> Layer<S> for WarningCount {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+}
+
+#[test]
+fn test_volume_is_stored_in_shares() {
+    assert_eq!(bar_volume(12.0, "QQQ"), dec!(1200));
+    assert_eq!(bar_volume(0.0, "QQQ"), Decimal::ZERO);
+}
+
+#[test]
+fn test_nan_volume_is_zeroed_with_a_warning() {
+    let warnings = WarningCount::default();
+    let subscriber = tracing_subscriber::registry().with(warnings.clone());
+
+    // bucket with one malformed 5 second bar
+    let bars = [
+        (1_752_586_200, 500.0, 501.0, 499.5, 500.5, 10.0),
+        (1_752_586_205, 500.5, 502.0, 500.0, 501.5, f64::NAN),
+    ];
+    let (volumes, final_bar) = tracing::subscriber::with_default(subscriber, || {
+        let final_bar = consolidate_partial_bucket(&bars).expect("Expected a bar");
+        let volumes = vec![
+            bar_volume(f64::NAN, "QQQ"),
+            bar_volume(f64::INFINITY, "QQQ"),
+            bar_volume(final_bar.5, "QQQ"),
+        ];
+        (volumes, final_bar)
+    });
+
+    assert_eq!(volumes, vec![Decimal::ZERO; 3]);
+    assert_eq!(*warnings.0.lock().unwrap(), 3);
+    // the rest of the bar still goes through
+    assert_eq!(
+        (final_bar.1, final_bar.2, final_bar.3, final_bar.4),
+        (500.0, 502.0, 499.5, 501.5)
+    );
+}
@@ -0,0 +1,44 @@
+use chrono::{TimeZone, Utc};
+use trading_app::market_data::consolidator::consolidate_partial_bucket;
+
+#[test]
+fn test_partial_bucket_at_close_is_flushed_to_a_bar() {
+    // 15:55:00 - 15:55:10 New York 5 sec bars, session closes before the bucket completes
+    let bucket_start = Utc
+        .with_ymd_and_hms(2025, 7, 15, 19, 55, 0)
+        .unwrap()
+        .timestamp();
+    let partial_bucket = [
+        (bucket_start, 100.0, 101.0, 99.5, 100.5, 10.0),
+        (bucket_start + 5, 100.5, 102.0, 100.0, 101.5, 20.0),
+        (bucket_start + 10, 101.5, 101.75, 98.0, 99.0, 5.0),
+    ];
+
+    let (time, open, high, low, close, volume) =
+        consolidate_partial_bucket(&partial_bucket).expect("Expected partial bucket to be flushed");
+
+    assert_eq!(time, Utc.timestamp_opt(bucket_start, 0).unwrap());
+    assert_eq!(open, 100.0);
+    assert_eq!(high, 102.0);
+    assert_eq!(low, 98.0);
+    assert_eq!(close, 99.0);
+    assert_eq!(volume, 35.0);
+}
+
+#[test]
+fn test_bar_time_is_start_of_bucket_of_first_bar() {
+    let bucket_start = Utc
+        .with_ymd_and_hms(2025, 7, 15, 19, 55, 0)
+        .unwrap()
+        .timestamp();
+    let partial_bucket = [(bucket_start + 45, 100.0, 100.0, 100.0, 100.0, 1.0)];
+
+    let (time, ..) = consolidate_partial_bucket(&partial_bucket).unwrap();
+
+    assert_eq!(time, Utc.timestamp_opt(bucket_start, 0).unwrap());
+}
+
+#[test]
+fn test_empty_bucket_is_not_flushed() {
+    assert!(consolidate_partial_bucket(&[]).is_none());
+}
@@ -0,0 +1,195 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use ibapi::{orders::Action, prelude::Contract};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{Status, StrategyFullKeys, TargetStockPositionsFullKeys},
+        models_crud::{
+            strategy::get_strategy_crud, target_stock_positions::get_target_stock_positions_crud,
+        },
+    },
+    execution::order_engine::OrderEngine,
+    market_data::{
+        consolidator::act_on_timestep_bar,
+        history_gate::{HistoryCheck, HistoryGate, HistoryKey, history_key},
+        market_hours::MarketHours,
+    },
+};
+
+use crate::common::{
+    fixtures::{RecordingSubmitter, TestStrategy, option, stock},
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+fn key(days: u32) -> HistoryKey {
+    history_key(&stock("NVDA"), days)
+}
+
+#[tokio::test]
+async fn new_symbol_is_not_acted_on_until_its_history_is_stored() {
+    let gate = HistoryGate::new();
+    let stored_days = AtomicU32::new(0);
+    let checks = AtomicU32::new(0);
+    let has_history = || async {
+        checks.fetch_add(1, Ordering::SeqCst);
+        Ok(stored_days.load(Ordering::SeqCst) >= 5)
+    };
+
+    // subscribed mid-session with only a stub of history - no bar is acted on
+    assert_eq!(gate.check(key(5), has_history).await, Ok(false));
+    stored_days.store(3, Ordering::SeqCst);
+    assert_eq!(gate.check(key(5), has_history).await, Ok(false));
+    assert!(!gate.is_ready(&key(5)));
+
+    // the warm up catches up
+    stored_days.store(5, Ordering::SeqCst);
+    assert_eq!(gate.check(key(5), has_history).await, Ok(true));
+    assert!(gate.is_ready(&key(5)));
+
+    // and the history isn't read again for the rest of the session
+    assert_eq!(gate.check(key(5), has_history).await, Ok(true));
+    assert_eq!(checks.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn requirement_is_tracked_per_days_required() {
+    let gate = HistoryGate::new();
+    assert_eq!(gate.check(key(5), || async { Ok(true) }).await, Ok(true));
+
+    // a strategy needing more history of the same contract is still checked
+    assert!(!gate.is_ready(&key(20)));
+    assert_eq!(gate.check(key(20), || async { Ok(false) }).await, Ok(false));
+}
+
+#[tokio::test]
+async fn no_required_history_never_reads_it() {
+    let gate = HistoryGate::new();
+    let result = gate
+        .check(key(0), || async { Err("history read".to_string()) })
+        .await;
+    assert_eq!(result, Ok(true));
+}
+
+#[tokio::test]
+async fn failed_history_read_is_not_cached() {
+    let gate = HistoryGate::new();
+    assert!(
+        gate.check(key(5), || async { Err("connection reset".to_string()) })
+            .await
+            .is_err()
+    );
+    assert_eq!(gate.check(key(5), || async { Ok(true) }).await, Ok(true));
+}
+
+#[tokio::test]
+async fn options_are_gated_apart_from_their_underlying() {
+    let gate = HistoryGate::new();
+    assert_eq!(gate.check(key(5), || async { Ok(true) }).await, Ok(true));
+
+    assert!(!gate.is_ready(&history_key(&option("NVDA", "20250718", 100.0, "C"), 5)));
+    assert_eq!(
+        gate.check(
+            history_key(&option("NVDA", "20250718", 100.0, "C"), 5),
+            || async { Ok(true) }
+        )
+        .await,
+        Ok(true)
+    );
+    // another strike on the same underlying still needs its own history
+    assert!(!gate.is_ready(&history_key(&option("NVDA", "20250718", 110.0, "C"), 5)));
+}
+
+fn nasdaq_stock(symbol: &str) -> Contract {
+    let mut contract = stock(symbol);
+    contract.primary_exchange = "NASDAQ".to_string();
+    contract
+}
+
+#[tokio::test]
+async fn new_symbol_with_too_little_history_places_no_orders() {
+    const STRATEGY: &str = "history_gate_strat";
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Active,
+            })
+            .await
+            .expect("Expected to create strategy");
+        // targets as the strategy would set them off the bar - no bars of NEWHIST are stored
+        get_target_stock_positions_crud(pool.clone())
+            .create(&TargetStockPositionsFullKeys {
+                strategy: STRATEGY.to_string(),
+                stock: "NEWHIST".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                avg_price: 0.0,
+                quantity: 10.0,
+            })
+            .await
+            .expect("Expected to create target");
+
+        let strategy = TestStrategy {
+            contracts: vec![nasdaq_stock("NEWHIST")],
+            required_history_days: 5,
+            bar_update: (true, true),
+            ..TestStrategy::new(STRATEGY)
+        };
+        let order_engine = Arc::new(OrderEngine::new(pool.clone(), vec![strategy.clone()]));
+        let history_check = HistoryCheck::new(pool.clone(), MarketHours::default());
+        let client = Arc::new(RecordingSubmitter::default());
+        let bar = (Utc::now(), 100.0, 100.0, 100.0, 100.0, 1000.0);
+
+        act_on_timestep_bar(
+            strategy.clone(),
+            nasdaq_stock("NEWHIST"),
+            5,
+            bar,
+            history_check.clone(),
+            order_engine.clone(),
+            client.clone(),
+        )
+        .await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(client.submitted().is_empty());
+
+        // the same bar is traded once the strategy needs no more history than is stored
+        act_on_timestep_bar(
+            TestStrategy {
+                required_history_days: 0,
+                ..strategy
+            },
+            nasdaq_stock("NEWHIST"),
+            5,
+            bar,
+            history_check,
+            order_engine,
+            client.clone(),
+        )
+        .await;
+        for _ in 0..200 {
+            if !client.submitted().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let submitted: Vec<(String, Action, f64)> = client
+            .submitted()
+            .into_iter()
+            .map(|(_, contract, order)| (contract.symbol, order.action, order.total_quantity))
+            .collect();
+        assert_eq!(submitted, vec![("NEWHIST".to_string(), Action::Buy, 10.0)]);
+    })
+    .await;
+}
@@ -0,0 +1,48 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Barrier, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use trading_app::market_data::latest_price::LatestPrice;
+
+#[test]
+fn test_latest_price_is_none_until_first_bar() {
+    let latest_price = LatestPrice::new();
+    assert_eq!(latest_price.get(), None);
+
+    // the bars thread's clone updates the price readers see
+    latest_price.clone().set(101.25);
+    assert_eq!(latest_price.get(), Some(101.25));
+}
+
+#[test]
+fn test_read_does_not_wait_on_bars_being_consolidated() {
+    let collected_bars = Arc::new(Mutex::new(VecDeque::<f64>::new()));
+    let latest_price = LatestPrice::new();
+    let locked = Arc::new(Barrier::new(2));
+
+    // as stream_realtime_bars / on_new_5sec_bar - price set, then the bars held while the bucket
+    // is consolidated
+    let consolidating = {
+        let collected_bars = collected_bars.clone();
+        let latest_price = latest_price.clone();
+        let locked = locked.clone();
+        thread::spawn(move || {
+            latest_price.set(450.5);
+            let mut bars = collected_bars.lock().unwrap();
+            bars.push_back(450.5);
+            locked.wait();
+            thread::sleep(Duration::from_millis(500));
+        })
+    };
+
+    locked.wait();
+    let start = Instant::now();
+    assert!(collected_bars.try_lock().is_err());
+    assert_eq!(latest_price.get(), Some(450.5));
+    assert!(start.elapsed() < Duration::from_millis(100));
+
+    consolidating.join().unwrap();
+}
@@ -0,0 +1,91 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use trading_app::market_data::{
+    freshness::{LastBarTime, MarketDataHealth, MarketDataStatus, market_data_freshness},
+    market_hours::{Clock, MarketHours},
+};
+
+struct FixedClock(DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// Tuesday 2025-07-15 is a regular NYSE trading day
+fn trading_day_at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+    New_York
+        .with_ymd_and_hms(2025, 7, 15, hour, minute, second)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+#[test]
+fn test_last_bar_time_keeps_newest_bar() {
+    let last_bar_time = LastBarTime::new();
+    assert_eq!(last_bar_time.get(), None);
+
+    let newest = trading_day_at(10, 0, 5);
+    last_bar_time.clone().record(newest.timestamp());
+    // a contract lagging behind doesn't move it back
+    last_bar_time.record(trading_day_at(10, 0, 0).timestamp());
+
+    assert_eq!(last_bar_time.get(), Some(newest));
+}
+
+#[test]
+fn test_stale_bar_during_market_hours_is_degraded() {
+    let clock = FixedClock(trading_day_at(11, 0, 0));
+    let stale_after = Duration::from_secs(60);
+
+    let fresh = market_data_freshness(
+        &MarketHours::default(),
+        &clock,
+        Some(trading_day_at(10, 59, 55)),
+        stale_after,
+    );
+    let stale = market_data_freshness(
+        &MarketHours::default(),
+        &clock,
+        Some(trading_day_at(10, 55, 0)),
+        stale_after,
+    );
+
+    assert_eq!(fresh.status, MarketDataStatus::Ok);
+    assert!(stale.market_open);
+    assert_eq!(stale.status, MarketDataStatus::Degraded);
+}
+
+#[test]
+fn test_stale_bar_outside_market_hours_is_ok() {
+    let after_close = FixedClock(trading_day_at(18, 0, 0));
+
+    let freshness = market_data_freshness(
+        &MarketHours::default(),
+        &after_close,
+        Some(trading_day_at(16, 0, 0)),
+        Duration::from_secs(60),
+    );
+
+    assert!(!freshness.market_open);
+    assert_eq!(freshness.status, MarketDataStatus::Ok);
+}
+
+#[test]
+fn test_health_check_degrades_once_bars_stop() {
+    let last_bar_time = LastBarTime::new();
+    let health = MarketDataHealth {
+        last_bar_time: last_bar_time.clone(),
+        market_hours: MarketHours::default(),
+        stale_after: Duration::from_secs(60),
+        clock: Arc::new(FixedClock(trading_day_at(11, 0, 0))),
+    };
+    // no bar yet during market hours
+    assert_eq!(health.check().status, MarketDataStatus::Degraded);
+
+    last_bar_time.record(trading_day_at(10, 59, 30).timestamp());
+    assert_eq!(health.check().status, MarketDataStatus::Ok);
+}
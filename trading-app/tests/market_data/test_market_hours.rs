@@ -0,0 +1,259 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::{America::New_York, Asia::Tokyo};
+use trading_app::market_data::market_hours::{
+    Clock, MarketHours, SessionOpenGate, is_market_open_now,
+};
+
+struct FixedClock(DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// Clock the test moves forward by hand
+struct SteppedClock(Mutex<DateTime<Utc>>);
+
+impl Clock for SteppedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Tuesday 2025-07-15 is a regular NYSE trading day
+fn trading_day_at(hour: u32, minute: u32) -> FixedClock {
+    FixedClock(
+        New_York
+            .with_ymd_and_hms(2025, 7, 15, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc),
+    )
+}
+
+#[test]
+fn pre_open_on_trading_day_sleeps_until_open() {
+    let clock = trading_day_at(9, 15);
+    assert!(!is_market_open_now(&clock));
+    assert_eq!(
+        MarketHours::default().duration_until_next_open(&clock),
+        Some(chrono::Duration::minutes(15))
+    );
+}
+
+#[test]
+fn during_session_on_trading_day_returns() {
+    let clock = trading_day_at(10, 0);
+    assert!(is_market_open_now(&clock));
+    assert_eq!(
+        MarketHours::default().duration_until_next_open(&clock),
+        None
+    );
+}
+
+#[test]
+fn after_close_sleeps_until_next_trading_day() {
+    // Friday 2025-07-18 after close -> Monday 2025-07-21 09:30
+    let clock = FixedClock(
+        New_York
+            .with_ymd_and_hms(2025, 7, 18, 16, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc),
+    );
+    assert!(!is_market_open_now(&clock));
+    assert_eq!(
+        MarketHours::default().duration_until_next_open(&clock),
+        Some(chrono::Duration::hours(65))
+    );
+}
+
+#[test]
+fn required_bars_are_counted_from_the_0930_open() {
+    let market_hours = MarketHours::default();
+    let bars_at = |hour, minute| market_hours.bars_since_open(trading_day_at(hour, minute).0, 5);
+
+    // nothing before the open, where counting from 09:00 gave 3 bars at 09:15
+    assert_eq!(bars_at(9, 15), 0);
+    assert_eq!(bars_at(9, 30), 0);
+    // 09:30, 09:35 and 09:40 have completed, 09:45 is still building
+    assert_eq!(bars_at(9, 47), 3);
+    assert_eq!(bars_at(12, 0), 30);
+    // a full session once closed
+    assert_eq!(bars_at(16, 0), 78);
+    assert_eq!(bars_at(20, 0), 78);
+    assert_eq!(market_hours.bars_per_session(5), 78);
+}
+
+#[test]
+fn warmup_leeway_is_half_a_session_of_bars() {
+    let market_hours = MarketHours::default();
+    // the 39 five minute bars update_at_least_n_days_data used to hardcode
+    assert_eq!(market_hours.half_session_bars(5), 39);
+    assert_eq!(
+        market_hours.half_session_bars(5) * 2,
+        market_hours.bars_per_session(5)
+    );
+    // follows the session rather than assuming a full NYSE day
+    let half_day = MarketHours {
+        close: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+        ..MarketHours::default()
+    };
+    assert_eq!(half_day.half_session_bars(5), 21);
+}
+
+#[test]
+fn sessions_through_midnight_count_their_full_day_of_bars() {
+    // FX trades around the clock, futures stop for an hour at 17:00
+    assert_eq!(MarketHours::forex().bars_per_session(5), 288);
+    assert_eq!(MarketHours::futures().bars_per_session(5), 276);
+
+    // the futures session trading on the 15th opened at 18:00 on the 14th
+    let futures = MarketHours::futures();
+    assert_eq!(
+        futures.session_open_on(NaiveDate::from_ymd_opt(2025, 7, 15).unwrap()),
+        New_York.with_ymd_and_hms(2025, 7, 14, 18, 0, 0).unwrap()
+    );
+    // 16 hours in by 10:00
+    assert_eq!(futures.bars_since_open(trading_day_at(10, 0).0, 5), 192);
+    assert!(futures.is_market_open_now(&trading_day_at(2, 0)));
+    assert!(!futures.is_market_open_now(&trading_day_at(17, 30)));
+}
+
+fn new_york(day: u32, hour: u32, minute: u32) -> FixedClock {
+    FixedClock(
+        New_York
+            .with_ymd_and_hms(2025, 7, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc),
+    )
+}
+
+#[test]
+fn sessions_through_midnight_close_over_the_weekend() {
+    // Friday 2025-07-18 to Sunday 2025-07-20
+    let futures = MarketHours::futures();
+    // Friday's session runs until 17:00, the one opening at 18:00 would trade on Saturday
+    assert!(futures.is_market_open_now(&new_york(18, 16, 0)));
+    assert!(!futures.is_market_open_now(&new_york(18, 18, 30)));
+    assert!(!futures.is_market_open_now(&new_york(19, 12, 0)));
+    assert!(!futures.is_market_open_now(&new_york(20, 17, 30)));
+    // Sunday evening opens Monday's session
+    assert!(futures.is_market_open_now(&new_york(20, 18, 30)));
+    assert_eq!(
+        futures.duration_until_next_open(&new_york(18, 17, 30)),
+        Some(chrono::Duration::minutes(48 * 60 + 30))
+    );
+    assert_eq!(
+        futures.duration_until_next_open(&new_york(20, 18, 30)),
+        None
+    );
+
+    let forex = MarketHours::forex();
+    assert!(forex.is_market_open_now(&new_york(18, 16, 59)));
+    assert!(!forex.is_market_open_now(&new_york(18, 17, 0)));
+    assert!(forex.is_market_open_now(&new_york(20, 17, 0)));
+
+    // Thursday evening would open the session trading on the July 4th holiday
+    assert!(!futures.is_market_open_now(&new_york(3, 18, 30)));
+    assert!(futures.is_market_open_now(&new_york(6, 18, 30)));
+
+    assert!(!MarketHours::default().is_market_open_now(&new_york(19, 10, 0)));
+    assert_eq!(
+        MarketHours::default().duration_until_next_open(&new_york(18, 16, 0)),
+        Some(chrono::Duration::minutes(65 * 60 + 30))
+    );
+}
+
+#[tokio::test]
+async fn open_gate_defers_subscription_until_open() {
+    let clock = Arc::new(SteppedClock(Mutex::new(trading_day_at(9, 15).0)));
+    let gate = SessionOpenGate {
+        poll_interval: Duration::from_millis(10),
+        ..SessionOpenGate::default()
+    };
+    let subscribed = Arc::new(AtomicBool::new(false));
+
+    let gate_clock = clock.clone();
+    let gate_subscribed = subscribed.clone();
+    let handle = tokio::spawn(async move {
+        gate.wait_for_open(&*gate_clock).await;
+        gate_subscribed.store(true, Ordering::SeqCst);
+    });
+
+    // still pre-open - warmup is done but nothing is subscribed yet
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!subscribed.load(Ordering::SeqCst));
+    *clock.0.lock().unwrap() = trading_day_at(9, 29).0;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!subscribed.load(Ordering::SeqCst));
+
+    *clock.0.lock().unwrap() = trading_day_at(9, 30).0;
+    tokio::time::timeout(Duration::from_secs(1), handle)
+        .await
+        .expect("Expected gate to open at 09:30")
+        .unwrap();
+    assert!(subscribed.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn disabled_open_gate_subscribes_straight_after_warmup() {
+    let gate = SessionOpenGate {
+        enabled: false,
+        ..SessionOpenGate::default()
+    };
+    tokio::time::timeout(
+        Duration::from_millis(100),
+        gate.wait_for_open(&trading_day_at(9, 15)),
+    )
+    .await
+    .expect("Expected disabled gate not to wait");
+}
+
+#[test]
+fn bars_before_the_active_window_are_skipped() {
+    let market_hours = MarketHours::default();
+    let window = Some((
+        NaiveTime::from_hms_opt(9, 45, 0).unwrap(),
+        NaiveTime::from_hms_opt(15, 30, 0).unwrap(),
+    ));
+
+    // the 09:35 bar is in the first 15 minutes the strategy sits out
+    assert!(!market_hours.is_within_window(window, trading_day_at(9, 35).0));
+    assert!(market_hours.is_within_window(window, trading_day_at(9, 45).0));
+    assert!(!market_hours.is_within_window(window, trading_day_at(15, 30).0));
+    // compared in New York time, not UTC (13:35 UTC)
+    assert!(!market_hours.is_within_window(
+        window,
+        Utc.with_ymd_and_hms(2025, 7, 15, 13, 35, 0).unwrap()
+    ));
+    // no window trades every bar
+    assert!(market_hours.is_within_window(None, trading_day_at(9, 35).0));
+}
+
+#[test]
+fn history_requirement_counts_days_in_the_exchange_timezone() {
+    let tokyo_hours = MarketHours {
+        timezone: Tokyo,
+        open: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        close: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+        ..MarketHours::default()
+    };
+    // 10:00 on Wednesday the 16th in Tokyo, still the 15th in New York
+    let now = Utc.with_ymd_and_hms(2025, 7, 16, 1, 0, 0).unwrap();
+
+    let (earliest, _, is_trading_day) = tokyo_hours.history_requirement(1, now);
+
+    assert_eq!(
+        earliest,
+        Tokyo.with_ymd_and_hms(2025, 7, 16, 9, 0, 0).unwrap()
+    );
+    assert!(is_trading_day);
+}
@@ -0,0 +1,95 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use trading_app::market_data::pacing::{MarketDataPacer, PacingConfig, TokenBucket};
+
+#[test]
+fn test_burst_within_capacity_is_not_delayed() {
+    let bucket = TokenBucket::new(5.0, 5.0);
+    let now = Instant::now();
+
+    for _ in 0..5 {
+        assert_eq!(bucket.reserve_at(now), Duration::ZERO);
+    }
+}
+
+#[test]
+fn test_burst_past_capacity_is_spaced_at_configured_rate() {
+    let bucket = TokenBucket::new(2.0, 10.0);
+    let now = Instant::now();
+
+    let waits: Vec<Duration> = (0..6).map(|_| bucket.reserve_at(now)).collect();
+
+    // 2 go straight away, the rest are 100ms apart
+    let expected_ms = [0, 0, 100, 200, 300, 400];
+    for (wait, expected) in waits.iter().zip(expected_ms) {
+        assert!(
+            wait.as_millis().abs_diff(expected) <= 1,
+            "Expected wait of {}ms, got {:?}",
+            expected,
+            wait
+        );
+    }
+}
+
+#[test]
+fn test_tokens_refill_over_time_up_to_capacity() {
+    let bucket = TokenBucket::new(2.0, 10.0);
+    let now = Instant::now();
+    bucket.reserve_at(now);
+    bucket.reserve_at(now);
+
+    // Long idle only refills up to capacity
+    let later = now + Duration::from_secs(10);
+    assert_eq!(bucket.reserve_at(later), Duration::ZERO);
+    assert_eq!(bucket.reserve_at(later), Duration::ZERO);
+    assert!(bucket.reserve_at(later) > Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_pacer_keeps_historical_requests_under_configured_rate() {
+    let pacer = MarketDataPacer::new(PacingConfig {
+        messages_per_sec: 50.0,
+        historical_burst: 2.0,
+        historical_per_sec: 20.0,
+    });
+
+    let start = Instant::now();
+    for _ in 0..6 {
+        pacer.before_historical_data().await;
+    }
+
+    // 2 in the burst, then 4 more at 20/s
+    assert!(start.elapsed() >= Duration::from_millis(195));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_acquire_waits_without_blocking_the_runtime() {
+    // empty after one token, refilled every 100ms
+    let bucket = TokenBucket::new(1.0, 10.0);
+    bucket.acquire().await;
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = tokio::spawn({
+        let ticks = ticks.clone();
+        async move {
+            for _ in 0..5 {
+                ticks.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    });
+
+    let start = Instant::now();
+    bucket.acquire().await;
+
+    assert!(start.elapsed() >= Duration::from_millis(95));
+    // the single worker thread kept running the other task while acquire waited
+    assert_eq!(ticks.load(Ordering::SeqCst), 5);
+    ticker.await.expect("Expected ticker to finish");
+}
@@ -0,0 +1,34 @@
+use std::collections::VecDeque;
+
+use trading_app::market_data::consolidator::{DEFAULT_MAX_RETAINED_BARS, push_capped};
+
+#[test]
+fn test_bars_beyond_cap_are_evicted_oldest_first() {
+    let mut bars: VecDeque<i64> = VecDeque::new();
+    let mut evicted = 0;
+    // a day of 5 second bars with no bucket ever completing
+    for timestamp in (0..4680).map(|i| 1_752_586_200 + i * 5) {
+        evicted += push_capped(&mut bars, timestamp, DEFAULT_MAX_RETAINED_BARS);
+        assert!(bars.len() <= DEFAULT_MAX_RETAINED_BARS);
+    }
+
+    assert_eq!(bars.len(), DEFAULT_MAX_RETAINED_BARS);
+    assert_eq!(evicted, 4680 - DEFAULT_MAX_RETAINED_BARS);
+    // the newest bars are the ones kept
+    assert_eq!(bars.back(), Some(&(1_752_586_200 + 4679 * 5)));
+    assert_eq!(
+        bars.front(),
+        Some(&(1_752_586_200 + (4680 - DEFAULT_MAX_RETAINED_BARS as i64) * 5))
+    );
+}
+
+#[test]
+fn test_bars_within_cap_are_kept() {
+    let mut bars: VecDeque<i64> = VecDeque::from([1, 2]);
+    assert_eq!(push_capped(&mut bars, 3, 3), 0);
+    assert_eq!(bars, VecDeque::from([1, 2, 3]));
+
+    // the latest bar is always kept
+    assert_eq!(push_capped(&mut bars, 4, 0), 3);
+    assert_eq!(bars, VecDeque::from([4]));
+}
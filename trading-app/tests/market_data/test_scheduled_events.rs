@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use trading_app::market_data::{
+    market_hours::Clock,
+    scheduler::{EventSchedule, ScheduledEvent},
+};
+
+/// Clock the test moves forward by hand
+struct SteppedClock(Mutex<DateTime<Utc>>);
+
+impl SteppedClock {
+    fn set(&self, day: u32, hour: u32, minute: u32) {
+        *self.0.lock().unwrap() = New_York
+            .with_ymd_and_hms(2025, 7, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+    }
+}
+
+impl Clock for SteppedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+fn clock_at(day: u32, hour: u32, minute: u32) -> SteppedClock {
+    let clock = SteppedClock(Mutex::new(Utc::now()));
+    clock.set(day, hour, minute);
+    clock
+}
+
+#[test]
+fn test_event_fires_once_when_clock_reaches_it() {
+    let flatten = ScheduledEvent::new("flatten", 15, 50);
+    let mut schedule = EventSchedule::new(New_York, vec![flatten.clone()]);
+    let clock = clock_at(15, 15, 49);
+    assert!(schedule.due(&clock).is_empty());
+    assert_eq!(
+        schedule.duration_until_next(&clock),
+        Some(chrono::Duration::minutes(1))
+    );
+
+    clock.set(15, 15, 50);
+    assert_eq!(schedule.due(&clock), vec![flatten.clone()]);
+    assert_eq!(schedule.duration_until_next(&clock), None);
+
+    clock.set(15, 15, 55);
+    assert!(schedule.due(&clock).is_empty());
+
+    // next session
+    clock.set(16, 15, 50);
+    assert_eq!(schedule.due(&clock), vec![flatten]);
+}
+
+#[test]
+fn test_times_are_in_exchange_timezone() {
+    let mut schedule = EventSchedule::new(New_York, vec![ScheduledEvent::new("open", 9, 30)]);
+    // 13:30 UTC is 09:30 New York in July
+    let clock = SteppedClock(Mutex::new(
+        Utc.with_ymd_and_hms(2025, 7, 15, 13, 29, 0).unwrap(),
+    ));
+    assert!(schedule.due(&clock).is_empty());
+    *clock.0.lock().unwrap() = Utc.with_ymd_and_hms(2025, 7, 15, 13, 30, 0).unwrap();
+    assert_eq!(schedule.due(&clock).len(), 1);
+}
@@ -0,0 +1,23 @@
+use ibapi::prelude::Contract;
+use trading_app::market_data::consolidator::strategy_subscriptions;
+
+use crate::common::fixtures::TestStrategy;
+
+#[test]
+fn test_every_contract_of_strategy_is_subscribed_at_its_timestep() {
+    // trades QQQ and SPY off 15 minute bars
+    let two_symbol_strategy = TestStrategy {
+        contracts: vec![Contract::stock("QQQ"), Contract::stock("SPY")],
+        timestep: 15,
+        ..TestStrategy::new("two_symbol_strat")
+    };
+    let subscriptions: Vec<(String, u32)> = strategy_subscriptions(&two_symbol_strategy)
+        .into_iter()
+        .map(|(contract, timestep)| (contract.symbol, timestep))
+        .collect();
+
+    assert_eq!(
+        subscriptions,
+        vec![("QQQ".to_string(), 15), ("SPY".to_string(), 15)]
+    );
+}
@@ -0,0 +1,67 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use trading_app::market_data::consolidator::{ConsolidatedBar, TimestepAggregator, aggregate_bars};
+
+fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+    New_York
+        .with_ymd_and_hms(2025, 7, 15, hour, minute, 0)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn five_min_bars() -> Vec<ConsolidatedBar> {
+    vec![
+        (at(9, 30), 100.0, 101.0, 99.5, 100.5, 1000.0),
+        (at(9, 35), 100.5, 103.0, 100.0, 102.0, 1500.0),
+        (at(9, 40), 102.0, 102.5, 98.0, 99.0, 500.0),
+    ]
+}
+
+#[test]
+fn test_three_5_min_bars_aggregate_into_one_15_min_bar() {
+    let mut aggregator = TimestepAggregator::new(15);
+    let emitted: Vec<_> = five_min_bars()
+        .into_iter()
+        .map(|bar| aggregator.push(bar))
+        .collect();
+
+    assert_eq!(emitted[0], None);
+    assert_eq!(emitted[1], None);
+    assert_eq!(
+        emitted[2],
+        Some((at(9, 30), 100.0, 103.0, 98.0, 99.0, 3000.0))
+    );
+    assert_eq!(aggregate_bars(&five_min_bars()), emitted[2]);
+}
+
+#[test]
+fn test_5_min_timestep_passes_every_bar_through() {
+    let mut aggregator = TimestepAggregator::new(5);
+    for bar in five_min_bars() {
+        assert_eq!(aggregator.push(bar), Some(bar));
+    }
+}
+
+#[test]
+fn test_incomplete_bucket_is_not_merged_into_next() {
+    let mut aggregator = TimestepAggregator::new(15);
+    // 09:30 bucket only ever sees its first bar
+    assert_eq!(
+        aggregator.push((at(9, 30), 100.0, 101.0, 99.0, 100.0, 10.0)),
+        None
+    );
+    assert_eq!(
+        aggregator.push((at(9, 45), 200.0, 201.0, 199.0, 200.5, 20.0)),
+        None
+    );
+    assert_eq!(
+        aggregator.push((at(9, 50), 200.5, 202.0, 200.0, 201.0, 20.0)),
+        None
+    );
+    assert_eq!(
+        aggregator.push((at(9, 55), 201.0, 201.5, 198.0, 199.0, 20.0)),
+        Some((at(9, 45), 200.0, 202.0, 198.0, 199.0, 60.0))
+    );
+    // pre-open bars are never part of a session bar
+    assert_eq!(aggregator.push((at(9, 25), 1.0, 1.0, 1.0, 1.0, 1.0)), None);
+}
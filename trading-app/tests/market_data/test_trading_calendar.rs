@@ -0,0 +1,74 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use nyse_holiday_cal::HolidayCal;
+use trading_app::market_data::market_hours::TradingCalendar;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn cached_calendar_agrees_with_nyse_holidays() {
+    let calendar = TradingCalendar::new(New_York);
+    // Friday 2025-07-04 is Independence Day, the market reopens Monday 2025-07-07
+    let holiday = date(2025, 7, 4);
+    let reopen = date(2025, 7, 7);
+
+    assert!(!calendar.is_trading_day(holiday));
+    assert_eq!(
+        calendar.is_trading_day(holiday),
+        holiday.is_busday().unwrap()
+    );
+    assert_eq!(calendar.next_trading_day(holiday), reopen);
+    assert!(calendar.is_trading_day(reopen));
+    assert_eq!(calendar.is_trading_day(reopen), reopen.is_busday().unwrap());
+    assert_eq!(calendar.prev_trading_day(reopen), date(2025, 7, 3));
+
+    // served from the cache the second time round
+    assert_eq!(calendar.next_trading_day(holiday), reopen);
+}
+
+#[test]
+fn trading_days_span_year_boundaries() {
+    let calendar = TradingCalendar::new(New_York);
+    // 2025-01-01 is New Year's Day
+    assert_eq!(
+        calendar.prev_trading_day(date(2025, 1, 2)),
+        date(2024, 12, 31)
+    );
+    assert_eq!(
+        calendar.next_trading_day(date(2024, 12, 31)),
+        date(2025, 1, 2)
+    );
+    assert_eq!(
+        calendar.trading_days_between(date(2024, 12, 30), date(2025, 1, 3)),
+        vec![
+            date(2024, 12, 30),
+            date(2024, 12, 31),
+            date(2025, 1, 2),
+            date(2025, 1, 3)
+        ]
+    );
+    assert!(
+        calendar
+            .trading_days_between(date(2025, 1, 3), date(2024, 12, 30))
+            .is_empty()
+    );
+}
+
+#[test]
+fn trading_date_is_taken_in_the_calendar_timezone() {
+    let calendar = TradingCalendar::nyse();
+    // 01:00 UTC on the 8th is still the evening of 2025-07-07 in New York
+    let time = Utc.with_ymd_and_hms(2025, 7, 8, 1, 0, 0).unwrap();
+    assert_eq!(calendar.trading_date(time), date(2025, 7, 7));
+    assert_eq!(
+        calendar.trading_date(
+            New_York
+                .with_ymd_and_hms(2025, 7, 8, 9, 30, 0)
+                .unwrap()
+                .to_utc()
+        ),
+        date(2025, 7, 8)
+    );
+}
@@ -0,0 +1,67 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use trading_app::market_data::in_flight::InFlightRequests;
+
+/// Stands in for the IBKR historical data request made during warm up
+async fn historical_request(requests: &AtomicUsize) -> Result<(), String> {
+    requests.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_warmups_for_same_contract_request_once() {
+    let warmups = InFlightRequests::<(String, String)>::new();
+    let requests = AtomicUsize::new(0);
+    let qqq = ("QQQ".to_string(), "NASDAQ".to_string());
+
+    let (strat_a, strat_b) = tokio::join!(
+        warmups.run_once(qqq.clone(), || historical_request(&requests)),
+        warmups.run_once(qqq.clone(), || historical_request(&requests)),
+    );
+
+    assert_eq!(strat_a, Ok(()));
+    assert_eq!(strat_b, Ok(()));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_different_contracts_and_later_warmups_request_again() {
+    let warmups = InFlightRequests::<(String, String)>::new();
+    let requests = AtomicUsize::new(0);
+    let qqq = ("QQQ".to_string(), "NASDAQ".to_string());
+    let spy = ("SPY".to_string(), "ARCA".to_string());
+
+    let (qqq_res, spy_res) = tokio::join!(
+        warmups.run_once(qqq.clone(), || historical_request(&requests)),
+        warmups.run_once(spy, || historical_request(&requests)),
+    );
+    assert!(qqq_res.is_ok() && spy_res.is_ok());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Nothing in flight anymore - runs again
+    warmups
+        .run_once(qqq, || historical_request(&requests))
+        .await
+        .expect("Expected warm up to succeed");
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_waiting_warmup_gets_error_of_running_one() {
+    let warmups = InFlightRequests::<String>::new();
+
+    let (first, second) = tokio::join!(
+        warmups.run_once("QQQ".to_string(), || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err("pacing violation".to_string())
+        }),
+        warmups.run_once("QQQ".to_string(), || async { Ok(()) }),
+    );
+
+    assert_eq!(first, Err("pacing violation".to_string()));
+    assert_eq!(second, Err("pacing violation".to_string()));
+}
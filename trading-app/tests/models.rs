@@ -1,5 +1,6 @@
+mod common;
+
 mod models {
-    pub mod init;
     pub mod test_current_option_positions;
     pub mod test_current_stock_positions;
     pub mod test_historical_data;
@@ -8,6 +9,8 @@ mod models {
     pub mod test_open_option_orders;
     pub mod test_open_stock_orders;
     pub mod test_option_transactions;
+    pub mod test_primary_key_attribute;
+    pub mod test_primary_key_tuple;
     pub mod test_stock_transactions;
     pub mod test_staged_commissions;
     pub mod test_strategy;
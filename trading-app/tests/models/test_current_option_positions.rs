@@ -2,7 +2,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::current_option_positions::get_current_option_positions_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -14,6 +14,7 @@ macro_rules! normal_fk {
     () => {
         &trading_app::database::models::CurrentOptionPositionsFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
@@ -28,6 +29,7 @@ macro_rules! inv_fk {
     () => {
         &trading_app::database::models::CurrentOptionPositionsFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
@@ -42,6 +44,7 @@ macro_rules! normal_pk {
     () => {
         &trading_app::database::models::CurrentOptionPositionsPrimaryKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
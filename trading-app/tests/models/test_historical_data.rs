@@ -4,7 +4,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::historical_data::get_specific_historical_data_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 
 macro_rules! get_crud {
     ($pool:expr) => {
@@ -15,6 +15,7 @@ macro_rules! normal_fk {
     () => {
         &trading_app::database::models::HistoricalDataFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             open: 0.0,
             high: 1.0,
             low: 2.0,
@@ -36,6 +37,7 @@ macro_rules! inv_fk {
     () => {
         &trading_app::database::models::HistoricalDataFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             open: 3.0,
             high: 2.0,
             low: 1.0,
@@ -57,6 +59,7 @@ macro_rules! normal_pk {
     () => {
         &trading_app::database::models::HistoricalDataPrimaryKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             time: Utc::now()
                 .with_hour(0)
                 .unwrap()
@@ -290,7 +293,7 @@ async fn test_read_last_bar() {
     let time = Utc::now();
     normal_create!(crud);
     let data = crud
-        .read_last_bar_of_stock("QQQ".to_string())
+        .read_last_bar_of_stock("QQQ".to_string(), "NASDAQ".to_string())
         .await
         .expect("Expected DB request to be fine")
         .expect("Expected to get last bar of stock");
@@ -8,7 +8,7 @@ use trading_app::database::{
     },
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 
 macro_rules! get_crud {
     ($pool:expr) => {
@@ -19,6 +19,7 @@ macro_rules! normal_fk {
     () => {
         &trading_app::database::models::HistoricalOptionsDataFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -44,6 +45,7 @@ macro_rules! inv_fk {
     () => {
         &trading_app::database::models::HistoricalOptionsDataFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -69,6 +71,7 @@ macro_rules! normal_pk {
     () => {
         &trading_app::database::models::HistoricalOptionsDataPrimaryKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
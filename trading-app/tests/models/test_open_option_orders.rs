@@ -3,7 +3,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::open_option_orders::get_open_option_orders_crud,
 };
 
-use crate::models::init::{setup_test_db, TEST_MUTEX};
+use crate::common::init::{setup_test_db, TEST_MUTEX};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -17,6 +17,7 @@ macro_rules! normal_fk {
             order_perm_id: 1,
             order_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -34,6 +35,7 @@ macro_rules! normal_fk {
             quantity: 9.0,
             executions: [].to_vec(),
             filled: 0.0,
+            order_snapshot: None,
         }
     };
 }
@@ -43,6 +45,7 @@ macro_rules! inv_fk {
             order_perm_id: 1,
             order_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -60,6 +63,7 @@ macro_rules! inv_fk {
             quantity: 0.0,
             executions: [].to_vec(),
             filled: 9.0,
+            order_snapshot: None,
         }
     };
 }
@@ -75,6 +79,7 @@ macro_rules! normal_uk {
     () => {
         &trading_app::database::models::OpenOptionOrdersUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             expiry: Some("20251122".to_string()),
             strike: Some(300.0),
             multiplier: Some("100".to_string()),
@@ -94,6 +99,7 @@ macro_rules! normal_uk {
             quantity: Some(9.0),
             executions: Some([].to_vec()),
             filled: Some(0.0),
+            order_snapshot: None,
         }
     };
 }
@@ -101,6 +107,7 @@ macro_rules! inv_uk {
     () => {
         &trading_app::database::models::OpenOptionOrdersUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             strategy: Some("strat_a".to_string()),
             expiry: Some("20251122".to_string()),
             strike: Some(300.0),
@@ -120,6 +127,7 @@ macro_rules! inv_uk {
             quantity: Some(0.0),
             executions: Some([].to_vec()),
             filled: Some(9.0),
+            order_snapshot: None,
         }
     };
 }
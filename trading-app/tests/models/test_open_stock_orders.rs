@@ -3,7 +3,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::open_stock_orders::get_open_stock_orders_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -17,6 +17,7 @@ macro_rules! normal_fk {
             order_perm_id: 1,
             order_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             time: Utc::now()
                 .with_hour(0)
@@ -30,6 +31,7 @@ macro_rules! normal_fk {
             quantity: 9.0,
             executions: [].to_vec(),
             filled: 0.0,
+            order_snapshot: None,
         }
     };
 }
@@ -39,6 +41,7 @@ macro_rules! inv_fk {
             order_perm_id: 1,
             order_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             time: Utc::now()
                 .with_hour(0)
@@ -52,6 +55,7 @@ macro_rules! inv_fk {
             quantity: 0.0,
             executions: [].to_vec(),
             filled: 9.0,
+            order_snapshot: None,
         }
     };
 }
@@ -67,6 +71,7 @@ macro_rules! normal_uk {
     () => {
         &trading_app::database::models::OpenStockOrdersUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             strategy: Some("strat_a".to_string()),
             time: Some(
                 Utc::now()
@@ -82,6 +87,7 @@ macro_rules! normal_uk {
             quantity: Some(9.0),
             executions: Some([].to_vec()),
             filled: Some(0.0),
+            order_snapshot: None,
         }
     };
 }
@@ -89,6 +95,7 @@ macro_rules! inv_uk {
     () => {
         &trading_app::database::models::OpenStockOrdersUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             strategy: Some("strat_a".to_string()),
             time: Some(
                 Utc::now()
@@ -104,6 +111,7 @@ macro_rules! inv_uk {
             quantity: Some(0.0),
             executions: Some([].to_vec()),
             filled: Some(9.0),
+            order_snapshot: None,
         }
     };
 }
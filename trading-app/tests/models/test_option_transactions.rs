@@ -4,7 +4,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::option_transactions::get_option_transactions_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -18,6 +18,7 @@ macro_rules! normal_fk {
             execution_id: "12".to_string(),
             order_perm_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -36,6 +37,9 @@ macro_rules! normal_fk {
             quantity: 2.0,
             fees: rust_decimal::Decimal::from_f64(0.0)
                 .expect("Expected commission from commission_report to be valid for Decimal"),
+            raw_broker_time: None,
+            closes_position: false,
+            realized_pnl: None,
         }
     };
 }
@@ -45,6 +49,7 @@ macro_rules! inv_fk {
             execution_id: "12".to_string(),
             order_perm_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             expiry: "20251122".to_string(),
             strike: 300.0,
             multiplier: "100".to_string(),
@@ -63,6 +68,9 @@ macro_rules! inv_fk {
             quantity: 2.0,
             fees: rust_decimal::Decimal::from_f64(1.0)
                 .expect("Expected commission from commission_report to be valid for Decimal"),
+            raw_broker_time: None,
+            closes_position: false,
+            realized_pnl: None,
         }
     };
 }
@@ -89,6 +97,7 @@ macro_rules! normal_uk {
                     .unwrap(),
             ),
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             expiry: Some("20251122".to_string()),
             strike: Some(300.0),
             multiplier: Some("100".to_string()),
@@ -100,6 +109,9 @@ macro_rules! normal_uk {
                 rust_decimal::Decimal::from_f64(0.0)
                     .expect("Expected commission from commission_report to be valid for Decimal"),
             ),
+            raw_broker_time: None,
+            closes_position: Some(false),
+            realized_pnl: None,
         }
     };
 }
@@ -119,6 +131,7 @@ macro_rules! inv_uk {
                     .unwrap(),
             ),
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             expiry: Some("20251122".to_string()),
             strike: Some(300.0),
             multiplier: Some("100".to_string()),
@@ -130,6 +143,9 @@ macro_rules! inv_uk {
                 rust_decimal::Decimal::from_f64(1.0)
                     .expect("Expected commission from commission_report to be valid for Decimal"),
             ),
+            raw_broker_time: None,
+            closes_position: Some(false),
+            realized_pnl: None,
         }
     };
 }
@@ -0,0 +1,119 @@
+use crud_insertable::DeriveInsertable;
+use crud_models::{ExtractFullKeys, ExtractPrimaryKeys, ExtractUpdateKeys};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Postgres, postgres::PgArguments, query::Query, query::QueryAs};
+use trading_app::{
+    Insertable,
+    database::crud::{CRUD, CRUDTrait},
+};
+
+use crate::common::init::{TEST_MUTEX, setup_test_db, with_rollback};
+
+// avg_price is a non-Option column that isn't part of the primary key
+#[allow(dead_code)]
+#[derive(
+    Debug, Clone, Serialize, Deserialize, ExtractFullKeys, ExtractPrimaryKeys, ExtractUpdateKeys,
+)]
+struct PositionFixture {
+    #[primary_key]
+    strategy: String,
+    #[primary_key]
+    stock: String,
+    avg_price: f64,
+    quantity: Option<f64>,
+}
+
+#[test]
+fn only_marked_fields_are_primary_keys() {
+    let primary_keys = PositionFixturePrimaryKeys {
+        strategy: "strat_a".to_string(),
+        stock: "AAPL".to_string(),
+    };
+    assert_eq!(primary_keys.pri_column_names(), vec!["strategy", "stock"]);
+    assert_eq!(
+        PositionFixturePrimaryKeys::column_names(),
+        vec!["strategy", "stock"]
+    );
+}
+
+#[test]
+fn every_unmarked_field_is_an_optional_update_key() {
+    let update_keys = PositionFixtureUpdateKeys {
+        avg_price: Some(450.0),
+        quantity: None,
+    };
+    assert!(update_keys.pri_column_names().is_empty());
+    assert_eq!(
+        PositionFixtureUpdateKeys::column_names(),
+        vec!["avg_price", "quantity"]
+    );
+    // only the data columns given are updated
+    assert_eq!(update_keys.opt_column_names(), vec!["avg_price"]);
+}
+
+fn full_keys(avg_price: f64, quantity: Option<f64>) -> PositionFixtureFullKeys {
+    PositionFixtureFullKeys {
+        strategy: "strat_a".to_string(),
+        stock: "AAPL".to_string(),
+        avg_price,
+        quantity,
+    }
+}
+
+#[test]
+fn full_keys_keep_every_field() {
+    assert_eq!(
+        PositionFixtureFullKeys::column_names(),
+        vec!["strategy", "stock", "avg_price", "quantity"]
+    );
+    // quantity stays nullable, avg_price is always written but isn't a primary key
+    let without_quantity = full_keys(450.0, None);
+    assert_eq!(
+        without_quantity.pri_column_names(),
+        vec!["strategy", "stock"]
+    );
+    assert_eq!(without_quantity.opt_column_names(), vec!["avg_price"]);
+    assert_eq!(
+        full_keys(450.0, Some(10.0)).opt_column_names(),
+        vec!["avg_price", "quantity"]
+    );
+}
+
+#[tokio::test]
+async fn upsert_many_conflicts_on_the_marked_fields_only() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        sqlx::query(
+            "CREATE TEMP TABLE position_fixture (
+                strategy TEXT, stock TEXT, avg_price DOUBLE PRECISION, quantity DOUBLE PRECISION,
+                PRIMARY KEY (strategy, stock)
+            )",
+        )
+        .execute(&pool)
+        .await
+        .expect("Expected to create position_fixture");
+        let crud = CRUD::<
+            PositionFixtureFullKeys,
+            PositionFixturePrimaryKeys,
+            PositionFixtureUpdateKeys,
+        >::new(pool.clone(), "position_fixture".to_string());
+
+        crud.upsert_many(&[full_keys(450.0, Some(10.0))])
+            .await
+            .expect("Expected rows to be inserted");
+        // a new avg_price for the same (strategy, stock) updates the row in place
+        crud.upsert_many(&[full_keys(455.0, Some(10.0))])
+            .await
+            .expect("Expected rows to be updated");
+
+        let rows = sqlx::query_as::<_, (f64, Option<f64>)>(
+            "SELECT avg_price, quantity FROM position_fixture",
+        )
+        .fetch_all(&pool)
+        .await
+        .expect("Expected to read position_fixture");
+        assert_eq!(rows, vec![(455.0, Some(10.0))]);
+    })
+    .await;
+}
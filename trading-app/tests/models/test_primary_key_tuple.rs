@@ -0,0 +1,116 @@
+use std::collections::{HashMap, HashSet};
+
+use crud_insertable::DeriveInsertable;
+use crud_models::ExtractPrimaryKeys;
+use ibapi::prelude::{Contract, SecurityType};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Postgres, postgres::PgArguments, query::Query, query::QueryAs};
+use trading_app::{
+    Insertable,
+    database::{
+        models::{CurrentOptionPositionsKeyTuple, CurrentOptionPositionsPrimaryKeys, OptionType},
+        models_crud::current_option_positions::GroupedByContract,
+    },
+    execution::sync::{broker_option_key, local_option_key},
+};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize, ExtractPrimaryKeys)]
+struct StrikeFixture {
+    stock: String,
+    strike: f64,
+    quantity: Option<f64>,
+}
+
+fn option_position(strategy: &str, strike: f64) -> CurrentOptionPositionsPrimaryKeys {
+    CurrentOptionPositionsPrimaryKeys {
+        stock: "AAPL".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        strategy: strategy.to_string(),
+        expiry: "20250718".to_string(),
+        strike,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Call,
+    }
+}
+
+#[test]
+fn key_tuple_wraps_float_keys_in_ordered_float() {
+    let primary_keys = StrikeFixturePrimaryKeys {
+        stock: "AAPL".to_string(),
+        strike: 150.5,
+    };
+    let key: (String, OrderedFloat<f64>) = primary_keys.as_key_tuple();
+    assert_eq!(key, ("AAPL".to_string(), OrderedFloat(150.5)));
+
+    let mut quantities: HashMap<StrikeFixtureKeyTuple, f64> = HashMap::new();
+    quantities.insert(key, 3.0);
+    assert_eq!(quantities[&primary_keys.as_key_tuple()], 3.0);
+}
+
+#[test]
+fn key_tuple_keeps_declaration_order() {
+    let key = option_position("strat_a", 200.0).as_key_tuple();
+    assert_eq!(
+        key,
+        (
+            "AAPL".to_string(),
+            "NASDAQ".to_string(),
+            "strat_a".to_string(),
+            "20250718".to_string(),
+            OrderedFloat(200.0),
+            "100".to_string(),
+            OptionType::Call,
+        )
+    );
+}
+
+#[test]
+fn key_tuple_tells_primary_keys_apart() {
+    let keys: HashSet<CurrentOptionPositionsKeyTuple> = [
+        option_position("strat_a", 200.0),
+        option_position("strat_a", 200.0),
+        option_position("strat_a", 205.0),
+        option_position("strat_b", 200.0),
+    ]
+    .iter()
+    .map(CurrentOptionPositionsPrimaryKeys::as_key_tuple)
+    .collect();
+    assert_eq!(keys.len(), 3);
+}
+
+#[test]
+fn broker_and_local_option_keys_match_on_the_contract() {
+    let local = GroupedByContract {
+        stock: "AAPL".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        expiry: "20250718".to_string(),
+        strike: 200.0,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Call,
+        quantity: 2.0,
+    };
+    let broker = Contract {
+        symbol: "AAPL".to_string(),
+        security_type: SecurityType::Option,
+        last_trade_date_or_contract_month: "20250718".to_string(),
+        strike: 200.0,
+        multiplier: "100".to_string(),
+        right: "C".to_string(),
+        ..Contract::default()
+    };
+    assert_eq!(
+        broker_option_key(&broker).expect("Expected C to parse as a call"),
+        local_option_key(&local)
+    );
+
+    let put = Contract {
+        right: "P".to_string(),
+        ..broker
+    };
+    assert_ne!(
+        broker_option_key(&put).expect("Expected P to parse as a put"),
+        local_option_key(&local)
+    );
+}
@@ -3,7 +3,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::staged_commissions::get_staged_commissions_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 
 macro_rules! get_crud {
     ($pool:expr) => {
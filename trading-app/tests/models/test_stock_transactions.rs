@@ -4,7 +4,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::stock_transactions::get_stock_transactions_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -18,6 +18,7 @@ macro_rules! normal_fk {
             execution_id: "12".to_string(),
             order_perm_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             time: Utc::now()
                 .with_hour(0)
@@ -32,6 +33,9 @@ macro_rules! normal_fk {
             quantity: 2.0,
             fees: rust_decimal::Decimal::from_f64(0.0)
                 .expect("Expected commission from commission_report to be valid for Decimal"),
+            raw_broker_time: None,
+            closes_position: false,
+            realized_pnl: None,
         }
     };
 }
@@ -41,6 +45,7 @@ macro_rules! inv_fk {
             execution_id: "12".to_string(),
             order_perm_id: 1,
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             time: Utc::now()
                 .with_hour(0)
@@ -55,6 +60,9 @@ macro_rules! inv_fk {
             quantity: 2.0,
             fees: rust_decimal::Decimal::from_f64(1.0)
                 .expect("Expected commission from commission_report to be valid for Decimal"),
+            raw_broker_time: None,
+            closes_position: false,
+            realized_pnl: None,
         }
     };
 }
@@ -69,6 +77,7 @@ macro_rules! normal_uk {
     () => {
         &trading_app::database::models::StockTransactionsUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             strategy: Some("strat_a".to_string()),
             order_perm_id: Some(1),
             time: Some(
@@ -88,6 +97,9 @@ macro_rules! normal_uk {
                 rust_decimal::Decimal::from_f64(0.0)
                     .expect("Expected commission from commission_report to be valid for Decimal"),
             ),
+            raw_broker_time: None,
+            closes_position: Some(false),
+            realized_pnl: None,
         }
     };
 }
@@ -95,6 +107,7 @@ macro_rules! inv_uk {
     () => {
         &trading_app::database::models::StockTransactionsUpdateKeys {
             stock: Some("QQQ".to_string()),
+            primary_exchange: Some("NASDAQ".to_string()),
             strategy: Some("strat_a".to_string()),
             order_perm_id: Some(1),
             time: Some(
@@ -114,6 +127,9 @@ macro_rules! inv_uk {
                 rust_decimal::Decimal::from_f64(1.0)
                     .expect("Expected commission from commission_report to be valid for Decimal"),
             ),
+            raw_broker_time: None,
+            closes_position: Some(false),
+            realized_pnl: None,
         }
     };
 }
@@ -2,7 +2,7 @@ use trading_app::database::{
     crud::CRUDTrait, models::Status, models_crud::strategy::get_strategy_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 
 macro_rules! get_crud {
     ($pool:expr) => {
@@ -2,7 +2,7 @@ use trading_app::database::{
     crud::CRUDTrait, models_crud::target_stock_positions::get_target_stock_positions_crud,
 };
 
-use crate::models::init::{TEST_MUTEX, setup_test_db};
+use crate::common::init::{TEST_MUTEX, setup_test_db};
 use crate::{del_strat, init_strat};
 
 macro_rules! get_crud {
@@ -14,6 +14,7 @@ macro_rules! normal_fk {
     () => {
         &trading_app::database::models::TargetStockPositionsFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             quantity: 9.0,
             avg_price: 0.0,
@@ -24,6 +25,7 @@ macro_rules! inv_fk {
     () => {
         &trading_app::database::models::TargetStockPositionsFullKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
             quantity: 0.0,
             avg_price: 9.0,
@@ -34,6 +36,7 @@ macro_rules! normal_pk {
     () => {
         &trading_app::database::models::TargetStockPositionsPrimaryKeys {
             stock: "QQQ".to_string(),
+            primary_exchange: "NASDAQ".to_string(),
             strategy: "strat_a".to_string(),
         }
     };
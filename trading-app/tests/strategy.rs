@@ -0,0 +1,8 @@
+mod common;
+
+mod strategy {
+    pub mod test_backtest_compare;
+    pub mod test_flatten_at_close;
+    pub mod test_on_fill;
+    pub mod test_strategy_params;
+}
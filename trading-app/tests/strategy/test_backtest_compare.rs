@@ -0,0 +1,74 @@
+use chrono::{DateTime, TimeZone, Utc};
+use trading_app::strategy::backtest::{
+    ComparisonTolerance, Trade, TradeDivergence, compare_trades,
+};
+
+fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 15, 14, minute, 0).unwrap()
+}
+
+fn trade(minute: u32, stock: &str, quantity: f64, price: f64) -> Trade {
+    Trade {
+        time: at(minute),
+        stock: stock.to_string(),
+        option: None,
+        quantity,
+        price,
+    }
+}
+
+#[test]
+fn test_matching_trades_have_no_divergences() {
+    let backtest = vec![
+        trade(0, "QQQ", 10.0, 500.0),
+        trade(30, "QQQ", -10.0, 505.0),
+        trade(35, "SPY", 5.0, 600.0),
+    ];
+    // filled a little after the bar the backtest traded on, within a cent
+    let live = vec![
+        trade(36, "SPY", 5.0, 600.005),
+        trade(1, "QQQ", 10.0, 500.0),
+        trade(31, "QQQ", -10.0, 504.995),
+    ];
+
+    let (matched, divergences) = compare_trades(&backtest, &live, &ComparisonTolerance::default());
+    assert_eq!(matched, 3);
+    assert_eq!(divergences, vec![]);
+}
+
+#[test]
+fn test_mismatched_trades_are_reported() {
+    let backtest = vec![
+        trade(0, "QQQ", 10.0, 500.0),
+        trade(30, "QQQ", -10.0, 505.0),
+        trade(35, "SPY", 5.0, 600.0),
+    ];
+    let live = vec![
+        trade(1, "QQQ", 10.0, 501.0),
+        trade(31, "QQQ", -6.0, 505.0),
+        trade(50, "IWM", 3.0, 220.0),
+    ];
+
+    let (matched, divergences) = compare_trades(&backtest, &live, &ComparisonTolerance::default());
+    assert_eq!(matched, 2);
+    assert_eq!(
+        divergences,
+        vec![
+            TradeDivergence::PriceDifference {
+                backtest: backtest[0].clone(),
+                live: live[0].clone(),
+                difference: 1.0,
+            },
+            TradeDivergence::QuantityDifference {
+                backtest: backtest[1].clone(),
+                live: live[1].clone(),
+            },
+            TradeDivergence::Missing {
+                backtest: backtest[2].clone(),
+            },
+            TradeDivergence::Extra {
+                live: live[2].clone(),
+            },
+        ]
+    );
+}
@@ -0,0 +1,212 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use ibapi::prelude::Contract;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{
+            CurrentOptionPositionsFullKeys, CurrentStockPositionsFullKeys, OptionType, Status,
+            StrategyFullKeys, TargetOptionPositionsFullKeys, TargetOptionPositionsPrimaryKeys,
+            TargetStockPositionsFullKeys, TargetStockPositionsPrimaryKeys,
+        },
+        models_crud::{
+            current_option_positions::get_current_option_positions_crud,
+            current_stock_positions::get_current_stock_positions_crud, strategy::get_strategy_crud,
+            target_option_positions::get_target_option_positions_crud,
+            target_stock_positions::get_target_stock_positions_crud,
+        },
+    },
+    market_data::{
+        market_hours::{Clock, MarketHours},
+        scheduler::EventSchedule,
+    },
+    strategy::flatten::{
+        FLATTEN_AT_CLOSE, FlattenedStrategies, flatten_targets, strategy_schedule,
+    },
+};
+
+use crate::common::{
+    fixtures::TestStrategy,
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const STRATEGY: &str = "flatten_strat";
+
+fn intraday_strategy(flatten_at_close: bool) -> TestStrategy {
+    TestStrategy {
+        contracts: vec![Contract::stock("FLAT")],
+        flatten_at_close,
+        ..TestStrategy::new(STRATEGY)
+    }
+}
+
+struct SteppedClock(Mutex<DateTime<Utc>>);
+
+impl SteppedClock {
+    fn set(&self, hour: u32, minute: u32) {
+        *self.0.lock().unwrap() = New_York
+            .with_ymd_and_hms(2025, 7, 15, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+    }
+}
+
+impl Clock for SteppedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[test]
+fn test_flatten_event_fires_configured_minutes_before_close() {
+    let market_hours = MarketHours::default();
+    let events = strategy_schedule(&intraday_strategy(true), &market_hours, 15);
+    let mut schedule = EventSchedule::new(New_York, events);
+
+    let clock = SteppedClock(Mutex::new(Utc::now()));
+    clock.set(15, 44);
+    assert!(schedule.due(&clock).is_empty());
+    clock.set(15, 45);
+    let due = schedule.due(&clock);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].name, FLATTEN_AT_CLOSE);
+
+    // held overnight, nothing to flatten
+    assert!(strategy_schedule(&intraday_strategy(false), &market_hours, 15).is_empty());
+}
+
+#[test]
+fn test_flattened_strategy_is_gated_until_the_next_session() {
+    let at = |day: u32, hour: u32, minute: u32| {
+        New_York
+            .with_ymd_and_hms(2025, 7, day, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+    let flattened = FlattenedStrategies::new();
+    assert!(!flattened.is_flattened(STRATEGY, at(15, 15, 50)));
+
+    flattened.flatten(STRATEGY, at(15, 15, 45));
+    assert!(flattened.is_flattened(STRATEGY, at(15, 15, 50)));
+    assert!(!flattened.is_flattened("other_strat", at(15, 15, 50)));
+    // the next session trades again
+    assert!(!flattened.is_flattened(STRATEGY, at(16, 9, 35)));
+}
+
+fn option_target(strike: f64, quantity: f64) -> TargetOptionPositionsFullKeys {
+    TargetOptionPositionsFullKeys {
+        strategy: STRATEGY.to_string(),
+        stock: "FLAT".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        expiry: "20250718".to_string(),
+        strike,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Put,
+        avg_price: 2.0,
+        quantity,
+    }
+}
+
+fn option_target_key(strike: f64) -> TargetOptionPositionsPrimaryKeys {
+    TargetOptionPositionsPrimaryKeys {
+        strategy: STRATEGY.to_string(),
+        stock: "FLAT".to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        expiry: "20250718".to_string(),
+        strike,
+        multiplier: "100".to_string(),
+        option_type: OptionType::Put,
+    }
+}
+
+fn stock_target_key(stock: &str) -> TargetStockPositionsPrimaryKeys {
+    TargetStockPositionsPrimaryKeys {
+        strategy: STRATEGY.to_string(),
+        primary_exchange: "NASDAQ".to_string(),
+        stock: stock.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_flatten_zeroes_every_target_and_position_of_strategy() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        // FLAT has a target, HELD and the 105 put are only held - they have no target row
+        let stock_targets_crud = get_target_stock_positions_crud(pool.clone());
+        stock_targets_crud
+            .create(&TargetStockPositionsFullKeys {
+                strategy: STRATEGY.to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                stock: "FLAT".to_string(),
+                avg_price: 100.0,
+                quantity: 10.0,
+            })
+            .await
+            .expect("Expected to create stock target");
+        let option_targets_crud = get_target_option_positions_crud(pool.clone());
+        option_targets_crud
+            .create(&option_target(100.0, -2.0))
+            .await
+            .expect("Expected to create option target");
+        let stock_positions_crud = get_current_stock_positions_crud(pool.clone());
+        for stock in ["FLAT", "HELD"] {
+            stock_positions_crud
+                .create(&CurrentStockPositionsFullKeys {
+                    stock: stock.to_string(),
+                    primary_exchange: "NASDAQ".to_string(),
+                    strategy: STRATEGY.to_string(),
+                    quantity: 10.0,
+                    avg_price: 100.0,
+                })
+                .await
+                .expect("Expected to create stock position");
+        }
+        get_current_option_positions_crud(pool.clone())
+            .create(&CurrentOptionPositionsFullKeys {
+                stock: "FLAT".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                strategy: STRATEGY.to_string(),
+                expiry: "20250718".to_string(),
+                strike: 105.0,
+                multiplier: "100".to_string(),
+                option_type: OptionType::Put,
+                quantity: 3.0,
+                avg_price: 1.5,
+            })
+            .await
+            .expect("Expected to create option position");
+
+        assert_eq!(flatten_targets(pool.clone(), STRATEGY).await, Ok(4));
+
+        for stock in ["FLAT", "HELD"] {
+            let target = stock_targets_crud
+                .read(&stock_target_key(stock))
+                .await
+                .expect("Expected to read stock target")
+                .expect("Expected stock target to exist");
+            assert_eq!(target.quantity, 0.0);
+        }
+        for strike in [100.0, 105.0] {
+            let target = option_targets_crud
+                .read(&option_target_key(strike))
+                .await
+                .expect("Expected to read option target")
+                .expect("Expected option target to exist");
+            assert_eq!(target.quantity, 0.0);
+        }
+    })
+    .await;
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use ibapi::{
+    orders::{Execution, ExecutionData},
+    prelude::Contract,
+};
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{OpenStockOrdersFullKeys, Status, StrategyFullKeys},
+        models_crud::{
+            current_stock_positions::{
+                get_current_stock_positions_crud, get_specific_current_stock_positions_crud,
+            },
+            open_stock_orders::get_open_stock_orders_crud,
+            stock_transactions::get_stock_transactions_crud,
+            strategy::get_strategy_crud,
+        },
+    },
+    execution::{
+        events::on_execution_updates::{DEFAULT_UNKNOWN_STRATEGY, on_new_stock_execution},
+        fills::{ExecutionSummary, dispatch_fill, spawn_fill_listener},
+    },
+};
+
+use crate::common::{
+    fixtures::TestStrategy,
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+fn recording_strategy(name: &str) -> TestStrategy {
+    TestStrategy {
+        contracts: vec![Contract::stock("FILL")],
+        ..TestStrategy::new(name)
+    }
+}
+
+fn sell_fill(strategy: &str) -> ExecutionSummary {
+    ExecutionSummary {
+        strategy: strategy.to_string(),
+        contract: Contract::stock("FILL"),
+        execution_id: "0000e0d5.6581c8d4.01.01".to_string(),
+        order_id: 730,
+        quantity: -3.0,
+        price: 412.5,
+        time: Utc::now(),
+    }
+}
+
+fn sell_execution() -> ExecutionData {
+    ExecutionData {
+        contract: Contract::stock("FILL"),
+        execution: Execution {
+            order_id: 730,
+            perm_id: 7300,
+            execution_id: "0000e0d5.6581c8d4.01.01".to_string(),
+            time: "20250715  14:30:00".to_string(),
+            side: "SLD".to_string(),
+            shares: 3.0,
+            price: 412.5,
+            cumulative_quantity: 3.0,
+            ..Execution::default()
+        },
+        ..ExecutionData::default()
+    }
+}
+
+#[tokio::test]
+async fn test_booked_execution_calls_on_fill_of_its_strategy() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: "fill_strat".to_string(),
+                capital: 10000.0,
+                initial_capital: 10000.0,
+                status: Status::Active,
+            })
+            .await
+            .expect("Expected to create strategy");
+        let open_stock_orders_crud = get_open_stock_orders_crud(pool.clone());
+        open_stock_orders_crud
+            .create(&OpenStockOrdersFullKeys {
+                order_perm_id: 7300,
+                order_id: 730,
+                strategy: "fill_strat".to_string(),
+                stock: "FILL".to_string(),
+                primary_exchange: "NASDAQ".to_string(),
+                time: Utc::now(),
+                quantity: -3.0,
+                executions: vec![],
+                filled: 0.0,
+                order_snapshot: None,
+            })
+            .await
+            .expect("Expected to create open order");
+
+        let filled = recording_strategy("fill_strat");
+        let other = recording_strategy("other_strat");
+        let fill_sender = Some(spawn_fill_listener(vec![filled.clone(), other.clone()]));
+        on_new_stock_execution(
+            open_stock_orders_crud,
+            get_stock_transactions_crud(pool.clone()),
+            get_current_stock_positions_crud(pool.clone()),
+            get_specific_current_stock_positions_crud(pool.clone()),
+            sell_execution(),
+            DEFAULT_UNKNOWN_STRATEGY.to_string(),
+            fill_sender,
+        );
+        // the execution is booked and on_fill run on spawned tasks
+        for _ in 0..200 {
+            if !filled.fills().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(filled.fills(), vec![("FILL".to_string(), -3.0, 412.5)]);
+        assert!(other.fills().is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_fill_of_unknown_strategy_is_not_dispatched() {
+    let strategy = recording_strategy("fill_strat");
+
+    let notified = dispatch_fill(std::slice::from_ref(&strategy), &sell_fill("unknown")).await;
+
+    assert_eq!(notified, 0);
+    assert!(strategy.fills().is_empty());
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use trading_app::{
+    database::{
+        crud::CRUDTrait,
+        models::{Status, StrategyFullKeys, StrategyParamsPrimaryKeys, StrategyParamsUpdateKeys},
+        models_crud::{strategy::get_strategy_crud, strategy_params::get_strategy_params_crud},
+    },
+    strategy::params::{load_strategy_params, validate_params},
+};
+
+use crate::common::{
+    fixtures::TestStrategy,
+    init::{TEST_MUTEX, setup_test_db, with_rollback},
+};
+
+const STRATEGY: &str = "params_strat";
+
+#[test]
+fn test_validate_params_against_schema() {
+    let schema = json!({ "lookback": "integer", "threshold": "number" });
+
+    assert!(validate_params(&schema, &json!({ "lookback": 20, "threshold": 0.5 })).is_ok());
+    assert!(validate_params(&schema, &json!({ "threshold": 1 })).is_ok());
+    assert!(validate_params(&schema, &json!({})).is_ok());
+    assert!(validate_params(&schema, &json!({ "lookback": 20.5 })).is_err());
+    assert!(validate_params(&schema, &json!({ "unknown": 1 })).is_err());
+    assert!(validate_params(&schema, &json!([20])).is_err());
+}
+
+#[tokio::test]
+async fn test_updated_param_is_reflected_on_reload() {
+    let _lock = TEST_MUTEX.lock().await;
+    let pool = setup_test_db().await;
+    with_rollback(&pool, |pool| async move {
+        get_strategy_crud(pool.clone())
+            .create_or_ignore(&StrategyFullKeys {
+                strategy: STRATEGY.to_string(),
+                capital: 10.0,
+                initial_capital: 10.0,
+                status: Status::Inactive,
+            })
+            .await
+            .expect("Expected to create strategy");
+
+        let strategy = TestStrategy {
+            params_schema: json!({ "lookback": "integer" }),
+            params: Arc::new(Mutex::new(json!({ "lookback": 10 }))),
+            ..TestStrategy::new(STRATEGY)
+        };
+        // First load registers the schema and keeps the compiled in default
+        load_strategy_params(pool.clone(), &strategy)
+            .await
+            .expect("Expected to load strategy params");
+        assert_eq!(strategy.param("lookback"), json!(10));
+
+        // Same write PUT /strategy_params does once validated
+        get_strategy_params_crud(pool.clone())
+            .update(
+                &StrategyParamsPrimaryKeys {
+                    strategy: STRATEGY.to_string(),
+                },
+                &StrategyParamsUpdateKeys {
+                    params: Some(json!({ "lookback": 30 })),
+                    params_schema: None,
+                },
+            )
+            .await
+            .expect("Expected to update strategy params");
+
+        load_strategy_params(pool.clone(), &strategy)
+            .await
+            .expect("Expected to reload strategy params");
+        assert_eq!(strategy.param("lookback"), json!(30));
+    })
+    .await;
+}